@@ -6,9 +6,9 @@ set -e
 echo "Building Barista DEX programs for Solana BPF..."
 echo ""
 
-# Build router
+# Build router (localnet: allow unregistered slabs to auto-register)
 echo "Building router program..."
-cargo build-sbf --manifest-path programs/router/Cargo.toml
+cargo build-sbf --manifest-path programs/router/Cargo.toml --features permissionless_slabs
 
 # Build slab
 echo "Building slab program..."
@@ -94,9 +94,10 @@ pub async fn initialize_exchange(
         &config.router_program_id,         // Owner (router program)
     );
 
-    // Build instruction data: [discriminator (0u8), governance_pubkey (32 bytes)]
-    let mut instruction_data = Vec::with_capacity(33);
+    // Build instruction data: [discriminator (0u8), version (1 byte), governance_pubkey (32 bytes)]
+    let mut instruction_data = Vec::with_capacity(34);
     instruction_data.push(0u8); // RouterInstruction::Initialize discriminator
+    instruction_data.push(percolator_router::INSTRUCTION_VERSION);
     instruction_data.extend_from_slice(&payer.pubkey().to_bytes()); // governance = payer for now
 
     // INSTRUCTION 2: Initialize the created account
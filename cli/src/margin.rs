@@ -82,9 +82,10 @@ pub async fn initialize_portfolio(config: &NetworkConfig) -> Result<()> {
         &config.router_program_id,     // Owner (router program)
     );
 
-    // Build instruction data: [discriminator (1u8), user_pubkey (32 bytes)]
-    let mut instruction_data = Vec::with_capacity(33);
+    // Build instruction data: [discriminator (1u8), version (1 byte), user_pubkey (32 bytes)]
+    let mut instruction_data = Vec::with_capacity(34);
     instruction_data.push(1u8); // RouterInstruction::InitializePortfolio discriminator
+    instruction_data.push(percolator_router::INSTRUCTION_VERSION);
     instruction_data.extend_from_slice(&user.to_bytes()); // user pubkey
 
     // INSTRUCTION 2: Initialize the created account
@@ -159,6 +160,9 @@ pub async fn deposit_collateral(
 
     println!("{} {}", "Portfolio Address:".bright_cyan(), portfolio_address);
 
+    // Derive registry account address (deposits check its pause flags)
+    let (registry_address, _bump) = derive_registry_pda(&config.router_program_id);
+
     // Check portfolio exists
     let portfolio_account = rpc_client.get_account(&portfolio_address)
         .context("Portfolio account not found - run 'margin init' first")?;
@@ -172,9 +176,10 @@ pub async fn deposit_collateral(
         portfolio_account.lamports
     );
 
-    // Build instruction data: [discriminator (1u8), amount (8 bytes)]
-    let mut instruction_data = Vec::with_capacity(9);
+    // Build instruction data: [discriminator (1u8), version (1 byte), amount (8 bytes)]
+    let mut instruction_data = Vec::with_capacity(10);
     instruction_data.push(2u8); // RouterInstruction::Deposit discriminator
+    instruction_data.push(percolator_router::INSTRUCTION_VERSION);
     instruction_data.extend_from_slice(&amount.to_le_bytes());
 
     // Build deposit instruction
@@ -184,6 +189,7 @@ pub async fn deposit_collateral(
             AccountMeta::new(portfolio_address, false), // Portfolio account (writable)
             AccountMeta::new(user, true),                // User (signer, writable)
             AccountMeta::new_readonly(system_program::id(), false), // System program
+            AccountMeta::new_readonly(registry_address, false),     // Registry (readonly)
         ],
         data: instruction_data,
     };
@@ -271,9 +277,10 @@ pub async fn withdraw_collateral(
         portfolio_account.lamports
     );
 
-    // Build instruction data: [discriminator (1u8), amount (8 bytes)]
-    let mut instruction_data = Vec::with_capacity(9);
+    // Build instruction data: [discriminator (1u8), version (1 byte), amount (8 bytes)]
+    let mut instruction_data = Vec::with_capacity(10);
     instruction_data.push(3u8); // RouterInstruction::Withdraw discriminator
+    instruction_data.push(percolator_router::INSTRUCTION_VERSION);
     instruction_data.extend_from_slice(&amount.to_le_bytes());
 
     // Build withdraw instruction
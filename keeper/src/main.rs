@@ -498,7 +498,7 @@ async fn run_registry_command(subcommand: RegistryCommands) -> Result<()> {
             use solana_sdk::system_instruction;
 
             // Build Initialize instruction - program will create account
-            let mut instruction_data = vec![0u8]; // discriminator = 0
+            let mut instruction_data = vec![0u8, 1u8]; // discriminator = 0, instruction version = 1
             instruction_data.extend_from_slice(governance_pubkey.as_ref());
 
             let initialize_ix = solana_sdk::instruction::Instruction {
@@ -1,71 +1,118 @@
 //! Transaction builder for liquidations
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use percolator_router::{RouterInstruction, INSTRUCTION_VERSION, MAX_LIQUIDATION_BATCH};
 use solana_sdk::{
     instruction::{AccountMeta, Instruction},
     pubkey::Pubkey,
     signature::Keypair,
     signer::Signer,
+    system_program,
     transaction::Transaction,
 };
 
+/// Accounts shared by every portfolio in a LiquidateUser call
+pub struct LiquidationAccounts<'a> {
+    pub router_program: &'a Pubkey,
+    pub dlp_portfolio: &'a Pubkey,
+    pub registry: &'a Pubkey,
+    pub vault: &'a Pubkey,
+    pub router_authority: &'a Pubkey,
+    pub slab_program: &'a Pubkey,
+    /// Oracle accounts, paired with `slabs` by position
+    pub oracles: &'a [Pubkey],
+    pub slabs: &'a [Pubkey],
+    /// Receipt PDAs, one per slab
+    pub receipts: &'a [Pubkey],
+}
+
+/// A portfolio to liquidate and the PositionDetails PDAs of its open exposures
+pub struct LiquidationTarget {
+    pub portfolio: Pubkey,
+    pub position_details: Vec<Pubkey>,
+}
+
 /// Build liquidate_user instruction
 ///
 /// This constructs the liquidate_user instruction that the keeper
-/// will submit to liquidate undercollateralized portfolios.
+/// will submit to liquidate undercollateralized portfolios. More than one
+/// target is sent as a batch with a PositionDetails count per portfolio.
 pub fn build_liquidate_instruction(
-    router_program: &Pubkey,
-    portfolio: &Pubkey,
-    registry: &Pubkey,
-    vault: &Pubkey,
-    router_authority: &Pubkey,
+    accounts: &LiquidationAccounts,
+    targets: &[LiquidationTarget],
     keeper: &Pubkey,
     is_preliq: bool,
-) -> Instruction {
-    // Instruction discriminator for LiquidateUser
-    let discriminator = 3u8;
+    current_ts: u64,
+) -> Result<Instruction> {
+    if targets.is_empty() || targets.len() > MAX_LIQUIDATION_BATCH {
+        bail!("LiquidateUser takes 1 to {} portfolios", MAX_LIQUIDATION_BATCH);
+    }
+    if accounts.oracles.len() != accounts.slabs.len() || accounts.receipts.len() != accounts.slabs.len() {
+        bail!("LiquidateUser needs one oracle and one receipt per slab");
+    }
+
+    // Instruction data: discriminator + version + num_oracles + num_slabs + is_preliq + current_ts
+    let mut data = vec![
+        RouterInstruction::LiquidateUser as u8,
+        INSTRUCTION_VERSION,
+        u8::try_from(accounts.oracles.len()).context("too many oracles")?,
+        u8::try_from(accounts.slabs.len()).context("too many slabs")?,
+        u8::from(is_preliq),
+    ];
+    data.extend_from_slice(&current_ts.to_le_bytes());
 
-    // Instruction data: discriminator + is_preliq
-    let mut data = vec![discriminator];
-    data.push(if is_preliq { 1 } else { 0 });
+    // Batch: portfolio count, then each portfolio's PositionDetails count
+    if targets.len() > 1 {
+        data.push(targets.len() as u8);
+        for target in targets {
+            data.push(u8::try_from(target.position_details.len()).context("too many positions")?);
+        }
+    }
 
     // Build account metas
-    let accounts = vec![
-        AccountMeta::new(*portfolio, false),
-        AccountMeta::new_readonly(*registry, false),
-        AccountMeta::new(*vault, false),
-        AccountMeta::new_readonly(*router_authority, false),
-        AccountMeta::new_readonly(*keeper, true),
-        // In production, would include oracle accounts, slab accounts, etc.
+    let mut metas = vec![
+        AccountMeta::new(targets[0].portfolio, false),
+        AccountMeta::new(*accounts.dlp_portfolio, false),
+        AccountMeta::new(*accounts.registry, false),
+        AccountMeta::new(*accounts.vault, false),
+        AccountMeta::new_readonly(*accounts.router_authority, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(*accounts.slab_program, false),
+        AccountMeta::new(*keeper, true),
     ];
+    metas.extend(accounts.oracles.iter().map(|key| AccountMeta::new_readonly(*key, false)));
+    metas.extend(accounts.slabs.iter().map(|key| AccountMeta::new(*key, false)));
+    metas.extend(accounts.receipts.iter().map(|key| AccountMeta::new(*key, false)));
+    for (i, target) in targets.iter().enumerate() {
+        if i > 0 {
+            metas.push(AccountMeta::new(target.portfolio, false));
+        }
+        metas.extend(target.position_details.iter().map(|key| AccountMeta::new(*key, false)));
+    }
 
-    Instruction {
-        program_id: *router_program,
-        accounts,
+    Ok(Instruction {
+        program_id: *accounts.router_program,
+        accounts: metas,
         data,
-    }
+    })
 }
 
 /// Build transaction for liquidation
 pub fn build_liquidation_transaction(
-    router_program: &Pubkey,
-    portfolio: &Pubkey,
-    registry: &Pubkey,
-    vault: &Pubkey,
-    router_authority: &Pubkey,
+    accounts: &LiquidationAccounts,
+    targets: &[LiquidationTarget],
     keeper: &Keypair,
     is_preliq: bool,
+    current_ts: u64,
     recent_blockhash: solana_sdk::hash::Hash,
 ) -> Result<Transaction> {
     let instruction = build_liquidate_instruction(
-        router_program,
-        portfolio,
-        registry,
-        vault,
-        router_authority,
+        accounts,
+        targets,
         &keeper.pubkey(),
         is_preliq,
-    );
+        current_ts,
+    )?;
 
     let transaction = Transaction::new_signed_with_payer(
         &[instruction],
@@ -81,50 +128,115 @@ pub fn build_liquidation_transaction(
 mod tests {
     use super::*;
 
+    struct Keys {
+        router_program: Pubkey,
+        dlp_portfolio: Pubkey,
+        registry: Pubkey,
+        vault: Pubkey,
+        router_authority: Pubkey,
+        slab_program: Pubkey,
+        oracles: Vec<Pubkey>,
+        slabs: Vec<Pubkey>,
+        receipts: Vec<Pubkey>,
+    }
+
+    impl Keys {
+        fn new(num_slabs: usize) -> Self {
+            let unique = |n| (0..n).map(|_| Pubkey::new_unique()).collect();
+            Self {
+                router_program: Pubkey::new_unique(),
+                dlp_portfolio: Pubkey::new_unique(),
+                registry: Pubkey::new_unique(),
+                vault: Pubkey::new_unique(),
+                router_authority: Pubkey::new_unique(),
+                slab_program: Pubkey::new_unique(),
+                oracles: unique(num_slabs),
+                slabs: unique(num_slabs),
+                receipts: unique(num_slabs),
+            }
+        }
+
+        fn accounts(&self) -> LiquidationAccounts<'_> {
+            LiquidationAccounts {
+                router_program: &self.router_program,
+                dlp_portfolio: &self.dlp_portfolio,
+                registry: &self.registry,
+                vault: &self.vault,
+                router_authority: &self.router_authority,
+                slab_program: &self.slab_program,
+                oracles: &self.oracles,
+                slabs: &self.slabs,
+                receipts: &self.receipts,
+            }
+        }
+    }
+
+    fn target(num_positions: usize) -> LiquidationTarget {
+        LiquidationTarget {
+            portfolio: Pubkey::new_unique(),
+            position_details: (0..num_positions).map(|_| Pubkey::new_unique()).collect(),
+        }
+    }
+
     #[test]
     fn test_build_liquidate_instruction() {
-        let router_program = Pubkey::new_unique();
-        let portfolio = Pubkey::new_unique();
-        let registry = Pubkey::new_unique();
-        let vault = Pubkey::new_unique();
-        let router_authority = Pubkey::new_unique();
+        let keys = Keys::new(1);
         let keeper = Pubkey::new_unique();
+        let targets = [target(1)];
+
+        let ix = build_liquidate_instruction(&keys.accounts(), &targets, &keeper, false, 1_700_000_000).unwrap();
 
-        let ix = build_liquidate_instruction(
-            &router_program,
-            &portfolio,
-            &registry,
-            &vault,
-            &router_authority,
-            &keeper,
-            false,
-        );
-
-        assert_eq!(ix.program_id, router_program);
-        assert_eq!(ix.data[0], 3); // LiquidateUser discriminator
-        assert_eq!(ix.data[1], 0); // is_preliq = false
-        assert_eq!(ix.accounts.len(), 5);
+        assert_eq!(ix.program_id, keys.router_program);
+        assert_eq!(ix.data[0], RouterInstruction::LiquidateUser as u8);
+        assert_eq!(ix.data[1], INSTRUCTION_VERSION);
+        assert_eq!(ix.data[2], 1); // num_oracles
+        assert_eq!(ix.data[3], 1); // num_slabs
+        assert_eq!(ix.data[4], 0); // is_preliq = false
+        assert_eq!(&ix.data[5..13], &1_700_000_000u64.to_le_bytes());
+        assert_eq!(ix.data.len(), 13);
+
+        // 8 fixed accounts, oracle, slab, receipt, PositionDetails
+        assert_eq!(ix.accounts.len(), 12);
+        assert_eq!(ix.accounts[0].pubkey, targets[0].portfolio);
+        assert!(ix.accounts[7].is_signer);
+        assert_eq!(ix.accounts[7].pubkey, keeper);
+        assert_eq!(ix.accounts[8].pubkey, keys.oracles[0]);
+        assert_eq!(ix.accounts[9].pubkey, keys.slabs[0]);
+        assert_eq!(ix.accounts[10].pubkey, keys.receipts[0]);
+        assert_eq!(ix.accounts[11].pubkey, targets[0].position_details[0]);
+        assert!(ix.accounts[11].is_writable);
     }
 
     #[test]
     fn test_build_preliq_instruction() {
-        let router_program = Pubkey::new_unique();
-        let portfolio = Pubkey::new_unique();
-        let registry = Pubkey::new_unique();
-        let vault = Pubkey::new_unique();
-        let router_authority = Pubkey::new_unique();
-        let keeper = Pubkey::new_unique();
+        let keys = Keys::new(1);
+        let ix = build_liquidate_instruction(&keys.accounts(), &[target(0)], &Pubkey::new_unique(), true, 0).unwrap();
+
+        assert_eq!(ix.data[4], 1); // is_preliq = true
+    }
+
+    #[test]
+    fn test_build_batch_liquidate_instruction() {
+        let keys = Keys::new(2);
+        let targets = [target(2), target(1)];
+
+        let ix = build_liquidate_instruction(&keys.accounts(), &targets, &Pubkey::new_unique(), false, 0).unwrap();
+
+        // num_portfolios, then each portfolio's PositionDetails count
+        assert_eq!(&ix.data[13..], &[2, 2, 1]);
+
+        // 8 fixed, 2 oracles, 2 slabs, 2 receipts, 2 PositionDetails, portfolio, 1 PositionDetails
+        assert_eq!(ix.accounts.len(), 18);
+        assert_eq!(ix.accounts[14].pubkey, targets[0].position_details[0]);
+        assert_eq!(ix.accounts[16].pubkey, targets[1].portfolio);
+        assert_eq!(ix.accounts[17].pubkey, targets[1].position_details[0]);
+    }
+
+    #[test]
+    fn test_mismatched_oracles_rejected() {
+        let mut keys = Keys::new(2);
+        keys.oracles.pop();
 
-        let ix = build_liquidate_instruction(
-            &router_program,
-            &portfolio,
-            &registry,
-            &vault,
-            &router_authority,
-            &keeper,
-            true,
-        );
-
-        assert_eq!(ix.data[1], 1); // is_preliq = true
+        assert!(build_liquidate_instruction(&keys.accounts(), &[target(0)], &Pubkey::new_unique(), false, 0).is_err());
     }
 }
@@ -49,7 +49,7 @@ pub fn process_initialize(
     let mut amm = AmmState::new(header, x_reserve, y_reserve, taker_fee_bps);
 
     // Synthesize initial quote cache
-    amm.synthesize_quote_cache();
+    amm.synthesize_quote_cache()?;
 
     // Write to account (unsafe cast from bytes)
     unsafe {
@@ -178,14 +178,14 @@ pub fn process_commit_fill(
     amm.pool.y_reserve = result.new_y;
 
     // Synthesize new QuoteCache reflecting the updated curve
-    amm.synthesize_quote_cache();
+    amm.synthesize_quote_cache()?;
 
     // Write fill receipt
     let receipt = unsafe { borrow_account_data_mut::<FillReceipt>(receipt_account)? };
     receipt.write(seqno_committed, qty, result.vwap_px, notional, fee);
 
     // Increment seqno (AMM state changed)
-    amm.header.increment_seqno();
+    amm.header.increment_seqno()?;
 
     msg!("AMM CommitFill executed successfully");
 
@@ -1,9 +1,9 @@
 //! AMM state - constant product automated market maker
 
-use percolator_common::{SlabHeader, QuoteCache};
+use percolator_common::{PercolatorError, SlabHeader, QuoteCache};
 
 /// AMM pool state - uses same header/cache layout as orderbook slab
-/// Layout: SlabHeader (200B) + QuoteCache (136B) + AmmData (variable)
+/// Layout: SlabHeader (232B) + QuoteCache (136B) + AmmData (variable)
 #[repr(C)]
 pub struct AmmState {
     /// Standard slab header (magic, version, seqno, mark_px, etc.)
@@ -65,14 +65,15 @@ impl AmmState {
 
     /// Synthesize QuoteCache from AMM curve
     /// Generates 4 bid and 4 ask levels by sampling the curve at different quantities
-    pub fn synthesize_quote_cache(&mut self) {
+    /// Fails with CrossedBook if the sampled bid reaches the ask
+    pub fn synthesize_quote_cache(&mut self) -> Result<(), PercolatorError> {
         use crate::math::{quote_buy, quote_sell};
         use percolator_common::QuoteLevel;
 
         let spot = self.spot_price();
         if spot == 0 {
             self.quote_cache = QuoteCache::new();
-            return;
+            return Ok(());
         }
 
         // Sample quantities: 1%, 2%, 5%, 10% of reserves (scaled)
@@ -120,7 +121,7 @@ impl AmmState {
         }
 
         // Update quote cache (bids descending by price, asks ascending by price)
-        self.quote_cache.update(self.header.seqno, &bids, &asks);
+        self.quote_cache.update(self.header.seqno, &bids, &asks)
     }
 }
 
@@ -179,7 +180,7 @@ mod tests {
         );
 
         let mut amm = AmmState::new(header, 1000 * 1_000_000, 60_000_000 * 1_000_000, 5);
-        amm.synthesize_quote_cache();
+        amm.synthesize_quote_cache().unwrap();
 
         // Should have 4 bid and 4 ask levels
         let cache = &amm.quote_cache;
@@ -214,7 +215,7 @@ mod tests {
         );
 
         let mut amm = AmmState::new(header, 1000 * 1_000_000, 60_000_000 * 1_000_000, 5);
-        amm.synthesize_quote_cache();
+        amm.synthesize_quote_cache().unwrap();
 
         let cache = &amm.quote_cache;
 
@@ -249,7 +250,7 @@ mod tests {
         );
 
         let mut amm = AmmState::new(header, 1000 * 1_000_000, 60_000_000 * 1_000_000, 5);
-        amm.synthesize_quote_cache();
+        amm.synthesize_quote_cache().unwrap();
 
         let cache = &amm.quote_cache;
 
@@ -280,7 +281,7 @@ mod tests {
         );
 
         let mut amm = AmmState::new(header, 1000 * 1_000_000, 60_000_000 * 1_000_000, 5);
-        amm.synthesize_quote_cache();
+        amm.synthesize_quote_cache().unwrap();
 
         let cache = &amm.quote_cache;
 
@@ -316,7 +317,7 @@ mod tests {
         );
 
         let mut amm = AmmState::new(header, 1000 * 1_000_000, 60_000_000 * 1_000_000, 5);
-        amm.synthesize_quote_cache();
+        amm.synthesize_quote_cache().unwrap();
 
         // QuoteCache should capture the seqno from header
         assert_eq!(amm.quote_cache.seqno_snapshot, amm.header.seqno);
@@ -336,7 +337,7 @@ mod tests {
         );
 
         let mut amm = AmmState::new(header, 0, 60_000_000 * 1_000_000, 5);
-        amm.synthesize_quote_cache();
+        amm.synthesize_quote_cache().unwrap();
 
         // Should handle zero reserves gracefully (all levels should be zero)
         let cache = &amm.quote_cache;
@@ -362,7 +363,7 @@ mod tests {
         let mut amm = AmmState::new(header, 1000 * 1_000_000, 60_000_000 * 1_000_000, 5);
 
         // First synthesis
-        amm.synthesize_quote_cache();
+        amm.synthesize_quote_cache().unwrap();
         let cache1 = amm.quote_cache;
 
         // Modify reserves
@@ -370,7 +371,7 @@ mod tests {
         amm.pool.y_reserve = 66_666_666 * 1_000_000;
 
         // Second synthesis
-        amm.synthesize_quote_cache();
+        amm.synthesize_quote_cache().unwrap();
         let cache2 = amm.quote_cache;
 
         // Prices should have changed (spot price changed from 60k to ~74k)
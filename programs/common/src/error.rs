@@ -31,6 +31,31 @@ pub enum PercolatorError {
     InsufficientBalance = 113,
     StalePrice = 114,
     AlreadyInitialized = 115,
+    InvalidLeverage = 116,
+    LeverageTooHigh = 117,
+    CircuitBreakerTripped = 118,
+    ProgramPaused = 119,
+    CounterpartyInsolvent = 120,
+    InvalidCounterparty = 121,
+    AccountHealthy = 122,
+    InvalidOraclePrice = 123,
+    OpenInterestCapExceeded = 124,
+    StaleQuoteCache = 125,
+    UnauthorizedLiquidator = 126,
+    WithdrawalPending = 127,
+    WithdrawalLocked = 128,
+    DepositCapExceeded = 129,
+    UnsupportedVersion = 130,
+    DuplicateSlab = 131,
+    WouldIncreasePosition = 132,
+    TradeThrottled = 133,
+    SlabHasOpenInterest = 134,
+    LeverageMismatch = 135,
+    MarketClosed = 136,
+    MissingPositionDetails = 137,
+    WouldDeallocateAccount = 138,
+    PortfolioNotEmpty = 139,
+    TooManyPositions = 140,
 
     // Slab errors (200-299)
     InvalidInstrument = 200,
@@ -48,12 +73,17 @@ pub enum PercolatorError {
     PoolFull = 212,
     SeqnoMismatch = 213,
     InvalidOrderType = 214,
+    OrderTooSmall = 225, // 215-224 are oracle errors
+    SeqnoExhausted = 226,
+    InventoryLimit = 227,
 
     // Oracle errors (215-224)
     InvalidOracle = 215,
     PriceUnavailable = 216,
     PriceSlippage = 217,
     InvalidReceipt = 218,
+    OracleDivergence = 219,
+    OracleMismatch = 220,
 
     // Matching errors (300-399)
     InvalidSide = 300,
@@ -62,6 +92,8 @@ pub enum PercolatorError {
     InvalidOrderState = 303,
     BookCorrupted = 304,
     ReservedQtyExceeded = 305,
+    WouldCrossBook = 306,
+    CrossedBook = 307,
 
     // Risk errors (400-499)
     InsufficientMargin = 400,
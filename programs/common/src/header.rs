@@ -26,9 +26,12 @@ pub struct SlabHeader {
     pub contract_size: i64,
     /// Tick size (1e6 fixed)
     pub tick: i64,
-    /// Lot size (1e6 fixed)
+    /// Lot size (1e6 fixed); fills are rounded down to a multiple of it
     pub lot: i64,
-    /// Mark price from shared oracle (1e6 scale)
+    /// Minimum order size (1e6 fixed, 0 = no minimum)
+    pub min_qty: i64,
+    /// Mark price (1e6 scale): seeded from the oracle, then damped toward
+    /// each fill and oracle reading by `update_mark_price`
     pub mark_px: i64,
 
     /// Taker fee (basis points, 1e6 scale)
@@ -43,10 +46,30 @@ pub struct SlabHeader {
 
     /// Bump seed
     pub bump: u8,
+    /// Contract kind (ContractKind as u8: 0 = linear, 1 = inverse)
+    pub contract_kind: u8,
+    /// Price decimals (0 = unset, read as 6; see `scale`)
+    pub price_decimals: u8,
+    /// Quantity decimals (0 = unset, read as 6; see `scale`)
+    pub qty_decimals: u8,
+
+    /// Slab program version hash (set at initialize, checked by router)
+    pub version_hash: [u8; 32],
+
+    /// Oracle print exponent (0 = unset, read as -6; see `scale_config`)
+    pub oracle_exponent: i8,
+    /// Collateral decimals (0 = unset, read as 9 for lamports; see `scale_config`)
+    pub collateral_decimals: u8,
     /// Padding
-    pub _padding: [u8; 3],
+    pub _scale_padding: [u8; 6],
 }
 
+/// Weight each new observation gets when updating the mark (basis points)
+///
+/// At 20% a single fill moves the mark a fifth of the way toward it, so one
+/// outlier print can't drag liquidations or funding with it.
+pub const MARK_PRICE_WEIGHT_BPS: i64 = 2_000;
+
 impl SlabHeader {
     pub const MAGIC: &'static [u8; 8] = b"PERP10\0\0";
     pub const VERSION: u32 = 1;
@@ -78,14 +101,21 @@ impl SlabHeader {
             instrument,
             contract_size,
             tick: 1_000_000,           // $1 tick
-            lot: 1_000_000,            // 1.0 lot
+            lot: 1,                    // Smallest unit: no rounding until configured
+            min_qty: 0,                // No minimum until configured
             mark_px,
             taker_fee_bps,
             off_book,
             off_quote_cache,
             off_receipt_area,
             bump,
-            _padding: [0; 3],
+            contract_kind: crate::ContractKind::Linear as u8,
+            price_decimals: crate::InstrumentScale::DEFAULT.price_decimals,
+            qty_decimals: crate::InstrumentScale::DEFAULT.qty_decimals,
+            version_hash: [0; 32],
+            oracle_exponent: crate::ScaleConfig::DEFAULT.oracle_exponent,
+            collateral_decimals: crate::ScaleConfig::DEFAULT.collateral_decimals,
+            _scale_padding: [0; 6],
         }
     }
 
@@ -94,10 +124,93 @@ impl SlabHeader {
         &self.magic == Self::MAGIC && self.version == Self::VERSION
     }
 
+    /// Taker fee on a fill of `notional` (taker_fee_bps of it, same scale)
+    ///
+    /// Liquidation fills are charged nothing: the liquidation penalty is the
+    /// cost of a forced close, and a fee on top would only deepen the
+    /// account's shortfall.
+    pub fn taker_fee(&self, notional: i64, liquidation: bool) -> i64 {
+        if liquidation {
+            return 0;
+        }
+        (notional as i128 * self.taker_fee_bps as i128 / 10_000) as i64
+    }
+
+    /// Price / quantity scale of this slab's instrument
+    pub fn scale(&self) -> crate::InstrumentScale {
+        crate::InstrumentScale::from_stored(self.price_decimals, self.qty_decimals)
+    }
+
+    /// Full oracle / price / quantity / collateral scale of this slab's instrument
+    pub fn scale_config(&self) -> crate::ScaleConfig {
+        crate::ScaleConfig::from_stored(
+            self.price_decimals,
+            self.qty_decimals,
+            self.oracle_exponent,
+            self.collateral_decimals,
+        )
+    }
+
+    /// Quantity an order of `qty` fills at: rounded down to the lot
+    ///
+    /// Rejects orders below `min_qty`, and orders that round down to nothing.
+    pub fn lot_rounded_qty(&self, qty: i64) -> Result<i64, crate::PercolatorError> {
+        if qty < self.min_qty {
+            return Err(crate::PercolatorError::OrderTooSmall);
+        }
+        let lot = self.lot.max(1);
+        let rounded = qty - qty % lot;
+        if rounded <= 0 {
+            return Err(crate::PercolatorError::OrderTooSmall);
+        }
+        Ok(rounded)
+    }
+
+    /// Blend the mark toward a fill and the oracle price at that fill
+    ///
+    /// The observation is the midpoint of `fill_px` and `oracle_px` (just the
+    /// fill when no oracle price is given); the mark then moves
+    /// `MARK_PRICE_WEIGHT_BPS` of the way toward it. An unset mark is seeded
+    /// with the observation.
+    pub fn update_mark_price(&mut self, fill_px: i64, oracle_px: i64) -> i64 {
+        let observed = if oracle_px > 0 {
+            ((fill_px as i128 + oracle_px as i128) / 2) as i64
+        } else {
+            fill_px
+        };
+
+        self.mark_px = if self.mark_px <= 0 {
+            observed
+        } else {
+            let step = (observed as i128 - self.mark_px as i128) * MARK_PRICE_WEIGHT_BPS as i128 / 10_000;
+            (self.mark_px as i128 + step) as i64
+        };
+        self.mark_px
+    }
+
     /// Increment sequence number (on any book change)
-    pub fn increment_seqno(&mut self) -> u32 {
-        self.seqno = self.seqno.wrapping_add(1);
-        self.seqno
+    ///
+    /// Refuses to wrap: a wrapped seqno could match a stale expected_seqno
+    /// in the router's TOCTOU check. Once at u32::MAX the slab stops filling
+    /// until `reset_seqno`.
+    pub fn increment_seqno(&mut self) -> Result<u32, crate::PercolatorError> {
+        self.seqno = self
+            .seqno
+            .checked_add(1)
+            .ok_or(crate::PercolatorError::SeqnoExhausted)?;
+        Ok(self.seqno)
+    }
+
+    /// Restart an exhausted sequence at zero
+    ///
+    /// Only allowed once the seqno has hit u32::MAX, so it can't be used to
+    /// replay seqnos a router may still hold.
+    pub fn reset_seqno(&mut self) -> Result<(), crate::PercolatorError> {
+        if self.seqno != u32::MAX {
+            return Err(crate::PercolatorError::InvalidInstruction);
+        }
+        self.seqno = 0;
+        Ok(())
     }
 }
 
@@ -121,6 +234,7 @@ mod tests {
         assert!(header.validate());
         assert_eq!(header.seqno, 0);
         assert_eq!(header.version, 1);
+        assert_eq!(header.version_hash, [0; 32]);
         assert_eq!(header.magic, *SlabHeader::MAGIC);
     }
 
@@ -138,11 +252,38 @@ mod tests {
         );
 
         assert_eq!(header.seqno, 0);
-        assert_eq!(header.increment_seqno(), 1);
-        assert_eq!(header.increment_seqno(), 2);
+        assert_eq!(header.increment_seqno(), Ok(1));
+        assert_eq!(header.increment_seqno(), Ok(2));
         assert_eq!(header.seqno, 2);
     }
 
+    #[test]
+    fn test_seqno_refuses_to_wrap() {
+        let mut header = SlabHeader::new(
+            Pubkey::default(),
+            Pubkey::default(),
+            Pubkey::default(),
+            Pubkey::default(),
+            50_000_000_000,
+            20,
+            1_000_000,
+            255,
+        );
+
+        // Resetting a live sequence is refused
+        assert_eq!(header.reset_seqno(), Err(crate::PercolatorError::InvalidInstruction));
+
+        header.seqno = u32::MAX - 1;
+        assert_eq!(header.increment_seqno(), Ok(u32::MAX));
+        assert_eq!(header.increment_seqno(), Err(crate::PercolatorError::SeqnoExhausted));
+        assert_eq!(header.seqno, u32::MAX);
+
+        // A router holding the last seqno can't match after the reset
+        assert_eq!(header.reset_seqno(), Ok(()));
+        assert_eq!(header.seqno, 0);
+        assert_eq!(header.increment_seqno(), Ok(1));
+    }
+
     #[test]
     fn test_offsets() {
         let header = SlabHeader::new(
@@ -161,4 +302,104 @@ mod tests {
         assert!(header.off_book > header.off_quote_cache);
         assert!(header.off_receipt_area > header.off_book);
     }
+
+    #[test]
+    fn test_order_below_min_qty_rejected() {
+        let mut header = SlabHeader::new(
+            Pubkey::default(),
+            Pubkey::default(),
+            Pubkey::default(),
+            Pubkey::default(),
+            50_000_000_000,
+            20,
+            1_000_000,
+            255,
+        );
+        header.min_qty = 100_000; // 0.1
+        header.lot = 10_000; // 0.01
+
+        assert_eq!(header.lot_rounded_qty(99_999), Err(crate::PercolatorError::OrderTooSmall));
+        assert_eq!(header.lot_rounded_qty(100_000), Ok(100_000));
+
+        // A lot bigger than the minimum can still round an order to nothing
+        header.lot = 1_000_000;
+        assert_eq!(header.lot_rounded_qty(500_000), Err(crate::PercolatorError::OrderTooSmall));
+    }
+
+    #[test]
+    fn test_order_rounded_down_to_lot() {
+        let mut header = SlabHeader::new(
+            Pubkey::default(),
+            Pubkey::default(),
+            Pubkey::default(),
+            Pubkey::default(),
+            50_000_000_000,
+            20,
+            1_000_000,
+            255,
+        );
+
+        // Defaults fill any positive quantity as-is
+        assert_eq!(header.lot_rounded_qty(1_234_567), Ok(1_234_567));
+
+        header.lot = 250_000; // 0.25
+        assert_eq!(header.lot_rounded_qty(1_234_567), Ok(1_000_000));
+        assert_eq!(header.lot_rounded_qty(1_250_000), Ok(1_250_000));
+        assert_eq!(header.lot_rounded_qty(1_499_999), Ok(1_250_000));
+    }
+
+    #[test]
+    fn test_liquidation_fill_pays_no_taker_fee() {
+        let header = SlabHeader::new(
+            Pubkey::default(),
+            Pubkey::default(),
+            Pubkey::default(),
+            Pubkey::default(),
+            50_000_000_000,
+            20,
+            1_000_000,
+            255,
+        );
+
+        // 20 bps of a $50,000 fill is $100 for a normal taker...
+        assert_eq!(header.taker_fee(50_000_000_000, false), 100_000_000);
+        // ...and nothing for the same fill closing a liquidated account
+        assert_eq!(header.taker_fee(50_000_000_000, true), 0);
+    }
+
+    #[test]
+    fn test_mark_moves_smoothly_toward_fills() {
+        let mut header = SlabHeader::new(
+            Pubkey::default(),
+            Pubkey::default(),
+            Pubkey::default(),
+            Pubkey::default(),
+            100_000_000, // $100 mark
+            20,
+            1_000_000,
+            255,
+        );
+
+        // A single $120 print with the oracle still at $100 moves the mark
+        // 20% of the way to the $110 midpoint, not to the fill
+        assert_eq!(header.update_mark_price(120_000_000, 100_000_000), 102_000_000);
+
+        // Repeated prints keep closing the gap without overshooting
+        let mut prev = header.mark_px;
+        for _ in 0..10 {
+            let mark = header.update_mark_price(120_000_000, 100_000_000);
+            assert!(mark > prev && mark < 110_000_000);
+            assert!(mark - prev <= (110_000_000 - prev) / 5);
+            prev = mark;
+        }
+
+        // A fill back at the oracle pulls it down just as gradually
+        let before = header.mark_px;
+        let after = header.update_mark_price(100_000_000, 100_000_000);
+        assert_eq!(after, before - (before - 100_000_000) / 5);
+
+        // Without an oracle reading the fill alone is the observation
+        header.mark_px = 0;
+        assert_eq!(header.update_mark_price(95_000_000, 0), 95_000_000);
+    }
 }
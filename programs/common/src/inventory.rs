@@ -0,0 +1,96 @@
+//! LP inventory - the slab's own net position from fills
+
+use crate::PercolatorError;
+
+/// Net position the slab's LP has taken on as every taker's counterparty
+///
+/// A taker buy is an LP sell: base goes down by the fill quantity and quote
+/// up by its notional, and the reverse for a taker sell. Stored on the slab
+/// so matching and risk limits don't have to read the DLP portfolio.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LpInventory {
+    /// Signed base position (instrument qty decimals, + long)
+    pub net_base: i64,
+    /// Signed quote balance from fills (1e6 quote scale, + received)
+    pub net_quote: i64,
+    /// Largest |net_base| a fill may leave the LP at (0 = no limit)
+    pub max_inventory: i64,
+}
+
+impl LpInventory {
+    /// Empty inventory capped at `max_inventory` (0 = no limit)
+    pub fn new(max_inventory: i64) -> Self {
+        Self { net_base: 0, net_quote: 0, max_inventory }
+    }
+
+    /// Book a fill against the LP
+    ///
+    /// `taker_qty` is signed by the taker (+buy, -sell) and `notional` is the
+    /// fill's unsigned quote notional. Fails with InventoryLimit, leaving the
+    /// inventory untouched, when the fill grows |net_base| past the limit;
+    /// fills that shrink the position always go through.
+    pub fn apply_fill(&mut self, taker_qty: i64, notional: i64) -> Result<(), PercolatorError> {
+        let net_base = self.net_base.saturating_sub(taker_qty);
+        if self.max_inventory > 0
+            && net_base.unsigned_abs() > self.max_inventory as u64
+            && net_base.unsigned_abs() > self.net_base.unsigned_abs()
+        {
+            return Err(PercolatorError::InventoryLimit);
+        }
+
+        let quote = if taker_qty > 0 { notional } else { -notional };
+        self.net_base = net_base;
+        self.net_quote = self.net_quote.saturating_add(quote);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inventory_accumulates_across_fills() {
+        let mut inventory = LpInventory::new(0);
+
+        // Taker buys 2 at $100: the LP is short 2 and holds $200
+        inventory.apply_fill(2_000_000, 200_000_000).unwrap();
+        assert_eq!(inventory.net_base, -2_000_000);
+        assert_eq!(inventory.net_quote, 200_000_000);
+
+        // Taker sells 3 at $110: the LP is long 1 having paid $330
+        inventory.apply_fill(-3_000_000, 330_000_000).unwrap();
+        assert_eq!(inventory.net_base, 1_000_000);
+        assert_eq!(inventory.net_quote, -130_000_000);
+    }
+
+    #[test]
+    fn test_inventory_limit_rejects_growth() {
+        let mut inventory = LpInventory::new(5_000_000);
+        inventory.apply_fill(4_000_000, 400_000_000).unwrap();
+
+        // One more than the limit is refused and nothing is booked
+        assert_eq!(inventory.apply_fill(2_000_000, 200_000_000), Err(PercolatorError::InventoryLimit));
+        assert_eq!(inventory.net_base, -4_000_000);
+        assert_eq!(inventory.net_quote, 400_000_000);
+
+        // Exactly at the limit is allowed
+        inventory.apply_fill(1_000_000, 100_000_000).unwrap();
+        assert_eq!(inventory.net_base, -5_000_000);
+
+        // Reducing fills go through, as does flipping to the same size
+        inventory.apply_fill(-10_000_000, 1_000_000_000).unwrap();
+        assert_eq!(inventory.net_base, 5_000_000);
+        assert_eq!(inventory.apply_fill(-1, 1), Err(PercolatorError::InventoryLimit));
+    }
+
+    #[test]
+    fn test_inventory_limit_allows_reducing_an_oversized_position() {
+        // A limit lowered below the current position still lets it unwind
+        let mut inventory = LpInventory { net_base: -8_000_000, net_quote: 800_000_000, max_inventory: 5_000_000 };
+        inventory.apply_fill(-2_000_000, 200_000_000).unwrap();
+        assert_eq!(inventory.net_base, -6_000_000);
+        assert_eq!(inventory.apply_fill(1_000_000, 100_000_000), Err(PercolatorError::InventoryLimit));
+    }
+}
@@ -114,10 +114,148 @@ pub fn calculate_mm(qty: i64, contract_size: u64, mark_price: u64, mmr_bps: u64)
     (notional_value * (mmr_bps as u128)) / 10_000
 }
 
+/// Decimals of a slab's `contract_size` multiplier (1e6 fixed)
+pub const CONTRACT_SIZE_DECIMALS: u8 = 6;
+
+/// Convert an amount in contract units to collateral base units
+///
+/// Realized PnL and margin are tracked in contracts at the instrument's
+/// quantity scale (`qty / 10^qty_decimals`). `contract_size` is the slab's
+/// contract multiplier (1e6 fixed), in collateral per contract:
+///
+/// collateral = amount * (contract_size / 1e6) * 10^collateral_decimals / 10^qty_decimals
+///
+/// With SOL collateral (9 decimals) and 6 qty decimals, a 1 SOL contract
+/// (`contract_size = 1_000_000`) is a plain micro-SOL to lamport conversion
+/// (x1000). Saturates rather than wrapping.
+#[inline]
+pub fn pnl_lamports(amount: i128, contract_size: i64, scale: &crate::ScaleConfig) -> i128 {
+    let sized = amount.saturating_mul(contract_size as i128);
+    let shift = scale.collateral_decimals as i32 - CONTRACT_SIZE_DECIMALS as i32 - scale.qty_decimals as i32;
+    if shift >= 0 {
+        sized.saturating_mul(10i128.pow(shift as u32))
+    } else {
+        sized / 10i128.pow((-shift) as u32)
+    }
+}
+
+/// Collateral notional of `qty` contracts (magnitude, see `pnl_lamports`)
+///
+/// Inverse contracts are sized in quote, so they convert to base at `price` first.
+#[inline]
+pub fn notional_lamports(
+    qty: i64,
+    price: i64,
+    contract_size: i64,
+    kind: crate::ContractKind,
+    scale: &crate::ScaleConfig,
+) -> u128 {
+    let mut base = qty.unsigned_abs() as i128;
+    if kind == crate::ContractKind::Inverse {
+        base = inverse_base_qty(base, price, scale.instrument());
+    }
+    pnl_lamports(base, contract_size, scale).max(0) as u128
+}
+
+/// Collateral a position of `qty` contracts must post at `leverage`
+///
+/// `notional_lamports / leverage`, rounded up at every step (inverse base
+/// conversion included) so the protocol is never under-margined by a
+/// truncated lamport. Saturates rather than wrapping.
+#[inline]
+pub fn margin_lamports(
+    qty: i64,
+    price: i64,
+    leverage: u8,
+    contract_size: i64,
+    kind: crate::ContractKind,
+    scale: &crate::ScaleConfig,
+) -> u128 {
+    let mut base = qty.unsigned_abs() as u128;
+    if kind == crate::ContractKind::Inverse {
+        if price <= 0 {
+            return 0;
+        }
+        base = base.saturating_mul(scale.instrument().price_unit()).div_ceil(price as u128);
+    }
+    let sized = base.saturating_mul(contract_size.max(0) as u128);
+    let leverage = leverage.max(1) as u128;
+    let shift = scale.collateral_decimals as i32 - CONTRACT_SIZE_DECIMALS as i32 - scale.qty_decimals as i32;
+    if shift >= 0 {
+        sized.saturating_mul(10u128.pow(shift as u32)).div_ceil(leverage)
+    } else {
+        sized.div_ceil(10u128.pow((-shift) as u32).saturating_mul(leverage))
+    }
+}
+
+/// Margin released by closing `qty_closed` of a `prev_qty` position holding `margin_held`
+///
+/// Proportional and rounded down, so the protocol never releases more than
+/// it holds; the dust left by partial closes goes back with the full close
+/// (`qty_closed >= prev_qty` releases everything).
+#[inline]
+pub fn margin_release(margin_held: u128, qty_closed: u64, prev_qty: u64) -> u128 {
+    if qty_closed >= prev_qty {
+        return margin_held;
+    }
+    match margin_held.checked_mul(qty_closed as u128) {
+        Some(product) => product / prev_qty as u128,
+        // Past u128 only for absurd margins; lose precision rather than wrap
+        None => margin_held / prev_qty as u128 * qty_closed as u128,
+    }
+}
+
+/// `pnl_lamports` for an instrument margined in SOL lamports
+#[inline]
+pub fn pnl_to_lamports(amount: i128, contract_size: i64, scale: crate::InstrumentScale) -> i128 {
+    pnl_lamports(amount, contract_size, &crate::ScaleConfig::from(scale))
+}
+
+/// Quote notional of a fill, 1e6 scale
+///
+/// Linear: |qty| * |price| * contract_size / (10^price_decimals * 10^qty_decimals)
+/// Inverse qty is already quote notional, so only the multiplier applies:
+/// |qty| * contract_size / 10^qty_decimals.
+/// `contract_size` is the 1e6-scale multiplier stored in the slab header.
+/// Saturates at u128::MAX instead of overflowing.
+#[inline]
+pub fn contract_notional(
+    qty: i64,
+    price: i64,
+    contract_size: i64,
+    kind: crate::ContractKind,
+    scale: crate::InstrumentScale,
+) -> u128 {
+    let per_unit = match kind {
+        crate::ContractKind::Linear => (qty.unsigned_abs() as u128)
+            .checked_mul(price.unsigned_abs() as u128)
+            .map(|n| n / scale.price_unit()),
+        crate::ContractKind::Inverse => Some(qty.unsigned_abs() as u128),
+    };
+    per_unit
+        .and_then(|n| n.checked_mul(contract_size.unsigned_abs() as u128))
+        .map(|n| n / scale.qty_unit())
+        .unwrap_or(u128::MAX)
+}
+
+/// Convert an inverse contract's quote-notional quantity into base units
+///
+/// Inverse contracts are sized in quote (e.g. USD); the base they represent at
+/// `price` is qty / price. The result keeps `quote_qty`'s scale; `price` is
+/// at the instrument's price scale.
+pub fn inverse_base_qty(quote_qty: i128, price: i64, scale: crate::InstrumentScale) -> i128 {
+    if price <= 0 {
+        return 0;
+    }
+    quote_qty.saturating_mul(scale.price_unit() as i128) / price as i128
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const DEFAULT: crate::InstrumentScale = crate::InstrumentScale::DEFAULT;
+
     #[test]
     fn test_vwap_calculation() {
         let (qty, notional) = update_vwap(0, 0, 100, 50_000);
@@ -150,6 +288,139 @@ mod tests {
         let pnl = calculate_pnl(-10, 50_000, 51_000);
         assert_eq!(pnl, -10_000);
     }
+    #[test]
+    fn test_pnl_to_lamports() {
+        // 1 SOL contract: 0.5 SOL (500_000 micro-SOL) = 500_000_000 lamports
+        assert_eq!(pnl_to_lamports(500_000, 1_000_000, DEFAULT), 500_000_000);
+        assert_eq!(pnl_to_lamports(-500_000, 1_000_000, DEFAULT), -500_000_000);
+
+        // 0.1 SOL contract moves a tenth of the lamports
+        assert_eq!(pnl_to_lamports(500_000, 100_000, DEFAULT), 50_000_000);
+
+        // Saturates instead of overflowing
+        assert_eq!(pnl_to_lamports(i128::MAX, 2_000_000, DEFAULT), i128::MAX / 1_000);
+    }
+
+    #[test]
+    fn test_contract_notional_scales_with_contract_size() {
+        use crate::ContractKind::{Inverse, Linear};
+        let qty = 2_000_000; // 2 contracts
+        let px = 100_000_000; // $100
+
+        // contract_size 1, 10, 100: notional and fee scale proportionally
+        let fee_bps = 5;
+        for multiplier in [1i64, 10, 100] {
+            let notional = contract_notional(qty, px, multiplier * 1_000_000, Linear, DEFAULT);
+            assert_eq!(notional, 200_000_000 * multiplier as u128);
+            assert_eq!(notional * fee_bps / 10_000, 100_000 * multiplier as u128);
+        }
+
+        // Sign of qty doesn't matter
+        assert_eq!(contract_notional(-qty, px, 1_000_000, Linear, DEFAULT), 200_000_000);
+
+        // Inverse: $1,000 of contracts is $1,000 notional at any price
+        assert_eq!(contract_notional(1_000_000_000, px, 1_000_000, Inverse, DEFAULT), 1_000_000_000);
+        assert_eq!(contract_notional(1_000_000_000, px, 10_000_000, Inverse, DEFAULT), 10_000_000_000);
+
+        // Saturates instead of overflowing
+        assert_eq!(contract_notional(i64::MIN, i64::MIN, i64::MAX, Linear, DEFAULT), u128::MAX);
+    }
+
+    #[test]
+    fn test_inverse_base_qty() {
+        // $1,000 of inverse contracts at $50,000 is 0.02 BTC
+        assert_eq!(inverse_base_qty(1_000_000_000, 50_000_000_000, DEFAULT), 20_000);
+        assert_eq!(inverse_base_qty(-1_000_000_000, 50_000_000_000, DEFAULT), -20_000);
+        assert_eq!(inverse_base_qty(1_000_000_000, 0, DEFAULT), 0);
+    }
+
+    #[test]
+    fn test_scale_follows_instrument_decimals() {
+        use crate::ContractKind::Linear;
+        use crate::InstrumentScale;
+
+        // Same position, 2 contracts at $100, expressed at two different scales
+        let micro = InstrumentScale::DEFAULT;
+        let fine = InstrumentScale { price_decimals: 8, qty_decimals: 9 };
+
+        assert_eq!(contract_notional(2_000_000, 100_000_000, 1_000_000, Linear, micro), 200_000_000);
+        assert_eq!(contract_notional(2_000_000_000, 10_000_000_000, 1_000_000, Linear, fine), 200_000_000);
+
+        // 0.5 contracts of 1 SOL is 0.5 SOL either way
+        assert_eq!(pnl_to_lamports(500_000, 1_000_000, micro), 500_000_000);
+        assert_eq!(pnl_to_lamports(500_000_000, 1_000_000, fine), 500_000_000);
+
+        // Coarser than lamports: whole-hundredth contracts scale up
+        let coarse = InstrumentScale { price_decimals: 2, qty_decimals: 2 };
+        assert_eq!(pnl_to_lamports(50, 1_000_000, coarse), 500_000_000);
+
+        // Inverse base at an 8-decimal price: $1,000 at $50,000 is 0.02
+        assert_eq!(inverse_base_qty(1_000_000_000, 5_000_000_000_000, fine), 20_000);
+    }
+
+    #[test]
+    fn test_margin_rounds_in_protocol_favor() {
+        use crate::ContractKind::{Inverse, Linear};
+        use crate::ScaleConfig;
+
+        let scale = ScaleConfig::DEFAULT;
+
+        // 1 micro-contract of 1 SOL is 1_000 lamports; at 3x that's 333.33..., posted as 334
+        assert_eq!(margin_lamports(1, 100_000_000, 3, 1_000_000, Linear, &scale), 334);
+        // Exact divisions aren't bumped
+        assert_eq!(margin_lamports(3, 100_000_000, 3, 1_000_000, Linear, &scale), 1_000);
+
+        // A sub-lamport notional still posts a lamport
+        let wei = ScaleConfig { qty_decimals: 18, ..scale };
+        assert_eq!(margin_lamports(1, 100_000_000, 1, 1_000_000, Linear, &wei), 1);
+
+        // Inverse base rounds up too: $1 at $3 is 0.333... base
+        assert_eq!(margin_lamports(1_000_000, 3_000_000, 1, 1_000_000, Inverse, &scale), 333_334_000);
+    }
+
+    #[test]
+    fn test_margin_release_rounds_down_and_sweeps_dust() {
+        // Closing a third of 1_000 lamports releases 333, never 334
+        assert_eq!(margin_release(1_000, 1, 3), 333);
+        assert_eq!(margin_release(667, 1, 2), 333);
+
+        // The full close takes what's left, dust included
+        assert_eq!(margin_release(334, 1, 1), 334);
+        assert_eq!(margin_release(334, 5, 1), 334);
+        assert_eq!(margin_release(0, 1, 3), 0);
+    }
+
+    #[test]
+    fn test_listing_18_decimal_asset() {
+        use crate::ContractKind::{Inverse, Linear};
+        use crate::ScaleConfig;
+
+        // An 18-decimal collateral token, 1-token contracts sized at 9
+        // decimals, priced at 8 decimals off an expo -8 feed
+        let token = ScaleConfig { oracle_exponent: -8, price_decimals: 8, qty_decimals: 9, collateral_decimals: 18 };
+        let px = token.oracle_to_price(250_000_000_000);
+        assert_eq!(px, 250_000_000_000);
+
+        // 2.5 contracts is 2.5e18 base units; 5x margin is a fifth of that
+        assert_eq!(notional_lamports(2_500_000_000, px, 1_000_000, Linear, &token), 2_500_000_000_000_000_000);
+        assert_eq!(margin_lamports(-2_500_000_000, px, 5, 1_000_000, Linear, &token), 500_000_000_000_000_000);
+        assert_eq!(pnl_lamports(-100_000_000, 1_000_000, &token), -100_000_000_000_000_000);
+
+        // Inverse: $1,000 of quote at $2,500 is 0.4 tokens
+        assert_eq!(notional_lamports(1_000_000_000_000, px, 1_000_000, Inverse, &token), 400_000_000_000_000_000);
+
+        // Quantities at the full 18 decimals scale down instead of up
+        let wei = ScaleConfig { qty_decimals: 18, ..token };
+        assert_eq!(pnl_lamports(2_500_000_000_000_000_000, 1_000_000, &wei), 2_500_000_000_000_000_000);
+
+        // The same feed rescaled for a 6-decimal book
+        let micro_book = ScaleConfig { price_decimals: 6, ..token };
+        assert_eq!(micro_book.oracle_to_price(250_000_000_000), 2_500_000_000);
+
+        // The default config is the old lamport conversion
+        assert_eq!(pnl_lamports(500_000, 1_000_000, &ScaleConfig::DEFAULT), 500_000_000);
+        assert_eq!(ScaleConfig::from(DEFAULT), ScaleConfig::DEFAULT);
+    }
 }
 
 // ═══════════════════════════════════════════════════════════════
@@ -29,6 +29,16 @@ pub enum OracleError {
     LowConfidence,
     /// Oracle price is missing or unavailable
     PriceUnavailable,
+    /// Price exponent is outside the range we can normalize to 1e6
+    UnsupportedExponent,
+}
+
+/// Whether a price published at `timestamp` is older than `max_age_secs`
+///
+/// `now_ts` is the caller's unix time; 0 (or less) means no clock is
+/// available and nothing is treated as stale.
+pub fn is_stale_at(timestamp: i64, now_ts: i64, max_age_secs: i64) -> bool {
+    now_ts > 0 && now_ts.saturating_sub(timestamp) > max_age_secs
 }
 
 /// Unified interface for reading prices from different oracle providers
@@ -3,8 +3,8 @@
 // Reads prices from our custom test oracle (programs/oracle/)
 // Used for localnet testing only - NOT for production
 
-use super::adapter::{OracleAdapter, OracleError, OraclePrice};
-use pinocchio::account_info::AccountInfo;
+use super::adapter::{is_stale_at, OracleAdapter, OracleError, OraclePrice};
+use pinocchio::{account_info::AccountInfo, pubkey::Pubkey};
 
 /// Custom oracle adapter for test oracle
 pub struct CustomAdapter {
@@ -31,31 +31,34 @@ impl CustomAdapter {
     }
 }
 
-/// Custom oracle format (from programs/oracle/src/state.rs):
-/// ```
-/// pub struct PriceOracle {
-///     pub magic: [u8; 8],        // "PRCLORCL" (offset 0)
-///     pub version: u8,           // offset 8
-///     pub bump: u8,              // offset 9
-///     pub _padding: [u8; 6],     // offset 10
-///     pub authority: Pubkey,     // offset 16 (32 bytes)
-///     pub instrument: Pubkey,    // offset 48 (32 bytes)
-///     pub price: i64,            // offset 80 (8 bytes) <<<
-///     pub timestamp: i64,        // offset 88 (8 bytes)
-///     pub confidence: i64,       // offset 96 (8 bytes)
-///     pub _reserved: [u8; 24],   // offset 104
-/// }
-/// Total: 128 bytes
-/// ```
-
+// Custom oracle format (from programs/oracle/src/state.rs):
+// pub struct PriceOracle {
+//     pub magic: [u8; 8],        // "PRCLORCL" (offset 0)
+//     pub version: u8,           // offset 8
+//     pub bump: u8,              // offset 9
+//     pub _padding: [u8; 6],     // offset 10
+//     pub authority: Pubkey,     // offset 16 (32 bytes)
+//     pub instrument: Pubkey,    // offset 48 (32 bytes)
+//     pub price: i64,            // offset 80 (8 bytes) <<<
+//     pub timestamp: i64,        // offset 88 (8 bytes)
+//     pub confidence: i64,       // offset 96 (8 bytes)
+//     pub _reserved: [u8; 24],   // offset 104
+// }
+// Total: 128 bytes
+
+const AUTHORITY_OFFSET: usize = 16;
 const PRICE_OFFSET: usize = 80;
 const TIMESTAMP_OFFSET: usize = 88;
 const CONFIDENCE_OFFSET: usize = 96;
 const ORACLE_SIZE: usize = 128;
 const MAGIC: &[u8; 8] = b"PRCLORCL";
 
-impl OracleAdapter for CustomAdapter {
-    fn read_price(&self, oracle_account: &AccountInfo) -> Result<OraclePrice, OracleError> {
+/// Size of a custom oracle account
+pub const CUSTOM_ORACLE_SIZE: usize = ORACLE_SIZE;
+
+impl CustomAdapter {
+    /// Read the oracle account, judging staleness at `now_ts` (0 = no clock)
+    pub fn read_price_at(&self, oracle_account: &AccountInfo, now_ts: i64) -> Result<OraclePrice, OracleError> {
         // Validate account first
         self.validate_account(oracle_account)?;
 
@@ -64,6 +67,11 @@ impl OracleAdapter for CustomAdapter {
             .try_borrow_data()
             .map_err(|_| OracleError::InvalidAccount)?;
 
+        self.parse_price_data(&data, now_ts)
+    }
+
+    /// Parse a raw custom oracle account
+    pub(crate) fn parse_price_data(&self, data: &[u8], now_ts: i64) -> Result<OraclePrice, OracleError> {
         // Validate data length
         if data.len() != ORACLE_SIZE {
             return Err(OracleError::InvalidFormat);
@@ -93,7 +101,7 @@ impl OracleAdapter for CustomAdapter {
         let confidence = i64::from_le_bytes(conf_bytes);
 
         // Check staleness
-        if self.is_stale(timestamp, self.max_age_secs) {
+        if is_stale_at(timestamp, now_ts, self.max_age_secs) {
             return Err(OracleError::StalePrice);
         }
 
@@ -106,6 +114,40 @@ impl OracleAdapter for CustomAdapter {
         })
     }
 
+    /// Write a price into raw custom oracle data (localnet / tests)
+    ///
+    /// Zeroed data is claimed for `authority`, after which only that
+    /// authority may write. Fails with InvalidFormat on data that is the wrong
+    /// size or isn't a custom oracle, and InvalidAccount for another authority.
+    pub fn write_price_data(data: &mut [u8], authority: &Pubkey, price: i64, timestamp: i64) -> Result<(), OracleError> {
+        if data.len() != ORACLE_SIZE {
+            return Err(OracleError::InvalidFormat);
+        }
+
+        if &data[0..8] == MAGIC {
+            if &data[AUTHORITY_OFFSET..AUTHORITY_OFFSET + 32] != authority.as_ref() {
+                return Err(OracleError::InvalidAccount);
+            }
+        } else if data.iter().all(|b| *b == 0) {
+            data[0..8].copy_from_slice(MAGIC);
+            data[8] = 1; // version
+            data[AUTHORITY_OFFSET..AUTHORITY_OFFSET + 32].copy_from_slice(authority.as_ref());
+        } else {
+            return Err(OracleError::InvalidFormat);
+        }
+
+        data[PRICE_OFFSET..PRICE_OFFSET + 8].copy_from_slice(&price.to_le_bytes());
+        data[TIMESTAMP_OFFSET..TIMESTAMP_OFFSET + 8].copy_from_slice(&timestamp.to_le_bytes());
+        data[CONFIDENCE_OFFSET..CONFIDENCE_OFFSET + 8].copy_from_slice(&0i64.to_le_bytes());
+        Ok(())
+    }
+}
+
+impl OracleAdapter for CustomAdapter {
+    fn read_price(&self, oracle_account: &AccountInfo) -> Result<OraclePrice, OracleError> {
+        self.read_price_at(oracle_account, Self::current_timestamp())
+    }
+
     fn validate_account(&self, oracle_account: &AccountInfo) -> Result<(), OracleError> {
         // Check account has data
         let data = oracle_account
@@ -126,8 +168,7 @@ impl OracleAdapter for CustomAdapter {
     }
 
     fn is_stale(&self, timestamp: i64, max_age_secs: i64) -> bool {
-        let current_ts = Self::current_timestamp();
-        current_ts - timestamp > max_age_secs
+        is_stale_at(timestamp, Self::current_timestamp(), max_age_secs)
     }
 
     fn provider_name(&self) -> &'static str {
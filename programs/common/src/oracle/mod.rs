@@ -0,0 +1,279 @@
+//! Oracle adapters shared by the router and slab programs
+//!
+//! Reads prices from:
+//! - Pyth Network (production mainnet/devnet)
+//! - Switchboard V2 aggregators (production mainnet/devnet)
+//! - Custom oracle (localnet testing)
+//!
+//! All prices are normalized to 1e6 scale (i.e., $50,000 = 50_000_000_000).
+//! Both programs read spot feeds through `read_price_1e6`, so staleness,
+//! confidence and exponent handling live in one place. The router layers its
+//! TWAP buffer on top of these.
+
+pub mod adapter;
+pub mod pyth;
+pub mod switchboard;
+pub mod custom;
+
+pub use adapter::{is_stale_at, OracleAdapter, OraclePrice, OracleError};
+pub use pyth::{PythAdapter, PYTH_PROGRAM_ID};
+pub use switchboard::{SwitchboardAdapter, SWITCHBOARD_PROGRAM_ID};
+pub use custom::{CustomAdapter, CUSTOM_ORACLE_SIZE};
+
+use crate::PercolatorError;
+use pinocchio::{account_info::AccountInfo, msg, pubkey::Pubkey};
+
+/// Read a spot price normalized to 1e6, picking the adapter from the account's owner
+///
+/// Pyth-owned accounts go through PythAdapter, Switchboard-owned ones
+/// through SwitchboardAdapter, anything else through CustomAdapter, each
+/// with its default staleness and confidence limits.
+/// Feeds publish unix timestamps, so `now_ts` is the Clock's unix time
+/// (0 = no clock, staleness unchecked).
+pub fn read_price_1e6(oracle_account: &AccountInfo, now_ts: i64) -> Result<OraclePrice, OracleError> {
+    if oracle_account.owner() == &PYTH_PROGRAM_ID {
+        PythAdapter::new().read_price_at(oracle_account, now_ts)
+    } else if oracle_account.owner() == &SWITCHBOARD_PROGRAM_ID {
+        SwitchboardAdapter::new().read_price_at(oracle_account, now_ts)
+    } else {
+        CustomAdapter::new().read_price_at(oracle_account, now_ts)
+    }
+}
+
+/// A venue's own check on the prices it is asked to fill at
+///
+/// Stored on the slab so a fill is bounded by the slab's registered oracle
+/// even if the caller passing the execution price is compromised.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OracleGuard {
+    /// Oracle account fills are checked against
+    pub oracle: Pubkey,
+    /// Largest execution price deviation from the oracle (basis points, 0 = guard off)
+    pub max_deviation_bps: u64,
+}
+
+impl OracleGuard {
+    /// Whether fills are checked at all
+    pub fn is_enabled(&self) -> bool {
+        self.max_deviation_bps != 0
+    }
+
+    /// Check that `oracle_key` is the registered oracle
+    ///
+    /// Fails with OracleMismatch otherwise, so a caller can't swap in a feed
+    /// that agrees with its price.
+    pub fn check_oracle_key(&self, oracle_key: &Pubkey) -> Result<(), PercolatorError> {
+        if oracle_key != &self.oracle {
+            msg!("Error: Oracle account is not the slab's registered oracle");
+            return Err(PercolatorError::OracleMismatch);
+        }
+        Ok(())
+    }
+
+    /// Check an execution price against an oracle price
+    ///
+    /// Fails with PriceSlippage when `execution_px` is more than
+    /// `max_deviation_bps` away from `oracle_px`; always passes when the
+    /// guard is off.
+    pub fn check_price(&self, execution_px: i64, oracle_px: i64) -> Result<(), PercolatorError> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        if oracle_px <= 0 {
+            msg!("Error: Non-positive oracle price");
+            return Err(PercolatorError::InvalidOracle);
+        }
+
+        let deviation = (execution_px as i128 - oracle_px as i128).unsigned_abs();
+        if deviation * 10_000 > oracle_px as u128 * self.max_deviation_bps as u128 {
+            msg!("Error: Execution price outside the slab's oracle band");
+            return Err(PercolatorError::PriceSlippage);
+        }
+        Ok(())
+    }
+
+    /// Check an execution price against the registered oracle account
+    ///
+    /// Verifies the account, reads it with `read_price_1e6` at `now_ts` and
+    /// applies `check_price`. Reads nothing when the guard is off.
+    pub fn check_fill(&self, oracle_account: &AccountInfo, execution_px: i64, now_ts: i64) -> Result<(), PercolatorError> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        self.check_oracle_key(oracle_account.key())?;
+        let oracle_price = read_price_1e6(oracle_account, now_ts).map_err(|_| {
+            msg!("Error: Oracle read failed");
+            PercolatorError::InvalidOracle
+        })?;
+        self.check_price(execution_px, oracle_price.price)
+    }
+}
+
+#[cfg(test)]
+mod adapter_tests {
+    //! Both adapters against synthetic account data
+
+    use super::*;
+
+    /// Custom oracle account (see custom.rs for the layout)
+    fn custom_account(price: i64, timestamp: i64) -> [u8; 128] {
+        let mut data = [0u8; 128];
+        data[0..8].copy_from_slice(b"PRCLORCL");
+        data[80..88].copy_from_slice(&price.to_le_bytes());
+        data[88..96].copy_from_slice(&timestamp.to_le_bytes());
+        data[96..104].copy_from_slice(&10_000i64.to_le_bytes());
+        data
+    }
+
+    /// Custom oracle account as written by CustomAdapter::write_price_data
+    fn custom_account_with(price: i64, timestamp: i64, authority: Pubkey) -> [u8; 128] {
+        let mut data = custom_account(price, timestamp);
+        data[8] = 1;
+        data[16..48].copy_from_slice(&authority);
+        data[96..104].fill(0);
+        data
+    }
+
+    /// Pyth V1 price account (see pyth.rs for the layout)
+    fn pyth_account(price: i64, conf: u64, expo: i32, status: u32, timestamp: i64) -> [u8; 184] {
+        let mut data = [0u8; 184];
+        data[0..4].copy_from_slice(&pyth::PYTH_MAGIC.to_le_bytes());
+        data[4..8].copy_from_slice(&pyth::PYTH_VERSION.to_le_bytes());
+        data[8..12].copy_from_slice(&pyth::PYTH_PRICE_ACCOUNT_TYPE.to_le_bytes());
+        data[80..88].copy_from_slice(&price.to_le_bytes());
+        data[88..96].copy_from_slice(&conf.to_le_bytes());
+        data[96..100].copy_from_slice(&status.to_le_bytes());
+        data[112..116].copy_from_slice(&expo.to_le_bytes());
+        data[176..184].copy_from_slice(&timestamp.to_le_bytes());
+        data
+    }
+
+    const TRADING: u32 = 1;
+
+    #[test]
+    fn test_both_adapters_normalize_to_1e6() {
+        // $100 from each source
+        let custom = CustomAdapter::new().parse_price_data(&custom_account(100_000_000, 1_000), 1_010).unwrap();
+        assert_eq!(custom.price, 100_000_000);
+        assert_eq!(custom.expo, -6);
+
+        let pyth = PythAdapter::new()
+            .parse_price_data(&pyth_account(10_000_000_000, 1_000_000, -8, TRADING, 1_000), 1_010)
+            .unwrap();
+        assert_eq!(pyth.price, 100_000_000);
+        assert_eq!(pyth.confidence, 10_000);
+        assert_eq!(pyth.timestamp, 1_000);
+    }
+
+    #[test]
+    fn test_staleness_shared_by_both_adapters() {
+        // 60s max age: 60s old passes, 61s old is stale
+        assert!(CustomAdapter::new().parse_price_data(&custom_account(100_000_000, 1_000), 1_060).is_ok());
+        assert_eq!(
+            CustomAdapter::new().parse_price_data(&custom_account(100_000_000, 1_000), 1_061).map(|p| p.price),
+            Err(OracleError::StalePrice)
+        );
+        let pyth = pyth_account(10_000_000_000, 0, -8, TRADING, 1_000);
+        assert!(PythAdapter::new().parse_price_data(&pyth, 1_060).is_ok());
+        assert_eq!(PythAdapter::new().parse_price_data(&pyth, 1_061).map(|p| p.price), Err(OracleError::StalePrice));
+
+        // Without a clock nothing is stale
+        assert!(!is_stale_at(0, 0, 60));
+        assert!(PythAdapter::new().parse_price_data(&pyth, 0).is_ok());
+    }
+
+    #[test]
+    fn test_pyth_rejects_wide_confidence_and_halted_feed() {
+        // 3% confidence against the 2% default
+        assert_eq!(
+            PythAdapter::new()
+                .parse_price_data(&pyth_account(10_000_000_000, 300_000_000, -8, TRADING, 0), 0)
+                .map(|p| p.price),
+            Err(OracleError::LowConfidence)
+        );
+        // Not trading (status 0 = unknown)
+        assert_eq!(
+            PythAdapter::new().parse_price_data(&pyth_account(10_000_000_000, 0, -8, 0, 0), 0).map(|p| p.price),
+            Err(OracleError::PriceUnavailable)
+        );
+    }
+
+    #[test]
+    fn test_custom_writer_round_trip() {
+        let authority = [7; 32];
+        let mut data = [0u8; CUSTOM_ORACLE_SIZE];
+
+        CustomAdapter::write_price_data(&mut data, &authority, 42_000_000, 1_000).unwrap();
+        let price = CustomAdapter::new().parse_price_data(&data, 1_000).unwrap();
+        assert_eq!((price.price, price.timestamp), (42_000_000, 1_000));
+        assert_eq!(data, custom_account_with(42_000_000, 1_000, authority));
+
+        // The first writer owns the feed
+        assert_eq!(
+            CustomAdapter::write_price_data(&mut data, &[8; 32], 1, 1_001),
+            Err(OracleError::InvalidAccount)
+        );
+        CustomAdapter::write_price_data(&mut data, &authority, 43_000_000, 1_001).unwrap();
+        assert_eq!(CustomAdapter::new().parse_price_data(&data, 1_001).unwrap().price, 43_000_000);
+
+        // Anything that isn't blank or a custom oracle is left alone
+        let mut other = [1u8; CUSTOM_ORACLE_SIZE];
+        assert_eq!(CustomAdapter::write_price_data(&mut other, &authority, 1, 0), Err(OracleError::InvalidFormat));
+        assert_eq!(CustomAdapter::write_price_data(&mut [0u8; 64], &authority, 1, 0), Err(OracleError::InvalidFormat));
+    }
+
+    #[test]
+    fn test_malformed_accounts_rejected() {
+        let mut bad_magic = custom_account(100_000_000, 0);
+        bad_magic[0] = b'X';
+        assert_eq!(CustomAdapter::new().parse_price_data(&bad_magic, 0).map(|p| p.price), Err(OracleError::InvalidFormat));
+        assert_eq!(
+            CustomAdapter::new().parse_price_data(&bad_magic[..100], 0).map(|p| p.price),
+            Err(OracleError::InvalidFormat)
+        );
+        assert_eq!(
+            PythAdapter::new().parse_price_data(&[0u8; 100], 0).map(|p| p.price),
+            Err(OracleError::InvalidFormat)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCALE: i64 = 1_000_000;
+
+    fn guard(max_deviation_bps: u64) -> OracleGuard {
+        OracleGuard { oracle: [4; 32], max_deviation_bps }
+    }
+
+    #[test]
+    fn test_router_price_in_band() {
+        // 2% band around $100
+        let guard = guard(200);
+        assert_eq!(guard.check_price(100 * SCALE, 100 * SCALE), Ok(()));
+        assert_eq!(guard.check_price(102 * SCALE, 100 * SCALE), Ok(()));
+        assert_eq!(guard.check_price(98 * SCALE, 100 * SCALE), Ok(()));
+        assert_eq!(guard.check_oracle_key(&[4; 32]), Ok(()));
+    }
+
+    #[test]
+    fn test_router_price_out_of_band() {
+        let guard = guard(200);
+        assert_eq!(guard.check_price(102 * SCALE + 1, 100 * SCALE), Err(PercolatorError::PriceSlippage));
+        assert_eq!(guard.check_price(SCALE, 100 * SCALE), Err(PercolatorError::PriceSlippage));
+        assert_eq!(guard.check_price(100 * SCALE, 0), Err(PercolatorError::InvalidOracle));
+
+        // A different feed is refused outright
+        assert_eq!(guard.check_oracle_key(&[5; 32]), Err(PercolatorError::OracleMismatch));
+    }
+
+    #[test]
+    fn test_guard_off_trusts_router() {
+        let guard = OracleGuard::default();
+        assert!(!guard.is_enabled());
+        assert_eq!(guard.check_price(SCALE, 100 * SCALE), Ok(()));
+    }
+}
@@ -0,0 +1,343 @@
+// Pyth Network oracle adapter for Barista DEX
+//
+// Reads prices from Pyth price feeds on Solana devnet/mainnet
+// Documentation: https://docs.pyth.network/price-feeds/use-real-time-data/solana
+
+use super::adapter::{is_stale_at, OracleAdapter, OracleError, OraclePrice};
+use pinocchio::account_info::AccountInfo;
+
+// Manual Pyth account parsing to avoid AccountInfo type incompatibility
+// Pyth V1 account format: https://github.com/pyth-network/pyth-client/blob/main/program/rust/src/oracle.rs
+
+/// Magic number at the start of every Pyth account
+pub(crate) const PYTH_MAGIC: u32 = 0xa1b2c3d4;
+
+/// Pyth account layout version this adapter understands
+pub(crate) const PYTH_VERSION: u32 = 2;
+
+/// Pyth account type of a price account
+pub(crate) const PYTH_PRICE_ACCOUNT_TYPE: u32 = 3;
+
+/// Bytes of a price account read here (through the aggregate timestamp)
+const PYTH_PRICE_ACCOUNT_MIN_LEN: usize = 184;
+
+/// Pyth price status
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+enum PythPriceStatus {
+    Unknown = 0,
+    Trading = 1,
+    Halted = 2,
+    Auction = 3,
+}
+
+impl PythPriceStatus {
+    fn from_u32(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(Self::Unknown),
+            1 => Some(Self::Trading),
+            2 => Some(Self::Halted),
+            3 => Some(Self::Auction),
+            _ => None,
+        }
+    }
+}
+
+/// Pyth Program ID (mainnet/devnet)
+/// FsJ3A3u2vn5cTVofAjvy6y5kwABJAqYWpe4975bi2epH
+pub const PYTH_PROGRAM_ID: [u8; 32] = [
+    0xd6, 0x8b, 0x8f, 0x6f, 0x8a, 0x8e, 0x5c, 0x2f,
+    0x6e, 0x3a, 0x7d, 0x8f, 0x5a, 0x4e, 0x9c, 0x1d,
+    0x2a, 0x3b, 0x4c, 0x5d, 0x6e, 0x7f, 0x8a, 0x9b,
+    0xac, 0xbd, 0xce, 0xdf, 0xf0, 0x01, 0x12, 0x23,
+];
+
+/// Most negative Pyth exponent accepted (below it, normalizing to 1e6
+/// would throw away more precision than the feed publishes)
+pub const PYTH_MIN_EXPO: i32 = -12;
+
+/// Most positive Pyth exponent accepted (Pyth USD feeds are never coarser
+/// than whole units)
+pub const PYTH_MAX_EXPO: i32 = 0;
+
+/// Pyth oracle adapter
+pub struct PythAdapter {
+    /// Maximum confidence as percentage of price (e.g., 2 = 2%)
+    /// Reject prices with confidence interval > this threshold
+    pub max_confidence_pct: u64,
+
+    /// Maximum price age in seconds (e.g., 60 = reject prices older than 1 minute)
+    pub max_age_secs: i64,
+}
+
+impl PythAdapter {
+    /// Create new Pyth adapter with default parameters
+    pub fn new() -> Self {
+        Self {
+            max_confidence_pct: 2,  // 2% max confidence
+            max_age_secs: 60,        // 60 seconds max age
+        }
+    }
+
+    /// Create Pyth adapter with custom parameters
+    pub fn with_params(max_confidence_pct: u64, max_age_secs: i64) -> Self {
+        Self {
+            max_confidence_pct,
+            max_age_secs,
+        }
+    }
+
+    /// Scale Pyth price (with exponent) to 1e6 fixed scale
+    ///
+    /// Pyth uses variable exponents (typically -8 for BTC/USD)
+    /// We normalize everything to 1e6 scale for consistency:
+    /// scaled = price * 10^(expo + 6)
+    fn scale_price(price: i64, expo: i32) -> Result<i64, OracleError> {
+        const TARGET_SCALE: i32 = 6; // 1e6
+
+        if !(PYTH_MIN_EXPO..=PYTH_MAX_EXPO).contains(&expo) {
+            return Err(OracleError::UnsupportedExponent);
+        }
+
+        let shift = expo + TARGET_SCALE;
+        if shift >= 0 {
+            // Coarser than 1e6 (e.g. -2): scale up, rejecting overflow
+            price
+                .checked_mul(10_i64.pow(shift as u32))
+                .ok_or(OracleError::InvalidFormat)
+        } else {
+            // Finer than 1e6 (e.g. -8): scale down, truncating toward zero
+            Ok(price / 10_i64.pow((-shift) as u32))
+        }
+    }
+
+    /// Read the price account, judging staleness at `now_ts` (0 = no clock)
+    pub fn read_price_at(&self, oracle_account: &AccountInfo, now_ts: i64) -> Result<OraclePrice, OracleError> {
+        // Validate account first
+        self.validate_account(oracle_account)?;
+
+        // Borrow raw account data
+        let data = oracle_account
+            .try_borrow_data()
+            .map_err(|_| OracleError::InvalidAccount)?;
+
+        self.parse_price_data(&data, now_ts)
+    }
+
+    /// Parse and normalize a raw Pyth V1 price account
+    pub(crate) fn parse_price_data(&self, data: &[u8], now_ts: i64) -> Result<OraclePrice, OracleError> {
+        // Parse Pyth price account manually
+        // Pyth V1 Price Account format (as of pyth-sdk-solana 0.10):
+        // Offset  | Size | Field
+        // --------|------|-------
+        // 0       | 4    | magic (0xa1b2c3d4)
+        // 4       | 4    | version (2)
+        // 8       | 4    | type (3 = price account)
+        // 12      | 4    | size
+        // 16      | 32   | product account
+        // 48      | 32   | next price account
+        // 80      | 8    | agg.price (i64)
+        // 88      | 8    | agg.conf (u64)
+        // 96      | 4    | agg.status (u32)
+        // 100     | 4    | agg.corp_act
+        // 104     | 8    | agg.pub_slot (u64)
+        // 112     | 4    | expo (i32)
+        // ...
+        // 176     | 8    | timestamp (i64)
+
+        // Shape before contents: anything that isn't a Pyth price account
+        // long enough to hold the fields below is rejected unread
+        if data.len() < PYTH_PRICE_ACCOUNT_MIN_LEN {
+            return Err(OracleError::InvalidFormat);
+        }
+        let header_u32 = |offset: usize| u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]]);
+        if header_u32(0) != PYTH_MAGIC
+            || header_u32(4) != PYTH_VERSION
+            || header_u32(8) != PYTH_PRICE_ACCOUNT_TYPE
+        {
+            return Err(OracleError::InvalidFormat);
+        }
+
+        // Read expo (offset 112, i32 little-endian)
+        let expo_bytes: [u8; 4] = data[112..116]
+            .try_into()
+            .map_err(|_| OracleError::InvalidFormat)?;
+        let expo = i32::from_le_bytes(expo_bytes);
+
+        // Read agg.price (offset 80, i64 little-endian)
+        let price_bytes: [u8; 8] = data[80..88]
+            .try_into()
+            .map_err(|_| OracleError::InvalidFormat)?;
+        let price = i64::from_le_bytes(price_bytes);
+
+        // Read agg.conf (offset 88, u64 little-endian)
+        let conf_bytes: [u8; 8] = data[88..96]
+            .try_into()
+            .map_err(|_| OracleError::InvalidFormat)?;
+        let conf = u64::from_le_bytes(conf_bytes);
+
+        // Read agg.status (offset 96, u32 little-endian)
+        let status_bytes: [u8; 4] = data[96..100]
+            .try_into()
+            .map_err(|_| OracleError::InvalidFormat)?;
+        let status_u32 = u32::from_le_bytes(status_bytes);
+        let status = PythPriceStatus::from_u32(status_u32)
+            .ok_or(OracleError::InvalidFormat)?;
+
+        // Read timestamp (offset 176, i64 little-endian)
+        let ts_bytes: [u8; 8] = data[176..184]
+            .try_into()
+            .map_err(|_| OracleError::InvalidFormat)?;
+        let timestamp = i64::from_le_bytes(ts_bytes);
+
+        // Check if price is valid/trading
+        if status != PythPriceStatus::Trading {
+            return Err(OracleError::PriceUnavailable);
+        }
+
+        // Check staleness
+        if is_stale_at(timestamp, now_ts, self.max_age_secs) {
+            return Err(OracleError::StalePrice);
+        }
+
+        // Validate confidence interval
+        let conf_abs = conf as u128;
+        let price_abs = price.unsigned_abs() as u128;
+
+        if let Some(confidence_pct) = (conf_abs * 100).checked_div(price_abs) {
+            if confidence_pct > self.max_confidence_pct as u128 {
+                return Err(OracleError::LowConfidence);
+            }
+        }
+
+        // Scale price and confidence to 1e6 format
+        let scaled_price = Self::scale_price(price, expo)?;
+        let scaled_conf = Self::scale_price(conf as i64, expo)?;
+
+        Ok(OraclePrice {
+            price: scaled_price,
+            confidence: scaled_conf,
+            timestamp,
+            expo,
+        })
+    }
+
+    /// Get current Unix timestamp
+    /// In BPF, this would read from Clock sysvar
+    fn current_timestamp() -> i64 {
+        // TODO: In actual BPF program, read from Clock sysvar
+        // For now, return placeholder
+        0
+    }
+}
+
+impl OracleAdapter for PythAdapter {
+    fn read_price(&self, oracle_account: &AccountInfo) -> Result<OraclePrice, OracleError> {
+        self.read_price_at(oracle_account, Self::current_timestamp())
+    }
+
+    fn validate_account(&self, oracle_account: &AccountInfo) -> Result<(), OracleError> {
+        // Check account owner is Pyth program
+        let owner = oracle_account.owner();
+        if owner != &PYTH_PROGRAM_ID {
+            return Err(OracleError::InvalidAccount);
+        }
+
+        Ok(())
+    }
+
+    fn is_stale(&self, timestamp: i64, max_age_secs: i64) -> bool {
+        is_stale_at(timestamp, Self::current_timestamp(), max_age_secs)
+    }
+
+    fn provider_name(&self) -> &'static str {
+        "Pyth"
+    }
+}
+
+impl Default for PythAdapter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Minimal Pyth V1 price account: Trading status, 1 conf unit
+    fn price_account(price: i64, expo: i32) -> [u8; 184] {
+        let mut data = [0u8; 184];
+        data[0..4].copy_from_slice(&PYTH_MAGIC.to_le_bytes());
+        data[4..8].copy_from_slice(&PYTH_VERSION.to_le_bytes());
+        data[8..12].copy_from_slice(&PYTH_PRICE_ACCOUNT_TYPE.to_le_bytes());
+        data[80..88].copy_from_slice(&price.to_le_bytes());
+        data[88..96].copy_from_slice(&1u64.to_le_bytes());
+        data[96..100].copy_from_slice(&(PythPriceStatus::Trading as u32).to_le_bytes());
+        data[112..116].copy_from_slice(&expo.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn test_expo_minus_8_normalizes_to_1e6() {
+        // BTC/USD $50,123.45678901 published at expo -8
+        let price = PythAdapter::new().parse_price_data(&price_account(5_012_345_678_901, -8), 0).unwrap();
+        assert_eq!(price.price, 50_123_456_789);
+        assert_eq!(price.expo, -8);
+    }
+
+    #[test]
+    fn test_expo_minus_2_normalizes_to_1e6() {
+        // $123.45 published at expo -2
+        let price = PythAdapter::new().parse_price_data(&price_account(12_345, -2), 0).unwrap();
+        assert_eq!(price.price, 123_450_000);
+        assert_eq!(price.confidence, 10_000);
+    }
+
+    #[test]
+    fn test_expo_out_of_range_rejected() {
+        let adapter = PythAdapter::new();
+        assert_eq!(
+            adapter.parse_price_data(&price_account(100, 2), 0).map(|p| p.price),
+            Err(OracleError::UnsupportedExponent)
+        );
+        assert_eq!(
+            adapter.parse_price_data(&price_account(100, -13), 0).map(|p| p.price),
+            Err(OracleError::UnsupportedExponent)
+        );
+
+        // In range, but the 1e6 price doesn't fit in i64
+        assert_eq!(PythAdapter::scale_price(i64::MAX / 10, 0), Err(OracleError::InvalidFormat));
+        assert_eq!(PythAdapter::scale_price(7, -6), Ok(7));
+    }
+
+    #[test]
+    fn test_truncated_account_rejected() {
+        let data = price_account(10_000_000_000, -8);
+        assert_eq!(
+            PythAdapter::new().parse_price_data(&data[..183], 0).map(|p| p.price),
+            Err(OracleError::InvalidFormat)
+        );
+        assert_eq!(PythAdapter::new().parse_price_data(&[], 0).map(|p| p.price), Err(OracleError::InvalidFormat));
+    }
+
+    #[test]
+    fn test_wrong_header_rejected() {
+        let adapter = PythAdapter::new();
+
+        // Wrong magic, e.g. an attacker-crafted account with a plausible price
+        let mut data = price_account(10_000_000_000, -8);
+        data[0] ^= 0xff;
+        assert_eq!(adapter.parse_price_data(&data, 0).map(|p| p.price), Err(OracleError::InvalidFormat));
+
+        // Unknown layout version
+        let mut data = price_account(10_000_000_000, -8);
+        data[4..8].copy_from_slice(&1u32.to_le_bytes());
+        assert_eq!(adapter.parse_price_data(&data, 0).map(|p| p.price), Err(OracleError::InvalidFormat));
+
+        // A product account (type 2) rather than a price account
+        let mut data = price_account(10_000_000_000, -8);
+        data[8..12].copy_from_slice(&2u32.to_le_bytes());
+        assert_eq!(adapter.parse_price_data(&data, 0).map(|p| p.price), Err(OracleError::InvalidFormat));
+    }
+}
@@ -0,0 +1,277 @@
+// Switchboard V2 oracle adapter for Barista DEX
+//
+// Reads the latest confirmed round of a Switchboard aggregator account
+// Documentation: https://docs.switchboard.xyz/
+
+use super::adapter::{is_stale_at, OracleAdapter, OracleError, OraclePrice};
+use pinocchio::account_info::AccountInfo;
+
+// Manual aggregator parsing, as for Pyth, to avoid pulling in the Switchboard SDK
+// Layout: AggregatorAccountData in switchboard-v2 (packed, Anchor account)
+
+/// Anchor discriminator of an AggregatorAccountData account
+pub(crate) const SWITCHBOARD_AGGREGATOR_DISCRIMINATOR: [u8; 8] = [217, 230, 65, 101, 201, 162, 27, 125];
+
+/// Offset of latest_confirmed_round.num_success (u32)
+const NUM_SUCCESS_OFFSET: usize = 341;
+/// Offset of latest_confirmed_round.round_open_timestamp (i64)
+const ROUND_OPEN_TIMESTAMP_OFFSET: usize = 358;
+/// Offset of latest_confirmed_round.result (SwitchboardDecimal: i128 mantissa, u32 scale)
+const RESULT_OFFSET: usize = 366;
+/// Offset of latest_confirmed_round.std_deviation (SwitchboardDecimal)
+const STD_DEVIATION_OFFSET: usize = 386;
+
+/// Bytes of an aggregator account read here (through std_deviation)
+const SWITCHBOARD_AGGREGATOR_MIN_LEN: usize = STD_DEVIATION_OFFSET + 20;
+
+/// Switchboard V2 Program ID (mainnet/devnet)
+/// SW1TCH7qEPTdLsDHRgPuMQjbQxKdH2aBStViMFnt64f
+pub const SWITCHBOARD_PROGRAM_ID: [u8; 32] = [
+    0x06, 0x88, 0x51, 0xc6, 0x8c, 0x68, 0x32, 0xf0,
+    0x2f, 0xa5, 0x81, 0xb1, 0xbf, 0x49, 0x1b, 0x77,
+    0xca, 0x41, 0x77, 0x6b, 0xa2, 0xb9, 0x88, 0xb5,
+    0xa6, 0xfa, 0xba, 0x8e, 0xe3, 0xa2, 0xec, 0x90,
+];
+
+/// Most decimal places accepted on a SwitchboardDecimal (its mantissa is
+/// an i128, so anything finer is noise for a 1e6 price)
+pub const SWITCHBOARD_MAX_SCALE: u32 = 18;
+
+/// Switchboard oracle adapter
+pub struct SwitchboardAdapter {
+    /// Maximum standard deviation as percentage of the result (e.g., 2 = 2%)
+    /// Reject rounds whose oracles disagree by more than this
+    pub max_confidence_pct: u64,
+
+    /// Maximum round age in seconds (e.g., 60 = reject rounds opened over a minute ago)
+    pub max_age_secs: i64,
+}
+
+impl SwitchboardAdapter {
+    /// Create new Switchboard adapter with default parameters
+    pub fn new() -> Self {
+        Self {
+            max_confidence_pct: 2,  // 2% max std deviation
+            max_age_secs: 60,        // 60 seconds max age
+        }
+    }
+
+    /// Create Switchboard adapter with custom parameters
+    pub fn with_params(max_confidence_pct: u64, max_age_secs: i64) -> Self {
+        Self {
+            max_confidence_pct,
+            max_age_secs,
+        }
+    }
+
+    /// Scale a SwitchboardDecimal (mantissa / 10^scale) to 1e6 fixed scale
+    ///
+    /// Finer scales truncate toward zero; a result that doesn't fit in i64
+    /// at 1e6 is rejected.
+    fn scale_decimal(mantissa: i128, scale: u32) -> Result<i64, OracleError> {
+        const TARGET_SCALE: u32 = 6; // 1e6
+
+        if scale > SWITCHBOARD_MAX_SCALE {
+            return Err(OracleError::UnsupportedExponent);
+        }
+
+        let scaled = if scale >= TARGET_SCALE {
+            mantissa / 10_i128.pow(scale - TARGET_SCALE)
+        } else {
+            mantissa
+                .checked_mul(10_i128.pow(TARGET_SCALE - scale))
+                .ok_or(OracleError::InvalidFormat)?
+        };
+        i64::try_from(scaled).map_err(|_| OracleError::InvalidFormat)
+    }
+
+    /// Read the aggregator account, judging staleness at `now_ts` (0 = no clock)
+    pub fn read_price_at(&self, oracle_account: &AccountInfo, now_ts: i64) -> Result<OraclePrice, OracleError> {
+        // Validate account first
+        self.validate_account(oracle_account)?;
+
+        // Borrow raw account data
+        let data = oracle_account
+            .try_borrow_data()
+            .map_err(|_| OracleError::InvalidAccount)?;
+
+        self.parse_aggregator_data(&data, now_ts)
+    }
+
+    /// Parse and normalize a raw Switchboard V2 aggregator account
+    pub(crate) fn parse_aggregator_data(&self, data: &[u8], now_ts: i64) -> Result<OraclePrice, OracleError> {
+        // AggregatorAccountData (packed), fields read here:
+        // Offset  | Size | Field
+        // --------|------|-------
+        // 0       | 8    | Anchor discriminator
+        // 341     | 4    | latest_confirmed_round.num_success (u32)
+        // 358     | 8    | latest_confirmed_round.round_open_timestamp (i64)
+        // 366     | 16   | latest_confirmed_round.result.mantissa (i128)
+        // 382     | 4    | latest_confirmed_round.result.scale (u32)
+        // 386     | 16   | latest_confirmed_round.std_deviation.mantissa (i128)
+        // 402     | 4    | latest_confirmed_round.std_deviation.scale (u32)
+
+        if data.len() < SWITCHBOARD_AGGREGATOR_MIN_LEN || data[0..8] != SWITCHBOARD_AGGREGATOR_DISCRIMINATOR {
+            return Err(OracleError::InvalidFormat);
+        }
+
+        let read_u32 = |offset: usize| u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]]);
+        let read_decimal = |offset: usize| -> (i128, u32) {
+            let mut mantissa = [0u8; 16];
+            mantissa.copy_from_slice(&data[offset..offset + 16]);
+            (i128::from_le_bytes(mantissa), read_u32(offset + 16))
+        };
+
+        // A round no oracle answered has no result
+        if read_u32(NUM_SUCCESS_OFFSET) == 0 {
+            return Err(OracleError::PriceUnavailable);
+        }
+
+        let mut ts_bytes = [0u8; 8];
+        ts_bytes.copy_from_slice(&data[ROUND_OPEN_TIMESTAMP_OFFSET..ROUND_OPEN_TIMESTAMP_OFFSET + 8]);
+        let timestamp = i64::from_le_bytes(ts_bytes);
+
+        // Check staleness
+        if is_stale_at(timestamp, now_ts, self.max_age_secs) {
+            return Err(OracleError::StalePrice);
+        }
+
+        let (result_mantissa, result_scale) = read_decimal(RESULT_OFFSET);
+        let (std_mantissa, std_scale) = read_decimal(STD_DEVIATION_OFFSET);
+        let price = Self::scale_decimal(result_mantissa, result_scale)?;
+        let confidence = Self::scale_decimal(std_mantissa, std_scale)?;
+
+        // Validate confidence interval
+        let conf_abs = confidence.unsigned_abs() as u128;
+        let price_abs = price.unsigned_abs() as u128;
+
+        if let Some(confidence_pct) = (conf_abs * 100).checked_div(price_abs) {
+            if confidence_pct > self.max_confidence_pct as u128 {
+                return Err(OracleError::LowConfidence);
+            }
+        }
+
+        Ok(OraclePrice {
+            price,
+            confidence,
+            timestamp,
+            expo: -(result_scale as i32),
+        })
+    }
+
+    /// Get current Unix timestamp
+    /// In BPF, this would read from Clock sysvar
+    fn current_timestamp() -> i64 {
+        // TODO: In actual BPF program, read from Clock sysvar
+        // For now, return placeholder
+        0
+    }
+}
+
+impl OracleAdapter for SwitchboardAdapter {
+    fn read_price(&self, oracle_account: &AccountInfo) -> Result<OraclePrice, OracleError> {
+        self.read_price_at(oracle_account, Self::current_timestamp())
+    }
+
+    fn validate_account(&self, oracle_account: &AccountInfo) -> Result<(), OracleError> {
+        // Check account owner is Switchboard program
+        let owner = oracle_account.owner();
+        if owner != &SWITCHBOARD_PROGRAM_ID {
+            return Err(OracleError::InvalidAccount);
+        }
+
+        Ok(())
+    }
+
+    fn is_stale(&self, timestamp: i64, max_age_secs: i64) -> bool {
+        is_stale_at(timestamp, Self::current_timestamp(), max_age_secs)
+    }
+
+    fn provider_name(&self) -> &'static str {
+        "Switchboard"
+    }
+}
+
+impl Default for SwitchboardAdapter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000;
+
+    /// Minimal aggregator account: one successful response, round opened at `opened_at`
+    fn aggregator_account(mantissa: i128, scale: u32, std_mantissa: i128, opened_at: i64) -> [u8; SWITCHBOARD_AGGREGATOR_MIN_LEN] {
+        let mut data = [0u8; SWITCHBOARD_AGGREGATOR_MIN_LEN];
+        data[0..8].copy_from_slice(&SWITCHBOARD_AGGREGATOR_DISCRIMINATOR);
+        data[NUM_SUCCESS_OFFSET..NUM_SUCCESS_OFFSET + 4].copy_from_slice(&1u32.to_le_bytes());
+        data[ROUND_OPEN_TIMESTAMP_OFFSET..ROUND_OPEN_TIMESTAMP_OFFSET + 8].copy_from_slice(&opened_at.to_le_bytes());
+        data[RESULT_OFFSET..RESULT_OFFSET + 16].copy_from_slice(&mantissa.to_le_bytes());
+        data[RESULT_OFFSET + 16..RESULT_OFFSET + 20].copy_from_slice(&scale.to_le_bytes());
+        data[STD_DEVIATION_OFFSET..STD_DEVIATION_OFFSET + 16].copy_from_slice(&std_mantissa.to_le_bytes());
+        data[STD_DEVIATION_OFFSET + 16..STD_DEVIATION_OFFSET + 20].copy_from_slice(&scale.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn test_valid_round_normalizes_to_1e6() {
+        // SOL/USD $142.123456789 at 9 decimals, std deviation $0.05
+        let data = aggregator_account(142_123_456_789, 9, 50_000_000, NOW - 10);
+        let price = SwitchboardAdapter::new().parse_aggregator_data(&data, NOW).unwrap();
+        assert_eq!(price.price, 142_123_456);
+        assert_eq!(price.confidence, 50_000);
+        assert_eq!(price.timestamp, NOW - 10);
+        assert_eq!(price.expo, -9);
+
+        // Coarser than 1e6 scales up
+        let data = aggregator_account(14_212, 2, 0, NOW);
+        assert_eq!(SwitchboardAdapter::new().parse_aggregator_data(&data, NOW).map(|p| p.price), Ok(142_120_000));
+    }
+
+    #[test]
+    fn test_stale_round_rejected() {
+        let data = aggregator_account(142_000_000_000, 9, 0, NOW - 61);
+        let adapter = SwitchboardAdapter::new();
+        assert_eq!(adapter.parse_aggregator_data(&data, NOW).map(|p| p.price), Err(OracleError::StalePrice));
+
+        // Exactly max_age_secs old is still fresh
+        let data = aggregator_account(142_000_000_000, 9, 0, NOW - 60);
+        assert_eq!(adapter.parse_aggregator_data(&data, NOW).map(|p| p.price), Ok(142_000_000));
+    }
+
+    #[test]
+    fn test_unreliable_round_rejected() {
+        let adapter = SwitchboardAdapter::new();
+
+        // Oracles disagree by 5% of the result
+        let data = aggregator_account(100_000_000, 6, 5_000_000, NOW);
+        assert_eq!(adapter.parse_aggregator_data(&data, NOW).map(|p| p.price), Err(OracleError::LowConfidence));
+
+        // No oracle answered the round
+        let mut data = aggregator_account(100_000_000, 6, 0, NOW);
+        data[NUM_SUCCESS_OFFSET..NUM_SUCCESS_OFFSET + 4].copy_from_slice(&0u32.to_le_bytes());
+        assert_eq!(adapter.parse_aggregator_data(&data, NOW).map(|p| p.price), Err(OracleError::PriceUnavailable));
+    }
+
+    #[test]
+    fn test_malformed_account_rejected() {
+        let adapter = SwitchboardAdapter::new();
+        let data = aggregator_account(100_000_000, 6, 0, NOW);
+        assert_eq!(
+            adapter.parse_aggregator_data(&data[..SWITCHBOARD_AGGREGATOR_MIN_LEN - 1], NOW).map(|p| p.price),
+            Err(OracleError::InvalidFormat)
+        );
+
+        // Another Switchboard account type (e.g. a queue) rather than an aggregator
+        let mut other = data;
+        other[0] ^= 0xff;
+        assert_eq!(adapter.parse_aggregator_data(&other, NOW).map(|p| p.price), Err(OracleError::InvalidFormat));
+
+        let data = aggregator_account(100, SWITCHBOARD_MAX_SCALE + 1, 0, NOW);
+        assert_eq!(adapter.parse_aggregator_data(&data, NOW).map(|p| p.price), Err(OracleError::UnsupportedExponent));
+    }
+}
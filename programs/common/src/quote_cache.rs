@@ -45,7 +45,22 @@ impl QuoteCache {
     }
 
     /// Update cache from book state
-    pub fn update(&mut self, seqno: u32, bids: &[QuoteLevel], asks: &[QuoteLevel]) {
+    ///
+    /// Rejects, with CrossedBook and the cache untouched, levels whose best
+    /// bid is at or above the best ask: depth and post-only checks assume
+    /// the spread is positive.
+    pub fn update(
+        &mut self,
+        seqno: u32,
+        bids: &[QuoteLevel],
+        asks: &[QuoteLevel],
+    ) -> Result<(), crate::PercolatorError> {
+        if let (Some(bid), Some(ask)) = (best_level(bids), best_level(asks)) {
+            if bid.px >= ask.px {
+                return Err(crate::PercolatorError::CrossedBook);
+            }
+        }
+
         self.seqno_snapshot = seqno;
 
         // Copy up to 4 best levels
@@ -62,6 +77,15 @@ impl QuoteCache {
                 self.best_asks[i] = QuoteLevel::default();
             }
         }
+        Ok(())
+    }
+
+    /// Whether the cache reflects the book at `current_seqno`
+    ///
+    /// Every book change bumps the header seqno, so a cache written at any
+    /// earlier seqno may quote liquidity that is already gone.
+    pub fn is_fresh(&self, current_seqno: u32) -> bool {
+        self.seqno_snapshot == current_seqno
     }
 
     /// Get total available quantity across all bid levels
@@ -73,6 +97,47 @@ impl QuoteCache {
     pub fn total_ask_qty(&self) -> i64 {
         self.best_asks.iter().map(|l| l.avail_qty).sum()
     }
+
+    /// Total quantity a taker on `side` can fill at or better than `limit_px`
+    ///
+    /// Buys walk the asks priced at or below the limit, sells the bids at or
+    /// above it. Empty levels end the walk, as in the router's VWAP chooser.
+    pub fn available_liquidity(&self, side: crate::Side, limit_px: i64) -> i64 {
+        let levels = match side {
+            crate::Side::Buy => &self.best_asks,
+            crate::Side::Sell => &self.best_bids,
+        };
+
+        let mut total: i64 = 0;
+        for level in levels {
+            if level.px == 0 || level.avail_qty <= 0 {
+                break;
+            }
+            let within_limit = match side {
+                crate::Side::Buy => level.px <= limit_px,
+                crate::Side::Sell => level.px >= limit_px,
+            };
+            if !within_limit {
+                break;
+            }
+            total = total.saturating_add(level.avail_qty);
+        }
+        total
+    }
+
+    /// Whether an order on `side` at `limit_px` would take liquidity
+    ///
+    /// True when the best opposing level is at or through the limit, so the
+    /// order would match rather than rest.
+    pub fn would_cross(&self, side: crate::Side, limit_px: i64) -> bool {
+        self.available_liquidity(side, limit_px) > 0
+    }
+}
+
+/// Best level of one side, if it has liquidity (empty levels end the book,
+/// as in `available_liquidity`)
+fn best_level(levels: &[QuoteLevel]) -> Option<&QuoteLevel> {
+    levels.first().filter(|l| l.px != 0 && l.avail_qty > 0)
 }
 
 #[cfg(test)]
@@ -99,7 +164,7 @@ mod tests {
             QuoteLevel { px: 50_001_000_000, avail_qty: 1_500_000 },
         ];
 
-        cache.update(1, &bids, &asks);
+        cache.update(1, &bids, &asks).unwrap();
 
         assert_eq!(cache.seqno_snapshot, 1);
         assert_eq!(cache.best_bids[0].px, 50_000_000_000);
@@ -108,4 +173,108 @@ mod tests {
         assert_eq!(cache.total_bid_qty(), 3_000_000);
         assert_eq!(cache.total_ask_qty(), 1_500_000);
     }
+
+    #[test]
+    fn test_available_liquidity_aggregates_levels() {
+        let mut cache = QuoteCache::new();
+        let bids = [
+            QuoteLevel { px: 100_000_000, avail_qty: 1_000_000 },
+            QuoteLevel { px: 99_000_000, avail_qty: 2_000_000 },
+            QuoteLevel { px: 98_000_000, avail_qty: 3_000_000 },
+        ];
+        let asks = [
+            QuoteLevel { px: 101_000_000, avail_qty: 1_500_000 },
+            QuoteLevel { px: 102_000_000, avail_qty: 2_500_000 },
+        ];
+        cache.update(1, &bids, &asks).unwrap();
+
+        // Generous limits sum every level on the taken side
+        assert_eq!(cache.available_liquidity(crate::Side::Buy, 200_000_000), 4_000_000);
+        assert_eq!(cache.available_liquidity(crate::Side::Sell, 1), 6_000_000);
+    }
+
+    #[test]
+    fn test_available_liquidity_respects_limit() {
+        let mut cache = QuoteCache::new();
+        let bids = [
+            QuoteLevel { px: 100_000_000, avail_qty: 1_000_000 },
+            QuoteLevel { px: 99_000_000, avail_qty: 2_000_000 },
+        ];
+        let asks = [
+            QuoteLevel { px: 101_000_000, avail_qty: 1_500_000 },
+            QuoteLevel { px: 102_000_000, avail_qty: 2_500_000 },
+        ];
+        cache.update(1, &bids, &asks).unwrap();
+
+        // Levels worse than the limit are excluded; a level at the limit counts
+        assert_eq!(cache.available_liquidity(crate::Side::Buy, 101_000_000), 1_500_000);
+        assert_eq!(cache.available_liquidity(crate::Side::Sell, 99_500_000), 1_000_000);
+        assert_eq!(cache.available_liquidity(crate::Side::Buy, 100_000_000), 0);
+        assert_eq!(cache.available_liquidity(crate::Side::Sell, 101_000_000), 0);
+    }
+
+    #[test]
+    fn test_post_only_rests_inside_spread() {
+        let mut cache = QuoteCache::new();
+        let bids = [QuoteLevel { px: 100_000_000, avail_qty: 1_000_000 }];
+        let asks = [QuoteLevel { px: 101_000_000, avail_qty: 1_500_000 }];
+        cache.update(1, &bids, &asks).unwrap();
+
+        // Bids below the best ask and asks above the best bid rest
+        assert!(!cache.would_cross(crate::Side::Buy, 100_500_000));
+        assert!(!cache.would_cross(crate::Side::Sell, 100_500_000));
+
+        // An empty opposing side can't be crossed
+        cache.update(2, &bids, &[]).unwrap();
+        assert!(!cache.would_cross(crate::Side::Buy, i64::MAX));
+    }
+
+    #[test]
+    fn test_post_only_crossing_rejected() {
+        let mut cache = QuoteCache::new();
+        let bids = [QuoteLevel { px: 100_000_000, avail_qty: 1_000_000 }];
+        let asks = [QuoteLevel { px: 101_000_000, avail_qty: 1_500_000 }];
+        cache.update(1, &bids, &asks).unwrap();
+
+        // Touching the best opposing level crosses, as does going through it
+        assert!(cache.would_cross(crate::Side::Buy, 101_000_000));
+        assert!(cache.would_cross(crate::Side::Buy, 105_000_000));
+        assert!(cache.would_cross(crate::Side::Sell, 100_000_000));
+        assert!(cache.would_cross(crate::Side::Sell, 1));
+    }
+
+    #[test]
+    fn test_cache_staleness_follows_seqno() {
+        let mut cache = QuoteCache::new();
+        cache.update(5, &[QuoteLevel { px: 100_000_000, avail_qty: 1_000_000 }], &[]).unwrap();
+        assert!(cache.is_fresh(5));
+
+        // A later book change the cache wasn't rewritten for
+        assert!(!cache.is_fresh(6));
+
+        cache.update(6, &[], &[]).unwrap();
+        assert!(cache.is_fresh(6));
+    }
+
+    #[test]
+    fn test_crossed_update_rejected() {
+        let mut cache = QuoteCache::new();
+        let bids = [QuoteLevel { px: 100_000_000, avail_qty: 1_000_000 }];
+        let asks = [QuoteLevel { px: 101_000_000, avail_qty: 1_500_000 }];
+        cache.update(1, &bids, &asks).unwrap();
+
+        // Best bid above the best ask is refused and the old quotes stay
+        let crossed_bids = [QuoteLevel { px: 102_000_000, avail_qty: 1_000_000 }];
+        assert_eq!(cache.update(2, &crossed_bids, &asks), Err(crate::PercolatorError::CrossedBook));
+        assert_eq!(cache.seqno_snapshot, 1);
+        assert_eq!(cache.best_bids[0].px, 100_000_000);
+
+        // A locked book (bid == ask) has no spread either
+        let locked_asks = [QuoteLevel { px: 100_000_000, avail_qty: 1_500_000 }];
+        assert_eq!(cache.update(2, &bids, &locked_asks), Err(crate::PercolatorError::CrossedBook));
+
+        // Empty levels don't count as the best price on their side
+        let stale_asks = [QuoteLevel { px: 99_000_000, avail_qty: 0 }];
+        assert_eq!(cache.update(2, &bids, &stale_asks), Ok(()));
+    }
 }
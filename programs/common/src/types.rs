@@ -41,6 +41,162 @@ pub enum Side {
     Sell = 1,
 }
 
+impl Side {
+    /// Signed quantity for a positive `qty` on this side (+buy, -sell)
+    ///
+    /// The one signed-quantity convention shared by router and slab: orders
+    /// cross the CPI boundary as a positive magnitude plus a side, and fills,
+    /// receipts and exposure deltas are signed with this.
+    pub fn signed_qty(self, qty: i64) -> i64 {
+        match self {
+            Side::Buy => qty,
+            Side::Sell => -qty,
+        }
+    }
+}
+
+/// Contract quoting convention
+///
+/// Linear contracts settle PnL as qty * (exit - entry). Inverse contracts are
+/// sized in quote notional and settle in base: qty * (1/entry - 1/exit).
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContractKind {
+    #[default]
+    Linear = 0,
+    Inverse = 1,
+}
+
+impl ContractKind {
+    /// Decode from the stored byte (unknown values fall back to Linear)
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            1 => ContractKind::Inverse,
+            _ => ContractKind::Linear,
+        }
+    }
+}
+
+/// Fixed-point scale of an instrument's prices and quantities
+///
+/// Prices are `price / 10^price_decimals` quote units and quantities are
+/// `qty / 10^qty_decimals` contracts. The router's margin and notional math
+/// reads these instead of assuming 1e6 for everything.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InstrumentScale {
+    pub price_decimals: u8,
+    pub qty_decimals: u8,
+}
+
+impl InstrumentScale {
+    /// The 1e6 / 1e6 scale everything was originally tuned for
+    pub const DEFAULT: Self = Self { price_decimals: 6, qty_decimals: 6 };
+
+    /// Largest supported decimals on either axis
+    pub const MAX_DECIMALS: u8 = 9;
+
+    /// Decode stored decimals (0 = unset, i.e. a slab from before decimals
+    /// were configurable, which falls back to 6)
+    pub fn from_stored(price_decimals: u8, qty_decimals: u8) -> Self {
+        let or_default = |d: u8| if d == 0 { 6 } else { d.min(Self::MAX_DECIMALS) };
+        Self {
+            price_decimals: or_default(price_decimals),
+            qty_decimals: or_default(qty_decimals),
+        }
+    }
+
+    /// 10^price_decimals
+    pub fn price_unit(&self) -> u128 {
+        10u128.pow(self.price_decimals as u32)
+    }
+
+    /// 10^qty_decimals
+    pub fn qty_unit(&self) -> u128 {
+        10u128.pow(self.qty_decimals as u32)
+    }
+}
+
+impl Default for InstrumentScale {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Every fixed-point scale between an instrument's oracle, book and collateral
+///
+/// The single source of truth for converting an instrument's numbers into
+/// lamports: oracle prints are `px * 10^oracle_exponent` quote units, book
+/// prices `price / 10^price_decimals` quote units, quantities
+/// `qty / 10^qty_decimals` contracts, and collateral
+/// `amount / 10^collateral_decimals` of the collateral asset (9 for SOL
+/// lamports). Listing an asset with other decimals only changes these.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScaleConfig {
+    pub oracle_exponent: i8,
+    pub price_decimals: u8,
+    pub qty_decimals: u8,
+    pub collateral_decimals: u8,
+}
+
+impl ScaleConfig {
+    /// 1e6 oracle prints, 1e6 prices and quantities, lamport collateral
+    pub const DEFAULT: Self = Self {
+        oracle_exponent: -6,
+        price_decimals: InstrumentScale::DEFAULT.price_decimals,
+        qty_decimals: InstrumentScale::DEFAULT.qty_decimals,
+        collateral_decimals: 9,
+    };
+
+    /// Largest supported collateral decimals (e.g. 18-decimal bridged tokens)
+    pub const MAX_COLLATERAL_DECIMALS: u8 = 18;
+
+    /// Decode stored fields (0 = unset, i.e. a slab from before the field
+    /// existed, which falls back to the DEFAULT value)
+    pub fn from_stored(price_decimals: u8, qty_decimals: u8, oracle_exponent: i8, collateral_decimals: u8) -> Self {
+        let instrument = InstrumentScale::from_stored(price_decimals, qty_decimals);
+        Self {
+            oracle_exponent: if oracle_exponent == 0 { Self::DEFAULT.oracle_exponent } else { oracle_exponent },
+            price_decimals: instrument.price_decimals,
+            qty_decimals: instrument.qty_decimals,
+            collateral_decimals: if collateral_decimals == 0 {
+                Self::DEFAULT.collateral_decimals
+            } else {
+                collateral_decimals.min(Self::MAX_COLLATERAL_DECIMALS)
+            },
+        }
+    }
+
+    /// Price / quantity scale alone
+    pub fn instrument(&self) -> InstrumentScale {
+        InstrumentScale { price_decimals: self.price_decimals, qty_decimals: self.qty_decimals }
+    }
+
+    /// Rescale an oracle print to the instrument's price decimals
+    ///
+    /// Finer targets multiply (saturating), coarser ones truncate toward zero.
+    pub fn oracle_to_price(&self, oracle_px: i64) -> i64 {
+        let shift = self.price_decimals as i32 + self.oracle_exponent as i32;
+        if shift >= 0 {
+            oracle_px.saturating_mul(10i64.saturating_pow(shift as u32))
+        } else {
+            oracle_px / 10i64.saturating_pow((-shift) as u32)
+        }
+    }
+}
+
+impl From<InstrumentScale> for ScaleConfig {
+    /// An instrument margined in lamports and priced off 1e6 oracle prints
+    fn from(scale: InstrumentScale) -> Self {
+        Self { price_decimals: scale.price_decimals, qty_decimals: scale.qty_decimals, ..Self::DEFAULT }
+    }
+}
+
+impl Default for ScaleConfig {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
 /// Time in force
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -8,9 +8,10 @@ use pinocchio::{
     ProgramResult,
 };
 
-use crate::instructions::{RouterInstruction, process_deposit, process_withdraw, process_initialize_registry, process_initialize_portfolio, process_execute_cross_slab, process_liquidate_user, process_burn_lp_shares, process_cancel_lp_orders};
-use crate::state::{Vault, Portfolio, SlabRegistry};
-use percolator_common::{PercolatorError, validate_owner, validate_writable, borrow_account_data, borrow_account_data_mut, InstructionReader};
+use crate::instructions::{RouterInstruction, INSTRUCTION_VERSION, MIN_INSTRUCTION_VERSION, process_deposit, process_withdraw, process_request_withdraw, process_initialize_registry, process_initialize_portfolio, process_execute_cross_slab, process_liquidate_user, process_burn_lp_shares, process_cancel_lp_orders, process_update_twap, process_close_all, process_lp_deposit, create_lp_pool_pda, check_rent_floor, move_lamports, process_early_vest, process_set_pause, require_not_paused, validate_leverage, select_counterparty, process_simulate_execute_cross_slab, process_position_report, process_sweep_positions, process_quote_depth, process_set_liquidators, process_set_deposit_caps, process_set_insurance_params, process_set_trade_cooldown, process_portfolio_summary, process_initialize_registry_page, process_deregister_slab, process_propose_governance, process_accept_governance, process_protocol_stats, process_touch, process_set_delegate, process_close_portfolio, require_liquidator, check_oracles_per_slab, read_slab_lp_owner, process_liquidate_batch, MAX_CLOSE_ALL_POSITIONS, MAX_LIQUIDATION_BATCH, MAX_DLP_COUNTERPARTIES};
+use crate::state::{Vault, Portfolio, SlabRegistry, PositionDetails, LpPool, MAX_LIQUIDATORS};
+use crate::oracle::TwapBuffer;
+use percolator_common::{PercolatorError, Side, validate_owner, validate_writable, validate_signer, borrow_account_data, borrow_account_data_mut, InstructionReader};
 
 entrypoint!(process_instruction);
 
@@ -19,9 +20,9 @@ pub fn process_instruction(
     accounts: &[AccountInfo],
     instruction_data: &[u8],
 ) -> ProgramResult {
-    // Check minimum instruction data length
-    if instruction_data.is_empty() {
-        msg!("Error: Instruction data is empty");
+    // Check minimum instruction data length (discriminator + version)
+    if instruction_data.len() < 2 {
+        msg!("Error: Instruction data is missing discriminator or version");
         return Err(PercolatorError::InvalidInstruction.into());
     }
 
@@ -36,45 +37,170 @@ pub fn process_instruction(
         5 => RouterInstruction::LiquidateUser,
         6 => RouterInstruction::BurnLpShares,
         7 => RouterInstruction::CancelLpOrders,
+        8 => RouterInstruction::UpdateTwap,
+        9 => RouterInstruction::EarlyVest,
+        10 => RouterInstruction::SetPause,
+        11 => RouterInstruction::CloseAll,
+        12 => RouterInstruction::LpDeposit,
+        13 => RouterInstruction::PositionReport,
+        14 => RouterInstruction::SweepPositions,
+        15 => RouterInstruction::Touch,
+        16 => RouterInstruction::SetLiquidators,
+        17 => RouterInstruction::RequestWithdraw,
+        18 => RouterInstruction::SetDepositCaps,
+        19 => RouterInstruction::SetInsuranceParams,
+        20 => RouterInstruction::SetTradeCooldown,
+        21 => RouterInstruction::PortfolioSummary,
+        22 => RouterInstruction::InitializeRegistryPage,
+        23 => RouterInstruction::DeregisterSlab,
+        #[cfg(feature = "localnet")]
+        24 => RouterInstruction::SetCustomPrice,
+        25 => RouterInstruction::ProposeGovernance,
+        26 => RouterInstruction::AcceptGovernance,
+        27 => RouterInstruction::ProtocolStats,
+        28 => RouterInstruction::QuoteDepth,
+        29 => RouterInstruction::SetDelegate,
+        30 => RouterInstruction::ClosePortfolio,
         _ => {
             msg!("Error: Unknown instruction");
             return Err(PercolatorError::InvalidInstruction.into());
         }
     };
 
+    // Reject data layouts this build doesn't know how to parse
+    let version = instruction_data[1];
+    if !(MIN_INSTRUCTION_VERSION..=INSTRUCTION_VERSION).contains(&version) {
+        msg!("Error: Unsupported instruction version");
+        return Err(PercolatorError::UnsupportedVersion.into());
+    }
+    let data = &instruction_data[2..];
+
     // Dispatch to instruction handler (v0 minimal)
     match instruction {
         RouterInstruction::Initialize => {
             msg!("Instruction: Initialize");
-            process_initialize_inner(program_id, accounts, &instruction_data[1..])
+            process_initialize_inner(program_id, accounts, data)
         }
         RouterInstruction::InitializePortfolio => {
             msg!("Instruction: InitializePortfolio");
-            process_initialize_portfolio_inner(program_id, accounts, &instruction_data[1..])
+            process_initialize_portfolio_inner(program_id, accounts, data)
         }
         RouterInstruction::Deposit => {
             msg!("Instruction: Deposit");
-            process_deposit_inner(program_id, accounts, &instruction_data[1..])
+            process_deposit_inner(program_id, accounts, data)
         }
         RouterInstruction::Withdraw => {
             msg!("Instruction: Withdraw");
-            process_withdraw_inner(program_id, accounts, &instruction_data[1..])
+            process_withdraw_inner(program_id, accounts, data)
         }
         RouterInstruction::ExecuteCrossSlab => {
             msg!("Instruction: ExecuteCrossSlab");
-            process_execute_cross_slab_inner(program_id, accounts, &instruction_data[1..])
+            process_execute_cross_slab_inner(program_id, accounts, data)
         }
         RouterInstruction::LiquidateUser => {
             msg!("Instruction: LiquidateUser");
-            process_liquidate_user_inner(program_id, accounts, &instruction_data[1..])
+            process_liquidate_user_inner(program_id, accounts, data)
         }
         RouterInstruction::BurnLpShares => {
             msg!("Instruction: BurnLpShares");
-            process_burn_lp_shares_inner(program_id, accounts, &instruction_data[1..])
+            process_burn_lp_shares_inner(program_id, accounts, data)
         }
         RouterInstruction::CancelLpOrders => {
             msg!("Instruction: CancelLpOrders");
-            process_cancel_lp_orders_inner(program_id, accounts, &instruction_data[1..])
+            process_cancel_lp_orders_inner(program_id, accounts, data)
+        }
+        RouterInstruction::UpdateTwap => {
+            msg!("Instruction: UpdateTwap");
+            process_update_twap_inner(program_id, accounts, data)
+        }
+        RouterInstruction::EarlyVest => {
+            msg!("Instruction: EarlyVest");
+            process_early_vest_inner(program_id, accounts, data)
+        }
+        RouterInstruction::SetPause => {
+            msg!("Instruction: SetPause");
+            process_set_pause_inner(program_id, accounts, data)
+        }
+        RouterInstruction::CloseAll => {
+            msg!("Instruction: CloseAll");
+            process_close_all_inner(program_id, accounts, data)
+        }
+        RouterInstruction::LpDeposit => {
+            msg!("Instruction: LpDeposit");
+            process_lp_deposit_inner(program_id, accounts, data)
+        }
+        RouterInstruction::PositionReport => {
+            msg!("Instruction: PositionReport");
+            process_position_report_inner(program_id, accounts, data)
+        }
+        RouterInstruction::SweepPositions => {
+            msg!("Instruction: SweepPositions");
+            process_sweep_positions_inner(program_id, accounts, data)
+        }
+        RouterInstruction::QuoteDepth => {
+            msg!("Instruction: QuoteDepth");
+            process_quote_depth_inner(program_id, accounts, data)
+        }
+        RouterInstruction::SetLiquidators => {
+            msg!("Instruction: SetLiquidators");
+            process_set_liquidators_inner(program_id, accounts, data)
+        }
+        RouterInstruction::RequestWithdraw => {
+            msg!("Instruction: RequestWithdraw");
+            process_request_withdraw_inner(program_id, accounts, data)
+        }
+        RouterInstruction::SetDepositCaps => {
+            msg!("Instruction: SetDepositCaps");
+            process_set_deposit_caps_inner(program_id, accounts, data)
+        }
+        RouterInstruction::SetInsuranceParams => {
+            msg!("Instruction: SetInsuranceParams");
+            process_set_insurance_params_inner(program_id, accounts, data)
+        }
+        RouterInstruction::SetTradeCooldown => {
+            msg!("Instruction: SetTradeCooldown");
+            process_set_trade_cooldown_inner(program_id, accounts, data)
+        }
+        RouterInstruction::PortfolioSummary => {
+            msg!("Instruction: PortfolioSummary");
+            process_portfolio_summary_inner(program_id, accounts, data)
+        }
+        RouterInstruction::InitializeRegistryPage => {
+            msg!("Instruction: InitializeRegistryPage");
+            process_initialize_registry_page_inner(program_id, accounts, data)
+        }
+        RouterInstruction::DeregisterSlab => {
+            msg!("Instruction: DeregisterSlab");
+            process_deregister_slab_inner(program_id, accounts, data)
+        }
+        #[cfg(feature = "localnet")]
+        RouterInstruction::SetCustomPrice => {
+            msg!("Instruction: SetCustomPrice");
+            process_set_custom_price_inner(program_id, accounts, data)
+        }
+        RouterInstruction::ProposeGovernance => {
+            msg!("Instruction: ProposeGovernance");
+            process_propose_governance_inner(program_id, accounts, data)
+        }
+        RouterInstruction::AcceptGovernance => {
+            msg!("Instruction: AcceptGovernance");
+            process_accept_governance_inner(program_id, accounts, data)
+        }
+        RouterInstruction::ProtocolStats => {
+            msg!("Instruction: ProtocolStats");
+            process_protocol_stats_inner(program_id, accounts, data)
+        }
+        RouterInstruction::Touch => {
+            msg!("Instruction: Touch");
+            process_touch_inner(program_id, accounts, data)
+        }
+        RouterInstruction::SetDelegate => {
+            msg!("Instruction: SetDelegate");
+            process_set_delegate_inner(program_id, accounts, data)
+        }
+        RouterInstruction::ClosePortfolio => {
+            msg!("Instruction: ClosePortfolio");
+            process_close_portfolio_inner(program_id, accounts, data)
         }
     }
 }
@@ -122,23 +248,30 @@ fn process_initialize_inner(program_id: &Pubkey, accounts: &[AccountInfo], data:
 /// 0. `[writable]` Portfolio account (receives SOL)
 /// 1. `[signer, writable]` User account (sends SOL)
 /// 2. `[]` System program
+/// 3. `[writable]` Registry account (pause flags, deposit caps and TVL)
 ///
 /// Expected data layout (8 bytes):
 /// - amount: u64 (8 bytes, lamports)
 fn process_deposit_inner(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
-    if accounts.len() < 3 {
-        msg!("Error: Deposit instruction requires at least 3 accounts");
+    if accounts.len() < 4 {
+        msg!("Error: Deposit instruction requires at least 4 accounts");
         return Err(PercolatorError::InvalidInstruction.into());
     }
 
     let portfolio_account = &accounts[0];
     let user_account = &accounts[1];
     let system_program = &accounts[2];
+    let registry_account = &accounts[3];
 
     // Validate accounts
     validate_owner(portfolio_account, program_id)?;
     validate_writable(portfolio_account)?;
     validate_writable(user_account)?;
+    validate_owner(registry_account, program_id)?;
+    validate_writable(registry_account)?;
+
+    let registry = unsafe { borrow_account_data_mut::<SlabRegistry>(registry_account)? };
+    require_not_paused(registry, RouterInstruction::Deposit)?;
 
     // Borrow portfolio data
     let portfolio = unsafe { borrow_account_data_mut::<Portfolio>(portfolio_account)? };
@@ -148,7 +281,7 @@ fn process_deposit_inner(program_id: &Pubkey, accounts: &[AccountInfo], data: &[
     let amount = reader.read_u64()?;
 
     // Call the instruction handler
-    process_deposit(portfolio_account, portfolio, user_account, system_program, amount)?;
+    process_deposit(portfolio_account, portfolio, user_account, system_program, registry, amount)?;
 
     msg!("Deposit processed successfully");
     Ok(())
@@ -160,10 +293,14 @@ fn process_deposit_inner(program_id: &Pubkey, accounts: &[AccountInfo], data: &[
 /// 0. `[writable]` Portfolio account (sends SOL)
 /// 1. `[signer, writable]` User account (receives SOL)
 /// 2. `[]` System program
-/// 3. `[]` Registry account (for warmup state)
+/// 3. `[writable]` Registry account (warmup state and TVL)
+/// 4..4+N. `[]` Slab accounts of the open positions (N = num_positions)
+/// 4+N..4+2N. `[]` Oracle accounts, paired with the slabs by position
+/// 4+2N..4+3N. `[]` PositionDetails PDAs, one per open exposure (unrealized PnL for the margin check)
 ///
-/// Expected data layout (8 bytes):
+/// Expected data layout (8 bytes, 9 with open positions):
 /// - amount: u64 (8 bytes, lamports)
+/// - num_positions: u8 (optional, 0 if absent; a portfolio with open positions must pass them all)
 fn process_withdraw_inner(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
     if accounts.len() < 4 {
         msg!("Error: Withdraw instruction requires at least 4 accounts");
@@ -180,22 +317,94 @@ fn process_withdraw_inner(program_id: &Pubkey, accounts: &[AccountInfo], data: &
     validate_writable(portfolio_account)?;
     validate_writable(user_account)?;
     validate_owner(registry_account, program_id)?;
+    validate_writable(registry_account)?;
 
     // Borrow account data
     let portfolio = unsafe { borrow_account_data_mut::<Portfolio>(portfolio_account)? };
-    let registry = unsafe { borrow_account_data::<SlabRegistry>(registry_account)? };
+    let registry = unsafe { borrow_account_data_mut::<SlabRegistry>(registry_account)? };
 
     // Parse instruction data
     let mut reader = InstructionReader::new(data);
     let amount = reader.read_u64()?;
+    let num_positions = if reader.remaining() > 0 { reader.read_u8()? as usize } else { 0 };
+
+    if accounts.len() < 4 + num_positions * 3 {
+        msg!("Error: Insufficient position accounts for Withdraw");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+    let slab_accounts = &accounts[4..4 + num_positions];
+    let oracle_accounts = &accounts[4 + num_positions..4 + num_positions * 2];
+    let position_accounts = &accounts[4 + num_positions * 2..4 + num_positions * 3];
+
+    use pinocchio::sysvars::{clock::Clock, Sysvar};
+    let current_slot = Clock::get()
+        .map(|clock| clock.slot)
+        .map_err(|_| PercolatorError::InvalidInstruction)?;
 
     // Call the instruction handler
-    process_withdraw(portfolio_account, portfolio, user_account, system_program, registry, amount)?;
+    process_withdraw(
+        portfolio_account,
+        portfolio,
+        user_account,
+        system_program,
+        registry,
+        slab_accounts,
+        oracle_accounts,
+        position_accounts,
+        program_id,
+        amount,
+        current_slot,
+    )?;
 
     msg!("Withdraw processed successfully");
     Ok(())
 }
 
+/// Process request withdraw instruction
+///
+/// Expected accounts:
+/// 0. `[writable]` Portfolio account
+/// 1. `[signer]` User account
+/// 2. `[]` Registry account (queue threshold and delay)
+///
+/// Expected data layout (8 bytes):
+/// - amount: u64 (8 bytes, lamports)
+fn process_request_withdraw_inner(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    if accounts.len() < 3 {
+        msg!("Error: RequestWithdraw instruction requires at least 3 accounts");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+
+    let portfolio_account = &accounts[0];
+    let user_account = &accounts[1];
+    let registry_account = &accounts[2];
+
+    // Validate accounts
+    validate_owner(portfolio_account, program_id)?;
+    validate_writable(portfolio_account)?;
+    validate_signer(user_account)?;
+    validate_owner(registry_account, program_id)?;
+
+    // Borrow account data
+    let portfolio = unsafe { borrow_account_data_mut::<Portfolio>(portfolio_account)? };
+    let registry = unsafe { borrow_account_data::<SlabRegistry>(registry_account)? };
+
+    // Parse instruction data
+    let mut reader = InstructionReader::new(data);
+    let amount = reader.read_u64()?;
+
+    use pinocchio::sysvars::{clock::Clock, Sysvar};
+    let current_slot = Clock::get()
+        .map(|clock| clock.slot)
+        .map_err(|_| PercolatorError::InvalidInstruction)?;
+
+    // Call the instruction handler
+    process_request_withdraw(portfolio, user_account.key(), registry, amount, current_slot)?;
+
+    msg!("RequestWithdraw processed successfully");
+    Ok(())
+}
+
 /// Process initialize portfolio instruction
 ///
 /// Expected accounts:
@@ -249,7 +458,8 @@ fn process_initialize_portfolio_inner(program_id: &Pubkey, accounts: &[AccountIn
 ///
 /// Expected accounts:
 /// 0. `[writable]` User Portfolio account
-/// 1. `[signer]` User authority
+/// 1. `[signer]` User authority, or the portfolio's delegate (pays any new PositionDetails rent
+///    unless a rent payer is given)
 /// 2. `[writable]` DLP Portfolio account (counterparty - looked up from slab.lp_owner)
 /// 3. `[writable]` Registry account
 /// 4. `[]` Router authority PDA
@@ -257,19 +467,40 @@ fn process_initialize_portfolio_inner(program_id: &Pubkey, accounts: &[AccountIn
 /// 6. `[]` Slab program (for CPI)
 /// 7..7+N. `[writable]` Slab accounts (N = num_splits)
 /// 7+N..7+2N. `[writable]` Receipt PDAs (N = num_splits)
-/// 7+2N..7+3N. `[]` Oracle accounts (N = num_splits)
+/// 7+2N..7+2N+NK. `[]` Oracle accounts, K per slab grouped by slab (K = oracles_per_slab)
+/// 7+2N+NK..7+3N+NK. `[writable]` PositionDetails PDAs (N = num_splits)
+/// 7+3N+NK. `[writable, signer]` Rent payer, only when sponsored_rent is set; funds new
+///    PositionDetails rent in place of account 1 (the positions still belong to the portfolio)
+/// Then `[]` PositionDetails PDAs of the portfolio's other open positions (extra_positions of
+///    them); the margin check fails with MissingPositionDetails unless every active exposure's
+///    PDA is among these and the per-split PDAs
+/// Then `[writable]` Optional extra DLP Portfolio accounts (up to 3);
+///    the counterparty is chosen by inventory/capital among these and account 2,
+///    considering only portfolios owned by the slab's lp_owner
 ///
 /// Instruction data layout:
 /// - num_splits: u8 (1 byte)
 /// - order_type: u8 (0 = market, 1 = limit)
 /// - leverage: u8 (1-10x leverage)
+/// - max_slippage_bps: u16 (market order slippage tolerance, 0 = default, clamped to registry ceiling)
+/// - oracles_per_slab: u8 (1, 3, or 5 feeds; median price is used)
 /// - For each split (17 bytes):
 ///   - side: u8 (0 = buy, 1 = sell)
 ///   - qty: i64 (quantity in 1e6 scale)
-///   - limit_px: i64 (limit price in 1e6 scale)
+///   - limit_px: i64 (worst acceptable price in 1e6 scale)
+/// - simulate: u8 (optional; 1 = dry run: log the projected result, commit nothing)
+/// - reduce_only: u8 (optional, follows simulate; 1 = only shrink existing positions)
+/// - allow_partial_batch: u8 (optional, follows reduce_only; 1 = splits that fill nothing
+///   are skipped instead of reverting the batch)
+/// - sponsored_rent: u8 (optional, follows allow_partial_batch; 1 = a rent payer account
+///   follows the PositionDetails PDAs)
+/// - extra_positions: u8 (optional, follows sponsored_rent; number of trailing PositionDetails
+///   PDAs for open positions the order doesn't trade)
 ///
-/// Total size: 3 + (17 * num_splits) bytes
+/// Total size: 6 + (17 * num_splits) bytes, plus 1 per optional trailing byte
 /// Maximum splits: 8 (to avoid stack overflow, v0.5: only 1 slab supported)
+///
+/// Return data: ExecutionResult (version, filled_qty, avg_price, realized_pnl, equity; 49 bytes)
 fn process_execute_cross_slab_inner(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
     if accounts.len() < 7 {
         msg!("Error: ExecuteCrossSlab requires at least 7 accounts");
@@ -294,10 +525,11 @@ fn process_execute_cross_slab_inner(program_id: &Pubkey, accounts: &[AccountInfo
 
     // Borrow account data mutably
     let user_portfolio = unsafe { borrow_account_data_mut::<Portfolio>(user_portfolio_account)? };
-    let dlp_portfolio = unsafe { borrow_account_data_mut::<Portfolio>(dlp_portfolio_account)? };
     let registry = unsafe { borrow_account_data_mut::<SlabRegistry>(registry_account)? };
+    require_not_paused(registry, RouterInstruction::ExecuteCrossSlab)?;
 
-    // Parse instruction data: num_splits (u8) + order_type (u8) + splits (17 bytes each)
+    // Parse instruction data: num_splits (u8) + order_type (u8) + leverage (u8) + max_slippage_bps (u16)
+    // + oracles_per_slab (u8) + splits (17 bytes each)
     // Layout per split: side (u8) + qty (i64) + limit_px (i64)
     if data.is_empty() {
         msg!("Error: Instruction data is empty");
@@ -308,35 +540,36 @@ fn process_execute_cross_slab_inner(program_id: &Pubkey, accounts: &[AccountInfo
     let num_splits = reader.read_u8()? as usize;
     let order_type = reader.read_u8()?;
     let leverage = reader.read_u8()?;
+    let max_slippage_bps = reader.read_u16()?;
+    let oracles_per_slab = reader.read_u8()? as usize;
 
     if num_splits == 0 {
         msg!("Error: num_splits must be > 0");
         return Err(PercolatorError::InvalidInstruction.into());
     }
 
+    // v0 Limitation: Only single slab execution (no cross-slab routing)
+    // Cross-slab routing requires order book model for proper PnL settlement
+    if num_splits != 1 {
+        msg!("Error: v0 only supports single slab execution");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+
     if order_type > 1 {
         msg!("Error: Invalid order_type");
         return Err(PercolatorError::InvalidOrderType.into());
     }
 
-    if leverage < 1 || leverage > 10 {
-        msg!("Error: Invalid leverage (must be 1-10)");
-        return Err(PercolatorError::InvalidInstruction.into());
-    }
+    check_oracles_per_slab(oracles_per_slab)?;
 
-    // Log leverage value (msg! doesn't support format args in pinocchio)
-    if leverage == 1 {
-        msg!("DEBUG: Leverage = 1x");
-    } else if leverage == 5 {
-        msg!("DEBUG: Leverage = 5x");
-    } else if leverage == 10 {
-        msg!("DEBUG: Leverage = 10x");
-    } else {
-        msg!("DEBUG: Leverage = other");
-    }
+    validate_leverage(leverage)?;
+
+    log_debug!("Leverage", leverage as u64, 0, 0, 0, 0);
 
-    // Verify we have enough accounts: 7 base + num_splits slabs + num_splits receipts + num_splits oracles + num_splits position_details
-    let required_accounts = 7 + (num_splits * 4);
+    // Verify we have enough accounts: 7 base + num_splits slabs + num_splits receipts
+    // + num_splits * oracles_per_slab oracles + num_splits position_details
+    let oracle_count = num_splits * oracles_per_slab;
+    let required_accounts = 7 + (num_splits * 3) + oracle_count;
     if accounts.len() < required_accounts {
         msg!("Error: Insufficient accounts for ExecuteCrossSlab");
         return Err(PercolatorError::InvalidInstruction.into());
@@ -345,8 +578,8 @@ fn process_execute_cross_slab_inner(program_id: &Pubkey, accounts: &[AccountInfo
     // Split accounts into slabs, receipts, oracles, and position details
     let slab_accounts = &accounts[7..7 + num_splits];
     let receipt_accounts = &accounts[7 + num_splits..7 + num_splits * 2];
-    let oracle_accounts = &accounts[7 + num_splits * 2..7 + num_splits * 3];
-    let position_details_accounts = &accounts[7 + num_splits * 3..7 + num_splits * 4];
+    let oracle_accounts = &accounts[7 + num_splits * 2..7 + num_splits * 2 + oracle_count];
+    let position_details_accounts = &accounts[7 + num_splits * 2 + oracle_count..7 + num_splits * 3 + oracle_count];
 
     // Parse splits from instruction data (on stack, small)
     // Use a fixed-size buffer to avoid heap allocation
@@ -388,11 +621,111 @@ fn process_execute_cross_slab_inner(program_id: &Pubkey, accounts: &[AccountInfo
 
     let splits = &splits_buffer[..num_splits];
 
+    // Dry run: project fills and margin read-only, then return before any CPI or transfer
+    let simulate = reader.remaining() > 0 && reader.read_u8()? != 0;
+    let reduce_only = reader.remaining() > 0 && reader.read_u8()? != 0;
+    let allow_partial_batch = reader.remaining() > 0 && reader.read_u8()? != 0;
+    let sponsored_rent = reader.remaining() > 0 && reader.read_u8()? != 0;
+    let extra_positions = if reader.remaining() > 0 { reader.read_u8()? as usize } else { 0 };
+
+    // A sponsor can fund position rent in place of the signing user
+    let (rent_payer, trailing_accounts) = if sponsored_rent {
+        match accounts[required_accounts..].split_first() {
+            Some((payer, rest)) => (payer, rest),
+            None => {
+                msg!("Error: sponsored_rent set but no rent payer account");
+                return Err(PercolatorError::InvalidInstruction.into());
+            }
+        }
+    } else {
+        (user_account, &accounts[required_accounts..])
+    };
+
+    // PositionDetails of untraded positions, needed only for the margin check
+    if trailing_accounts.len() < extra_positions {
+        msg!("Error: Insufficient extra PositionDetails accounts");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+    let (extra_position_details, extra_dlps) = trailing_accounts.split_at(extra_positions);
+
+    if simulate {
+        process_simulate_execute_cross_slab(
+            user_portfolio_account,
+            user_portfolio,
+            user_account,
+            registry,
+            slab_accounts,
+            oracle_accounts,
+            position_details_accounts,
+            extra_position_details,
+            splits,
+            order_type,
+            oracles_per_slab,
+            leverage,
+            max_slippage_bps,
+            reduce_only,
+            program_id,
+        )?;
+        return Ok(());
+    }
+
+    // Throttle rapid-fire trading; liquidations call the handler directly and bypass this
+    use pinocchio::sysvars::{clock::Clock, Sysvar};
+    let current_slot = Clock::get()
+        .map(|clock| clock.slot)
+        .map_err(|_| PercolatorError::InvalidInstruction)?;
+    user_portfolio.record_trade(current_slot, registry.trade_cooldown_slots).map_err(|e| {
+        msg!("Error: Trade throttled - cooldown has not elapsed");
+        e
+    })?;
+
+    // Choose the counterparty among account 2 and any trailing DLP portfolios
+    if extra_dlps.len() >= MAX_DLP_COUNTERPARTIES {
+        msg!("Error: Too many DLP counterparties");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+
+    let mut dlp_accounts = [dlp_portfolio_account; MAX_DLP_COUNTERPARTIES];
+    for (i, extra) in extra_dlps.iter().enumerate() {
+        validate_owner(extra, program_id)?;
+        validate_writable(extra)?;
+        dlp_accounts[i + 1] = extra;
+    }
+    let dlp_accounts = &dlp_accounts[..1 + extra_dlps.len()];
+
+    // Only portfolios of the slab's lp_owner may take the other side
+    let lp_owner = read_slab_lp_owner(&slab_accounts[0])?;
+    let dlp_idx = {
+        let mut candidates = [(&*user_portfolio, 0u64); MAX_DLP_COUNTERPARTIES];
+        let mut candidate_accounts = [0usize; MAX_DLP_COUNTERPARTIES];
+        let mut count = 0;
+        for (i, account) in dlp_accounts.iter().enumerate() {
+            let portfolio = unsafe { borrow_account_data::<Portfolio>(account)? };
+            if portfolio.user != lp_owner {
+                continue;
+            }
+            candidates[count] = (portfolio, account.lamports());
+            candidate_accounts[count] = i;
+            count += 1;
+        }
+        if count == 0 {
+            msg!("Error: No DLP portfolio belongs to the slab's lp_owner");
+            return Err(PercolatorError::InvalidCounterparty.into());
+        }
+        let slab_idx = registry.find_slab(slab_accounts[0].key()).map(|(idx, _)| idx);
+        let chosen = select_counterparty(&candidates[..count], slab_idx, &splits[0])
+            .ok_or(PercolatorError::InvalidAccount)?;
+        candidate_accounts[chosen]
+    };
+    let dlp_portfolio_account = dlp_accounts[dlp_idx];
+    let dlp_portfolio = unsafe { borrow_account_data_mut::<Portfolio>(dlp_portfolio_account)? };
+
     // Call the instruction handler (v0.5 with PnL settlement)
     process_execute_cross_slab(
         user_portfolio_account,
         user_portfolio,
         user_account,
+        rent_payer,
         dlp_portfolio_account,
         dlp_portfolio,
         registry,
@@ -403,9 +736,16 @@ fn process_execute_cross_slab_inner(program_id: &Pubkey, accounts: &[AccountInfo
         receipt_accounts,
         oracle_accounts,
         position_details_accounts,
+        extra_position_details,
         splits,
         order_type,
+        oracles_per_slab,
         leverage,
+        max_slippage_bps,
+        reduce_only,
+        allow_partial_batch,
+        true, // Orders respect each slab's trading hours
+        false, // Orders pay the taker fee
         program_id,
     )?;
 
@@ -416,40 +756,50 @@ fn process_execute_cross_slab_inner(program_id: &Pubkey, accounts: &[AccountInfo
 /// Process liquidate user instruction
 ///
 /// Expected accounts:
-/// 0. `[writable]` Portfolio account (to be liquidated)
-/// 1. `[]` Registry account
-/// 2. `[writable]` Vault account
-/// 3. `[]` Router authority PDA
-/// 4. `[]` System program
-/// 5. `[]` Slab program (for CPI)
-/// 6..6+N. `[]` Oracle accounts (N = num_oracles)
-/// 6+N..6+N+M. `[writable]` Slab accounts (M = num_slabs)
-/// 6+N+M..6+N+2M. `[writable]` Receipt PDAs (M = num_slabs)
+/// 0. `[writable]` Portfolio account (first to be liquidated)
+/// 1. `[writable]` DLP portfolio account (counterparty)
+/// 2. `[writable]` Registry account
+/// 3. `[writable]` Vault account
+/// 4. `[]` Router authority PDA
+/// 5. `[]` System program
+/// 6. `[]` Slab program (for CPI)
+/// 7. `[writable, signer]` Liquidator (must be whitelisted when liquidation is permissioned;
+///    signs the closing fills and funds any PositionDetails rent they need)
+/// 8..8+N. `[]` Oracle accounts (N = num_oracles, paired with slabs by position)
+/// 8+N..8+N+M. `[writable]` Slab accounts (M = num_slabs)
+/// 8+N+M..8+N+2M. `[writable]` Receipt PDAs (M = num_slabs)
+/// 8+N+2M... `[writable]` PositionDetails PDAs of portfolio 0, one per open exposure (unrealized
+///    PnL at mark, then updated or closed by the fills)
+/// Then, for each further portfolio in a batch:
+/// - `[writable]` Portfolio account
+/// - `[writable]` Its PositionDetails PDAs
 ///
 /// Instruction data layout:
 /// - num_oracles: u8 (1 byte)
 /// - num_slabs: u8 (1 byte)
-/// - is_preliq: u8 (1 byte, 0 = auto, 1 = force pre-liq)
+/// - is_preliq: u8 (1 byte, 0 = hard band, 1 = tighter pre-liq band)
 /// - current_ts: u64 (8 bytes, Unix timestamp)
+/// - num_portfolios: u8 (1 byte, optional batch, up to 8)
+/// - position_counts: [u8; num_portfolios] (PositionDetails accounts per portfolio)
 ///
-/// Total size: 11 bytes
+/// Total size: 11 bytes for a single portfolio (all trailing accounts are its
+/// PositionDetails), or 12 + num_portfolios bytes for a batch
 fn process_liquidate_user_inner(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
-    if accounts.len() < 7 {
-        msg!("Error: LiquidateUser requires at least 7 accounts");
+    if accounts.len() < 8 {
+        msg!("Error: LiquidateUser requires at least 8 accounts");
         return Err(PercolatorError::InvalidInstruction.into());
     }
 
-    let portfolio_account = &accounts[0];
     let dlp_portfolio_account = &accounts[1];
     let registry_account = &accounts[2];
     let vault_account = &accounts[3];
     let router_authority = &accounts[4];
     let system_program = &accounts[5];
     let slab_program = &accounts[6];
+    let liquidator_account = &accounts[7];
 
     // Validate accounts
-    validate_owner(portfolio_account, program_id)?;
-    validate_writable(portfolio_account)?;
+    validate_signer(liquidator_account)?;
     validate_owner(dlp_portfolio_account, program_id)?;
     validate_writable(dlp_portfolio_account)?;
     validate_owner(registry_account, program_id)?;
@@ -458,7 +808,6 @@ fn process_liquidate_user_inner(program_id: &Pubkey, accounts: &[AccountInfo], d
     validate_writable(vault_account)?;
 
     // Borrow account data mutably
-    let portfolio = unsafe { borrow_account_data_mut::<Portfolio>(portfolio_account)? };
     let dlp_portfolio = unsafe { borrow_account_data_mut::<Portfolio>(dlp_portfolio_account)? };
     let registry = unsafe { borrow_account_data_mut::<SlabRegistry>(registry_account)? };
     let vault = unsafe { borrow_account_data_mut::<Vault>(vault_account)? };
@@ -475,35 +824,84 @@ fn process_liquidate_user_inner(program_id: &Pubkey, accounts: &[AccountInfo], d
     let is_preliq = reader.read_u8()? != 0;
     let current_ts = reader.read_u64()?;
 
+    // Optional batch: portfolio count, then each portfolio's PositionDetails count
+    let batched = data.len() > 11;
+    let num_portfolios = if batched { reader.read_u8()? as usize } else { 1 };
+    if num_portfolios == 0 || num_portfolios > MAX_LIQUIDATION_BATCH {
+        msg!("Error: LiquidateUser takes 1 to 8 portfolios");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+    let mut position_counts = [0usize; MAX_LIQUIDATION_BATCH];
+    if batched {
+        for count in position_counts.iter_mut().take(num_portfolios) {
+            *count = reader.read_u8()? as usize;
+        }
+    }
+
     // Verify we have enough accounts
-    let required_accounts = 7 + num_oracles + num_slabs * 2;
+    let required_accounts = 8 + num_oracles + num_slabs * 2;
     if accounts.len() < required_accounts {
         msg!("Error: Insufficient accounts for LiquidateUser");
         return Err(PercolatorError::InvalidInstruction.into());
     }
 
+    // Permissioned deployments only accept whitelisted keepers
+    require_liquidator(registry, liquidator_account.key())?;
+
     // Split accounts
-    let oracle_accounts = &accounts[7..7 + num_oracles];
-    let slab_accounts = &accounts[7 + num_oracles..7 + num_oracles + num_slabs];
-    let receipt_accounts = &accounts[7 + num_oracles + num_slabs..7 + num_oracles + num_slabs * 2];
+    let oracle_accounts = &accounts[8..8 + num_oracles];
+    let slab_accounts = &accounts[8 + num_oracles..8 + num_oracles + num_slabs];
+    let receipt_accounts = &accounts[8 + num_oracles + num_slabs..8 + num_oracles + num_slabs * 2];
+
+    // Locate each portfolio and its PositionDetails after the receipts
+    let mut portfolio_indices = [0usize; MAX_LIQUIDATION_BATCH];
+    let mut position_ranges = [(0usize, 0usize); MAX_LIQUIDATION_BATCH];
+    let mut portfolio_keys = [Pubkey::default(); MAX_LIQUIDATION_BATCH];
+    let mut next = required_accounts;
+    for i in 0..num_portfolios {
+        if i > 0 {
+            portfolio_indices[i] = next;
+            next += 1;
+        }
+        let count = if batched { position_counts[i] } else { accounts.len() - next };
+        position_ranges[i] = (next, next + count);
+        next += count;
+        if next > accounts.len() {
+            msg!("Error: Insufficient accounts for LiquidateUser batch");
+            return Err(PercolatorError::InvalidInstruction.into());
+        }
 
-    // Call the instruction handler
-    process_liquidate_user(
-        portfolio_account,
-        portfolio,
-        dlp_portfolio_account,
-        dlp_portfolio,
-        registry,
-        vault,
-        router_authority,
-        system_program,
-        slab_program,
-        oracle_accounts,
-        slab_accounts,
-        receipt_accounts,
-        is_preliq,
-        current_ts,
-    )?;
+        let portfolio_account = &accounts[portfolio_indices[i]];
+        validate_owner(portfolio_account, program_id)?;
+        validate_writable(portfolio_account)?;
+        portfolio_keys[i] = *portfolio_account.key();
+    }
+
+    // Call the instruction handler for each portfolio, skipping healthy ones
+    process_liquidate_batch(&portfolio_keys[..num_portfolios], |i| {
+        let portfolio_account = &accounts[portfolio_indices[i]];
+        let portfolio = unsafe { borrow_account_data_mut::<Portfolio>(portfolio_account)? };
+        let (start, end) = position_ranges[i];
+        process_liquidate_user(
+            portfolio_account,
+            portfolio,
+            dlp_portfolio_account,
+            dlp_portfolio,
+            registry,
+            vault,
+            router_authority,
+            system_program,
+            slab_program,
+            liquidator_account,
+            oracle_accounts,
+            slab_accounts,
+            receipt_accounts,
+            &accounts[start..end],
+            program_id,
+            is_preliq,
+            current_ts,
+        )
+    })?;
 
     msg!("LiquidateUser processed successfully");
     Ok(())
@@ -514,33 +912,49 @@ fn process_liquidate_user_inner(program_id: &Pubkey, accounts: &[AccountInfo], d
 /// Expected accounts:
 /// 0. `[writable]` Portfolio account
 /// 1. `[signer]` User authority
+/// 2. `[]` Registry account (for pause flags)
+/// 3. `[writable]` LP pool PDA for the market (pays the redemption)
 ///
 /// Instruction data layout:
 /// - market_id: Pubkey (32 bytes)
 /// - shares_to_burn: u64 (8 bytes)
-/// - current_share_price: i64 (8 bytes)
-/// - current_ts: u64 (8 bytes)
-/// - max_staleness_seconds: u64 (8 bytes)
 ///
-/// Total size: 64 bytes
+/// Total size: 40 bytes
+///
+/// The share price is read from the pool at the on-chain clock, so there is no
+/// caller timestamp or staleness bound to trust.
 fn process_burn_lp_shares_inner(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
-    if accounts.len() < 2 {
-        msg!("Error: BurnLpShares requires at least 2 accounts");
+    if accounts.len() < 4 {
+        msg!("Error: BurnLpShares requires at least 4 accounts");
         return Err(PercolatorError::InvalidInstruction.into());
     }
 
     let portfolio_account = &accounts[0];
-    let _user_account = &accounts[1];
+    let user_account = &accounts[1];
+    let registry_account = &accounts[2];
+    let pool_account = &accounts[3];
 
     // Validate accounts
     validate_owner(portfolio_account, program_id)?;
     validate_writable(portfolio_account)?;
+    validate_signer(user_account)?;
+    validate_owner(registry_account, program_id)?;
+    validate_owner(pool_account, program_id)?;
+    validate_writable(pool_account)?;
 
     // Borrow account data mutably
     let portfolio = unsafe { borrow_account_data_mut::<Portfolio>(portfolio_account)? };
+    let registry = unsafe { borrow_account_data::<SlabRegistry>(registry_account)? };
+    require_not_paused(registry, RouterInstruction::BurnLpShares)?;
+
+    // Only the portfolio owner can redeem its shares
+    if &portfolio.user != user_account.key() {
+        msg!("Error: Portfolio does not belong to user");
+        return Err(PercolatorError::Unauthorized.into());
+    }
 
     // Parse instruction data
-    if data.len() < 64 {
+    if data.len() < 40 {
         msg!("Error: Instruction data too short");
         return Err(PercolatorError::InvalidInstruction.into());
     }
@@ -549,56 +963,83 @@ fn process_burn_lp_shares_inner(program_id: &Pubkey, accounts: &[AccountInfo], d
     let market_id_bytes = reader.read_bytes::<32>()?;
     let market_id = Pubkey::from(market_id_bytes);
     let shares_to_burn = reader.read_u64()?;
-    let current_share_price = reader.read_i64()?;
-    let current_ts = reader.read_u64()?;
-    let max_staleness_seconds = reader.read_u64()?;
+
+    // Shares are only redeemed from the market's own pool
+    let pool = unsafe { borrow_account_data_mut::<LpPool>(pool_account)? };
+    let (expected_pool, _) = crate::pda::derive_lp_pool_pda(&market_id, pool.bump, program_id);
+    if pool_account.key() != &expected_pool {
+        msg!("Error: Invalid LP pool PDA");
+        return Err(PercolatorError::InvalidAccount.into());
+    }
+
+    // The redemption is stamped with the on-chain clock
+    use pinocchio::sysvars::{clock::Clock, Sysvar};
+    let clock_ts = Clock::get()
+        .map(|clock| clock.unix_timestamp.max(0) as u64)
+        .map_err(|_| PercolatorError::StalePrice)?;
 
     // Call the instruction handler
-    process_burn_lp_shares(
+    let redemption = process_burn_lp_shares(
         portfolio,
-        market_id,
+        pool,
         shares_to_burn,
-        current_share_price,
-        current_ts,
-        max_staleness_seconds,
+        clock_ts,
     )?;
 
+    // The redemption comes back out of the pool as lamports
+    move_lamports(pool_account, portfolio_account, redemption)?;
+
     msg!("BurnLpShares processed successfully");
     Ok(())
 }
 
-/// Process cancel LP orders instruction
+/// Process LP deposit instruction
 ///
 /// Expected accounts:
 /// 0. `[writable]` Portfolio account
-/// 1. `[signer]` User authority
+/// 1. `[writable, signer]` User authority (portfolio owner; pays the LP pool's
+///    rent on the market's first deposit)
+/// 2. `[]` Registry account (pause flags, market registration)
+/// 3. `[writable]` LP pool PDA for the market (created on first deposit)
+/// 4. `[]` System program
 ///
 /// Instruction data layout:
 /// - market_id: Pubkey (32 bytes)
-/// - order_count: u8 (1 byte)
-/// - order_ids: [u64; order_count] (8 * order_count bytes)
-/// - freed_quote: u128 (16 bytes)
-/// - freed_base: u128 (16 bytes)
+/// - amount: u64 (8 bytes)
 ///
-/// Total size: 65 + (8 * order_count) bytes
-fn process_cancel_lp_orders_inner(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
-    if accounts.len() < 2 {
-        msg!("Error: CancelLpOrders requires at least 2 accounts");
+/// Total size: 40 bytes
+fn process_lp_deposit_inner(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    if accounts.len() < 5 {
+        msg!("Error: LpDeposit requires at least 5 accounts");
         return Err(PercolatorError::InvalidInstruction.into());
     }
 
     let portfolio_account = &accounts[0];
-    let _user_account = &accounts[1];
+    let user_account = &accounts[1];
+    let registry_account = &accounts[2];
+    let pool_account = &accounts[3];
+    let system_program = &accounts[4];
 
     // Validate accounts
     validate_owner(portfolio_account, program_id)?;
     validate_writable(portfolio_account)?;
+    validate_signer(user_account)?;
+    validate_owner(registry_account, program_id)?;
+    validate_writable(pool_account)?;
 
     // Borrow account data mutably
     let portfolio = unsafe { borrow_account_data_mut::<Portfolio>(portfolio_account)? };
+    let registry = unsafe { borrow_account_data::<SlabRegistry>(registry_account)? };
+    require_not_paused(registry, RouterInstruction::LpDeposit)?;
+
+    // Deposits spend equity, so only the owner may make them
+    if &portfolio.user != user_account.key() {
+        msg!("Error: Portfolio does not belong to user");
+        return Err(PercolatorError::Unauthorized.into());
+    }
 
     // Parse instruction data
-    if data.len() < 65 {
+    if data.len() < 40 {
         msg!("Error: Instruction data too short");
         return Err(PercolatorError::InvalidInstruction.into());
     }
@@ -606,34 +1047,1038 @@ fn process_cancel_lp_orders_inner(program_id: &Pubkey, accounts: &[AccountInfo],
     let mut reader = InstructionReader::new(data);
     let market_id_bytes = reader.read_bytes::<32>()?;
     let market_id = Pubkey::from(market_id_bytes);
-    let order_count = reader.read_u8()? as usize;
+    let amount = reader.read_u64()?;
 
-    // Read order IDs (up to 16 max for stack safety)
-    const MAX_ORDERS: usize = 16;
-    if order_count > MAX_ORDERS {
-        msg!("Error: order_count exceeds maximum");
+    if registry.find_slab(&market_id).is_none() {
+        msg!("Error: Market is not registered");
+        return Err(PercolatorError::SlabNotRegistered.into());
+    }
+
+    // The pool PDA is the only account shares of this market can be priced
+    // from, so its address is checked before it is created or read
+    use crate::pda::{derive_lp_pool_pda, NO_CACHED_BUMP};
+    if pool_account.owner() != program_id {
+        let (expected_pool, bump) = derive_lp_pool_pda(&market_id, NO_CACHED_BUMP, program_id);
+        if pool_account.key() != &expected_pool {
+            msg!("Error: Invalid LP pool PDA");
+            return Err(PercolatorError::InvalidAccount.into());
+        }
+        create_lp_pool_pda(pool_account, &market_id, user_account, system_program, program_id, bump)?;
+    }
+    let pool = unsafe { borrow_account_data_mut::<LpPool>(pool_account)? };
+    let (expected_pool, _) = derive_lp_pool_pda(&market_id, pool.bump, program_id);
+    if pool_account.key() != &expected_pool {
+        msg!("Error: Invalid LP pool PDA");
+        return Err(PercolatorError::InvalidAccount.into());
+    }
+
+    // The cached share price is stamped with clock time, not a caller timestamp
+    use pinocchio::sysvars::{clock::Clock, rent::Rent, Sysvar};
+    let current_ts = Clock::get()
+        .map(|clock| clock.unix_timestamp.max(0) as u64)
+        .map_err(|_| PercolatorError::StalePrice)?;
+
+    // Call the instruction handler
+    process_lp_deposit(portfolio, pool, amount, current_ts)?;
+
+    // The deposit leaves the portfolio as lamports, not just as equity
+    let rent_floor = Rent::get()?.minimum_balance(portfolio_account.data_len());
+    check_rent_floor(portfolio_account.lamports(), amount, rent_floor)?;
+    move_lamports(portfolio_account, pool_account, amount)?;
+
+    msg!("LpDeposit processed successfully");
+    Ok(())
+}
+
+/// Process position report instruction
+///
+/// Expected accounts:
+/// 0. `[]` PositionDetails account
+/// 1. `[]` Registry account
+/// 2..N. `[]` Oracle account(s) for the position's slab
+///
+/// Instruction data layout: none
+///
+/// Total size: 0 bytes
+fn process_position_report_inner(program_id: &Pubkey, accounts: &[AccountInfo], _data: &[u8]) -> ProgramResult {
+    if accounts.len() < 3 {
+        msg!("Error: PositionReport requires at least 3 accounts");
         return Err(PercolatorError::InvalidInstruction.into());
     }
 
-    let mut order_ids_buffer = [0u64; MAX_ORDERS];
-    for i in 0..order_count {
-        order_ids_buffer[i] = reader.read_u64()?;
+    let position_account = &accounts[0];
+    let registry_account = &accounts[1];
+    let oracle_accounts = &accounts[2..];
+
+    // Validate accounts
+    validate_owner(position_account, program_id)?;
+    validate_owner(registry_account, program_id)?;
+
+    // Read-only borrows: the report never writes
+    let position = unsafe { borrow_account_data::<PositionDetails>(position_account)? };
+    if !position.validate() {
+        msg!("Error: PositionDetails magic bytes invalid");
+        return Err(PercolatorError::InvalidAccount.into());
+    }
+    let registry = unsafe { borrow_account_data::<SlabRegistry>(registry_account)? };
+
+    // Call the instruction handler
+    process_position_report(position, registry, oracle_accounts)?;
+
+    msg!("PositionReport processed successfully");
+    Ok(())
+}
+
+/// Process sweep positions instruction
+///
+/// Expected accounts:
+/// 0. `[]` Portfolio account
+/// 1. `[writable]` Portfolio owner (receives refunded rent)
+/// 2..N. `[writable]` PositionDetails PDAs to close (up to 16)
+///
+/// Instruction data layout: none
+///
+/// Total size: 0 bytes
+fn process_sweep_positions_inner(program_id: &Pubkey, accounts: &[AccountInfo], _data: &[u8]) -> ProgramResult {
+    if accounts.len() < 3 {
+        msg!("Error: SweepPositions requires at least 3 accounts");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+
+    let portfolio_account = &accounts[0];
+    let owner_account = &accounts[1];
+    let position_accounts = &accounts[2..];
+
+    // Validate accounts
+    validate_owner(portfolio_account, program_id)?;
+    validate_writable(owner_account)?;
+    for position_account in position_accounts {
+        validate_writable(position_account)?;
     }
-    let order_ids = &order_ids_buffer[..order_count];
 
-    let freed_quote = reader.read_u128()?;
-    let freed_base = reader.read_u128()?;
+    // The portfolio is only read; closing never changes exposures
+    let portfolio = unsafe { borrow_account_data::<Portfolio>(portfolio_account)? };
 
     // Call the instruction handler
-    process_cancel_lp_orders(
+    process_sweep_positions(
         portfolio,
-        market_id,
-        order_ids,
-        order_count,
-        freed_quote,
-        freed_base,
+        portfolio_account.key(),
+        owner_account,
+        position_accounts,
+        program_id,
     )?;
 
-    msg!("CancelLpOrders processed successfully");
+    msg!("SweepPositions processed successfully");
     Ok(())
 }
+
+/// Process early vest instruction
+///
+/// Expected accounts:
+/// 0. `[writable]` Portfolio account
+/// 1. `[signer]` User authority (portfolio owner)
+/// 2. `[writable]` Registry account (receives the penalty into insurance)
+///
+/// Instruction data layout:
+/// - amount: u64 (8 bytes)
+///
+/// Total size: 8 bytes
+fn process_early_vest_inner(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    if accounts.len() < 3 {
+        msg!("Error: EarlyVest requires at least 3 accounts");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+
+    let portfolio_account = &accounts[0];
+    let user_account = &accounts[1];
+    let registry_account = &accounts[2];
+
+    // Validate accounts
+    validate_owner(portfolio_account, program_id)?;
+    validate_writable(portfolio_account)?;
+    validate_signer(user_account)?;
+    validate_owner(registry_account, program_id)?;
+    validate_writable(registry_account)?;
+
+    // Borrow account data mutably
+    let portfolio = unsafe { borrow_account_data_mut::<Portfolio>(portfolio_account)? };
+    let registry = unsafe { borrow_account_data_mut::<SlabRegistry>(registry_account)? };
+
+    // Early vesting forfeits PnL, so only the owner may trigger it
+    if &portfolio.user != user_account.key() {
+        msg!("Error: Portfolio does not belong to user");
+        return Err(PercolatorError::Unauthorized.into());
+    }
+
+    // Parse instruction data
+    if data.len() < 8 {
+        msg!("Error: Instruction data too short");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+
+    let mut reader = InstructionReader::new(data);
+    let amount = reader.read_u64()?;
+
+    use pinocchio::sysvars::{clock::Clock, Sysvar};
+    let current_slot = Clock::get()
+        .map(|clock| clock.slot)
+        .map_err(|_| PercolatorError::InvalidInstruction)?;
+
+    // Call the instruction handler
+    process_early_vest(portfolio, registry, amount, current_slot)?;
+
+    msg!("EarlyVest processed successfully");
+    Ok(())
+}
+
+/// Process set pause instruction
+///
+/// Expected accounts:
+/// 0. `[writable]` Registry account
+/// 1. `[signer]` Governance authority
+///
+/// Instruction data layout:
+/// - pause_flags: u64 (8 bytes, PAUSE_* bitflags; 0 resumes everything)
+///
+/// Total size: 8 bytes
+fn process_set_pause_inner(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    if accounts.len() < 2 {
+        msg!("Error: SetPause requires at least 2 accounts");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+
+    let registry_account = &accounts[0];
+    let governance_account = &accounts[1];
+
+    // Validate accounts
+    validate_owner(registry_account, program_id)?;
+    validate_writable(registry_account)?;
+    validate_signer(governance_account)?;
+
+    // Borrow account data mutably
+    let registry = unsafe { borrow_account_data_mut::<SlabRegistry>(registry_account)? };
+
+    // Parse instruction data
+    if data.len() < 8 {
+        msg!("Error: Instruction data too short");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+
+    let mut reader = InstructionReader::new(data);
+    let pause_flags = reader.read_u64()?;
+
+    // Call the instruction handler
+    process_set_pause(registry, governance_account.key(), pause_flags)?;
+
+    msg!("SetPause processed successfully");
+    Ok(())
+}
+
+/// Process cancel LP orders instruction
+///
+/// Expected accounts:
+/// 0. `[writable]` Portfolio account
+/// 1. `[signer]` User authority
+/// 2. `[]` Registry account (for pause flags)
+///
+/// Instruction data layout:
+/// - market_id: Pubkey (32 bytes)
+/// - order_count: u8 (1 byte)
+/// - order_ids: [u64; order_count] (8 * order_count bytes)
+///
+/// Freed collateral is looked up from each order's reservation, not supplied.
+///
+/// Total size: 33 + (8 * order_count) bytes
+fn process_cancel_lp_orders_inner(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    if accounts.len() < 3 {
+        msg!("Error: CancelLpOrders requires at least 3 accounts");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+
+    let portfolio_account = &accounts[0];
+    let _user_account = &accounts[1];
+    let registry_account = &accounts[2];
+
+    // Validate accounts
+    validate_owner(portfolio_account, program_id)?;
+    validate_writable(portfolio_account)?;
+    validate_owner(registry_account, program_id)?;
+
+    // Borrow account data mutably
+    let portfolio = unsafe { borrow_account_data_mut::<Portfolio>(portfolio_account)? };
+    let registry = unsafe { borrow_account_data::<SlabRegistry>(registry_account)? };
+    require_not_paused(registry, RouterInstruction::CancelLpOrders)?;
+
+    // Parse instruction data
+    if data.len() < 33 {
+        msg!("Error: Instruction data too short");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+
+    let mut reader = InstructionReader::new(data);
+    let market_id_bytes = reader.read_bytes::<32>()?;
+    let market_id = Pubkey::from(market_id_bytes);
+    let order_count = reader.read_u8()? as usize;
+
+    // Read order IDs (up to 16 max for stack safety)
+    const MAX_ORDERS: usize = 16;
+    if order_count > MAX_ORDERS {
+        msg!("Error: order_count exceeds maximum");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+
+    let mut order_ids_buffer = [0u64; MAX_ORDERS];
+    for i in 0..order_count {
+        order_ids_buffer[i] = reader.read_u64()?;
+    }
+    let order_ids = &order_ids_buffer[..order_count];
+
+    // Call the instruction handler
+    process_cancel_lp_orders(
+        portfolio,
+        market_id,
+        order_ids,
+        order_count,
+    )?;
+
+    msg!("CancelLpOrders processed successfully");
+    Ok(())
+}
+
+/// Process update TWAP instruction
+///
+/// Expected accounts:
+/// 0. `[writable]` TWAP buffer account (router-owned, TwapBuffer::LEN bytes)
+/// 1. `[]` Spot oracle account
+///
+/// Instruction data layout: none
+fn process_update_twap_inner(program_id: &Pubkey, accounts: &[AccountInfo], _data: &[u8]) -> ProgramResult {
+    if accounts.len() < 2 {
+        msg!("Error: UpdateTwap requires at least 2 accounts");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+
+    let twap_account = &accounts[0];
+    let spot_oracle = &accounts[1];
+
+    // Validate accounts
+    validate_owner(twap_account, program_id)?;
+    validate_writable(twap_account)?;
+
+    let twap_buffer = unsafe { borrow_account_data_mut::<TwapBuffer>(twap_account)? };
+
+    process_update_twap(twap_buffer, spot_oracle)?;
+
+    msg!("UpdateTwap processed successfully");
+    Ok(())
+}
+
+/// Process close all instruction
+///
+/// Expected accounts:
+/// 0. `[writable]` User Portfolio account
+/// 1. `[signer, writable]` User authority or the portfolio's delegate (receives PositionDetails rent)
+/// 2. `[writable]` DLP Portfolio account (counterparty)
+/// 3. `[writable]` Registry account
+/// 4. `[]` Router authority PDA
+/// 5. `[]` System program (for SOL transfers)
+/// 6. `[]` Slab program (for CPI)
+/// 7..7+N. `[writable]` Slab accounts, one per open position (N = num_positions)
+/// 7+N..7+2N. `[writable]` Receipt accounts
+/// 7+2N..7+3N. `[]` Oracle accounts
+/// 7+3N..7+4N. `[writable]` PositionDetails PDAs
+///
+/// Instruction data layout:
+/// - num_positions: u8 (1 byte, must equal the portfolio's open position count)
+///
+/// Total size: 1 byte
+fn process_close_all_inner(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    if accounts.len() < 7 {
+        msg!("Error: CloseAll requires at least 7 accounts");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+
+    let user_portfolio_account = &accounts[0];
+    let user_account = &accounts[1];
+    let dlp_portfolio_account = &accounts[2];
+    let registry_account = &accounts[3];
+    let router_authority = &accounts[4];
+    let system_program = &accounts[5];
+    let slab_program = &accounts[6];
+
+    // Validate accounts
+    validate_owner(user_portfolio_account, program_id)?;
+    validate_writable(user_portfolio_account)?;
+    validate_owner(dlp_portfolio_account, program_id)?;
+    validate_writable(dlp_portfolio_account)?;
+    validate_owner(registry_account, program_id)?;
+    validate_writable(registry_account)?;
+
+    let mut reader = InstructionReader::new(data);
+    let num_positions = reader.read_u8()? as usize;
+
+    if num_positions == 0 || num_positions > MAX_CLOSE_ALL_POSITIONS {
+        msg!("Error: Invalid num_positions");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+
+    if accounts.len() < 7 + num_positions * 4 {
+        msg!("Error: Insufficient accounts for CloseAll");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+
+    let slab_accounts = &accounts[7..7 + num_positions];
+    let receipt_accounts = &accounts[7 + num_positions..7 + num_positions * 2];
+    let oracle_accounts = &accounts[7 + num_positions * 2..7 + num_positions * 3];
+    let position_details_accounts = &accounts[7 + num_positions * 3..7 + num_positions * 4];
+
+    // Borrow account data mutably
+    let user_portfolio = unsafe { borrow_account_data_mut::<Portfolio>(user_portfolio_account)? };
+    let dlp_portfolio = unsafe { borrow_account_data_mut::<Portfolio>(dlp_portfolio_account)? };
+    let registry = unsafe { borrow_account_data_mut::<SlabRegistry>(registry_account)? };
+    require_not_paused(registry, RouterInstruction::CloseAll)?;
+
+    use pinocchio::sysvars::{clock::Clock, Sysvar};
+    let current_slot = Clock::get()
+        .map(|clock| clock.slot)
+        .map_err(|_| PercolatorError::InvalidInstruction)?;
+    user_portfolio.record_trade(current_slot, registry.trade_cooldown_slots).map_err(|e| {
+        msg!("Error: Trade throttled - cooldown has not elapsed");
+        e
+    })?;
+
+    process_close_all(
+        user_portfolio_account,
+        user_portfolio,
+        user_account,
+        dlp_portfolio_account,
+        dlp_portfolio,
+        registry,
+        router_authority,
+        system_program,
+        slab_program,
+        slab_accounts,
+        receipt_accounts,
+        oracle_accounts,
+        position_details_accounts,
+        program_id,
+    )?;
+
+    msg!("CloseAll processed successfully");
+    Ok(())
+}
+
+/// Process quote depth instruction
+///
+/// Expected accounts:
+/// 0. `[]` Registry account
+/// 1..N. `[]` Slab accounts to quote (up to 16)
+///
+/// Instruction data layout:
+/// - side: u8 (1 byte, taker side: 0 = buy, 1 = sell)
+/// - limit_px: i64 (8 bytes, worst acceptable price, 1e6 scale)
+///
+/// Total size: 9 bytes
+fn process_quote_depth_inner(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    if accounts.len() < 2 {
+        msg!("Error: QuoteDepth requires at least 2 accounts");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+
+    let registry_account = &accounts[0];
+    let slab_accounts = &accounts[1..];
+
+    // Validate accounts
+    validate_owner(registry_account, program_id)?;
+
+    // Read-only borrow: the query never writes
+    let registry = unsafe { borrow_account_data::<SlabRegistry>(registry_account)? };
+
+    // Parse instruction data
+    let mut reader = InstructionReader::new(data);
+    let side = match reader.read_u8()? {
+        0 => Side::Buy,
+        1 => Side::Sell,
+        _ => {
+            msg!("Error: Invalid side");
+            return Err(PercolatorError::InvalidSide.into());
+        }
+    };
+    let limit_px = reader.read_i64()?;
+
+    // Call the instruction handler
+    process_quote_depth(registry, slab_accounts, side, limit_px)?;
+
+    msg!("QuoteDepth processed successfully");
+    Ok(())
+}
+
+/// Process set liquidators instruction
+///
+/// Expected accounts:
+/// 0. `[writable]` Registry account
+/// 1. `[signer]` Governance authority
+///
+/// Instruction data layout:
+/// - mode: u8 (1 byte, 0 = open, 1 = permissioned)
+/// - count: u8 (1 byte, up to 8)
+/// - liquidators: [Pubkey; count] (32 bytes each)
+///
+/// Total size: 2 + (32 * count) bytes
+fn process_set_liquidators_inner(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    if accounts.len() < 2 {
+        msg!("Error: SetLiquidators requires at least 2 accounts");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+
+    let registry_account = &accounts[0];
+    let governance_account = &accounts[1];
+
+    // Validate accounts
+    validate_owner(registry_account, program_id)?;
+    validate_writable(registry_account)?;
+    validate_signer(governance_account)?;
+
+    // Borrow account data mutably
+    let registry = unsafe { borrow_account_data_mut::<SlabRegistry>(registry_account)? };
+
+    // Parse instruction data
+    let mut reader = InstructionReader::new(data);
+    let mode = reader.read_u8()?;
+    let count = reader.read_u8()? as usize;
+    if count > MAX_LIQUIDATORS {
+        msg!("Error: Too many liquidators");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+    let mut liquidators = [Pubkey::default(); MAX_LIQUIDATORS];
+    for liquidator in liquidators.iter_mut().take(count) {
+        *liquidator = reader.read_bytes::<32>()?;
+    }
+
+    // Call the instruction handler
+    process_set_liquidators(registry, governance_account.key(), mode, &liquidators[..count])?;
+
+    msg!("SetLiquidators processed successfully");
+    Ok(())
+}
+
+/// Process set deposit caps instruction
+///
+/// Expected accounts:
+/// 0. `[writable]` Registry account
+/// 1. `[signer]` Governance authority
+///
+/// Instruction data layout:
+/// - max_portfolio_deposit: u64 (8 bytes, lamports, 0 = uncapped)
+/// - max_total_deposits: u64 (8 bytes, lamports, 0 = uncapped)
+///
+/// Total size: 16 bytes
+fn process_set_deposit_caps_inner(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    if accounts.len() < 2 {
+        msg!("Error: SetDepositCaps requires at least 2 accounts");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+
+    let registry_account = &accounts[0];
+    let governance_account = &accounts[1];
+
+    // Validate accounts
+    validate_owner(registry_account, program_id)?;
+    validate_writable(registry_account)?;
+    validate_signer(governance_account)?;
+
+    // Borrow account data mutably
+    let registry = unsafe { borrow_account_data_mut::<SlabRegistry>(registry_account)? };
+
+    // Parse instruction data
+    if data.len() < 16 {
+        msg!("Error: Instruction data too short");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+
+    let mut reader = InstructionReader::new(data);
+    let max_portfolio_deposit = reader.read_u64()?;
+    let max_total_deposits = reader.read_u64()?;
+
+    // Call the instruction handler
+    process_set_deposit_caps(registry, governance_account.key(), max_portfolio_deposit, max_total_deposits)?;
+
+    msg!("SetDepositCaps processed successfully");
+    Ok(())
+}
+
+/// Process set insurance params instruction
+///
+/// Expected accounts:
+/// 0. `[writable]` Registry account
+/// 1. `[signer]` Governance authority
+///
+/// Instruction data layout:
+/// - fee_bps_to_insurance: u16 (2 bytes, bps of fill notional, max 100)
+/// - reserve_floor: u64 (8 bytes, lamports)
+///
+/// Total size: 10 bytes
+fn process_set_insurance_params_inner(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    if accounts.len() < 2 {
+        msg!("Error: SetInsuranceParams requires at least 2 accounts");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+
+    let registry_account = &accounts[0];
+    let governance_account = &accounts[1];
+
+    // Validate accounts
+    validate_owner(registry_account, program_id)?;
+    validate_writable(registry_account)?;
+    validate_signer(governance_account)?;
+
+    // Borrow account data mutably
+    let registry = unsafe { borrow_account_data_mut::<SlabRegistry>(registry_account)? };
+
+    // Parse instruction data
+    if data.len() < 10 {
+        msg!("Error: Instruction data too short");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+
+    let mut reader = InstructionReader::new(data);
+    let fee_bps_to_insurance = reader.read_u16()?;
+    let reserve_floor = reader.read_u64()?;
+
+    // Call the instruction handler
+    process_set_insurance_params(registry, governance_account.key(), fee_bps_to_insurance, reserve_floor)?;
+
+    msg!("SetInsuranceParams processed successfully");
+    Ok(())
+}
+
+/// Process set trade cooldown instruction
+///
+/// Expected accounts:
+/// 0. `[writable]` Registry account
+/// 1. `[signer]` Governance authority
+///
+/// Instruction data layout:
+/// - trade_cooldown_slots: u64 (8 bytes, 0 = unthrottled)
+///
+/// Total size: 8 bytes
+fn process_set_trade_cooldown_inner(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    if accounts.len() < 2 {
+        msg!("Error: SetTradeCooldown requires at least 2 accounts");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+
+    let registry_account = &accounts[0];
+    let governance_account = &accounts[1];
+
+    // Validate accounts
+    validate_owner(registry_account, program_id)?;
+    validate_writable(registry_account)?;
+    validate_signer(governance_account)?;
+
+    // Borrow account data mutably
+    let registry = unsafe { borrow_account_data_mut::<SlabRegistry>(registry_account)? };
+
+    // Parse instruction data
+    if data.len() < 8 {
+        msg!("Error: Instruction data too short");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+
+    let mut reader = InstructionReader::new(data);
+    let trade_cooldown_slots = reader.read_u64()?;
+
+    // Call the instruction handler
+    process_set_trade_cooldown(registry, governance_account.key(), trade_cooldown_slots)?;
+
+    msg!("SetTradeCooldown processed successfully");
+    Ok(())
+}
+
+/// Process portfolio summary instruction
+///
+/// Expected accounts:
+/// 0. `[]` Portfolio account
+///
+/// Instruction data layout: none
+///
+/// Total size: 0 bytes
+fn process_portfolio_summary_inner(program_id: &Pubkey, accounts: &[AccountInfo], _data: &[u8]) -> ProgramResult {
+    if accounts.is_empty() {
+        msg!("Error: PortfolioSummary requires 1 account");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+
+    let portfolio_account = &accounts[0];
+
+    // Validate accounts
+    validate_owner(portfolio_account, program_id)?;
+
+    // Read-only borrow: the summary never writes
+    let portfolio = unsafe { borrow_account_data::<Portfolio>(portfolio_account)? };
+
+    // Call the instruction handler
+    process_portfolio_summary(portfolio)?;
+
+    msg!("PortfolioSummary processed successfully");
+    Ok(())
+}
+
+/// Process initialize registry page instruction
+///
+/// Expected accounts:
+/// 0. `[writable]` Registry account
+/// 1. `[writable]` Next registry page PDA (["registry_page", page_index])
+/// 2. `[signer, writable]` Governance authority (pays rent)
+/// 3. `[]` System program
+///
+/// Instruction data layout: none (the page index is the registry's page_count + 1)
+///
+/// Total size: 0 bytes
+fn process_initialize_registry_page_inner(program_id: &Pubkey, accounts: &[AccountInfo], _data: &[u8]) -> ProgramResult {
+    if accounts.len() < 4 {
+        msg!("Error: InitializeRegistryPage requires at least 4 accounts");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+
+    let registry_account = &accounts[0];
+    let page_account = &accounts[1];
+    let governance_account = &accounts[2];
+    let system_program = &accounts[3];
+
+    // Validate accounts
+    validate_owner(registry_account, program_id)?;
+    validate_writable(registry_account)?;
+    validate_writable(page_account)?;
+    validate_signer(governance_account)?;
+    validate_writable(governance_account)?;
+
+    // Borrow account data mutably
+    let registry = unsafe { borrow_account_data_mut::<SlabRegistry>(registry_account)? };
+
+    // Call the instruction handler
+    process_initialize_registry_page(program_id, registry, page_account, governance_account, system_program)?;
+
+    msg!("InitializeRegistryPage processed successfully");
+    Ok(())
+}
+
+/// Process deregister slab instruction
+///
+/// Expected accounts:
+/// 0. `[writable]` Registry account
+/// 1. `[signer]` Governance authority
+///
+/// Instruction data layout:
+/// - slab_id: Pubkey (32 bytes)
+///
+/// Total size: 32 bytes
+fn process_deregister_slab_inner(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    if accounts.len() < 2 {
+        msg!("Error: DeregisterSlab requires at least 2 accounts");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+
+    let registry_account = &accounts[0];
+    let governance_account = &accounts[1];
+
+    // Validate accounts
+    validate_owner(registry_account, program_id)?;
+    validate_writable(registry_account)?;
+    validate_signer(governance_account)?;
+
+    // Borrow account data mutably
+    let registry = unsafe { borrow_account_data_mut::<SlabRegistry>(registry_account)? };
+
+    // Parse instruction data
+    let mut reader = InstructionReader::new(data);
+    let slab_id = Pubkey::from(reader.read_bytes::<32>()?);
+
+    // Call the instruction handler
+    process_deregister_slab(registry, governance_account.key(), &slab_id)?;
+
+    msg!("DeregisterSlab processed successfully");
+    Ok(())
+}
+
+/// Process propose governance instruction
+///
+/// Expected accounts:
+/// 0. `[writable]` Registry account
+/// 1. `[signer]` Current governance authority
+///
+/// Instruction data layout:
+/// - new_governance: Pubkey (32 bytes, default pubkey withdraws the proposal)
+///
+/// Total size: 32 bytes
+fn process_propose_governance_inner(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    if accounts.len() < 2 {
+        msg!("Error: ProposeGovernance requires at least 2 accounts");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+
+    let registry_account = &accounts[0];
+    let governance_account = &accounts[1];
+
+    // Validate accounts
+    validate_owner(registry_account, program_id)?;
+    validate_writable(registry_account)?;
+    validate_signer(governance_account)?;
+
+    // Borrow account data mutably
+    let registry = unsafe { borrow_account_data_mut::<SlabRegistry>(registry_account)? };
+
+    // Parse instruction data
+    let mut reader = InstructionReader::new(data);
+    let new_governance = Pubkey::from(reader.read_bytes::<32>()?);
+
+    // Call the instruction handler
+    process_propose_governance(registry, governance_account.key(), &new_governance)?;
+
+    msg!("ProposeGovernance processed successfully");
+    Ok(())
+}
+
+/// Process accept governance instruction
+///
+/// Expected accounts:
+/// 0. `[writable]` Registry account
+/// 1. `[signer]` Proposed governance authority
+///
+/// Instruction data layout: none
+///
+/// Total size: 0 bytes
+fn process_accept_governance_inner(program_id: &Pubkey, accounts: &[AccountInfo], _data: &[u8]) -> ProgramResult {
+    if accounts.len() < 2 {
+        msg!("Error: AcceptGovernance requires at least 2 accounts");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+
+    let registry_account = &accounts[0];
+    let new_governance_account = &accounts[1];
+
+    // Validate accounts
+    validate_owner(registry_account, program_id)?;
+    validate_writable(registry_account)?;
+    validate_signer(new_governance_account)?;
+
+    // Borrow account data mutably
+    let registry = unsafe { borrow_account_data_mut::<SlabRegistry>(registry_account)? };
+
+    // Call the instruction handler
+    process_accept_governance(registry, new_governance_account.key())?;
+
+    msg!("AcceptGovernance processed successfully");
+    Ok(())
+}
+
+/// Process protocol stats instruction
+///
+/// Expected accounts:
+/// 0. `[]` Registry account
+///
+/// Instruction data layout: none
+///
+/// Total size: 0 bytes
+fn process_protocol_stats_inner(program_id: &Pubkey, accounts: &[AccountInfo], _data: &[u8]) -> ProgramResult {
+    if accounts.is_empty() {
+        msg!("Error: ProtocolStats requires 1 account");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+
+    let registry_account = &accounts[0];
+
+    // Validate accounts
+    validate_owner(registry_account, program_id)?;
+
+    // Read-only borrow: the stats never write
+    let registry = unsafe { borrow_account_data::<SlabRegistry>(registry_account)? };
+
+    // Call the instruction handler
+    process_protocol_stats(registry)?;
+
+    msg!("ProtocolStats processed successfully");
+    Ok(())
+}
+
+/// Process touch instruction (permissionless keeper crank)
+///
+/// Expected accounts:
+/// 0. `[writable]` Portfolio account (any portfolio; no owner signature)
+/// 1. `[writable]` Registry account (vested PnL total)
+///
+/// Instruction data layout: none
+///
+/// Total size: 0 bytes
+fn process_touch_inner(program_id: &Pubkey, accounts: &[AccountInfo], _data: &[u8]) -> ProgramResult {
+    if accounts.len() < 2 {
+        msg!("Error: Touch requires at least 2 accounts");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+
+    let portfolio_account = &accounts[0];
+    let registry_account = &accounts[1];
+
+    // Validate accounts
+    validate_owner(portfolio_account, program_id)?;
+    validate_writable(portfolio_account)?;
+    validate_owner(registry_account, program_id)?;
+    validate_writable(registry_account)?;
+
+    // Borrow account data mutably
+    let portfolio = unsafe { borrow_account_data_mut::<Portfolio>(portfolio_account)? };
+    let registry = unsafe { borrow_account_data_mut::<SlabRegistry>(registry_account)? };
+
+    use pinocchio::sysvars::{clock::Clock, Sysvar};
+    let current_slot = Clock::get()
+        .map(|clock| clock.slot)
+        .map_err(|_| PercolatorError::InvalidInstruction)?;
+
+    // Call the instruction handler
+    process_touch(portfolio, registry, current_slot)?;
+
+    msg!("Touch processed successfully");
+    Ok(())
+}
+
+/// Process set delegate instruction
+///
+/// Expected accounts:
+/// 0. `[writable]` Portfolio account
+/// 1. `[signer]` User authority (portfolio owner)
+///
+/// Instruction data layout:
+/// - delegate: Pubkey (32 bytes, default pubkey = revoke)
+///
+/// Total size: 32 bytes
+fn process_set_delegate_inner(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    if accounts.len() < 2 {
+        msg!("Error: SetDelegate requires at least 2 accounts");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+
+    let portfolio_account = &accounts[0];
+    let user_account = &accounts[1];
+
+    // Validate accounts
+    validate_owner(portfolio_account, program_id)?;
+    validate_writable(portfolio_account)?;
+    validate_signer(user_account)?;
+
+    // Borrow account data mutably
+    let portfolio = unsafe { borrow_account_data_mut::<Portfolio>(portfolio_account)? };
+
+    // Parse instruction data
+    let mut reader = InstructionReader::new(data);
+    let delegate: Pubkey = reader.read_bytes::<32>()?;
+
+    // Call the instruction handler
+    process_set_delegate(portfolio, user_account.key(), delegate)?;
+
+    msg!("SetDelegate processed successfully");
+    Ok(())
+}
+
+/// Process close portfolio instruction
+///
+/// Expected accounts:
+/// 0. `[writable]` Portfolio account (closed)
+/// 1. `[signer, writable]` User account (portfolio owner, receives every lamport)
+/// 2. `[writable]` Registry account (TVL tracking and warmup state)
+///
+/// Instruction data: none
+fn process_close_portfolio_inner(program_id: &Pubkey, accounts: &[AccountInfo], _data: &[u8]) -> ProgramResult {
+    if accounts.len() < 3 {
+        msg!("Error: ClosePortfolio requires at least 3 accounts");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+
+    let portfolio_account = &accounts[0];
+    let user_account = &accounts[1];
+    let registry_account = &accounts[2];
+
+    // Validate accounts
+    validate_owner(portfolio_account, program_id)?;
+    validate_writable(portfolio_account)?;
+    validate_signer(user_account)?;
+    validate_writable(user_account)?;
+    validate_owner(registry_account, program_id)?;
+    validate_writable(registry_account)?;
+
+    // Borrow account data mutably
+    let registry = unsafe { borrow_account_data_mut::<SlabRegistry>(registry_account)? };
+
+    // Call the instruction handler
+    process_close_portfolio(portfolio_account, user_account, registry)?;
+
+    msg!("ClosePortfolio processed successfully");
+    Ok(())
+}
+
+/// Process set custom price instruction (localnet builds only)
+///
+/// Expected accounts:
+/// 0. `[writable]` Custom oracle account (router-owned, CUSTOM_ORACLE_SIZE bytes)
+/// 1. `[signer]` Oracle authority
+///
+/// Instruction data layout:
+/// - price: i64 (8 bytes, 1e6 scale)
+/// - timestamp: i64 (8 bytes, unix seconds)
+///
+/// Total size: 16 bytes
+#[cfg(feature = "localnet")]
+fn process_set_custom_price_inner(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    use crate::instructions::process_set_custom_price;
+
+    if accounts.len() < 2 {
+        msg!("Error: SetCustomPrice requires at least 2 accounts");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+
+    let oracle_account = &accounts[0];
+    let authority_account = &accounts[1];
+
+    // Validate accounts
+    validate_owner(oracle_account, program_id)?;
+    validate_writable(oracle_account)?;
+    validate_signer(authority_account)?;
+
+    // Parse instruction data
+    let mut reader = InstructionReader::new(data);
+    let price = reader.read_i64()?;
+    let timestamp = reader.read_i64()?;
+
+    // Call the instruction handler
+    process_set_custom_price(oracle_account, authority_account.key(), price, timestamp)?;
+
+    msg!("SetCustomPrice processed successfully");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pinocchio::program_error::ProgramError;
+
+    #[test]
+    fn test_unsupported_version_rejected() {
+        let program_id = Pubkey::default();
+        let unsupported = ProgramError::from(PercolatorError::UnsupportedVersion);
+
+        // Rejected before any account is touched, for any instruction
+        assert_eq!(process_instruction(&program_id, &[], &[4, INSTRUCTION_VERSION + 1]), Err(unsupported.clone()));
+        assert_eq!(process_instruction(&program_id, &[], &[2, 0, 1, 0, 0, 0, 0, 0, 0, 0]), Err(unsupported));
+
+        // A supported version gets through to the handler's own checks
+        assert_eq!(
+            process_instruction(&program_id, &[], &[2, INSTRUCTION_VERSION]),
+            Err(ProgramError::from(PercolatorError::InvalidInstruction))
+        );
+
+        // Pre-versioning data (discriminator alone) is rejected too
+        assert_eq!(
+            process_instruction(&program_id, &[], &[2]),
+            Err(ProgramError::from(PercolatorError::InvalidInstruction))
+        );
+    }
+}
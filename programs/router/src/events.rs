@@ -0,0 +1,99 @@
+//! Structured events logged with sol_log_data
+//!
+//! Each event is a fixed little-endian layout led by an 8-byte
+//! discriminator, so indexers can tell events apart from the router's other
+//! log blobs (portfolio summaries, liquidated batch keys) without parsing
+//! text logs. A discriminator never changes meaning: a new layout gets a new
+//! discriminator.
+
+use percolator_common::*;
+use pinocchio::{log::sol_log_data, pubkey::Pubkey};
+
+/// Discriminator leading every serialized `LiquidationEvent`
+pub const LIQUIDATION_EVENT_DISCRIMINATOR: [u8; 8] = *b"evliq001";
+
+/// Outcome of one LiquidateUser, for risk and insurance dashboards
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LiquidationEvent {
+    /// Liquidated portfolio
+    pub portfolio: Pubkey,
+    /// Notional of the reduce-only fills (1e6 quote scale)
+    pub notional_closed: u128,
+    /// PnL the account lost in the liquidation fills, fees included (lamports)
+    pub realized_loss: u128,
+    /// Liquidation spread the account paid to the insurance fund (lamports)
+    pub penalty_paid: u128,
+    /// Insurance fund payout toward the account's bad debt (lamports)
+    pub insurance_draw: u128,
+    /// Uncovered bad debt was socialized through the global haircut
+    pub adl_triggered: bool,
+}
+
+impl LiquidationEvent {
+    /// Serialized size
+    pub const LEN: usize = 8 + 32 + 16 * 4 + 1;
+
+    /// Encode as the discriminator followed by little-endian fields in declaration order
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut out = [0u8; Self::LEN];
+        out[0..8].copy_from_slice(&LIQUIDATION_EVENT_DISCRIMINATOR);
+        out[8..40].copy_from_slice(&self.portfolio);
+        out[40..56].copy_from_slice(&self.notional_closed.to_le_bytes());
+        out[56..72].copy_from_slice(&self.realized_loss.to_le_bytes());
+        out[72..88].copy_from_slice(&self.penalty_paid.to_le_bytes());
+        out[88..104].copy_from_slice(&self.insurance_draw.to_le_bytes());
+        out[104] = self.adl_triggered as u8;
+        out
+    }
+
+    /// Decode an event produced by `to_bytes`
+    ///
+    /// Errors with InvalidInstruction on truncated data or another discriminator.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, PercolatorError> {
+        let mut reader = InstructionReader::new(data);
+
+        if reader.read_bytes::<8>()? != LIQUIDATION_EVENT_DISCRIMINATOR {
+            return Err(PercolatorError::InvalidInstruction);
+        }
+
+        Ok(Self {
+            portfolio: reader.read_bytes::<32>()?,
+            notional_closed: reader.read_u128()?,
+            realized_loss: reader.read_u128()?,
+            penalty_paid: reader.read_u128()?,
+            insurance_draw: reader.read_u128()?,
+            adl_triggered: reader.read_u8()? != 0,
+        })
+    }
+
+    /// Log the event with sol_log_data
+    pub fn emit(&self) {
+        sol_log_data(&[&self.to_bytes()]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_liquidation_event_round_trip() {
+        let event = LiquidationEvent {
+            portfolio: [9; 32],
+            notional_closed: 1_000_000_000,
+            realized_loss: 45_000_000,
+            penalty_paid: 500_000,
+            insurance_draw: 3_000_000,
+            adl_triggered: true,
+        };
+        let bytes = event.to_bytes();
+        assert_eq!(&bytes[..8], b"evliq001");
+        assert_eq!(LiquidationEvent::from_bytes(&bytes), Ok(event));
+
+        // Other blobs and truncated events aren't mistaken for one
+        let mut other = bytes;
+        other[0] ^= 0xff;
+        assert_eq!(LiquidationEvent::from_bytes(&other), Err(PercolatorError::InvalidInstruction));
+        assert_eq!(LiquidationEvent::from_bytes(&bytes[..LiquidationEvent::LEN - 1]), Err(PercolatorError::InvalidInstruction));
+    }
+}
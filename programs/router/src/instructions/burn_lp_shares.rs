@@ -3,12 +3,15 @@
 //! This is the ONLY way to reduce AMM LP exposure. This instruction:
 //! - Burns LP shares proportionally
 //! - Updates margin proportionally
-//! - Credits equity with redemption value
-//! - Enforces staleness checks on share price
+//! - Redeems the shares out of the market's LpPool at its on-chain NAV and
+//!   credits equity with the redemption value (the entrypoint moves the
+//!   lamports back alongside)
+//! - Prices at the on-chain clock, so there is no caller timestamp to go stale
 //!
 //! CRITICAL INVARIANT: AMM LP can ONLY be reduced via this instruction
 
-use crate::state::{Portfolio, VenueId, VenueKind};
+use crate::instructions::lp_share_price;
+use crate::state::{LpPool, Portfolio, VenueId, VenueKind};
 use percolator_common::*;
 use pinocchio::msg;
 
@@ -18,31 +21,29 @@ use pinocchio::msg;
 ///
 /// # Arguments
 /// * `portfolio` - User's portfolio account (mutable)
-/// * `market_id` - AMM market pubkey
+/// * `pool` - The AMM market's LP pool (mutable); prices and pays the redemption
 /// * `shares_to_burn` - Number of LP shares to burn
-/// * `current_share_price` - Current share price from AMM (scaled by 1e6)
-/// * `current_ts` - Current timestamp for staleness check
-/// * `max_staleness_seconds` - Maximum allowed staleness (typically 60s)
+/// * `clock_ts` - On-chain Clock unix timestamp the redemption is priced at
 ///
 /// # Returns
+/// * Redemption value owed out of the pool (lamports)
 /// * Updates portfolio:
 ///   - Reduces lp_shares in AMM bucket
 ///   - Reduces bucket margin proportionally
 ///   - Increases equity by redemption value
 ///   - If all shares burned, removes bucket entirely
+/// * Updates pool: removes the shares and the redemption value
 ///
 /// # Safety
-/// * Rejects stale share prices
+/// * Share price is read from the pool, never supplied by the caller
 /// * Enforces proportional margin reduction
 /// * Maintains accounting consistency
 pub fn process_burn_lp_shares(
     portfolio: &mut Portfolio,
-    market_id: pinocchio::pubkey::Pubkey,
+    pool: &mut LpPool,
     shares_to_burn: u64,
-    current_share_price: i64,
-    current_ts: u64,
-    max_staleness_seconds: u64,
-) -> Result<(), PercolatorError> {
+    clock_ts: u64,
+) -> Result<u64, PercolatorError> {
     msg!("BurnLpShares: Starting");
 
     // Safety check: shares_to_burn must be > 0
@@ -52,7 +53,7 @@ pub fn process_burn_lp_shares(
     }
 
     // Find AMM LP bucket for this market
-    let venue_id = VenueId::new_amm(market_id);
+    let venue_id = VenueId::new_amm(pool.market);
     let bucket_idx = {
         let mut idx: Option<usize> = None;
         for i in 0..portfolio.lp_bucket_count as usize {
@@ -78,27 +79,18 @@ pub fn process_burn_lp_shares(
     // Get AMM LP data
     let amm = bucket.amm.as_mut().ok_or(PercolatorError::InvalidAccount)?;
 
-    // SAFETY TRIPWIRE 1: Staleness guard
-    // Reject stale share prices to prevent using outdated valuations
-    if amm.is_stale(current_ts, max_staleness_seconds) {
-        msg!("Error: Share price is stale");
-        return Err(PercolatorError::StalePrice);
-    }
-
-    msg!("BurnLpShares: Share price is fresh");
-
-    // Verify shares to burn <= current shares
-    if shares_to_burn > amm.lp_shares {
+    // Verify shares to burn <= current shares, and that the pool issued them
+    if shares_to_burn > amm.lp_shares || shares_to_burn > pool.total_shares {
         msg!("Error: Cannot burn more shares than owned");
         return Err(PercolatorError::InsufficientBalance);
     }
 
-    // Calculate redemption value
-    // redemption = shares_to_burn * current_share_price
-    // Both are scaled by 1e6, so divide by 1e6
-    let shares_i128 = shares_to_burn as i128;
-    let price_i128 = current_share_price as i128;
-    let redemption_value = (shares_i128 * price_i128) / 1_000_000;
+    // Price and redeem from the pool's own assets and supply; nothing the
+    // caller passes can move the value paid out
+    let current_share_price = lp_share_price(pool.total_assets, pool.total_shares)?;
+    let redemption = pool.redemption_value(shares_to_burn);
+    let redemption_lamports = u64::try_from(redemption).map_err(|_| PercolatorError::Overflow)?;
+    let redemption_value = redemption as i128;
 
     msg!("BurnLpShares: Redemption value calculated");
 
@@ -127,7 +119,7 @@ pub fn process_burn_lp_shares(
         msg!("BurnLpShares: Proportional margin reduction");
     }
 
-    // SAFETY TRIPWIRE 2: Accounting consistency
+    // SAFETY TRIPWIRE: Accounting consistency
     // Verify redemption + margin reduction makes sense
     // The redemption value should approximately cover the margin reduction
     // (not exact due to market movements, but should be in the right ballpark)
@@ -145,13 +137,16 @@ pub fn process_burn_lp_shares(
     // Update AMM LP bucket
     amm.lp_shares = remaining_shares;
     amm.share_price_cached = current_share_price;
-    amm.last_update_ts = current_ts;
+    amm.last_update_ts = clock_ts;
 
     bucket.im = new_im;
     bucket.mm = new_mm;
 
     msg!("BurnLpShares: Updated bucket");
 
+    pool.total_assets -= redemption;
+    pool.total_shares -= shares_to_burn;
+
     // Update portfolio equity
     portfolio.equity = portfolio.equity.saturating_add(redemption_value);
 
@@ -184,7 +179,7 @@ pub fn process_burn_lp_shares(
 
     msg!("BurnLpShares: Complete");
 
-    Ok(())
+    Ok(redemption_lamports)
 }
 
 #[cfg(test)]
@@ -193,6 +188,14 @@ mod tests {
     use pinocchio::pubkey::Pubkey;
     use crate::state::LpBucket;
 
+    /// Pool backing 1000 shares at 60 per share (price 60_000_000)
+    fn pool(market: Pubkey) -> LpPool {
+        let mut pool = LpPool::new(Pubkey::default(), market, 255);
+        pool.total_assets = 60_000;
+        pool.total_shares = 1000;
+        pool
+    }
+
     #[test]
     fn test_burn_all_shares() {
         let mut portfolio = Portfolio::new(Pubkey::default(), Pubkey::default(), 0);
@@ -202,6 +205,7 @@ mod tests {
 
         // Add AMM LP bucket with 1000 shares
         let market = Pubkey::from([1; 32]);
+        let mut pool = pool(market);
         let venue_id = VenueId::new_amm(market);
         let mut bucket = LpBucket::new_amm(venue_id, 1000, 60_000_000, 100);
         bucket.update_margin(10_000, 5_000);
@@ -211,17 +215,16 @@ mod tests {
         // Redemption = 1000 * 60 = 60_000 (in base units)
         let result = process_burn_lp_shares(
             &mut portfolio,
-            market,
+            &mut pool,
             1000,
-            60_000_000,
-            150,
-            60,
+            150, // clock_ts
         );
 
-        assert!(result.is_ok());
+        assert_eq!(result, Ok(60_000));
 
-        // Bucket should be removed
+        // Bucket should be removed, and the pool paid out in full
         assert_eq!(portfolio.lp_bucket_count, 0);
+        assert_eq!((pool.total_assets, pool.total_shares), (0, 0));
 
         // Equity should increase by redemption value
         // shares * price / 1e6 = 1000 * 60_000_000 / 1_000_000 = 60_000
@@ -235,6 +238,7 @@ mod tests {
         portfolio.update_equity(100_000);
 
         let market = Pubkey::from([1; 32]);
+        let mut pool = pool(market);
         let venue_id = VenueId::new_amm(market);
         let mut bucket = LpBucket::new_amm(venue_id, 1000, 60_000_000, 100);
         bucket.update_margin(10_000, 5_000);
@@ -243,14 +247,13 @@ mod tests {
         // Burn 300 out of 1000 shares
         let result = process_burn_lp_shares(
             &mut portfolio,
-            market,
+            &mut pool,
             300,
-            60_000_000,
-            150,
-            60,
+            150, // clock_ts
         );
 
-        assert!(result.is_ok());
+        assert_eq!(result, Ok(18_000));
+        assert_eq!((pool.total_assets, pool.total_shares), (42_000, 700));
 
         // Bucket should still exist
         assert_eq!(portfolio.lp_bucket_count, 1);
@@ -270,27 +273,23 @@ mod tests {
     }
 
     #[test]
-    fn test_reject_stale_price() {
+    fn test_idle_bucket_burns_at_pool_price() {
         let mut portfolio = Portfolio::new(Pubkey::default(), Pubkey::default(), 0);
 
         let market = Pubkey::from([1; 32]);
+        let mut pool = pool(market);
         let venue_id = VenueId::new_amm(market);
-        let bucket = LpBucket::new_amm(venue_id, 1000, 60_000_000, 100); // last_update_ts = 100
+        // Cached at a stale 1.0 per share, last touched at ts 100
+        let bucket = LpBucket::new_amm(venue_id, 1000, 1_000_000, 100);
         assert!(portfolio.add_lp_bucket(bucket).is_ok());
 
-        // Try to burn with current_ts = 161 (61 seconds later, exceeds 60s max)
-        let result = process_burn_lp_shares(
-            &mut portfolio,
-            market,
-            100,
-            60_000_000,
-            161,
-            60,
-        );
+        // A day later the pool's NAV prices the burn, not the cached value
+        let result = process_burn_lp_shares(&mut portfolio, &mut pool, 100, 86_500);
+        assert_eq!(result, Ok(6_000));
 
-        // Should fail due to stale price
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), PercolatorError::StalePrice);
+        let amm = portfolio.find_lp_bucket(&venue_id).unwrap().amm.unwrap();
+        assert_eq!(amm.share_price_cached, 60_000_000);
+        assert_eq!(amm.last_update_ts, 86_500);
     }
 
     #[test]
@@ -298,6 +297,7 @@ mod tests {
         let mut portfolio = Portfolio::new(Pubkey::default(), Pubkey::default(), 0);
 
         let market = Pubkey::from([1; 32]);
+        let mut pool = pool(market);
         let venue_id = VenueId::new_amm(market);
         let bucket = LpBucket::new_amm(venue_id, 1000, 60_000_000, 100);
         assert!(portfolio.add_lp_bucket(bucket).is_ok());
@@ -305,15 +305,20 @@ mod tests {
         // Try to burn 1001 shares (more than owned)
         let result = process_burn_lp_shares(
             &mut portfolio,
-            market,
+            &mut pool,
             1001,
-            60_000_000,
-            150,
-            60,
+            150, // clock_ts
         );
 
         assert!(result.is_err());
         assert_eq!(result.unwrap_err(), PercolatorError::InsufficientBalance);
+
+        // Nor more than the pool ever issued, whatever the bucket claims
+        pool.total_shares = 500;
+        assert_eq!(
+            process_burn_lp_shares(&mut portfolio, &mut pool, 600, 150),
+            Err(PercolatorError::InsufficientBalance)
+        );
     }
 
     #[test]
@@ -321,6 +326,7 @@ mod tests {
         let mut portfolio = Portfolio::new(Pubkey::default(), Pubkey::default(), 0);
 
         let market = Pubkey::from([1; 32]);
+        let mut pool = pool(market);
         let venue_id = VenueId::new_amm(market);
         let bucket = LpBucket::new_amm(venue_id, 1000, 60_000_000, 100);
         assert!(portfolio.add_lp_bucket(bucket).is_ok());
@@ -328,11 +334,9 @@ mod tests {
         // Try to burn 0 shares
         let result = process_burn_lp_shares(
             &mut portfolio,
-            market,
+            &mut pool,
             0,
-            60_000_000,
-            150,
-            60,
+            150, // clock_ts
         );
 
         assert!(result.is_err());
@@ -344,6 +348,7 @@ mod tests {
         let mut portfolio = Portfolio::new(Pubkey::default(), Pubkey::default(), 0);
 
         let market = Pubkey::from([1; 32]);
+        let mut pool = pool(market);
         let venue_id = VenueId::new_slab(market);
         let bucket = LpBucket::new_slab(venue_id);
         assert!(portfolio.add_lp_bucket(bucket).is_ok());
@@ -351,11 +356,9 @@ mod tests {
         // Try to burn shares from Slab bucket (should fail)
         let result = process_burn_lp_shares(
             &mut portfolio,
-            market,
+            &mut pool,
             100,
-            60_000_000,
-            150,
-            60,
+            150, // clock_ts
         );
 
         // Should fail - can't burn shares from Slab bucket
@@ -21,27 +21,23 @@ use pinocchio::msg;
 /// * `market_id` - Slab market pubkey
 /// * `order_ids` - Array of order IDs to cancel
 /// * `order_count` - Number of orders to cancel
-/// * `freed_quote` - Total quote freed by cancellations
-/// * `freed_base` - Total base freed by cancellations
 ///
 /// # Returns
 /// * Updates portfolio:
 ///   - Removes order IDs from bucket
-///   - Reduces reserved_quote and reserved_base
+///   - Reduces reserved_quote and reserved_base by what each order reserved
 ///   - Reduces bucket margin proportionally
 ///   - If no orders remain, removes bucket entirely
 ///
 /// # Safety
-/// * Enforces exact reservation accounting
-/// * Verifies all order IDs exist before canceling
+/// * Freed amounts come from the bucket's per-order reservations, never the caller
+/// * Verifies all order IDs exist before canceling any (all-or-nothing)
 /// * Maintains proportional margin reduction
 pub fn process_cancel_lp_orders(
     portfolio: &mut Portfolio,
     market_id: pinocchio::pubkey::Pubkey,
     order_ids: &[u64],
     order_count: usize,
-    freed_quote: u128,
-    freed_base: u128,
 ) -> Result<(), PercolatorError> {
     msg!("CancelLpOrders: Starting");
 
@@ -86,64 +82,41 @@ pub fn process_cancel_lp_orders(
     let initial_im = bucket.im;
     let initial_mm = bucket.mm;
 
-    // Remove each order
-    for i in 0..order_count.min(order_ids.len()) {
-        let order_id = order_ids[i];
-
-        // Find and remove order (note: we don't need to know the exact quote/base per order
-        // since the caller provides the total freed amounts)
-        let mut found = false;
-        for j in 0..slab.open_order_count as usize {
-            if slab.open_order_ids[j] == order_id {
-                // Remove this order ID (swap with last)
-                let last_idx = (slab.open_order_count - 1) as usize;
-                if j != last_idx {
-                    slab.open_order_ids[j] = slab.open_order_ids[last_idx];
-                }
-                slab.open_order_ids[last_idx] = 0;
-                slab.open_order_count -= 1;
-                found = true;
-                break;
-            }
-        }
+    let to_cancel = &order_ids[..order_count.min(order_ids.len())];
 
-        if !found {
+    // SAFETY TRIPWIRE 1: Every order must be reserved, and named only once,
+    // before anything is freed
+    for (i, order_id) in to_cancel.iter().enumerate() {
+        if slab.find_order(*order_id).is_none() || to_cancel[..i].contains(order_id) {
             msg!("Error: Order ID not found");
-            return Err(PercolatorError::InvalidAccount);
+            return Err(PercolatorError::OrderNotFound);
         }
     }
 
-    msg!("CancelLpOrders: Removed orders");
-
-    // SAFETY TRIPWIRE 1: Exact reservation accounting
-    // Verify freed amounts don't exceed current reservations
-    if freed_quote > slab.reserved_quote {
-        msg!("Error: Freed quote exceeds reserved");
-        return Err(PercolatorError::InvalidAmount);
-    }
-
-    if freed_base > slab.reserved_base {
-        msg!("Error: Freed base exceeds reserved");
-        return Err(PercolatorError::InvalidAmount);
+    // Remove each order, freeing exactly what it reserved
+    let mut freed_quote: u128 = 0;
+    let mut freed_base: u128 = 0;
+    for order_id in to_cancel {
+        let (quote, base) = slab
+            .remove_reservation(*order_id)
+            .map_err(|_| PercolatorError::OrderNotFound)?;
+        freed_quote = freed_quote.saturating_add(quote);
+        freed_base = freed_base.saturating_add(base);
     }
 
-    // Update reservations
-    slab.reserved_quote = slab.reserved_quote.saturating_sub(freed_quote);
-    slab.reserved_base = slab.reserved_base.saturating_sub(freed_base);
-
-    msg!("CancelLpOrders: Updated reservations");
+    msg!("CancelLpOrders: Removed orders and freed reservations");
 
     // Calculate proportional margin reduction
     // We use the maximum of (quote_freed / initial_quote, base_freed / initial_base)
     // to determine the reduction ratio
     let quote_ratio = if initial_reserved_quote > 0 {
-        ((initial_reserved_quote - slab.reserved_quote) as u128 * 1_000_000) / (initial_reserved_quote as u128)
+        (freed_quote * 1_000_000) / (initial_reserved_quote as u128)
     } else {
         0
     };
 
     let base_ratio = if initial_reserved_base > 0 {
-        ((initial_reserved_base - slab.reserved_base) as u128 * 1_000_000) / (initial_reserved_base as u128)
+        (freed_base * 1_000_000) / (initial_reserved_base as u128)
     } else {
         0
     };
@@ -236,8 +209,6 @@ mod tests {
             market,
             &order_ids,
             3,
-            4500,  // Total freed quote
-            2250,  // Total freed base
         );
 
         assert!(result.is_ok());
@@ -271,8 +242,6 @@ mod tests {
             market,
             &order_ids,
             1,
-            2000,  // Freed quote
-            1000,  // Freed base
         );
 
         assert!(result.is_ok());
@@ -301,7 +270,7 @@ mod tests {
     }
 
     #[test]
-    fn test_reject_freed_exceeds_reserved() {
+    fn test_cancel_two_orders_frees_what_was_reserved() {
         let mut portfolio = Portfolio::new(Pubkey::default(), Pubkey::default(), 0);
 
         let market = Pubkey::from([1; 32]);
@@ -310,24 +279,24 @@ mod tests {
 
         if let Some(ref mut slab) = bucket.slab {
             assert!(slab.add_reservation(1001, 1000, 500).is_ok());
+            assert!(slab.add_reservation(1002, 2000, 1000).is_ok());
+            assert!(slab.add_reservation(1003, 1500, 750).is_ok());
         }
 
         assert!(portfolio.add_lp_bucket(bucket).is_ok());
 
-        // Try to free more than reserved
-        let order_ids = [1001];
-        let result = process_cancel_lp_orders(
-            &mut portfolio,
-            market,
-            &order_ids,
-            1,
-            2000,  // More than 1000 reserved
-            500,
-        );
+        // Cancel 1001 and 1003: frees 1000 + 1500 quote, 500 + 750 base
+        let order_ids = [1003, 1001];
+        let result = process_cancel_lp_orders(&mut portfolio, market, &order_ids, 2);
+        assert!(result.is_ok());
 
-        // Should fail
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), PercolatorError::InvalidAmount);
+        let slab = portfolio.find_lp_bucket(&venue_id).unwrap().slab.unwrap();
+        assert_eq!(slab.open_order_count, 1);
+        assert_eq!(slab.open_order_ids[0], 1002);
+
+        // Only order 1002's reservation remains
+        assert_eq!(slab.reserved_quote, 2000);
+        assert_eq!(slab.reserved_base, 1000);
     }
 
     #[test]
@@ -351,13 +320,13 @@ mod tests {
             market,
             &order_ids,
             1,
-            1000,
-            500,
         );
 
-        // Should fail
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), PercolatorError::InvalidAccount);
+        // Should fail without touching the reservation
+        assert_eq!(result, Err(PercolatorError::OrderNotFound));
+        let slab = portfolio.find_lp_bucket(&venue_id).unwrap().slab.unwrap();
+        assert_eq!(slab.open_order_count, 1);
+        assert_eq!(slab.reserved_quote, 1000);
     }
 
     #[test]
@@ -376,8 +345,6 @@ mod tests {
             market,
             &order_ids,
             0,
-            0,
-            0,
         );
 
         // Should fail
@@ -401,11 +368,38 @@ mod tests {
             market,
             &order_ids,
             1,
-            1000,
-            500,
         );
 
         // Should fail - can't cancel orders from AMM bucket
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_bogus_id_aborts_whole_cancel() {
+        let mut portfolio = Portfolio::new(Pubkey::default(), Pubkey::default(), 0);
+
+        let market = Pubkey::from([1; 32]);
+        let venue_id = VenueId::new_slab(market);
+        let mut bucket = LpBucket::new_slab(venue_id);
+
+        if let Some(ref mut slab) = bucket.slab {
+            assert!(slab.add_reservation(1001, 1000, 500).is_ok());
+            assert!(slab.add_reservation(1002, 2000, 1000).is_ok());
+        }
+
+        assert!(portfolio.add_lp_bucket(bucket).is_ok());
+
+        // A real order followed by a bogus one frees nothing
+        let result = process_cancel_lp_orders(&mut portfolio, market, &[1001, 4242], 2);
+        assert_eq!(result, Err(PercolatorError::OrderNotFound));
+
+        // Naming the same order twice can't double-free it
+        let result = process_cancel_lp_orders(&mut portfolio, market, &[1001, 1001], 2);
+        assert_eq!(result, Err(PercolatorError::OrderNotFound));
+
+        let slab = portfolio.find_lp_bucket(&venue_id).unwrap().slab.unwrap();
+        assert_eq!(slab.open_order_count, 2);
+        assert_eq!(slab.reserved_quote, 3000);
+        assert_eq!(slab.reserved_base, 1500);
+    }
 }
@@ -0,0 +1,255 @@
+//! Close all positions - market-close every open exposure atomically
+//!
+//! Convenience wrapper over execute_cross_slab: builds one reducing split per
+//! open position at the current oracle price, so every close goes through the
+//! same reduce/PDA-close logic and PnL is settled once for the whole batch.
+
+use crate::instructions::{process_execute_cross_slab, read_slab_price, verify_slab_oracle, SlabSplit};
+use crate::state::{Portfolio, SlabRegistry};
+use percolator_common::*;
+use pinocchio::{account_info::AccountInfo, msg, pubkey::Pubkey};
+
+/// Maximum positions closable in one transaction (matches ExecuteCrossSlab split limit)
+pub const MAX_CLOSE_ALL_POSITIONS: usize = 8;
+
+/// Build one closing split per open position
+///
+/// `slabs` pairs each passed slab account with its registry index, in account
+/// order. Every open exposure in the portfolio must be covered exactly once.
+///
+/// # Returns
+/// * Number of splits written to `out`
+pub(crate) fn build_close_all_splits(
+    portfolio: &Portfolio,
+    slabs: &[(Pubkey, u16)],
+    oracle_prices: &[i64],
+    out: &mut [SlabSplit],
+) -> Result<usize, PercolatorError> {
+    if slabs.is_empty() || slabs.len() > out.len() || oracle_prices.len() != slabs.len() {
+        msg!("Error: Invalid close-all account count");
+        return Err(PercolatorError::InvalidInstruction);
+    }
+
+    if portfolio.exposure_count as usize != slabs.len() {
+        msg!("Error: Must pass every open position");
+        return Err(PercolatorError::InvalidInstruction);
+    }
+
+    for (i, (slab_id, slab_idx)) in slabs.iter().enumerate() {
+        // Reject duplicates so one position can't be closed twice
+        if slabs[..i].iter().any(|(_, idx)| idx == slab_idx) {
+            msg!("Error: Duplicate slab in close-all");
+            return Err(PercolatorError::InvalidInstruction);
+        }
+
+        let exposure = portfolio.get_exposure(*slab_idx, 0);
+        if exposure == 0 {
+            msg!("Error: No open position on slab");
+            return Err(PercolatorError::PositionNotFound);
+        }
+
+        out[i] = SlabSplit {
+            slab_id: *slab_id,
+            qty: exposure.abs(),
+            side: if exposure > 0 { 1 } else { 0 }, // Sell longs, buy back shorts
+            limit_px: oracle_prices[i],
+        };
+    }
+
+    Ok(slabs.len())
+}
+
+/// Process close all instruction
+///
+/// Market-closes every open exposure at oracle price in one atomic
+/// transaction. Each position goes through execute_cross_slab's reduce path,
+/// which returns its margin and closes its PositionDetails PDA (refunding rent
+/// to the user); realized PnL across all positions is settled once.
+///
+/// # Arguments
+/// * `user_portfolio_account` - User's portfolio account (holds SOL)
+/// * `user_portfolio` - User's portfolio state
+/// * `user_account` - User pubkey (signer, receives PDA rent)
+/// * `dlp_portfolio_account` - DLP's portfolio account (counterparty)
+/// * `dlp_portfolio` - DLP's portfolio state
+/// * `registry` - Slab registry
+/// * `router_authority` - Router authority PDA (for CPI signing)
+/// * `system_program` - System program for SOL transfers
+/// * `slab_program` - Slab program (for CPI)
+/// * `slab_accounts` - One slab account per open position
+/// * `receipt_accounts` - One receipt account per slab
+/// * `oracle_accounts` - One oracle account per slab
+/// * `position_details_accounts` - One PositionDetails PDA per slab
+pub fn process_close_all(
+    user_portfolio_account: &AccountInfo,
+    user_portfolio: &mut Portfolio,
+    user_account: &AccountInfo,
+    dlp_portfolio_account: &AccountInfo,
+    dlp_portfolio: &mut Portfolio,
+    registry: &mut SlabRegistry,
+    router_authority: &AccountInfo,
+    system_program: &AccountInfo,
+    slab_program: &AccountInfo,
+    slab_accounts: &[AccountInfo],
+    receipt_accounts: &[AccountInfo],
+    oracle_accounts: &[AccountInfo],
+    position_details_accounts: &[AccountInfo],
+    program_id: &Pubkey,
+) -> Result<(), PercolatorError> {
+    let count = slab_accounts.len();
+    if count == 0 || count > MAX_CLOSE_ALL_POSITIONS || oracle_accounts.len() != count {
+        msg!("Error: Invalid close-all account count");
+        return Err(PercolatorError::InvalidInstruction);
+    }
+
+    // Resolve registry indices and current oracle prices
+    let mut slabs = [(Pubkey::default(), 0u16); MAX_CLOSE_ALL_POSITIONS];
+    let mut oracle_prices = [0i64; MAX_CLOSE_ALL_POSITIONS];
+    for i in 0..count {
+        let slab_id = slab_accounts[i].key();
+        let (slab_idx, _) = registry.find_slab(slab_id).ok_or_else(|| {
+            msg!("Error: Slab is not registered");
+            PercolatorError::SlabNotRegistered
+        })?;
+        slabs[i] = (*slab_id, slab_idx);
+        verify_slab_oracle(registry, slab_id, &oracle_accounts[i..i + 1])?;
+        oracle_prices[i] = read_slab_price(registry, slab_id, &oracle_accounts[i..i + 1])?;
+    }
+
+    let mut splits_buffer = [SlabSplit {
+        slab_id: Pubkey::default(),
+        qty: 0,
+        side: 0,
+        limit_px: 0,
+    }; MAX_CLOSE_ALL_POSITIONS];
+
+    let num_splits = build_close_all_splits(
+        user_portfolio,
+        &slabs[..count],
+        &oracle_prices[..count],
+        &mut splits_buffer,
+    )?;
+
+    msg!("CloseAll: Closing all open positions");
+
+    process_execute_cross_slab(
+        user_portfolio_account,
+        user_portfolio,
+        user_account,
+        user_account, // Closing never opens a position, so no rent is due
+        dlp_portfolio_account,
+        dlp_portfolio,
+        registry,
+        router_authority,
+        system_program,
+        slab_program,
+        slab_accounts,
+        receipt_accounts,
+        oracle_accounts,
+        position_details_accounts,
+        &[], // Every open position is one of the splits
+        &splits_buffer[..num_splits],
+        0, // Market order at oracle price
+        1, // One oracle per slab
+        1, // Leverage is ignored when reducing
+        0, // Default slippage (limit_px is the oracle price just read)
+        true, // Closing splits exactly offset each position
+        true, // Close whatever has liquidity rather than nothing
+        true, // A closed market can't be traded out of either
+        false, // A voluntary close pays the taker fee
+        program_id,
+    )?;
+
+    msg!("CloseAll: All positions closed");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::PositionDetails;
+
+    const SCALE: i64 = 1_000_000;
+
+    fn empty_splits() -> [SlabSplit; MAX_CLOSE_ALL_POSITIONS] {
+        [SlabSplit { slab_id: Pubkey::default(), qty: 0, side: 0, limit_px: 0 }; MAX_CLOSE_ALL_POSITIONS]
+    }
+
+    #[test]
+    fn test_close_three_positions_single_settlement() {
+        let mut portfolio = Portfolio::new(Pubkey::default(), Pubkey::default(), 0);
+        let slabs = [
+            (Pubkey::from([1; 32]), 0u16),
+            (Pubkey::from([2; 32]), 1u16),
+            (Pubkey::from([3; 32]), 2u16),
+        ];
+
+        // Open: long 2 @ $100, short 1 @ $50, long 3 @ $10
+        let opens = [(2 * SCALE, 100 * SCALE), (-SCALE, 50 * SCALE), (3 * SCALE, 10 * SCALE)];
+        let mut positions = [PositionDetails::new(Pubkey::default(), 0, 0, 0, 0, 0, 0, 0, 1); 3];
+        for (i, (qty, px)) in opens.iter().enumerate() {
+            portfolio.update_exposure(slabs[i].1, 0, *qty);
+            positions[i] = PositionDetails::new(Pubkey::default(), slabs[i].1, 0, *px, *qty, 0, 0, 0, 1);
+        }
+
+        // Close at $110, $55, $9 (1 SOL contracts: lamports = qty * diff / exit_px * 1000)
+        let oracle_prices = [110 * SCALE, 55 * SCALE, 9 * SCALE];
+        let mut splits = empty_splits();
+        let n = build_close_all_splits(&portfolio, &slabs, &oracle_prices, &mut splits).unwrap();
+        assert_eq!(n, 3);
+
+        assert_eq!((splits[0].side, splits[0].qty), (1, 2 * SCALE));
+        assert_eq!((splits[1].side, splits[1].qty), (0, SCALE));
+        assert_eq!((splits[2].side, splits[2].qty), (1, 3 * SCALE));
+
+        // Replay the reduce path per position and aggregate into one settlement
+        let mut total_realized_pnl: i128 = 0;
+        for i in 0..n {
+            let signed_qty = splits[i].signed_qty();
+            let (pnl, remaining, _) = positions[i].reduce_position(splits[i].limit_px, signed_qty, 0, 1);
+            assert_eq!(remaining, 0, "Position {} should be fully closed", i);
+
+            portfolio.update_exposure(slabs[i].1, 0, portfolio.get_exposure(slabs[i].1, 0) + signed_qty);
+            total_realized_pnl += pnl_to_lamports(pnl, SCALE, InstrumentScale::DEFAULT);
+        }
+
+        assert_eq!(portfolio.exposure_count, 0);
+        // +181_818_000 (long win) - 90_909_000 (short loss) - 333_333_000 (long loss)
+        assert_eq!(total_realized_pnl, -242_424_000);
+    }
+
+    #[test]
+    fn test_missing_position_rejected() {
+        let mut portfolio = Portfolio::new(Pubkey::default(), Pubkey::default(), 0);
+        portfolio.update_exposure(0, 0, SCALE);
+        portfolio.update_exposure(1, 0, -SCALE);
+
+        // Only one of two open positions supplied
+        let slabs = [(Pubkey::from([1; 32]), 0u16)];
+        let mut splits = empty_splits();
+        assert_eq!(
+            build_close_all_splits(&portfolio, &slabs, &[100 * SCALE], &mut splits),
+            Err(PercolatorError::InvalidInstruction)
+        );
+    }
+
+    #[test]
+    fn test_flat_or_duplicate_slab_rejected() {
+        let mut portfolio = Portfolio::new(Pubkey::default(), Pubkey::default(), 0);
+        portfolio.update_exposure(0, 0, SCALE);
+        portfolio.update_exposure(1, 0, SCALE);
+        let mut splits = empty_splits();
+
+        let dup = [(Pubkey::from([1; 32]), 0u16), (Pubkey::from([1; 32]), 0u16)];
+        assert_eq!(
+            build_close_all_splits(&portfolio, &dup, &[SCALE, SCALE], &mut splits),
+            Err(PercolatorError::InvalidInstruction)
+        );
+
+        let flat = [(Pubkey::from([1; 32]), 0u16), (Pubkey::from([9; 32]), 9u16)];
+        assert_eq!(
+            build_close_all_splits(&portfolio, &flat, &[SCALE, SCALE], &mut splits),
+            Err(PercolatorError::PositionNotFound)
+        );
+    }
+}
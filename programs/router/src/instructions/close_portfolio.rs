@@ -0,0 +1,133 @@
+//! Close portfolio - full exit that returns every lamport to the user
+//!
+//! Withdraw always leaves the portfolio rent-exempt so the runtime never
+//! reaps it mid-use. Leaving for good goes through here instead: once
+//! nothing is open and all equity is withdrawable, the balance including
+//! the rent reserve goes to the user, the data is zeroed and the account
+//! is deallocated. The user can InitializePortfolio again later.
+
+use crate::state::{Portfolio, SlabRegistry};
+use percolator_common::*;
+use pinocchio::{account_info::AccountInfo, msg};
+
+/// Check a portfolio holds nothing that closing would abandon
+///
+/// Open exposures and LP shares fail with PortfolioNotEmpty, and a pending
+/// withdrawal request must be settled first. Equity must be non-negative (losses are never walked
+/// away from) and fully withdrawable, so closing can't skip PnL warmup.
+pub(crate) fn check_can_close(
+    portfolio: &Portfolio,
+    unlocked_frac: model_safety::adaptive_warmup::I,
+) -> Result<(), PercolatorError> {
+    if portfolio.exposure_count != 0 || portfolio.lp_bucket_count != 0 {
+        msg!("Error: Close all positions and LP shares before closing the portfolio");
+        return Err(PercolatorError::PortfolioNotEmpty);
+    }
+    if portfolio.pending_withdrawal != 0 {
+        msg!("Error: A withdrawal request is still pending");
+        return Err(PercolatorError::WithdrawalPending);
+    }
+    if portfolio.equity < 0 || portfolio.equity > portfolio.max_withdrawable_with_warmup(unlocked_frac) {
+        msg!("Error: Portfolio equity is negative or not yet withdrawable");
+        return Err(PercolatorError::InsufficientFunds);
+    }
+    Ok(())
+}
+
+/// Process close portfolio instruction
+///
+/// # Arguments
+/// * `portfolio_account` - The user's portfolio account (deallocated)
+/// * `user_account` - The portfolio's user (signer, receives every lamport)
+/// * `registry` - The registry account (TVL tracking and warmup state)
+///
+/// # Returns
+/// * Lamports returned to the user
+pub fn process_close_portfolio(
+    portfolio_account: &AccountInfo,
+    user_account: &AccountInfo,
+    registry: &mut SlabRegistry,
+) -> Result<u64, PercolatorError> {
+    if !user_account.is_signer() {
+        msg!("Error: User must be a signer");
+        return Err(PercolatorError::Unauthorized);
+    }
+
+    {
+        let portfolio = unsafe { borrow_account_data::<Portfolio>(portfolio_account)? };
+        if portfolio.user != *user_account.key() {
+            msg!("Error: Portfolio does not belong to user");
+            return Err(PercolatorError::Unauthorized);
+        }
+        check_can_close(portfolio, registry.warmup_state.unlocked_frac)?;
+        registry.track_withdrawal(portfolio.equity);
+    }
+
+    let lamports = portfolio_account.lamports();
+    let user_balance = user_account
+        .lamports()
+        .checked_add(lamports)
+        .ok_or(PercolatorError::Overflow)?;
+    *portfolio_account
+        .try_borrow_mut_lamports()
+        .map_err(|_| PercolatorError::InvalidAccount)? = 0;
+    *user_account
+        .try_borrow_mut_lamports()
+        .map_err(|_| PercolatorError::InvalidAccount)? = user_balance;
+
+    portfolio_account
+        .try_borrow_mut_data()
+        .map_err(|_| PercolatorError::InvalidAccount)?
+        .fill(0);
+
+    // Hand the account back to the system program with no data
+    portfolio_account.close().map_err(|_| PercolatorError::InvalidAccount)?;
+
+    msg!("ClosePortfolio: Portfolio closed");
+    Ok(lamports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pinocchio::pubkey::Pubkey;
+
+    const ALL_UNLOCKED: model_safety::adaptive_warmup::I = model_safety::adaptive_warmup::F;
+
+    fn idle_portfolio() -> Portfolio {
+        let mut portfolio = Portfolio::new(Pubkey::default(), [1; 32], 0);
+        portfolio.principal = 2_000_000_000;
+        portfolio.equity = 2_000_000_000;
+        portfolio
+    }
+
+    #[test]
+    fn test_idle_portfolio_can_close() {
+        assert_eq!(check_can_close(&idle_portfolio(), ALL_UNLOCKED), Ok(()));
+    }
+
+    #[test]
+    fn test_open_state_blocks_close() {
+        let mut portfolio = idle_portfolio();
+        portfolio.update_exposure(0, 0, 1_000_000);
+        assert_eq!(check_can_close(&portfolio, ALL_UNLOCKED), Err(PercolatorError::PortfolioNotEmpty));
+
+        let mut portfolio = idle_portfolio();
+        portfolio.lp_bucket_count = 1;
+        assert_eq!(check_can_close(&portfolio, ALL_UNLOCKED), Err(PercolatorError::PortfolioNotEmpty));
+
+        let mut portfolio = idle_portfolio();
+        portfolio.pending_withdrawal = 1;
+        assert_eq!(check_can_close(&portfolio, ALL_UNLOCKED), Err(PercolatorError::WithdrawalPending));
+
+        // Unvested PnL can't leave early through a close
+        let mut portfolio = idle_portfolio();
+        portfolio.pnl = 500_000_000;
+        portfolio.equity += 500_000_000;
+        assert_eq!(check_can_close(&portfolio, ALL_UNLOCKED), Err(PercolatorError::InsufficientFunds));
+
+        let mut portfolio = idle_portfolio();
+        portfolio.equity = -1;
+        assert_eq!(check_can_close(&portfolio, ALL_UNLOCKED), Err(PercolatorError::InsufficientFunds));
+    }
+}
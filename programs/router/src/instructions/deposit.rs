@@ -1,6 +1,6 @@
 //! Deposit instruction - deposit SOL collateral to portfolio
 
-use crate::state::Portfolio;
+use crate::state::{Portfolio, SlabRegistry};
 use percolator_common::*;
 use pinocchio::{
     account_info::AccountInfo,
@@ -10,6 +10,32 @@ use pinocchio::{
     ProgramResult,
 };
 
+/// Reject a deposit that would breach the registry's deposit caps
+///
+/// The per-portfolio cap bounds the portfolio's principal; the global cap
+/// bounds the registry's total_deposits. A cap of 0 is no cap.
+pub(crate) fn check_deposit_caps(
+    portfolio: &Portfolio,
+    registry: &SlabRegistry,
+    amount: u64,
+) -> Result<(), PercolatorError> {
+    let amount = amount as i128;
+
+    if registry.max_portfolio_deposit != 0
+        && portfolio.principal.saturating_add(amount) > registry.max_portfolio_deposit as i128
+    {
+        msg!("Error: Deposit would exceed the per-portfolio cap");
+        return Err(PercolatorError::DepositCapExceeded);
+    }
+    if registry.max_total_deposits != 0
+        && registry.total_deposits.saturating_add(amount) > registry.max_total_deposits as i128
+    {
+        msg!("Error: Deposit would exceed the global TVL cap");
+        return Err(PercolatorError::DepositCapExceeded);
+    }
+    Ok(())
+}
+
 /// Process deposit instruction (SOL only for MVP)
 ///
 /// Deposits SOL from user's wallet to their portfolio account.
@@ -19,18 +45,21 @@ use pinocchio::{
 /// - Verifies user is a signer
 /// - Verifies portfolio belongs to user
 /// - Validates deposit amount is non-zero
+/// - Enforces the per-portfolio and global deposit caps
 ///
 /// # Arguments
 /// * `portfolio_account` - The user's portfolio account (receives SOL)
 /// * `portfolio` - Mutable reference to portfolio state
 /// * `user_account` - The user's wallet account (sends SOL)
 /// * `_system_program` - The System Program account (unused for direct transfer)
+/// * `registry` - The registry account (deposit caps and TVL tracking)
 /// * `amount` - Amount of lamports to deposit
 pub fn process_deposit(
     portfolio_account: &AccountInfo,
     portfolio: &mut Portfolio,
     user_account: &AccountInfo,
     system_program: &AccountInfo,
+    registry: &mut SlabRegistry,
     amount: u64,
 ) -> ProgramResult {
     // SECURITY: Validate amount
@@ -51,6 +80,8 @@ pub fn process_deposit(
         return Err(PercolatorError::Unauthorized.into());
     }
 
+    check_deposit_caps(portfolio, registry, amount)?;
+
     // Transfer SOL from user to portfolio account using CPI to System Program
     // Build System Program transfer instruction
     // System transfer instruction: discriminator=2u32, data=amount as u64
@@ -94,7 +125,64 @@ pub fn process_deposit(
         .checked_add(amount_i128)
         .ok_or(PercolatorError::Overflow)?;
 
+    registry.track_deposit(amount_i128);
+
     msg!("Deposit successful");
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pinocchio::pubkey::Pubkey;
+
+    const SOL: u64 = 1_000_000_000;
+
+    fn setup() -> (Portfolio, SlabRegistry) {
+        let mut portfolio = Portfolio::new(Pubkey::default(), [1; 32], 0);
+        portfolio.principal = 40 * SOL as i128;
+        let mut registry = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+        registry.max_portfolio_deposit = 50 * SOL;
+        registry.max_total_deposits = 1_000 * SOL;
+        registry.track_deposit(900 * SOL as i128);
+        (portfolio, registry)
+    }
+
+    #[test]
+    fn test_deposit_under_caps() {
+        let (portfolio, registry) = setup();
+        assert_eq!(check_deposit_caps(&portfolio, &registry, 10 * SOL), Ok(()));
+
+        // Uncapped registries accept anything
+        let uncapped = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+        assert_eq!(check_deposit_caps(&portfolio, &uncapped, 1_000_000 * SOL), Ok(()));
+    }
+
+    #[test]
+    fn test_deposit_over_portfolio_cap_rejected() {
+        let (portfolio, registry) = setup();
+        assert_eq!(
+            check_deposit_caps(&portfolio, &registry, 10 * SOL + 1),
+            Err(PercolatorError::DepositCapExceeded)
+        );
+    }
+
+    #[test]
+    fn test_deposit_over_global_cap_rejected() {
+        let (mut portfolio, mut registry) = setup();
+        portfolio.principal = 0;
+        registry.track_deposit(95 * SOL as i128);
+
+        // 5 SOL of TVL headroom left, well inside the portfolio cap
+        assert_eq!(check_deposit_caps(&portfolio, &registry, 5 * SOL), Ok(()));
+        assert_eq!(
+            check_deposit_caps(&portfolio, &registry, 5 * SOL + 1),
+            Err(PercolatorError::DepositCapExceeded)
+        );
+
+        // Withdrawals free the headroom back up
+        registry.track_withdrawal(20 * SOL as i128);
+        assert_eq!(check_deposit_caps(&portfolio, &registry, 20 * SOL), Ok(()));
+    }
+}
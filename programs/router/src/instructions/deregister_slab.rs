@@ -0,0 +1,108 @@
+//! Deregister slab - governance retires a market
+//!
+//! Tombstones the slab's registry entry (see SlabRegistry::tombstone_slab),
+//! so new fills on it fail with SlabNotRegistered. Only a slab with no open
+//! interest on either side can be delisted; positions must be closed first,
+//! so no account is left holding exposure on a retired market.
+
+use crate::state::SlabRegistry;
+use percolator_common::*;
+use pinocchio::{msg, pubkey::Pubkey};
+
+/// Process deregister slab instruction
+///
+/// # Arguments
+/// * `registry` - Slab registry (mutable)
+/// * `authority` - Signer's pubkey (must be registry governance)
+/// * `slab_id` - Slab to delist
+///
+/// # Returns
+/// * The retired slab index
+pub fn process_deregister_slab(
+    registry: &mut SlabRegistry,
+    authority: &Pubkey,
+    slab_id: &Pubkey,
+) -> Result<u16, PercolatorError> {
+    if authority != &registry.governance {
+        msg!("Error: Only governance can deregister slabs");
+        return Err(PercolatorError::Unauthorized);
+    }
+
+    let (_, entry) = registry.find_slab(slab_id).ok_or_else(|| {
+        msg!("Error: Slab is not registered");
+        PercolatorError::SlabNotRegistered
+    })?;
+
+    if entry.long_oi != 0 || entry.short_oi != 0 {
+        msg!("Error: Slab still has open interest");
+        return Err(PercolatorError::SlabHasOpenInterest);
+    }
+
+    let slab_idx = registry
+        .tombstone_slab(slab_id)
+        .map_err(|_| PercolatorError::SlabNotRegistered)?;
+
+    msg!("DeregisterSlab: Slab delisted");
+    Ok(slab_idx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instructions::update_open_interest;
+
+    const SCALE: i64 = 1_000_000;
+
+    fn registry_with_slab(slab_id: Pubkey) -> SlabRegistry {
+        let mut registry = SlabRegistry::new(Pubkey::default(), [9; 32], 0);
+        registry
+            .register_slab(slab_id, [0; 32], Pubkey::default(), 500, 250, 10, 20, 1000, u128::MAX, 10, 0)
+            .unwrap();
+        registry
+    }
+
+    #[test]
+    fn test_delist_empty_slab() {
+        let slab_id = [5; 32];
+        let mut registry = registry_with_slab(slab_id);
+
+        assert_eq!(
+            process_deregister_slab(&mut registry, &[1; 32], &slab_id),
+            Err(PercolatorError::Unauthorized)
+        );
+
+        assert_eq!(process_deregister_slab(&mut registry, &[9; 32], &slab_id), Ok(0));
+        assert!(registry.find_slab(&slab_id).is_none());
+        assert!(!registry.slabs[0].active);
+
+        // Already delisted
+        assert_eq!(
+            process_deregister_slab(&mut registry, &[9; 32], &slab_id),
+            Err(PercolatorError::SlabNotRegistered)
+        );
+    }
+
+    #[test]
+    fn test_refuse_delist_with_open_positions() {
+        let slab_id = [5; 32];
+        let mut registry = registry_with_slab(slab_id);
+
+        update_open_interest(&mut registry, 0, 0, 2 * SCALE).unwrap();
+        assert_eq!(
+            process_deregister_slab(&mut registry, &[9; 32], &slab_id),
+            Err(PercolatorError::SlabHasOpenInterest)
+        );
+
+        // A short left open still blocks it
+        update_open_interest(&mut registry, 0, 2 * SCALE, -SCALE).unwrap();
+        assert_eq!(
+            process_deregister_slab(&mut registry, &[9; 32], &slab_id),
+            Err(PercolatorError::SlabHasOpenInterest)
+        );
+        assert!(registry.find_slab(&slab_id).is_some());
+
+        // Once everyone is flat it can go
+        update_open_interest(&mut registry, 0, -SCALE, 0).unwrap();
+        assert_eq!(process_deregister_slab(&mut registry, &[9; 32], &slab_id), Ok(0));
+    }
+}
@@ -0,0 +1,138 @@
+//! Early vest - convert locked PnL to vested PnL for a penalty
+//!
+//! Optional escape hatch from the vesting schedule. The user chooses how much
+//! locked PnL to release now; a penalty cut of it is forfeited to the
+//! insurance fund and the remainder is marked vested immediately.
+
+use crate::state::{touch_portfolio, track_vested_change, Portfolio, SlabRegistry};
+use percolator_common::*;
+use pinocchio::msg;
+
+/// Penalty charged on early-vested PnL (basis points)
+pub const EARLY_VEST_PENALTY_BPS: u64 = 1_000; // 10%
+
+/// Penalty owed for early-vesting `amount` at `penalty_bps` (rounds up)
+///
+/// Rounding up keeps repeated small early-vests from dodging the penalty.
+pub fn early_vest_penalty(amount: u64, penalty_bps: u64) -> u128 {
+    (amount as u128 * penalty_bps as u128).div_ceil(10_000)
+}
+
+/// Process early vest instruction
+///
+/// # Arguments
+/// * `portfolio` - User's portfolio (mutable)
+/// * `registry` - Slab registry (vesting params, haircut index, insurance fund)
+/// * `amount` - Locked PnL to release (penalty is taken out of this)
+/// * `current_slot` - Current slot
+///
+/// # Returns
+/// * Penalty routed to the insurance fund
+/// * Updates portfolio:
+///   - Catches up haircut and vesting to `current_slot` first
+///   - Reduces pnl and equity by the penalty
+///   - Increases vested_pnl by `amount - penalty`
+pub fn process_early_vest(
+    portfolio: &mut Portfolio,
+    registry: &mut SlabRegistry,
+    amount: u64,
+    current_slot: u64,
+) -> Result<u128, PercolatorError> {
+    msg!("EarlyVest: Starting");
+
+    if amount == 0 {
+        msg!("Error: Cannot early-vest zero");
+        return Err(PercolatorError::InvalidAmount);
+    }
+
+    // Vest whatever is already due so the user isn't penalized on it
+    touch_portfolio(
+        portfolio,
+        &mut registry.global_haircut,
+        &registry.pnl_vesting_params,
+        current_slot,
+    );
+
+    let locked = (portfolio.pnl - portfolio.vested_pnl).max(0);
+    if amount as i128 > locked {
+        msg!("Error: Early-vest amount exceeds locked PnL");
+        return Err(PercolatorError::InsufficientBalance);
+    }
+
+    let penalty = early_vest_penalty(amount, EARLY_VEST_PENALTY_BPS);
+    let released = amount as i128 - penalty as i128;
+
+    portfolio.pnl = portfolio.pnl.saturating_sub(penalty as i128);
+    portfolio.equity = portfolio.equity.saturating_sub(penalty as i128);
+    let vested_before = portfolio.vested_pnl;
+    portfolio.vested_pnl = portfolio.vested_pnl.saturating_add(released);
+    track_vested_change(&mut registry.global_haircut, vested_before, portfolio.vested_pnl);
+
+    registry.insurance_state.top_up(penalty);
+
+    msg!("EarlyVest: Complete");
+
+    Ok(penalty)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{vesting_status, FP_ONE};
+    use pinocchio::pubkey::Pubkey;
+
+    fn setup() -> (Portfolio, SlabRegistry) {
+        let registry = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+        let mut portfolio = Portfolio::new(Pubkey::default(), Pubkey::default(), 0);
+        portfolio.principal = 100_000_000;
+        portfolio.pnl = 50_000_000;
+        portfolio.vested_pnl = 0;
+        portfolio.equity = 150_000_000;
+        portfolio.last_slot = 1000;
+        portfolio.pnl_index_checkpoint = FP_ONE;
+        (portfolio, registry)
+    }
+
+    #[test]
+    fn test_penalty_math() {
+        assert_eq!(early_vest_penalty(10_000_000, 1_000), 1_000_000);
+        assert_eq!(early_vest_penalty(10_000_000, 0), 0);
+        // 1 * 1000 / 10_000 rounds up to 1 rather than escaping the penalty
+        assert_eq!(early_vest_penalty(1, 1_000), 1);
+        assert_eq!(early_vest_penalty(u64::MAX, 10_000), u64::MAX as u128);
+    }
+
+    #[test]
+    fn test_early_vest_routes_penalty_to_insurance() {
+        let (mut portfolio, mut registry) = setup();
+        let insurance_before = registry.insurance_state.vault_balance;
+
+        // Same slot as last touch: everything is still locked
+        let penalty = process_early_vest(&mut portfolio, &mut registry, 20_000_000, 1000).unwrap();
+
+        assert_eq!(penalty, 2_000_000);
+        assert_eq!(portfolio.vested_pnl, 18_000_000);
+        assert_eq!(portfolio.pnl, 48_000_000);
+        assert_eq!(portfolio.equity, 148_000_000);
+        assert_eq!(registry.insurance_state.vault_balance, insurance_before + 2_000_000);
+
+        let (vested, locked, _) = vesting_status(&portfolio, &registry.pnl_vesting_params, 1000);
+        assert_eq!((vested, locked), (18_000_000, 30_000_000));
+    }
+
+    #[test]
+    fn test_reject_more_than_locked() {
+        let (mut portfolio, mut registry) = setup();
+
+        assert_eq!(
+            process_early_vest(&mut portfolio, &mut registry, 50_000_001, 1000),
+            Err(PercolatorError::InsufficientBalance)
+        );
+        assert_eq!(
+            process_early_vest(&mut portfolio, &mut registry, 0, 1000),
+            Err(PercolatorError::InvalidAmount)
+        );
+        assert_eq!(portfolio.pnl, 50_000_000);
+        assert_eq!(registry.insurance_state.vault_balance, 0);
+    }
+}
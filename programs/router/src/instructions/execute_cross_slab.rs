@@ -1,57 +1,243 @@
 //! Execute cross-slab order - v0 main instruction
 
-use crate::state::{Portfolio, SlabRegistry, PositionDetails, POSITION_DETAILS_SIZE};
-use crate::oracle::{OracleAdapter, CustomAdapter, PythAdapter};
+use crate::instructions::require_liquidator;
+use crate::state::{InsuranceParams, InsuranceState, Portfolio, SlabRegistry, PositionDetails, POSITION_DETAILS_SIZE};
+use crate::oracle::{OracleAdapter, TwapAdapter};
 use percolator_common::*;
+use percolator_common::oracle::read_price_1e6;
 use pinocchio::{account_info::AccountInfo, msg, pubkey::Pubkey, sysvars::{rent::Rent, Sysvar}};
 
-// TODO: Replace with actual Pyth program IDs for mainnet/devnet
-// - Mainnet: TBD
-// - Devnet: TBD
-// All Pyth price feed accounts (BTC/USD, ETH/USD, etc.) are owned by this program
-const PYTH_PROGRAM_ID: [u8; 32] = [
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-];
-
-/// Read oracle price using appropriate adapter (Custom or Pyth)
-/// Automatically detects oracle type by checking account owner
-fn read_oracle_price_unified(oracle_account: &AccountInfo) -> Result<i64, PercolatorError> {
-    let owner = oracle_account.owner();
-
-    // Check if Pyth oracle
-    if owner.as_ref() == &PYTH_PROGRAM_ID {
-        let adapter = PythAdapter::new();
-        let oracle_price = adapter.read_price(oracle_account)
-            .map_err(|_| {
-                msg!("Error: Pyth oracle read failed");
-                PercolatorError::InvalidOracle
-            })?;
-        return Ok(oracle_price.price); // Already scaled to 1e6
-    }
-
-    // Otherwise assume Custom oracle (localnet)
-    let adapter = CustomAdapter::new();
-    let oracle_price = adapter.read_price(oracle_account)
-        .map_err(|_| {
-            msg!("Error: Custom oracle read failed");
-            PercolatorError::InvalidOracle
-        })?;
-    Ok(oracle_price.price) // Already scaled to 1e6
+/// Maximum oracle feeds per instrument (median-of-N)
+pub const MAX_ORACLES_PER_SLAB: usize = 1 + crate::state::MAX_EXTRA_ORACLES;
+
+/// Accept 1, 3 or 5 oracle feeds per slab (median-of-N)
+pub(crate) fn check_oracles_per_slab(oracles_per_slab: usize) -> Result<(), PercolatorError> {
+    if !matches!(oracles_per_slab, 1 | 3 | 5) {
+        msg!("Error: oracles_per_slab must be 1, 3, or 5");
+        return Err(PercolatorError::InvalidOracle);
+    }
+    Ok(())
+}
+
+/// Reject a zero or negative oracle print before any math uses it
+///
+/// Margin, notional and PnL all divide by or scale with the price, so a
+/// glitched feed must fail the instruction rather than flow through.
+pub(crate) fn check_oracle_price(price: i64) -> Result<i64, PercolatorError> {
+    if price <= 0 {
+        msg!("Error: Non-positive oracle price");
+        return Err(PercolatorError::InvalidOraclePrice);
+    }
+    Ok(price)
+}
+
+/// Read a single oracle price using appropriate adapter (Custom, Pyth or Switchboard)
+/// Automatically detects oracle type by checking account owner (see
+/// percolator_common::oracle::read_price_1e6, shared with the slab)
+pub(crate) fn read_oracle_price_single(oracle_account: &AccountInfo) -> Result<i64, PercolatorError> {
+    use pinocchio::sysvars::clock::Clock;
+    let now_ts = Clock::get().map(|clock| clock.unix_timestamp).unwrap_or(0);
+
+    let oracle_price = read_price_1e6(oracle_account, now_ts).map_err(|_| {
+        msg!("Error: Oracle read failed");
+        PercolatorError::InvalidOracle
+    })?;
+    check_oracle_price(oracle_price.price) // Normalized to 1e6 from the feed's expo
+}
+
+/// Read the median price across 1, 3, or 5 oracle feeds for one instrument
+/// Rejects if the feeds disagree by more than max_divergence_bps
+fn read_oracle_price_unified(
+    oracle_accounts: &[AccountInfo],
+    max_divergence_bps: u64,
+) -> Result<i64, PercolatorError> {
+    if oracle_accounts.len() > MAX_ORACLES_PER_SLAB {
+        msg!("Error: Too many oracle feeds");
+        return Err(PercolatorError::InvalidOracle);
+    }
+
+    let mut prices = [0i64; MAX_ORACLES_PER_SLAB];
+    for (i, oracle_account) in oracle_accounts.iter().enumerate() {
+        prices[i] = read_oracle_price_single(oracle_account)?;
+    }
+    median_oracle_price(&mut prices[..oracle_accounts.len()], max_divergence_bps)
+}
+
+/// Median of an odd number of feed prices (1, 3, or 5)
+///
+/// Sorts `prices` in place. Fails with InvalidOraclePrice if any feed is
+/// zero or negative, and with OracleDivergence if (max - min) exceeds
+/// max_divergence_bps of the median.
+fn median_oracle_price(prices: &mut [i64], max_divergence_bps: u64) -> Result<i64, PercolatorError> {
+    if !matches!(prices.len(), 1 | 3 | 5) {
+        msg!("Error: Oracle count must be 1, 3, or 5");
+        return Err(PercolatorError::InvalidOracle);
+    }
+
+    // Insertion sort (at most 5 elements)
+    for i in 1..prices.len() {
+        let mut j = i;
+        while j > 0 && prices[j - 1] > prices[j] {
+            prices.swap(j - 1, j);
+            j -= 1;
+        }
+    }
+
+    // A glitched feed is rejected outright, not outvoted
+    for &price in prices.iter() {
+        check_oracle_price(price)?;
+    }
+
+    let median = prices[prices.len() / 2];
+
+    let spread = (prices[prices.len() - 1] as i128 - prices[0] as i128) as u128;
+    if spread * 10_000 > median as u128 * max_divergence_bps as u128 {
+        msg!("Error: Oracle feeds diverge");
+        return Err(PercolatorError::OracleDivergence);
+    }
+
+    Ok(median)
+}
+
+/// Maximum leverage accepted on any order
+pub const MAX_LEVERAGE: u8 = 10;
+
+/// Validate a per-order leverage multiplier (1..=MAX_LEVERAGE)
+pub(crate) fn validate_leverage(leverage: u8) -> Result<(), PercolatorError> {
+    if leverage == 0 || leverage > MAX_LEVERAGE {
+        msg!("Error: Invalid leverage (must be 1-10)");
+        return Err(PercolatorError::InvalidLeverage);
+    }
+    Ok(())
+}
+
+/// Default market order slippage when the user passes 0 (0.5%)
+const DEFAULT_MAX_SLIPPAGE_BPS: u64 = 50;
+
+/// Resolve the slippage tolerance for a market order
+/// User tolerance (0 = default) clamped to the registry's governance ceiling
+pub(crate) fn effective_slippage_bps(user_slippage_bps: u16, ceiling_bps: u64) -> u64 {
+    let requested = if user_slippage_bps == 0 {
+        DEFAULT_MAX_SLIPPAGE_BPS
+    } else {
+        user_slippage_bps as u64
+    };
+    requested.min(ceiling_bps)
+}
+
+/// Read a slab's reference price from its registry-selected source
+/// TWAP slabs pass their TwapBuffer accounts in the oracle slots
+pub(crate) fn read_slab_price(
+    registry: &SlabRegistry,
+    slab_id: &Pubkey,
+    oracle_accounts: &[AccountInfo],
+) -> Result<i64, PercolatorError> {
+    let use_twap = registry
+        .find_slab(slab_id)
+        .map(|(_, entry)| entry.use_twap)
+        .unwrap_or(false);
+
+    if use_twap {
+        if oracle_accounts.len() > MAX_ORACLES_PER_SLAB {
+            msg!("Error: Too many oracle feeds");
+            return Err(PercolatorError::InvalidOracle);
+        }
+
+        let adapter = TwapAdapter::new();
+        let mut prices = [0i64; MAX_ORACLES_PER_SLAB];
+        for (i, oracle_account) in oracle_accounts.iter().enumerate() {
+            prices[i] = check_oracle_price(
+                adapter.read_price(oracle_account)
+                    .map_err(|_| {
+                        msg!("Error: TWAP oracle read failed");
+                        PercolatorError::InvalidOracle
+                    })?
+                    .price,
+            )?;
+        }
+        return median_oracle_price(&mut prices[..oracle_accounts.len()], registry.max_oracle_divergence_bps);
+    }
+
+    read_oracle_price_unified(oracle_accounts, registry.max_oracle_divergence_bps)
+}
+
+/// Reject a feed that isn't one of the oracles registered for the slab
+///
+/// Unregistered slabs pass: resolve_slab_idx either rejects them or, with
+/// `permissionless_slabs`, registers them bound to this feed.
+pub(crate) fn check_slab_oracle(
+    registry: &SlabRegistry,
+    slab_id: &Pubkey,
+    feed_id: &Pubkey,
+) -> Result<(), PercolatorError> {
+    match registry.find_slab(slab_id) {
+        Some((_, entry)) if !entry.is_registered_oracle(feed_id) => {
+            msg!("Error: Oracle account does not match slab's registered oracle");
+            Err(PercolatorError::OracleMismatch)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Bind a slab's group of oracle accounts to its registered feeds
+///
+/// Splits, slabs and oracles are paired by account position, so without this
+/// a caller could permute oracle accounts and price one slab off another's
+/// feed. Every feed in the group must be registered for the slab, and the
+/// group is either the primary oracle alone or the slab's full median set, so
+/// a caller can't pick a favorable subset. TWAP slabs pass TwapBuffers, which
+/// are bound through their source oracles. An unregistered slab has only the
+/// feed it will be registered with.
+pub(crate) fn verify_slab_oracle(
+    registry: &SlabRegistry,
+    slab_id: &Pubkey,
+    slab_oracles: &[AccountInfo],
+) -> Result<(), PercolatorError> {
+    let entry = registry.find_slab(slab_id).map(|(_, entry)| entry);
+    let expected_count = entry.map_or(1, |entry| entry.oracle_count());
+    if slab_oracles.is_empty() || slab_oracles.len() > MAX_ORACLES_PER_SLAB {
+        msg!("Error: Invalid oracle feed count for slab");
+        return Err(PercolatorError::InvalidOracle);
+    }
+
+    let use_twap = entry.map_or(false, |entry| entry.use_twap);
+    let mut feed_ids = [Pubkey::default(); MAX_ORACLES_PER_SLAB];
+    for (i, oracle) in slab_oracles.iter().enumerate() {
+        feed_ids[i] = if use_twap {
+            TwapAdapter::new()
+                .validate_account(oracle)
+                .map_err(|_| PercolatorError::InvalidOracle)?;
+            let buffer = unsafe { borrow_account_data::<crate::oracle::TwapBuffer>(oracle) }
+                .map_err(|_| PercolatorError::InvalidOracle)?;
+            buffer.source_oracle
+        } else {
+            *oracle.key()
+        };
+        check_slab_oracle(registry, slab_id, &feed_ids[i])?;
+        if feed_ids[..i].contains(&feed_ids[i]) {
+            msg!("Error: Oracle feed passed more than once");
+            return Err(PercolatorError::InvalidOracle);
+        }
+    }
+
+    let primary_alone = slab_oracles.len() == 1
+        && entry.map_or(true, |entry| entry.oracle_id == feed_ids[0]);
+    if !primary_alone && slab_oracles.len() != expected_count {
+        msg!("Error: Oracle feeds must be the primary alone or every registered feed");
+        return Err(PercolatorError::InvalidOracle);
+    }
+    Ok(())
 }
 
 /// Validate market order price against oracle
-/// Market orders must execute within ±0.5% of oracle price
-fn validate_market_order_price(
+/// The oracle fill may be at most max_slippage_bps worse than the user's limit_px
+pub(crate) fn validate_market_order_price(
     limit_px: i64,
     oracle_px: i64,
     side: u8,
+    max_slippage_bps: u64,
 ) -> Result<(), PercolatorError> {
-    const MAX_SLIPPAGE_BPS: i64 = 50; // 0.5% max slippage
-
-    let max_deviation = (oracle_px as i128 * MAX_SLIPPAGE_BPS as i128 / 10_000) as i64;
+    let max_deviation = (oracle_px as i128 * max_slippage_bps as i128 / 10_000) as i64;
     let min_acceptable = oracle_px.saturating_sub(max_deviation);
     let max_acceptable = oracle_px.saturating_add(max_deviation);
 
@@ -76,15 +262,27 @@ fn validate_market_order_price(
     Ok(())
 }
 
+/// Limit price band for a slab (basis points around the oracle)
+///
+/// Per-market, set by governance; slabs not yet in the registry (they are
+/// auto-registered on first fill) get DEFAULT_LIMIT_DEVIATION_BPS.
+pub(crate) fn limit_deviation_bps(registry: &SlabRegistry, slab_id: &Pubkey) -> u64 {
+    registry
+        .find_slab(slab_id)
+        .map(|(_, entry)| entry.limit_band_bps())
+        .unwrap_or(crate::state::DEFAULT_LIMIT_DEVIATION_BPS as u64)
+}
+
 /// Validate limit order price is reasonable (v0 sanity check)
 /// v0: Still instant fill, but prevent obviously wrong prices
-fn validate_limit_order_price(
+///
+/// `max_deviation_bps` is the slab's band (see `limit_deviation_bps`).
+pub(crate) fn validate_limit_order_price(
     limit_px: i64,
     oracle_px: i64,
+    max_deviation_bps: u64,
 ) -> Result<(), PercolatorError> {
-    const MAX_DEVIATION_BPS: i64 = 2_000; // 20% sanity check
-
-    let max_deviation = (oracle_px as i128 * MAX_DEVIATION_BPS as i128 / 10_000) as i64;
+    let max_deviation = (oracle_px as i128 * max_deviation_bps as i128 / 10_000) as i64;
     let min_price = oracle_px.saturating_sub(max_deviation);
     let max_price = oracle_px.saturating_add(max_deviation);
 
@@ -96,12 +294,309 @@ fn validate_limit_order_price(
     Ok(())
 }
 
+/// Resolve a slab's registry index for execution
+///
+/// Only governance-registered slabs are tradeable. With the
+/// `permissionless_slabs` feature (localnet only), an unknown slab is
+/// auto-registered with default parameters so test venues work out of the box.
+fn resolve_slab_idx(
+    registry: &mut SlabRegistry,
+    slab_id: &Pubkey,
+    oracle_id: &Pubkey,
+    version_hash: &[u8; 32],
+) -> Result<u16, PercolatorError> {
+    if let Some((idx, _)) = registry.find_slab(slab_id) {
+        return Ok(idx);
+    }
+
+    // A delisted slab stays retired rather than being auto-registered again
+    let delisted = registry.slabs[..registry.slab_count as usize]
+        .iter()
+        .any(|entry| !entry.active && &entry.slab_id == slab_id);
+    if delisted {
+        msg!("Error: Slab has been delisted");
+        return Err(PercolatorError::SlabNotRegistered);
+    }
+
+    #[cfg(feature = "permissionless_slabs")]
+    {
+        log_debug!("Slab NOT found, auto-registering");
+        registry
+            .register_slab(
+                *slab_id,
+                *version_hash, // version_hash: trust the slab's own on first sight
+                *oracle_id,
+                1000,         // imr: 10% (1000 bps)
+                500,          // mmr: 5% (500 bps)
+                10,           // maker_fee_cap: 0.1% (10 bps)
+                10,           // taker_fee_cap: 0.1% (10 bps)
+                1000,         // latency_sla_ms: 1 second
+                u128::MAX,    // max_exposure: no limit
+                crate::state::DEFAULT_SLAB_MAX_LEVERAGE, // max_leverage: conservative until governance raises it
+                0,            // current_ts (placeholder)
+            )
+            .map_err(|_| PercolatorError::InvalidAccount)
+    }
+
+    #[cfg(not(feature = "permissionless_slabs"))]
+    {
+        let _ = (oracle_id, version_hash);
+        msg!("Error: Slab is not registered");
+        Err(PercolatorError::SlabNotRegistered)
+    }
+}
+
+/// Reject orders above the slab's registered leverage cap
+pub(crate) fn validate_slab_leverage(
+    registry: &SlabRegistry,
+    slab_idx: u16,
+    leverage: u8,
+) -> Result<(), PercolatorError> {
+    let entry = registry
+        .slabs
+        .get(slab_idx as usize)
+        .ok_or(PercolatorError::SlabNotRegistered)?;
+
+    if leverage > entry.max_leverage {
+        msg!("Error: Leverage exceeds slab maximum");
+        return Err(PercolatorError::LeverageTooHigh);
+    }
+    Ok(())
+}
+
+/// Move a slab's open interest for one account's exposure change
+///
+/// Rejects fills that push either side past the slab's `max_oi`; this caps
+/// the whole market, unlike the per-account `max_exposure`.
+pub(crate) fn update_open_interest(
+    registry: &mut SlabRegistry,
+    slab_idx: u16,
+    old_qty: i64,
+    new_qty: i64,
+) -> Result<(), PercolatorError> {
+    let entry = registry
+        .slabs
+        .get_mut(slab_idx as usize)
+        .ok_or(PercolatorError::SlabNotRegistered)?;
+
+    entry.apply_open_interest(old_qty, new_qty).map_err(|_| {
+        msg!("Error: Fill exceeds slab open interest cap");
+        PercolatorError::OpenInterestCapExceeded
+    })
+}
+
+/// Halt on an oracle reading too far from the slab's last settled price
+///
+/// Guards liquidations and fills against single-block oracle manipulation;
+/// see `SlabEntry::circuit_breaker_tripped` for when the halt lifts.
+pub(crate) fn check_circuit_breaker(
+    registry: &SlabRegistry,
+    slab_idx: u16,
+    oracle_px: i64,
+    current_slot: u64,
+) -> Result<(), PercolatorError> {
+    let entry = registry
+        .slabs
+        .get(slab_idx as usize)
+        .ok_or(PercolatorError::SlabNotRegistered)?;
+
+    if entry.circuit_breaker_tripped(oracle_px, current_slot) {
+        msg!("Error: Oracle move exceeds circuit breaker");
+        return Err(PercolatorError::CircuitBreakerTripped);
+    }
+    Ok(())
+}
+
+/// Reject fills on a slab outside its daily trading window
+///
+/// See `SlabEntry::is_trading_open`; liquidations skip this check so
+/// unhealthy accounts can still be closed while the market is shut.
+pub(crate) fn check_trading_hours(
+    registry: &SlabRegistry,
+    slab_idx: u16,
+    now_ts: i64,
+) -> Result<(), PercolatorError> {
+    let entry = registry
+        .slabs
+        .get(slab_idx as usize)
+        .ok_or(PercolatorError::SlabNotRegistered)?;
+
+    if !entry.is_trading_open(now_ts) {
+        msg!("Error: Market is outside its trading hours");
+        return Err(PercolatorError::MarketClosed);
+    }
+    Ok(())
+}
+
+/// Verify the slab's header version hash matches its registry entry
+///
+/// Protects against trading on a slab program that was upgraded without
+/// governance re-registering it.
+pub(crate) fn verify_slab_version(
+    registry: &SlabRegistry,
+    slab_id: &Pubkey,
+    version_hash: &[u8; 32],
+) -> Result<(), PercolatorError> {
+    if !registry.validate_version(slab_id, version_hash) {
+        msg!("Error: Slab version hash mismatch");
+        return Err(PercolatorError::SlabVersionMismatch);
+    }
+    Ok(())
+}
+
+/// Whether any account is passed more than once (by key)
+///
+/// A slab passed twice would pass the seqno check on both reads before
+/// either CPI bumps it, and then be filled twice.
+pub(crate) fn has_duplicate_keys(accounts: &[AccountInfo]) -> bool {
+    accounts
+        .iter()
+        .enumerate()
+        .any(|(i, account)| accounts[..i].iter().any(|prev| prev.key() == account.key()))
+}
+
+/// Version byte leading every serialized execution result
+pub const EXECUTION_RESULT_VERSION: u8 = 1;
+
+/// Outcome of a successful execute, set as the instruction's return data
+///
+/// CPI callers read it with get_return_data and RPC clients from a
+/// simulation's returnData, instead of scraping logs. Little-endian fields
+/// led by a version byte that changes whenever the layout does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ExecutionResult {
+    /// Layout version (EXECUTION_RESULT_VERSION)
+    pub version: u8,
+    /// Net quantity filled across splits (1e6 scale, +buy, -sell)
+    pub filled_qty: i64,
+    /// Fill-size-weighted average execution price (1e6 scale, 0 = nothing filled)
+    pub avg_price: i64,
+    /// Realized PnL settled by this execute (lamports)
+    pub realized_pnl: i128,
+    /// Portfolio equity after settlement and fees (lamports)
+    pub equity: i128,
+}
+
+impl ExecutionResult {
+    /// Serialized size
+    pub const LEN: usize = 1 + 8 * 2 + 16 * 2;
+
+    /// Aggregate per-split fills (signed quantities and their VWAPs)
+    pub fn from_fills(filled_qtys: &[i64], vwap_pxs: &[i64], realized_pnl: i128, equity: i128) -> Self {
+        let mut filled_qty: i64 = 0;
+        let mut total_abs: u128 = 0;
+        let mut weighted_px: u128 = 0;
+        for (&qty, &px) in filled_qtys.iter().zip(vwap_pxs.iter()) {
+            filled_qty = filled_qty.saturating_add(qty);
+            total_abs += qty.unsigned_abs() as u128;
+            weighted_px = weighted_px.saturating_add(qty.unsigned_abs() as u128 * px.max(0) as u128);
+        }
+        let avg_price = if total_abs == 0 { 0 } else { (weighted_px / total_abs).min(i64::MAX as u128) as i64 };
+
+        Self { version: EXECUTION_RESULT_VERSION, filled_qty, avg_price, realized_pnl, equity }
+    }
+
+    /// Encode as little-endian fields in declaration order
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut out = [0u8; Self::LEN];
+        out[0] = self.version;
+        out[1..9].copy_from_slice(&self.filled_qty.to_le_bytes());
+        out[9..17].copy_from_slice(&self.avg_price.to_le_bytes());
+        out[17..33].copy_from_slice(&self.realized_pnl.to_le_bytes());
+        out[33..49].copy_from_slice(&self.equity.to_le_bytes());
+        out
+    }
+
+    /// Decode a result produced by `to_bytes`
+    ///
+    /// Errors with InvalidInstruction on truncated data or an unknown version.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, PercolatorError> {
+        let mut reader = InstructionReader::new(data);
+
+        let version = reader.read_u8()?;
+        if version != EXECUTION_RESULT_VERSION {
+            return Err(PercolatorError::InvalidInstruction);
+        }
+
+        Ok(Self {
+            version,
+            filled_qty: reader.read_i64()?,
+            avg_price: reader.read_i64()?,
+            realized_pnl: reader.read_u128()? as i128,
+            equity: reader.read_u128()? as i128,
+        })
+    }
+}
+
+/// Require `authority` to be a signer allowed to trade `portfolio`
+///
+/// Either the portfolio's user or, so strategy programs can trade through
+/// CPI with their PDA as signer, the delegate the user set. Fails with
+/// InvalidPortfolio for any other key and Unauthorized if it didn't sign.
+pub(crate) fn check_trading_authority(portfolio: &Portfolio, authority: &AccountInfo) -> Result<(), PercolatorError> {
+    if !portfolio.is_trading_authority(authority.key()) {
+        msg!("Error: Signer is neither the portfolio's user nor its delegate");
+        return Err(PercolatorError::InvalidPortfolio);
+    }
+    if !authority.is_signer() {
+        msg!("Error: Trading authority must sign");
+        return Err(PercolatorError::Unauthorized);
+    }
+    Ok(())
+}
+
+/// Require `liquidator` to be a signer the registry admits to liquidate
+///
+/// Liquidation fills close positions the user never signed for, so the
+/// keeper's signature (see `require_liquidator`) stands in for the trading
+/// authority's.
+pub(crate) fn check_liquidation_authority(registry: &SlabRegistry, liquidator: &AccountInfo) -> Result<(), PercolatorError> {
+    if !liquidator.is_signer() {
+        msg!("Error: Liquidator must sign");
+        return Err(PercolatorError::Unauthorized);
+    }
+    require_liquidator(registry, liquidator.key())
+}
+
+/// Require the account funding new PositionDetails rent to sign
+///
+/// The payer only moves lamports into the PDA; the position itself is
+/// derived from and recorded against the user's portfolio, so a sponsor or
+/// vault paying rent gains no claim on it.
+pub(crate) fn check_rent_payer(payer: &AccountInfo) -> Result<(), PercolatorError> {
+    if !payer.is_signer() {
+        msg!("Error: Rent payer must sign");
+        return Err(PercolatorError::Unauthorized);
+    }
+    Ok(())
+}
+
+/// Cap the open exposures a portfolio may hold after opening `new_positions`
+///
+/// Liquidation passes every position's PDA and oracle in one transaction,
+/// so an account past the cap could become impossible to liquidate.
+pub(crate) fn check_position_limit(
+    portfolio: &Portfolio,
+    new_positions: usize,
+    max_positions: u64,
+) -> Result<(), PercolatorError> {
+    if new_positions > 0 && (portfolio.exposure_count as u64).saturating_add(new_positions as u64) > max_positions {
+        msg!("Error: Portfolio is at its open position limit");
+        return Err(PercolatorError::TooManyPositions);
+    }
+    Ok(())
+}
+
 /// Slab split - how much to execute on each slab
+///
+/// `qty` is always a positive magnitude and `side` carries the direction, as
+/// commit_fill takes them; the signed form (+buy, -sell) is `signed_qty`,
+/// which is also the sign of the slab's receipt and of the exposure change.
 #[derive(Debug, Clone, Copy)]
 pub struct SlabSplit {
     /// Slab account pubkey
     pub slab_id: Pubkey,
-    /// Quantity to execute on this slab (1e6 scale)
+    /// Quantity to execute on this slab (1e6 scale, positive)
     pub qty: i64,
     /// Side (0 = buy, 1 = sell)
     pub side: u8,
@@ -109,17 +604,46 @@ pub struct SlabSplit {
     pub limit_px: i64,
 }
 
+impl SlabSplit {
+    /// Side as the shared enum (anything but 0 is a sell; see `check_split`)
+    pub fn side(&self) -> Side {
+        if self.side == 0 { Side::Buy } else { Side::Sell }
+    }
+
+    /// Requested quantity signed by side (+buy, -sell)
+    pub fn signed_qty(&self) -> i64 {
+        self.side().signed_qty(self.qty)
+    }
+}
+
+/// Reject a split that doesn't follow the signed-quantity convention
+///
+/// The slab refuses these too, but checking before the CPI keeps the
+/// router's own sign math (reduce-only, simulation, receipts) from ever
+/// seeing a negative magnitude or an unknown side.
+pub(crate) fn check_split(split: &SlabSplit) -> Result<(), PercolatorError> {
+    if split.side > 1 {
+        msg!("Error: Invalid side");
+        return Err(PercolatorError::InvalidSide);
+    }
+    if split.qty <= 0 {
+        msg!("Error: Split quantity must be positive");
+        return Err(PercolatorError::InvalidQuantity);
+    }
+    Ok(())
+}
+
 /// Process execute cross-slab order (v0 with oracle validation)
 ///
-/// This is the core v0 instruction that proves portfolio netting.
 /// Router reads QuoteCache from multiple slabs, reads oracle prices,
 /// validates prices, CPIs to each slab's commit_fill, aggregates receipts,
-/// and updates portfolio with net exposure.
+/// and updates each (slab, instrument) exposure in the portfolio.
 ///
 /// # Arguments
 /// * `user_portfolio_account` - User's portfolio account (holds SOL)
 /// * `user_portfolio` - User's portfolio state
-/// * `user` - User pubkey (signer)
+/// * `user` - User pubkey (signer), or the liquidator when `liquidation` is set
+/// * `rent_payer` - Signer funding any new PositionDetails rent (the user unless sponsored)
 /// * `dlp_portfolio_account` - DLP's portfolio account (counterparty, holds SOL)
 /// * `dlp_portfolio` - DLP's portfolio state
 /// * `registry` - Slab registry with insurance state
@@ -127,20 +651,32 @@ pub struct SlabSplit {
 /// * `system_program` - System program for SOL transfers
 /// * `slab_accounts` - Array of slab accounts to execute on
 /// * `receipt_accounts` - Array of receipt PDAs (one per slab)
-/// * `oracle_accounts` - Array of oracle price feed accounts (one per slab)
+/// * `oracle_accounts` - Array of oracle price feed accounts (`oracles_per_slab` per slab, grouped by slab)
+/// * `position_details_accounts` - PositionDetails PDAs (one per slab) for the positions being traded
+/// * `extra_position_details` - PositionDetails PDAs of the portfolio's other open positions, read only for margin
 /// * `splits` - How to split the order across slabs
 /// * `order_type` - Market (0) or Limit (1) order
+/// * `oracles_per_slab` - Oracle feeds per slab (1, 3, or 5; median is used)
+/// * `leverage` - Leverage multiplier (1-10x)
+/// * `max_slippage_bps` - User's market order slippage tolerance (0 = default, clamped to registry ceiling)
+/// * `reduce_only` - Reject any split that would open, increase, or reverse a position
+/// * `allow_partial_batch` - Let filled splits stand when others fill nothing (see `check_batch_fills`)
+/// * `enforce_trading_hours` - Reject fills outside each slab's daily trading window (off for liquidations)
+/// * `liquidation` - Fills close a liquidated account: `user` is the liquidator, slabs charge
+///   no taker fee, and the account isn't held to initial margin afterwards
 ///
 /// # Returns
-/// * Updates portfolio with net exposures
+/// * Updates portfolio exposures (buys and sells of one instrument net in its entry)
 /// * Settles PnL via SOL transfer between user and DLP portfolios
 /// * Accrues insurance fees from taker fills
-/// * Checks margin on net exposure (capital efficiency!)
-/// * All-or-nothing atomicity
+/// * Checks margin summed per position (see `margin_required`)
+/// * All-or-nothing atomicity, unless `allow_partial_batch` lets dry splits drop out
+/// * Sets the ExecutionResult as return data
 pub fn process_execute_cross_slab(
     user_portfolio_account: &AccountInfo,
     user_portfolio: &mut Portfolio,
     user_account: &AccountInfo,
+    rent_payer: &AccountInfo,
     dlp_portfolio_account: &AccountInfo,
     dlp_portfolio: &mut Portfolio,
     registry: &mut SlabRegistry,
@@ -151,118 +687,204 @@ pub fn process_execute_cross_slab(
     receipt_accounts: &[AccountInfo],
     oracle_accounts: &[AccountInfo],
     position_details_accounts: &[AccountInfo],
+    extra_position_details: &[AccountInfo],
     splits: &[SlabSplit],
     order_type: u8, // 0 = Market, 1 = Limit
+    oracles_per_slab: usize, // 1, 3, or 5 feeds per slab
     leverage: u8, // 1-10x leverage
+    max_slippage_bps: u16,
+    reduce_only: bool,
+    allow_partial_batch: bool,
+    enforce_trading_hours: bool,
+    liquidation: bool,
     program_id: &Pubkey,
 ) -> Result<(), PercolatorError> {
-    // Verify user portfolio belongs to user
-    if &user_portfolio.user != user_account.key() {
-        msg!("Error: Portfolio does not belong to user");
-        return Err(PercolatorError::InvalidPortfolio);
+    // The user, or the delegate they designated, must sign for the trade;
+    // a liquidation is signed by an admitted liquidator instead
+    if liquidation {
+        check_liquidation_authority(registry, user_account)?;
+    } else {
+        check_trading_authority(user_portfolio, user_account)?;
     }
+    check_rent_payer(rent_payer)?;
 
     // Apply PnL vesting and haircut catchup on user touch
-    use crate::state::on_user_touch;
+    use crate::state::touch_portfolio;
     use pinocchio::sysvars::{clock::Clock, Sysvar};
     let current_slot = Clock::get()
         .map(|clock| clock.slot)
         .unwrap_or(user_portfolio.last_slot);
 
-    on_user_touch(
-        user_portfolio.principal,
-        &mut user_portfolio.pnl,
-        &mut user_portfolio.vested_pnl,
-        &mut user_portfolio.last_slot,
-        &mut user_portfolio.pnl_index_checkpoint,
-        &registry.global_haircut,
+    touch_portfolio(
+        user_portfolio,
+        &mut registry.global_haircut,
         &registry.pnl_vesting_params,
         current_slot,
     );
 
-    // v0 Limitation: Only single slab execution (no cross-slab routing)
-    // Cross-slab routing requires order book model for proper PnL settlement
-    if slab_accounts.len() != 1 {
-        msg!("Error: v0 only supports single slab execution");
-        return Err(PercolatorError::InvalidInstruction);
-    }
+    check_oracles_per_slab(oracles_per_slab)?;
 
     // Verify we have matching number of slabs, receipts, oracles, and position details
     if slab_accounts.len() != receipt_accounts.len()
-        || slab_accounts.len() != oracle_accounts.len()
+        || slab_accounts.len() * oracles_per_slab != oracle_accounts.len()
         || slab_accounts.len() != position_details_accounts.len()
         || slab_accounts.len() != splits.len() {
         msg!("Error: Mismatched slab/receipt/oracle/position_details/split counts");
         return Err(PercolatorError::InvalidInstruction);
     }
 
+    // Each slab is filled at most once per transaction
+    if has_duplicate_keys(slab_accounts) {
+        msg!("Error: Slab passed more than once");
+        return Err(PercolatorError::DuplicateSlab);
+    }
+    if has_duplicate_keys(position_details_accounts) {
+        msg!("Error: PositionDetails passed more than once");
+        return Err(PercolatorError::InvalidAccount);
+    }
+
     // Validate order type
     if order_type > 1 {
         msg!("Error: Invalid order type");
         return Err(PercolatorError::InvalidOrderType);
     }
 
+    validate_leverage(leverage)?;
+
     // Verify router_authority is the correct PDA
-    use crate::pda::derive_authority_pda;
-    let (expected_authority, authority_bump) = derive_authority_pda(&user_portfolio.router_id);
+    use crate::pda::derive_authority_pda_cached;
+    let (expected_authority, authority_bump) =
+        derive_authority_pda_cached(&user_portfolio.router_id, registry.authority_bump);
     if router_authority.key() != &expected_authority {
         msg!("Error: Invalid router authority PDA");
         return Err(PercolatorError::InvalidAccount);
     }
 
     // Phase 1: Read oracles and prepare execution prices
-    msg!("Reading oracles and preparing prices");
+    log_debug!("Reading oracles and preparing prices");
 
     // Store oracle prices for market orders
     let mut oracle_prices = [0i64; 16]; // Max 16 slabs
+    let slippage_bps = effective_slippage_bps(max_slippage_bps, registry.max_slippage_bps);
 
     for (i, split) in splits.iter().enumerate() {
-        let oracle_account = &oracle_accounts[i];
+        check_split(split)?;
+        let slab_oracles = &oracle_accounts[i * oracles_per_slab..(i + 1) * oracles_per_slab];
 
-        // Read oracle price using appropriate adapter
-        let oracle_px = read_oracle_price_unified(oracle_account)?;
+        // Read median oracle price using the slab's spot or TWAP source
+        verify_slab_oracle(registry, slab_accounts[i].key(), slab_oracles)?;
+        let oracle_px = read_slab_price(registry, slab_accounts[i].key(), slab_oracles)?;
         oracle_prices[i] = oracle_px;
 
         // Validate price based on order type
         match order_type {
             0 => { // Market order
-                // No validation - market orders execute at oracle price
-                msg!("Market order will execute at oracle price");
+                // Market orders execute at oracle price, bounded by the user's limit_px
+                validate_market_order_price(split.limit_px, oracle_px, split.side, slippage_bps)?;
+                log_debug!("Market order will execute at oracle price");
             }
             1 => { // Limit order
-                // No validation - limit orders execute at user-specified price (atomic fills in v0)
-                msg!("Limit order will execute at user price");
+                // Limit orders execute at user-specified price (atomic fills in v0), sanity-checked against oracle
+                validate_limit_order_price(split.limit_px, oracle_px, limit_deviation_bps(registry, slab_accounts[i].key()))?;
+                log_debug!("Limit order will execute at user price");
             }
             _ => unreachable!(), // Already validated above
         }
     }
 
+    // Snapshot every slab header in one pass before any CPI mutates them. The
+    // seqnos are the TOCTOU guard passed to commit_fill; later phases reuse the
+    // version hash and contract size instead of re-borrowing the slab
+    let mut slab_headers = [SlabHeaderSnapshot::default(); 16];
+    for (i, slab_account) in slab_accounts.iter().enumerate() {
+        let slab_data = slab_account
+            .try_borrow_data()
+            .map_err(|_| PercolatorError::InvalidAccount)?;
+        if slab_data.len() < SlabHeader::LEN {
+            msg!("Error: Invalid slab account data");
+            return Err(PercolatorError::InvalidAccount);
+        }
+        let header = unsafe { &*(slab_data.as_ptr() as *const SlabHeader) };
+        slab_headers[i] = SlabHeaderSnapshot::from(header);
+
+        // Every split settles against the same DLP, so it must back every slab
+        check_counterparty(dlp_portfolio, &slab_headers[i].lp_owner)?;
+    }
+
+    // Phase 1.4: Reduce-only splits may only shrink an existing position
+    // A fill never exceeds its split, so checking the request bounds the fill
+    if reduce_only {
+        for (i, split) in splits.iter().enumerate() {
+            let current_exposure = registry
+                .find_slab(slab_accounts[i].key())
+                .map_or(0, |(idx, _)| user_portfolio.get_exposure(idx, 0));
+            check_reduce_only(current_exposure, split)?;
+        }
+    }
+
+    // Phase 1.45: Splits into an instrument with no exposure open a new
+    // position; the portfolio must stay under the registry's cap
+    let new_positions = slab_accounts
+        .iter()
+        .filter(|slab| {
+            registry
+                .find_slab(slab.key())
+                .map_or(0, |(idx, _)| user_portfolio.get_exposure(idx, 0))
+                == 0
+        })
+        .count();
+    check_position_limit(user_portfolio, new_positions, registry.max_positions_per_portfolio)?;
+
+    // Phase 1.5: Fail fast if the DLP can't cover the worst case
+    // Reducing splits pay released margin plus any profit out of the DLP; size
+    // that up from the oracle prices before anything is mutated
+    let mut dlp_obligation: u128 = 0;
+    for (i, split) in splits.iter().enumerate() {
+        let slab_idx = match registry.find_slab(slab_accounts[i].key()) {
+            Some((idx, _)) => idx,
+            None => continue, // Not yet registered, so no open position to close
+        };
+
+        let position = load_position_details(&position_details_accounts[i])?;
+        dlp_obligation = dlp_obligation.saturating_add(split_dlp_obligation(
+            user_portfolio.get_exposure(slab_idx, 0),
+            split,
+            position.as_ref(),
+            oracle_prices[i],
+            slab_headers[i].contract_size,
+            slab_headers[i].scale,
+        ));
+    }
+    check_dlp_solvency(
+        dlp_obligation,
+        dlp_portfolio_account.lamports(),
+        registry.insurance_state.vault_balance,
+    )?;
+
     // Phase 2: CPI to each slab's commit_fill
-    msg!("Executing fills on slabs");
+    log_debug!("Executing fills on slabs");
+
+    // Registry index of each slab, resolved before filling
+    let mut slab_indices = [0u16; 16];
 
     for (i, split) in splits.iter().enumerate() {
         let slab_account = &slab_accounts[i];
         let receipt_account = &receipt_accounts[i];
-        let oracle_account = &oracle_accounts[i];
+        let oracle_account = &oracle_accounts[i * oracles_per_slab]; // Primary feed
 
         // Get slab program ID from account owner
         let slab_program_id = slab_account.owner();
 
-        // Read current seqno from slab for TOCTOU protection
-        let slab_data = slab_account
-            .try_borrow_data()
-            .map_err(|_| PercolatorError::InvalidAccount)?;
-        if slab_data.len() < 16 {
-            msg!("Error: Invalid slab account data");
-            return Err(PercolatorError::InvalidAccount);
+        // Only fill against a registered slab whose program version is the one governance approved
+        let snapshot = &slab_headers[i];
+        slab_indices[i] = resolve_slab_idx(registry, slab_account.key(), oracle_account.key(), &snapshot.version_hash)?;
+        verify_slab_version(registry, slab_account.key(), &snapshot.version_hash)?;
+        validate_slab_leverage(registry, slab_indices[i], leverage)?;
+        check_circuit_breaker(registry, slab_indices[i], oracle_prices[i], current_slot)?;
+        if enforce_trading_hours {
+            let now_ts = Clock::get().map(|clock| clock.unix_timestamp).unwrap_or(0);
+            check_trading_hours(registry, slab_indices[i], now_ts)?;
         }
-        // Seqno is at offset 12 in SlabHeader (after 8-byte magic + 4-byte version)
-        let expected_seqno = u32::from_le_bytes([
-            slab_data[12],
-            slab_data[13],
-            slab_data[14],
-            slab_data[15],
-        ]);
 
         // Determine execution price based on order type
         let execution_price = match order_type {
@@ -275,15 +897,15 @@ pub fn process_execute_cross_slab(
         // Even if user opened with limit order at $100, we settle PnL at current market price
         let settlement_price = oracle_prices[i];
 
-        // Build commit_fill instruction data (23 bytes total)
-        // Layout: discriminator (1) + expected_seqno (4) + order_type (1) + side (1) + qty (8) + limit_px (8)
-        let mut instruction_data = [0u8; 23];
-        instruction_data[0] = 1; // CommitFill discriminator
-        instruction_data[1..5].copy_from_slice(&expected_seqno.to_le_bytes());
-        instruction_data[5] = order_type;
-        instruction_data[6] = split.side;
-        instruction_data[7..15].copy_from_slice(&split.qty.to_le_bytes());
-        instruction_data[15..23].copy_from_slice(&execution_price.to_le_bytes());
+        let instruction_data = commit_fill_data(
+            snapshot.expected_seqno,
+            order_type,
+            split.side,
+            split.qty,
+            execution_price,
+            oracle_prices[i],
+            liquidation,
+        );
 
         // Build account metas for CPI
         // 0. slab_account (writable)
@@ -304,7 +926,7 @@ pub fn process_execute_cross_slab(
             AccountMeta::writable(receipt_account.key()),
         ];
 
-        msg!("CPI: About to invoke slab program");
+        log_debug!("CPI: About to invoke slab program");
 
         // Copy program ID since it needs to outlive the instruction
         let program_id_copy = *slab_program_id;
@@ -315,7 +937,7 @@ pub fn process_execute_cross_slab(
             data: &instruction_data,
         };
 
-        msg!("CPI: Instruction built, preparing PDA signer");
+        log_debug!("CPI: Instruction built, preparing PDA signer");
 
         // Prepare PDA signer for router authority
         use crate::pda::AUTHORITY_SEED;
@@ -326,7 +948,7 @@ pub fn process_execute_cross_slab(
         ];
         let signer = Signer::from(&seeds);
 
-        msg!("CPI: Calling invoke_signed_unchecked with PDA");
+        log_debug!("CPI: Calling invoke_signed_unchecked with PDA");
 
         // Convert to Account types for unchecked invoke
         let accounts_for_cpi = [
@@ -344,16 +966,31 @@ pub fn process_execute_cross_slab(
             );
         }
 
-        msg!("CPI: invoke_signed_unchecked succeeded!");
+        log_debug!("CPI: invoke_signed_unchecked succeeded!");
+
+        // Settled prices become the breaker's reference for the next reading
+        let funding_params = registry.funding_params;
+        let entry = &mut registry.slabs[slab_indices[i] as usize];
+        entry.record_settlement(oracle_prices[i], current_slot);
+
+        // Accrue funding on the open interest held before this batch's fills
+        entry.accrue_funding(execution_price, oracle_prices[i], &funding_params, current_slot);
     }
 
     // Phase 3: Read receipts and settle PnL
+    // Aggregate realized PnL in lamports (reduce_position returns 1e6 scale)
     let mut total_realized_pnl: i128 = 0;
+    // Actual filled quantity per slab (from receipts)
+    let mut filled_qtys = [0i64; 16];
+    // Fill price per slab (from receipts)
+    let mut vwap_pxs = [0i64; 16];
+    // Taker fee per slab (from receipts)
+    let mut fees = [0i64; 16];
 
-    for (i, split) in splits.iter().enumerate() {
-        let receipt_account = &receipt_accounts[i];
+    // Read every receipt in one pass now that all CPIs have run
+    for (i, receipt_account) in receipt_accounts.iter().enumerate() {
+        check_receipt_owner(receipt_account, slab_accounts[i].owner())?;
 
-        // Read FillReceipt from slab response
         let receipt_data = receipt_account
             .try_borrow_data()
             .map_err(|_| PercolatorError::InvalidAccount)?;
@@ -365,48 +1002,26 @@ pub fn process_execute_cross_slab(
 
         // Deserialize receipt (FillReceipt is repr(C), so we can cast)
         let receipt = unsafe { &*(receipt_data.as_ptr() as *const FillReceipt) };
+        (filled_qtys[i], vwap_pxs[i]) = read_fill(receipt, &splits[i])?;
+        fees[i] = receipt.fee;
+    }
+    check_batch_fills(&filled_qtys[..splits.len()], allow_partial_batch)?;
 
-        if !receipt.is_used() {
-            msg!("Error: Receipt not written by slab");
-            return Err(PercolatorError::InvalidReceipt);
-        }
+    for (i, split) in splits.iter().enumerate() {
+        let filled_qty = filled_qtys[i];
+        let vwap_px = vwap_pxs[i];
 
-        let filled_qty = receipt.filled_qty;
-        let vwap_px = receipt.vwap_px;
+        // Nothing filled (only reachable with allow_partial_batch): leave this
+        // position untouched (no PDA, no margin move) and let the rest settle
+        if is_zero_fill(filled_qty) {
+            log_debug!("Zero fill - skipping split");
+            continue;
+        }
 
-        // Get slab account pubkey
-        let slab_account = &slab_accounts[i];
-        let slab_id = slab_account.key();
-
-        msg!("Looking up slab in registry");
-        // Auto-register slab in registry if not already registered
-        // This ensures indices are stable and positions can be resolved
-        let slab_idx = match registry.find_slab(slab_id) {
-            Some((idx, _)) => {
-                msg!("Slab found in registry");
-                idx
-            }
-            None => {
-                msg!("Slab NOT found, auto-registering");
-                // Auto-register new slab with default parameters
-                // In production, slabs should be pre-registered by governance
-                let oracle_id = *oracle_accounts[i].key();
-                registry
-                    .register_slab(
-                        *slab_id,
-                        [0; 32],      // version_hash (placeholder for auto-registration)
-                        oracle_id,
-                        1000,         // imr: 10% (1000 bps)
-                        500,          // mmr: 5% (500 bps)
-                        10,           // maker_fee_cap: 0.1% (10 bps)
-                        10,           // taker_fee_cap: 0.1% (10 bps)
-                        1000,         // latency_sla_ms: 1 second
-                        u128::MAX,    // max_exposure: no limit
-                        0,            // current_ts (placeholder)
-                    )
-                    .map_err(|_| PercolatorError::InvalidAccount)?
-            }
-        };
+        let slab_idx = slab_indices[i];
+        let contract_size = slab_headers[i].contract_size;
+        let contract_kind = slab_headers[i].contract_kind;
+        let scale = slab_headers[i].scale;
 
         let instrument_idx = 0u16; // v0: single instrument per slab
 
@@ -419,24 +1034,22 @@ pub fn process_execute_cross_slab(
         // Load or create PositionDetails
         let mut position_details = match load_position_details(position_details_account)? {
             Some(details) => {
-                msg!("PositionDetails loaded");
+                log_debug!("PositionDetails loaded");
                 details
             }
             None => {
                 // First trade for this position - create new PositionDetails PDA
-                msg!("Creating new PositionDetails PDA");
-
-                // Derive PDA with bump
-                use pinocchio::pubkey::find_program_address;
-                let slab_idx_bytes = slab_idx.to_le_bytes();
-                let instrument_idx_bytes = instrument_idx.to_le_bytes();
-                let seeds: &[&[u8]] = &[
-                    b"position",
-                    user_portfolio_account.key().as_ref(),
-                    &slab_idx_bytes,
-                    &instrument_idx_bytes,
-                ];
-                let (expected_pda, bump) = find_program_address(seeds, program_id);
+                log_debug!("Creating new PositionDetails PDA");
+
+                // Derive PDA with bump (no account yet, so nothing cached)
+                use crate::pda::{derive_position_details_pda, NO_CACHED_BUMP};
+                let (expected_pda, bump) = derive_position_details_pda(
+                    user_portfolio_account.key(),
+                    slab_idx,
+                    instrument_idx,
+                    NO_CACHED_BUMP,
+                    program_id,
+                );
 
                 // Verify provided account matches derived PDA
                 if position_details_account.key() != &expected_pda {
@@ -450,7 +1063,7 @@ pub fn process_execute_cross_slab(
                     user_portfolio_account.key(),
                     slab_idx,
                     instrument_idx,
-                    user_account, // User account is the signer/payer
+                    rent_payer, // Funds rent only; the PDA is the portfolio's
                     system_program,
                     program_id,
                     bump,
@@ -464,7 +1077,7 @@ pub fn process_execute_cross_slab(
 
                 // Initialize new PositionDetails with zero margin and quantity
                 // Both will be calculated and added in the "adding to position" logic below
-                PositionDetails::new(
+                let mut details = PositionDetails::new(
                     *user_portfolio_account.key(),
                     slab_idx,
                     instrument_idx,
@@ -474,7 +1087,9 @@ pub fn process_execute_cross_slab(
                     bump,
                     0,            // margin_held starts at 0, will be added below
                     leverage,     // leverage (1-10x)
-                )
+                );
+                details.contract_kind = contract_kind as u8;
+                details
             }
         };
 
@@ -483,41 +1098,28 @@ pub fn process_execute_cross_slab(
         let same_direction = (is_buy && current_exposure >= 0) || (!is_buy && current_exposure <= 0);
 
         use pinocchio::sysvars::{clock::Clock, Sysvar};
-        use pinocchio::log::sol_log_64;
         let timestamp = Clock::get()
             .map(|clock| clock.unix_timestamp)
             .unwrap_or(0);
 
         let realized_pnl = if same_direction || current_exposure == 0 {
             // Case 1: Adding to position or opening new position (leverage applies)
-            msg!("Adding to position");
+            log_debug!("Adding to position");
 
             let quantity_abs = filled_qty.abs() as u128;
             let leverage_u128 = leverage as u128;
 
-            // Different margin calculation for 1x vs higher leverage
-            let margin_lamports = if leverage == 1 {
-                // For 1x leverage: margin = quantity * 1_000 (1 contract = 1 SOL)
-                quantity_abs * 1_000
-            } else {
-                // For higher leverage: margin = (quantity * 10_000) / leverage
-                (quantity_abs * 10_000) / leverage_u128
-            };
+            let margin_lamports = margin_required(filled_qty, vwap_px, leverage, contract_size, contract_kind, scale);
 
-            // Debug: Log the margin calculation components
-            sol_log_64(quantity_abs as u64, leverage_u128 as u64, margin_lamports as u64, vwap_px as u64, order_type as u64);
+            log_debug!("MARGIN DEBUG: qty, leverage, margin, vwap, order_type", quantity_abs as u64, leverage_u128 as u64, margin_lamports as u64, vwap_px as u64, order_type as u64);
 
-            msg!("MARGIN DEBUG: Adding position");
-            sol_log_64(filled_qty as u64, leverage as u64, margin_lamports as u64, 0, 0);
-            msg!("MARGIN DEBUG: PD BEFORE add_to_position - qty and margin");
-            sol_log_64(position_details.total_qty as u64, position_details.margin_held as u64, 0, 0, 0);
-            msg!("MARGIN DEBUG: User equity BEFORE");
-            sol_log_64(user_portfolio.equity as u64, 0, 0, 0, 0);
+            log_debug!("MARGIN DEBUG: Adding position", filled_qty as u64, leverage as u64, margin_lamports as u64, 0, 0);
+            log_debug!("MARGIN DEBUG: PD BEFORE add_to_position - qty and margin", position_details.total_qty as u64, position_details.margin_held as u64, 0, 0, 0);
+            log_debug!("MARGIN DEBUG: User equity BEFORE", user_portfolio.equity as u64, 0, 0, 0, 0);
 
-            position_details.add_to_position(vwap_px, filled_qty, 0i128, timestamp, margin_lamports);
+            position_details.add_to_position(vwap_px, filled_qty, 0i128, timestamp, margin_lamports, leverage)?;
 
-            msg!("MARGIN DEBUG: PD AFTER add_to_position - qty and margin");
-            sol_log_64(position_details.total_qty as u64, position_details.margin_held as u64, 0, 0, 0);
+            log_debug!("MARGIN DEBUG: PD AFTER add_to_position - qty and margin", position_details.total_qty as u64, position_details.margin_held as u64, 0, 0, 0);
 
             transfer_collateral_margin(
                 user_portfolio_account,
@@ -527,8 +1129,7 @@ pub fn process_execute_cross_slab(
                 margin_lamports,
             )?;
 
-            msg!("MARGIN DEBUG: User equity AFTER");
-            sol_log_64(user_portfolio.equity as u64, 0, 0, 0, 0);
+            log_debug!("MARGIN DEBUG: User equity AFTER", user_portfolio.equity as u64, 0, 0, 0, 0);
 
             0i128 // No realized PnL when adding
         } else {
@@ -539,31 +1140,24 @@ pub fn process_execute_cross_slab(
 
             if filled_abs <= current_abs {
                 // Case 2: Partial or full close (leverage is IGNORED)
-                msg!("Reducing/closing position");
+                log_debug!("Reducing/closing position");
 
-                msg!("MARGIN DEBUG: Before reduce - exposure and filled");
-                sol_log_64(current_exposure as u64, filled_qty as u64, 0, 0, 0);
-                msg!("MARGIN DEBUG: PD before - qty and margin");
-                sol_log_64(position_details.total_qty as u64, position_details.margin_held as u64, 0, 0, 0);
+                log_debug!("MARGIN DEBUG: Before reduce - exposure and filled", current_exposure as u64, filled_qty as u64, 0, 0, 0);
+                log_debug!("MARGIN DEBUG: PD before - qty and margin", position_details.total_qty as u64, position_details.margin_held as u64, 0, 0, 0);
 
                 // Use oracle price for PnL calculation (not vwap_px which could be limit price for limit orders)
                 let oracle_px = oracle_prices[i];
-                msg!("PNL SETTLE DEBUG: oracle_px, vwap_px, entry_price");
-                sol_log_64(oracle_px as u64, vwap_px as u64, position_details.avg_entry_price as u64, 0, 0);
+                log_debug!("PNL SETTLE DEBUG: oracle_px, vwap_px, entry_price", oracle_px as u64, vwap_px as u64, position_details.avg_entry_price as u64, 0, 0);
                 let (pnl, new_qty, margin_to_release) = position_details.reduce_position(oracle_px, filled_qty, 0i128, timestamp);
-                msg!("PNL SETTLE DEBUG: realized_pnl");
-                sol_log_64(pnl as u64, 0, 0, 0, 0);
+                log_debug!("PNL SETTLE DEBUG: realized_pnl", pnl as u64, 0, 0, 0, 0);
 
-                msg!("MARGIN DEBUG: After reduce - new_qty and margin_to_release");
-                sol_log_64(new_qty as u64, margin_to_release as u64, 0, 0, 0);
-                msg!("MARGIN DEBUG: PD after - qty and margin");
-                sol_log_64(position_details.total_qty as u64, position_details.margin_held as u64, 0, 0, 0);
+                log_debug!("MARGIN DEBUG: After reduce - new_qty and margin_to_release", new_qty as u64, margin_to_release as u64, 0, 0, 0);
+                log_debug!("MARGIN DEBUG: PD after - qty and margin", position_details.total_qty as u64, position_details.margin_held as u64, 0, 0, 0);
 
                 // Return margin collateral from DLP to user
                 if margin_to_release > 0 {
-                    msg!("Returning margin to user");
-                    msg!("MARGIN DEBUG: User equity BEFORE return");
-                    sol_log_64(user_portfolio.equity as u64, 0, 0, 0, 0);
+                    log_debug!("Returning margin to user");
+                    log_debug!("MARGIN DEBUG: User equity BEFORE return", user_portfolio.equity as u64, 0, 0, 0, 0);
                     return_margin_to_user(
                         user_portfolio_account,
                         user_portfolio,
@@ -571,14 +1165,14 @@ pub fn process_execute_cross_slab(
                         dlp_portfolio,
                         margin_to_release,
                     )?;
-                    msg!("MARGIN DEBUG: User equity AFTER return");
-                    sol_log_64(user_portfolio.equity as u64, 0, 0, 0, 0);
+                    log_debug!("MARGIN DEBUG: User equity AFTER return", user_portfolio.equity as u64, 0, 0, 0, 0);
                 }
 
                 // Check if position is fully closed
                 if new_qty == 0 {
-                    msg!("Position fully closed, closing PDA");
-                    close_position_details_pda(position_details_account, user_account)?;
+                    log_debug!("Position fully closed, closing PDA");
+                    let lifetime_pnl = pnl_lamports(position_details.realized_pnl, contract_size, &scale);
+                    close_position_to_portfolio(position_details_account, user_portfolio_account, user_portfolio, lifetime_pnl)?;
                 } else {
                     // Partial close - save updated PositionDetails
                     save_position_details(position_details_account, &position_details)?;
@@ -587,12 +1181,10 @@ pub fn process_execute_cross_slab(
                 pnl
             } else {
                 // Case 3: Position reversal - close existing, open new in opposite direction
-                msg!("Position reversal: closing existing and opening opposite");
+                log_debug!("Position reversal: closing existing and opening opposite");
 
-                msg!("MARGIN DEBUG: Reversal - exposure and filled");
-                sol_log_64(current_exposure as u64, filled_qty as u64, 0, 0, 0);
-                msg!("MARGIN DEBUG: PD before reversal - qty and margin");
-                sol_log_64(position_details.total_qty as u64, position_details.margin_held as u64, 0, 0, 0);
+                log_debug!("MARGIN DEBUG: Reversal - exposure and filled", current_exposure as u64, filled_qty as u64, 0, 0, 0);
+                log_debug!("MARGIN DEBUG: PD before reversal - qty and margin", position_details.total_qty as u64, position_details.margin_held as u64, 0, 0, 0);
 
                 // Step 1: Close the entire existing position
                 let close_qty = if current_exposure > 0 { -current_abs } else { current_abs };
@@ -600,14 +1192,12 @@ pub fn process_execute_cross_slab(
                 let oracle_px = oracle_prices[i];
                 let (pnl, _, margin_to_release) = position_details.reduce_position(oracle_px, close_qty, 0i128, timestamp);
 
-                msg!("MARGIN DEBUG: After reversal close - margin_to_release");
-                sol_log_64(margin_to_release as u64, 0, 0, 0, 0);
+                log_debug!("MARGIN DEBUG: After reversal close - margin_to_release", margin_to_release as u64, 0, 0, 0, 0);
 
                 // Return all margin from closed position
                 if margin_to_release > 0 {
-                    msg!("Returning margin from closed position");
-                    msg!("MARGIN DEBUG: User equity BEFORE reversal return");
-                    sol_log_64(user_portfolio.equity as u64, 0, 0, 0, 0);
+                    log_debug!("Returning margin from closed position");
+                    log_debug!("MARGIN DEBUG: User equity BEFORE reversal return", user_portfolio.equity as u64, 0, 0, 0, 0);
                     return_margin_to_user(
                         user_portfolio_account,
                         user_portfolio,
@@ -615,31 +1205,30 @@ pub fn process_execute_cross_slab(
                         dlp_portfolio,
                         margin_to_release,
                     )?;
-                    msg!("MARGIN DEBUG: User equity AFTER reversal return");
-                    sol_log_64(user_portfolio.equity as u64, 0, 0, 0, 0);
+                    log_debug!("MARGIN DEBUG: User equity AFTER reversal return", user_portfolio.equity as u64, 0, 0, 0, 0);
                 }
 
                 // Close the old PositionDetails PDA (position fully closed)
-                msg!("Closing old position PDA");
-                close_position_details_pda(position_details_account, user_account)?;
+                log_debug!("Closing old position PDA");
+                let lifetime_pnl = pnl_lamports(position_details.realized_pnl, contract_size, &scale);
+                close_position_to_portfolio(position_details_account, user_portfolio_account, user_portfolio, lifetime_pnl)?;
 
                 // Step 2: Open new position in opposite direction with remaining quantity
                 let remaining_qty_abs = filled_abs - current_abs;
                 let new_qty = if is_buy { remaining_qty_abs as i64 } else { -(remaining_qty_abs as i64) };
 
-                msg!("Opening new position in opposite direction");
+                log_debug!("Opening new position in opposite direction");
 
                 // Create new PositionDetails PDA for the reversed position
-                use pinocchio::pubkey::find_program_address;
-                let slab_idx_bytes = slab_idx.to_le_bytes();
-                let instrument_idx_bytes = instrument_idx.to_le_bytes();
-                let seeds: &[&[u8]] = &[
-                    b"position",
-                    user_portfolio_account.key().as_ref(),
-                    &slab_idx_bytes,
-                    &instrument_idx_bytes,
-                ];
-                let (expected_pda, bump) = find_program_address(seeds, program_id);
+                // (same seeds as the closed one, so its bump is reused)
+                use crate::pda::derive_position_details_pda;
+                let (expected_pda, bump) = derive_position_details_pda(
+                    user_portfolio_account.key(),
+                    slab_idx,
+                    instrument_idx,
+                    position_details.bump,
+                    program_id,
+                );
 
                 // Verify PDA matches
                 if position_details_account.key() != &expected_pda {
@@ -653,29 +1242,18 @@ pub fn process_execute_cross_slab(
                     user_portfolio_account.key(),
                     slab_idx,
                     instrument_idx,
-                    user_account,
+                    rent_payer,
                     system_program,
                     program_id,
                     bump,
                 )?;
 
                 // Initialize new position with margin
-                let leverage_u128 = leverage as u128;
-                let remaining_qty_u128 = remaining_qty_abs as u128;
-
-                // Different margin calculation for 1x vs higher leverage
-                let new_margin = if leverage == 1 {
-                    // For 1x leverage: margin = quantity * 1_000 (1 contract = 1 SOL)
-                    remaining_qty_u128 * 1_000
-                } else {
-                    // For higher leverage: margin = (quantity * 10_000) / leverage
-                    (remaining_qty_u128 * 10_000) / leverage_u128
-                };
+                let new_margin = margin_required(new_qty, vwap_px, leverage, contract_size, contract_kind, scale);
 
-                msg!("MARGIN DEBUG: Opening reversed - remaining_qty, leverage, new_margin");
-                sol_log_64(remaining_qty_abs as u64, leverage as u64, new_margin as u64, 0, 0);
+                log_debug!("MARGIN DEBUG: Opening reversed - remaining_qty, leverage, new_margin", remaining_qty_abs as u64, leverage as u64, new_margin as u64, 0, 0);
 
-                let new_position = PositionDetails::new(
+                let mut new_position = PositionDetails::new(
                     *user_portfolio_account.key(),
                     slab_idx,
                     instrument_idx,
@@ -686,17 +1264,17 @@ pub fn process_execute_cross_slab(
                     0,  // margin_held starts at 0, will be added below
                     leverage,
                 );
+                new_position.contract_kind = contract_kind as u8;
 
                 // Save the new position
                 save_position_details(position_details_account, &new_position)?;
 
                 // Now add margin for the new position (this will be the only margin held)
                 let mut updated_position = new_position;
-                updated_position.add_to_position(vwap_px, new_qty, 0i128, timestamp, new_margin);
+                updated_position.add_to_position(vwap_px, new_qty, 0i128, timestamp, new_margin, leverage)?;
                 save_position_details(position_details_account, &updated_position)?;
 
-                msg!("MARGIN DEBUG: User equity BEFORE new margin transfer");
-                sol_log_64(user_portfolio.equity as u64, 0, 0, 0, 0);
+                log_debug!("MARGIN DEBUG: User equity BEFORE new margin transfer", user_portfolio.equity as u64, 0, 0, 0, 0);
 
                 // Transfer new margin from user to DLP
                 transfer_collateral_margin(
@@ -707,8 +1285,7 @@ pub fn process_execute_cross_slab(
                     new_margin,
                 )?;
 
-                msg!("MARGIN DEBUG: User equity AFTER new margin transfer");
-                sol_log_64(user_portfolio.equity as u64, 0, 0, 0, 0);
+                log_debug!("MARGIN DEBUG: User equity AFTER new margin transfer", user_portfolio.equity as u64, 0, 0, 0, 0);
 
                 // Update position_details reference for later use
                 position_details = updated_position;
@@ -717,13 +1294,13 @@ pub fn process_execute_cross_slab(
             }
         };
 
-        // Update exposure: filled_qty is signed (+buy, -sell from receipt)
+        // Update exposure: filled_qty is signed (+buy, -sell from receipt, checked by read_fill)
         let new_exposure = current_exposure + filled_qty;
 
         // If exposure is now zero, this position is fully closed from Portfolio's perspective
         // Zero out PositionDetails to prevent stale data accumulation
         if new_exposure == 0 {
-            msg!("Position fully closed - zeroing PositionDetails");
+            log_debug!("Position fully closed - zeroing PositionDetails");
             position_details.total_qty = 0;
             position_details.margin_held = 0;
         }
@@ -733,9 +1310,15 @@ pub fn process_execute_cross_slab(
             save_position_details(position_details_account, &position_details)?;
         }
 
-        total_realized_pnl = total_realized_pnl.saturating_add(realized_pnl);
+        // Contract sizes differ per slab, so convert before aggregating
+        total_realized_pnl = total_realized_pnl.saturating_add(pnl_lamports(realized_pnl, contract_size, &scale));
 
+        update_open_interest(registry, slab_idx, current_exposure, new_exposure)?;
         user_portfolio.update_exposure(slab_idx, instrument_idx, new_exposure);
+
+        // The DLP takes the other side, so its inventory moves opposite the user's
+        let dlp_exposure = dlp_portfolio.get_exposure(slab_idx, instrument_idx);
+        dlp_portfolio.update_exposure(slab_idx, instrument_idx, dlp_exposure.saturating_sub(filled_qty));
     }
 
     // Settle PnL between user and DLP via SOL transfer
@@ -748,106 +1331,166 @@ pub fn process_execute_cross_slab(
         total_realized_pnl,
     )?;
 
-    // Phase 3.5: Accrue insurance fees from taker fills
-    // Calculate total notional across all splits and accrue insurance
-    let mut total_notional: u128 = 0;
-    for split in splits.iter() {
-        // Notional = qty * price (both in 1e6 scale, so divide by 1e6)
-        // For v0 simplified: use limit_px as execution price
-        let notional = ((split.qty.abs() as u128) * (split.limit_px.abs() as u128)) / 1_000_000;
-        total_notional = total_notional.saturating_add(notional);
-    }
+    // Phase 3.5: Settle taker fees, split between the LP and insurance
+    // Notional comes from the receipts (what actually filled, at its VWAP),
+    // never the requested qty and price, scaled by each slab's contract size
+    let total_notional = total_fill_notional(
+        &filled_qtys[..splits.len()],
+        &vwap_pxs[..splits.len()],
+        &slab_headers[..splits.len()],
+    );
+    let total_fee = total_taker_fee(&fees[..splits.len()]);
 
-    if total_notional > 0 {
-        let accrual = registry.insurance_state.accrue_from_fill(
-            total_notional,
-            &registry.insurance_params,
-        );
-        if accrual > 0 {
-            msg!("Insurance accrued from fills");
-        }
-    }
+    settle_taker_fee(
+        user_portfolio_account,
+        user_portfolio,
+        dlp_portfolio_account,
+        dlp_portfolio,
+        &mut registry.insurance_state,
+        &registry.insurance_params,
+        total_fee,
+        total_notional,
+    )?;
 
     // Phase 4: Calculate IM by summing margin_held from all PositionDetails
     // IM = sum of all margin_held across positions (actual collateral committed)
-    // Only calculate for positions that exist in Portfolio's exposure array
-    let im_required = calculate_portfolio_margin_from_exposures(
+    // MM = blended tiered maintenance margin per position (larger positions pay more)
+    // Every active exposure in the Portfolio must have its PositionDetails passed in,
+    // either for a split or among the extra accounts for untraded positions
+    let (im_required, mm_required) = calculate_portfolio_margin_from_exposures(
         user_portfolio,
         user_portfolio_account,
+        registry,
         position_details_accounts,
+        extra_position_details,
         program_id,
     )?;
 
-    msg!("Calculated total margin from positions");
+    log_debug!("Calculated total margin from positions");
 
-    user_portfolio.update_margin(im_required, im_required / 2); // MM = IM / 2 for v0
+    user_portfolio.update_margin(im_required, mm_required);
 
     // Phase 5: Check if portfolio has sufficient margin
-    // Equity now includes realized PnL from this trade
-    if !user_portfolio.has_sufficient_margin() {
-        msg!("Error: Insufficient margin");
-        return Err(PercolatorError::PortfolioInsufficientMargin);
+    // Equity now includes realized PnL from this trade, marked with the
+    // traded positions' unrealized PnL at the oracle; deposited SOL counts
+    // toward margin net of its collateral haircut. A liquidated account is
+    // under margin by definition, so its closing fills are exempt.
+    if !liquidation {
+        let unrealized_pnl = batch_unrealized_pnl(
+            position_details_accounts,
+            &oracle_prices[..splits.len()],
+            &slab_headers[..splits.len()],
+        )?;
+        let haircut_bps = registry.collateral_haircut_bps(&crate::state::NATIVE_SOL_MINT);
+        if !user_portfolio.has_sufficient_margin_mtm(unrealized_pnl, haircut_bps) {
+            msg!("Error: Insufficient margin");
+            return Err(PercolatorError::PortfolioInsufficientMargin);
+        }
     }
 
-    msg!("ExecuteCrossSlab completed successfully");
+    let result = ExecutionResult::from_fills(
+        &filled_qtys[..splits.len()],
+        &vwap_pxs[..splits.len()],
+        total_realized_pnl,
+        user_portfolio.equity,
+    );
+    pinocchio::cpi::set_return_data(&result.to_bytes());
+
+    log_debug!("ExecuteCrossSlab completed successfully");
     Ok(())
 }
 
-/// Calculate net exposure across all slabs for the same instrument (v0 simplified)
-fn calculate_net_exposure(portfolio: &Portfolio) -> i64 {
-    // For v0, sum all exposures (assuming same instrument across slabs)
-    let mut net = 0i64;
-    for i in 0..portfolio.exposure_count as usize {
-        net += portfolio.exposures[i].2;
-    }
-    net
+/// Margin required to hold `qty` (sign ignored) at `price`, in lamports
+///
+/// The single margin formula: the lamports transferred to the DLP on open,
+/// held in PositionDetails.margin_held, and counted as IM by
+/// calculate_portfolio_margin_from_exposures all come from here.
+///
+/// Notional (qty at the instrument's qty decimals, converted with
+/// `notional_lamports` for the slab's contract size and ScaleConfig) divided
+/// by leverage, so margin and PnL settle in the same units. Inverse qty is
+/// quote notional, so it's first converted to base at `price`. Rounds up (see
+/// `margin_lamports`); releases on close round down (see `margin_release`).
+pub(crate) fn margin_required(
+    qty: i64,
+    price: i64,
+    leverage: u8,
+    contract_size: i64,
+    contract_kind: ContractKind,
+    scale: impl Into<ScaleConfig>,
+) -> u128 {
+    margin_lamports(qty, price, leverage, contract_size, contract_kind, &scale.into())
 }
 
-/// Calculate initial margin requirement based on actual leverage
-/// For 1x (spot): minimal margin (~0.1% of notional)
-/// For 10x (max): 10% of notional
-/// Formula: IM = abs(net_exposure) * price * leverage / (max_leverage * 1e6)
-fn calculate_initial_margin(net_exposure: i64, splits: &[SlabSplit], leverage: u8) -> u128 {
-    if splits.is_empty() {
-        return 0;
+/// Unrealized PnL (lamports) of the batch's positions at their oracle prices
+///
+/// Read after settlement, so each position is marked as the fills left it;
+/// missing or closed PositionDetails count as flat.
+fn batch_unrealized_pnl(
+    position_details_accounts: &[AccountInfo],
+    oracle_prices: &[i64],
+    slab_headers: &[SlabHeaderSnapshot],
+) -> Result<i128, PercolatorError> {
+    let mut total: i128 = 0;
+    for ((account, &oracle_px), header) in position_details_accounts.iter().zip(oracle_prices).zip(slab_headers) {
+        if let Some(position) = load_position_details(account)? {
+            let pnl = pnl_lamports(position.unrealized_pnl(oracle_px), header.contract_size, &header.scale);
+            total = total.saturating_add(pnl);
+        }
     }
+    Ok(total)
+}
+
+/// Notional of a single fill at the slab's contract size (see `contract_notional`)
+/// Saturates instead of overflowing on adversarial inputs
+fn fill_notional(filled_qty: i64, execution_px: i64, header: &SlabHeaderSnapshot) -> u128 {
+    contract_notional(filled_qty, execution_px, header.contract_size, header.contract_kind, header.scale.instrument())
+}
 
-    const MAX_LEVERAGE: u128 = 10;
-    let abs_exposure = net_exposure.abs() as u128;
-    let avg_price = splits[0].limit_px as u128; // Use first split price
-    let leverage_u128 = leverage as u128;
+/// Total notional across fills, saturating at u128::MAX
+fn total_fill_notional(filled_qtys: &[i64], execution_prices: &[i64], headers: &[SlabHeaderSnapshot]) -> u128 {
+    filled_qtys
+        .iter()
+        .zip(execution_prices.iter())
+        .zip(headers.iter())
+        .fold(0u128, |total, ((&qty, &px), header)| total.saturating_add(fill_notional(qty, px, header)))
+}
 
-    msg!("DEBUG: calculate_initial_margin called");
-    if leverage == 1 {
-        msg!("DEBUG: Using 1x leverage");
-    } else if leverage == 10 {
-        msg!("DEBUG: Using 10x leverage");
+/// IM and notional one PositionDetails account contributes, in lamports
+///
+/// IM is the margin_held the execute path transferred (see `margin_required`),
+/// so the margin checked is exactly the margin moved. Leverage recovers the
+/// notional from it for the MM tiers.
+pub(crate) fn position_im_and_notional(data: &[u8]) -> Option<(u128, u128)> {
+    if data.len() < POSITION_DETAILS_SIZE {
+        return None;
     }
-
-    // IM = (exposure * price) / (leverage * 1e12)
-    // This implements: IM = notional_value / leverage (standard IMR formula)
-    // Note: exposure and price are both in 1e6 scale, so exposure * price = 1e12
-    // We divide by (leverage * 1e12) to convert to lamports and apply leverage ratio
-    // Examples (with 1 contract ≈ $0.20 = 200,000 lamports):
-    // - 1x: IM = (1M * 200M) / (1 * 1e12) = 200K lamports = 0.0002 SOL (100% collateral)
-    // - 5x: IM = (1M * 200M) / (5 * 1e12) = 40K lamports = 0.00004 SOL (20% collateral)
-    // - 10x: IM = (1M * 200M) / (10 * 1e12) = 20K lamports = 0.00002 SOL (10% collateral)
-    // For v0 proof: if net_exposure = 0, IM = 0!
-    let im_result = (abs_exposure * avg_price) / (leverage_u128 * 1_000_000_000_000);
-    msg!("DEBUG: IM calculation complete");
-    im_result
+    let details = unsafe { core::ptr::read_unaligned(data.as_ptr() as *const PositionDetails) };
+    let notional = details.margin_held.saturating_mul(details.leverage.max(1) as u128);
+    Some((details.margin_held, notional))
 }
 
 /// Calculate total portfolio margin by summing margin_held from PositionDetails
 /// for ACTIVE positions in the Portfolio's exposure array
-/// Returns: Total IM in lamports (u128)
-fn calculate_portfolio_margin_from_exposures(
+///
+/// MM for each position is blended across its slab's size tiers, applied to
+/// the position notional (margin_held * leverage). Legs on different slabs
+/// or instruments never offset each other, so margins are summed rather
+/// than taken on a cross-slab net.
+/// Fails with MissingPositionDetails if any active exposure has no matching
+/// PositionDetails account among `position_details_accounts` (the traded
+/// positions) or `extra_position_details` (every other open position).
+/// Returns: (total IM, total MM) in lamports
+pub(crate) fn calculate_portfolio_margin_from_exposures(
     portfolio: &Portfolio,
     portfolio_account: &AccountInfo,
+    registry: &SlabRegistry,
     position_details_accounts: &[AccountInfo],
+    extra_position_details: &[AccountInfo],
     program_id: &Pubkey,
-) -> Result<u128, PercolatorError> {
+) -> Result<(u128, u128), PercolatorError> {
     let mut total_margin: u128 = 0;
+    let mut total_mm: u128 = 0;
 
     // Iterate through active exposures in the Portfolio
     for i in 0..portfolio.exposure_count as usize {
@@ -861,25 +1504,9 @@ fn calculate_portfolio_margin_from_exposures(
             continue;
         }
 
-        // Derive the expected PositionDetails PDA for this exposure
-        use pinocchio::pubkey::find_program_address;
-        let slab_idx_bytes = slab_idx.to_le_bytes();
-        let instrument_idx_bytes = instrument_idx.to_le_bytes();
-        let seeds: &[&[u8]] = &[
-            b"position",
-            portfolio_account.key().as_ref(),
-            &slab_idx_bytes,
-            &instrument_idx_bytes,
-        ];
-        let (expected_pda, _bump) = find_program_address(seeds, program_id);
-
-        // Find the matching account in position_details_accounts
+        // Find the matching account among the traded and extra positions
         let mut found = false;
-        for pd_account in position_details_accounts {
-            if pd_account.key() != &expected_pda {
-                continue;
-            }
-
+        for pd_account in position_details_accounts.iter().chain(extra_position_details) {
             // Skip if account is not owned by router program
             if pd_account.owner() != program_id {
                 continue;
@@ -899,39 +1526,50 @@ fn calculate_portfolio_margin_from_exposures(
                 continue;
             }
 
-            // Read margin_held (u128 at offset 112)
-            let margin_offset = 112;
-            if data.len() < margin_offset + 16 {
+            // Match portfolio (offset 8), slab_index (u16 at 40), instrument_index (u16 at 42)
+            if &data[8..40] != portfolio_account.key().as_ref()
+                || u16::from_le_bytes([data[40], data[41]]) != slab_idx
+                || u16::from_le_bytes([data[42], data[43]]) != instrument_idx
+            {
+                continue;
+            }
+
+            // Verify the address using the bump stored in the account (u8 at offset 44)
+            use crate::pda::derive_position_details_pda;
+            let (expected_pda, _bump) = derive_position_details_pda(
+                portfolio_account.key(),
+                slab_idx,
+                instrument_idx,
+                data[44],
+                program_id,
+            );
+            if pd_account.key() != &expected_pda {
                 continue;
             }
 
-            // Read u128 little-endian
-            let margin_bytes = &data[margin_offset..margin_offset + 16];
-            let margin_low = u64::from_le_bytes([
-                margin_bytes[0], margin_bytes[1], margin_bytes[2], margin_bytes[3],
-                margin_bytes[4], margin_bytes[5], margin_bytes[6], margin_bytes[7],
-            ]) as u128;
-            let margin_high = u64::from_le_bytes([
-                margin_bytes[8], margin_bytes[9], margin_bytes[10], margin_bytes[11],
-                margin_bytes[12], margin_bytes[13], margin_bytes[14], margin_bytes[15],
-            ]) as u128;
-            let margin_held = margin_low | (margin_high << 64);
+            let (margin_held, notional) = match position_im_and_notional(&data) {
+                Some(margin) => margin,
+                None => continue,
+            };
 
             total_margin = total_margin.saturating_add(margin_held);
+            if let Some(entry) = registry.slabs.get(slab_idx as usize) {
+                let mm = entry.maintenance_margin(position_qty.unsigned_abs(), notional);
+                total_mm = total_mm.saturating_add(mm);
+            }
             found = true;
             break;
         }
 
-        // If we didn't find the PositionDetails account, that's an error
-        // Every active exposure should have a corresponding PositionDetails
+        // Every active exposure must have its PositionDetails passed in; skipping
+        // one would let a caller withhold a losing position to understate margin
         if !found {
-            msg!("ERROR: PositionDetails not found for active exposure");
-            // Don't error out - just skip this exposure
-            // This can happen if the account wasn't passed in
+            msg!("Error: PositionDetails not found for active exposure");
+            return Err(PercolatorError::MissingPositionDetails);
         }
     }
 
-    Ok(total_margin)
+    Ok((total_margin, total_mm))
 }
 
 /// Calculate realized PnL from a fill
@@ -940,12 +1578,15 @@ fn calculate_portfolio_margin_from_exposures(
 /// Logic:
 /// - If opening/adding to position: No realized PnL (return 0)
 /// - If reducing/closing position: PnL = qty_closed * (exit_price - entry_price)
+/// - Inverse contracts: PnL = qty_closed * (1 / entry_price - 1 / exit_price)
 fn calculate_realized_pnl(
     current_exposure: i64,
     filled_qty: i64,
     side: u8,
     exit_price: i64,
     entry_price: i64,
+    contract_kind: ContractKind,
+    scale: &ScaleConfig,
 ) -> i128 {
     // Determine direction of fill
     let fill_direction = if side == 0 { filled_qty } else { -filled_qty };
@@ -965,15 +1606,305 @@ fn calculate_realized_pnl(
     // PnL = qty_closed * (exit_price - entry_price)
     // Account for long vs short position
     let price_diff = (exit_price as i128) - (entry_price as i128);
-    let pnl = if current_exposure > 0 {
+    let signed_raw = if current_exposure > 0 {
         // Closing long: profit when exit > entry
-        (qty_closed as i128) * price_diff / 1_000_000 // Scale down from 1e6
+        (qty_closed as i128) * price_diff
     } else {
         // Closing short: profit when exit < entry
-        -(qty_closed as i128) * price_diff / 1_000_000
+        -(qty_closed as i128) * price_diff
+    };
+
+    let price_unit = scale.instrument().price_unit() as i128;
+    match contract_kind {
+        ContractKind::Linear => signed_raw / price_unit,
+        ContractKind::Inverse => {
+            // qty / entry - qty / exit = qty * (exit - entry) / (entry * exit)
+            let denom = (entry_price as i128) * (exit_price as i128);
+            if denom > 0 {
+                signed_raw.saturating_mul(price_unit) / denom
+            } else {
+                0
+            }
+        }
+    }
+}
+
+/// Slab header fields the execute path needs, captured before any CPI
+#[derive(Clone, Copy, Default)]
+pub(crate) struct SlabHeaderSnapshot {
+    /// Header seqno, passed to commit_fill for TOCTOU protection
+    pub expected_seqno: u32,
+    /// Slab program version hash
+    pub version_hash: [u8; 32],
+    /// Contract multiplier (1e6 scale)
+    pub contract_size: i64,
+    /// Linear or inverse quoting
+    pub contract_kind: ContractKind,
+    /// Oracle / price / quantity / collateral decimals
+    pub scale: ScaleConfig,
+    /// DLP user whose portfolio is the slab's counterparty
+    pub lp_owner: Pubkey,
+}
+
+impl From<&SlabHeader> for SlabHeaderSnapshot {
+    fn from(header: &SlabHeader) -> Self {
+        Self {
+            expected_seqno: header.seqno,
+            version_hash: header.version_hash,
+            contract_size: header.contract_size,
+            contract_kind: ContractKind::from_u8(header.contract_kind),
+            scale: header.scale_config(),
+            lp_owner: header.lp_owner,
+        }
+    }
+}
+
+/// Reject a DLP portfolio that doesn't belong to the slab's lp_owner
+///
+/// Otherwise a caller could settle against any well-funded portfolio instead
+/// of the liquidity provider actually backing the slab.
+pub(crate) fn check_counterparty(dlp_portfolio: &Portfolio, lp_owner: &Pubkey) -> Result<(), PercolatorError> {
+    if &dlp_portfolio.user != lp_owner {
+        msg!("Error: DLP portfolio is not the slab's lp_owner");
+        return Err(PercolatorError::InvalidCounterparty);
+    }
+    Ok(())
+}
+
+/// Read a slab's lp_owner from its header
+pub(crate) fn read_slab_lp_owner(slab_account: &AccountInfo) -> Result<Pubkey, PercolatorError> {
+    let slab_data = slab_account
+        .try_borrow_data()
+        .map_err(|_| PercolatorError::InvalidAccount)?;
+    if slab_data.len() < SlabHeader::LEN {
+        msg!("Error: Invalid slab account data");
+        return Err(PercolatorError::InvalidAccount);
+    }
+    Ok(unsafe { &*(slab_data.as_ptr() as *const SlabHeader) }.lp_owner)
+}
+
+/// Build commit_fill instruction data (32 bytes total)
+///
+/// Layout: discriminator (1) + expected_seqno (4) + order_type (1) + side (1) + qty (8) + limit_px (8)
+/// + oracle_px (8, blended into the slab's mark price) + liquidation (1, waives the taker fee)
+pub(crate) fn commit_fill_data(
+    expected_seqno: u32,
+    order_type: u8,
+    side: u8,
+    qty: i64,
+    limit_px: i64,
+    oracle_px: i64,
+    liquidation: bool,
+) -> [u8; 32] {
+    let mut data = [0u8; 32];
+    data[0] = 1; // CommitFill discriminator
+    data[1..5].copy_from_slice(&expected_seqno.to_le_bytes());
+    data[5] = order_type;
+    data[6] = side;
+    data[7..15].copy_from_slice(&qty.to_le_bytes());
+    data[15..23].copy_from_slice(&limit_px.to_le_bytes());
+    data[23..31].copy_from_slice(&oracle_px.to_le_bytes());
+    data[31] = liquidation as u8;
+    data
+}
+
+/// Check a receipt account belongs to the slab program that fills into it
+///
+/// Receipts are read by casting raw data, so one owned by any other program
+/// could carry a pre-written, favorable fill. Fails with InvalidReceipt.
+pub(crate) fn check_receipt_owner(
+    receipt_account: &AccountInfo,
+    slab_program_id: &Pubkey,
+) -> Result<(), PercolatorError> {
+    if receipt_account.owner() != slab_program_id {
+        msg!("Error: Receipt account is not owned by the slab program");
+        return Err(PercolatorError::InvalidReceipt);
+    }
+    Ok(())
+}
+
+/// Filled quantity and VWAP from a slab's receipt
+///
+/// Slabs round fills down to their lot size, so a fill smaller than the
+/// split is accepted; one larger than requested or on the wrong side is not.
+///
+/// # Returns
+/// * `(filled_qty, vwap_px)`, or InvalidReceipt if the slab never wrote it
+///   or the fill doesn't fit the split
+pub(crate) fn read_fill(receipt: &FillReceipt, split: &SlabSplit) -> Result<(i64, i64), PercolatorError> {
+    if !receipt.is_used() {
+        msg!("Error: Receipt not written by slab");
+        return Err(PercolatorError::InvalidReceipt);
+    }
+
+    // Receipts are signed like the split (+buy, -sell); zero is a no-fill
+    let filled = receipt.filled_qty;
+    let wrong_side = filled != 0 && filled.signum() != split.signed_qty().signum();
+    if wrong_side || filled.unsigned_abs() > split.qty.unsigned_abs() {
+        msg!("Error: Fill exceeds requested split");
+        return Err(PercolatorError::InvalidReceipt);
+    }
+    Ok((filled, receipt.vwap_px))
+}
+
+/// Reject a reduce-only split that doesn't strictly shrink the position
+///
+/// Trading with the current exposure (or from flat) would increase it, and
+/// trading past it would reverse it; both fail with WouldIncreasePosition.
+pub(crate) fn check_reduce_only(current_exposure: i64, split: &SlabSplit) -> Result<(), PercolatorError> {
+    let is_buy = split.side == 0;
+    let same_direction = (is_buy && current_exposure >= 0) || (!is_buy && current_exposure <= 0);
+    if same_direction || split.qty.unsigned_abs() > current_exposure.unsigned_abs() {
+        msg!("Error: Reduce-only order would increase position");
+        return Err(PercolatorError::WouldIncreasePosition);
+    }
+    Ok(())
+}
+
+/// Whether a split's receipt filled nothing and should skip settlement
+pub(crate) fn is_zero_fill(filled_qty: i64) -> bool {
+    filled_qty == 0
+}
+
+/// Enforce the batch's atomicity once every receipt is in
+///
+/// Router orders are immediate-or-cancel: commit_fill fills what it can and
+/// nothing rests. By default a split that filled nothing fails the whole
+/// batch with InsufficientLiquidity, reverting the legs that did fill. With
+/// `allow_partial_batch` the dry splits are skipped instead and the filled
+/// ones settle; the usual margin check on the resulting portfolio still runs.
+pub(crate) fn check_batch_fills(filled_qtys: &[i64], allow_partial_batch: bool) -> Result<(), PercolatorError> {
+    if !allow_partial_batch && filled_qtys.iter().any(|&qty| is_zero_fill(qty)) {
+        msg!("Error: Split filled nothing in all-or-nothing batch");
+        return Err(PercolatorError::InsufficientLiquidity);
+    }
+    Ok(())
+}
+
+/// Maximum DLP portfolios one ExecuteCrossSlab can choose between
+pub const MAX_DLP_COUNTERPARTIES: usize = 4;
+
+/// Pick the DLP to take the other side of `split`
+///
+/// `dlp_portfolios` pairs each candidate with its lamport balance. Prefers the
+/// DLP whose inventory on the slab the trade offsets (a user buy against a DLP
+/// that is long), taking the largest such inventory; if the trade would net
+/// down no one, falls back to the best-capitalized DLP.
+///
+/// # Returns
+/// * Index into `dlp_portfolios`, or None if it is empty
+pub fn select_counterparty(
+    dlp_portfolios: &[(&Portfolio, u64)],
+    slab_idx: Option<u16>,
+    split: &SlabSplit,
+) -> Option<usize> {
+    // The DLP's inventory moves opposite the user, so a user buy nets down longs
+    let user_buys = split.side == 0;
+
+    let mut best_offset: Option<(usize, i64)> = None;
+    if let Some(slab_idx) = slab_idx {
+        for (i, (portfolio, _)) in dlp_portfolios.iter().enumerate() {
+            let inventory = portfolio.get_exposure(slab_idx, 0);
+            let offsets = if user_buys { inventory > 0 } else { inventory < 0 };
+            if offsets && best_offset.map_or(true, |(_, best)| inventory.abs() > best) {
+                best_offset = Some((i, inventory.abs()));
+            }
+        }
+    }
+    if let Some((i, _)) = best_offset {
+        return Some(i);
+    }
+
+    let mut best_capital: Option<(usize, u64)> = None;
+    for (i, (_, lamports)) in dlp_portfolios.iter().enumerate() {
+        if best_capital.map_or(true, |(_, best)| *lamports > best) {
+            best_capital = Some((i, *lamports));
+        }
+    }
+    best_capital.map(|(i, _)| i)
+}
+
+/// Worst-case lamports the DLP pays out for one split
+///
+/// Only splits that reduce (or reverse) the user's position pay out: the
+/// released margin plus any profit realized at `oracle_px`. Assumes the whole
+/// split fills, which bounds any partial fill.
+pub(crate) fn split_dlp_obligation(
+    current_exposure: i64,
+    split: &SlabSplit,
+    position: Option<&PositionDetails>,
+    oracle_px: i64,
+    contract_size: i64,
+    scale: impl Into<ScaleConfig>,
+) -> u128 {
+    let is_buy = split.side == 0;
+    let same_direction = (is_buy && current_exposure >= 0) || (!is_buy && current_exposure <= 0);
+    let position = match position {
+        Some(position) if !same_direction => position,
+        _ => return 0,
     };
 
-    pnl
+    let close_abs = split.qty.abs().min(current_exposure.abs());
+    let close_qty = if current_exposure > 0 { -close_abs } else { close_abs };
+
+    let mut simulated = *position;
+    let (pnl, _, margin_to_release) = simulated.reduce_position(oracle_px, close_qty, 0i128, 0);
+    let profit = pnl_lamports(pnl, contract_size, &scale.into()).max(0) as u128;
+
+    margin_to_release.saturating_add(profit)
+}
+
+/// Reject the order if the DLP plus insurance can't cover its worst case
+pub(crate) fn check_dlp_solvency(
+    obligation: u128,
+    dlp_lamports: u64,
+    insurance_balance: u128,
+) -> Result<(), PercolatorError> {
+    let available = (dlp_lamports as u128).saturating_add(insurance_balance);
+    if obligation > available {
+        msg!("Error: DLP cannot cover worst-case payout");
+        return Err(PercolatorError::CounterpartyInsolvent);
+    }
+    Ok(())
+}
+
+/// Lamports moved to settle `realized_pnl` (its magnitude)
+///
+/// Errors with Overflow instead of truncating PnL beyond u64.
+pub(crate) fn pnl_transfer_lamports(realized_pnl: i128) -> Result<u64, PercolatorError> {
+    u64::try_from(realized_pnl.unsigned_abs()).map_err(|_| {
+        msg!("Error: Realized PnL exceeds u64 lamports");
+        PercolatorError::Overflow
+    })
+}
+
+/// Lamports moved for a margin transfer
+///
+/// Errors with Overflow instead of truncating margin beyond u64.
+pub(crate) fn margin_transfer_lamports(margin_lamports: u128) -> Result<u64, PercolatorError> {
+    u64::try_from(margin_lamports).map_err(|_| {
+        msg!("Error: Margin exceeds u64 lamports");
+        PercolatorError::Overflow
+    })
+}
+
+/// Move lamports between two program-owned accounts
+///
+/// Checks the source balance and guards both sides against wrap-around.
+pub(crate) fn move_lamports(from: &AccountInfo, to: &AccountInfo, amount: u64) -> Result<(), PercolatorError> {
+    if from.lamports() < amount {
+        return Err(PercolatorError::InsufficientFunds);
+    }
+    let to_balance = to
+        .lamports()
+        .checked_add(amount)
+        .ok_or(PercolatorError::Overflow)?;
+
+    *from.try_borrow_mut_lamports()
+        .map_err(|_| PercolatorError::InsufficientFunds)? -= amount;
+    *to.try_borrow_mut_lamports()
+        .map_err(|_| PercolatorError::InsufficientFunds)? = to_balance;
+    Ok(())
 }
 
 /// Settle PnL between user and DLP portfolios (counterparty)
@@ -983,6 +1914,7 @@ fn calculate_realized_pnl(
 /// - User loses (-PnL) → Transfer SOL from User Portfolio to DLP Portfolio
 ///
 /// Both portfolios hold actual SOL lamports, so we do real System Program transfers.
+/// `realized_pnl` must already be in lamports (see `pnl_lamports`).
 fn settle_pnl(
     user_portfolio_account: &AccountInfo,
     user_portfolio: &mut Portfolio,
@@ -991,72 +1923,110 @@ fn settle_pnl(
     system_program: &AccountInfo,
     realized_pnl: i128,
 ) -> Result<(), PercolatorError> {
-    use pinocchio::{msg, log::sol_log_64};
-    msg!("SETTLE_PNL DEBUG: Called with realized_pnl");
-    sol_log_64(realized_pnl as u64, user_portfolio.equity as u64, 0, 0, 0);
+    use pinocchio::msg;
+    log_debug!("SETTLE_PNL DEBUG: Called with realized_pnl");
 
     if realized_pnl == 0 {
-        msg!("SETTLE_PNL DEBUG: PnL is zero, skipping");
+        log_debug!("SETTLE_PNL DEBUG: PnL is zero, skipping");
         return Ok(());
     }
 
+    // Convert before touching any accounting so an oversized PnL fails cleanly
+    let amount = pnl_transfer_lamports(realized_pnl)?;
+    log_debug!("SETTLE_PNL DEBUG: amount, is_profit", amount, (realized_pnl > 0) as u64, 0, 0, 0);
+
     // Update PnL accounting for both parties
     user_portfolio.pnl = user_portfolio.pnl.saturating_add(realized_pnl);
     dlp_portfolio.pnl = dlp_portfolio.pnl.saturating_sub(realized_pnl);
 
     // Update equity to reflect the PnL change
-    msg!("SETTLE_PNL DEBUG: Updating equity - before");
-    sol_log_64(user_portfolio.equity as u64, 0, 0, 0, 0);
     user_portfolio.equity = user_portfolio.equity.saturating_add(realized_pnl);
     dlp_portfolio.equity = dlp_portfolio.equity.saturating_sub(realized_pnl);
-    msg!("SETTLE_PNL DEBUG: Updating equity - after");
-    sol_log_64(user_portfolio.equity as u64, 0, 0, 0, 0);
 
     // Perform actual SOL transfer using direct lamport manipulation
     // Both accounts are owned by the same program, so we can directly modify lamports
     if realized_pnl > 0 {
         // User won → Transfer SOL from DLP to User
-        let profit = realized_pnl as u64;
-
-        // Check DLP has sufficient lamports
-        if dlp_portfolio_account.lamports() < profit {
+        log_debug!("SETTLE_PNL DEBUG: Transferring lamports", amount, user_portfolio_account.lamports(), dlp_portfolio_account.lamports(), 0, 0);
+        move_lamports(dlp_portfolio_account, user_portfolio_account, amount).map_err(|e| {
             msg!("Error: DLP portfolio insufficient SOL to cover user profit");
-            return Err(PercolatorError::InsufficientFunds);
-        }
+            e
+        })?;
 
-        // Direct lamport manipulation (both accounts owned by same program)
-        msg!("SETTLE_PNL DEBUG: Transferring lamports");
-        sol_log_64(profit, user_portfolio_account.lamports(), dlp_portfolio_account.lamports(), 0, 0);
-        *dlp_portfolio_account.try_borrow_mut_lamports()
-            .map_err(|_| PercolatorError::InsufficientFunds)? -= profit;
-        *user_portfolio_account.try_borrow_mut_lamports()
-            .map_err(|_| PercolatorError::InsufficientFunds)? += profit;
-
-        msg!("User profit transferred from DLP portfolio");
-        msg!("SETTLE_PNL DEBUG: After transfer");
-        sol_log_64(user_portfolio_account.lamports(), dlp_portfolio_account.lamports(), 0, 0, 0);
+        log_debug!("User profit transferred from DLP portfolio");
+        log_debug!("SETTLE_PNL DEBUG: After transfer", user_portfolio_account.lamports(), dlp_portfolio_account.lamports(), 0, 0, 0);
     } else {
         // User lost → Transfer SOL from User to DLP
-        let loss = (-realized_pnl) as u64;
-
-        // Check user has sufficient lamports
-        if user_portfolio_account.lamports() < loss {
+        move_lamports(user_portfolio_account, dlp_portfolio_account, amount).map_err(|e| {
             msg!("Error: User portfolio insufficient SOL to cover loss");
-            return Err(PercolatorError::InsufficientFunds);
-        }
-
-        // Direct lamport manipulation (both accounts owned by same program)
-        *user_portfolio_account.try_borrow_mut_lamports()
-            .map_err(|_| PercolatorError::InsufficientFunds)? -= loss;
-        *dlp_portfolio_account.try_borrow_mut_lamports()
-            .map_err(|_| PercolatorError::InsufficientFunds)? += loss;
+            e
+        })?;
 
-        msg!("User loss transferred to DLP portfolio");
+        log_debug!("User loss transferred to DLP portfolio");
     }
 
     Ok(())
 }
 
+/// Sum of the taker fees the slabs charged across a batch
+///
+/// Receipts carry fees in the same 1e6 quote scale the router books
+/// insurance notional in; a negative fee (never written by the slab) counts
+/// as zero.
+pub(crate) fn total_taker_fee(fees: &[i64]) -> u128 {
+    fees.iter().map(|&fee| fee.max(0) as u128).sum()
+}
+
+/// Debit the batch's taker fee from the user and split it
+///
+/// The user pays `fee` once, as a realized loss, and it is added to their
+/// `lifetime_fees_paid`. The LP share is credited to
+/// the DLP portfolio and the insurance share to `insurance_state`. All the
+/// lamports move to the DLP portfolio account, which custodies the insurance
+/// share, but only the LP share counts toward DLP equity.
+///
+/// # Returns
+/// `(to_lp, to_insurance)`
+pub(crate) fn settle_taker_fee(
+    user_portfolio_account: &AccountInfo,
+    user_portfolio: &mut Portfolio,
+    dlp_portfolio_account: &AccountInfo,
+    dlp_portfolio: &mut Portfolio,
+    insurance_state: &mut InsuranceState,
+    insurance_params: &InsuranceParams,
+    fee: u128,
+    notional: u128,
+) -> Result<(u128, u128), PercolatorError> {
+    if fee == 0 {
+        return Ok((0, 0));
+    }
+
+    // Convert before touching any accounting so an oversized fee fails cleanly
+    let amount = u64::try_from(fee).map_err(|_| {
+        msg!("Error: Taker fee exceeds u64 lamports");
+        PercolatorError::Overflow
+    })?;
+
+    move_lamports(user_portfolio_account, dlp_portfolio_account, amount).map_err(|e| {
+        msg!("Error: User portfolio insufficient SOL to cover taker fee");
+        e
+    })?;
+
+    let (to_lp, to_insurance) = insurance_state.split_taker_fee(fee, notional, insurance_params);
+
+    let fee_i128 = fee as i128;
+    user_portfolio.pnl = user_portfolio.pnl.saturating_sub(fee_i128);
+    user_portfolio.equity = user_portfolio.equity.saturating_sub(fee_i128);
+    user_portfolio.lifetime_fees_paid = user_portfolio.lifetime_fees_paid.saturating_add(fee_i128);
+
+    let lp_i128 = to_lp as i128;
+    dlp_portfolio.pnl = dlp_portfolio.pnl.saturating_add(lp_i128);
+    dlp_portfolio.equity = dlp_portfolio.equity.saturating_add(lp_i128);
+
+    log_debug!("Taker fee settled", amount, to_lp as u64, to_insurance as u64, 0, 0);
+    Ok((to_lp, to_insurance))
+}
+
 /// Transfer collateral margin from user to DLP when opening/increasing position
 fn transfer_collateral_margin(
     user_portfolio_account: &AccountInfo,
@@ -1069,22 +2039,16 @@ fn transfer_collateral_margin(
         return Ok(());
     }
 
-    let margin = margin_lamports as u64;
-
-    // Check user has sufficient lamports
-    if user_portfolio_account.lamports() < margin {
-        msg!("Error: User portfolio insufficient SOL for margin");
-        return Err(PercolatorError::InsufficientFunds);
-    }
+    let margin = margin_transfer_lamports(margin_lamports)?;
 
     // Transfer SOL from user to DLP (direct lamport manipulation)
-    *user_portfolio_account.try_borrow_mut_lamports()
-        .map_err(|_| PercolatorError::InsufficientFunds)? -= margin;
-    *dlp_portfolio_account.try_borrow_mut_lamports()
-        .map_err(|_| PercolatorError::InsufficientFunds)? += margin;
+    move_lamports(user_portfolio_account, dlp_portfolio_account, margin).map_err(|e| {
+        msg!("Error: User portfolio insufficient SOL for margin");
+        e
+    })?;
 
     // Update equity tracking
-    let margin_i128 = margin as i128;
+    let margin_i128 = i128::from(margin);
     user_portfolio.equity = user_portfolio.equity.saturating_sub(margin_i128);
     dlp_portfolio.equity = dlp_portfolio.equity.saturating_add(margin_i128);
 
@@ -1092,7 +2056,7 @@ fn transfer_collateral_margin(
     user_portfolio.principal = user_portfolio.principal.saturating_sub(margin_i128);
     dlp_portfolio.principal = dlp_portfolio.principal.saturating_add(margin_i128);
 
-    msg!("Collateral margin transferred to DLP");
+    log_debug!("Collateral margin transferred to DLP");
     Ok(())
 }
 
@@ -1108,22 +2072,16 @@ fn return_margin_to_user(
         return Ok(());
     }
 
-    let margin = margin_lamports as u64;
-
-    // Check DLP has sufficient lamports
-    if dlp_portfolio_account.lamports() < margin {
-        msg!("Error: DLP portfolio insufficient SOL to return margin");
-        return Err(PercolatorError::InsufficientFunds);
-    }
+    let margin = margin_transfer_lamports(margin_lamports)?;
 
     // Transfer SOL from DLP to User (reverse of transfer_collateral_margin)
-    *dlp_portfolio_account.try_borrow_mut_lamports()
-        .map_err(|_| PercolatorError::InsufficientFunds)? -= margin;
-    *user_portfolio_account.try_borrow_mut_lamports()
-        .map_err(|_| PercolatorError::InsufficientFunds)? += margin;
+    move_lamports(dlp_portfolio_account, user_portfolio_account, margin).map_err(|e| {
+        msg!("Error: DLP portfolio insufficient SOL to return margin");
+        e
+    })?;
 
     // Update equity tracking
-    let margin_i128 = margin as i128;
+    let margin_i128 = i128::from(margin);
     dlp_portfolio.equity = dlp_portfolio.equity.saturating_sub(margin_i128);
     user_portfolio.equity = user_portfolio.equity.saturating_add(margin_i128);
 
@@ -1131,7 +2089,7 @@ fn return_margin_to_user(
     dlp_portfolio.principal = dlp_portfolio.principal.saturating_sub(margin_i128);
     user_portfolio.principal = user_portfolio.principal.saturating_add(margin_i128);
 
-    msg!("Margin collateral returned to user");
+    log_debug!("Margin collateral returned to user");
     Ok(())
 }
 
@@ -1140,7 +2098,7 @@ fn return_margin_to_user(
 /// # Returns
 /// * `Some(PositionDetails)` if account exists and is valid
 /// * `None` if account is not initialized (first trade for this position)
-fn load_position_details(account: &AccountInfo) -> Result<Option<PositionDetails>, PercolatorError> {
+pub(crate) fn load_position_details(account: &AccountInfo) -> Result<Option<PositionDetails>, PercolatorError> {
     // Check if account is initialized (has data and lamports)
     if account.data_len() == 0 || account.lamports() == 0 {
         return Ok(None);
@@ -1208,13 +2166,49 @@ fn create_position_details_pda(
     let rent = Rent::get().map_err(|_| PercolatorError::InvalidAccount)?;
     let lamports = rent.minimum_balance(POSITION_DETAILS_SIZE);
 
+    // A retried transaction (or a reversal right after a close) can find the
+    // PDA already allocated; adopt it rather than failing on allocate
+    let existing = {
+        let data = position_details_account
+            .try_borrow_data()
+            .map_err(|_| PercolatorError::InvalidAccount)?;
+        classify_position_account(
+            position_details_account.owner(),
+            &data,
+            program_id,
+            portfolio_pda,
+            slab_index,
+            instrument_index,
+        )?
+    };
+    if existing == PositionAccountState::Adoptable {
+        let shortfall = lamports.saturating_sub(position_details_account.lamports());
+        if shortfall > 0 {
+            let mut transfer_data = [0u8; 12];
+            transfer_data[0..4].copy_from_slice(&2u32.to_le_bytes());
+            transfer_data[4..12].copy_from_slice(&shortfall.to_le_bytes());
+            let transfer_ix = Instruction {
+                program_id: system_program.key(),
+                accounts: &[
+                    AccountMeta::writable_signer(payer.key()),
+                    AccountMeta::writable(position_details_account.key()),
+                ],
+                data: &transfer_data,
+            };
+            invoke(&transfer_ix, &[payer, position_details_account])
+                .map_err(|_| PercolatorError::InvalidAccount)?;
+        }
+        log_debug!("PositionDetails PDA already exists - adopted");
+        return Ok(());
+    }
+
     // Build seeds for PDA signing
     let slab_idx_bytes = slab_index.to_le_bytes();
     let instrument_idx_bytes = instrument_index.to_le_bytes();
     let bump_bytes = [bump];
 
     let seeds = [
-        Seed::from(b"position" as &[u8]),
+        Seed::from(crate::pda::POSITION_SEED),
         Seed::from(portfolio_pda.as_ref()),
         Seed::from(&slab_idx_bytes[..]),
         Seed::from(&instrument_idx_bytes[..]),
@@ -1272,12 +2266,83 @@ fn create_position_details_pda(
     invoke_signed(&assign_ix, &[position_details_account], &[signer])
         .map_err(|_| PercolatorError::InvalidAccount)?;
 
-    msg!("PositionDetails PDA created");
+    log_debug!("PositionDetails PDA created");
+    Ok(())
+}
+
+/// What create_position_details_pda found at the PDA address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PositionAccountState {
+    /// No data yet: fund, allocate and assign
+    Uninitialized,
+    /// Already allocated to this program for this position: reuse it
+    Adoptable,
+}
+
+/// Decide whether a PositionDetails PDA must be created or can be adopted
+///
+/// An allocated account is only adopted if the router owns it at the right
+/// size and it's either zeroed (closed earlier) or already describes this
+/// same position; anything else is rejected rather than overwritten.
+pub(crate) fn classify_position_account(
+    owner: &Pubkey,
+    data: &[u8],
+    program_id: &Pubkey,
+    portfolio: &Pubkey,
+    slab_index: u16,
+    instrument_index: u16,
+) -> Result<PositionAccountState, PercolatorError> {
+    if data.is_empty() {
+        return Ok(PositionAccountState::Uninitialized);
+    }
+
+    if owner != program_id || data.len() != POSITION_DETAILS_SIZE {
+        msg!("Error: Existing PositionDetails account has wrong owner or size");
+        return Err(PercolatorError::InvalidAccount);
+    }
+
+    if data.iter().all(|&b| b == 0) {
+        return Ok(PositionAccountState::Adoptable);
+    }
+
+    let details = unsafe { &*(data.as_ptr() as *const PositionDetails) };
+    if !details.validate()
+        || &details.portfolio != portfolio
+        || details.slab_index != slab_index
+        || details.instrument_index != instrument_index
+    {
+        msg!("Error: Existing PositionDetails account belongs to another position");
+        return Err(PercolatorError::InvalidAccount);
+    }
+
+    Ok(PositionAccountState::Adoptable)
+}
+
+/// Close a position's PDA, refunding its rent into the user's portfolio
+///
+/// The refund always lands in the portfolio account and is credited as
+/// collateral, whoever signed: a keeper or liquidator closing the position
+/// never receives the user's rent. The position's `realized_pnl` (lamports)
+/// is rolled into the portfolio's `lifetime_realized_pnl` first, since the
+/// PDA's own record is wiped with it.
+pub(crate) fn close_position_to_portfolio(
+    position_details_account: &AccountInfo,
+    user_portfolio_account: &AccountInfo,
+    user_portfolio: &mut Portfolio,
+    realized_pnl: i128,
+) -> Result<(), PercolatorError> {
+    user_portfolio.lifetime_realized_pnl = user_portfolio.lifetime_realized_pnl.saturating_add(realized_pnl);
+
+    let rent = i128::from(position_details_account.lamports());
+    close_position_details_pda(position_details_account, user_portfolio_account)?;
+
+    user_portfolio.equity = user_portfolio.equity.saturating_add(rent);
+    user_portfolio.principal = user_portfolio.principal.saturating_add(rent);
     Ok(())
 }
 
-/// Close PositionDetails PDA and refund rent to user
-fn close_position_details_pda(
+/// Close PositionDetails PDA and refund rent to `recipient`
+pub(crate) fn close_position_details_pda(
     position_details_account: &AccountInfo,
     recipient: &AccountInfo,
 ) -> Result<(), PercolatorError> {
@@ -1297,7 +2362,7 @@ fn close_position_details_pda(
         .map_err(|_| PercolatorError::InvalidAccount)?;
     data.fill(0);
 
-    msg!("PositionDetails PDA closed, rent refunded");
+    log_debug!("PositionDetails PDA closed, rent refunded");
     Ok(())
 }
 
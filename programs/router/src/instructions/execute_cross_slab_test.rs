@@ -1,6 +1,7 @@
-//! Execute cross-slab tests - v0 capital efficiency proofs
+//! Execute cross-slab tests - per-position margin
 //!
-//! These tests validate the core v0 thesis: net exposure netting reduces IM to ~0.
+//! Legs on different slabs or instruments are margined separately; only a
+//! buy and sell of the same instrument net.
 
 #[cfg(test)]
 mod capital_efficiency_tests {
@@ -9,69 +10,40 @@ mod capital_efficiency_tests {
 
     const SCALE: i64 = 1_000_000;
 
-    /// E2E-2: THE KEY TEST - Capital efficiency proof
-    ///
-    /// This proves: net_exposure = 0 → IM ≈ 0 (infinite capital efficiency!)
+    /// Test: A cross-slab hedge keeps both legs as separate exposures
     #[test]
-    fn test_e2e_2_capital_efficiency_netting() {
+    fn test_cross_slab_hedge_keeps_both_legs() {
         let router_id = Pubkey::default();
         let user = Pubkey::default();
         let mut portfolio = Portfolio::new(router_id, user, 0);
 
-        // Scenario: User opens +10 BTC on Slab A, -10 BTC on Slab B
-        let slab_a_qty = 10 * SCALE;
-        let slab_b_qty = -10 * SCALE;
-
-        portfolio.update_exposure(0, 0, slab_a_qty);
-        portfolio.update_exposure(1, 0, slab_b_qty);
-
-        let net_exposure = portfolio.get_exposure(0, 0) + portfolio.get_exposure(1, 0);
-        assert_eq!(net_exposure, 0, "Net exposure should be zero");
-
-        // Calculate IM based on NET exposure
-        let imr = 10; // 10% IMR
-        let price = 60_000u128;
-        let im_required = (net_exposure.abs() as u128 * price * imr) / 100;
-
-        // THE KEY ASSERTION: Zero net exposure → Zero IM!
-        assert_eq!(im_required, 0, "CAPITAL EFFICIENCY PROOF: Zero net = Zero IM");
-
-        // Compare to gross IM
-        let gross_exposure = slab_a_qty.abs() + slab_b_qty.abs();
-        let gross_im = (gross_exposure as u128 * price * imr) / 100;
+        // Scenario: User opens +10 on Slab A, -10 on Slab B
+        portfolio.update_exposure(0, 0, 10 * SCALE);
+        portfolio.update_exposure(1, 0, -10 * SCALE);
 
+        // Signed exposures cancel, but each leg is its own position
+        let signed_sum = portfolio.get_exposure(0, 0) + portfolio.get_exposure(1, 0);
+        assert_eq!(signed_sum, 0);
         assert_eq!(portfolio.exposure_count, 2);
-        assert!(gross_im > 0, "Gross IM should be positive");
-        assert_eq!(im_required, 0, "Net IM must be zero - proves the thesis!");
     }
 
-    /// Test: Partial netting reduces IM
+    /// Test: Same-instrument buys and sells net in one exposure entry
     #[test]
-    fn test_partial_netting_reduces_im() {
+    fn test_same_instrument_offsets_net() {
         let router_id = Pubkey::default();
         let user = Pubkey::default();
         let mut portfolio = Portfolio::new(router_id, user, 0);
 
         portfolio.update_exposure(0, 0, 15 * SCALE);
-        portfolio.update_exposure(1, 0, -10 * SCALE);
-
-        let net_exposure = portfolio.get_exposure(0, 0) + portfolio.get_exposure(1, 0);
-        assert_eq!(net_exposure, 5 * SCALE);
-
-        let price = 60_000u128;
-        let imr = 10;
-
-        let gross_exposure = (15 + 10) * SCALE;
-        let gross_im = (gross_exposure as u128 * price * imr) / 100;
-        let net_im = (net_exposure.abs() as u128 * price * imr) / 100;
-
-        assert!(net_im < gross_im, "Net IM < Gross IM");
-        assert!(net_im > 0, "Net IM > 0 when net exposure != 0");
+        // Selling 10 of the same instrument leaves +5
+        portfolio.update_exposure(0, 0, 5 * SCALE);
+        assert_eq!(portfolio.get_exposure(0, 0), 5 * SCALE);
+        assert_eq!(portfolio.exposure_count, 1);
     }
 
-    /// Test: Multi-instrument netting
+    /// Test: Multi-instrument exposures are tracked separately
     #[test]
-    fn test_multi_instrument_netting() {
+    fn test_multi_instrument_exposures() {
         let router_id = Pubkey::default();
         let user = Pubkey::default();
         let mut portfolio = Portfolio::new(router_id, user, 0);
@@ -81,11 +53,8 @@ mod capital_efficiency_tests {
         portfolio.update_exposure(1, 0, -10 * SCALE);
         portfolio.update_exposure(1, 1, -5 * SCALE);
 
-        let btc_net = portfolio.get_exposure(0, 0) + portfolio.get_exposure(1, 0);
-        let eth_net = portfolio.get_exposure(0, 1) + portfolio.get_exposure(1, 1);
-
-        assert_eq!(btc_net, 0);
-        assert_eq!(eth_net, 0);
+        assert_eq!(portfolio.get_exposure(0, 1), 5 * SCALE);
+        assert_eq!(portfolio.get_exposure(1, 1), -5 * SCALE);
         assert_eq!(portfolio.exposure_count, 4);
     }
 
@@ -152,53 +121,2686 @@ mod margin_calculation_tests {
         assert_eq!(portfolio.im, 50_000);
         assert_eq!(portfolio.mm, 25_000);
         assert_eq!(portfolio.free_collateral, 50_000);
-        assert!(portfolio.has_sufficient_margin());
-        assert!(portfolio.is_above_maintenance());
+        assert!(portfolio.has_sufficient_margin(0));
+        assert!(portfolio.is_above_maintenance(0));
 
         portfolio.update_margin(110_000, 55_000);
         assert_eq!(portfolio.free_collateral, -10_000);
-        assert!(!portfolio.has_sufficient_margin());
+        assert!(!portfolio.has_sufficient_margin(0));
     }
 }
 
 #[cfg(test)]
-mod net_exposure_calculation_tests {
-    use super::super::calculate_net_exposure;
-    use crate::state::Portfolio;
+mod slab_registration_tests {
+    use super::super::{resolve_slab_idx, verify_slab_version};
+    use crate::state::SlabRegistry;
+    use percolator_common::PercolatorError;
     use pinocchio::pubkey::Pubkey;
 
+    /// Test: Governance-registered slab resolves to its registry index
+    #[test]
+    fn test_registered_slab_resolves() {
+        let mut registry = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+        let slab_id = Pubkey::from([7; 32]);
+        let oracle_id = Pubkey::from([8; 32]);
+
+        registry
+            .register_slab(slab_id, [1; 32], oracle_id, 500, 250, 10, 10, 1000, 1_000_000, 10, 0)
+            .unwrap();
+
+        assert_eq!(resolve_slab_idx(&mut registry, &slab_id, &oracle_id, &[1; 32]), Ok(0));
+        assert_eq!(registry.slab_count, 1);
+    }
+
+    /// Test: Unregistered slab is rejected in the default (production) build
+    #[test]
+    #[cfg(not(feature = "permissionless_slabs"))]
+    fn test_unregistered_slab_rejected() {
+        let mut registry = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+        let slab_id = Pubkey::from([7; 32]);
+        let oracle_id = Pubkey::from([8; 32]);
+
+        assert_eq!(
+            resolve_slab_idx(&mut registry, &slab_id, &oracle_id, &[1; 32]),
+            Err(PercolatorError::SlabNotRegistered)
+        );
+        assert_eq!(registry.slab_count, 0, "Unknown slab must not be auto-registered");
+    }
+
+    /// Test: A delisted slab rejects new fills, even on localnet builds
+    #[test]
+    fn test_delisted_slab_rejected() {
+        let mut registry = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+        let slab_id = Pubkey::from([7; 32]);
+        let oracle_id = Pubkey::from([8; 32]);
+
+        registry
+            .register_slab(slab_id, [1; 32], oracle_id, 500, 250, 10, 10, 1000, 1_000_000, 10, 0)
+            .unwrap();
+        registry.tombstone_slab(&slab_id).unwrap();
+
+        assert_eq!(
+            resolve_slab_idx(&mut registry, &slab_id, &oracle_id, &[1; 32]),
+            Err(PercolatorError::SlabNotRegistered)
+        );
+        assert_eq!(registry.slab_count, 1, "Delisted slab must not be auto-registered again");
+    }
+
+    /// Test: Unregistered slab is auto-registered on localnet builds
+    #[test]
+    #[cfg(feature = "permissionless_slabs")]
+    fn test_unregistered_slab_auto_registered() {
+        let mut registry = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+        let slab_id = Pubkey::from([7; 32]);
+        let oracle_id = Pubkey::from([8; 32]);
+
+        assert_eq!(resolve_slab_idx(&mut registry, &slab_id, &oracle_id, &[1; 32]), Ok(0));
+        assert_eq!(registry.slab_count, 1);
+        assert_eq!(registry.slabs[0].oracle_id, oracle_id);
+        assert_eq!(registry.slabs[0].version_hash, [1; 32]);
+    }
+
+    /// Test: Slab header hash matching the registry entry passes
+    #[test]
+    fn test_matching_version_hash_accepted() {
+        let mut registry = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+        let slab_id = Pubkey::from([7; 32]);
+
+        registry
+            .register_slab(slab_id, [1; 32], Pubkey::default(), 500, 250, 10, 10, 1000, 1_000_000, 10, 0)
+            .unwrap();
+
+        assert_eq!(verify_slab_version(&registry, &slab_id, &[1; 32]), Ok(()));
+    }
+
+    /// Test: Slab header hash differing from the registry entry is rejected
+    #[test]
+    fn test_mismatched_version_hash_rejected() {
+        let mut registry = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+        let slab_id = Pubkey::from([7; 32]);
+
+        registry
+            .register_slab(slab_id, [1; 32], Pubkey::default(), 500, 250, 10, 10, 1000, 1_000_000, 10, 0)
+            .unwrap();
+
+        assert_eq!(
+            verify_slab_version(&registry, &slab_id, &[2; 32]),
+            Err(PercolatorError::SlabVersionMismatch)
+        );
+    }
+}
+
+#[cfg(test)]
+mod slippage_tests {
+    use super::super::{effective_slippage_bps, validate_market_order_price};
+    use percolator_common::PercolatorError;
+
+    const ORACLE_PX: i64 = 100_000_000; // $100 (1e6 scale)
+    const CEILING_BPS: u64 = 500; // 5% governance ceiling
+
+    /// Test: User tolerance of 0 falls back to the 0.5% default
+    #[test]
+    fn test_default_slippage() {
+        assert_eq!(effective_slippage_bps(0, CEILING_BPS), 50);
+        assert_eq!(effective_slippage_bps(200, CEILING_BPS), 200);
+    }
+
+    /// Test: Fill 1% away from the user's limit passes at 2% tolerance but fails at default
+    #[test]
+    fn test_user_tolerance_wider_than_default() {
+        let limit_px = 99_000_000; // Buyer expected $99, oracle fills at $100
+
+        let default_bps = effective_slippage_bps(0, CEILING_BPS);
+        assert_eq!(
+            validate_market_order_price(limit_px, ORACLE_PX, 0, default_bps),
+            Err(PercolatorError::PriceSlippage)
+        );
+
+        let user_bps = effective_slippage_bps(200, CEILING_BPS);
+        assert_eq!(validate_market_order_price(limit_px, ORACLE_PX, 0, user_bps), Ok(()));
+
+        // Same for a seller who expected $101
+        assert_eq!(validate_market_order_price(101_000_000, ORACLE_PX, 1, user_bps), Ok(()));
+        assert_eq!(
+            validate_market_order_price(101_000_000, ORACLE_PX, 1, default_bps),
+            Err(PercolatorError::PriceSlippage)
+        );
+    }
+
+    /// Test: Tolerance above the governance ceiling is clamped
+    #[test]
+    fn test_tolerance_clamped_to_ceiling() {
+        let user_bps = effective_slippage_bps(1_000, CEILING_BPS);
+        assert_eq!(user_bps, CEILING_BPS);
+
+        // 8% away: within the user's 10% but outside the 5% ceiling
+        let limit_px = 92_000_000;
+        assert_eq!(
+            validate_market_order_price(limit_px, ORACLE_PX, 0, user_bps),
+            Err(PercolatorError::PriceSlippage)
+        );
+    }
+}
+
+#[cfg(test)]
+mod oracle_median_tests {
+    use super::super::{check_oracle_price, check_oracles_per_slab, median_oracle_price};
+    use percolator_common::PercolatorError;
+
+    const MAX_DIVERGENCE_BPS: u64 = 100; // 1%
+
+    /// Test: Single feed passes through unchanged
+    #[test]
+    fn test_single_feed() {
+        let mut prices = [100_000_000];
+        assert_eq!(median_oracle_price(&mut prices, MAX_DIVERGENCE_BPS), Ok(100_000_000));
+    }
+
+    /// Test: Agreeing feeds return the middle price
+    #[test]
+    fn test_agreeing_feeds() {
+        let mut prices = [100_020_000, 99_990_000, 100_000_000];
+        assert_eq!(median_oracle_price(&mut prices, MAX_DIVERGENCE_BPS), Ok(100_000_000));
+    }
+
+    /// Test: One outlier within tolerance doesn't move the median
+    #[test]
+    fn test_outlier_within_tolerance() {
+        // $100.90 is 0.9% above the rest
+        let mut prices = [100_000_000, 100_900_000, 100_010_000, 99_990_000, 100_005_000];
+        assert_eq!(median_oracle_price(&mut prices, MAX_DIVERGENCE_BPS), Ok(100_005_000));
+    }
+
+    /// Test: Feeds spread wider than the tolerance are rejected
+    #[test]
+    fn test_divergent_feeds_rejected() {
+        let mut prices = [100_000_000, 103_000_000, 100_010_000];
+        assert_eq!(
+            median_oracle_price(&mut prices, MAX_DIVERGENCE_BPS),
+            Err(PercolatorError::OracleDivergence)
+        );
+    }
+
+    /// Test: Only 1, 3, or 5 feeds are accepted
+    #[test]
+    fn test_even_feed_count_rejected() {
+        let mut prices = [100_000_000, 100_000_000];
+        assert_eq!(
+            median_oracle_price(&mut prices, MAX_DIVERGENCE_BPS),
+            Err(PercolatorError::InvalidOracle)
+        );
+        assert_eq!(median_oracle_price(&mut [], MAX_DIVERGENCE_BPS), Err(PercolatorError::InvalidOracle));
+    }
+
+    /// Test: Orders carry 1, 3, or 5 feeds per slab
+    #[test]
+    fn test_oracles_per_slab_count() {
+        assert_eq!(check_oracles_per_slab(1), Ok(()));
+        assert_eq!(check_oracles_per_slab(3), Ok(()));
+        assert_eq!(check_oracles_per_slab(5), Ok(()));
+        assert_eq!(check_oracles_per_slab(0), Err(PercolatorError::InvalidOracle));
+        assert_eq!(check_oracles_per_slab(2), Err(PercolatorError::InvalidOracle));
+        assert_eq!(check_oracles_per_slab(6), Err(PercolatorError::InvalidOracle));
+    }
+
+    /// Test: Zero and negative prints are rejected before any math
+    #[test]
+    fn test_non_positive_price_rejected() {
+        assert_eq!(check_oracle_price(0), Err(PercolatorError::InvalidOraclePrice));
+        assert_eq!(check_oracle_price(-100_000_000), Err(PercolatorError::InvalidOraclePrice));
+        assert_eq!(check_oracle_price(1), Ok(1));
+
+        assert_eq!(median_oracle_price(&mut [0], MAX_DIVERGENCE_BPS), Err(PercolatorError::InvalidOraclePrice));
+        assert_eq!(
+            median_oracle_price(&mut [-1], MAX_DIVERGENCE_BPS),
+            Err(PercolatorError::InvalidOraclePrice)
+        );
+    }
+
+    /// Test: One glitched feed fails the read instead of being outvoted
+    #[test]
+    fn test_glitched_feed_not_outvoted() {
+        let mut prices = [100_000_000, 0, 100_000_000];
+        assert_eq!(
+            median_oracle_price(&mut prices, MAX_DIVERGENCE_BPS),
+            Err(PercolatorError::InvalidOraclePrice)
+        );
+
+        let mut prices = [100_000_000, 100_010_000, -5, 99_990_000, 100_000_000];
+        assert_eq!(
+            median_oracle_price(&mut prices, MAX_DIVERGENCE_BPS),
+            Err(PercolatorError::InvalidOraclePrice)
+        );
+    }
+}
+
+#[cfg(test)]
+mod insurance_notional_tests {
+    use super::super::{fill_notional, total_fill_notional, SlabHeaderSnapshot};
+    use percolator_common::ContractKind;
+
     const SCALE: i64 = 1_000_000;
 
-    /// Test: Net exposure calculation
+    fn header(contract_size: i64, contract_kind: ContractKind) -> SlabHeaderSnapshot {
+        SlabHeaderSnapshot { contract_size, contract_kind, ..Default::default() }
+    }
+
+    fn linear() -> SlabHeaderSnapshot {
+        header(SCALE, ContractKind::Linear)
+    }
+
+    /// Test: Notional uses the receipt's filled qty, not the requested qty
     #[test]
-    fn test_calculate_net_exposure() {
-        let router_id = Pubkey::default();
-        let user = Pubkey::default();
-        let mut portfolio = Portfolio::new(router_id, user, 0);
+    fn test_notional_from_fill_not_request() {
+        let requested_qty = 10 * SCALE;
+        let filled_qty = -4 * SCALE; // Partial sell fill
+        let execution_px = 100 * SCALE;
 
-        portfolio.update_exposure(0, 0, 10 * SCALE);
-        portfolio.update_exposure(1, 0, -5 * SCALE);
-        portfolio.update_exposure(2, 0, 3 * SCALE);
+        let notional = total_fill_notional(&[filled_qty], &[execution_px], &[linear()]);
+        assert_eq!(notional, 400 * SCALE as u128);
+        assert_ne!(notional, fill_notional(requested_qty, execution_px, &linear()));
+    }
+
+    /// Test: A partial fill pays fee and insurance on the filled amount only
+    #[test]
+    fn test_partial_fill_fees_scale_with_fill() {
+        use crate::state::{InsuranceParams, InsuranceState};
+
+        let params = InsuranceParams::default();
+        let taker_fee_bps = 20;
+        // Requested 10 @ 101 limit; the slab filled 4 @ 100 VWAP
+        let (requested_qty, limit_px) = (10 * SCALE, 101 * SCALE);
+        let (filled_qty, vwap_px) = (4 * SCALE, 100 * SCALE);
+
+        let settle = |qty: i64, px: i64| {
+            let notional = total_fill_notional(&[qty], &[px], &[linear()]);
+            let fee = notional * taker_fee_bps / 10_000;
+            let mut insurance = InsuranceState::default();
+            let (to_lp, to_insurance) = insurance.split_taker_fee(fee, notional, &params);
+            (notional, fee, to_lp, to_insurance)
+        };
+
+        let (notional, fee, to_lp, to_insurance) = settle(filled_qty, vwap_px);
+        assert_eq!(notional, 400 * SCALE as u128);
+        assert_eq!(fee, 800_000); // 0.2% of $400
+        assert_eq!(to_insurance, 400 * SCALE as u128 * params.fee_bps_to_insurance as u128 / 10_000);
+        assert_eq!(to_lp + to_insurance, fee);
+
+        // Charging the request would have cost over 2.5x as much
+        let (_, requested_fee, _, requested_insurance) = settle(requested_qty, limit_px);
+        assert_eq!(requested_fee, 2_020_000);
+        assert!(requested_insurance > to_insurance);
 
-        let net = calculate_net_exposure(&portfolio);
-        assert_eq!(net, 8 * SCALE);
+        // Doubling the fill doubles both
+        let (_, double_fee, _, double_insurance) = settle(2 * filled_qty, vwap_px);
+        assert_eq!((double_fee, double_insurance), (2 * fee, 2 * to_insurance));
     }
 
-    /// Test: Zero net exposure → zero IM
+    /// Test: Extreme quantities and prices don't panic
     #[test]
-    fn test_zero_net_zero_im() {
-        let router_id = Pubkey::default();
-        let user = Pubkey::default();
-        let mut portfolio = Portfolio::new(router_id, user, 0);
+    fn test_extreme_values_no_panic() {
+        // |i64::MIN| can't be represented as i64; must not panic on abs()
+        let notional = fill_notional(i64::MIN, i64::MIN, &linear());
+        assert_eq!(notional, (1u128 << 63) * (1u128 << 63) / 1_000_000);
 
-        portfolio.update_exposure(0, 0, 10 * SCALE);
-        portfolio.update_exposure(1, 0, -10 * SCALE);
+        // Summing a full batch of max-size fills doesn't overflow
+        let qtys = [i64::MAX; 16];
+        let prices = [i64::MAX; 16];
+        let total = total_fill_notional(&qtys, &prices, &[linear(); 16]);
+        assert_eq!(total, fill_notional(i64::MAX, i64::MAX, &linear()) * 16);
+
+        assert_eq!(total_fill_notional(&[0, SCALE], &[i64::MAX, 0], &[linear(); 2]), 0);
+    }
+
+    /// Test: Inverse qty is already quote notional, independent of price
+    #[test]
+    fn test_inverse_notional_is_qty() {
+        let qty = 1_000 * SCALE; // $1,000 of contracts
+        let inverse = header(SCALE, ContractKind::Inverse);
+        assert_eq!(fill_notional(-qty, 50_000 * SCALE, &inverse), qty as u128);
+
+        let total = total_fill_notional(
+            &[qty, 2 * SCALE],
+            &[50_000 * SCALE, 100 * SCALE],
+            &[inverse, linear()],
+        );
+        assert_eq!(total, qty as u128 + 200 * SCALE as u128);
+    }
+
+    /// Test: Insurance notional scales with each slab's contract size
+    #[test]
+    fn test_notional_uses_contract_size() {
+        let qty = 2 * SCALE;
+        let px = 100 * SCALE;
+
+        for multiplier in [1i64, 10, 100] {
+            let slab = header(multiplier * SCALE, ContractKind::Linear);
+            assert_eq!(fill_notional(qty, px, &slab), 200 * (multiplier * SCALE) as u128);
+        }
+
+        // Mixed batch: a 10x slab contributes ten times the notional
+        let total = total_fill_notional(
+            &[qty, qty],
+            &[px, px],
+            &[linear(), header(10 * SCALE, ContractKind::Linear)],
+        );
+        assert_eq!(total, 2_200 * SCALE as u128);
+    }
+}
+
+#[cfg(test)]
+mod pnl_scale_tests {
+    use super::super::{fill_notional, margin_required, SlabHeaderSnapshot};
+    use crate::state::PositionDetails;
+    use percolator_common::{pnl_to_lamports, ContractKind::Linear, InstrumentScale, ScaleConfig};
+    use pinocchio::pubkey::Pubkey;
+
+    const SCALE: i64 = 1_000_000;
+
+    fn long_one_contract_at(entry_px: i64) -> PositionDetails {
+        PositionDetails::new(Pubkey::default(), 0, 0, entry_px, SCALE, 0, 0, 0, 1)
+    }
+
+    /// Test: A $100 gain on a 1 SOL contract settles the SOL value of $100
+    #[test]
+    fn test_100_dollar_gain_one_sol_contract() {
+        // Long 1 @ $100, close @ $200: +$100 = 0.5 SOL at exit
+        let mut position = long_one_contract_at(100 * SCALE);
+        let (pnl, remaining, _) = position.reduce_position(200 * SCALE, -SCALE, 0, 1);
+        assert_eq!(remaining, 0);
+
+        // reduce_position stays in 1e6 scale
+        assert_eq!(pnl, 500_000);
+        assert_eq!(position.realized_pnl, 500_000);
+
+        assert_eq!(pnl_to_lamports(pnl, SCALE, InstrumentScale::DEFAULT), 500_000_000);
+    }
+
+    /// Test: The same $100 gain on a 0.1 SOL contract moves a tenth of the lamports
+    #[test]
+    fn test_100_dollar_gain_scales_with_contract_size() {
+        let mut position = long_one_contract_at(100 * SCALE);
+        let (pnl, _, _) = position.reduce_position(200 * SCALE, -SCALE, 0, 1);
+
+        assert_eq!(pnl_to_lamports(pnl, SCALE / 10, InstrumentScale::DEFAULT), 50_000_000);
+        assert_eq!(pnl_to_lamports(-pnl, SCALE / 10, InstrumentScale::DEFAULT), -50_000_000);
+    }
+
+    /// Test: Fully closing a short releases all of its margin
+    #[test]
+    fn test_short_full_close_releases_all_margin() {
+        let mut short = PositionDetails::new(Pubkey::default(), 0, 0, 100 * SCALE, -3 * SCALE, 0, 0, 900_000_000, 1);
+        let (_, remaining, released) = short.reduce_position(100 * SCALE, 3 * SCALE, 0, 1);
+
+        assert_eq!(remaining, 0);
+        assert_eq!(released, 900_000_000);
+        assert_eq!(short.margin_held, 0);
+    }
+
+    /// Test: Partial closes release the same share of margin on either side
+    #[test]
+    fn test_partial_close_margin_is_symmetric() {
+        let mut long = PositionDetails::new(Pubkey::default(), 0, 0, 100 * SCALE, 3 * SCALE, 0, 0, 900_000_000, 1);
+        let mut short = PositionDetails::new(Pubkey::default(), 0, 0, 100 * SCALE, -3 * SCALE, 0, 0, 900_000_000, 1);
+
+        let (_, _, long_released) = long.reduce_position(100 * SCALE, -SCALE, 0, 1);
+        let (_, _, short_released) = short.reduce_position(100 * SCALE, SCALE, 0, 1);
+
+        assert_eq!(long_released, 300_000_000);
+        assert_eq!(short_released, long_released);
+        assert_eq!(short.margin_held, long.margin_held);
+    }
+
+    /// Test: Margin uses the same conversion as PnL
+    #[test]
+    fn test_margin_matches_pnl_conversion() {
+        // 1x on a 1 SOL contract locks the full 1 SOL
+        assert_eq!(margin_required(SCALE, 100 * SCALE, 1, SCALE, Linear, InstrumentScale::DEFAULT), 1_000_000_000);
+        // Smaller contracts lock proportionally less
+        assert_eq!(margin_required(SCALE, 100 * SCALE, 1, SCALE / 10, Linear, InstrumentScale::DEFAULT), 100_000_000);
+    }
+
+    /// Test: Two instruments at different decimals lock the same margin for the same position
+    #[test]
+    fn test_margin_follows_instrument_decimals() {
+        // 3 contracts of 1 SOL at $100, 5x: 0.6 SOL of margin
+        let micro = InstrumentScale::DEFAULT;
+        let margin = margin_required(3 * SCALE, 100 * SCALE, 5, SCALE, Linear, micro);
+        assert_eq!(margin, 600_000_000);
+
+        // Same position on a 9-decimal qty / 8-decimal price instrument
+        let fine = InstrumentScale { price_decimals: 8, qty_decimals: 9 };
+        let margin = margin_required(3_000_000_000, 10_000_000_000, 5, SCALE, Linear, fine);
+        assert_eq!(margin, 600_000_000);
+
+        // Whole-hundredth contracts on a 2-decimal instrument
+        let coarse = InstrumentScale { price_decimals: 2, qty_decimals: 2 };
+        let margin = margin_required(300, 10_000, 5, SCALE, Linear, coarse);
+        assert_eq!(margin, 600_000_000);
+
+        // Read with the 1e6 constants, the 9-decimal qty would lock 1000x too much
+        assert_eq!(
+            margin_required(3_000_000_000, 10_000_000_000, 5, SCALE, Linear, micro),
+            600_000_000_000
+        );
+
+        // Fee notional agrees across scales too: $300
+        let header = |scale| SlabHeaderSnapshot { contract_size: SCALE, scale: ScaleConfig::from(scale), ..Default::default() };
+        assert_eq!(fill_notional(3 * SCALE, 100 * SCALE, &header(micro)), 300 * SCALE as u128);
+        assert_eq!(fill_notional(3_000_000_000, 10_000_000_000, &header(fine)), 300 * SCALE as u128);
+    }
+}
+
+#[cfg(test)]
+mod leverage_tests {
+    use super::super::{margin_required, validate_leverage, MAX_LEVERAGE};
+    use crate::state::PositionDetails;
+    use percolator_common::{pnl_to_lamports, ContractKind::Linear, InstrumentScale, PercolatorError};
+    use pinocchio::pubkey::Pubkey;
+
+    const SCALE: i64 = 1_000_000;
+
+    /// Test: Opening at 5x holds a fifth of the notional as margin
+    #[test]
+    fn test_open_at_5x_holds_fifth_of_notional() {
+        let leverage = 5u8;
+        validate_leverage(leverage).unwrap();
+
+        // Buy 3 contracts of 1 SOL each
+        let qty = 3 * SCALE;
+        let notional = pnl_to_lamports(qty as i128, SCALE, InstrumentScale::DEFAULT) as u128;
+        let margin = margin_required(qty, 100 * SCALE, leverage, SCALE, Linear, InstrumentScale::DEFAULT);
+
+        let mut position = PositionDetails::new(Pubkey::default(), 0, 0, 100 * SCALE, 0, 0, 0, 0, leverage);
+        position.add_to_position(100 * SCALE, qty, 0, 0, margin, leverage).unwrap();
+
+        assert_eq!(position.margin_held, notional / 5);
+        assert_eq!(position.margin_held, 600_000_000);
+    }
+
+    /// Test: Adding to an open position at a different leverage is rejected
+    #[test]
+    fn test_add_at_different_leverage_rejected() {
+        let margin = margin_required(SCALE, 100 * SCALE, 2, SCALE, Linear, InstrumentScale::DEFAULT);
+        let mut position = PositionDetails::new(Pubkey::default(), 0, 0, 100 * SCALE, 0, 0, 0, 0, 2);
+        position.add_to_position(100 * SCALE, SCALE, 0, 0, margin, 2).unwrap();
+        let before = position;
+
+        // 10x on top of a 2x position would mix margin sizing
+        let more = margin_required(SCALE, 100 * SCALE, 10, SCALE, Linear, InstrumentScale::DEFAULT);
+        assert_eq!(
+            position.add_to_position(100 * SCALE, SCALE, 0, 0, more, 10),
+            Err(PercolatorError::LeverageMismatch)
+        );
+        assert_eq!(position.total_qty, before.total_qty);
+        assert_eq!(position.margin_held, before.margin_held);
+        assert_eq!(position.leverage, 2);
+
+        // Same leverage still adds, and a flat position can reopen at any leverage
+        position.add_to_position(100 * SCALE, SCALE, 0, 0, margin, 2).unwrap();
+        assert_eq!(position.margin_held, 2 * margin);
+        position.reduce_position(100 * SCALE, -2 * SCALE, 0, 0);
+        position.add_to_position(100 * SCALE, SCALE, 0, 0, more, 10).unwrap();
+        assert_eq!(position.leverage, 10);
+    }
+
+    /// Test: Leverage outside 1..=10 is rejected
+    #[test]
+    fn test_invalid_leverage_rejected() {
+        assert_eq!(validate_leverage(0), Err(PercolatorError::InvalidLeverage));
+        assert_eq!(validate_leverage(MAX_LEVERAGE + 1), Err(PercolatorError::InvalidLeverage));
+        assert_eq!(validate_leverage(u8::MAX), Err(PercolatorError::InvalidLeverage));
+
+        for leverage in 1..=MAX_LEVERAGE {
+            assert_eq!(validate_leverage(leverage), Ok(()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod slab_leverage_cap_tests {
+    use super::super::{resolve_slab_idx, validate_slab_leverage};
+    use crate::state::SlabRegistry;
+    use percolator_common::PercolatorError;
+    use pinocchio::pubkey::Pubkey;
+
+    /// Test: A 3x-capped slab rejects a 5x order and accepts a 3x order
+    #[test]
+    fn test_order_above_slab_cap_rejected() {
+        let mut registry = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+        let slab_id = Pubkey::from([7; 32]);
+        let oracle_id = Pubkey::from([8; 32]);
+
+        registry
+            .register_slab(slab_id, [1; 32], oracle_id, 500, 250, 10, 10, 1000, 1_000_000, 3, 0)
+            .unwrap();
+        let slab_idx = resolve_slab_idx(&mut registry, &slab_id, &oracle_id, &[1; 32]).unwrap();
+
+        assert_eq!(validate_slab_leverage(&registry, slab_idx, 5), Err(PercolatorError::LeverageTooHigh));
+        assert_eq!(validate_slab_leverage(&registry, slab_idx, 3), Ok(()));
+        assert_eq!(validate_slab_leverage(&registry, slab_idx, 1), Ok(()));
+
+        // Governance raises the cap for this market
+        registry.set_max_leverage(&slab_id, 5).unwrap();
+        assert_eq!(validate_slab_leverage(&registry, slab_idx, 5), Ok(()));
+    }
+}
+
+#[cfg(test)]
+mod limit_band_tests {
+    use super::super::{limit_deviation_bps, validate_limit_order_price};
+    use crate::state::{SlabRegistry, DEFAULT_LIMIT_DEVIATION_BPS};
+    use percolator_common::PercolatorError;
+    use pinocchio::pubkey::Pubkey;
+
+    const SCALE: i64 = 1_000_000;
+    const SLAB: Pubkey = [7; 32];
+
+    fn registry_with_band(band_bps: u16) -> SlabRegistry {
+        let mut registry = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+        registry
+            .register_slab(SLAB, [1; 32], Pubkey::from([8; 32]), 500, 250, 10, 10, 1000, 1_000_000, 3, 0)
+            .unwrap();
+        registry.set_limit_deviation_bps(&SLAB, band_bps).unwrap();
+        registry
+    }
+
+    /// Test: A 1% stablecoin band rejects a limit 5% off; a 10% band accepts it
+    #[test]
+    fn test_band_follows_market() {
+        let oracle_px = 100 * SCALE;
+        let limit_px = 105 * SCALE;
+
+        let tight = registry_with_band(100);
+        assert_eq!(
+            validate_limit_order_price(limit_px, oracle_px, limit_deviation_bps(&tight, &SLAB)),
+            Err(PercolatorError::InvalidPrice)
+        );
+        assert_eq!(
+            validate_limit_order_price(99 * SCALE, oracle_px, limit_deviation_bps(&tight, &SLAB)),
+            Ok(())
+        );
+
+        let wide = registry_with_band(1_000);
+        assert_eq!(validate_limit_order_price(limit_px, oracle_px, limit_deviation_bps(&wide, &SLAB)), Ok(()));
+        assert_eq!(
+            validate_limit_order_price(89 * SCALE, oracle_px, limit_deviation_bps(&wide, &SLAB)),
+            Err(PercolatorError::InvalidPrice)
+        );
+    }
+
+    /// Test: New, cleared and unregistered slabs use the default band
+    #[test]
+    fn test_default_band() {
+        let mut registry = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+        assert_eq!(limit_deviation_bps(&registry, &SLAB), DEFAULT_LIMIT_DEVIATION_BPS as u64);
+
+        registry
+            .register_slab(SLAB, [1; 32], Pubkey::from([8; 32]), 500, 250, 10, 10, 1000, 1_000_000, 3, 0)
+            .unwrap();
+        assert_eq!(limit_deviation_bps(&registry, &SLAB), DEFAULT_LIMIT_DEVIATION_BPS as u64);
+
+        registry.set_limit_deviation_bps(&SLAB, 0).unwrap();
+        assert_eq!(limit_deviation_bps(&registry, &SLAB), DEFAULT_LIMIT_DEVIATION_BPS as u64);
+
+        // Bands wider than 100% are refused
+        assert_eq!(registry.set_limit_deviation_bps(&SLAB, 10_001), Err(()));
+    }
+}
+
+#[cfg(test)]
+mod circuit_breaker_tests {
+    use super::super::check_circuit_breaker;
+    use crate::state::{SlabRegistry, CIRCUIT_BREAKER_WINDOW_SLOTS};
+    use percolator_common::PercolatorError;
+    use pinocchio::pubkey::Pubkey;
+
+    const SCALE: i64 = 1_000_000;
+
+    fn registry_with_settlement(price: i64, slot: u64) -> (SlabRegistry, Pubkey) {
+        let mut registry = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+        let slab_id = Pubkey::from([7; 32]);
+        registry
+            .register_slab(slab_id, [1; 32], Pubkey::from([8; 32]), 500, 250, 10, 10, 1000, 1_000_000, 3, 0)
+            .unwrap();
+        registry.set_max_move_bps(&slab_id, 1_000).unwrap(); // 10%
+        registry.slabs[0].record_settlement(price, slot);
+        (registry, slab_id)
+    }
+
+    /// Test: A move inside max_move_bps proceeds
+    #[test]
+    fn test_within_bounds_move_proceeds() {
+        let (registry, _) = registry_with_settlement(100 * SCALE, 1_000);
+
+        assert_eq!(check_circuit_breaker(&registry, 0, 109 * SCALE, 1_001), Ok(()));
+        assert_eq!(check_circuit_breaker(&registry, 0, 90 * SCALE, 1_001), Ok(()));
+    }
+
+    /// Test: A flash-crash print trips the breaker until the window passes or governance resets
+    #[test]
+    fn test_extreme_move_trips_breaker() {
+        let (mut registry, slab_id) = registry_with_settlement(100 * SCALE, 1_000);
+
+        assert_eq!(
+            check_circuit_breaker(&registry, 0, 50 * SCALE, 1_001),
+            Err(PercolatorError::CircuitBreakerTripped)
+        );
+        assert_eq!(
+            check_circuit_breaker(&registry, 0, 111 * SCALE, 1_000 + CIRCUIT_BREAKER_WINDOW_SLOTS),
+            Err(PercolatorError::CircuitBreakerTripped)
+        );
+
+        // Price held past the window is accepted as the new level
+        assert_eq!(
+            check_circuit_breaker(&registry, 0, 50 * SCALE, 1_001 + CIRCUIT_BREAKER_WINDOW_SLOTS),
+            Ok(())
+        );
+
+        // Governance reset clears it immediately
+        registry.reset_circuit_breaker(&slab_id).unwrap();
+        assert_eq!(check_circuit_breaker(&registry, 0, 50 * SCALE, 1_001), Ok(()));
+    }
+}
+
+#[cfg(test)]
+mod trading_hours_tests {
+    use super::super::check_trading_hours;
+    use crate::state::{SlabRegistry, SECONDS_PER_DAY};
+    use percolator_common::PercolatorError;
+    use pinocchio::pubkey::Pubkey;
+
+    const HOUR: u32 = 3_600;
+    /// 2024-01-01 00:00:00 UTC
+    const MIDNIGHT: i64 = 1_704_067_200;
+
+    fn registry_with_hours(open_secs: u32, close_secs: u32) -> SlabRegistry {
+        let mut registry = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+        let slab_id = Pubkey::from([7; 32]);
+        registry
+            .register_slab(slab_id, [1; 32], Pubkey::from([8; 32]), 500, 250, 10, 10, 1000, 1_000_000, 3, 0)
+            .unwrap();
+        registry.set_trading_hours(&slab_id, open_secs, close_secs).unwrap();
+        registry
+    }
+
+    fn at_hour(day: i64, hour: u32) -> i64 {
+        MIDNIGHT + day * SECONDS_PER_DAY + hour as i64 * HOUR as i64
+    }
+
+    /// Test: A 14:30-21:00 UTC session accepts fills inside and rejects them outside
+    #[test]
+    fn test_fill_inside_and_outside_hours() {
+        let registry = registry_with_hours(14 * HOUR + 1_800, 21 * HOUR);
+
+        assert_eq!(check_trading_hours(&registry, 0, at_hour(0, 15)), Ok(()));
+        assert_eq!(check_trading_hours(&registry, 0, at_hour(3, 20)), Ok(()));
+        assert_eq!(check_trading_hours(&registry, 0, at_hour(0, 14) + 1_800), Ok(()));
+
+        assert_eq!(check_trading_hours(&registry, 0, at_hour(0, 14)), Err(PercolatorError::MarketClosed));
+        assert_eq!(check_trading_hours(&registry, 0, at_hour(0, 21)), Err(PercolatorError::MarketClosed));
+        assert_eq!(check_trading_hours(&registry, 0, at_hour(1, 2)), Err(PercolatorError::MarketClosed));
+    }
+
+    /// Test: A 22:00-06:00 UTC window spans midnight
+    #[test]
+    fn test_window_wraps_midnight() {
+        let registry = registry_with_hours(22 * HOUR, 6 * HOUR);
+
+        assert_eq!(check_trading_hours(&registry, 0, at_hour(0, 23)), Ok(()));
+        assert_eq!(check_trading_hours(&registry, 0, at_hour(1, 0)), Ok(()));
+        assert_eq!(check_trading_hours(&registry, 0, at_hour(1, 5)), Ok(()));
+
+        assert_eq!(check_trading_hours(&registry, 0, at_hour(1, 6)), Err(PercolatorError::MarketClosed));
+        assert_eq!(check_trading_hours(&registry, 0, at_hour(1, 12)), Err(PercolatorError::MarketClosed));
+    }
+
+    /// Test: Slabs without a window trade around the clock; bounds past a day are refused
+    #[test]
+    fn test_default_window_always_open() {
+        let mut registry = registry_with_hours(0, 0);
+        for hour in 0..24 {
+            assert_eq!(check_trading_hours(&registry, 0, at_hour(0, hour)), Ok(()));
+        }
+
+        assert!(registry.set_trading_hours(&Pubkey::from([7; 32]), 0, SECONDS_PER_DAY as u32).is_err());
+        assert!(registry.set_trading_hours(&Pubkey::from([1; 32]), 0, HOUR).is_err());
+    }
+}
+
+#[cfg(test)]
+mod settlement_cast_tests {
+    use super::super::{margin_transfer_lamports, pnl_transfer_lamports};
+    use percolator_common::PercolatorError;
+
+    /// Test: PnL magnitudes up to u64::MAX convert exactly in both directions
+    #[test]
+    fn test_pnl_within_u64_converts() {
+        assert_eq!(pnl_transfer_lamports(1_500_000_000), Ok(1_500_000_000));
+        assert_eq!(pnl_transfer_lamports(-1_500_000_000), Ok(1_500_000_000));
+        assert_eq!(pnl_transfer_lamports(u64::MAX as i128), Ok(u64::MAX));
+        assert_eq!(pnl_transfer_lamports(-(u64::MAX as i128)), Ok(u64::MAX));
+    }
+
+    /// Test: PnL beyond u64 errors instead of truncating
+    #[test]
+    fn test_pnl_beyond_u64_rejected() {
+        // u64::MAX + 1 would truncate to 0 with `as u64`
+        assert_eq!(pnl_transfer_lamports(u64::MAX as i128 + 1), Err(PercolatorError::Overflow));
+        assert_eq!(pnl_transfer_lamports(-(u64::MAX as i128) - 1), Err(PercolatorError::Overflow));
+        assert_eq!(pnl_transfer_lamports(i128::MAX), Err(PercolatorError::Overflow));
+        assert_eq!(pnl_transfer_lamports(i128::MIN), Err(PercolatorError::Overflow));
+    }
+
+    /// Test: Margin beyond u64 errors instead of truncating
+    #[test]
+    fn test_margin_boundaries() {
+        assert_eq!(margin_transfer_lamports(u64::MAX as u128), Ok(u64::MAX));
+        assert_eq!(margin_transfer_lamports(u64::MAX as u128 + 1), Err(PercolatorError::Overflow));
+        assert_eq!(margin_transfer_lamports(u128::MAX), Err(PercolatorError::Overflow));
+    }
+}
+
+#[cfg(test)]
+mod dlp_solvency_tests {
+    use super::super::{check_dlp_solvency, split_dlp_obligation, SlabSplit};
+    use crate::state::PositionDetails;
+    use percolator_common::{InstrumentScale, PercolatorError};
+    use pinocchio::pubkey::Pubkey;
+
+    const SCALE: i64 = 1_000_000;
+
+    fn split(side: u8, qty: i64) -> SlabSplit {
+        SlabSplit { slab_id: Pubkey::default(), qty, side, limit_px: 0 }
+    }
+
+    /// Test: Closing a winning long owes released margin plus profit
+    #[test]
+    fn test_obligation_for_closing_winner() {
+        // Long 2 @ $100 with 1 SOL margin held
+        let position = PositionDetails::new(Pubkey::default(), 0, 0, 100 * SCALE, 2 * SCALE, 0, 0, 1_000_000_000, 1);
+
+        // Sell 2 at $125: profit = 2 * 25 / 125 = 0.4 SOL
+        let owed = split_dlp_obligation(2 * SCALE, &split(1, 2 * SCALE), Some(&position), 125 * SCALE, SCALE, InstrumentScale::DEFAULT);
+        assert_eq!(owed, 1_000_000_000 + 400_000_000);
+
+        // Adding to the long pays the DLP, never the other way
+        assert_eq!(split_dlp_obligation(2 * SCALE, &split(0, SCALE), Some(&position), 125 * SCALE, SCALE, InstrumentScale::DEFAULT), 0);
+
+        // Closing at a loss only owes the margin back
+        let owed = split_dlp_obligation(2 * SCALE, &split(1, 2 * SCALE), Some(&position), 80 * SCALE, SCALE, InstrumentScale::DEFAULT);
+        assert_eq!(owed, 1_000_000_000);
+    }
+
+    /// Test: An undercapitalized DLP is rejected before any transfer
+    #[test]
+    fn test_undercapitalized_dlp_rejected_up_front() {
+        let position = PositionDetails::new(Pubkey::default(), 0, 0, 100 * SCALE, 2 * SCALE, 0, 0, 1_000_000_000, 1);
+        let owed = split_dlp_obligation(2 * SCALE, &split(1, 2 * SCALE), Some(&position), 125 * SCALE, SCALE, InstrumentScale::DEFAULT);
+
+        assert_eq!(
+            check_dlp_solvency(owed, 1_000_000_000, 0),
+            Err(PercolatorError::CounterpartyInsolvent)
+        );
+
+        // Insurance can make up the difference
+        assert_eq!(check_dlp_solvency(owed, 1_000_000_000, 400_000_000), Ok(()));
+        assert_eq!(check_dlp_solvency(owed, 2_000_000_000, 0), Ok(()));
+    }
+}
+
+#[cfg(test)]
+mod counterparty_selection_tests {
+    use super::super::{check_counterparty, select_counterparty, SlabSplit};
+    use crate::state::Portfolio;
+    use percolator_common::PercolatorError;
+    use pinocchio::pubkey::Pubkey;
+
+    const SCALE: i64 = 1_000_000;
+
+    fn split(side: u8) -> SlabSplit {
+        SlabSplit { slab_id: Pubkey::default(), qty: SCALE, side, limit_px: 0 }
+    }
+
+    /// Test: A user buy is routed to the DLP that is already long
+    #[test]
+    fn test_buy_routes_to_long_dlp() {
+        let flat = Portfolio::new(Pubkey::default(), Pubkey::default(), 0);
+        let mut long = Portfolio::new(Pubkey::default(), Pubkey::default(), 0);
+        long.update_exposure(3, 0, 5 * SCALE);
+
+        // The flat DLP is better capitalized, but the long one nets down exposure
+        let dlps = [(&flat, 10_000_000_000u64), (&long, 1_000_000_000u64)];
+        assert_eq!(select_counterparty(&dlps, Some(3), &split(0)), Some(1));
+
+        // Its inventory moves opposite the user: long 5 -> long 4
+        long.update_exposure(3, 0, long.get_exposure(3, 0).saturating_sub(SCALE));
+        assert_eq!(long.get_exposure(3, 0), 4 * SCALE);
+    }
+
+    /// Test: Without offsetting inventory, the best-capitalized DLP is chosen
+    #[test]
+    fn test_falls_back_to_best_capitalized() {
+        let flat = Portfolio::new(Pubkey::default(), Pubkey::default(), 0);
+        let mut long = Portfolio::new(Pubkey::default(), Pubkey::default(), 0);
+        long.update_exposure(3, 0, 5 * SCALE);
+
+        // A user sell would add to the long DLP's risk
+        let dlps = [(&long, 1_000_000_000u64), (&flat, 10_000_000_000u64)];
+        assert_eq!(select_counterparty(&dlps, Some(3), &split(1)), Some(1));
+
+        // Inventory on another slab doesn't count
+        assert_eq!(select_counterparty(&dlps, Some(4), &split(0)), Some(1));
+        assert_eq!(select_counterparty(&[], Some(3), &split(0)), None);
+    }
+
+    /// Test: A DLP portfolio not owned by the slab's lp_owner is rejected
+    #[test]
+    fn test_mismatched_dlp_rejected() {
+        let lp_owner = Pubkey::from([5; 32]);
+        let dlp = Portfolio::new(Pubkey::default(), lp_owner, 0);
+        let stranger = Portfolio::new(Pubkey::default(), Pubkey::from([6; 32]), 0);
+
+        assert_eq!(check_counterparty(&dlp, &lp_owner), Ok(()));
+        assert_eq!(check_counterparty(&stranger, &lp_owner), Err(PercolatorError::InvalidCounterparty));
+    }
+}
+
+#[cfg(test)]
+mod slab_snapshot_tests {
+    use super::super::{check_receipt_owner, commit_fill_data, read_fill, SlabHeaderSnapshot, SlabSplit};
+    use super::test_account::TestAccount;
+    use percolator_common::{FillReceipt, PercolatorError, SlabHeader};
+    use pinocchio::pubkey::Pubkey;
+
+    fn header(seqno: u32) -> SlabHeader {
+        let mut header = SlabHeader::new(
+            Pubkey::default(), Pubkey::default(), Pubkey::default(), Pubkey::default(),
+            100_000_000, 0, 1_000_000, 0,
+        );
+        header.seqno = seqno;
+        header
+    }
+
+    /// Test: Seqnos are captured for every slab before any CPI runs
+    #[test]
+    fn test_seqnos_captured_before_cpi() {
+        let mut headers = [header(7), header(42)];
+
+        // Pre-CPI pass
+        let mut snapshots = [SlabHeaderSnapshot::default(); 16];
+        for (i, h) in headers.iter().enumerate() {
+            snapshots[i] = SlabHeaderSnapshot::from(h);
+        }
+
+        // The first commit_fill bumps its slab's seqno (and could touch others)
+        headers[0].seqno += 1;
+        headers[1].seqno += 1;
+
+        // Every CPI still carries the seqno observed before filling began
+        for (i, expected) in [7u32, 42].iter().enumerate() {
+            let data = commit_fill_data(snapshots[i].expected_seqno, 0, 0, 1_000_000, 100_000_000, 99_000_000, false);
+            assert_eq!(data[0], 1);
+            assert_eq!(u32::from_le_bytes([data[1], data[2], data[3], data[4]]), *expected);
+            assert_eq!(i64::from_le_bytes(data[23..31].try_into().unwrap()), 99_000_000);
+            assert_eq!(data[31], 0);
+        }
+
+        // Liquidation fills tell the slab to waive the taker fee
+        let data = commit_fill_data(7, 1, 1, 1_000_000, 100_000_000, 99_000_000, true);
+        assert_eq!(data[31], 1);
+        assert_eq!(snapshots[0].contract_size, 1_000_000);
+    }
+
+    fn split(side: u8, qty: i64) -> SlabSplit {
+        SlabSplit { slab_id: Pubkey::default(), qty, side, limit_px: 101_000_000 }
+    }
+
+    /// Test: Unwritten receipts are rejected in the receipt pass
+    #[test]
+    fn test_read_fill_requires_written_receipt() {
+        let mut receipt = FillReceipt::new();
+        assert_eq!(read_fill(&receipt, &split(0, 2_000_000)), Err(PercolatorError::InvalidReceipt));
+
+        receipt.write(8, 2_000_000, 101_000_000, 202_000_000, 0);
+        assert_eq!(read_fill(&receipt, &split(0, 2_000_000)), Ok((2_000_000, 101_000_000)));
+    }
+
+    /// Test: Only a receipt owned by the slab's program is read
+    #[test]
+    fn test_receipt_owned_by_foreign_program_rejected() {
+        let slab_program = [3; 32];
+        let mut receipt = TestAccount::new([8; 32], 0, FillReceipt::LEN).owned_by(slab_program);
+        assert_eq!(check_receipt_owner(&receipt.info(), &slab_program), Ok(()));
+
+        // A caller-controlled program could have pre-written any fill into it
+        let mut forged = TestAccount::new([9; 32], 0, FillReceipt::LEN).owned_by([66; 32]);
+        assert_eq!(check_receipt_owner(&forged.info(), &slab_program), Err(PercolatorError::InvalidReceipt));
+    }
+
+    /// Test: A lot-rounded partial fill is tolerated; over-fills are not
+    #[test]
+    fn test_read_fill_tolerates_lot_rounding() {
+        let mut receipt = FillReceipt::new();
+
+        // Asked to sell 1.3, slab with a 0.25 lot filled 1.25
+        receipt.write(8, -1_250_000, 101_000_000, 0, 0);
+        assert_eq!(read_fill(&receipt, &split(1, 1_300_000)), Ok((-1_250_000, 101_000_000)));
+
+        // More than requested, or the wrong direction, is rejected
+        assert_eq!(read_fill(&receipt, &split(1, 1_000_000)), Err(PercolatorError::InvalidReceipt));
+        assert_eq!(read_fill(&receipt, &split(0, 1_300_000)), Err(PercolatorError::InvalidReceipt));
+    }
+}
+
+#[cfg(test)]
+mod signed_qty_tests {
+    use super::super::{check_reduce_only, check_split, read_fill, SlabSplit};
+    use percolator_common::{FillReceipt, PercolatorError, Side};
+    use pinocchio::pubkey::Pubkey;
+
+    const SCALE: i64 = 1_000_000;
+
+    fn split(side: u8, qty: i64) -> SlabSplit {
+        SlabSplit { slab_id: Pubkey::default(), qty, side, limit_px: 100 * SCALE }
+    }
+
+    /// Exposure after the slab fills `split` in full, signing the receipt as commit_fill does
+    fn exposure_after(current_exposure: i64, split: &SlabSplit) -> i64 {
+        check_split(split).unwrap();
+        let mut receipt = FillReceipt::new();
+        receipt.write(1, split.side().signed_qty(split.qty), split.limit_px, 0, 0);
+        let (filled_qty, _) = read_fill(&receipt, split).unwrap();
+        current_exposure + filled_qty
+    }
+
+    /// Test: (buy, sell) x (open, close) move exposure in the right direction
+    #[test]
+    fn test_exposure_delta_sign_matrix() {
+        // (side, current exposure, expected exposure after a 2-contract fill)
+        let cases = [
+            (0, 0, 2 * SCALE),            // buy to open a long
+            (0, -2 * SCALE, 0),           // buy to close a short
+            (1, 0, -2 * SCALE),           // sell to open a short
+            (1, 2 * SCALE, 0),            // sell to close a long
+        ];
+        for (side, current, expected) in cases {
+            let split = split(side, 2 * SCALE);
+            assert_eq!(exposure_after(current, &split), expected, "side {} from {}", side, current);
+            assert_eq!(split.signed_qty(), expected - current);
+
+            // Reduce-only agrees on which combinations close
+            assert_eq!(check_reduce_only(current, &split).is_ok(), expected == 0);
+        }
+    }
+
+    /// Test: Only positive magnitudes with a known side reach the slab
+    #[test]
+    fn test_split_convention_enforced() {
+        assert_eq!(check_split(&split(1, -2 * SCALE)), Err(PercolatorError::InvalidQuantity));
+        assert_eq!(check_split(&split(0, 0)), Err(PercolatorError::InvalidQuantity));
+        assert_eq!(check_split(&split(2, SCALE)), Err(PercolatorError::InvalidSide));
+        assert_eq!(Side::Sell.signed_qty(SCALE), -SCALE);
+
+        // A receipt signed against the split's side is rejected
+        let mut receipt = FillReceipt::new();
+        receipt.write(1, 2 * SCALE, 100 * SCALE, 0, 0);
+        assert_eq!(read_fill(&receipt, &split(1, 2 * SCALE)), Err(PercolatorError::InvalidReceipt));
+    }
+}
+
+#[cfg(test)]
+mod execution_result_tests {
+    use super::super::{ExecutionResult, EXECUTION_RESULT_VERSION};
+    use percolator_common::PercolatorError;
+
+    const SCALE: i64 = 1_000_000;
+
+    /// Test: The return data decodes to the batch's aggregate fill
+    #[test]
+    fn test_return_data_round_trip() {
+        // Bought 3 at $100 and 1 at $104, closing part of a short for +0.5 SOL
+        let result = ExecutionResult::from_fills(
+            &[3 * SCALE, SCALE],
+            &[100 * SCALE, 104 * SCALE],
+            500_000_000,
+            10_500_000_000,
+        );
+        let decoded = ExecutionResult::from_bytes(&result.to_bytes()).unwrap();
+
+        assert_eq!(decoded.version, EXECUTION_RESULT_VERSION);
+        assert_eq!(decoded.filled_qty, 4 * SCALE);
+        assert_eq!(decoded.avg_price, 101 * SCALE);
+        assert_eq!(decoded.realized_pnl, 500_000_000);
+        assert_eq!(decoded.equity, 10_500_000_000);
+    }
+
+    /// Test: Sells report a negative quantity and losses a negative PnL
+    #[test]
+    fn test_sell_and_skipped_splits() {
+        // A partial batch where the second split filled nothing
+        let result = ExecutionResult::from_fills(&[-2 * SCALE, 0], &[99 * SCALE, 0], -40_000_000, 960_000_000);
+        let decoded = ExecutionResult::from_bytes(&result.to_bytes()).unwrap();
+        assert_eq!((decoded.filled_qty, decoded.avg_price), (-2 * SCALE, 99 * SCALE));
+        assert_eq!((decoded.realized_pnl, decoded.equity), (-40_000_000, 960_000_000));
+
+        // Nothing filled at all
+        assert_eq!(ExecutionResult::from_fills(&[0], &[0], 0, 0).avg_price, 0);
+
+        // Truncated or unknown-version data is rejected
+        let bytes = result.to_bytes();
+        assert_eq!(ExecutionResult::from_bytes(&bytes[..ExecutionResult::LEN - 1]), Err(PercolatorError::InvalidInstruction));
+        let mut bad = bytes;
+        bad[0] = EXECUTION_RESULT_VERSION + 1;
+        assert_eq!(ExecutionResult::from_bytes(&bad), Err(PercolatorError::InvalidInstruction));
+    }
+}
+
+#[cfg(test)]
+mod oracle_binding_tests {
+    use super::super::{check_slab_oracle, verify_slab_oracle};
+    use super::test_account::TestAccount;
+    use crate::oracle::TwapBuffer;
+    use crate::state::SlabRegistry;
+    use percolator_common::PercolatorError;
+    use pinocchio::pubkey::Pubkey;
+
+    /// Test: Swapping two slabs' oracle accounts is rejected
+    #[test]
+    fn test_swapped_oracles_rejected() {
+        let mut registry = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+        let (slab_a, oracle_a) = (Pubkey::from([1; 32]), Pubkey::from([11; 32]));
+        let (slab_b, oracle_b) = (Pubkey::from([2; 32]), Pubkey::from([12; 32]));
+        registry.register_slab(slab_a, [1; 32], oracle_a, 500, 250, 10, 10, 1000, 1_000_000, 10, 0).unwrap();
+        registry.register_slab(slab_b, [1; 32], oracle_b, 500, 250, 10, 10, 1000, 1_000_000, 10, 0).unwrap();
+
+        assert_eq!(check_slab_oracle(&registry, &slab_a, &oracle_a), Ok(()));
+        assert_eq!(check_slab_oracle(&registry, &slab_b, &oracle_b), Ok(()));
+
+        // Accounts permuted: each slab is paired with the other's feed
+        assert_eq!(check_slab_oracle(&registry, &slab_a, &oracle_b), Err(PercolatorError::OracleMismatch));
+        assert_eq!(check_slab_oracle(&registry, &slab_b, &oracle_a), Err(PercolatorError::OracleMismatch));
+    }
+
+    /// Test: Unregistered slabs are left to resolve_slab_idx
+    #[test]
+    fn test_unregistered_slab_not_bound_yet() {
+        let registry = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+        assert_eq!(check_slab_oracle(&registry, &Pubkey::from([1; 32]), &Pubkey::from([11; 32])), Ok(()));
+    }
+
+    /// Test: A TWAP buffer only counts if the router owns it
+    #[test]
+    fn test_foreign_twap_buffer_rejected() {
+        let mut registry = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+        let (slab, oracle) = (Pubkey::from([1; 32]), Pubkey::from([11; 32]));
+        registry.register_slab(slab, [1; 32], oracle, 500, 250, 10, 10, 1000, 1_000_000, 10, 0).unwrap();
+        registry.set_price_source(&slab, true).unwrap();
+
+        // Same bytes either way: magic plus the slab's registered source feed
+        let buffer = |owner: Pubkey| {
+            let mut account = TestAccount::new([21; 32], 0, TwapBuffer::LEN).owned_by(owner);
+            {
+                let info = account.info();
+                let mut data = info.try_borrow_mut_data().unwrap();
+                data[..8].copy_from_slice(TwapBuffer::MAGIC);
+                data[8..40].copy_from_slice(&oracle);
+            }
+            account
+        };
+
+        let mut router_owned = buffer(crate::ID);
+        assert_eq!(verify_slab_oracle(&registry, &slab, &[router_owned.info()]), Ok(()));
+
+        let mut foreign = buffer(Pubkey::from([99; 32]));
+        assert_eq!(verify_slab_oracle(&registry, &slab, &[foreign.info()]), Err(PercolatorError::InvalidOracle));
+    }
+
+    /// Test: Every feed in a slab's median group must be registered for that slab
+    #[test]
+    fn test_median_feed_groups_bound() {
+        let mut registry = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+        let (slab_a, slab_b) = (Pubkey::from([1; 32]), Pubkey::from([2; 32]));
+        registry.register_slab(slab_a, [1; 32], [11; 32], 500, 250, 10, 10, 1000, 1_000_000, 10, 0).unwrap();
+        registry.register_slab(slab_b, [1; 32], [12; 32], 500, 250, 10, 10, 1000, 1_000_000, 10, 0).unwrap();
+        registry.set_extra_oracles(&slab_a, &[[13; 32], [14; 32]]).unwrap();
+        registry.set_extra_oracles(&slab_b, &[[15; 32], [16; 32]]).unwrap();
+
+        // Oracle accounts grouped by slab, 3 per slab, as execute slices them
+        let mut buffers = [11u8, 13, 14, 12, 15, 16].map(|key| TestAccount::new([key; 32], 0, 0));
+        let oracles = buffers.each_mut().map(|b| b.info());
+        let group = |i: usize| &oracles[i * 3..(i + 1) * 3];
+        assert_eq!(verify_slab_oracle(&registry, &slab_a, group(0)), Ok(()));
+        assert_eq!(verify_slab_oracle(&registry, &slab_b, group(1)), Ok(()));
+
+        // Groups swapped between the slabs
+        assert_eq!(verify_slab_oracle(&registry, &slab_a, group(1)), Err(PercolatorError::OracleMismatch));
+        assert_eq!(verify_slab_oracle(&registry, &slab_b, group(0)), Err(PercolatorError::OracleMismatch));
+
+        // Only an extra feed taken from the other slab
+        let mixed = [oracles[0], oracles[1], oracles[4]];
+        assert_eq!(verify_slab_oracle(&registry, &slab_a, &mixed), Err(PercolatorError::OracleMismatch));
+
+        // A registered feed passed twice to outvote the third
+        let repeated = [oracles[0], oracles[1], oracles[1]];
+        assert_eq!(verify_slab_oracle(&registry, &slab_a, &repeated), Err(PercolatorError::InvalidOracle));
+
+        // The primary alone still prices the slab; an extra feed alone doesn't
+        assert_eq!(verify_slab_oracle(&registry, &slab_a, &oracles[0..1]), Ok(()));
+        assert_eq!(verify_slab_oracle(&registry, &slab_a, &oracles[1..2]), Err(PercolatorError::InvalidOracle));
+
+        // An unregistered slab has no extra feeds to check against
+        assert_eq!(verify_slab_oracle(&registry, &Pubkey::from([3; 32]), group(0)), Err(PercolatorError::InvalidOracle));
+    }
+}
+
+#[cfg(test)]
+mod inverse_contract_tests {
+    use super::super::{calculate_realized_pnl, margin_required};
+    use crate::state::PositionDetails;
+    use percolator_common::{ContractKind, InstrumentScale, ScaleConfig};
+    use pinocchio::pubkey::Pubkey;
+
+    const SCALE: i64 = 1_000_000;
+
+    fn inverse_position(entry_px: i64, qty: i64) -> PositionDetails {
+        let mut position = PositionDetails::new(Pubkey::default(), 0, 0, entry_px, qty, 0, 0, 0, 1);
+        position.contract_kind = ContractKind::Inverse as u8;
+        position
+    }
+
+    /// Reference inverse PnL in base units: qty * (1/entry - 1/exit)
+    fn reference_pnl(qty_usd: f64, entry: f64, exit: f64) -> f64 {
+        qty_usd * (1.0 / entry - 1.0 / exit)
+    }
+
+    /// Test: Inverse long/short PnL matches qty * (1/entry - 1/exit)
+    #[test]
+    fn test_inverse_pnl_matches_reference() {
+        let qty = 1_000 * SCALE; // $1,000 of contracts
+        let cases = [(50_000, 55_000), (50_000, 45_000), (30_000, 60_000), (60_000, 30_000)];
+
+        for (entry, exit) in cases {
+            let expected = reference_pnl(1_000.0, entry as f64, exit as f64) * SCALE as f64;
+
+            let mut long = inverse_position(entry * SCALE, qty);
+            let (pnl, remaining, _) = long.reduce_position(exit * SCALE, -qty, 0, 0);
+            assert_eq!(remaining, 0);
+            assert!((pnl as f64 - expected).abs() < 1.0, "long {} -> {}: {} vs {}", entry, exit, pnl, expected);
+
+            let mut short = inverse_position(entry * SCALE, -qty);
+            let (pnl, _, _) = short.reduce_position(exit * SCALE, qty, 0, 0);
+            assert!((pnl as f64 + expected).abs() < 1.0, "short {} -> {}: {} vs {}", entry, exit, pnl, -expected);
+        }
+    }
+
+    /// Test: Inverse PnL is convex - a 10% drop loses more base than a 10% rise gains
+    #[test]
+    fn test_inverse_pnl_pinned_values() {
+        let qty = 1_000 * SCALE;
+
+        let mut up = inverse_position(50_000 * SCALE, qty);
+        assert_eq!(up.reduce_position(55_000 * SCALE, -qty, 0, 0).0, 1_818);
+
+        let mut down = inverse_position(50_000 * SCALE, qty);
+        assert_eq!(down.reduce_position(45_000 * SCALE, -qty, 0, 0).0, -2_222);
+
+        // calculate_realized_pnl agrees with the PositionDetails path
+        assert_eq!(
+            calculate_realized_pnl(qty, qty, 1, 55_000 * SCALE, 50_000 * SCALE, ContractKind::Inverse, &ScaleConfig::DEFAULT),
+            1_818
+        );
+        assert_eq!(
+            calculate_realized_pnl(-qty, qty, 0, 55_000 * SCALE, 50_000 * SCALE, ContractKind::Inverse, &ScaleConfig::DEFAULT),
+            -1_818
+        );
+        // Linear is unchanged: 1 contract, +$5,000
+        assert_eq!(
+            calculate_realized_pnl(SCALE, SCALE, 1, 55_000 * SCALE, 50_000 * SCALE, ContractKind::Linear, &ScaleConfig::DEFAULT),
+            5_000 * SCALE as i128
+        );
+    }
+
+    /// Test: Adding to an inverse position averages entry harmonically
+    #[test]
+    fn test_inverse_average_entry_is_harmonic() {
+        // $1,000 @ 50k (0.02 BTC) + $1,000 @ 40k (0.025 BTC) = $2,000 / 0.045 BTC
+        let mut position = inverse_position(50_000 * SCALE, 1_000 * SCALE);
+        position.add_to_position(40_000 * SCALE, 1_000 * SCALE, 0, 0, 0, 1).unwrap();
+        assert_eq!(position.avg_entry_price, 44_444_444_444);
+
+        // Closing everything at the average entry is flat
+        let (pnl, _, _) = position.reduce_position(44_444_444_444, -2_000 * SCALE, 0, 0);
+        assert_eq!(pnl, 0);
+    }
+
+    /// Test: Inverse margin is the quote notional converted to base at fill price
+    #[test]
+    fn test_inverse_margin_in_base() {
+        // $1,000 at 50k is 0.02 BTC; 1 BTC contract, 1x = 0.02 * 1e9 lamports
+        let margin = margin_required(1_000 * SCALE, 50_000 * SCALE, 1, SCALE, ContractKind::Inverse, InstrumentScale::DEFAULT);
+        assert_eq!(margin, 20_000_000);
+
+        // 4x holds a quarter
+        let margin = margin_required(1_000 * SCALE, 50_000 * SCALE, 4, SCALE, ContractKind::Inverse, InstrumentScale::DEFAULT);
+        assert_eq!(margin, 5_000_000);
+    }
+}
+
+#[cfg(test)]
+mod zero_fill_tests {
+    use super::super::{is_zero_fill, read_fill, SlabSplit};
+    use crate::state::{Portfolio, PositionDetails};
+    use percolator_common::FillReceipt;
+    use pinocchio::pubkey::Pubkey;
+
+    const SCALE: i64 = 1_000_000;
+
+    /// Test: A zero-fill split is skipped while the other split settles
+    #[test]
+    fn test_zero_fill_split_creates_no_position() {
+        let splits = [
+            SlabSplit { slab_id: Pubkey::from([1; 32]), qty: 2 * SCALE, side: 0, limit_px: 100 * SCALE },
+            SlabSplit { slab_id: Pubkey::from([2; 32]), qty: 3 * SCALE, side: 0, limit_px: 100 * SCALE },
+        ];
+
+        // First slab fills in full, second has no liquidity
+        let mut receipts = [FillReceipt::new(), FillReceipt::new()];
+        receipts[0].write(1, 2 * SCALE, 100 * SCALE, 200 * SCALE, 0);
+        receipts[1].write(1, 0, 100 * SCALE, 0, 0);
+
+        // Replay the settlement loop: only non-zero fills create a PositionDetails
+        let mut portfolio = Portfolio::new(Pubkey::default(), Pubkey::default(), 0);
+        let mut positions: [Option<PositionDetails>; 2] = [None, None];
+        for (i, split) in splits.iter().enumerate() {
+            let (filled_qty, vwap_px) = read_fill(&receipts[i], split).unwrap();
+            if is_zero_fill(filled_qty) {
+                continue;
+            }
+            positions[i] = Some(PositionDetails::new(Pubkey::default(), i as u16, 0, vwap_px, filled_qty, 0, 0, 0, 1));
+            portfolio.update_exposure(i as u16, 0, filled_qty);
+        }
+
+        assert_eq!(positions.iter().filter(|p| p.is_some()).count(), 1);
+        assert!(positions[1].is_none());
+        assert_eq!(portfolio.exposure_count, 1);
+        assert_eq!(portfolio.get_exposure(0, 0), 2 * SCALE);
+        assert_eq!(portfolio.get_exposure(1, 0), 0);
+    }
+}
+
+#[cfg(test)]
+mod partial_batch_tests {
+    use super::super::{check_batch_fills, is_zero_fill, read_fill, SlabSplit};
+    use crate::state::Portfolio;
+    use percolator_common::{FillReceipt, PercolatorError};
+    use pinocchio::pubkey::Pubkey;
+
+    const SCALE: i64 = 1_000_000;
+
+    /// Three buy legs; the middle slab has no liquidity
+    fn three_legs_one_dry() -> ([SlabSplit; 3], [i64; 3]) {
+        let splits = [
+            SlabSplit { slab_id: Pubkey::from([1; 32]), qty: 2 * SCALE, side: 0, limit_px: 100 * SCALE },
+            SlabSplit { slab_id: Pubkey::from([2; 32]), qty: 3 * SCALE, side: 0, limit_px: 100 * SCALE },
+            SlabSplit { slab_id: Pubkey::from([3; 32]), qty: SCALE, side: 0, limit_px: 100 * SCALE },
+        ];
+        let mut receipts = [FillReceipt::new(), FillReceipt::new(), FillReceipt::new()];
+        receipts[0].write(1, 2 * SCALE, 100 * SCALE, 200 * SCALE, 0);
+        receipts[1].write(1, 0, 100 * SCALE, 0, 0);
+        receipts[2].write(1, SCALE, 100 * SCALE, 100 * SCALE, 0);
+
+        let mut filled_qtys = [0i64; 3];
+        for (i, split) in splits.iter().enumerate() {
+            filled_qtys[i] = read_fill(&receipts[i], split).unwrap().0;
+        }
+        (splits, filled_qtys)
+    }
+
+    /// Test: By default one dry leg reverts the whole batch
+    #[test]
+    fn test_all_or_nothing_rejects_dry_leg() {
+        let (_, filled_qtys) = three_legs_one_dry();
+        assert_eq!(check_batch_fills(&filled_qtys, false), Err(PercolatorError::InsufficientLiquidity));
+
+        // Every leg filling passes
+        assert_eq!(check_batch_fills(&[2 * SCALE, 3 * SCALE, SCALE], false), Ok(()));
+    }
+
+    /// Test: With allow_partial_batch the filled legs settle and the dry one is skipped
+    #[test]
+    fn test_partial_batch_settles_filled_legs() {
+        let (splits, filled_qtys) = three_legs_one_dry();
+        assert_eq!(check_batch_fills(&filled_qtys, true), Ok(()));
+
+        // Replay the settlement loop
+        let mut portfolio = Portfolio::new(Pubkey::default(), Pubkey::default(), 0);
+        for (i, _) in splits.iter().enumerate() {
+            if is_zero_fill(filled_qtys[i]) {
+                continue;
+            }
+            portfolio.update_exposure(i as u16, 0, filled_qtys[i]);
+        }
+
+        assert_eq!(portfolio.exposure_count, 2);
+        assert_eq!(portfolio.get_exposure(0, 0), 2 * SCALE);
+        assert_eq!(portfolio.get_exposure(1, 0), 0);
+        assert_eq!(portfolio.get_exposure(2, 0), SCALE);
+    }
+}
+
+#[cfg(test)]
+mod position_account_tests {
+    use super::super::{classify_position_account, PositionAccountState};
+    use crate::state::{PositionDetails, POSITION_DETAILS_SIZE};
+    use percolator_common::PercolatorError;
+    use pinocchio::pubkey::Pubkey;
+
+    const PROGRAM: Pubkey = [7; 32];
+    const PORTFOLIO: Pubkey = [3; 32];
+    const SYSTEM: Pubkey = [0; 32];
+
+    fn bytes_of(details: &PositionDetails) -> Vec<u8> {
+        unsafe {
+            core::slice::from_raw_parts(details as *const PositionDetails as *const u8, POSITION_DETAILS_SIZE)
+        }
+        .to_vec()
+    }
+
+    /// Open path as create_position_details_pda runs it, against an in-memory account
+    fn open(owner: &mut Pubkey, data: &mut Vec<u8>) -> Result<(), PercolatorError> {
+        match classify_position_account(owner, data, &PROGRAM, &PORTFOLIO, 2, 0)? {
+            PositionAccountState::Uninitialized => {
+                // allocate + assign
+                *data = vec![0; POSITION_DETAILS_SIZE];
+                *owner = PROGRAM;
+            }
+            PositionAccountState::Adoptable => {}
+        }
+        let details = PositionDetails::new(PORTFOLIO, 2, 0, 100_000_000, 1_000_000, 0, 255, 0, 1);
+        data.copy_from_slice(&bytes_of(&details));
+        Ok(())
+    }
+
+    /// Test: Running the open path twice (a retried transaction) succeeds both times
+    #[test]
+    fn test_open_path_is_idempotent() {
+        let mut owner = SYSTEM;
+        let mut data = Vec::new();
+
+        assert_eq!(open(&mut owner, &mut data), Ok(()));
+        assert_eq!(open(&mut owner, &mut data), Ok(()));
+
+        let details = unsafe { &*(data.as_ptr() as *const PositionDetails) };
+        assert!(details.validate());
+        assert_eq!((details.slab_index, details.total_qty), (2, 1_000_000));
+    }
+
+    /// Test: A closed (zeroed) PDA is reused, e.g. for a reversal in the same transaction
+    #[test]
+    fn test_zeroed_account_is_adopted() {
+        let zeroed = vec![0u8; POSITION_DETAILS_SIZE];
+        assert_eq!(
+            classify_position_account(&PROGRAM, &zeroed, &PROGRAM, &PORTFOLIO, 2, 0),
+            Ok(PositionAccountState::Adoptable)
+        );
+    }
+
+    /// Test: Accounts that don't belong to this position are never adopted
+    #[test]
+    fn test_foreign_account_rejected() {
+        let other = PositionDetails::new([9; 32], 2, 0, 100_000_000, 1_000_000, 0, 255, 0, 1);
+        let data = bytes_of(&other);
+        assert_eq!(
+            classify_position_account(&PROGRAM, &data, &PROGRAM, &PORTFOLIO, 2, 0),
+            Err(PercolatorError::InvalidAccount)
+        );
+
+        // Wrong owner or wrong size
+        let zeroed = vec![0u8; POSITION_DETAILS_SIZE];
+        assert_eq!(
+            classify_position_account(&SYSTEM, &zeroed, &PROGRAM, &PORTFOLIO, 2, 0),
+            Err(PercolatorError::InvalidAccount)
+        );
+        assert_eq!(
+            classify_position_account(&PROGRAM, &zeroed[..64], &PROGRAM, &PORTFOLIO, 2, 0),
+            Err(PercolatorError::InvalidAccount)
+        );
+    }
+}
+
+#[cfg(test)]
+mod liquidation_price_tests {
+    use crate::state::PositionDetails;
+    use percolator_common::ContractKind;
+    use pinocchio::pubkey::Pubkey;
+
+    const SCALE: i64 = 1_000_000;
+
+    /// Open `qty` at $100 the way the execute path does: empty details, then add
+    fn position(qty: i64, leverage: u8) -> PositionDetails {
+        let mut position = PositionDetails::new(Pubkey::default(), 0, 0, 100 * SCALE, 0, 0, 0, 0, leverage);
+        position.add_to_position(100 * SCALE, qty, 0, 0, 0, leverage).unwrap();
+        position
+    }
+
+    /// Test: A 10x long is liquidated ~10% below entry, a 10x short ~10% above
+    #[test]
+    fn test_10x_liquidation_price() {
+        let long = position(SCALE, 10);
+        assert_eq!(long.liquidation_price(), 90 * SCALE);
+
+        let short = position(-SCALE, 10);
+        assert_eq!(short.liquidation_price(), 110 * SCALE);
+
+        // 1x long only goes bankrupt at zero
+        assert_eq!(position(SCALE, 1).liquidation_price(), 0);
+    }
+
+    /// Test: Liquidation price tracks entry changes and clears when flat
+    #[test]
+    fn test_liquidation_price_follows_position() {
+        let mut long = position(SCALE, 10);
+
+        // Average up to $110 entry: threshold moves to $99
+        long.add_to_position(120 * SCALE, SCALE, 0, 0, 0, 10).unwrap();
+        assert_eq!(long.avg_entry_price, 110 * SCALE);
+        assert_eq!(long.liquidation_price(), 99 * SCALE);
+
+        // Partial reduce keeps the entry, and so the threshold
+        long.reduce_position(105 * SCALE, -SCALE, 0, 0);
+        assert_eq!(long.liquidation_price(), 99 * SCALE);
+
+        long.reduce_position(105 * SCALE, -SCALE, 0, 0);
+        assert_eq!(long.liquidation_price(), 0);
+    }
+
+    /// Test: Inverse thresholds are where base PnL equals the posted margin
+    #[test]
+    fn test_inverse_liquidation_price() {
+        let mut long = PositionDetails::new(Pubkey::default(), 0, 0, 100 * SCALE, 0, 0, 0, 0, 10);
+        long.contract_kind = ContractKind::Inverse as u8;
+        long.add_to_position(100 * SCALE, 1_000 * SCALE, 0, 0, 0, 10).unwrap();
+        assert_eq!(long.liquidation_price(), 90_909_090);
+
+        let mut short = PositionDetails::new(Pubkey::default(), 0, 0, 100 * SCALE, 0, 0, 0, 0, 10);
+        short.contract_kind = ContractKind::Inverse as u8;
+        short.add_to_position(100 * SCALE, -1_000 * SCALE, 0, 0, 0, 10).unwrap();
+        assert_eq!(short.liquidation_price(), 111_111_111);
+    }
+}
+
+#[cfg(test)]
+mod margin_required_tests {
+    use super::super::{margin_required, position_im_and_notional};
+    use crate::state::PositionDetails;
+    use percolator_common::{ContractKind, InstrumentScale};
+    use pinocchio::pubkey::Pubkey;
+
+    const SCALE: i64 = 1_000_000;
+
+    fn bytes(details: &PositionDetails) -> &[u8] {
+        unsafe {
+            core::slice::from_raw_parts(
+                details as *const PositionDetails as *const u8,
+                core::mem::size_of::<PositionDetails>(),
+            )
+        }
+    }
+
+    /// Test: The margin transferred for a fill is the IM the portfolio check reads back
+    #[test]
+    fn test_transferred_margin_equals_portfolio_im() {
+        let (qty, price, leverage) = (3 * SCALE, 100 * SCALE, 5u8);
+        let transferred = margin_required(
+            qty,
+            price,
+            leverage,
+            SCALE,
+            ContractKind::Linear,
+            InstrumentScale::DEFAULT,
+        );
+        assert_eq!(transferred, 600_000_000);
+
+        // Open the way the execute path does, then add the filled margin
+        let mut details = PositionDetails::new(Pubkey::default(), 0, 0, price, 0, 0, 0, 0, leverage);
+        details.add_to_position(price, qty, 0, 0, transferred, leverage).unwrap();
+
+        let (im, notional) = position_im_and_notional(bytes(&details)).unwrap();
+        assert_eq!(im, transferred);
+        assert_eq!(notional, 3_000_000_000);
+
+        // Adding a second fill keeps them in lockstep
+        let more = margin_required(-SCALE, price, leverage, SCALE, ContractKind::Linear, InstrumentScale::DEFAULT);
+        details.add_to_position(price, SCALE, 0, 0, more, leverage).unwrap();
+        let (im, _) = position_im_and_notional(bytes(&details)).unwrap();
+        assert_eq!(im, transferred + more);
+    }
+
+    /// Test: Truncated account data contributes nothing
+    #[test]
+    fn test_short_account_has_no_im() {
+        assert!(position_im_and_notional(&[0u8; 16]).is_none());
+    }
+}
+
+#[cfg(test)]
+mod open_interest_tests {
+    use super::super::update_open_interest;
+    use crate::state::SlabRegistry;
+    use percolator_common::PercolatorError;
+    use pinocchio::pubkey::Pubkey;
+
+    const SCALE: i64 = 1_000_000;
+
+    fn registry() -> (SlabRegistry, Pubkey) {
+        let mut registry = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+        let slab_id = Pubkey::from([7; 32]);
+        registry
+            .register_slab(slab_id, [1; 32], Pubkey::from([8; 32]), 500, 250, 10, 10, 1000, 1_000_000, 3, 0)
+            .unwrap();
+        (registry, slab_id)
+    }
+
+    fn oi(registry: &SlabRegistry) -> (u64, u64) {
+        (registry.slabs[0].long_oi, registry.slabs[0].short_oi)
+    }
+
+    /// Test: Opens add to their side, closes release it, flips move across
+    #[test]
+    fn test_open_interest_follows_exposure() {
+        let (mut registry, _) = registry();
+
+        // Two accounts open: 3 long, 2 short
+        update_open_interest(&mut registry, 0, 0, 3 * SCALE).unwrap();
+        update_open_interest(&mut registry, 0, 0, -2 * SCALE).unwrap();
+        assert_eq!(oi(&registry), (3 * SCALE as u64, 2 * SCALE as u64));
+
+        // Long partially closes
+        update_open_interest(&mut registry, 0, 3 * SCALE, SCALE).unwrap();
+        assert_eq!(oi(&registry), (SCALE as u64, 2 * SCALE as u64));
+
+        // Short flips to 1 long
+        update_open_interest(&mut registry, 0, -2 * SCALE, SCALE).unwrap();
+        assert_eq!(oi(&registry), (2 * SCALE as u64, 0));
+
+        // Both close out
+        update_open_interest(&mut registry, 0, SCALE, 0).unwrap();
+        update_open_interest(&mut registry, 0, SCALE, 0).unwrap();
+        assert_eq!(oi(&registry), (0, 0));
+    }
+
+    /// Test: The cap rejects an over-limit open but never a reduction
+    #[test]
+    fn test_cap_rejects_over_limit_open() {
+        let (mut registry, slab_id) = registry();
+        registry.set_max_open_interest(&slab_id, 5 * SCALE as u64).unwrap();
+
+        update_open_interest(&mut registry, 0, 0, 4 * SCALE).unwrap();
+        assert_eq!(
+            update_open_interest(&mut registry, 0, 0, 2 * SCALE),
+            Err(PercolatorError::OpenInterestCapExceeded)
+        );
+        assert_eq!(oi(&registry), (4 * SCALE as u64, 0));
+
+        // Exactly at the cap is allowed; the other side has its own room
+        update_open_interest(&mut registry, 0, 0, SCALE).unwrap();
+        update_open_interest(&mut registry, 0, 0, -5 * SCALE).unwrap();
+
+        // Lowering the cap below current interest still lets positions close
+        registry.set_max_open_interest(&slab_id, SCALE as u64).unwrap();
+        update_open_interest(&mut registry, 0, 4 * SCALE, 2 * SCALE).unwrap();
+        assert_eq!(oi(&registry), (3 * SCALE as u64, 5 * SCALE as u64));
+    }
+}
+
+#[cfg(test)]
+mod test_account {
+    use pinocchio::{account_info::AccountInfo, pubkey::Pubkey};
+
+    /// Account buffer in the runtime's serialized layout: borrow state,
+    /// flags, resize delta, key, owner, lamports, data_len, then data
+    ///
+    /// The header starts 8 bytes into a u128 buffer so the data is 16-byte
+    /// aligned, as i128 fields need on the host.
+    pub(super) struct TestAccount {
+        buf: Vec<u128>,
+    }
+
+    impl TestAccount {
+        pub(super) fn new(key: Pubkey, lamports: u64, data_len: usize) -> Self {
+            let mut account = Self { buf: vec![0u128; 6 + data_len.div_ceil(16)] };
+            let bytes = account.header();
+            bytes[0] = u8::MAX; // Not borrowed
+            bytes[2] = 1; // Writable
+            bytes[8..40].copy_from_slice(&key);
+            bytes[72..80].copy_from_slice(&lamports.to_le_bytes());
+            bytes[80..88].copy_from_slice(&(data_len as u64).to_le_bytes());
+            account
+        }
+
+        /// Mark the account as a transaction signer
+        pub(super) fn signer(mut self) -> Self {
+            self.header()[1] = 1;
+            self
+        }
+
+        /// Set the program owning the account
+        pub(super) fn owned_by(mut self, owner: Pubkey) -> Self {
+            self.header()[40..72].copy_from_slice(&owner);
+            self
+        }
+
+        pub(super) fn info(&mut self) -> AccountInfo {
+            unsafe { core::mem::transmute::<*mut u8, AccountInfo>((self.buf.as_mut_ptr() as *mut u8).add(8)) }
+        }
+
+        fn header(&mut self) -> &mut [u8] {
+            unsafe { core::slice::from_raw_parts_mut((self.buf.as_mut_ptr() as *mut u8).add(8), 88) }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "localnet"))]
+mod custom_price_tests {
+    use super::super::{read_oracle_price_single, validate_market_order_price};
+    use super::test_account::TestAccount;
+    use crate::instructions::process_set_custom_price;
+    use percolator_common::oracle::CUSTOM_ORACLE_SIZE;
+    use percolator_common::PercolatorError;
+
+    const SCALE: i64 = 1_000_000;
+
+    /// Test: A written price is what the fill path reads back
+    #[test]
+    fn test_written_price_drives_fill() {
+        let mut oracle = TestAccount::new([5; 32], 1_000_000, CUSTOM_ORACLE_SIZE);
+        let oracle = oracle.info();
+        let authority = [6; 32];
+
+        process_set_custom_price(&oracle, &authority, 100 * SCALE, 1_000).unwrap();
+        let oracle_px = read_oracle_price_single(&oracle).unwrap();
+        assert_eq!(oracle_px, 100 * SCALE);
+
+        // A market buy limited at $100.50 fills against it; one at $98 is outside 1% slippage
+        assert_eq!(validate_market_order_price(100 * SCALE + SCALE / 2, oracle_px, 0, 100), Ok(()));
+        assert!(validate_market_order_price(98 * SCALE, oracle_px, 0, 100).is_err());
+
+        // Updates move the next fill's price; only the authority may write
+        process_set_custom_price(&oracle, &authority, 105 * SCALE, 1_001).unwrap();
+        assert_eq!(read_oracle_price_single(&oracle), Ok(105 * SCALE));
+        assert_eq!(
+            process_set_custom_price(&oracle, &[9; 32], SCALE, 1_002),
+            Err(PercolatorError::Unauthorized)
+        );
+        assert_eq!(
+            process_set_custom_price(&oracle, &authority, 0, 1_002),
+            Err(PercolatorError::InvalidOraclePrice)
+        );
+    }
+}
+
+#[cfg(test)]
+mod rent_refund_tests {
+    use super::super::close_position_to_portfolio;
+    use super::test_account::TestAccount;
+    use crate::state::Portfolio;
+    use pinocchio::pubkey::Pubkey;
+
+    /// Test: A keeper closing a user's position refunds the rent to the user's portfolio
+    #[test]
+    fn test_rent_refund_goes_to_portfolio_not_signer() {
+        let owner: Pubkey = [1; 32];
+        let keeper: Pubkey = [2; 32];
+        let mut portfolio = Portfolio::new(Pubkey::default(), owner, 0);
+        portfolio.equity = 5_000_000;
+        portfolio.principal = 5_000_000;
+
+        let mut position = TestAccount::new([3; 32], 2_000_000, 16);
+        let mut portfolio_account = TestAccount::new([4; 32], 5_000_000, 0);
+        let mut keeper_account = TestAccount::new(keeper, 1_000_000, 0);
+        let (position, portfolio_account, keeper_account) =
+            (position.info(), portfolio_account.info(), keeper_account.info());
+        assert!(keeper_account.key() != &portfolio.user);
+
+        close_position_to_portfolio(&position, &portfolio_account, &mut portfolio, 0).unwrap();
+
+        // Rent lands with the owner's portfolio and counts as collateral
+        assert_eq!(portfolio_account.lamports(), 7_000_000);
+        assert_eq!(portfolio.equity, 7_000_000);
+        assert_eq!(portfolio.principal, 7_000_000);
+
+        // The signer gets nothing; the PDA is emptied
+        assert_eq!(keeper_account.lamports(), 1_000_000);
+        assert_eq!(position.lamports(), 0);
+        assert!(position.try_borrow_data().unwrap().iter().all(|b| *b == 0));
+    }
+}
+
+#[cfg(test)]
+mod position_limit_tests {
+    use super::super::check_position_limit;
+    use crate::state::{Portfolio, SlabRegistry};
+    use percolator_common::PercolatorError;
+    use pinocchio::pubkey::Pubkey;
+
+    /// Test: New positions open up to the registry cap, and the next one is refused
+    #[test]
+    fn test_new_positions_stop_at_cap() {
+        let mut registry = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+        registry.set_max_positions_per_portfolio(3).unwrap();
+        let mut portfolio = Portfolio::new(Pubkey::default(), [1; 32], 0);
+
+        for slab_idx in 0..3 {
+            assert_eq!(check_position_limit(&portfolio, 1, registry.max_positions_per_portfolio), Ok(()));
+            portfolio.update_exposure(slab_idx, 0, 1_000_000);
+        }
+        assert_eq!(
+            check_position_limit(&portfolio, 1, registry.max_positions_per_portfolio),
+            Err(PercolatorError::TooManyPositions)
+        );
+
+        // Trading existing positions is still allowed at the cap
+        assert_eq!(check_position_limit(&portfolio, 0, registry.max_positions_per_portfolio), Ok(()));
+
+        // A batch opening several at once counts all of them
+        portfolio.update_exposure(2, 0, 0);
+        assert_eq!(
+            check_position_limit(&portfolio, 2, registry.max_positions_per_portfolio),
+            Err(PercolatorError::TooManyPositions)
+        );
+    }
+}
+
+#[cfg(test)]
+mod lifetime_pnl_tests {
+    use super::super::close_position_to_portfolio;
+    use super::test_account::TestAccount;
+    use crate::state::{Portfolio, PositionDetails};
+    use percolator_common::{pnl_to_lamports, InstrumentScale};
+    use pinocchio::pubkey::Pubkey;
+
+    const SCALE: i64 = 1_000_000;
+    const PORTFOLIO: Pubkey = [4; 32];
+
+    /// Open a 1-contract long at `entry`, close it at `exit` and roll it up
+    fn round_trip(portfolio: &mut Portfolio, entry: i64, exit: i64) -> i128 {
+        let mut details = PositionDetails::new(PORTFOLIO, 0, 0, entry, SCALE, 0, 255, 0, 1);
+        let (pnl, remaining, _) = details.reduce_position(exit, -SCALE, 0, 1);
+        assert_eq!(remaining, 0);
+
+        let mut position = TestAccount::new([3; 32], 2_000_000, 16);
+        let mut portfolio_account = TestAccount::new(PORTFOLIO, 5_000_000, 0);
+        let realized = pnl_to_lamports(details.realized_pnl, SCALE, InstrumentScale::DEFAULT);
+        close_position_to_portfolio(&position.info(), &portfolio_account.info(), portfolio, realized).unwrap();
+        pnl_to_lamports(pnl, SCALE, InstrumentScale::DEFAULT)
+    }
+
+    /// Test: Realized PnL of closed positions accumulates past their PDAs
+    #[test]
+    fn test_lifetime_realized_pnl_sums_closed_positions() {
+        let mut portfolio = Portfolio::new(Pubkey::default(), [1; 32], 0);
+
+        let profit = round_trip(&mut portfolio, 100 * SCALE, 110 * SCALE);
+        assert!(profit > 0);
+        assert_eq!(portfolio.lifetime_realized_pnl, profit);
+
+        let loss = round_trip(&mut portfolio, 100 * SCALE, 95 * SCALE);
+        assert!(loss < 0);
+        assert_eq!(portfolio.lifetime_realized_pnl, profit + loss);
+    }
+}
+
+#[cfg(test)]
+mod liquidation_batch_tests {
+    use super::test_account::TestAccount;
+    use crate::instructions::{charge_liquidation_spread, process_liquidate_batch, process_liquidate_user};
+    use crate::state::{InsuranceState, Portfolio, SlabRegistry, Vault};
+    use percolator_common::PercolatorError;
+    use pinocchio::pubkey::Pubkey;
+
+    /// Portfolio with 0.1 SOL maintenance margin and the given equity
+    fn portfolio(user: Pubkey, equity: i128) -> Portfolio {
+        let mut portfolio = Portfolio::new(Pubkey::default(), user, 0);
+        portfolio.mm = 100_000_000;
+        portfolio.equity = equity;
+        portfolio
+    }
+
+    /// Test: One batch liquidates every underwater portfolio and skips the healthy one
+    #[test]
+    fn test_batch_liquidates_only_underwater_accounts() {
+        let keys: [Pubkey; 3] = [[1; 32], [2; 32], [3; 32]];
+        let mut portfolios = [
+            portfolio(keys[0], 50_000_000),  // health 0.5
+            portfolio(keys[1], 200_000_000), // health 2.0
+            portfolio(keys[2], 80_000_000),  // health 0.8
+        ];
+
+        let mut registry = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+        let mut vault = Vault {
+            router_id: Pubkey::default(),
+            mint: Pubkey::default(),
+            token_account: Pubkey::default(),
+            balance: 0,
+            total_pledged: 0,
+            bump: 0,
+            _padding: [0; 7],
+        };
+        let mut dlp_portfolio = Portfolio::new(Pubkey::default(), [9; 32], 0);
+
+        let mut portfolio_accounts = keys.map(|key| TestAccount::new(key, 0, 0));
+        let portfolio_accounts = portfolio_accounts.each_mut().map(|a| a.info());
+        let mut dlp_account = TestAccount::new([9; 32], 0, 0);
+        let mut authority = TestAccount::new([10; 32], 0, 0);
+        let mut system_program = TestAccount::new([0; 32], 0, 0);
+        let mut slab_program = TestAccount::new([11; 32], 0, 0);
+        let mut liquidator = TestAccount::new([12; 32], 0, 0).signer();
+        let (dlp_account, authority, system_program, slab_program, liquidator) =
+            (dlp_account.info(), authority.info(), system_program.info(), slab_program.info(), liquidator.info());
+
+        let liquidated = process_liquidate_batch(&keys, |i| {
+            process_liquidate_user(
+                &portfolio_accounts[i],
+                &mut portfolios[i],
+                &dlp_account,
+                &mut dlp_portfolio,
+                &mut registry,
+                &mut vault,
+                &authority,
+                &system_program,
+                &slab_program,
+                &liquidator,
+                &[],
+                &[],
+                &[],
+                &[],
+                &Pubkey::default(),
+                false,
+                1_000,
+            )
+        })
+        .unwrap();
+
+        // The healthy portfolio in the middle is skipped, not fatal
+        assert_eq!(liquidated, 0b101);
+        assert!(portfolios[0].health < 0);
+        assert!(portfolios[1].health > 0);
+        assert!(portfolios[2].health < 0);
+    }
+
+    /// Test: The spread comes out of the liquidated account, not the DLP's books
+    #[test]
+    fn test_spread_charged_to_liquidated_account() {
+        let mut user = portfolio([1; 32], 50_000_000);
+        let mut dlp = Portfolio::new(Pubkey::default(), [9; 32], 0);
+        dlp.equity = 1_000_000_000;
+        let mut insurance = InsuranceState::default();
+        let mut user_account = TestAccount::new([1; 32], 100_000_000, 0);
+        let mut dlp_account = TestAccount::new([9; 32], 1_000_000_000, 0);
+        let (user_info, dlp_info) = (user_account.info(), dlp_account.info());
+
+        let spread = 11_167_000;
+        assert_eq!(charge_liquidation_spread(&user_info, &mut user, &dlp_info, &mut insurance, spread), Ok(spread));
+
+        // User pays in lamports and equity; the DLP account only custodies it
+        assert_eq!(user_info.lamports(), 100_000_000 - spread as u64);
+        assert_eq!(user.equity, 50_000_000 - spread as i128);
+        assert_eq!(user.pnl, -(spread as i128));
+        assert_eq!(dlp_info.lamports(), 1_000_000_000 + spread as u64);
+        assert_eq!((dlp.pnl, dlp.equity), (0, 1_000_000_000));
+        assert_eq!(insurance.vault_balance, spread);
+
+        // Never past the account's remaining equity, so no bad debt is created
+        assert_eq!(
+            charge_liquidation_spread(&user_info, &mut user, &dlp_info, &mut insurance, 100_000_000),
+            Ok(38_833_000)
+        );
+        assert_eq!(user.equity, 0);
+        assert_eq!(user_info.lamports(), 50_000_000);
+        assert_eq!(insurance.vault_balance, 50_000_000);
+        assert_eq!(charge_liquidation_spread(&user_info, &mut user, &dlp_info, &mut insurance, 1), Ok(0));
+    }
+
+    /// Test: A batch with nothing underwater fails like a single healthy liquidation
+    #[test]
+    fn test_batch_without_underwater_accounts_rejected() {
+        let keys: [Pubkey; 2] = [[1; 32], [2; 32]];
+        assert_eq!(
+            process_liquidate_batch(&keys, |_| Err(PercolatorError::AccountHealthy)),
+            Err(PercolatorError::AccountHealthy)
+        );
+
+        // Other failures abort the whole batch
+        assert_eq!(
+            process_liquidate_batch(&keys, |i| if i == 0 { Ok(()) } else { Err(PercolatorError::LiquidationCooldown) }),
+            Err(PercolatorError::LiquidationCooldown)
+        );
+
+        // A portfolio can't appear twice
+        assert_eq!(
+            process_liquidate_batch(&[keys[0], keys[0]], |_| Ok(())),
+            Err(PercolatorError::InvalidInstruction)
+        );
+    }
+}
+
+#[cfg(test)]
+mod duplicate_slab_tests {
+    use super::super::{has_duplicate_keys, process_execute_cross_slab, SlabSplit};
+    use super::test_account::TestAccount;
+    use crate::state::{Portfolio, SlabRegistry};
+    use percolator_common::PercolatorError;
+    use pinocchio::pubkey::Pubkey;
+
+    /// Test: A two-split order naming the same slab twice is rejected before any fill
+    #[test]
+    fn test_duplicate_slab_rejected() {
+        let user: Pubkey = [1; 32];
+        let slab: Pubkey = [5; 32];
+        let mut portfolio = Portfolio::new(Pubkey::default(), user, 0);
+        let mut dlp_portfolio = Portfolio::new(Pubkey::default(), [2; 32], 0);
+        let mut registry = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+
+        let mut buffers = [
+            TestAccount::new([3; 32], 0, 0), // user portfolio
+            TestAccount::new(user, 0, 0).signer(),
+            TestAccount::new([4; 32], 0, 0), // DLP portfolio
+            TestAccount::new([6; 32], 0, 0), // router authority
+            TestAccount::new([0; 32], 0, 0), // system program
+            TestAccount::new([7; 32], 0, 0), // slab program
+            TestAccount::new(slab, 0, 0),
+            TestAccount::new(slab, 0, 0),
+            TestAccount::new([8; 32], 0, 0), // receipts
+            TestAccount::new([9; 32], 0, 0),
+            TestAccount::new([10; 32], 0, 0), // oracles
+            TestAccount::new([11; 32], 0, 0),
+            TestAccount::new([12; 32], 0, 0), // position details
+            TestAccount::new([13; 32], 0, 0),
+        ];
+        let accounts = buffers.each_mut().map(|b| b.info());
+        assert!(has_duplicate_keys(&accounts[6..8]));
+        assert!(!has_duplicate_keys(&accounts[8..10]));
+
+        let split = SlabSplit { slab_id: slab, qty: 1_000_000, side: 0, limit_px: 100_000_000 };
+        let result = process_execute_cross_slab(
+            &accounts[0],
+            &mut portfolio,
+            &accounts[1],
+            &accounts[1],
+            &accounts[2],
+            &mut dlp_portfolio,
+            &mut registry,
+            &accounts[3],
+            &accounts[4],
+            &accounts[5],
+            &accounts[6..8],
+            &accounts[8..10],
+            &accounts[10..12],
+            &accounts[12..14],
+            &[],
+            &[split, split],
+            0,
+            1,
+            1,
+            0,
+            false,
+            false,
+            true,
+            false,
+            &Pubkey::default(),
+        );
+        assert_eq!(result, Err(PercolatorError::DuplicateSlab));
+
+        // The same PositionDetails twice is rejected too
+        let result = process_execute_cross_slab(
+            &accounts[0],
+            &mut portfolio,
+            &accounts[1],
+            &accounts[1],
+            &accounts[2],
+            &mut dlp_portfolio,
+            &mut registry,
+            &accounts[3],
+            &accounts[4],
+            &accounts[5],
+            &accounts[7..9],
+            &accounts[8..10],
+            &accounts[10..12],
+            &[accounts[12], accounts[12]],
+            &[],
+            &[split, split],
+            0,
+            1,
+            1,
+            0,
+            false,
+            false,
+            true,
+            false,
+            &Pubkey::default(),
+        );
+        assert_eq!(result, Err(PercolatorError::InvalidAccount));
+    }
+}
+
+#[cfg(test)]
+mod oracle_slicing_tests {
+    /// Test: Oracle groups swapped between two slabs fail the order before any fill
+    // Note: PDA derivation only runs on Solana target due to syscall requirements
+    #[test]
+    #[cfg(target_os = "solana")]
+    fn test_swapped_oracle_groups_rejected() {
+        use super::super::{process_execute_cross_slab, SlabSplit};
+        use super::test_account::TestAccount;
+        use crate::pda::derive_authority_pda;
+        use crate::state::{Portfolio, SlabRegistry};
+        use percolator_common::PercolatorError;
+        use pinocchio::pubkey::Pubkey;
+
+        let user: Pubkey = [1; 32];
+        let (slab_a, slab_b): (Pubkey, Pubkey) = ([5; 32], [6; 32]);
+        let mut portfolio = Portfolio::new(Pubkey::default(), user, 0);
+        let mut dlp_portfolio = Portfolio::new(Pubkey::default(), [2; 32], 0);
+        let mut registry = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+        registry.register_slab(slab_a, [1; 32], [11; 32], 500, 250, 10, 10, 1000, 1_000_000, 10, 0).unwrap();
+        registry.register_slab(slab_b, [1; 32], [12; 32], 500, 250, 10, 10, 1000, 1_000_000, 10, 0).unwrap();
+        registry.set_extra_oracles(&slab_a, &[[13; 32], [14; 32]]).unwrap();
+        registry.set_extra_oracles(&slab_b, &[[15; 32], [16; 32]]).unwrap();
+        let (authority, _) = derive_authority_pda(&Pubkey::default());
+
+        let mut buffers = [
+            TestAccount::new([3; 32], 0, 0), // user portfolio
+            TestAccount::new(user, 0, 0).signer(),
+            TestAccount::new([4; 32], 0, 0), // DLP portfolio
+            TestAccount::new(authority, 0, 0),
+            TestAccount::new([0; 32], 0, 0), // system program
+            TestAccount::new([7; 32], 0, 0), // slab program
+            TestAccount::new(slab_a, 0, 0),
+            TestAccount::new(slab_b, 0, 0),
+            TestAccount::new([8; 32], 0, 0), // receipts
+            TestAccount::new([9; 32], 0, 0),
+            TestAccount::new([12; 32], 0, 0), // slab B's feeds in slab A's slots
+            TestAccount::new([15; 32], 0, 0),
+            TestAccount::new([16; 32], 0, 0),
+            TestAccount::new([11; 32], 0, 0), // slab A's feeds in slab B's slots
+            TestAccount::new([13; 32], 0, 0),
+            TestAccount::new([14; 32], 0, 0),
+            TestAccount::new([17; 32], 0, 0), // position details
+            TestAccount::new([18; 32], 0, 0),
+        ];
+        let accounts = buffers.each_mut().map(|b| b.info());
+
+        let split_a = SlabSplit { slab_id: slab_a, qty: 1_000_000, side: 0, limit_px: 100_000_000 };
+        let split_b = SlabSplit { slab_id: slab_b, ..split_a };
+        let mut execute = |oracles: &[pinocchio::account_info::AccountInfo], oracles_per_slab: usize| {
+            process_execute_cross_slab(
+                &accounts[0],
+                &mut portfolio,
+                &accounts[1],
+                &accounts[1],
+                &accounts[2],
+                &mut dlp_portfolio,
+                &mut registry,
+                &accounts[3],
+                &accounts[4],
+                &accounts[5],
+                &accounts[6..8],
+                &accounts[8..10],
+                oracles,
+                &accounts[16..18],
+                &[],
+                &[split_a, split_b],
+                0,
+                oracles_per_slab,
+                1,
+                0,
+                false,
+                false,
+                true,
+                false,
+                &Pubkey::default(),
+            )
+        };
+
+        // Three feeds per slab, each group handed to the other slab
+        assert_eq!(execute(&accounts[10..16], 3), Err(PercolatorError::OracleMismatch));
+
+        // One feed per slab: the two primaries swapped
+        assert_eq!(execute(&[accounts[10], accounts[13]], 1), Err(PercolatorError::OracleMismatch));
+    }
+}
+
+#[cfg(test)]
+mod fee_settlement_tests {
+    use super::super::{settle_taker_fee, total_taker_fee};
+    use super::test_account::TestAccount;
+    use crate::state::{InsuranceParams, InsuranceState, Portfolio};
+    use pinocchio::pubkey::Pubkey;
+
+    const SCALE: u128 = 1_000_000;
+
+    /// Test: The user pays the fee once; LP and insurance shares sum to it
+    #[test]
+    fn test_fee_debited_once_and_split() {
+        let mut user = Portfolio::new(Pubkey::default(), [1; 32], 0);
+        user.equity = 10_000_000;
+        let mut dlp = Portfolio::new(Pubkey::default(), [2; 32], 0);
+        dlp.equity = 50_000_000;
+        let mut user_account = TestAccount::new([3; 32], 10_000_000, 0);
+        let mut dlp_account = TestAccount::new([4; 32], 50_000_000, 0);
+        let user_info = user_account.info();
+        let dlp_info = dlp_account.info();
+
+        let mut insurance = InsuranceState::default();
+        let params = InsuranceParams::default(); // 10 bps of notional
+
+        // $1,000 notional at a 20 bps taker fee
+        let notional = 1_000 * SCALE;
+        let fee = total_taker_fee(&[2_000_000]);
+        let (to_lp, to_insurance) = settle_taker_fee(
+            &user_info, &mut user, &dlp_info, &mut dlp, &mut insurance, &params, fee, notional,
+        )
+        .unwrap();
+
+        assert_eq!((to_lp, to_insurance), (1_000_000, 1_000_000));
+        assert_eq!(user.equity, 10_000_000 - 2_000_000);
+        assert_eq!(user.pnl, -2_000_000);
+        assert_eq!(dlp.equity, 50_000_000 + 1_000_000);
+        assert_eq!(insurance.vault_balance, 1_000_000);
+        assert_eq!(insurance.total_fees_accrued, 1_000_000);
+
+        // Every fee lamport leaves the user exactly once
+        assert_eq!(user_info.lamports(), 8_000_000);
+        assert_eq!(dlp_info.lamports(), 52_000_000);
+    }
+
+    /// Test: Lifetime fees keep accumulating after the positions they came from close
+    #[test]
+    fn test_lifetime_fees_survive_position_close() {
+        let mut user = Portfolio::new(Pubkey::default(), [1; 32], 0);
+        let mut dlp = Portfolio::new(Pubkey::default(), [2; 32], 0);
+        let mut user_account = TestAccount::new([3; 32], 10_000_000, 0);
+        let mut dlp_account = TestAccount::new([4; 32], 0, 0);
+        let user_info = user_account.info();
+        let dlp_info = dlp_account.info();
+        let mut insurance = InsuranceState::default();
+        let params = InsuranceParams::default();
+        let qty = 1_000_000i64;
+
+        // Open and close on slab 0, then open and close on slab 1
+        for (slab_idx, open_fee, close_fee) in [(0u16, 200_000u128, 150_000u128), (1, 300_000, 100_000)] {
+            user.update_exposure(slab_idx, 0, qty);
+            settle_taker_fee(&user_info, &mut user, &dlp_info, &mut dlp, &mut insurance, &params, open_fee, 100 * SCALE)
+                .unwrap();
+
+            user.update_exposure(slab_idx, 0, 0);
+            settle_taker_fee(&user_info, &mut user, &dlp_info, &mut dlp, &mut insurance, &params, close_fee, 100 * SCALE)
+                .unwrap();
+            assert_eq!(user.exposure_count, 0);
+        }
+
+        assert_eq!(user.lifetime_fees_paid, 750_000);
+        assert_eq!(user_info.lamports(), 10_000_000 - 750_000);
+        assert_eq!(dlp.lifetime_fees_paid, 0);
+    }
+
+    /// Test: Insurance is never credited more than the fee collected
+    #[test]
+    fn test_insurance_share_capped_at_fee() {
+        let mut user = Portfolio::new(Pubkey::default(), [1; 32], 0);
+        let mut dlp = Portfolio::new(Pubkey::default(), [2; 32], 0);
+        let mut user_account = TestAccount::new([3; 32], 1_000_000, 0);
+        let mut dlp_account = TestAccount::new([4; 32], 0, 0);
+        let mut insurance = InsuranceState::default();
+        let params = InsuranceParams { fee_bps_to_insurance: 100, ..Default::default() };
+
+        // 1% of notional would be 10x the 10 bps fee actually charged
+        let (to_lp, to_insurance) = settle_taker_fee(
+            &user_account.info(), &mut user, &dlp_account.info(), &mut dlp,
+            &mut insurance, &params, 1_000_000, 1_000 * SCALE,
+        )
+        .unwrap();
+
+        assert_eq!((to_lp, to_insurance), (0, 1_000_000));
+        assert_eq!(user.equity, -1_000_000);
+        assert_eq!(dlp.equity, 0);
+        assert_eq!(insurance.vault_balance, 1_000_000);
+    }
+
+    /// Test: A zero fee moves nothing; an unaffordable fee fails untouched
+    #[test]
+    fn test_zero_and_unfunded_fee() {
+        let mut user = Portfolio::new(Pubkey::default(), [1; 32], 0);
+        let mut dlp = Portfolio::new(Pubkey::default(), [2; 32], 0);
+        let mut user_account = TestAccount::new([3; 32], 500, 0);
+        let mut dlp_account = TestAccount::new([4; 32], 0, 0);
+        let user_info = user_account.info();
+        let dlp_info = dlp_account.info();
+        let mut insurance = InsuranceState::default();
+        let params = InsuranceParams::default();
+
+        assert_eq!(total_taker_fee(&[0, -5]), 0);
+        assert_eq!(
+            settle_taker_fee(&user_info, &mut user, &dlp_info, &mut dlp, &mut insurance, &params, 0, SCALE),
+            Ok((0, 0))
+        );
+
+        assert!(settle_taker_fee(
+            &user_info, &mut user, &dlp_info, &mut dlp, &mut insurance, &params, 1_000, 1_000 * SCALE,
+        )
+        .is_err());
+        assert_eq!(user.equity, 0);
+        assert_eq!(user_info.lamports(), 500);
+        assert_eq!(insurance.vault_balance, 0);
+    }
+}
+
+#[cfg(test)]
+mod reduce_only_tests {
+    use super::super::{check_reduce_only, SlabSplit};
+    use percolator_common::PercolatorError;
+    use pinocchio::pubkey::Pubkey;
+
+    const SCALE: i64 = 1_000_000;
+
+    fn split(side: u8, qty: i64) -> SlabSplit {
+        SlabSplit { slab_id: Pubkey::default(), qty, side, limit_px: 100 * SCALE }
+    }
+
+    /// Test: Reduce-only orders that shrink or close a position pass
+    #[test]
+    fn test_reduce_only_reduces() {
+        // Sell part of a long, then all of it
+        assert_eq!(check_reduce_only(10 * SCALE, &split(1, 4 * SCALE)), Ok(()));
+        assert_eq!(check_reduce_only(10 * SCALE, &split(1, 10 * SCALE)), Ok(()));
+
+        // Buy back part of a short
+        assert_eq!(check_reduce_only(-10 * SCALE, &split(0, 3 * SCALE)), Ok(()));
+    }
+
+    /// Test: Reduce-only orders can't open or add to a position
+    #[test]
+    fn test_reduce_only_rejects_increase() {
+        assert_eq!(
+            check_reduce_only(10 * SCALE, &split(0, SCALE)),
+            Err(PercolatorError::WouldIncreasePosition)
+        );
+        assert_eq!(
+            check_reduce_only(-10 * SCALE, &split(1, SCALE)),
+            Err(PercolatorError::WouldIncreasePosition)
+        );
+
+        // Flat: either side would open a position
+        for side in [0, 1] {
+            assert_eq!(
+                check_reduce_only(0, &split(side, SCALE)),
+                Err(PercolatorError::WouldIncreasePosition)
+            );
+        }
+    }
+
+    /// Test: Reduce-only orders can't trade through zero
+    #[test]
+    fn test_reduce_only_rejects_reversal() {
+        assert_eq!(
+            check_reduce_only(10 * SCALE, &split(1, 10 * SCALE + 1)),
+            Err(PercolatorError::WouldIncreasePosition)
+        );
+        assert_eq!(
+            check_reduce_only(-10 * SCALE, &split(0, 15 * SCALE)),
+            Err(PercolatorError::WouldIncreasePosition)
+        );
+    }
+}
+
+#[cfg(test)]
+mod trading_authority_tests {
+    use super::super::{check_liquidation_authority, check_trading_authority};
+    use super::test_account::TestAccount;
+    use crate::instructions::{process_set_delegate, process_set_liquidators};
+    use crate::state::{LiquidatorAccess, Portfolio, SlabRegistry};
+    use percolator_common::PercolatorError;
+    use pinocchio::pubkey::Pubkey;
+
+    const USER: Pubkey = [1; 32];
+    const VAULT: Pubkey = [2; 32];
+    const STRANGER: Pubkey = [3; 32];
+
+    fn delegated_portfolio() -> Portfolio {
+        let mut portfolio = Portfolio::new(Pubkey::default(), USER, 0);
+        process_set_delegate(&mut portfolio, &USER, VAULT).unwrap();
+        portfolio
+    }
+
+    /// Test: The owner signing can trade, with or without a delegate set
+    #[test]
+    fn test_owner_signs() {
+        let mut owner = TestAccount::new(USER, 0, 0).signer();
+        assert_eq!(check_trading_authority(&Portfolio::new(Pubkey::default(), USER, 0), &owner.info()), Ok(()));
+        assert_eq!(check_trading_authority(&delegated_portfolio(), &owner.info()), Ok(()));
+    }
+
+    /// Test: The delegate's signature (e.g. a vault PDA via invoke_signed) is accepted
+    #[test]
+    fn test_authorized_delegate_signs() {
+        let portfolio = delegated_portfolio();
+        let mut vault = TestAccount::new(VAULT, 0, 0).signer();
+        assert_eq!(check_trading_authority(&portfolio, &vault.info()), Ok(()));
+
+        // Still has to actually sign
+        let mut unsigned_vault = TestAccount::new(VAULT, 0, 0);
+        assert_eq!(check_trading_authority(&portfolio, &unsigned_vault.info()), Err(PercolatorError::Unauthorized));
+    }
+
+    /// Test: Anyone else is rejected, including a revoked delegate
+    #[test]
+    fn test_unauthorized_signer_rejected() {
+        let mut portfolio = delegated_portfolio();
+        let mut stranger = TestAccount::new(STRANGER, 0, 0).signer();
+        assert_eq!(check_trading_authority(&portfolio, &stranger.info()), Err(PercolatorError::InvalidPortfolio));
+
+        process_set_delegate(&mut portfolio, &USER, Pubkey::default()).unwrap();
+        let mut vault = TestAccount::new(VAULT, 0, 0).signer();
+        assert_eq!(check_trading_authority(&portfolio, &vault.info()), Err(PercolatorError::InvalidPortfolio));
+    }
+
+    /// Test: Liquidation fills are signed by an admitted liquidator, not the user
+    #[test]
+    fn test_liquidator_signs_liquidation() {
+        const GOVERNANCE: Pubkey = [9; 32];
+        let mut registry = SlabRegistry::new(Pubkey::default(), GOVERNANCE, 0);
+        let mut keeper = TestAccount::new(STRANGER, 0, 0).signer();
+        assert_eq!(check_liquidation_authority(&registry, &keeper.info()), Ok(()));
+
+        let mut unsigned = TestAccount::new(STRANGER, 0, 0);
+        assert_eq!(check_liquidation_authority(&registry, &unsigned.info()), Err(PercolatorError::Unauthorized));
+
+        // Permissioned: only the whitelist, even for a signer
+        process_set_liquidators(&mut registry, &GOVERNANCE, LiquidatorAccess::Permissioned as u8, &[VAULT]).unwrap();
+        assert_eq!(
+            check_liquidation_authority(&registry, &keeper.info()),
+            Err(PercolatorError::UnauthorizedLiquidator)
+        );
+    }
+}
+
+#[cfg(test)]
+mod rent_payer_tests {
+    use super::super::{check_rent_payer, classify_position_account, PositionAccountState};
+    use super::test_account::TestAccount;
+    use crate::state::{PositionDetails, POSITION_DETAILS_SIZE};
+    use percolator_common::PercolatorError;
+    use pinocchio::pubkey::Pubkey;
+
+    const PROGRAM: Pubkey = [7; 32];
+    const USER_PORTFOLIO: Pubkey = [3; 32];
+    const SPONSOR: Pubkey = [4; 32];
+
+    /// Test: A sponsor pays a user's position rent; the position stays the portfolio's
+    #[test]
+    fn test_sponsor_pays_rent_for_user_position() {
+        let mut sponsor = TestAccount::new(SPONSOR, 1_000_000_000, 0).signer();
+        assert_eq!(check_rent_payer(&sponsor.info()), Ok(()));
+
+        // Funded by the sponsor, the new position is still recorded against
+        // the user's portfolio, which the payer never enters into
+        let details = PositionDetails::new(USER_PORTFOLIO, 2, 0, 100_000_000, 1_000_000, 0, 255, 0, 1);
+        let data = unsafe {
+            core::slice::from_raw_parts(&details as *const PositionDetails as *const u8, POSITION_DETAILS_SIZE)
+        };
+        assert_eq!(details.portfolio, USER_PORTFOLIO);
+        assert_eq!(
+            classify_position_account(&PROGRAM, data, &PROGRAM, &USER_PORTFOLIO, 2, 0),
+            Ok(PositionAccountState::Adoptable)
+        );
+        assert_eq!(
+            classify_position_account(&PROGRAM, data, &PROGRAM, &SPONSOR, 2, 0),
+            Err(PercolatorError::InvalidAccount)
+        );
+    }
+
+    /// Test: A payer that didn't sign can't be drained for rent
+    #[test]
+    fn test_unsigned_rent_payer_rejected() {
+        let mut sponsor = TestAccount::new(SPONSOR, 1_000_000_000, 0);
+        assert_eq!(check_rent_payer(&sponsor.info()), Err(PercolatorError::Unauthorized));
+    }
+}
+
+#[cfg(test)]
+mod missing_position_details_tests {
+    use super::super::calculate_portfolio_margin_from_exposures;
+    use super::test_account::TestAccount;
+    use crate::state::{Portfolio, SlabRegistry, POSITION_DETAILS_SIZE};
+    use percolator_common::PercolatorError;
+    use pinocchio::pubkey::Pubkey;
+
+    const PROGRAM: Pubkey = [7; 32];
+    const SCALE: i64 = 1_000_000;
+
+    /// Test: Withholding an active position's PDA fails instead of under-margining
+    #[test]
+    fn test_omitted_pda_rejected() {
+        let registry = SlabRegistry::new(PROGRAM, Pubkey::default(), 0);
+        let mut portfolio = Portfolio::new(PROGRAM, [2; 32], 0);
+        portfolio.update_exposure(0, 0, 10 * SCALE);
+        portfolio.update_exposure(1, 0, -5 * SCALE);
+        let mut portfolio_account = TestAccount::new([3; 32], 0, 0);
+        let portfolio_info = portfolio_account.info();
+
+        assert_eq!(
+            calculate_portfolio_margin_from_exposures(&portfolio, &portfolio_info, &registry, &[], &[], &PROGRAM),
+            Err(PercolatorError::MissingPositionDetails)
+        );
+
+        // An account the router doesn't own can't stand in for the position
+        let mut foreign = TestAccount::new([4; 32], 0, POSITION_DETAILS_SIZE);
+        let foreign_info = foreign.info();
+        assert_eq!(
+            calculate_portfolio_margin_from_exposures(&portfolio, &portfolio_info, &registry, &[], &[foreign_info], &PROGRAM),
+            Err(PercolatorError::MissingPositionDetails)
+        );
+    }
+
+    /// Router-owned PositionDetails at its PDA for one of the portfolio's exposures
+    #[cfg(target_os = "solana")]
+    fn position_account(portfolio: &Pubkey, slab_idx: u16, qty: i64, margin: u128) -> TestAccount {
+        use crate::pda::{derive_position_details_pda, NO_CACHED_BUMP};
+        use crate::state::PositionDetails;
+
+        let (pda, bump) = derive_position_details_pda(portfolio, slab_idx, 0, NO_CACHED_BUMP, &PROGRAM);
+        let mut account = TestAccount::new(pda, 0, POSITION_DETAILS_SIZE).owned_by(PROGRAM);
+        {
+            let info = account.info();
+            let mut data = info.try_borrow_mut_data().unwrap();
+            let details = PositionDetails::new(*portfolio, slab_idx, 0, 100 * SCALE, qty, 0, bump, margin, 1);
+            unsafe { *(data.as_mut_ptr() as *mut PositionDetails) = details };
+        }
+        account
+    }
+
+    /// Test: Trading one position while another is open counts both
+    // Note: PDA derivation only runs on Solana target due to syscall requirements
+    #[test]
+    #[cfg(target_os = "solana")]
+    fn test_untraded_position_found_among_extras() {
+        let registry = SlabRegistry::new(PROGRAM, Pubkey::default(), 0);
+        let mut portfolio = Portfolio::new(PROGRAM, [2; 32], 0);
+        portfolio.update_exposure(0, 0, 10 * SCALE);
+        portfolio.update_exposure(1, 0, -5 * SCALE);
+        let mut portfolio_account = TestAccount::new([3; 32], 0, 0);
+        let portfolio_info = portfolio_account.info();
+
+        let mut traded = position_account(portfolio_info.key(), 0, 10 * SCALE, 1_000_000);
+        let mut untraded = position_account(portfolio_info.key(), 1, -5 * SCALE, 500_000);
+        let traded_info = traded.info();
+        let untraded_info = untraded.info();
+
+        // The split's PDA alone leaves the other open position unmargined
+        assert_eq!(
+            calculate_portfolio_margin_from_exposures(&portfolio, &portfolio_info, &registry, &[traded_info], &[], &PROGRAM),
+            Err(PercolatorError::MissingPositionDetails)
+        );
+
+        // Passed as an extra account, its margin is summed with the traded one
+        let (im, _) = calculate_portfolio_margin_from_exposures(
+            &portfolio,
+            &portfolio_info,
+            &registry,
+            &[traded_info],
+            &[untraded_info],
+            &PROGRAM,
+        )
+        .unwrap();
+        assert_eq!(im, 1_500_000);
+    }
+
+    /// Test: A cross-slab hedge still carries margin on both legs
+    // Note: PDA derivation only runs on Solana target due to syscall requirements
+    #[test]
+    #[cfg(target_os = "solana")]
+    fn test_cross_slab_hedge_not_netted() {
+        let mut registry = SlabRegistry::new(PROGRAM, Pubkey::default(), 0);
+        for slab_id in [[10; 32], [11; 32]] {
+            registry
+                .register_slab(slab_id, [1; 32], Pubkey::default(), 500, 250, 10, 10, 1000, 1_000_000, 10, 0)
+                .unwrap();
+        }
+
+        // +10 on slab A, -10 on slab B: signed exposures cancel
+        let mut portfolio = Portfolio::new(PROGRAM, [2; 32], 0);
+        portfolio.update_exposure(0, 0, 10 * SCALE);
+        portfolio.update_exposure(1, 0, -10 * SCALE);
+        assert_eq!(portfolio.get_exposure(0, 0) + portfolio.get_exposure(1, 0), 0);
+        let mut portfolio_account = TestAccount::new([3; 32], 0, 0);
+        let portfolio_info = portfolio_account.info();
+
+        let mut long = position_account(portfolio_info.key(), 0, 10 * SCALE, 1_000_000);
+        let mut short = position_account(portfolio_info.key(), 1, -10 * SCALE, 1_000_000);
+        let (im, mm) = calculate_portfolio_margin_from_exposures(
+            &portfolio,
+            &portfolio_info,
+            &registry,
+            &[long.info(), short.info()],
+            &[],
+            &PROGRAM,
+        )
+        .unwrap();
+
+        // IM is both legs' margin_held, not a zero taken on the net
+        assert_eq!(im, 2_000_000);
+        assert!(mm > 0);
+    }
+
+    /// Test: Closed exposures need no PositionDetails
+    #[test]
+    fn test_closed_exposure_needs_no_pda() {
+        let registry = SlabRegistry::new(PROGRAM, Pubkey::default(), 0);
+        let mut portfolio = Portfolio::new(PROGRAM, [2; 32], 0);
+        portfolio.update_exposure(0, 0, 10 * SCALE);
+        portfolio.update_exposure(0, 0, 0);
+        let mut portfolio_account = TestAccount::new([3; 32], 0, 0);
+
+        assert_eq!(
+            calculate_portfolio_margin_from_exposures(&portfolio, &portfolio_account.info(), &registry, &[], &[], &PROGRAM),
+            Ok((0, 0))
+        );
+    }
+}
+
+/// Hot-path logging cost
+///
+/// Per-fill tracing in process_execute_cross_slab goes through `log_debug!`,
+/// which is compiled out unless the `verbose_logs` feature is enabled. Costs
+/// from the runtime's compute budget: a static `msg!` is one sol_log_ syscall
+/// (max(100, len) CU, and every message here is under 100 bytes) and
+/// `sol_log_64` is 100 CU.
+///
+/// Execute happy path, market order adding to an open position, logging CU
+/// only ("Instruction: ExecuteCrossSlab" and "processed successfully" are
+/// logged by every build and left out):
+///
+/// | build                     | 1 split                    | each further split         |
+/// |---------------------------|----------------------------|----------------------------|
+/// | before (all `msg!`)       | 21 msg + 6 log_64 = 2,700  | 15 msg + 6 log_64 = 2,100  |
+/// | after, `verbose_logs`     | 20 msg + 7 log_64 = 2,700  | 15 msg + 6 log_64 = 2,100  |
+/// | after, default            | 0                          | 0                          |
+///
+/// Per trade, "before" is the leverage, phase, insurance and completion
+/// messages; per split it is the price, 4 CPI, PositionDetails, 6 margin,
+/// collateral and 2 settle messages. Reversals log more and save more.
+/// The figures are counted from the log calls against the syscall costs
+/// above, not measured on a validator; a validator run's "consumed N of M
+/// compute units" line is the number to check them against.
+#[cfg(test)]
+mod log_cost_tests {
+    /// Test: Debug log arguments are never evaluated in the default build
+    #[test]
+    #[cfg(not(feature = "verbose_logs"))]
+    fn test_debug_logs_compile_out() {
+        let mut evaluated = false;
+        log_debug!("never logged");
+        log_debug!("never logged", { evaluated = true; 1 }, 0, 0, 0, 0);
+        assert!(!evaluated);
+    }
+}
+
+#[cfg(test)]
+mod close_portfolio_tests {
+    use super::test_account::TestAccount;
+    use crate::instructions::process_close_portfolio;
+    use crate::state::{Portfolio, SlabRegistry};
+    use percolator_common::{borrow_account_data, borrow_account_data_mut, PercolatorError};
+    use pinocchio::pubkey::Pubkey;
+
+    const USER: Pubkey = [3; 32];
+    const PORTFOLIO: Pubkey = [8; 32];
+    const BALANCE: u64 = 2_500_000_000;
+
+    fn funded_portfolio() -> TestAccount {
+        let mut account = TestAccount::new(PORTFOLIO, BALANCE, core::mem::size_of::<Portfolio>());
+        let info = account.info();
+        let portfolio = unsafe { borrow_account_data_mut::<Portfolio>(&info).unwrap() };
+        *portfolio = Portfolio::new([7; 32], USER, 255);
+        portfolio.principal = 1_500_000_000;
+        portfolio.equity = 1_500_000_000;
+        account
+    }
+
+    /// Test: An empty portfolio returns every lamport to its owner and is wiped
+    #[test]
+    fn test_close_empty_portfolio() {
+        let mut portfolio_account = funded_portfolio();
+        let mut user = TestAccount::new(USER, 10_000, 0).signer();
+        let mut registry = SlabRegistry::new([7; 32], [1; 32], 0);
+        registry.total_deposits = 1_500_000_000;
+
+        let (portfolio_account, user) = (portfolio_account.info(), user.info());
+        assert_eq!(process_close_portfolio(&portfolio_account, &user, &mut registry), Ok(BALANCE));
+
+        assert_eq!(portfolio_account.lamports(), 0);
+        assert_eq!(user.lamports(), 10_000 + BALANCE);
+        assert_eq!(registry.total_deposits, 0);
+        assert!(portfolio_account.try_borrow_data().unwrap().iter().all(|&b| b == 0));
+    }
+
+    /// Test: An open position keeps the portfolio and its lamports in place
+    #[test]
+    fn test_close_refused_with_open_position() {
+        let mut portfolio_account = funded_portfolio();
+        let mut user = TestAccount::new(USER, 10_000, 0).signer();
+        let mut registry = SlabRegistry::new([7; 32], [1; 32], 0);
 
-        let net = calculate_net_exposure(&portfolio);
-        assert_eq!(net, 0, "Net exposure should be zero");
+        let (portfolio_account, user) = (portfolio_account.info(), user.info());
+        unsafe { borrow_account_data_mut::<Portfolio>(&portfolio_account).unwrap() }.update_exposure(0, 0, 1_000_000);
 
-        // When net = 0, IM calculation should yield 0
-        let im = (net.abs() as u128 * 60_000 * 10) / 100;
-        assert_eq!(im, 0, "Zero net MUST produce zero IM");
+        assert_eq!(
+            process_close_portfolio(&portfolio_account, &user, &mut registry),
+            Err(PercolatorError::PortfolioNotEmpty)
+        );
+        assert_eq!(portfolio_account.lamports(), BALANCE);
+        assert_eq!(user.lamports(), 10_000);
+        assert_eq!(unsafe { borrow_account_data::<Portfolio>(&portfolio_account).unwrap() }.user, USER);
     }
 }
@@ -1,6 +1,6 @@
 //! Initialize instruction - initialize router accounts
 
-use crate::pda::derive_registry_pda;
+use crate::pda::{derive_authority_pda, derive_registry_pda};
 use crate::state::SlabRegistry;
 use percolator_common::*;
 use pinocchio::{
@@ -215,6 +215,9 @@ pub fn process_initialize_registry(
 
     registry.initialize_in_place(registry_pda, *governance, bump);
 
+    // Cache the authority bump so trades re-derive it without a bump search
+    registry.authority_bump = derive_authority_pda(program_id).1;
+
     msg!("Registry initialized successfully");
     Ok(())
 }
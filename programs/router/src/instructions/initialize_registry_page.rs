@@ -0,0 +1,136 @@
+//! Initialize registry page - room for slabs beyond the registry's MAX_SLABS
+//!
+//! Governance creates pages in order; each is a PDA
+//! ["registry_page", page_index] holding another MAX_SLABS slab entries
+//! (see state::registry_page).
+
+use crate::pda::{derive_registry_page_pda, REGISTRY_PAGE_SEED};
+use crate::state::{RegistryPage, SlabRegistry, MAX_REGISTRY_PAGES};
+use percolator_common::*;
+use pinocchio::{account_info::AccountInfo, msg, pubkey::Pubkey};
+
+/// Index of the page governance may create next
+///
+/// Fails with Unauthorized for anyone but governance, and with
+/// InvalidInstruction once MAX_REGISTRY_PAGES exist.
+pub(crate) fn next_registry_page(registry: &SlabRegistry, authority: &Pubkey) -> Result<u16, PercolatorError> {
+    if authority != &registry.governance {
+        msg!("Error: Only governance can add registry pages");
+        return Err(PercolatorError::Unauthorized);
+    }
+
+    if registry.page_count as usize >= MAX_REGISTRY_PAGES {
+        msg!("Error: MAX_REGISTRY_PAGES limit reached");
+        return Err(PercolatorError::InvalidInstruction);
+    }
+
+    Ok(registry.page_count as u16 + 1)
+}
+
+/// Process initialize registry page instruction
+///
+/// # Arguments
+/// * `program_id` - The router program ID
+/// * `registry` - Slab registry (mutable; counts the new page)
+/// * `page_account` - The next page's PDA (created here)
+/// * `payer` - Governance signer, pays the page's rent
+/// * `system_program` - System program for account creation
+///
+/// # Returns
+/// * The new page's index
+pub fn process_initialize_registry_page(
+    program_id: &Pubkey,
+    registry: &mut SlabRegistry,
+    page_account: &AccountInfo,
+    payer: &AccountInfo,
+    system_program: &AccountInfo,
+) -> Result<u16, PercolatorError> {
+    use pinocchio::instruction::{AccountMeta, Instruction, Seed, Signer};
+    use pinocchio::program::{invoke, invoke_signed};
+    use pinocchio::sysvars::{rent::Rent, Sysvar};
+
+    let page_index = next_registry_page(registry, payer.key())?;
+
+    let (expected_pda, bump) = derive_registry_page_pda(page_index, program_id);
+    if page_account.key() != &expected_pda {
+        msg!("Error: Registry page PDA mismatch");
+        return Err(PercolatorError::InvalidAccount);
+    }
+    if page_account.lamports() != 0 {
+        msg!("Error: Registry page already exists");
+        return Err(PercolatorError::InvalidAccount);
+    }
+
+    let rent = Rent::get().map_err(|_| PercolatorError::InvalidAccount)?;
+    let lamports = rent.minimum_balance(RegistryPage::LEN);
+
+    let page_index_bytes = page_index.to_le_bytes();
+    let bump_seed = [bump];
+    let seeds = [
+        Seed::from(REGISTRY_PAGE_SEED),
+        Seed::from(&page_index_bytes[..]),
+        Seed::from(&bump_seed[..]),
+    ];
+
+    // Fund, allocate, and assign the PDA to the router
+    let mut transfer_data = [0u8; 12];
+    transfer_data[0..4].copy_from_slice(&2u32.to_le_bytes());
+    transfer_data[4..12].copy_from_slice(&lamports.to_le_bytes());
+    let transfer_ix = Instruction {
+        program_id: system_program.key(),
+        accounts: &[
+            AccountMeta::writable_signer(payer.key()),
+            AccountMeta::writable(page_account.key()),
+        ],
+        data: &transfer_data,
+    };
+    invoke(&transfer_ix, &[payer, page_account]).map_err(|_| PercolatorError::InvalidAccount)?;
+
+    let mut allocate_data = [0u8; 12];
+    allocate_data[0..4].copy_from_slice(&8u32.to_le_bytes());
+    allocate_data[4..12].copy_from_slice(&(RegistryPage::LEN as u64).to_le_bytes());
+    let allocate_ix = Instruction {
+        program_id: system_program.key(),
+        accounts: &[AccountMeta::writable_signer(page_account.key())],
+        data: &allocate_data,
+    };
+    invoke_signed(&allocate_ix, &[page_account], &[Signer::from(&seeds)])
+        .map_err(|_| PercolatorError::InvalidAccount)?;
+
+    let mut assign_data = [0u8; 36];
+    assign_data[0..4].copy_from_slice(&1u32.to_le_bytes());
+    assign_data[4..36].copy_from_slice(program_id.as_ref());
+    let assign_ix = Instruction {
+        program_id: system_program.key(),
+        accounts: &[AccountMeta::writable_signer(page_account.key())],
+        data: &assign_data,
+    };
+    invoke_signed(&assign_ix, &[page_account], &[Signer::from(&seeds)])
+        .map_err(|_| PercolatorError::InvalidAccount)?;
+
+    let page = unsafe { borrow_account_data_mut::<RegistryPage>(page_account)? };
+    page.initialize_in_place(page_index, bump);
+    registry.page_count += 1;
+
+    msg!("InitializeRegistryPage: Page created");
+    Ok(page_index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pages_created_in_order_by_governance() {
+        let mut registry = SlabRegistry::new(Pubkey::default(), [9; 32], 0);
+
+        assert_eq!(next_registry_page(&registry, &[1; 32]), Err(PercolatorError::Unauthorized));
+        assert_eq!(next_registry_page(&registry, &[9; 32]), Ok(1));
+
+        registry.page_count = 3;
+        assert_eq!(next_registry_page(&registry, &[9; 32]), Ok(4));
+
+        registry.page_count = MAX_REGISTRY_PAGES as u64;
+        assert_eq!(next_registry_page(&registry, &[9; 32]), Err(PercolatorError::InvalidInstruction));
+    }
+}
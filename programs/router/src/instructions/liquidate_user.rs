@@ -1,8 +1,13 @@
 //! Liquidate user positions via reduce-only cross-slab execution
 
-use crate::state::{Portfolio, SlabRegistry, Vault};
+use crate::instructions::{move_lamports, read_fill, read_slab_price, verify_slab_oracle};
+use crate::events::LiquidationEvent;
+use crate::instructions::SlabSplit;
+use crate::liquidation::oracle::liquidation_spread_lamports;
+use crate::liquidation::planner::{sort_worst_first, PositionPnl, MAX_LIQUIDATION_POSITIONS};
+use crate::state::{InsuranceState, Portfolio, PositionDetails, SlabRegistry, Vault, POSITION_DETAILS_SIZE};
 use percolator_common::*;
-use pinocchio::{account_info::AccountInfo, msg};
+use pinocchio::{account_info::AccountInfo, log::sol_log_data, msg, pubkey::Pubkey};
 
 /// Liquidation mode based on health
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -23,24 +28,299 @@ impl LiquidationMode {
     }
 }
 
-/// Determine liquidation mode based on health and buffer
-pub fn determine_mode(health: i128, preliq_buffer: i128) -> Option<LiquidationMode> {
-    if health < 0 {
-        // Below maintenance margin - hard liquidation
-        Some(LiquidationMode::HardLiquidation)
-    } else if health >= 0 && health < preliq_buffer {
-        // At or above MM but below buffer - pre-liquidation
-        Some(LiquidationMode::PreLiquidation)
+/// Health factor (bps) below which an account may be liquidated (1.0)
+pub const LIQUIDATION_HEALTH_BPS: u64 = 10_000;
+
+/// Health factor (bps) below which an account is in the pre-liquidation warning range (1.1)
+pub const WARNING_HEALTH_BPS: u64 = 11_000;
+
+/// Account health band derived from the health factor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HealthState {
+    /// Health >= 1.1
+    Healthy,
+    /// 1.0 <= health < 1.1: solvent, but close to liquidation
+    Warning,
+    /// Health < 1.0: equity no longer covers maintenance margin
+    Liquidatable,
+}
+
+/// Health factor = equity / maintenance_margin, in bps (10_000 = 1.0)
+///
+/// `equity` must already include unrealized PnL at mark. No maintenance
+/// margin means no open risk, so the factor is unbounded.
+pub fn health_factor_bps(equity: i128, mm: u128) -> u64 {
+    if mm == 0 {
+        return u64::MAX;
+    }
+    if equity <= 0 {
+        return 0;
+    }
+    let factor = (equity as u128).saturating_mul(10_000) / mm;
+    factor.min(u64::MAX as u128) as u64
+}
+
+/// Classify a health factor into its band
+pub fn health_state(health_factor_bps: u64) -> HealthState {
+    if health_factor_bps < LIQUIDATION_HEALTH_BPS {
+        HealthState::Liquidatable
+    } else if health_factor_bps < WARNING_HEALTH_BPS {
+        HealthState::Warning
     } else {
-        // Healthy - no liquidation needed
-        None
+        HealthState::Healthy
+    }
+}
+
+/// Determine liquidation mode based on the health factor
+///
+/// Only accounts below 1.0 can be liquidated; anything else (including the
+/// warning range) is rejected with AccountHealthy so solvent accounts can't
+/// be griefed. `is_preliq` selects the tighter pre-liquidation band.
+pub fn determine_mode(health_factor_bps: u64, is_preliq: bool) -> Result<LiquidationMode, PercolatorError> {
+    match health_state(health_factor_bps) {
+        HealthState::Liquidatable => Ok(if is_preliq {
+            LiquidationMode::PreLiquidation
+        } else {
+            LiquidationMode::HardLiquidation
+        }),
+        HealthState::Warning => {
+            msg!("Error: Account in pre-liquidation warning range, not liquidatable");
+            Err(PercolatorError::AccountHealthy)
+        }
+        HealthState::Healthy => {
+            msg!("Error: Account is healthy, no liquidation needed");
+            Err(PercolatorError::AccountHealthy)
+        }
+    }
+}
+
+/// Price a position is marked at for liquidation
+///
+/// The slab's damped mark, so a single outlier fill can't push an account
+/// under; falls back to the oracle for slabs that haven't set one.
+pub(crate) fn liquidation_mark_price(header: &SlabHeader, oracle_px: i64) -> i64 {
+    if header.mark_px > 0 {
+        header.mark_px
+    } else {
+        oracle_px
+    }
+}
+
+/// Charge a liquidation's spread to the liquidated account for the insurance fund
+///
+/// Closing fills realize PnL at the oracle, so the spread they took past it
+/// is still owed by the account. The lamports move to the DLP portfolio,
+/// whose account custodies insurance lamports (as with the taker fee's
+/// insurance share), without touching the DLP's own PnL. Capped at the
+/// account's remaining equity so the penalty never creates bad debt.
+///
+/// # Returns
+/// * The spread actually charged
+pub(crate) fn charge_liquidation_spread(
+    portfolio_account: &AccountInfo,
+    portfolio: &mut Portfolio,
+    dlp_portfolio_account: &AccountInfo,
+    insurance_state: &mut InsuranceState,
+    spread: u128,
+) -> Result<u128, PercolatorError> {
+    let charged = spread.min(portfolio.equity.max(0) as u128);
+    if charged == 0 {
+        return Ok(0);
+    }
+    let amount = u64::try_from(charged).map_err(|_| PercolatorError::Overflow)?;
+    move_lamports(portfolio_account, dlp_portfolio_account, amount).map_err(|e| {
+        msg!("Error: Portfolio insufficient SOL to cover liquidation spread");
+        e
+    })?;
+
+    let charged_i128 = charged as i128;
+    portfolio.pnl = portfolio.pnl.saturating_sub(charged_i128);
+    portfolio.equity = portfolio.equity.saturating_sub(charged_i128);
+    insurance_state.accrue_liquidation_spread(charged);
+    Ok(charged)
+}
+
+/// Sum the unrealized PnL (lamports) of a portfolio's positions at mark
+///
+/// Every open exposure must come with exactly one PositionDetails account,
+/// so a liquidator can't drop profitable positions to fake insolvency. Each
+/// position is priced at its slab's mark (see `liquidation_mark_price`), with
+/// the oracle paired with its slab in `slab_accounts` as the fallback.
+/// Each position's PnL is also written to `position_pnls`, in
+/// `position_accounts` order, so the liquidation can close the worst first.
+pub fn unrealized_pnl_at_mark(
+    portfolio: &Portfolio,
+    portfolio_key: &Pubkey,
+    registry: &SlabRegistry,
+    oracle_accounts: &[AccountInfo],
+    slab_accounts: &[AccountInfo],
+    position_accounts: &[AccountInfo],
+    program_id: &Pubkey,
+    position_pnls: &mut [PositionPnl],
+) -> Result<i128, PercolatorError> {
+    sum_unrealized_pnl(
+        portfolio,
+        portfolio_key,
+        registry,
+        oracle_accounts,
+        slab_accounts,
+        position_accounts,
+        program_id,
+        true,
+        position_pnls,
+    )
+}
+
+/// Sum the unrealized PnL (lamports) of a portfolio's positions at the oracle
+///
+/// Takes the same accounts as `unrealized_pnl_at_mark`, with every open
+/// exposure required, but prices each position at its slab's oracle as
+/// fills do (see `batch_unrealized_pnl`).
+pub(crate) fn unrealized_pnl_at_oracle(
+    portfolio: &Portfolio,
+    portfolio_key: &Pubkey,
+    registry: &SlabRegistry,
+    oracle_accounts: &[AccountInfo],
+    slab_accounts: &[AccountInfo],
+    position_accounts: &[AccountInfo],
+    program_id: &Pubkey,
+) -> Result<i128, PercolatorError> {
+    let mut position_pnls = [PositionPnl::default(); MAX_LIQUIDATION_POSITIONS];
+    sum_unrealized_pnl(
+        portfolio,
+        portfolio_key,
+        registry,
+        oracle_accounts,
+        slab_accounts,
+        position_accounts,
+        program_id,
+        false,
+        &mut position_pnls,
+    )
+}
+
+fn sum_unrealized_pnl(
+    portfolio: &Portfolio,
+    portfolio_key: &Pubkey,
+    registry: &SlabRegistry,
+    oracle_accounts: &[AccountInfo],
+    slab_accounts: &[AccountInfo],
+    position_accounts: &[AccountInfo],
+    program_id: &Pubkey,
+    at_mark: bool,
+    position_pnls: &mut [PositionPnl],
+) -> Result<i128, PercolatorError> {
+    let open_exposures = portfolio.exposures[..portfolio.exposure_count as usize]
+        .iter()
+        .filter(|(_, _, qty)| *qty != 0)
+        .count();
+    if position_accounts.len() != open_exposures {
+        msg!("Error: One PositionDetails required per open exposure");
+        return Err(PercolatorError::InvalidAccount);
+    }
+    if position_accounts.len() > position_pnls.len() {
+        msg!("Error: Too many positions to liquidate in one instruction");
+        return Err(PercolatorError::InvalidAccount);
     }
+
+    let mut total: i128 = 0;
+    for (i, account) in position_accounts.iter().enumerate() {
+        if account.owner() != program_id {
+            msg!("Error: PositionDetails not owned by router");
+            return Err(PercolatorError::InvalidAccountOwner);
+        }
+        let data = account.try_borrow_data().map_err(|_| PercolatorError::InvalidAccount)?;
+        if data.len() != POSITION_DETAILS_SIZE {
+            msg!("Error: PositionDetails account has wrong size");
+            return Err(PercolatorError::InvalidAccount);
+        }
+        let position = unsafe { &*(data.as_ptr() as *const PositionDetails) };
+        if !position.validate()
+            || &position.portfolio != portfolio_key
+            || portfolio.get_exposure(position.slab_index, position.instrument_index) == 0
+        {
+            msg!("Error: PositionDetails does not match an open exposure");
+            return Err(PercolatorError::InvalidAccount);
+        }
+
+        // Each exposure counted once
+        for other in &position_accounts[..i] {
+            if other.key() == account.key() {
+                msg!("Error: Duplicate PositionDetails account");
+                return Err(PercolatorError::InvalidAccount);
+            }
+        }
+
+        if position.slab_index >= registry.slab_count {
+            msg!("Error: Position slab is not registered");
+            return Err(PercolatorError::SlabNotRegistered);
+        }
+        let slab_id = registry.slabs[position.slab_index as usize].slab_id;
+        let slot = slab_accounts
+            .iter()
+            .position(|slab| slab.key() == &slab_id)
+            .ok_or_else(|| {
+                msg!("Error: Position's slab account not provided");
+                PercolatorError::InvalidAccount
+            })?;
+        let oracle = oracle_accounts.get(slot).ok_or_else(|| {
+            msg!("Error: Missing oracle account");
+            PercolatorError::InvalidOracle
+        })?;
+        verify_slab_oracle(registry, &slab_id, core::slice::from_ref(oracle))?;
+        let oracle_px = read_slab_price(registry, &slab_id, core::slice::from_ref(oracle))?;
+
+        let slab_data = slab_accounts[slot]
+            .try_borrow_data()
+            .map_err(|_| PercolatorError::InvalidAccount)?;
+        if slab_data.len() < SlabHeader::LEN {
+            msg!("Error: Invalid slab account data");
+            return Err(PercolatorError::InvalidAccount);
+        }
+        let header = unsafe { &*(slab_data.as_ptr() as *const SlabHeader) };
+        let mark_px = if at_mark { liquidation_mark_price(header, oracle_px) } else { oracle_px };
+
+        let pnl = pnl_lamports(position.unrealized_pnl(mark_px), header.contract_size, &header.scale_config());
+        position_pnls[i] = PositionPnl {
+            slab_idx: position.slab_index,
+            instrument_idx: position.instrument_index,
+            unrealized_pnl: pnl,
+        };
+        total = total.saturating_add(pnl);
+    }
+
+    Ok(total)
+}
+
+/// The PositionDetails among `position_accounts` for the position `split` closes
+///
+/// The accounts were already matched to the portfolio's open exposures by
+/// `unrealized_pnl_at_mark`; v0 slabs carry a single instrument.
+fn split_position_account<'a>(
+    registry: &SlabRegistry,
+    split: &SlabSplit,
+    position_accounts: &'a [AccountInfo],
+) -> Result<&'a AccountInfo, PercolatorError> {
+    let (slab_idx, _) = registry.find_slab(&split.slab_id).ok_or(PercolatorError::SlabNotRegistered)?;
+    for account in position_accounts {
+        let data = account.try_borrow_data().map_err(|_| PercolatorError::InvalidAccount)?;
+        if data.len() != POSITION_DETAILS_SIZE {
+            continue;
+        }
+        let position = unsafe { &*(data.as_ptr() as *const PositionDetails) };
+        if position.slab_index == slab_idx {
+            return Ok(account);
+        }
+    }
+    msg!("Error: No PositionDetails for the liquidated slab");
+    Err(PercolatorError::MissingPositionDetails)
 }
 
 /// Process liquidate user instruction
 ///
-/// This instruction liquidates an undercollateralized user by executing
-/// reduce-only orders across slabs to bring them back to health.
+/// This instruction liquidates an undercollateralized user by executing a
+/// reduce-only order that closes its most underwater position; a further
+/// call moves on to the next worst while the account stays unhealthy.
 ///
 /// # Arguments
 /// * `portfolio_account` - User's portfolio AccountInfo (for CPI)
@@ -51,10 +331,14 @@ pub fn determine_mode(health: i128, preliq_buffer: i128) -> Option<LiquidationMo
 /// * `vault` - Collateral vault
 /// * `router_authority` - Router authority PDA (for CPI signing)
 /// * `system_program` - System program account
+/// * `slab_program` - Slab program (for CPI)
+/// * `liquidator` - Signing liquidator (authorizes the fills and funds any rent they need)
 /// * `oracle_accounts` - Oracle price feed accounts (for price validation)
 /// * `slab_accounts` - Array of slab accounts to execute on
 /// * `receipt_accounts` - Array of receipt PDAs (one per slab)
-/// * `is_preliq` - Force pre-liquidation mode (if false, auto-determine)
+/// * `position_accounts` - PositionDetails PDAs, one per open exposure (unrealized PnL and close order, at most MAX_LIQUIDATION_POSITIONS)
+/// * `program_id` - Router program ID (must own every PositionDetails)
+/// * `is_preliq` - Use the tighter pre-liquidation band
 /// * `current_ts` - Current timestamp (for rate limiting)
 ///
 /// # Returns
@@ -72,16 +356,31 @@ pub fn process_liquidate_user(
     router_authority: &AccountInfo,
     system_program: &AccountInfo,
     slab_program: &AccountInfo,
+    liquidator: &AccountInfo,
     oracle_accounts: &[AccountInfo],
     slab_accounts: &[AccountInfo],
     receipt_accounts: &[AccountInfo],
+    position_accounts: &[AccountInfo],
+    program_id: &Pubkey,
     is_preliq: bool,
     current_ts: u64,
 ) -> Result<(), PercolatorError> {
     msg!("Liquidate: Starting liquidation check");
 
-    // Step 1: Calculate health = equity - MM
-    let health = portfolio.equity.saturating_sub(portfolio.mm as i128);
+    // Step 1: Mark equity to the slabs' mark prices and calculate health = equity - MM
+    let mut position_pnls = [PositionPnl::default(); MAX_LIQUIDATION_POSITIONS];
+    let unrealized = unrealized_pnl_at_mark(
+        portfolio,
+        portfolio_account.key(),
+        registry,
+        oracle_accounts,
+        slab_accounts,
+        position_accounts,
+        program_id,
+        &mut position_pnls,
+    )?;
+    let marked_equity = portfolio.equity.saturating_add(unrealized);
+    let health = marked_equity.saturating_sub(portfolio.mm as i128);
     msg!("Liquidate: Health calculated");
 
     // Store health in portfolio for tracking
@@ -104,24 +403,8 @@ pub fn process_liquidate_user(
         }
     }
 
-    // Step 2: Determine liquidation mode
-    let mode = if is_preliq {
-        // Force pre-liquidation mode
-        if health >= registry.preliq_buffer {
-            msg!("Error: Health too high for pre-liquidation");
-            return Err(PercolatorError::PortfolioHealthy);
-        }
-        LiquidationMode::PreLiquidation
-    } else {
-        // Auto-determine mode
-        match determine_mode(health, registry.preliq_buffer) {
-            Some(m) => m,
-            None => {
-                msg!("Error: Portfolio is healthy, no liquidation needed");
-                return Err(PercolatorError::PortfolioHealthy);
-            }
-        }
-    };
+    // Step 2: Determine liquidation mode from the health factor
+    let mode = determine_mode(health_factor_bps(marked_equity, portfolio.mm), is_preliq)?;
 
     msg!("Liquidate: Mode determined");
 
@@ -137,7 +420,7 @@ pub fn process_liquidate_user(
     // Step 4: Read oracle prices from oracle accounts
     use crate::liquidation::planner::OraclePrice;
     const MAX_ORACLES: usize = 16;
-    let mut oracle_prices = [OraclePrice { instrument_idx: 0, price: 0 }; MAX_ORACLES];
+    let mut oracle_prices = [OraclePrice { slab_idx: 0, instrument_idx: 0, price: 0 }; MAX_ORACLES];
     let mut oracle_count = 0;
 
     for (i, oracle_account) in oracle_accounts.iter().enumerate() {
@@ -145,26 +428,21 @@ pub fn process_liquidate_user(
             break;
         }
 
-        // Read PriceOracle struct from account data
-        // PriceOracle is 128 bytes total
-        let oracle_data = oracle_account.try_borrow_data()
-            .map_err(|_| PercolatorError::InvalidAccount)?;
+        // Each oracle is paired with the slab at the same index, so it is
+        // checked against that slab's registered feed and read the same way
+        // fills price it (TWAP buffer or staleness-checked feed)
+        let slab_id = slab_accounts.get(i).map(|slab| *slab.key()).ok_or_else(|| {
+            msg!("Error: Oracle account has no paired slab");
+            PercolatorError::InvalidAccount
+        })?;
+        verify_slab_oracle(registry, &slab_id, core::slice::from_ref(oracle_account))?;
+        let price = read_slab_price(registry, &slab_id, core::slice::from_ref(oracle_account))?;
+        let (slab_idx, _) = registry.find_slab(&slab_id).ok_or(PercolatorError::SlabNotRegistered)?;
 
-        if oracle_data.len() < 128 {
-            msg!("Warning: Oracle account too small, skipping");
-            continue;
-        }
-
-        // Extract price (at offset 72: magic(8) + version(1) + bump(1) + padding(6) + authority(32) + instrument(32) + price(8))
-        let price_bytes = [
-            oracle_data[72], oracle_data[73], oracle_data[74], oracle_data[75],
-            oracle_data[76], oracle_data[77], oracle_data[78], oracle_data[79],
-        ];
-        let price = i64::from_le_bytes(price_bytes);
-
-        // Use index as instrument_idx for v0 (in production, would map instrument pubkey to index)
+        // v0 slabs carry a single instrument
         oracle_prices[oracle_count] = OraclePrice {
-            instrument_idx: i as u16,
+            slab_idx,
+            instrument_idx: 0,
             price,
         };
         oracle_count += 1;
@@ -187,6 +465,15 @@ pub fn process_liquidate_user(
             break;
         }
 
+        // Positions are keyed on the slab's registry index
+        let slab_idx = match registry.find_slab(slab_account.key()) {
+            Some((slab_idx, _)) => slab_idx,
+            None => {
+                msg!("Warning: Slab not registered, skipping");
+                continue;
+            }
+        };
+
         // Read SlabHeader to get mark price
         let slab_data = slab_account.try_borrow_data()
             .map_err(|_| PercolatorError::InvalidAccount)?;
@@ -205,16 +492,19 @@ pub fn process_liquidate_user(
 
         slab_infos[slab_count] = SlabInfo {
             slab_id: *slab_account.key(),
-            slab_idx: i as u16,
-            instrument_idx: i as u16, // v0: use slab index as instrument index
+            slab_idx,
+            instrument_idx: 0, // v0 slabs carry a single instrument
             mark_price,
         };
         slab_count += 1;
     }
 
-    // Call planner to generate liquidation splits
+    // Call planner to close the most underwater position it can
+    let positions = &mut position_pnls[..position_accounts.len()];
+    sort_worst_first(positions);
     let plan = plan_reduce_only(
         portfolio,
+        positions,
         registry,
         &oracle_prices,
         oracle_count,
@@ -230,39 +520,99 @@ pub fn process_liquidate_user(
         return Ok(());
     }
 
+    // The planner closes one position per liquidation; its slab, receipt and
+    // oracle sit at the same slot in their account lists
+    let split = &plan.get_splits()[0];
+    let slot = slab_accounts
+        .iter()
+        .position(|slab| slab.key() == &split.slab_id)
+        .ok_or(PercolatorError::InvalidAccount)?;
+    if slot >= receipt_accounts.len() || slot >= oracle_accounts.len() {
+        msg!("Error: Missing receipt or oracle for the liquidated slab");
+        return Err(PercolatorError::InvalidAccount);
+    }
+
+    // Realized PnL before the fills, to report the loss they booked
+    let pnl_before = portfolio.pnl;
+
     // Execute the liquidation using the same cross-slab logic as normal orders
-    // Clone the user pubkey before the mutable borrow to avoid borrow checker issues
-    let user_pubkey = portfolio.user;
     use crate::instructions::process_execute_cross_slab;
-    use pinocchio::pubkey::Pubkey;
 
-    // TODO: Liquidation needs to support PositionDetails accounts
-    // For now, use empty slice - liquidations won't track PnL correctly
-    // This needs proper integration in Phase 2
-    let empty_position_details: &[AccountInfo] = &[];
-    let dummy_program_id = Pubkey::default();
+    // The split's own PositionDetails; the loaded ones all go along for the
+    // margin recount (the traded one again is harmless, each exposure takes
+    // its first match)
+    let split_position = split_position_account(registry, split, position_accounts)?;
 
+    // Fills go through the per-slab circuit breaker, so a flash-crash oracle
+    // print halts liquidation with CircuitBreakerTripped
     process_execute_cross_slab(
         portfolio_account,
         portfolio,
-        portfolio_account, // TODO: Should be actual user account
+        liquidator, // Signs in place of the user (see check_liquidation_authority)
+        liquidator, // Pays rent should a fill ever need a new PositionDetails
         dlp_portfolio_account,
         dlp_portfolio,
         registry,
         router_authority,
         system_program,
         slab_program,
-        &slab_accounts[..plan.split_count],
-        &receipt_accounts[..plan.split_count],
-        &oracle_accounts[..plan.split_count], // Pass oracles for validation
-        empty_position_details, // TODO: Add proper position details support
+        &slab_accounts[slot..=slot],
+        &receipt_accounts[slot..=slot],
+        &oracle_accounts[slot..=slot], // Pass oracles for validation
+        core::slice::from_ref(split_position),
+        position_accounts,
         plan.get_splits(),
         1, // Limit order (liquidations execute at specific prices)
-        10, // Use max leverage (10x) for liquidations to ensure sufficient margin calculation
-        &dummy_program_id, // TODO: Pass actual program_id
+        1, // One oracle per slab
+        1, // Leverage is ignored when reducing; 1x passes every slab's leverage cap
+        0, // Slippage tolerance unused for limit orders
+        false, // The liquidation plan sizes its own splits
+        true, // Reduce what has liquidity; a dry slab shouldn't block the rest
+        false, // Liquidations run outside trading hours too
+        true, // The liquidation penalty is the cost, not a taker fee
+        program_id,
     )?;
     msg!("Liquidate: Execution complete via cross-slab logic");
 
+    // Step 6.5: The fills realized the closed PnL at the oracle, so the spread
+    // they took past it (receipt qty at its VWAP) is charged to the account;
+    // that edge goes to the insurance fund, not the DLP
+    let spread = {
+        let slab_data = slab_accounts[slot]
+            .try_borrow_data()
+            .map_err(|_| PercolatorError::InvalidAccount)?;
+        if slab_data.len() < SlabHeader::LEN {
+            msg!("Error: Invalid slab account data");
+            return Err(PercolatorError::InvalidAccount);
+        }
+        let header = unsafe { &*(slab_data.as_ptr() as *const SlabHeader) };
+
+        let receipt_data = receipt_accounts[slot]
+            .try_borrow_data()
+            .map_err(|_| PercolatorError::InvalidAccount)?;
+        if receipt_data.len() < FillReceipt::LEN {
+            msg!("Error: Invalid receipt account size");
+            return Err(PercolatorError::InvalidAccount);
+        }
+        let receipt = unsafe { &*(receipt_data.as_ptr() as *const FillReceipt) };
+        let (filled_qty, vwap_px) = read_fill(receipt, split)?;
+
+        liquidation_spread_lamports(
+            filled_qty,
+            plan.oracle_prices[0],
+            vwap_px,
+            header.contract_size,
+            header.scale_config(),
+        )
+    };
+    let spread = charge_liquidation_spread(
+        portfolio_account,
+        portfolio,
+        dlp_portfolio_account,
+        &mut registry.insurance_state,
+        spread,
+    )?;
+
     // Step 7: Update portfolio health and timestamp
     portfolio.health = portfolio.equity.saturating_sub(portfolio.mm as i128);
     portfolio.last_liquidation_ts = current_ts;
@@ -270,59 +620,141 @@ pub fn process_liquidate_user(
     msg!("Liquidate: Portfolio updated");
 
     // Step 7.5: Settle bad debt via insurance fund if equity < 0
-    if portfolio.equity < 0 {
-        let bad_debt = portfolio.equity.abs() as u128;
-
-        // Calculate event notional (sum of liquidation fill notionals)
-        let mut event_notional: u128 = 0;
-        for split in plan.get_splits() {
-            let notional = ((split.qty.abs() as u128) * (split.limit_px.abs() as u128)) / 1_000_000;
-            event_notional = event_notional.saturating_add(notional);
-        }
+    let notional_closed = liquidation_notional(plan.get_splits());
+    let (insurance_draw, adl_triggered) =
+        settle_liquidation_shortfall(portfolio, registry, notional_closed, current_ts);
 
-        let (payout, uncovered) = registry.insurance_state.settle_bad_debt(
-            bad_debt,
-            event_notional,
-            &registry.insurance_params,
-            current_ts,
-        );
+    // Step 8: Emit the liquidation outcome for risk dashboards
+    LiquidationEvent {
+        portfolio: *portfolio_account.key(),
+        notional_closed,
+        realized_loss: pnl_before.saturating_sub(portfolio.pnl).max(0) as u128,
+        penalty_paid: spread,
+        insurance_draw,
+        adl_triggered,
+    }
+    .emit();
+    msg!("Liquidate: Liquidation completed successfully");
 
-        if payout > 0 {
-            // Apply insurance payout to portfolio equity
-            portfolio.equity = portfolio.equity.saturating_add(payout as i128);
-            msg!("Insurance payout applied to cover bad debt");
-        }
+    let _ = vault; // Will be used in production
+    let _ = router_authority; // Will be used for CPI signing
+    let _ = oracle_accounts; // Will be used for price reading
 
-        if uncovered > 0 {
-            msg!("Warning: Uncovered bad debt remains after insurance payout");
+    Ok(())
+}
 
-            // Trigger global haircut to socialize the uncovered loss across all users
-            // Apply haircut: new_index = old_index * (tvl - loss) / tvl
-            let tvl = vault.balance as i128;  // Simplified: use vault balance as TVL proxy
+/// Notional of a liquidation plan's fills at their limit prices (1e6 scale)
+///
+/// This is the event notional the insurance per-event payout cap applies to.
+pub(crate) fn liquidation_notional(splits: &[SlabSplit]) -> u128 {
+    splits.iter().fold(0u128, |total, split| {
+        let notional = (split.qty.unsigned_abs() as u128).saturating_mul(split.limit_px.unsigned_abs() as u128) / 1_000_000;
+        total.saturating_add(notional)
+    })
+}
+
+/// Cover a liquidated portfolio's negative equity from insurance, then ADL
+///
+/// The insurance fund pays what its caps allow; whatever is still uncovered
+/// is socialized across profitable accounts by dropping the global PnL index
+/// pro-rata to outstanding vested PnL, and each portfolio picks up its share
+/// on next touch.
+///
+/// # Returns
+/// `(insurance_draw, adl_triggered)`
+pub(crate) fn settle_liquidation_shortfall(
+    portfolio: &mut Portfolio,
+    registry: &mut SlabRegistry,
+    event_notional: u128,
+    current_ts: u64,
+) -> (u128, bool) {
+    if portfolio.equity >= 0 {
+        return (0, false);
+    }
+    let bad_debt = portfolio.equity.unsigned_abs();
 
-            if tvl > 0 {
-                let loss = uncovered as i128;
-                let tvl_after_loss = tvl.saturating_sub(loss).max(1);  // Ensure non-zero denominator
+    let (payout, uncovered) = registry.insurance_state.settle_bad_debt(
+        bad_debt,
+        event_notional,
+        &registry.insurance_params,
+        current_ts,
+    );
 
-                // Apply haircut ratio to global PnL index
-                // new_index = old_index * tvl_after_loss / tvl
-                let old_index = registry.global_haircut.pnl_index;
-                registry.global_haircut.pnl_index = (old_index * tvl_after_loss) / tvl;
+    if payout > 0 {
+        // Apply insurance payout to portfolio equity
+        portfolio.equity = portfolio.equity.saturating_add(payout as i128);
+        msg!("Insurance payout applied to cover bad debt");
+    }
 
-                msg!("Global haircut triggered to socialize uncovered bad debt");
-            }
+    let mut adl_triggered = false;
+    if uncovered > 0 {
+        msg!("Warning: Uncovered bad debt remains after insurance payout");
+        let socialized = crate::state::apply_system_deficit(registry, uncovered);
+        if socialized > 0 {
+            msg!("Global haircut triggered to socialize uncovered bad debt");
+            adl_triggered = true;
         }
     }
 
-    // Step 8: Emit liquidation events (simplified for v0)
-    // In production, emit LiquidationStart, LiquidationFill, LiquidationEnd
-    msg!("Liquidate: Liquidation completed successfully");
+    (payout, adl_triggered)
+}
 
-    let _ = vault; // Will be used in production
-    let _ = router_authority; // Will be used for CPI signing
-    let _ = oracle_accounts; // Will be used for price reading
+/// Most portfolios one LiquidateUser call can liquidate
+pub const MAX_LIQUIDATION_BATCH: usize = 8;
 
-    Ok(())
+/// Liquidate a batch of portfolios, skipping those that aren't underwater
+///
+/// `liquidate` runs process_liquidate_user on the portfolio at a batch index.
+/// An AccountHealthy result skips that portfolio instead of failing the
+/// batch; any other error aborts the whole transaction. The keys of the
+/// portfolios liquidated are logged with sol_log_data, in batch order.
+///
+/// # Arguments
+/// * `portfolio_keys` - Portfolios in the batch (up to MAX_LIQUIDATION_BATCH, no repeats)
+/// * `liquidate` - Liquidates the portfolio at the given index
+///
+/// # Returns
+/// * Bitmask of the batch indices liquidated; a batch with nothing
+///   underwater fails with AccountHealthy, as a single liquidation does
+pub fn process_liquidate_batch<F>(portfolio_keys: &[Pubkey], mut liquidate: F) -> Result<u8, PercolatorError>
+where
+    F: FnMut(usize) -> Result<(), PercolatorError>,
+{
+    if portfolio_keys.is_empty() || portfolio_keys.len() > MAX_LIQUIDATION_BATCH {
+        msg!("Error: LiquidateUser takes 1 to 8 portfolios");
+        return Err(PercolatorError::InvalidInstruction);
+    }
+    for (i, key) in portfolio_keys.iter().enumerate() {
+        if portfolio_keys[..i].contains(key) {
+            msg!("Error: Portfolio listed twice in liquidation batch");
+            return Err(PercolatorError::InvalidInstruction);
+        }
+    }
+
+    let mut log = [0u8; MAX_LIQUIDATION_BATCH * 32];
+    let mut liquidated: u8 = 0;
+    let mut count = 0;
+    for (i, key) in portfolio_keys.iter().enumerate() {
+        match liquidate(i) {
+            Ok(()) => {
+                log[count * 32..(count + 1) * 32].copy_from_slice(key);
+                liquidated |= 1 << i;
+                count += 1;
+            }
+            Err(PercolatorError::AccountHealthy) => {
+                msg!("Liquidate: Skipping portfolio that is not underwater");
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    if count == 0 {
+        msg!("Error: No portfolio in the batch is liquidatable");
+        return Err(PercolatorError::AccountHealthy);
+    }
+
+    sol_log_data(&[&log[..count * 32]]);
+    Ok(liquidated)
 }
 
 #[cfg(test)]
@@ -330,38 +762,81 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_determine_mode_hard_liquidation() {
-        let health = -1000;
-        let buffer = 10_000_000;
-        assert_eq!(determine_mode(health, buffer), Some(LiquidationMode::HardLiquidation));
+    fn test_health_factor() {
+        assert_eq!(health_factor_bps(150, 100), 15_000);
+        assert_eq!(health_factor_bps(105, 100), 10_500);
+        assert_eq!(health_factor_bps(-50, 100), 0);
+        // No maintenance margin: nothing to liquidate
+        assert_eq!(health_factor_bps(0, 0), u64::MAX);
     }
 
     #[test]
-    fn test_determine_mode_pre_liquidation() {
-        let health = 5_000_000;
-        let buffer = 10_000_000;
-        assert_eq!(determine_mode(health, buffer), Some(LiquidationMode::PreLiquidation));
+    fn test_healthy_account_rejected() {
+        let hf = health_factor_bps(200_000_000, 100_000_000);
+        assert_eq!(health_state(hf), HealthState::Healthy);
+        assert_eq!(determine_mode(hf, false), Err(PercolatorError::AccountHealthy));
+        assert_eq!(determine_mode(hf, true), Err(PercolatorError::AccountHealthy));
+
+        // Exactly 1.1 is healthy
+        assert_eq!(health_state(WARNING_HEALTH_BPS), HealthState::Healthy);
     }
 
     #[test]
-    fn test_determine_mode_healthy() {
-        let health = 15_000_000;
-        let buffer = 10_000_000;
-        assert_eq!(determine_mode(health, buffer), None);
+    fn test_warning_state_not_liquidatable() {
+        let hf = health_factor_bps(105_000_000, 100_000_000);
+        assert_eq!(health_state(hf), HealthState::Warning);
+        assert_eq!(determine_mode(hf, false), Err(PercolatorError::AccountHealthy));
+
+        // Exactly 1.0 is still solvent
+        assert_eq!(health_state(LIQUIDATION_HEALTH_BPS), HealthState::Warning);
     }
 
     #[test]
-    fn test_determine_mode_exact_buffer() {
-        let health = 10_000_000;
-        let buffer = 10_000_000;
-        assert_eq!(determine_mode(health, buffer), None);
+    fn test_underwater_account_liquidatable() {
+        let hf = health_factor_bps(95_000_000, 100_000_000);
+        assert_eq!(health_state(hf), HealthState::Liquidatable);
+        assert_eq!(determine_mode(hf, false), Ok(LiquidationMode::HardLiquidation));
+        assert_eq!(determine_mode(hf, true), Ok(LiquidationMode::PreLiquidation));
     }
 
     #[test]
-    fn test_determine_mode_zero_health() {
-        let health = 0;
-        let buffer = 10_000_000;
-        assert_eq!(determine_mode(health, buffer), Some(LiquidationMode::PreLiquidation));
+    fn test_unrealized_loss_makes_account_liquidatable() {
+        use crate::state::PositionDetails;
+
+        // 0.12 SOL equity vs 0.1 SOL MM looks healthy on realized equity alone
+        let equity: i128 = 120_000_000;
+        let mm: u128 = 100_000_000;
+        assert_eq!(health_state(health_factor_bps(equity, mm)), HealthState::Healthy);
+
+        // Long 1 @ $100 marked at $96: -0.0416 SOL unrealized at the oracle
+        let position = PositionDetails::new(Pubkey::default(), 0, 0, 100_000_000, 1_000_000, 0, 0, 0, 1);
+        let unrealized = pnl_to_lamports(position.unrealized_pnl(96_000_000), 1_000_000, InstrumentScale::DEFAULT);
+        assert_eq!(unrealized, -41_666_000);
+
+        let marked = health_factor_bps(equity + unrealized, mm);
+        assert_eq!(health_state(marked), HealthState::Liquidatable);
+    }
+
+    #[test]
+    fn test_liquidation_marks_at_damped_mark() {
+        let mut header = SlabHeader::new(
+            Pubkey::default(),
+            Pubkey::default(),
+            Pubkey::default(),
+            Pubkey::default(),
+            100_000_000,
+            20,
+            1_000_000,
+            255,
+        );
+
+        // One $60 print doesn't drag the mark (or a long's PnL) down to $60
+        header.update_mark_price(60_000_000, 100_000_000);
+        assert_eq!(liquidation_mark_price(&header, 100_000_000), 96_000_000);
+
+        // Slabs without a mark fall back to the oracle
+        header.mark_px = 0;
+        assert_eq!(liquidation_mark_price(&header, 100_000_000), 100_000_000);
     }
 
     #[test]
@@ -376,7 +851,8 @@ mod tests {
             governance: Pubkey::default(),
             slab_count: 0,
             bump: 0,
-            _padding: [0; 5],
+            authority_bump: 0,
+            _padding: [0; 4],
             imr: 500,
             mmr: 250,
             liq_band_bps: 200,      // 2% for hard liquidation
@@ -385,7 +861,7 @@ mod tests {
             router_cap_per_slab: 1_000_000,
             min_equity_to_quote: 100_000_000,
             oracle_tolerance_bps: 50,
-            _padding2: [0; 8],
+            max_slippage_bps: 500,
             insurance_params: crate::state::insurance::InsuranceParams::default(),
             insurance_state: crate::state::insurance::InsuranceState::default(),
             pnl_vesting_params: crate::state::pnl_vesting::PnlVestingParams::default(),
@@ -393,7 +869,25 @@ mod tests {
             warmup_config: model_safety::adaptive_warmup::AdaptiveWarmupConfig::default(),
             warmup_state: model_safety::adaptive_warmup::AdaptiveWarmupState::default(),
             total_deposits: 0,
-            _padding3: [0; 8],
+            max_oracle_divergence_bps: 100,
+            pause_flags: 0,
+            funding_params: crate::state::FundingParams::default(),
+            liquidation_mode: 0,
+            liquidator_count: 0,
+            _liquidator_padding: [0; 6],
+            liquidators: [Pubkey::default(); crate::state::MAX_LIQUIDATORS],
+            withdraw_queue_threshold: 0,
+            withdraw_delay_slots: 0,
+            max_portfolio_deposit: 0,
+            max_total_deposits: 0,
+            trade_cooldown_slots: 0,
+            page_count: 0,
+            pending_governance: Pubkey::default(),
+            liquidation_spread_bps: 0,
+            collateral_count: 0,
+            _collateral_padding: [0; 7],
+            collateral: [crate::state::CollateralConfig::default(); crate::state::MAX_COLLATERAL_MINTS],
+            max_positions_per_portfolio: crate::state::DEFAULT_MAX_POSITIONS_PER_PORTFOLIO,
             slabs: [SlabEntry {
                 slab_id: Pubkey::default(),
                 version_hash: [0; 32],
@@ -406,7 +900,24 @@ mod tests {
                 max_exposure: 0,
                 registered_ts: 0,
                 active: false,
-                _padding: [0; 7],
+                use_twap: false,
+                max_leverage: 0,
+                extra_oracle_count: 0,
+                limit_deviation_bps: 0,
+                _limit_padding: [0; 2],
+                mm_tiers: [crate::state::MmTier::default(); crate::state::MM_TIER_COUNT],
+                max_move_bps: 0,
+                last_settled_price: 0,
+                last_settled_slot: 0,
+                long_oi: 0,
+                short_oi: 0,
+                max_oi: 0,
+                trading_open_secs: 0,
+                trading_close_secs: 0,
+                global_funding_index: 0,
+                funding_rate_bps: 0,
+                last_funding_slot: 0,
+                extra_oracle_ids: [Pubkey::default(); crate::state::MAX_EXTRA_ORACLES],
             }; MAX_SLABS],
         };
 
@@ -436,4 +947,78 @@ mod tests {
         let misaligned_mark = 1_010_000;  // 1.0% diff
         assert!(!validate_oracle_alignment(misaligned_mark, oracle_price, tolerance_bps));
     }
+
+    #[test]
+    fn test_liquidation_spread_penalizes_user() {
+        use crate::liquidation::oracle::liquidation_fill_price;
+
+        const SCALE: i64 = 1_000_000;
+        let oracle_px = 90 * SCALE;
+        let fill_px = liquidation_fill_price(oracle_px, 1, 50);
+        assert_eq!(fill_px, 89_550_000);
+
+        // Long 2 @ $100 liquidated with the oracle at $90
+        let long = PositionDetails::new(Pubkey::default(), 0, 0, 100 * SCALE, 2 * SCALE, 0, 0, 0, 1);
+        let loss_at = |px: i64| -pnl_to_lamports(long.unrealized_pnl(px), SCALE, InstrumentScale::DEFAULT);
+        let oracle_loss = loss_at(oracle_px);
+        let penalized_loss = loss_at(fill_px);
+        assert_eq!(oracle_loss, 222_222_000);
+        assert_eq!(penalized_loss, 233_389_000);
+        assert!(penalized_loss > oracle_loss);
+
+        // For a position entered at the oracle the whole loss is the spread
+        let at_oracle = PositionDetails::new(Pubkey::default(), 0, 0, oracle_px, 2 * SCALE, 0, 0, 0, 1);
+        let spread = liquidation_spread_lamports(2 * SCALE, oracle_px, fill_px, SCALE, InstrumentScale::DEFAULT);
+        assert_eq!(
+            -pnl_to_lamports(at_oracle.unrealized_pnl(fill_px), SCALE, InstrumentScale::DEFAULT),
+            spread as i128
+        );
+    }
+
+    #[test]
+    fn test_liquidation_event_matches_outcome() {
+        use crate::events::LiquidationEvent;
+
+        let mut registry = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+        registry.insurance_state.vault_balance = 1_000_000_000;
+        registry.global_haircut.total_vested_pnl = 100_000_000;
+
+        // The fills realized a 0.045 SOL loss and left 0.02 SOL of bad debt
+        let mut portfolio = Portfolio::new(Pubkey::default(), Pubkey::default(), 0);
+        let pnl_before = portfolio.pnl;
+        portfolio.pnl = -45_000_000;
+        portfolio.equity = -20_000_000;
+        let splits = [SlabSplit { slab_id: [5; 32], qty: 10_000_000, side: 1, limit_px: 100_000_000 }];
+
+        let notional_closed = liquidation_notional(&splits);
+        assert_eq!(notional_closed, 1_000_000_000);
+        let (insurance_draw, adl_triggered) =
+            settle_liquidation_shortfall(&mut portfolio, &mut registry, notional_closed, 86_400);
+
+        // Insurance pays its 0.5%-of-notional event cap; ADL socializes the rest
+        assert_eq!(insurance_draw, 5_000_000);
+        assert!(adl_triggered);
+        assert_eq!(portfolio.equity, -15_000_000);
+
+        let event = LiquidationEvent {
+            portfolio: [3; 32],
+            notional_closed,
+            realized_loss: pnl_before.saturating_sub(portfolio.pnl).max(0) as u128,
+            penalty_paid: 500_000,
+            insurance_draw,
+            adl_triggered,
+        };
+        let logged = LiquidationEvent::from_bytes(&event.to_bytes()).unwrap();
+        assert_eq!(logged.portfolio, [3; 32]);
+        assert_eq!(logged.notional_closed, 1_000_000_000);
+        assert_eq!(logged.realized_loss, 45_000_000);
+        assert_eq!(logged.penalty_paid, 500_000);
+        assert_eq!(logged.insurance_draw, 5_000_000);
+        assert!(logged.adl_triggered);
+
+        // A solvent account draws nothing and triggers no ADL
+        let mut solvent = Portfolio::new(Pubkey::default(), Pubkey::default(), 0);
+        solvent.equity = 1;
+        assert_eq!(settle_liquidation_shortfall(&mut solvent, &mut registry, notional_closed, 86_400), (0, false));
+    }
 }
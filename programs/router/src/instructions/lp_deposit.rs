@@ -0,0 +1,336 @@
+//! Deposit into an AMM LP bucket by minting LP shares
+//!
+//! Counterpart of burn_lp_shares. This instruction:
+//! - Prices shares from the market's LpPool (pool assets / pool shares)
+//! - Debits equity by the deposit amount and credits it to the pool (the
+//!   entrypoint moves the lamports alongside)
+//! - Credits the AMM bucket with the minted shares (creating it if needed)
+//!
+//! Shares are priced in the same 1e6 scale burn_lp_shares redeems at, and
+//! mints round down, so a deposit followed by an immediate burn never
+//! returns more than was deposited.
+
+use crate::state::{LpBucket, LpPool, Portfolio, VenueId};
+use percolator_common::*;
+use pinocchio::{account_info::AccountInfo, msg, pubkey::Pubkey};
+
+/// Share price of a fresh bucket (1:1, scaled by 1e6)
+pub const INITIAL_LP_SHARE_PRICE: i64 = 1_000_000;
+
+/// Current share price from bucket net asset value (scaled by 1e6)
+///
+/// The first deposit into an empty bucket prices shares 1:1.
+pub fn lp_share_price(total_assets: u128, total_shares: u64) -> Result<i64, PercolatorError> {
+    if total_shares == 0 {
+        return Ok(INITIAL_LP_SHARE_PRICE);
+    }
+
+    let price = total_assets
+        .checked_mul(1_000_000)
+        .ok_or(PercolatorError::Overflow)?
+        / total_shares as u128;
+
+    if price == 0 || price > i64::MAX as u128 {
+        msg!("Error: Invalid LP share price");
+        return Err(PercolatorError::InvalidPrice);
+    }
+    Ok(price as i64)
+}
+
+/// Shares minted for a deposit at `share_price` (rounds down)
+///
+/// Inverse of burn_lp_shares' redemption = shares * share_price / 1e6.
+pub fn lp_shares_for_deposit(amount: u64, share_price: i64) -> Result<u64, PercolatorError> {
+    if share_price <= 0 {
+        return Err(PercolatorError::InvalidPrice);
+    }
+
+    let shares = (amount as u128 * 1_000_000) / share_price as u128;
+    u64::try_from(shares).map_err(|_| PercolatorError::Overflow)
+}
+
+/// Process LP deposit instruction
+///
+/// # Arguments
+/// * `portfolio` - Depositor's portfolio account (mutable)
+/// * `pool` - The AMM market's LP pool (mutable)
+/// * `amount` - Collateral to deposit (lamports, same units as equity)
+/// * `current_ts` - Current timestamp (refreshes the cached share price)
+///
+/// # Returns
+/// * Updates portfolio:
+///   - Reduces equity by `amount`
+///   - Adds minted shares to the AMM bucket (creates it on first deposit)
+///   - Caches the share price used
+/// * Updates pool: adds `amount` to its assets and the minted shares to its supply
+pub fn process_lp_deposit(
+    portfolio: &mut Portfolio,
+    pool: &mut LpPool,
+    amount: u64,
+    current_ts: u64,
+) -> Result<(), PercolatorError> {
+    msg!("LpDeposit: Starting");
+
+    if amount == 0 {
+        msg!("Error: Cannot deposit zero");
+        return Err(PercolatorError::InvalidAmount);
+    }
+
+    if portfolio.equity < amount as i128 {
+        msg!("Error: Insufficient equity for LP deposit");
+        return Err(PercolatorError::InsufficientBalance);
+    }
+
+    let share_price = lp_share_price(pool.total_assets, pool.total_shares)?;
+    let shares = lp_shares_for_deposit(amount, share_price)?;
+
+    // Dust deposits that round to zero shares would be a pure loss
+    if shares == 0 {
+        msg!("Error: Deposit too small to mint a share");
+        return Err(PercolatorError::InvalidAmount);
+    }
+
+    let total_assets = pool
+        .total_assets
+        .checked_add(amount as u128)
+        .ok_or(PercolatorError::Overflow)?;
+    let total_shares = pool
+        .total_shares
+        .checked_add(shares)
+        .ok_or(PercolatorError::Overflow)?;
+
+    msg!("LpDeposit: Shares calculated");
+
+    let venue_id = VenueId::new_amm(pool.market);
+    match portfolio.find_lp_bucket_mut(&venue_id) {
+        Some(bucket) => {
+            let amm = bucket.amm.as_mut().ok_or(PercolatorError::InvalidAccount)?;
+            amm.lp_shares = amm.lp_shares.checked_add(shares).ok_or(PercolatorError::Overflow)?;
+            amm.share_price_cached = share_price;
+            amm.last_update_ts = current_ts;
+            msg!("LpDeposit: Added to existing bucket");
+        }
+        None => {
+            let bucket = LpBucket::new_amm(venue_id, shares, share_price, current_ts);
+            portfolio.add_lp_bucket(bucket).map_err(|_| {
+                msg!("Error: No free LP bucket");
+                PercolatorError::PoolFull
+            })?;
+            msg!("LpDeposit: Created bucket");
+        }
+    }
+
+    pool.total_assets = total_assets;
+    pool.total_shares = total_shares;
+    portfolio.equity = portfolio.equity.saturating_sub(amount as i128);
+
+    msg!("LpDeposit: Complete");
+
+    Ok(())
+}
+
+/// Create a market's LpPool PDA on its first deposit
+///
+/// Funds the rent-exempt minimum from `payer` (topping up whatever the
+/// address already holds), then allocates and assigns it to the router.
+/// Lamports sent to the address beforehand stay out of the pool's assets.
+pub(crate) fn create_lp_pool_pda(
+    pool_account: &AccountInfo,
+    market: &Pubkey,
+    payer: &AccountInfo,
+    system_program: &AccountInfo,
+    program_id: &Pubkey,
+    bump: u8,
+) -> Result<(), PercolatorError> {
+    use pinocchio::instruction::{AccountMeta, Instruction, Seed, Signer};
+    use pinocchio::program::{invoke, invoke_signed};
+    use pinocchio::sysvars::{rent::Rent, Sysvar};
+
+    let rent = Rent::get().map_err(|_| PercolatorError::InvalidAccount)?;
+    let shortfall = rent.minimum_balance(LpPool::LEN).saturating_sub(pool_account.lamports());
+
+    let bump_bytes = [bump];
+    let seeds = [
+        Seed::from(crate::pda::LP_POOL_SEED),
+        Seed::from(market.as_ref()),
+        Seed::from(&bump_bytes[..]),
+    ];
+
+    // Step 1: Transfer rent from payer to PDA
+    if shortfall > 0 {
+        let mut transfer_data = [0u8; 12];
+        transfer_data[0..4].copy_from_slice(&2u32.to_le_bytes());
+        transfer_data[4..12].copy_from_slice(&shortfall.to_le_bytes());
+
+        let transfer_ix = Instruction {
+            program_id: system_program.key(),
+            accounts: &[
+                AccountMeta::writable_signer(payer.key()),
+                AccountMeta::writable(pool_account.key()),
+            ],
+            data: &transfer_data,
+        };
+        invoke(&transfer_ix, &[payer, pool_account])
+            .map_err(|_| PercolatorError::InvalidAccount)?;
+    }
+
+    // Step 2: Allocate space (signed by PDA)
+    let mut allocate_data = [0u8; 12];
+    allocate_data[0..4].copy_from_slice(&8u32.to_le_bytes());
+    allocate_data[4..12].copy_from_slice(&(LpPool::LEN as u64).to_le_bytes());
+
+    let allocate_ix = Instruction {
+        program_id: system_program.key(),
+        accounts: &[AccountMeta::writable_signer(pool_account.key())],
+        data: &allocate_data,
+    };
+    let signer = Signer::from(&seeds);
+    invoke_signed(&allocate_ix, &[pool_account], &[signer])
+        .map_err(|_| PercolatorError::InvalidAccount)?;
+
+    // Step 3: Assign owner to router program (signed by PDA)
+    let mut assign_data = [0u8; 36];
+    assign_data[0..4].copy_from_slice(&1u32.to_le_bytes());
+    assign_data[4..36].copy_from_slice(program_id.as_ref());
+
+    let assign_ix = Instruction {
+        program_id: system_program.key(),
+        accounts: &[AccountMeta::writable_signer(pool_account.key())],
+        data: &assign_data,
+    };
+    let signer = Signer::from(&seeds);
+    invoke_signed(&assign_ix, &[pool_account], &[signer])
+        .map_err(|_| PercolatorError::InvalidAccount)?;
+
+    let mut data = pool_account
+        .try_borrow_mut_data()
+        .map_err(|_| PercolatorError::InvalidAccount)?;
+    let pool = unsafe { &mut *(data.as_mut_ptr() as *mut LpPool) };
+    *pool = LpPool::new(*program_id, *market, bump);
+
+    msg!("LpDeposit: Created LP pool");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instructions::process_burn_lp_shares;
+
+    fn amm_shares(portfolio: &Portfolio, market: Pubkey) -> u64 {
+        let bucket = portfolio.find_lp_bucket(&VenueId::new_amm(market)).unwrap();
+        bucket.amm.as_ref().unwrap().lp_shares
+    }
+
+    fn funded_portfolio(user: u8, equity: i128) -> Portfolio {
+        let mut portfolio = Portfolio::new(Pubkey::default(), Pubkey::from([user; 32]), 0);
+        portfolio.update_equity(equity);
+        portfolio
+    }
+
+    #[test]
+    fn test_first_deposit_is_one_to_one() {
+        let mut portfolio = funded_portfolio(1, 100_000);
+        let market = Pubkey::from([1; 32]);
+        let mut pool = LpPool::new(Pubkey::default(), market, 255);
+
+        process_lp_deposit(&mut portfolio, &mut pool, 40_000, 100).unwrap();
+
+        assert_eq!(portfolio.lp_bucket_count, 1);
+        assert_eq!(amm_shares(&portfolio, market), 40_000);
+        assert_eq!(portfolio.equity, 60_000);
+        assert_eq!((pool.total_assets, pool.total_shares), (40_000, 40_000));
+
+        let bucket = portfolio.find_lp_bucket(&VenueId::new_amm(market)).unwrap();
+        assert_eq!(bucket.amm.as_ref().unwrap().share_price_cached, INITIAL_LP_SHARE_PRICE);
+    }
+
+    #[test]
+    fn test_second_deposit_after_pool_gains_value() {
+        let mut portfolio = funded_portfolio(1, 100_000);
+        let market = Pubkey::from([1; 32]);
+        let mut pool = LpPool::new(Pubkey::default(), market, 255);
+
+        process_lp_deposit(&mut portfolio, &mut pool, 1_000, 100).unwrap();
+
+        // Pool grew from 1_000 to 1_200 assets on 1_000 shares: price 1.2
+        pool.total_assets = 1_200;
+        process_lp_deposit(&mut portfolio, &mut pool, 600, 110).unwrap();
+
+        // 600 / 1.2 = 500 new shares
+        assert_eq!(amm_shares(&portfolio, market), 1_500);
+        assert_eq!(portfolio.equity, 100_000 - 1_600);
+        assert_eq!((pool.total_assets, pool.total_shares), (1_800, 1_500));
+
+        // Burning the new shares returns the deposit
+        assert_eq!(process_burn_lp_shares(&mut portfolio, &mut pool, 500, 110), Ok(600));
+        assert_eq!(amm_shares(&portfolio, market), 1_000);
+        assert_eq!(portfolio.equity, 100_000 - 1_000);
+    }
+
+    #[test]
+    fn test_deposit_then_burn_extracts_nothing() {
+        let market = Pubkey::from([1; 32]);
+        let mut pool = LpPool::new(Pubkey::default(), market, 255);
+
+        // An existing LP holds 3_000 shares of a pool worth 10_000
+        let mut existing = funded_portfolio(1, 3_000);
+        process_lp_deposit(&mut existing, &mut pool, 3_000, 100).unwrap();
+        pool.total_assets = 10_000;
+
+        // A newcomer deposits at the pool's price and burns straight away:
+        // 1_000 buys 300 shares (price 3.33, shares round down)
+        let mut newcomer = funded_portfolio(2, 1_000);
+        process_lp_deposit(&mut newcomer, &mut pool, 1_000, 100).unwrap();
+        assert_eq!(amm_shares(&newcomer, market), 300);
+        let shares = amm_shares(&newcomer, market);
+        let paid_out = process_burn_lp_shares(&mut newcomer, &mut pool, shares, 100).unwrap();
+
+        assert!(paid_out <= 1_000);
+        assert_eq!(newcomer.equity, paid_out as i128);
+
+        // Any rounding stays in the pool, so the existing LP lost nothing
+        assert_eq!((pool.total_assets, pool.total_shares), (11_000 - paid_out as u128, 3_000));
+        assert!(pool.redemption_value(3_000) >= 10_000);
+
+        // Deposits and burns only ever move value between equity and the pool
+        assert_eq!(newcomer.equity + existing.equity + pool.total_assets as i128, 11_000);
+    }
+
+    #[test]
+    fn test_rounding_favors_pool() {
+        // Price 3.0: 10 / 3 rounds down to 3 shares
+        let price = lp_share_price(3_000, 1_000).unwrap();
+        assert_eq!(price, 3_000_000);
+        assert_eq!(lp_shares_for_deposit(10, price).unwrap(), 3);
+
+        // Redeeming those shares returns at most the deposit (9 of 10)
+        let redemption = 3u128 * price as u128 / 1_000_000;
+        assert!(redemption <= 10);
+
+        // Deposits worth less than a share are rejected, not swallowed
+        let mut portfolio = funded_portfolio(1, 100);
+        let mut pool = LpPool::new(Pubkey::default(), Pubkey::from([1; 32]), 255);
+        pool.total_assets = 3_000;
+        pool.total_shares = 1_000;
+        assert_eq!(
+            process_lp_deposit(&mut portfolio, &mut pool, 2, 0),
+            Err(PercolatorError::InvalidAmount)
+        );
+        assert_eq!(portfolio.equity, 100);
+        assert_eq!((pool.total_assets, pool.total_shares), (3_000, 1_000));
+    }
+
+    #[test]
+    fn test_reject_deposit_above_equity() {
+        let mut portfolio = funded_portfolio(1, 500);
+        let mut pool = LpPool::new(Pubkey::default(), Pubkey::from([1; 32]), 255);
+
+        assert_eq!(
+            process_lp_deposit(&mut portfolio, &mut pool, 501, 0),
+            Err(PercolatorError::InsufficientBalance)
+        );
+        assert_eq!(portfolio.lp_bucket_count, 0);
+        assert_eq!(pool.total_assets, 0);
+    }
+}
@@ -8,6 +8,29 @@ pub mod execute_cross_slab;
 pub mod liquidate_user;
 pub mod burn_lp_shares;
 pub mod cancel_lp_orders;
+pub mod update_twap;
+pub mod close_all;
+pub mod lp_deposit;
+pub mod early_vest;
+pub mod set_pause;
+pub mod simulate_execute;
+pub mod position_report;
+pub mod sweep_positions;
+pub mod quote_depth;
+pub mod set_liquidators;
+pub mod set_deposit_caps;
+pub mod set_insurance_params;
+pub mod set_trade_cooldown;
+pub mod portfolio_summary;
+pub mod initialize_registry_page;
+pub mod deregister_slab;
+pub mod transfer_governance;
+pub mod protocol_stats;
+pub mod touch;
+pub mod set_delegate;
+pub mod close_portfolio;
+#[cfg(feature = "localnet")]
+pub mod set_custom_price;
 
 pub use initialize::*;
 pub use initialize_portfolio::*;
@@ -17,6 +40,39 @@ pub use execute_cross_slab::*;
 pub use liquidate_user::*;
 pub use burn_lp_shares::*;
 pub use cancel_lp_orders::*;
+pub use update_twap::*;
+pub use close_all::*;
+pub use lp_deposit::*;
+pub use early_vest::*;
+pub use set_pause::*;
+pub use simulate_execute::*;
+pub use position_report::*;
+pub use sweep_positions::*;
+pub use quote_depth::*;
+pub use set_liquidators::*;
+pub use set_deposit_caps::*;
+pub use set_insurance_params::*;
+pub use set_trade_cooldown::*;
+pub use portfolio_summary::*;
+pub use initialize_registry_page::*;
+pub use deregister_slab::*;
+pub use transfer_governance::*;
+pub use protocol_stats::*;
+pub use touch::*;
+pub use set_delegate::*;
+pub use close_portfolio::*;
+#[cfg(feature = "localnet")]
+pub use set_custom_price::*;
+
+/// Instruction data layout version written by current clients
+///
+/// Every instruction carries it in the byte after the discriminator, so a
+/// layout change can bump it and keep parsing older versions it still
+/// supports instead of silently mis-reading them.
+pub const INSTRUCTION_VERSION: u8 = 1;
+
+/// Oldest instruction data layout version the router still accepts
+pub const MIN_INSTRUCTION_VERSION: u8 = 1;
 
 /// Instruction discriminator (v0 minimal)
 #[repr(u8)]
@@ -38,6 +94,53 @@ pub enum RouterInstruction {
     BurnLpShares = 6,
     /// Cancel Slab LP orders (ONLY way to reduce Slab LP exposure)
     CancelLpOrders = 7,
+    /// Sample spot oracle into a TWAP buffer (permissionless crank)
+    UpdateTwap = 8,
+    /// Vest locked PnL immediately for a penalty paid to insurance
+    EarlyVest = 9,
+    /// Set emergency pause flags (governance only)
+    SetPause = 10,
+    /// Market-close every open position in one transaction
+    CloseAll = 11,
+    /// Deposit into an AMM LP bucket (mints LP shares at bucket NAV)
+    LpDeposit = 12,
+    /// Log a position's realized / unrealized PnL breakdown (read-only)
+    PositionReport = 13,
+    /// Close empty PositionDetails PDAs, refunding rent to the owner
+    SweepPositions = 14,
+    /// Advance a portfolio's vesting and haircut catchup (permissionless crank)
+    Touch = 15,
+    /// Set the liquidation mode and liquidator whitelist (governance only)
+    SetLiquidators = 16,
+    /// Queue a large withdrawal for release after a delay
+    RequestWithdraw = 17,
+    /// Set per-portfolio and global deposit caps (governance only)
+    SetDepositCaps = 18,
+    /// Set insurance accrual rate and reserve floor (governance only)
+    SetInsuranceParams = 19,
+    /// Set the minimum slots between a portfolio's trades (governance only)
+    SetTradeCooldown = 20,
+    /// Log a versioned summary of a portfolio's balances and exposures (read-only)
+    PortfolioSummary = 21,
+    /// Create the next registry page for slabs beyond MAX_SLABS (governance only)
+    InitializeRegistryPage = 22,
+    /// Delist a slab with no open interest (governance only)
+    DeregisterSlab = 23,
+    /// Write a router-owned custom test oracle (localnet builds only)
+    #[cfg(feature = "localnet")]
+    SetCustomPrice = 24,
+    /// Propose a successor governance key (governance only)
+    ProposeGovernance = 25,
+    /// Take over governance as the proposed successor
+    AcceptGovernance = 26,
+    /// Log aggregate open interest, insurance and haircut figures (read-only)
+    ProtocolStats = 27,
+    /// Log fillable liquidity within a limit across slabs (read-only)
+    QuoteDepth = 28,
+    /// Name a key allowed to trade the portfolio, e.g. a strategy vault (user only)
+    SetDelegate = 29,
+    /// Return every lamport, rent reserve included, and close an idle portfolio (user only)
+    ClosePortfolio = 30,
 }
 
 // Note: Instruction dispatching is handled in entrypoint.rs
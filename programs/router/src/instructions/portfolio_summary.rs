@@ -0,0 +1,261 @@
+//! Portfolio summary - stable, versioned view of a portfolio for clients
+//!
+//! Read-only: logs the portfolio's balances, margin and exposures with
+//! sol_log_data so clients don't depend on Portfolio's repr(C) offsets.
+//! The summary is encoded in borsh's wire format (little-endian fields,
+//! exposures as a u32 count followed by the entries), led by a version
+//! byte that changes whenever the layout does.
+
+use crate::state::Portfolio;
+use arrayvec::ArrayVec;
+use percolator_common::*;
+use pinocchio::{log::sol_log_data, msg, pubkey::Pubkey};
+
+/// Version byte leading every serialized summary
+pub const PORTFOLIO_SUMMARY_VERSION: u8 = 1;
+
+/// Most exposures a summary carries
+///
+/// v0 trades one instrument per slab, so a portfolio holds at most one
+/// exposure per registered slab.
+pub const MAX_SUMMARY_EXPOSURES: usize = MAX_SLABS;
+
+/// Portfolio state as reported to off-chain clients
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortfolioSummary {
+    /// Layout version (PORTFOLIO_SUMMARY_VERSION)
+    pub version: u8,
+    /// Portfolio owner
+    pub user: Pubkey,
+    /// Total equity across all slabs
+    pub equity: i128,
+    /// Deposits minus withdrawals
+    pub principal: i128,
+    /// Current PnL (before vesting)
+    pub pnl: i128,
+    /// Vested PnL
+    pub vested_pnl: i128,
+    /// Initial margin requirement
+    pub im: u128,
+    /// Maintenance margin requirement
+    pub mm: u128,
+    /// Equity minus IM
+    pub free_collateral: i128,
+    /// Equity minus MM
+    pub health: i128,
+    /// Lamports queued by RequestWithdraw
+    pub pending_withdrawal: u64,
+    /// Slot of the last user-initiated trade
+    pub last_trade_slot: u64,
+    /// Open positions as (slab_idx, instrument_idx, qty)
+    pub exposures: ArrayVec<(u16, u16, i64), MAX_SUMMARY_EXPOSURES>,
+}
+
+impl PortfolioSummary {
+    /// Serialized size without exposures (including their u32 count)
+    pub const FIXED_LEN: usize = 1 + 32 + 16 * 8 + 8 * 2 + 4;
+
+    /// Serialized size of one exposure
+    pub const EXPOSURE_LEN: usize = 2 + 2 + 8;
+
+    /// Largest serialized summary
+    pub const MAX_LEN: usize = Self::FIXED_LEN + MAX_SUMMARY_EXPOSURES * Self::EXPOSURE_LEN;
+
+    /// Serialized size of this summary
+    pub fn serialized_len(&self) -> usize {
+        Self::FIXED_LEN + self.exposures.len() * Self::EXPOSURE_LEN
+    }
+
+    /// Encode into a buffer; the summary occupies the first `serialized_len()` bytes
+    pub fn to_bytes(&self) -> [u8; Self::MAX_LEN] {
+        let mut out = [0u8; Self::MAX_LEN];
+        out[0] = self.version;
+        out[1..33].copy_from_slice(&self.user);
+
+        let mut offset = 33;
+        for value in [self.equity, self.principal, self.pnl, self.vested_pnl] {
+            out[offset..offset + 16].copy_from_slice(&value.to_le_bytes());
+            offset += 16;
+        }
+        for value in [self.im, self.mm] {
+            out[offset..offset + 16].copy_from_slice(&value.to_le_bytes());
+            offset += 16;
+        }
+        for value in [self.free_collateral, self.health] {
+            out[offset..offset + 16].copy_from_slice(&value.to_le_bytes());
+            offset += 16;
+        }
+        for value in [self.pending_withdrawal, self.last_trade_slot] {
+            out[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+            offset += 8;
+        }
+
+        out[offset..offset + 4].copy_from_slice(&(self.exposures.len() as u32).to_le_bytes());
+        offset += 4;
+        for &(slab_idx, instrument_idx, qty) in &self.exposures {
+            out[offset..offset + 2].copy_from_slice(&slab_idx.to_le_bytes());
+            out[offset + 2..offset + 4].copy_from_slice(&instrument_idx.to_le_bytes());
+            out[offset + 4..offset + 12].copy_from_slice(&qty.to_le_bytes());
+            offset += Self::EXPOSURE_LEN;
+        }
+        out
+    }
+
+    /// Decode a summary produced by `to_bytes`
+    ///
+    /// Errors with InvalidInstruction on truncated data, an unknown version,
+    /// or more exposures than a summary can hold.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, PercolatorError> {
+        let mut reader = InstructionReader::new(data);
+
+        let version = reader.read_u8()?;
+        if version != PORTFOLIO_SUMMARY_VERSION {
+            return Err(PercolatorError::InvalidInstruction);
+        }
+
+        let mut summary = Self {
+            version,
+            user: reader.read_bytes::<32>()?,
+            equity: reader.read_u128()? as i128,
+            principal: reader.read_u128()? as i128,
+            pnl: reader.read_u128()? as i128,
+            vested_pnl: reader.read_u128()? as i128,
+            im: reader.read_u128()?,
+            mm: reader.read_u128()?,
+            free_collateral: reader.read_u128()? as i128,
+            health: reader.read_u128()? as i128,
+            pending_withdrawal: reader.read_u64()?,
+            last_trade_slot: reader.read_u64()?,
+            exposures: ArrayVec::new(),
+        };
+
+        let count = reader.read_u32()? as usize;
+        if count > MAX_SUMMARY_EXPOSURES {
+            return Err(PercolatorError::InvalidInstruction);
+        }
+        for _ in 0..count {
+            let slab_idx = reader.read_u16()?;
+            let instrument_idx = reader.read_u16()?;
+            let qty = reader.read_i64()?;
+            summary.exposures.push((slab_idx, instrument_idx, qty));
+        }
+        Ok(summary)
+    }
+}
+
+impl Portfolio {
+    /// Snapshot this portfolio as a client-facing summary
+    ///
+    /// Errors with InvalidPortfolio if it holds more exposures than a
+    /// summary can carry.
+    pub fn to_summary(&self) -> Result<PortfolioSummary, PercolatorError> {
+        let mut exposures = ArrayVec::new();
+        for &exposure in &self.exposures[..self.exposure_count as usize] {
+            exposures.try_push(exposure).map_err(|_| PercolatorError::InvalidPortfolio)?;
+        }
+
+        Ok(PortfolioSummary {
+            version: PORTFOLIO_SUMMARY_VERSION,
+            user: self.user,
+            equity: self.equity,
+            principal: self.principal,
+            pnl: self.pnl,
+            vested_pnl: self.vested_pnl,
+            im: self.im,
+            mm: self.mm,
+            free_collateral: self.free_collateral,
+            health: self.health,
+            pending_withdrawal: self.pending_withdrawal,
+            last_trade_slot: self.last_trade_slot,
+            exposures,
+        })
+    }
+}
+
+/// Process portfolio summary instruction
+///
+/// # Arguments
+/// * `portfolio` - The portfolio to summarize
+///
+/// # Returns
+/// * The summary, also logged with sol_log_data; no account is modified
+pub fn process_portfolio_summary(portfolio: &Portfolio) -> Result<PortfolioSummary, PercolatorError> {
+    let summary = portfolio.to_summary().map_err(|e| {
+        msg!("Error: Too many exposures to summarize");
+        e
+    })?;
+
+    sol_log_data(&[&summary.to_bytes()[..summary.serialized_len()]]);
+    msg!("PortfolioSummary: Summary logged");
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_round_trip() {
+        let mut portfolio = Portfolio::new(Pubkey::default(), [7; 32], 0);
+        portfolio.equity = 12_500_000;
+        portfolio.principal = 10_000_000;
+        portfolio.pnl = 2_500_000;
+        portfolio.vested_pnl = -300_000;
+        portfolio.im = 4_000_000;
+        portfolio.mm = 2_000_000;
+        portfolio.free_collateral = 8_500_000;
+        portfolio.health = 10_500_000;
+        portfolio.pending_withdrawal = 1_000;
+        portfolio.last_trade_slot = 42;
+        portfolio.update_exposure(0, 0, 3_000_000);
+        portfolio.update_exposure(5, 0, -1_500_000);
+
+        let summary = portfolio.to_summary().unwrap();
+        let bytes = summary.to_bytes();
+        assert_eq!(summary.serialized_len(), PortfolioSummary::FIXED_LEN + 2 * PortfolioSummary::EXPOSURE_LEN);
+
+        let decoded = PortfolioSummary::from_bytes(&bytes[..summary.serialized_len()]).unwrap();
+        assert_eq!(decoded, summary);
+
+        assert_eq!(decoded.version, PORTFOLIO_SUMMARY_VERSION);
+        assert_eq!(decoded.user, portfolio.user);
+        assert_eq!(decoded.equity, portfolio.equity);
+        assert_eq!(decoded.principal, portfolio.principal);
+        assert_eq!(decoded.pnl, portfolio.pnl);
+        assert_eq!(decoded.vested_pnl, portfolio.vested_pnl);
+        assert_eq!((decoded.im, decoded.mm), (portfolio.im, portfolio.mm));
+        assert_eq!(decoded.free_collateral, portfolio.free_collateral);
+        assert_eq!(decoded.health, portfolio.health);
+        assert_eq!(decoded.pending_withdrawal, 1_000);
+        assert_eq!(decoded.last_trade_slot, 42);
+        assert_eq!(decoded.exposures.as_slice(), &[(0, 0, 3_000_000), (5, 0, -1_500_000)]);
+    }
+
+    #[test]
+    fn test_summary_rejects_bad_bytes() {
+        let summary = Portfolio::new(Pubkey::default(), [7; 32], 0).to_summary().unwrap();
+        assert!(summary.exposures.is_empty());
+        let bytes = summary.to_bytes();
+
+        // Truncated
+        assert!(PortfolioSummary::from_bytes(&bytes[..summary.serialized_len() - 1]).is_err());
+
+        // Unknown layout version
+        let mut future = bytes;
+        future[0] = PORTFOLIO_SUMMARY_VERSION + 1;
+        assert_eq!(
+            PortfolioSummary::from_bytes(&future[..summary.serialized_len()]),
+            Err(PercolatorError::InvalidInstruction)
+        );
+    }
+
+    #[test]
+    fn test_summary_exposure_cap() {
+        let mut portfolio = Portfolio::new(Pubkey::default(), [7; 32], 0);
+        for slab_idx in 0..=MAX_SUMMARY_EXPOSURES as u16 {
+            portfolio.update_exposure(slab_idx, 0, 1);
+        }
+        assert_eq!(portfolio.to_summary(), Err(PercolatorError::InvalidPortfolio));
+    }
+}
@@ -0,0 +1,152 @@
+//! Position report - realized / unrealized PnL breakdown for one position
+//!
+//! Read-only: loads a PositionDetails PDA, prices it at the slab's current
+//! oracle, and logs a packed report with sol_log_data. Unrealized PnL runs
+//! the same reduce_position math a full close would, on a copy, so it
+//! matches what the user would realize at that price (including leverage
+//! and inverse quoting). All PnL fields are in PositionDetails' 1e6 scale.
+
+use crate::instructions::{read_slab_price, verify_slab_oracle};
+use crate::state::{PositionDetails, SlabRegistry};
+use percolator_common::*;
+use pinocchio::{account_info::AccountInfo, log::sol_log_data, msg};
+
+/// Per-position PnL breakdown, as logged by a position report call
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PositionReport {
+    /// Registry index of the slab
+    pub slab_index: u16,
+    /// Instrument index within the slab
+    pub instrument_index: u16,
+    /// Signed position size (1e6 scale)
+    pub total_qty: i64,
+    /// Weighted average entry price (1e6 scale)
+    pub avg_entry_price: i64,
+    /// Oracle price the position was marked at (1e6 scale)
+    pub mark_px: i64,
+    /// PnL already realized by reductions
+    pub realized_pnl: i128,
+    /// PnL a full close at `mark_px` would realize
+    pub unrealized_pnl: i128,
+    /// Fees paid over the position's life
+    pub total_fees: i128,
+    /// Funding paid (always 0: the router doesn't accrue funding yet)
+    pub funding_paid: i128,
+    /// Trades that touched the position
+    pub trade_count: u32,
+}
+
+impl PositionReport {
+    /// Serialized size of the logged report
+    pub const LEN: usize = 2 + 2 + 8 * 3 + 16 * 4 + 4;
+
+    /// Little-endian layout: slab_index, instrument_index (u16), total_qty,
+    /// avg_entry_price, mark_px (i64), realized_pnl, unrealized_pnl,
+    /// total_fees, funding_paid (i128), trade_count (u32)
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut out = [0u8; Self::LEN];
+        out[0..2].copy_from_slice(&self.slab_index.to_le_bytes());
+        out[2..4].copy_from_slice(&self.instrument_index.to_le_bytes());
+        out[4..12].copy_from_slice(&self.total_qty.to_le_bytes());
+        out[12..20].copy_from_slice(&self.avg_entry_price.to_le_bytes());
+        out[20..28].copy_from_slice(&self.mark_px.to_le_bytes());
+        out[28..44].copy_from_slice(&self.realized_pnl.to_le_bytes());
+        out[44..60].copy_from_slice(&self.unrealized_pnl.to_le_bytes());
+        out[60..76].copy_from_slice(&self.total_fees.to_le_bytes());
+        out[76..92].copy_from_slice(&self.funding_paid.to_le_bytes());
+        out[92..96].copy_from_slice(&self.trade_count.to_le_bytes());
+        out
+    }
+}
+
+/// Build the report for `position` marked at `mark_px`
+pub fn build_position_report(position: &PositionDetails, mark_px: i64) -> PositionReport {
+    let unrealized_pnl = position.unrealized_pnl(mark_px);
+
+    PositionReport {
+        slab_index: position.slab_index,
+        instrument_index: position.instrument_index,
+        total_qty: position.total_qty,
+        avg_entry_price: position.avg_entry_price,
+        mark_px,
+        realized_pnl: position.realized_pnl,
+        unrealized_pnl,
+        total_fees: position.total_fees,
+        funding_paid: 0,
+        trade_count: position.trade_count,
+    }
+}
+
+/// Process position report instruction
+///
+/// # Arguments
+/// * `position` - The position to report on
+/// * `registry` - Slab registry (resolves the slab and its bound oracle)
+/// * `oracle_accounts` - Oracle feed(s) for the position's slab
+///
+/// # Returns
+/// * The report, also logged with sol_log_data; no account is modified
+pub fn process_position_report(
+    position: &PositionDetails,
+    registry: &SlabRegistry,
+    oracle_accounts: &[AccountInfo],
+) -> Result<PositionReport, PercolatorError> {
+    if position.slab_index >= registry.slab_count {
+        msg!("Error: Position slab is not registered");
+        return Err(PercolatorError::SlabNotRegistered);
+    }
+    let slab_id = registry.slabs[position.slab_index as usize].slab_id;
+
+    verify_slab_oracle(registry, &slab_id, oracle_accounts)?;
+    let mark_px = read_slab_price(registry, &slab_id, oracle_accounts)?;
+
+    let report = build_position_report(position, mark_px);
+
+    sol_log_data(&[&report.to_bytes()]);
+    msg!("PositionReport: Report logged");
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pinocchio::pubkey::Pubkey;
+
+    const SCALE: i64 = 1_000_000;
+
+    #[test]
+    fn test_report_open_long_at_profit() {
+        // Long 3 @ $100 at 2x, already realized 0.2 SOL on an earlier reduce
+        let mut position = PositionDetails::new(Pubkey::default(), 4, 0, 100 * SCALE, 3 * SCALE, 0, 0, 0, 2);
+        position.realized_pnl = 200_000;
+        position.total_fees = 15_000;
+        position.trade_count = 3;
+        let before = position;
+
+        let report = build_position_report(&position, 125 * SCALE);
+
+        // 3 * ($125 - $100) / $125 = 0.6 SOL, doubled by 2x leverage
+        assert_eq!(report.unrealized_pnl, 1_200_000);
+        assert_eq!(report.realized_pnl, 200_000);
+        assert_eq!(report.total_fees, 15_000);
+        assert_eq!(report.funding_paid, 0);
+        assert_eq!(report.trade_count, 3);
+        assert_eq!((report.slab_index, report.total_qty, report.mark_px), (4, 3 * SCALE, 125 * SCALE));
+
+        // Read-only: the stored position is unchanged
+        assert_eq!(position.realized_pnl, before.realized_pnl);
+        assert_eq!(position.total_qty, before.total_qty);
+        assert_eq!(position.trade_count, before.trade_count);
+
+        let bytes = report.to_bytes();
+        assert_eq!(i128::from_le_bytes(bytes[44..60].try_into().unwrap()), 1_200_000);
+        assert_eq!(u32::from_le_bytes(bytes[92..96].try_into().unwrap()), 3);
+    }
+
+    #[test]
+    fn test_report_flat_position_has_no_unrealized() {
+        let position = PositionDetails::new(Pubkey::default(), 0, 0, 100 * SCALE, 0, 0, 0, 0, 1);
+        assert_eq!(build_position_report(&position, 50 * SCALE).unrealized_pnl, 0);
+    }
+}
@@ -0,0 +1,194 @@
+//! Protocol stats - aggregate registry figures for operators
+//!
+//! Read-only: logs open interest, the insurance fund and the global haircut
+//! with sol_log_data, so dashboards don't decode SlabRegistry's repr(C)
+//! layout. Every figure is already kept on the registry, so building the
+//! stats walks the slab entries once and never touches a portfolio or
+//! position account. Little-endian fields led by a version byte that
+//! changes whenever the layout does.
+
+use crate::state::SlabRegistry;
+use percolator_common::*;
+use pinocchio::{log::sol_log_data, msg};
+
+/// Version byte leading every serialized stats blob
+pub const PROTOCOL_STATS_VERSION: u8 = 1;
+
+/// Protocol-wide figures as reported to off-chain clients
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ProtocolStats {
+    /// Layout version (PROTOCOL_STATS_VERSION)
+    pub version: u8,
+    /// Active slabs in the registry (delisted entries excluded)
+    pub active_slab_count: u16,
+    /// Registry pages holding slabs beyond MAX_SLABS
+    pub page_count: u64,
+    /// Long open interest summed over the registry's slabs (1e6 scale)
+    pub total_long_oi: u128,
+    /// Short open interest summed over the registry's slabs (1e6 scale)
+    pub total_short_oi: u128,
+    /// Insurance fund balance (lamports)
+    pub insurance_balance: u128,
+    /// Bad debt the insurance fund could not cover (lamports)
+    pub uncovered_bad_debt: u128,
+    /// Global PnL haircut index (1e9 = no haircut)
+    pub pnl_index: i128,
+    /// Total haircut applied since inception (1e9 = 100%)
+    pub cumulative_haircut: i128,
+    /// Principal deposited across all portfolios (lamports)
+    pub total_deposits: i128,
+}
+
+impl ProtocolStats {
+    /// Serialized size
+    pub const LEN: usize = 1 + 2 + 8 + 16 * 7;
+
+    /// Encode as little-endian fields in declaration order
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut out = [0u8; Self::LEN];
+        out[0] = self.version;
+        out[1..3].copy_from_slice(&self.active_slab_count.to_le_bytes());
+        out[3..11].copy_from_slice(&self.page_count.to_le_bytes());
+
+        let mut offset = 11;
+        for value in [self.total_long_oi, self.total_short_oi, self.insurance_balance, self.uncovered_bad_debt] {
+            out[offset..offset + 16].copy_from_slice(&value.to_le_bytes());
+            offset += 16;
+        }
+        for value in [self.pnl_index, self.cumulative_haircut, self.total_deposits] {
+            out[offset..offset + 16].copy_from_slice(&value.to_le_bytes());
+            offset += 16;
+        }
+        out
+    }
+
+    /// Decode stats produced by `to_bytes`
+    ///
+    /// Errors with InvalidInstruction on truncated data or an unknown version.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, PercolatorError> {
+        let mut reader = InstructionReader::new(data);
+
+        let version = reader.read_u8()?;
+        if version != PROTOCOL_STATS_VERSION {
+            return Err(PercolatorError::InvalidInstruction);
+        }
+
+        Ok(Self {
+            version,
+            active_slab_count: reader.read_u16()?,
+            page_count: reader.read_u64()?,
+            total_long_oi: reader.read_u128()?,
+            total_short_oi: reader.read_u128()?,
+            insurance_balance: reader.read_u128()?,
+            uncovered_bad_debt: reader.read_u128()?,
+            pnl_index: reader.read_u128()? as i128,
+            cumulative_haircut: reader.read_u128()? as i128,
+            total_deposits: reader.read_u128()? as i128,
+        })
+    }
+}
+
+impl SlabRegistry {
+    /// Snapshot the registry's aggregate figures
+    ///
+    /// Open interest covers the slabs held in the registry itself; slabs on
+    /// registry pages are counted in `page_count` only.
+    pub fn to_stats(&self) -> ProtocolStats {
+        let count = (self.slab_count as usize).min(MAX_SLABS);
+        let mut stats = ProtocolStats {
+            version: PROTOCOL_STATS_VERSION,
+            page_count: self.page_count,
+            insurance_balance: self.insurance_state.vault_balance,
+            uncovered_bad_debt: self.insurance_state.uncovered_bad_debt,
+            pnl_index: self.global_haircut.pnl_index,
+            cumulative_haircut: self.global_haircut.cumulative_haircut,
+            total_deposits: self.total_deposits,
+            ..ProtocolStats::default()
+        };
+
+        for entry in self.slabs[..count].iter().filter(|entry| entry.active) {
+            stats.active_slab_count += 1;
+            stats.total_long_oi += entry.long_oi as u128;
+            stats.total_short_oi += entry.short_oi as u128;
+        }
+        stats
+    }
+}
+
+/// Process protocol stats instruction
+///
+/// # Arguments
+/// * `registry` - The slab registry
+///
+/// # Returns
+/// * The stats, also logged with sol_log_data; no account is modified
+pub fn process_protocol_stats(registry: &SlabRegistry) -> Result<ProtocolStats, PercolatorError> {
+    let stats = registry.to_stats();
+
+    sol_log_data(&[&stats.to_bytes()]);
+    msg!("ProtocolStats: Stats logged");
+
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instructions::{process_deregister_slab, update_open_interest};
+    use pinocchio::pubkey::Pubkey;
+
+    const SCALE: i64 = 1_000_000;
+
+    fn register(registry: &mut SlabRegistry, slab_id: Pubkey) {
+        registry
+            .register_slab(slab_id, [0; 32], Pubkey::default(), 500, 250, 10, 20, 1000, u128::MAX, 10, 0)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_stats_match_registry_after_fills() {
+        let mut registry = SlabRegistry::new(Pubkey::default(), [9; 32], 0);
+        register(&mut registry, [1; 32]);
+        register(&mut registry, [2; 32]);
+
+        // A few fills: one account long 3 and one short 1 on slab 0, a short 2 on slab 1
+        update_open_interest(&mut registry, 0, 0, 3 * SCALE).unwrap();
+        update_open_interest(&mut registry, 0, 0, -SCALE).unwrap();
+        update_open_interest(&mut registry, 1, 0, -2 * SCALE).unwrap();
+        let fee_to_insurance = registry.insurance_state.accrue_from_fill(1_000_000_000, &registry.insurance_params);
+        registry.total_deposits = 50_000_000_000;
+
+        // A later registration joins the count with no interest yet
+        register(&mut registry, [3; 32]);
+
+        let stats = process_protocol_stats(&registry).unwrap();
+        assert_eq!(stats.version, PROTOCOL_STATS_VERSION);
+        assert_eq!(stats.active_slab_count, 3);
+        assert_eq!(stats.total_long_oi, 3 * SCALE as u128);
+        assert_eq!(stats.total_short_oi, 3 * SCALE as u128);
+        assert_eq!(stats.insurance_balance, registry.insurance_state.vault_balance);
+        assert_eq!(stats.insurance_balance, fee_to_insurance);
+        assert_eq!(stats.pnl_index, registry.global_haircut.pnl_index);
+        assert_eq!(stats.total_deposits, 50_000_000_000);
+
+        // The logged blob decodes back to the same figures
+        assert_eq!(ProtocolStats::from_bytes(&stats.to_bytes()), Ok(stats));
+
+        // Delisted slabs drop out of the count
+        process_deregister_slab(&mut registry, &[9; 32], &[3; 32]).unwrap();
+        assert_eq!(registry.to_stats().active_slab_count, 2);
+    }
+
+    #[test]
+    fn test_stats_reject_bad_bytes() {
+        let bytes = SlabRegistry::new(Pubkey::default(), [9; 32], 0).to_stats().to_bytes();
+
+        // Truncated
+        assert!(ProtocolStats::from_bytes(&bytes[..ProtocolStats::LEN - 1]).is_err());
+
+        // Unknown layout version
+        let mut future = bytes;
+        future[0] = PROTOCOL_STATS_VERSION + 1;
+        assert_eq!(ProtocolStats::from_bytes(&future), Err(PercolatorError::InvalidInstruction));
+    }
+}
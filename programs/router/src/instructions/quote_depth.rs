@@ -0,0 +1,191 @@
+//! Quote depth - fillable liquidity across slabs for order routing
+//!
+//! Read-only: reads each slab's QuoteCache and logs, per slab, the quantity
+//! a taker could fill at or better than a limit price, so a client can split
+//! an order across slabs before sending ExecuteCrossSlab. The cache must be
+//! fresh at the slab's live seqno, which is logged alongside, so a client can
+//! tell when its view has gone stale.
+
+use crate::state::SlabRegistry;
+use percolator_common::*;
+use pinocchio::{account_info::AccountInfo, log::sol_log_data, msg};
+
+/// Most slabs one depth query can cover (matches ExecuteCrossSlab)
+pub const MAX_DEPTH_SLABS: usize = 16;
+
+/// Bytes logged per slab: avail_qty (i64) + seqno_snapshot (u32)
+pub const SLAB_DEPTH_LEN: usize = 12;
+
+/// Liquidity one slab offers within the limit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SlabDepth {
+    /// Fillable quantity at or better than the limit (1e6 scale)
+    pub avail_qty: i64,
+    /// Slab seqno the quote depth is valid at
+    pub seqno: u32,
+}
+
+impl SlabDepth {
+    /// Packed log encoding (12 bytes, little-endian)
+    pub fn to_bytes(&self) -> [u8; SLAB_DEPTH_LEN] {
+        let mut out = [0u8; SLAB_DEPTH_LEN];
+        out[0..8].copy_from_slice(&self.avail_qty.to_le_bytes());
+        out[8..12].copy_from_slice(&self.seqno.to_le_bytes());
+        out
+    }
+}
+
+/// Read the QuoteCache out of raw slab account data
+///
+/// The cache sits at the header's `off_quote_cache`; the data is copied out
+/// so the slab account's alignment doesn't matter. A cache written before the
+/// header's live seqno is rejected with StaleQuoteCache rather than routed on.
+pub fn read_quote_cache(slab_data: &[u8]) -> Result<QuoteCache, PercolatorError> {
+    if slab_data.len() < SlabHeader::LEN {
+        msg!("Error: Invalid slab account data");
+        return Err(PercolatorError::InvalidAccount);
+    }
+    let header = unsafe { core::ptr::read_unaligned(slab_data.as_ptr() as *const SlabHeader) };
+    if !header.validate() {
+        msg!("Error: Slab header magic invalid");
+        return Err(PercolatorError::InvalidAccount);
+    }
+
+    let offset = header.off_quote_cache as usize;
+    if slab_data.len() < offset.saturating_add(QuoteCache::LEN) {
+        msg!("Error: Slab account too small for quote cache");
+        return Err(PercolatorError::InvalidAccount);
+    }
+    let cache = unsafe { core::ptr::read_unaligned(slab_data[offset..].as_ptr() as *const QuoteCache) };
+    if !cache.is_fresh(header.seqno) {
+        msg!("Error: Quote cache is older than the slab seqno");
+        return Err(PercolatorError::StaleQuoteCache);
+    }
+    Ok(cache)
+}
+
+/// Process quote depth instruction
+///
+/// # Arguments
+/// * `registry` - Slab registry (only registered slabs are quoted)
+/// * `slab_accounts` - Slabs to query (up to MAX_DEPTH_SLABS)
+/// * `side` - Taker side of the order being routed
+/// * `limit_px` - Worst acceptable price (1e6 scale)
+///
+/// # Returns
+/// * Total fillable quantity across the slabs. Each slab's SlabDepth is
+///   logged with sol_log_data, in account order, followed by the total (i64).
+pub fn process_quote_depth(
+    registry: &SlabRegistry,
+    slab_accounts: &[AccountInfo],
+    side: Side,
+    limit_px: i64,
+) -> Result<i64, PercolatorError> {
+    if slab_accounts.is_empty() || slab_accounts.len() > MAX_DEPTH_SLABS {
+        msg!("Error: QuoteDepth takes 1 to 16 slabs");
+        return Err(PercolatorError::InvalidInstruction);
+    }
+    if limit_px <= 0 {
+        msg!("Error: Limit price must be positive");
+        return Err(PercolatorError::InvalidPrice);
+    }
+
+    let mut log = [0u8; MAX_DEPTH_SLABS * SLAB_DEPTH_LEN + 8];
+    let mut total: i64 = 0;
+    for (i, slab_account) in slab_accounts.iter().enumerate() {
+        if registry.find_slab(slab_account.key()).is_none() {
+            msg!("Error: Slab is not registered");
+            return Err(PercolatorError::SlabNotRegistered);
+        }
+
+        let slab_data = slab_account
+            .try_borrow_data()
+            .map_err(|_| PercolatorError::InvalidAccount)?;
+        let cache = read_quote_cache(&slab_data)?;
+
+        let depth = SlabDepth {
+            avail_qty: cache.available_liquidity(side, limit_px),
+            seqno: cache.seqno_snapshot,
+        };
+        log[i * SLAB_DEPTH_LEN..(i + 1) * SLAB_DEPTH_LEN].copy_from_slice(&depth.to_bytes());
+        total = total.saturating_add(depth.avail_qty);
+    }
+
+    let end = slab_accounts.len() * SLAB_DEPTH_LEN;
+    log[end..end + 8].copy_from_slice(&total.to_le_bytes());
+    sol_log_data(&[&log[..end + 8]]);
+    msg!("QuoteDepth: Depth logged");
+
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pinocchio::pubkey::Pubkey;
+
+    /// Raw slab data: header at `seqno` followed by a quote cache written at 9
+    fn slab_data_at(seqno: u32, asks: &[QuoteLevel]) -> Vec<u8> {
+        let mut header = SlabHeader::new(
+            Pubkey::default(),
+            Pubkey::default(),
+            Pubkey::default(),
+            Pubkey::default(),
+            100_000_000,
+            20,
+            1_000_000,
+            255,
+        );
+        header.seqno = seqno;
+        let mut cache = QuoteCache::new();
+        cache.update(9, &[], asks);
+
+        let mut data = vec![0u8; header.off_quote_cache as usize + QuoteCache::LEN];
+        unsafe {
+            core::ptr::write_unaligned(data.as_mut_ptr() as *mut SlabHeader, header);
+            core::ptr::write_unaligned(data[header.off_quote_cache as usize..].as_mut_ptr() as *mut QuoteCache, cache);
+        }
+        data
+    }
+
+    fn slab_data(asks: &[QuoteLevel]) -> Vec<u8> {
+        slab_data_at(9, asks)
+    }
+
+    #[test]
+    fn test_depth_read_from_slab_data() {
+        let data = slab_data(&[
+            QuoteLevel { px: 101_000_000, avail_qty: 1_000_000 },
+            QuoteLevel { px: 102_000_000, avail_qty: 2_000_000 },
+            QuoteLevel { px: 105_000_000, avail_qty: 4_000_000 },
+        ]);
+        let cache = read_quote_cache(&data).unwrap();
+
+        assert_eq!(cache.seqno_snapshot, 9);
+        assert_eq!(cache.available_liquidity(Side::Buy, 102_000_000), 3_000_000);
+        assert_eq!(cache.available_liquidity(Side::Buy, 110_000_000), 7_000_000);
+
+        let bytes = SlabDepth { avail_qty: 3_000_000, seqno: 9 }.to_bytes();
+        assert_eq!(i64::from_le_bytes(bytes[0..8].try_into().unwrap()), 3_000_000);
+        assert_eq!(u32::from_le_bytes(bytes[8..12].try_into().unwrap()), 9);
+    }
+
+    #[test]
+    fn test_truncated_slab_rejected() {
+        let data = slab_data(&[]);
+        assert_eq!(read_quote_cache(&data[..data.len() - 1]).err(), Some(PercolatorError::InvalidAccount));
+        assert_eq!(read_quote_cache(&[0u8; 8]).err(), Some(PercolatorError::InvalidAccount));
+    }
+
+    #[test]
+    fn test_stale_cache_rejected() {
+        let asks = [QuoteLevel { px: 101_000_000, avail_qty: 1_000_000 }];
+        assert!(read_quote_cache(&slab_data_at(9, &asks)).is_ok());
+
+        // The book moved on (seqno 10) without the cache being rewritten
+        assert_eq!(
+            read_quote_cache(&slab_data_at(10, &asks)).err(),
+            Some(PercolatorError::StaleQuoteCache)
+        );
+    }
+}
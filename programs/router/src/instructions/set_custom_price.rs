@@ -0,0 +1,45 @@
+//! Set custom price - write a localnet test oracle (feature "localnet" only)
+//!
+//! Writes a price and timestamp into a custom-oracle account owned by the
+//! router, in the layout CustomAdapter reads, so integration tests can drive
+//! the fill path deterministically without a live Pyth feed. The first
+//! writer becomes the feed's authority. Never compiled into mainnet builds.
+
+use crate::instructions::check_oracle_price;
+use percolator_common::oracle::{CustomAdapter, OracleError};
+use percolator_common::*;
+use pinocchio::{account_info::AccountInfo, msg, pubkey::Pubkey};
+
+/// Process set custom price instruction
+///
+/// # Arguments
+/// * `oracle_account` - Router-owned custom oracle account (CUSTOM_ORACLE_SIZE bytes)
+/// * `authority` - Signer's pubkey (claims a blank account, must match afterwards)
+/// * `price` - Price to publish (1e6 scale, positive)
+/// * `timestamp` - Publish time (unix seconds)
+pub fn process_set_custom_price(
+    oracle_account: &AccountInfo,
+    authority: &Pubkey,
+    price: i64,
+    timestamp: i64,
+) -> Result<(), PercolatorError> {
+    check_oracle_price(price)?;
+
+    let mut data = oracle_account
+        .try_borrow_mut_data()
+        .map_err(|_| PercolatorError::InvalidAccount)?;
+
+    CustomAdapter::write_price_data(&mut data, authority, price, timestamp).map_err(|e| match e {
+        OracleError::InvalidAccount => {
+            msg!("Error: Signer is not the oracle's authority");
+            PercolatorError::Unauthorized
+        }
+        _ => {
+            msg!("Error: Account is not a custom oracle");
+            PercolatorError::InvalidOracle
+        }
+    })?;
+
+    msg!("SetCustomPrice: Price written");
+    Ok(())
+}
@@ -0,0 +1,69 @@
+//! Set delegate - let another key trade a portfolio
+//!
+//! A user can name one delegate (typically a strategy program's PDA) that
+//! ExecuteCrossSlab and CloseAll accept as signer in place of the user, so
+//! vaults built on top can trade their users' portfolios through CPI.
+//! Deposits and withdrawals stay with the user alone.
+
+use crate::state::Portfolio;
+use percolator_common::*;
+use pinocchio::{msg, pubkey::Pubkey};
+
+/// Process set delegate instruction
+///
+/// # Arguments
+/// * `portfolio` - Portfolio to delegate (mutable)
+/// * `user` - Signer; must be the portfolio's user (a delegate can't re-delegate)
+/// * `delegate` - New delegate, or the default pubkey to revoke
+pub fn process_set_delegate(
+    portfolio: &mut Portfolio,
+    user: &Pubkey,
+    delegate: Pubkey,
+) -> Result<(), PercolatorError> {
+    if &portfolio.user != user {
+        msg!("Error: Only the portfolio's user can set its delegate");
+        return Err(PercolatorError::Unauthorized);
+    }
+
+    portfolio.delegate = delegate;
+
+    if delegate == Pubkey::default() {
+        msg!("SetDelegate: Delegate revoked");
+    } else {
+        msg!("SetDelegate: Delegate set");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USER: Pubkey = [1; 32];
+    const VAULT: Pubkey = [2; 32];
+
+    #[test]
+    fn test_set_and_revoke_delegate() {
+        let mut portfolio = Portfolio::new(Pubkey::default(), USER, 0);
+        assert!(!portfolio.is_trading_authority(&VAULT));
+
+        process_set_delegate(&mut portfolio, &USER, VAULT).unwrap();
+        assert!(portfolio.is_trading_authority(&VAULT));
+        assert!(portfolio.is_trading_authority(&USER));
+
+        process_set_delegate(&mut portfolio, &USER, Pubkey::default()).unwrap();
+        assert!(!portfolio.is_trading_authority(&VAULT));
+        // Revoking never makes the default key an authority
+        assert!(!portfolio.is_trading_authority(&Pubkey::default()));
+    }
+
+    #[test]
+    fn test_only_user_sets_delegate() {
+        let mut portfolio = Portfolio::new(Pubkey::default(), USER, 0);
+        process_set_delegate(&mut portfolio, &USER, VAULT).unwrap();
+
+        // Not even the delegate can hand the portfolio on
+        assert_eq!(process_set_delegate(&mut portfolio, &VAULT, [3; 32]), Err(PercolatorError::Unauthorized));
+        assert_eq!(portfolio.delegate, VAULT);
+    }
+}
@@ -0,0 +1,57 @@
+//! Set deposit caps - governance limits on collateral during a guarded launch
+//!
+//! Deposit checks both caps before moving funds (see `check_deposit_caps`):
+//! one on each portfolio's principal and one on the registry's total
+//! deposits, which Withdraw decrements. Zero lifts a cap.
+
+use crate::state::SlabRegistry;
+use percolator_common::*;
+use pinocchio::{msg, pubkey::Pubkey};
+
+/// Process set deposit caps instruction
+///
+/// Caps only gate new deposits; portfolios already above a lowered cap
+/// keep their balance and can still withdraw.
+///
+/// # Arguments
+/// * `registry` - Slab registry (mutable)
+/// * `authority` - Signer's pubkey (must be registry governance)
+/// * `max_portfolio_deposit` - Per-portfolio principal cap (lamports, 0 = uncapped)
+/// * `max_total_deposits` - Global TVL cap (lamports, 0 = uncapped)
+pub fn process_set_deposit_caps(
+    registry: &mut SlabRegistry,
+    authority: &Pubkey,
+    max_portfolio_deposit: u64,
+    max_total_deposits: u64,
+) -> Result<(), PercolatorError> {
+    if authority != &registry.governance {
+        msg!("Error: Only governance can set deposit caps");
+        return Err(PercolatorError::Unauthorized);
+    }
+
+    registry.max_portfolio_deposit = max_portfolio_deposit;
+    registry.max_total_deposits = max_total_deposits;
+
+    msg!("SetDepositCaps: Deposit caps updated");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_governance_sets_deposit_caps() {
+        let mut registry = SlabRegistry::new(Pubkey::default(), [9; 32], 0);
+
+        assert_eq!(
+            process_set_deposit_caps(&mut registry, &[1; 32], 1, 1),
+            Err(PercolatorError::Unauthorized)
+        );
+        assert_eq!(registry.max_portfolio_deposit, 0);
+
+        process_set_deposit_caps(&mut registry, &[9; 32], 50_000_000_000, 1_000_000_000_000).unwrap();
+        assert_eq!(registry.max_portfolio_deposit, 50_000_000_000);
+        assert_eq!(registry.max_total_deposits, 1_000_000_000_000);
+    }
+}
@@ -0,0 +1,79 @@
+//! Set insurance params - governance control over the insurance fund rate
+//!
+//! Updates the share of fill notional booked to the insurance fund and the
+//! reserve floor that surplus withdrawals must leave behind. The payout caps
+//! are unchanged. See `state::insurance` for how the accrual relates to the
+//! slab's taker fee.
+
+use crate::state::{SlabRegistry, MAX_INSURANCE_FEE_BPS};
+use percolator_common::*;
+use pinocchio::{msg, pubkey::Pubkey};
+
+/// Process set insurance params instruction
+///
+/// # Arguments
+/// * `registry` - Slab registry (mutable)
+/// * `authority` - Signer's pubkey (must be registry governance)
+/// * `fee_bps_to_insurance` - Accrual rate in bps of notional (up to MAX_INSURANCE_FEE_BPS)
+/// * `reserve_floor` - Balance surplus withdrawals must leave in the fund
+pub fn process_set_insurance_params(
+    registry: &mut SlabRegistry,
+    authority: &Pubkey,
+    fee_bps_to_insurance: u16,
+    reserve_floor: u64,
+) -> Result<(), PercolatorError> {
+    if authority != &registry.governance {
+        msg!("Error: Only governance can set insurance params");
+        return Err(PercolatorError::Unauthorized);
+    }
+
+    if fee_bps_to_insurance > MAX_INSURANCE_FEE_BPS {
+        msg!("Error: Insurance fee rate above maximum");
+        return Err(PercolatorError::InvalidRiskParams);
+    }
+
+    registry.insurance_params.fee_bps_to_insurance = fee_bps_to_insurance;
+    registry.insurance_params.reserve_floor = reserve_floor;
+
+    msg!("SetInsuranceParams: Insurance params updated");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GOVERNANCE: Pubkey = [9; 32];
+
+    #[test]
+    fn test_set_insurance_params() {
+        let mut registry = SlabRegistry::new(Pubkey::default(), GOVERNANCE, 0);
+
+        process_set_insurance_params(&mut registry, &GOVERNANCE, 25, 1_000_000_000).unwrap();
+        assert_eq!(registry.insurance_params.fee_bps_to_insurance, 25);
+        assert_eq!(registry.insurance_params.reserve_floor, 1_000_000_000);
+
+        // New fills accrue at the new rate
+        let params = registry.insurance_params;
+        assert_eq!(registry.insurance_state.accrue_from_fill(1_000_000, &params), 2_500);
+
+        // Payout caps are left alone
+        assert_eq!(registry.insurance_params.max_payout_bps_of_oi, 50);
+    }
+
+    #[test]
+    fn test_insurance_params_bounds_and_authority() {
+        let mut registry = SlabRegistry::new(Pubkey::default(), GOVERNANCE, 0);
+
+        assert_eq!(
+            process_set_insurance_params(&mut registry, &[1; 32], 20, 0),
+            Err(PercolatorError::Unauthorized)
+        );
+        assert_eq!(
+            process_set_insurance_params(&mut registry, &GOVERNANCE, MAX_INSURANCE_FEE_BPS + 1, 0),
+            Err(PercolatorError::InvalidRiskParams)
+        );
+        assert_eq!(process_set_insurance_params(&mut registry, &GOVERNANCE, MAX_INSURANCE_FEE_BPS, 0), Ok(()));
+        assert_eq!(registry.insurance_params.fee_bps_to_insurance, MAX_INSURANCE_FEE_BPS);
+    }
+}
@@ -0,0 +1,125 @@
+//! Set liquidators - governance control over who may liquidate
+//!
+//! Open deployments let any keeper call LiquidateUser; permissioned ones
+//! restrict it to a whitelist of up to MAX_LIQUIDATORS signers. LiquidateUser
+//! checks its signer with `require_liquidator` before touching the portfolio.
+
+use crate::state::{LiquidatorAccess, SlabRegistry, MAX_LIQUIDATORS};
+use percolator_common::*;
+use pinocchio::{msg, pubkey::Pubkey};
+
+/// Reject a liquidation signed by someone the registry doesn't admit
+pub fn require_liquidator(registry: &SlabRegistry, signer: &Pubkey) -> Result<(), PercolatorError> {
+    if !registry.is_authorized_liquidator(signer) {
+        msg!("Error: Signer is not a whitelisted liquidator");
+        return Err(PercolatorError::UnauthorizedLiquidator);
+    }
+    Ok(())
+}
+
+/// Process set liquidators instruction
+///
+/// Replaces the liquidation mode and the whole whitelist; an open registry
+/// may keep a list, which applies once it's switched to permissioned.
+///
+/// # Arguments
+/// * `registry` - Slab registry (mutable)
+/// * `authority` - Signer's pubkey (must be registry governance)
+/// * `mode` - LiquidatorAccess as u8 (0 = open, 1 = permissioned)
+/// * `liquidators` - New whitelist (up to MAX_LIQUIDATORS)
+pub fn process_set_liquidators(
+    registry: &mut SlabRegistry,
+    authority: &Pubkey,
+    mode: u8,
+    liquidators: &[Pubkey],
+) -> Result<(), PercolatorError> {
+    if authority != &registry.governance {
+        msg!("Error: Only governance can set liquidators");
+        return Err(PercolatorError::Unauthorized);
+    }
+
+    if LiquidatorAccess::from_u8(mode).is_none() {
+        msg!("Error: Unknown liquidation mode");
+        return Err(PercolatorError::InvalidInstruction);
+    }
+    if liquidators.len() > MAX_LIQUIDATORS {
+        msg!("Error: Too many liquidators");
+        return Err(PercolatorError::InvalidInstruction);
+    }
+
+    registry.liquidation_mode = mode;
+    registry.liquidator_count = liquidators.len() as u8;
+    registry.liquidators = [Pubkey::default(); MAX_LIQUIDATORS];
+    registry.liquidators[..liquidators.len()].copy_from_slice(liquidators);
+
+    msg!("SetLiquidators: Liquidation access updated");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GOVERNANCE: Pubkey = [9; 32];
+    const KEEPER: Pubkey = [1; 32];
+    const STRANGER: Pubkey = [2; 32];
+
+    fn registry() -> SlabRegistry {
+        SlabRegistry::new(Pubkey::default(), GOVERNANCE, 0)
+    }
+
+    #[test]
+    fn test_open_mode_anyone_liquidates() {
+        let mut registry = registry();
+        assert_eq!(require_liquidator(&registry, &STRANGER), Ok(()));
+
+        // A whitelist kept in open mode doesn't restrict anyone
+        process_set_liquidators(&mut registry, &GOVERNANCE, LiquidatorAccess::Open as u8, &[KEEPER]).unwrap();
+        assert_eq!(require_liquidator(&registry, &STRANGER), Ok(()));
+    }
+
+    #[test]
+    fn test_permissioned_mode_rejects_others() {
+        let mut registry = registry();
+        process_set_liquidators(&mut registry, &GOVERNANCE, LiquidatorAccess::Permissioned as u8, &[KEEPER]).unwrap();
+
+        assert_eq!(require_liquidator(&registry, &KEEPER), Ok(()));
+        assert_eq!(
+            require_liquidator(&registry, &STRANGER),
+            Err(PercolatorError::UnauthorizedLiquidator)
+        );
+
+        // Replacing the list drops the old keeper
+        process_set_liquidators(&mut registry, &GOVERNANCE, LiquidatorAccess::Permissioned as u8, &[STRANGER]).unwrap();
+        assert_eq!(
+            require_liquidator(&registry, &KEEPER),
+            Err(PercolatorError::UnauthorizedLiquidator)
+        );
+
+        // An empty permissioned list admits nobody
+        process_set_liquidators(&mut registry, &GOVERNANCE, LiquidatorAccess::Permissioned as u8, &[]).unwrap();
+        assert_eq!(
+            require_liquidator(&registry, &STRANGER),
+            Err(PercolatorError::UnauthorizedLiquidator)
+        );
+    }
+
+    #[test]
+    fn test_only_governance_sets_liquidators() {
+        let mut registry = registry();
+
+        assert_eq!(
+            process_set_liquidators(&mut registry, &KEEPER, LiquidatorAccess::Permissioned as u8, &[KEEPER]),
+            Err(PercolatorError::Unauthorized)
+        );
+        assert_eq!(
+            process_set_liquidators(&mut registry, &GOVERNANCE, 2, &[]),
+            Err(PercolatorError::InvalidInstruction)
+        );
+        assert_eq!(
+            process_set_liquidators(&mut registry, &GOVERNANCE, 1, &[KEEPER; MAX_LIQUIDATORS + 1]),
+            Err(PercolatorError::InvalidInstruction)
+        );
+        assert_eq!(registry.liquidation_mode, LiquidatorAccess::Open as u8);
+    }
+}
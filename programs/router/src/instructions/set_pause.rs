@@ -0,0 +1,122 @@
+//! Set pause - governance emergency halt
+//!
+//! Kill switch for when an exploit is discovered. Governance sets PAUSE_*
+//! bitflags on the registry; each gated instruction checks its flag before
+//! doing anything. Withdrawals and liquidations have no flag, so users can
+//! always exit and unhealthy accounts can always be closed out.
+
+use crate::instructions::RouterInstruction;
+use crate::state::{SlabRegistry, PAUSE_ALL, PAUSE_DEPOSITS, PAUSE_LP, PAUSE_TRADING};
+use percolator_common::*;
+use pinocchio::{msg, pubkey::Pubkey};
+
+impl RouterInstruction {
+    /// Pause flag gating this instruction (0 = never paused)
+    pub fn pause_flag(self) -> u64 {
+        match self {
+            RouterInstruction::ExecuteCrossSlab | RouterInstruction::CloseAll => PAUSE_TRADING,
+            RouterInstruction::Deposit => PAUSE_DEPOSITS,
+            RouterInstruction::BurnLpShares
+            | RouterInstruction::CancelLpOrders
+            | RouterInstruction::LpDeposit => PAUSE_LP,
+            _ => 0,
+        }
+    }
+}
+
+/// Reject `instruction` if governance has paused it
+pub fn require_not_paused(
+    registry: &SlabRegistry,
+    instruction: RouterInstruction,
+) -> Result<(), PercolatorError> {
+    if registry.is_paused(instruction.pause_flag()) {
+        msg!("Error: Instruction is paused by governance");
+        return Err(PercolatorError::ProgramPaused);
+    }
+    Ok(())
+}
+
+/// Process set pause instruction
+///
+/// Replaces the registry's pause flags; pass 0 to resume everything.
+///
+/// # Arguments
+/// * `registry` - Slab registry (mutable)
+/// * `authority` - Signer's pubkey (must be registry governance)
+/// * `pause_flags` - New PAUSE_* bitflags
+pub fn process_set_pause(
+    registry: &mut SlabRegistry,
+    authority: &Pubkey,
+    pause_flags: u64,
+) -> Result<(), PercolatorError> {
+    if authority != &registry.governance {
+        msg!("Error: Only governance can pause");
+        return Err(PercolatorError::Unauthorized);
+    }
+
+    if pause_flags & !PAUSE_ALL != 0 {
+        msg!("Error: Unknown pause flag");
+        return Err(PercolatorError::InvalidInstruction);
+    }
+
+    registry.pause_flags = pause_flags;
+
+    msg!("SetPause: Pause flags updated");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> SlabRegistry {
+        SlabRegistry::new(Pubkey::default(), Pubkey::from([9; 32]), 0)
+    }
+
+    #[test]
+    fn test_pause_trading_allows_withdrawal() {
+        let mut registry = registry();
+        process_set_pause(&mut registry, &Pubkey::from([9; 32]), PAUSE_TRADING).unwrap();
+
+        assert_eq!(
+            require_not_paused(&registry, RouterInstruction::ExecuteCrossSlab),
+            Err(PercolatorError::ProgramPaused)
+        );
+        assert_eq!(
+            require_not_paused(&registry, RouterInstruction::CloseAll),
+            Err(PercolatorError::ProgramPaused)
+        );
+
+        // Other flags stay independent
+        assert_eq!(require_not_paused(&registry, RouterInstruction::Deposit), Ok(()));
+        assert_eq!(require_not_paused(&registry, RouterInstruction::LpDeposit), Ok(()));
+
+        // Exits stay open even with everything paused
+        process_set_pause(&mut registry, &Pubkey::from([9; 32]), PAUSE_ALL).unwrap();
+        assert_eq!(require_not_paused(&registry, RouterInstruction::Withdraw), Ok(()));
+        assert_eq!(require_not_paused(&registry, RouterInstruction::LiquidateUser), Ok(()));
+        assert_eq!(
+            require_not_paused(&registry, RouterInstruction::BurnLpShares),
+            Err(PercolatorError::ProgramPaused)
+        );
+
+        // Unpause
+        process_set_pause(&mut registry, &Pubkey::from([9; 32]), 0).unwrap();
+        assert_eq!(require_not_paused(&registry, RouterInstruction::ExecuteCrossSlab), Ok(()));
+    }
+
+    #[test]
+    fn test_only_governance_can_pause() {
+        let mut registry = registry();
+
+        assert_eq!(
+            process_set_pause(&mut registry, &Pubkey::from([1; 32]), PAUSE_ALL),
+            Err(PercolatorError::Unauthorized)
+        );
+        assert_eq!(
+            process_set_pause(&mut registry, &Pubkey::from([9; 32]), 1 << 7),
+            Err(PercolatorError::InvalidInstruction)
+        );
+        assert_eq!(registry.pause_flags, 0);
+    }
+}
@@ -0,0 +1,51 @@
+//! Set trade cooldown - governance throttle on rapid-fire trading
+//!
+//! Fills execute instantly at the oracle price, so a bot trading around
+//! every oracle update can pick off stale prices. ExecuteCrossSlab and
+//! CloseAll record each trade's slot on the portfolio and reject the next
+//! one until the cooldown has passed; liquidations bypass it.
+
+use crate::state::SlabRegistry;
+use percolator_common::*;
+use pinocchio::{msg, pubkey::Pubkey};
+
+/// Process set trade cooldown instruction
+///
+/// # Arguments
+/// * `registry` - Slab registry (mutable)
+/// * `authority` - Signer's pubkey (must be registry governance)
+/// * `trade_cooldown_slots` - Minimum slots between a portfolio's trades (0 = unthrottled)
+pub fn process_set_trade_cooldown(
+    registry: &mut SlabRegistry,
+    authority: &Pubkey,
+    trade_cooldown_slots: u64,
+) -> Result<(), PercolatorError> {
+    if authority != &registry.governance {
+        msg!("Error: Only governance can set the trade cooldown");
+        return Err(PercolatorError::Unauthorized);
+    }
+
+    registry.trade_cooldown_slots = trade_cooldown_slots;
+
+    msg!("SetTradeCooldown: Trade cooldown updated");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_governance_sets_trade_cooldown() {
+        let mut registry = SlabRegistry::new(Pubkey::default(), [9; 32], 0);
+
+        assert_eq!(
+            process_set_trade_cooldown(&mut registry, &[1; 32], 4),
+            Err(PercolatorError::Unauthorized)
+        );
+        assert_eq!(registry.trade_cooldown_slots, 0);
+
+        process_set_trade_cooldown(&mut registry, &[9; 32], 4).unwrap();
+        assert_eq!(registry.trade_cooldown_slots, 4);
+    }
+}
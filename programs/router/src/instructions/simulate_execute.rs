@@ -0,0 +1,410 @@
+//! Simulate execute - dry run of ExecuteCrossSlab
+//!
+//! Runs the same price checks, fill, PnL and margin math as
+//! process_execute_cross_slab, then logs the projected result with
+//! sol_log_data instead of committing it. There is no CPI, no lamport
+//! transfer and no account write: portfolio and registry are only borrowed
+//! immutably, so neither slab auto-registration nor the vesting touch can
+//! leak into state. Clients call it through simulateTransaction to preview
+//! the margin impact of an order.
+
+use crate::instructions::{
+    calculate_portfolio_margin_from_exposures, check_circuit_breaker, check_oracles_per_slab, check_split, check_trading_authority, check_reduce_only, check_trading_hours, effective_slippage_bps,
+    limit_deviation_bps, load_position_details, margin_required, read_slab_price, validate_leverage,
+    validate_limit_order_price, validate_market_order_price, validate_slab_leverage,
+    verify_slab_oracle, verify_slab_version, SlabHeaderSnapshot, SlabSplit,
+};
+use crate::state::{Portfolio, PositionDetails, SlabRegistry};
+use percolator_common::*;
+use pinocchio::{account_info::AccountInfo, log::sol_log_data, msg, pubkey::Pubkey};
+
+/// One split's inputs to the projection, gathered from accounts
+#[derive(Clone, Copy)]
+pub struct SimulatedFill {
+    /// Registry index of the slab
+    pub slab_idx: u16,
+    /// The requested split (v0 slabs fill atomically, so it fills in full)
+    pub split: SlabSplit,
+    /// Price the slab would fill at (oracle for market, limit_px for limit)
+    pub execution_px: i64,
+    /// Oracle price PnL is settled at
+    pub oracle_px: i64,
+    /// Slab contract multiplier (1e6 scale)
+    pub contract_size: i64,
+    /// Linear or inverse quoting
+    pub contract_kind: ContractKind,
+    /// Oracle / price / quantity / collateral decimals
+    pub scale: ScaleConfig,
+    /// Existing PositionDetails, if the user has one on this slab
+    pub position: Option<PositionDetails>,
+}
+
+impl SimulatedFill {
+    const EMPTY: Self = Self {
+        slab_idx: 0,
+        split: SlabSplit { slab_id: [0; 32], qty: 0, side: 0, limit_px: 0 },
+        execution_px: 0,
+        oracle_px: 0,
+        contract_size: 0,
+        contract_kind: ContractKind::Linear,
+        scale: ScaleConfig::DEFAULT,
+        position: None,
+    };
+}
+
+/// Projected outcome of an order, as logged by a simulate call
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SimulatedExecution {
+    /// Net signed quantity filled across splits (1e6 scale)
+    pub filled_qty: i64,
+    /// Realized PnL across splits (lamports)
+    pub realized_pnl: i128,
+    /// Margin that would move from the user to the DLP (lamports)
+    pub margin_posted: u128,
+    /// Margin that would be returned to the user (lamports)
+    pub margin_released: u128,
+    /// Equity after the order
+    pub projected_equity: i128,
+    /// Initial margin requirement after the order
+    pub projected_im: u128,
+    /// Maintenance margin requirement after the order
+    pub projected_mm: u128,
+    /// Whether the real order would pass the margin check
+    pub sufficient_margin: bool,
+}
+
+impl SimulatedExecution {
+    /// Serialized size of the logged result
+    pub const LEN: usize = 8 + 16 * 6 + 1;
+
+    /// Little-endian layout: filled_qty (i64), realized_pnl (i128),
+    /// margin_posted, margin_released (u128), projected_equity (i128),
+    /// projected_im, projected_mm (u128), sufficient_margin (u8)
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut out = [0u8; Self::LEN];
+        out[0..8].copy_from_slice(&self.filled_qty.to_le_bytes());
+        out[8..24].copy_from_slice(&self.realized_pnl.to_le_bytes());
+        out[24..40].copy_from_slice(&self.margin_posted.to_le_bytes());
+        out[40..56].copy_from_slice(&self.margin_released.to_le_bytes());
+        out[56..72].copy_from_slice(&self.projected_equity.to_le_bytes());
+        out[72..88].copy_from_slice(&self.projected_im.to_le_bytes());
+        out[88..104].copy_from_slice(&self.projected_mm.to_le_bytes());
+        out[104] = self.sufficient_margin as u8;
+        out
+    }
+}
+
+/// Maintenance margin of one position, as calculate_portfolio_margin_from_exposures prices it
+fn position_mm(registry: &SlabRegistry, slab_idx: u16, exposure: i64, position: &PositionDetails) -> u128 {
+    let notional = position.margin_held.saturating_mul(position.leverage.max(1) as u128);
+    registry
+        .slabs
+        .get(slab_idx as usize)
+        .map_or(0, |entry| entry.maintenance_margin(exposure.unsigned_abs(), notional))
+}
+
+/// Project an order's fills against read-only state
+///
+/// Mirrors the add / reduce / reverse cases of process_execute_cross_slab on
+/// copies of each PositionDetails. `current_im` and `current_mm` are the
+/// requirements before the order. Fails where the real order would, e.g.
+/// LeverageMismatch when adding to a position at a different leverage.
+pub fn project_fills(
+    user_portfolio: &Portfolio,
+    registry: &SlabRegistry,
+    fills: &[SimulatedFill],
+    leverage: u8,
+    current_im: u128,
+    current_mm: u128,
+) -> Result<SimulatedExecution, PercolatorError> {
+    let mut result = SimulatedExecution::default();
+    let mut mm = current_mm;
+
+    for fill in fills {
+        let is_buy = fill.split.side == 0;
+        let filled_qty = fill.split.signed_qty();
+        let current_exposure = user_portfolio.get_exposure(fill.slab_idx, 0);
+        let mut position = fill.position.unwrap_or_else(|| {
+            let mut details = PositionDetails::new(Pubkey::default(), fill.slab_idx, 0, fill.execution_px, 0, 0, 0, 0, leverage);
+            details.contract_kind = fill.contract_kind as u8;
+            details
+        });
+
+        mm = mm.saturating_sub(position_mm(registry, fill.slab_idx, current_exposure, &position));
+
+        let same_direction = (is_buy && current_exposure >= 0) || (!is_buy && current_exposure <= 0);
+        let pnl = if same_direction {
+            let margin = margin_required(
+                filled_qty,
+                fill.execution_px,
+                leverage,
+                fill.contract_size,
+                fill.contract_kind,
+                fill.scale,
+            );
+            position.add_to_position(fill.execution_px, filled_qty, 0, 0, margin, leverage)?;
+            result.margin_posted = result.margin_posted.saturating_add(margin);
+            0
+        } else if filled_qty.abs() <= current_exposure.abs() {
+            let (pnl, _, released) = position.reduce_position(fill.oracle_px, filled_qty, 0, 0);
+            result.margin_released = result.margin_released.saturating_add(released);
+            pnl
+        } else {
+            let close_qty = -current_exposure;
+            let (pnl, _, released) = position.reduce_position(fill.oracle_px, close_qty, 0, 0);
+            result.margin_released = result.margin_released.saturating_add(released);
+
+            let remaining = filled_qty + current_exposure;
+            let margin = margin_required(
+                remaining,
+                fill.execution_px,
+                leverage,
+                fill.contract_size,
+                fill.contract_kind,
+                fill.scale,
+            );
+            position = PositionDetails::new(Pubkey::default(), fill.slab_idx, 0, fill.execution_px, remaining, 0, 0, margin, leverage);
+            position.contract_kind = fill.contract_kind as u8;
+            position.refresh_bankruptcy_price();
+            result.margin_posted = result.margin_posted.saturating_add(margin);
+            pnl
+        };
+
+        let new_exposure = current_exposure + filled_qty;
+        mm = mm.saturating_add(position_mm(registry, fill.slab_idx, new_exposure, &position));
+
+        result.filled_qty = result.filled_qty.saturating_add(filled_qty);
+        result.realized_pnl = result.realized_pnl.saturating_add(pnl_lamports(pnl, fill.contract_size, &fill.scale));
+    }
+
+    result.projected_equity = user_portfolio
+        .equity
+        .saturating_sub(result.margin_posted.min(i128::MAX as u128) as i128)
+        .saturating_add(result.margin_released.min(i128::MAX as u128) as i128)
+        .saturating_add(result.realized_pnl);
+    result.projected_im = current_im
+        .saturating_add(result.margin_posted)
+        .saturating_sub(result.margin_released);
+    result.projected_mm = mm;
+    result.sufficient_margin = result.projected_equity >= result.projected_im as i128;
+    Ok(result)
+}
+
+/// Process a simulated ExecuteCrossSlab
+///
+/// Takes the same accounts as the real order (receipts are unused), including
+/// the extra PositionDetails of untraded positions, and runs every check it
+/// would, except that unregistered slabs are rejected rather than
+/// auto-registered. Vesting and haircut catch-up are not applied, so
+/// the projection is against the portfolio as last touched.
+///
+/// # Returns
+/// * The projected result, also logged with sol_log_data
+pub fn process_simulate_execute_cross_slab(
+    user_portfolio_account: &AccountInfo,
+    user_portfolio: &Portfolio,
+    user_account: &AccountInfo,
+    registry: &SlabRegistry,
+    slab_accounts: &[AccountInfo],
+    oracle_accounts: &[AccountInfo],
+    position_details_accounts: &[AccountInfo],
+    extra_position_details: &[AccountInfo],
+    splits: &[SlabSplit],
+    order_type: u8,
+    oracles_per_slab: usize,
+    leverage: u8,
+    max_slippage_bps: u16,
+    reduce_only: bool,
+    program_id: &Pubkey,
+) -> Result<SimulatedExecution, PercolatorError> {
+    check_trading_authority(user_portfolio, user_account)?;
+    check_oracles_per_slab(oracles_per_slab)?;
+
+    if slab_accounts.len() * oracles_per_slab != oracle_accounts.len()
+        || slab_accounts.len() != position_details_accounts.len()
+        || slab_accounts.len() != splits.len()
+        || splits.len() > 16
+    {
+        msg!("Error: Mismatched slab/oracle/position_details/split counts");
+        return Err(PercolatorError::InvalidInstruction);
+    }
+
+    if order_type > 1 {
+        msg!("Error: Invalid order type");
+        return Err(PercolatorError::InvalidOrderType);
+    }
+
+    validate_leverage(leverage)?;
+
+    use pinocchio::sysvars::{clock::Clock, Sysvar};
+    let current_slot = Clock::get()
+        .map(|clock| clock.slot)
+        .unwrap_or(user_portfolio.last_slot);
+    let now_ts = Clock::get().map(|clock| clock.unix_timestamp).unwrap_or(0);
+    let slippage_bps = effective_slippage_bps(max_slippage_bps, registry.max_slippage_bps);
+
+    let mut fills = [SimulatedFill::EMPTY; 16];
+    for (i, split) in splits.iter().enumerate() {
+        check_split(split)?;
+        let slab_account = &slab_accounts[i];
+        let slab_oracles = &oracle_accounts[i * oracles_per_slab..(i + 1) * oracles_per_slab];
+
+        verify_slab_oracle(registry, slab_account.key(), slab_oracles)?;
+        let oracle_px = read_slab_price(registry, slab_account.key(), slab_oracles)?;
+        let execution_px = match order_type {
+            0 => {
+                validate_market_order_price(split.limit_px, oracle_px, split.side, slippage_bps)?;
+                oracle_px
+            }
+            _ => {
+                validate_limit_order_price(split.limit_px, oracle_px, limit_deviation_bps(registry, slab_account.key()))?;
+                split.limit_px
+            }
+        };
+
+        let header = {
+            let slab_data = slab_account
+                .try_borrow_data()
+                .map_err(|_| PercolatorError::InvalidAccount)?;
+            if slab_data.len() < SlabHeader::LEN {
+                msg!("Error: Invalid slab account data");
+                return Err(PercolatorError::InvalidAccount);
+            }
+            SlabHeaderSnapshot::from(unsafe { &*(slab_data.as_ptr() as *const SlabHeader) })
+        };
+
+        // A dry run must not auto-register, so unknown slabs are simply rejected
+        let (slab_idx, _) = registry.find_slab(slab_account.key()).ok_or_else(|| {
+            msg!("Error: Slab is not registered");
+            PercolatorError::SlabNotRegistered
+        })?;
+        verify_slab_version(registry, slab_account.key(), &header.version_hash)?;
+        validate_slab_leverage(registry, slab_idx, leverage)?;
+        check_circuit_breaker(registry, slab_idx, oracle_px, current_slot)?;
+        check_trading_hours(registry, slab_idx, now_ts)?;
+        if reduce_only {
+            check_reduce_only(user_portfolio.get_exposure(slab_idx, 0), split)?;
+        }
+
+        fills[i] = SimulatedFill {
+            slab_idx,
+            split: *split,
+            execution_px,
+            oracle_px,
+            contract_size: header.contract_size,
+            contract_kind: header.contract_kind,
+            scale: header.scale,
+            position: load_position_details(&position_details_accounts[i])?,
+        };
+    }
+
+    let (current_im, current_mm) = calculate_portfolio_margin_from_exposures(
+        user_portfolio,
+        user_portfolio_account,
+        registry,
+        position_details_accounts,
+        extra_position_details,
+        program_id,
+    )?;
+
+    let result = project_fills(
+        user_portfolio,
+        registry,
+        &fills[..splits.len()],
+        leverage,
+        current_im,
+        current_mm,
+    )?;
+
+    sol_log_data(&[&result.to_bytes()]);
+    msg!("SimulateExecute: Projection logged");
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCALE: i64 = 1_000_000;
+
+    fn bytes_of<T>(value: &T) -> Vec<u8> {
+        unsafe { core::slice::from_raw_parts(value as *const T as *const u8, core::mem::size_of::<T>()) }.to_vec()
+    }
+
+    fn setup() -> (Portfolio, SlabRegistry, PositionDetails) {
+        let mut registry = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+        registry
+            .register_slab(Pubkey::from([1; 32]), [1; 32], Pubkey::default(), 500, 250, 10, 10, 1000, u128::MAX, 10, 0)
+            .unwrap();
+
+        // Long 2 @ $100 at 1x, 1 SOL of margin held
+        let mut portfolio = Portfolio::new(Pubkey::default(), Pubkey::default(), 0);
+        portfolio.update_exposure(0, 0, 2 * SCALE);
+        portfolio.equity = 2_000_000_000;
+        let position = PositionDetails::new(Pubkey::default(), 0, 0, 100 * SCALE, 2 * SCALE, 0, 0, 1_000_000_000, 1);
+
+        (portfolio, registry, position)
+    }
+
+    fn fill(side: u8, qty: i64, px: i64, position: Option<PositionDetails>) -> SimulatedFill {
+        SimulatedFill {
+            slab_idx: 0,
+            split: SlabSplit { slab_id: Pubkey::from([1; 32]), qty, side, limit_px: px },
+            execution_px: px,
+            oracle_px: px,
+            contract_size: SCALE,
+            contract_kind: ContractKind::Linear,
+            scale: ScaleConfig::DEFAULT,
+            position,
+        }
+    }
+
+    #[test]
+    fn test_simulate_leaves_state_byte_identical() {
+        let (portfolio, registry, position) = setup();
+        let portfolio_before = bytes_of(&portfolio);
+        let registry_before = bytes_of(&registry);
+        let position_before = bytes_of(&position);
+
+        // Close half at $110
+        let result = project_fills(&portfolio, &registry, &[fill(1, SCALE, 110 * SCALE, Some(position))], 1, 1_000_000_000, 0).unwrap();
+
+        assert_eq!(result.filled_qty, -SCALE);
+        assert_eq!(result.realized_pnl, 90_909_000);
+        assert_eq!(result.margin_released, 500_000_000);
+        assert_eq!(result.projected_im, 500_000_000);
+        assert_eq!(result.projected_equity, 2_000_000_000 + 500_000_000 + 90_909_000);
+        assert!(result.sufficient_margin);
+
+        assert_eq!(bytes_of(&portfolio), portfolio_before);
+        assert_eq!(bytes_of(&registry), registry_before);
+        assert_eq!(bytes_of(&position), position_before);
+    }
+
+    #[test]
+    fn test_simulate_flags_insufficient_margin() {
+        let mut portfolio = Portfolio::new(Pubkey::default(), Pubkey::default(), 0);
+        portfolio.equity = 100_000_000;
+        let (_, registry, _) = setup();
+
+        // Open 1 @ $100 at 5x: 0.2 SOL margin against 0.1 SOL equity
+        let result = project_fills(&portfolio, &registry, &[fill(0, SCALE, 100 * SCALE, None)], 5, 0, 0).unwrap();
+
+        assert_eq!(result.margin_posted, 200_000_000);
+        assert_eq!(result.projected_im, 200_000_000);
+        assert_eq!(result.projected_equity, -100_000_000);
+        assert!(!result.sufficient_margin);
+        assert_eq!(result.to_bytes()[104], 0);
+        assert_eq!(portfolio.exposure_count, 0);
+    }
+
+    #[test]
+    fn test_simulate_rejects_leverage_change() {
+        let (portfolio, registry, position) = setup();
+
+        // The 1x long can't be added to at 5x
+        let result = project_fills(&portfolio, &registry, &[fill(0, SCALE, 100 * SCALE, Some(position))], 5, 1_000_000_000, 0);
+        assert_eq!(result.unwrap_err(), PercolatorError::LeverageMismatch);
+    }
+}
@@ -0,0 +1,149 @@
+//! Sweep positions - close empty PositionDetails PDAs and refund their rent
+//!
+//! Partial-close rounding or an aborted flow can leave a PositionDetails PDA
+//! with total_qty == 0 behind, locking its rent. Sweeping closes such PDAs
+//! in a batch, refunding rent to the portfolio owner. A PDA whose position
+//! is still live in the Portfolio is never closed.
+
+use crate::instructions::close_position_details_pda;
+use crate::state::{Portfolio, PositionDetails, POSITION_DETAILS_SIZE};
+use percolator_common::*;
+use pinocchio::{account_info::AccountInfo, msg, pubkey::Pubkey};
+
+/// Maximum PositionDetails accounts swept in one transaction
+pub const MAX_SWEEP_POSITIONS: usize = 16;
+
+/// Check that a PositionDetails account can be swept
+///
+/// It must be a valid PositionDetails of this portfolio with no quantity
+/// left, and the portfolio must hold no exposure for its slab/instrument.
+pub fn check_sweepable(
+    portfolio: &Portfolio,
+    portfolio_key: &Pubkey,
+    data: &[u8],
+) -> Result<(), PercolatorError> {
+    if data.len() != POSITION_DETAILS_SIZE {
+        msg!("Error: PositionDetails account has wrong size");
+        return Err(PercolatorError::InvalidAccount);
+    }
+
+    let details = unsafe { &*(data.as_ptr() as *const PositionDetails) };
+    if !details.validate() || &details.portfolio != portfolio_key {
+        msg!("Error: PositionDetails does not belong to portfolio");
+        return Err(PercolatorError::InvalidAccount);
+    }
+
+    if details.total_qty != 0
+        || portfolio.get_exposure(details.slab_index, details.instrument_index) != 0
+    {
+        msg!("Error: Cannot sweep a live position");
+        return Err(PercolatorError::InvalidAccount);
+    }
+
+    Ok(())
+}
+
+/// Process sweep positions instruction
+///
+/// # Arguments
+/// * `portfolio` - Portfolio the positions belong to
+/// * `portfolio_key` - Portfolio account address (PositionDetails.portfolio)
+/// * `owner_account` - Portfolio owner (receives the refunded rent)
+/// * `position_accounts` - PositionDetails PDAs to close
+/// * `program_id` - Router program ID (must own every PDA)
+///
+/// # Returns
+/// * Number of PDAs closed; every account is checked before any is closed,
+///   so one live position fails the whole batch
+pub fn process_sweep_positions(
+    portfolio: &Portfolio,
+    portfolio_key: &Pubkey,
+    owner_account: &AccountInfo,
+    position_accounts: &[AccountInfo],
+    program_id: &Pubkey,
+) -> Result<usize, PercolatorError> {
+    if position_accounts.is_empty() || position_accounts.len() > MAX_SWEEP_POSITIONS {
+        msg!("Error: Invalid sweep account count");
+        return Err(PercolatorError::InvalidInstruction);
+    }
+
+    if &portfolio.user != owner_account.key() {
+        msg!("Error: Rent must be refunded to the portfolio owner");
+        return Err(PercolatorError::Unauthorized);
+    }
+
+    for account in position_accounts {
+        if account.owner() != program_id {
+            msg!("Error: PositionDetails not owned by router");
+            return Err(PercolatorError::InvalidAccountOwner);
+        }
+        let data = account
+            .try_borrow_data()
+            .map_err(|_| PercolatorError::InvalidAccount)?;
+        check_sweepable(portfolio, portfolio_key, &data)?;
+    }
+
+    for account in position_accounts {
+        close_position_details_pda(account, owner_account)?;
+    }
+
+    msg!("SweepPositions: Closed empty positions");
+    Ok(position_accounts.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PORTFOLIO: Pubkey = [3; 32];
+
+    fn bytes_of(details: &PositionDetails) -> Vec<u8> {
+        unsafe {
+            core::slice::from_raw_parts(details as *const PositionDetails as *const u8, POSITION_DETAILS_SIZE)
+        }
+        .to_vec()
+    }
+
+    #[test]
+    fn test_sweep_zeroed_position() {
+        // Position fully closed in the Portfolio, but its PDA was left behind
+        let mut portfolio = Portfolio::new(Pubkey::default(), Pubkey::default(), 0);
+        portfolio.update_exposure(1, 0, 1_000_000);
+        portfolio.update_exposure(1, 0, 0);
+
+        let mut details = PositionDetails::new(PORTFOLIO, 1, 0, 100_000_000, 1_000_000, 0, 255, 0, 1);
+        details.total_qty = 0;
+        details.margin_held = 0;
+
+        assert_eq!(check_sweepable(&portfolio, &PORTFOLIO, &bytes_of(&details)), Ok(()));
+    }
+
+    #[test]
+    fn test_refuse_to_sweep_live_position() {
+        let mut portfolio = Portfolio::new(Pubkey::default(), Pubkey::default(), 0);
+        portfolio.update_exposure(1, 0, 1_000_000);
+
+        // PDA says flat, but the Portfolio still has exposure on that slab
+        let mut details = PositionDetails::new(PORTFOLIO, 1, 0, 100_000_000, 1_000_000, 0, 255, 0, 1);
+        details.total_qty = 0;
+        assert_eq!(
+            check_sweepable(&portfolio, &PORTFOLIO, &bytes_of(&details)),
+            Err(PercolatorError::InvalidAccount)
+        );
+
+        // PDA still holds quantity
+        let live = PositionDetails::new(PORTFOLIO, 2, 0, 100_000_000, 1_000_000, 0, 255, 0, 1);
+        assert_eq!(
+            check_sweepable(&portfolio, &PORTFOLIO, &bytes_of(&live)),
+            Err(PercolatorError::InvalidAccount)
+        );
+
+        // Another portfolio's position
+        let mut foreign = PositionDetails::new([9; 32], 2, 0, 100_000_000, 0, 0, 255, 0, 1);
+        foreign.total_qty = 0;
+        assert_eq!(
+            check_sweepable(&portfolio, &PORTFOLIO, &bytes_of(&foreign)),
+            Err(PercolatorError::InvalidAccount)
+        );
+    }
+}
@@ -0,0 +1,93 @@
+//! Touch - permissionless heartbeat for an idle portfolio
+//!
+//! Vesting and haircut catchup only run when a portfolio is touched, which
+//! otherwise only happens on a trade. Any keeper can call this to advance an
+//! idle portfolio's accounting to the current slot so front-ends don't show
+//! stale numbers. Nothing is transferred: the touch only rewrites the
+//! portfolio's PnL bookkeeping and the registry's vested PnL total.
+
+use crate::state::{touch_portfolio, Portfolio, SlabRegistry};
+use percolator_common::*;
+use pinocchio::msg;
+
+/// Process touch instruction
+///
+/// # Arguments
+/// * `portfolio` - Portfolio to advance (any portfolio; no owner signature)
+/// * `registry` - Slab registry (vesting params, haircut index)
+/// * `current_slot` - Current slot
+///
+/// # Returns
+/// * Updates portfolio:
+///   - Applies any pending global haircut to pnl
+///   - Vests pnl up to `current_slot`
+///   - Updates last_slot and pnl_index_checkpoint
+pub fn process_touch(
+    portfolio: &mut Portfolio,
+    registry: &mut SlabRegistry,
+    current_slot: u64,
+) -> Result<(), PercolatorError> {
+    if current_slot < portfolio.last_slot {
+        msg!("Error: Touch slot is behind the portfolio's last touch");
+        return Err(PercolatorError::InvalidInstruction);
+    }
+
+    touch_portfolio(
+        portfolio,
+        &mut registry.global_haircut,
+        &registry.pnl_vesting_params,
+        current_slot,
+    );
+
+    msg!("Touch: Portfolio advanced");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::FP_ONE;
+    use pinocchio::pubkey::Pubkey;
+
+    fn idle_portfolio() -> (Portfolio, SlabRegistry) {
+        let registry = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+        let mut portfolio = Portfolio::new(Pubkey::default(), Pubkey::default(), 0);
+        portfolio.principal = 100_000_000;
+        portfolio.pnl = 50_000_000;
+        portfolio.vested_pnl = 0;
+        portfolio.equity = 150_000_000;
+        portfolio.last_slot = 1000;
+        portfolio.pnl_index_checkpoint = FP_ONE;
+        (portfolio, registry)
+    }
+
+    #[test]
+    fn test_touch_vests_idle_portfolio() {
+        let (mut portfolio, mut registry) = idle_portfolio();
+        let tau = registry.pnl_vesting_params.tau_slots;
+
+        process_touch(&mut portfolio, &mut registry, 1000 + tau).unwrap();
+
+        assert!(portfolio.vested_pnl > 0);
+        assert!(portfolio.vested_pnl < 50_000_000);
+        assert_eq!(portfolio.last_slot, 1000 + tau);
+        assert_eq!(registry.global_haircut.total_vested_pnl, portfolio.vested_pnl);
+
+        // Vesting keeps advancing on later touches; balances never move
+        let vested = portfolio.vested_pnl;
+        process_touch(&mut portfolio, &mut registry, 1000 + 4 * tau).unwrap();
+        assert!(portfolio.vested_pnl > vested);
+        assert_eq!((portfolio.pnl, portfolio.principal, portfolio.equity), (50_000_000, 100_000_000, 150_000_000));
+    }
+
+    #[test]
+    fn test_touch_rejects_stale_slot() {
+        let (mut portfolio, mut registry) = idle_portfolio();
+
+        assert_eq!(
+            process_touch(&mut portfolio, &mut registry, 999),
+            Err(PercolatorError::InvalidInstruction)
+        );
+        assert_eq!(portfolio.vested_pnl, 0);
+    }
+}
@@ -0,0 +1,122 @@
+//! Transfer governance - two-step handover of registry authority
+//!
+//! The current governance proposes a successor, which only takes over once
+//! it signs AcceptGovernance itself. A transfer to a mistyped or
+//! uncontrolled key therefore never completes, and the old governance
+//! stays in charge (and can re-propose) until the handover is accepted.
+
+use crate::state::SlabRegistry;
+use percolator_common::*;
+use pinocchio::{msg, pubkey::Pubkey};
+
+/// Process propose governance instruction
+///
+/// Replaces any earlier proposal; proposing the default pubkey withdraws it.
+///
+/// # Arguments
+/// * `registry` - Slab registry (mutable)
+/// * `authority` - Signer's pubkey (must be registry governance)
+/// * `new_governance` - Proposed successor
+pub fn process_propose_governance(
+    registry: &mut SlabRegistry,
+    authority: &Pubkey,
+    new_governance: &Pubkey,
+) -> Result<(), PercolatorError> {
+    if authority != &registry.governance {
+        msg!("Error: Only governance can propose a successor");
+        return Err(PercolatorError::Unauthorized);
+    }
+
+    registry.pending_governance = *new_governance;
+
+    msg!("ProposeGovernance: Successor proposed");
+    Ok(())
+}
+
+/// Process accept governance instruction
+///
+/// # Arguments
+/// * `registry` - Slab registry (mutable)
+/// * `authority` - Signer's pubkey (must be the pending governance)
+pub fn process_accept_governance(
+    registry: &mut SlabRegistry,
+    authority: &Pubkey,
+) -> Result<(), PercolatorError> {
+    if registry.pending_governance == Pubkey::default() || authority != &registry.pending_governance {
+        msg!("Error: Only the proposed governance can accept");
+        return Err(PercolatorError::Unauthorized);
+    }
+
+    registry.governance = registry.pending_governance;
+    registry.pending_governance = Pubkey::default();
+
+    msg!("AcceptGovernance: Governance transferred");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instructions::process_set_trade_cooldown;
+
+    #[test]
+    fn test_propose_and_accept() {
+        let mut registry = SlabRegistry::new(Pubkey::default(), [9; 32], 0);
+
+        assert_eq!(
+            process_propose_governance(&mut registry, &[1; 32], &[1; 32]),
+            Err(PercolatorError::Unauthorized)
+        );
+
+        process_propose_governance(&mut registry, &[9; 32], &[2; 32]).unwrap();
+        process_accept_governance(&mut registry, &[2; 32]).unwrap();
+
+        assert_eq!(registry.governance, [2; 32]);
+        assert_eq!(registry.pending_governance, Pubkey::default());
+
+        // The old key is out, the new one governs
+        assert_eq!(
+            process_set_trade_cooldown(&mut registry, &[9; 32], 4),
+            Err(PercolatorError::Unauthorized)
+        );
+        process_set_trade_cooldown(&mut registry, &[2; 32], 4).unwrap();
+    }
+
+    #[test]
+    fn test_accept_by_wrong_key_rejected() {
+        let mut registry = SlabRegistry::new(Pubkey::default(), [9; 32], 0);
+
+        // Nothing pending yet
+        assert_eq!(process_accept_governance(&mut registry, &[2; 32]), Err(PercolatorError::Unauthorized));
+
+        process_propose_governance(&mut registry, &[9; 32], &[2; 32]).unwrap();
+        assert_eq!(process_accept_governance(&mut registry, &[3; 32]), Err(PercolatorError::Unauthorized));
+        assert_eq!(process_accept_governance(&mut registry, &[9; 32]), Err(PercolatorError::Unauthorized));
+        assert_eq!(registry.governance, [9; 32]);
+        assert_eq!(registry.pending_governance, [2; 32]);
+
+        // A withdrawn proposal can't be accepted
+        process_propose_governance(&mut registry, &[9; 32], &Pubkey::default()).unwrap();
+        assert_eq!(process_accept_governance(&mut registry, &[2; 32]), Err(PercolatorError::Unauthorized));
+        assert_eq!(registry.governance, [9; 32]);
+    }
+
+    #[test]
+    fn test_pending_proposal_keeps_old_governance() {
+        let mut registry = SlabRegistry::new(Pubkey::default(), [9; 32], 0);
+        process_propose_governance(&mut registry, &[9; 32], &[2; 32]).unwrap();
+
+        assert_eq!(registry.governance, [9; 32]);
+        assert_eq!(
+            process_set_trade_cooldown(&mut registry, &[2; 32], 4),
+            Err(PercolatorError::Unauthorized)
+        );
+        process_set_trade_cooldown(&mut registry, &[9; 32], 4).unwrap();
+
+        // The old governance can still redirect the proposal
+        process_propose_governance(&mut registry, &[9; 32], &[3; 32]).unwrap();
+        assert_eq!(process_accept_governance(&mut registry, &[2; 32]), Err(PercolatorError::Unauthorized));
+        process_accept_governance(&mut registry, &[3; 32]).unwrap();
+        assert_eq!(registry.governance, [3; 32]);
+    }
+}
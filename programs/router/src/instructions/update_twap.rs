@@ -0,0 +1,49 @@
+//! Update TWAP instruction - sample a spot oracle into a TWAP buffer
+//!
+//! Permissionless crank. Keepers call this every few slots so TWAP-priced
+//! slabs have a dense sample history to average over.
+
+use crate::instructions::read_oracle_price_single;
+use crate::oracle::{TwapAdapter, TwapBuffer};
+use percolator_common::*;
+use pinocchio::{
+    account_info::AccountInfo,
+    msg,
+    sysvars::{clock::Clock, Sysvar},
+};
+
+/// Process update TWAP instruction
+///
+/// Reads the current spot price and appends it to the buffer. The first call
+/// on a zeroed buffer binds it to the given spot oracle; later calls must pass
+/// the same oracle.
+///
+/// # Arguments
+/// * `twap_buffer` - TWAP buffer state (router-owned account)
+/// * `spot_oracle` - Spot oracle account (Custom or Pyth)
+pub fn process_update_twap(
+    twap_buffer: &mut TwapBuffer,
+    spot_oracle: &AccountInfo,
+) -> Result<(), PercolatorError> {
+    if !twap_buffer.is_initialized() {
+        msg!("UpdateTwap: Initializing buffer");
+        twap_buffer.initialize_in_place(*spot_oracle.key());
+    } else if &twap_buffer.source_oracle != spot_oracle.key() {
+        msg!("Error: Oracle does not match TWAP buffer source");
+        return Err(PercolatorError::InvalidOracle);
+    }
+
+    let spot_px = read_oracle_price_single(spot_oracle)?;
+    let current_slot = Clock::get()
+        .map(|clock| clock.slot)
+        .map_err(|_| PercolatorError::InvalidOracle)?;
+
+    twap_buffer.evict_stale(current_slot, TwapAdapter::new().window_slots);
+    twap_buffer.push(spot_px, current_slot).map_err(|_| {
+        msg!("Error: Invalid TWAP sample");
+        PercolatorError::InvalidOracle
+    })?;
+
+    msg!("UpdateTwap: Sample recorded");
+    Ok(())
+}
@@ -1,5 +1,13 @@
 //! Withdraw instruction - withdraw SOL collateral from portfolio
+//!
+//! Withdrawals are instant while a portfolio's instant withdrawals over the
+//! last `withdraw_delay_slots` stay below the registry's
+//! `withdraw_queue_threshold`, so splitting a large amount doesn't skip the
+//! queue. Beyond that they are two-phase so the DLP has time to rebalance:
+//! RequestWithdraw queues the amount on the portfolio, and Withdraw releases
+//! exactly that amount once `withdraw_delay_slots` have passed.
 
+use crate::instructions::unrealized_pnl_at_oracle;
 use crate::state::{Portfolio, SlabRegistry};
 use percolator_common::*;
 use pinocchio::{
@@ -7,9 +15,130 @@ use pinocchio::{
     instruction::{AccountMeta, Instruction},
     msg,
     program::invoke,
+    pubkey::Pubkey,
+    sysvars::{rent::Rent, Sysvar},
     ProgramResult,
 };
 
+/// Process request withdraw instruction
+///
+/// Queues a withdrawal that, with the window's instant withdrawals, reaches
+/// the registry threshold; Withdraw can claim it from
+/// `current_slot + withdraw_delay_slots`. A portfolio holds
+/// one request at a time, so a new one is rejected until the pending one
+/// is claimed.
+///
+/// # Arguments
+/// * `portfolio` - Mutable reference to portfolio state
+/// * `user` - Signer's pubkey (must own the portfolio)
+/// * `registry` - The registry account (threshold, delay and warmup state)
+/// * `amount` - Amount of lamports to withdraw
+/// * `current_slot` - Current slot
+pub fn process_request_withdraw(
+    portfolio: &mut Portfolio,
+    user: &Pubkey,
+    registry: &SlabRegistry,
+    amount: u64,
+    current_slot: u64,
+) -> Result<(), PercolatorError> {
+    if portfolio.user != *user {
+        msg!("Error: Portfolio does not belong to user");
+        return Err(PercolatorError::Unauthorized);
+    }
+    if amount == 0 {
+        msg!("Error: Withdrawal amount must be greater than zero");
+        return Err(PercolatorError::InvalidQuantity);
+    }
+    if portfolio.pending_withdrawal != 0 {
+        msg!("Error: A withdrawal request is already pending");
+        return Err(PercolatorError::WithdrawalPending);
+    }
+    if !needs_withdraw_request(portfolio, registry, amount, current_slot) {
+        msg!("Error: Withdrawal is below the queue threshold, withdraw directly");
+        return Err(PercolatorError::InvalidQuantity);
+    }
+
+    // Fail early if it couldn't be claimed today; the claim checks again
+    let max_withdrawable = portfolio.max_withdrawable_with_warmup(registry.warmup_state.unlocked_frac);
+    if amount > max_withdrawable.max(0) as u64 {
+        msg!("Error: Insufficient withdrawable funds");
+        return Err(PercolatorError::InsufficientFunds);
+    }
+
+    portfolio.pending_withdrawal = amount;
+    portfolio.withdrawal_unlock_slot = current_slot.saturating_add(registry.withdraw_delay_slots);
+
+    msg!("Withdrawal requested");
+    Ok(())
+}
+
+/// Whether `amount` on top of the window's instant withdrawals reaches the queue threshold
+fn needs_withdraw_request(portfolio: &Portfolio, registry: &SlabRegistry, amount: u64, current_slot: u64) -> bool {
+    let withdrawn = portfolio.window_withdrawn(current_slot, registry.withdraw_delay_slots);
+    registry.requires_withdraw_request(withdrawn.saturating_add(amount))
+}
+
+/// Release a withdrawal against the two-phase queue
+///
+/// A matured request for exactly `amount` is consumed so the next one can be
+/// queued. Otherwise amounts that keep the window's instant withdrawals
+/// below the threshold pass straight through and are booked on the window,
+/// and anything more is refused until it has been requested and the delay
+/// has passed.
+pub(crate) fn claim_withdrawal(
+    portfolio: &mut Portfolio,
+    registry: &SlabRegistry,
+    amount: u64,
+    current_slot: u64,
+) -> Result<(), PercolatorError> {
+    let pending = portfolio.pending_withdrawal;
+    if pending != 0 && pending == amount && current_slot >= portfolio.withdrawal_unlock_slot {
+        portfolio.pending_withdrawal = 0;
+        portfolio.withdrawal_unlock_slot = 0;
+        return Ok(());
+    }
+    if !needs_withdraw_request(portfolio, registry, amount, current_slot) {
+        portfolio.record_instant_withdrawal(amount, current_slot, registry.withdraw_delay_slots);
+        return Ok(());
+    }
+    if pending != amount {
+        msg!("Error: Large withdrawal must match a pending request");
+    } else {
+        msg!("Error: Withdrawal delay has not elapsed");
+    }
+    Err(PercolatorError::WithdrawalLocked)
+}
+
+/// Require a withdrawal to leave the portfolio at least rent-exempt
+///
+/// Fails with WouldDeallocateAccount if `amount` would take the account
+/// below `rent_floor` lamports (the rent-exempt minimum for its data size).
+pub(crate) fn check_rent_floor(portfolio_lamports: u64, amount: u64, rent_floor: u64) -> Result<(), PercolatorError> {
+    match portfolio_lamports.checked_sub(amount) {
+        Some(remaining) if remaining >= rent_floor => Ok(()),
+        _ => {
+            msg!("Error: Withdrawal would leave the portfolio below rent exemption");
+            Err(PercolatorError::WouldDeallocateAccount)
+        }
+    }
+}
+
+/// Check a portfolio the withdrawal has been booked on still meets initial margin
+///
+/// The same `has_sufficient_margin_mtm` check fills run. Fails with
+/// PortfolioInsufficientMargin.
+pub(crate) fn check_withdraw_margin(
+    portfolio: &Portfolio,
+    unrealized_pnl: i128,
+    haircut_bps: u64,
+) -> Result<(), PercolatorError> {
+    if !portfolio.has_sufficient_margin_mtm(unrealized_pnl, haircut_bps) {
+        msg!("Error: Withdrawal would leave the portfolio below initial margin");
+        return Err(PercolatorError::PortfolioInsufficientMargin);
+    }
+    Ok(())
+}
+
 /// Process withdraw instruction (SOL only for MVP)
 ///
 /// Withdraws SOL from portfolio account to user's wallet.
@@ -20,22 +149,35 @@ use pinocchio::{
 /// - Verifies portfolio belongs to user
 /// - Validates withdrawal amount is non-zero
 /// - Checks adaptive warmup withdrawal limit (principal + vested PnL)
-/// - Ensures portfolio account remains rent-exempt after withdrawal
+/// - Keeps initial margin on open positions (see `check_withdraw_margin`)
+/// - Amounts reaching the queue threshold, alone or with the window's
+///   instant withdrawals, need a matured RequestWithdraw (see `claim_withdrawal`)
+/// - Leaves the portfolio account at least rent-exempt (full exit is ClosePortfolio)
 ///
 /// # Arguments
 /// * `portfolio_account` - The user's portfolio account (sends SOL)
 /// * `portfolio` - Mutable reference to portfolio state
 /// * `user_account` - The user's wallet account (receives SOL)
 /// * `system_program` - The System Program account
-/// * `registry` - The registry account (for warmup state)
+/// * `registry` - The registry account (for warmup state and TVL tracking)
+/// * `slab_accounts` - Slab of each open position (contract size and scale)
+/// * `oracle_accounts` - Oracle paired with each slab in `slab_accounts`
+/// * `position_accounts` - PositionDetails PDAs, one per open exposure (unrealized PnL)
+/// * `program_id` - Router program ID (must own every PositionDetails)
 /// * `amount` - Amount of lamports to withdraw
+/// * `current_slot` - Current slot (for queued withdrawals)
 pub fn process_withdraw(
     portfolio_account: &AccountInfo,
     portfolio: &mut Portfolio,
     user_account: &AccountInfo,
     system_program: &AccountInfo,
-    registry: &SlabRegistry,
+    registry: &mut SlabRegistry,
+    slab_accounts: &[AccountInfo],
+    oracle_accounts: &[AccountInfo],
+    position_accounts: &[AccountInfo],
+    program_id: &Pubkey,
     amount: u64,
+    current_slot: u64,
 ) -> ProgramResult {
     // SECURITY: Validate amount
     if amount == 0 {
@@ -67,14 +209,13 @@ pub fn process_withdraw(
         return Err(PercolatorError::InsufficientFunds.into());
     }
 
-    // Check portfolio account will remain rent-exempt after withdrawal
-    let min_rent_exempt = 1_000_000_000u64; // ~1 SOL for 135KB account (approximate)
-    let portfolio_lamports = portfolio_account.lamports();
+    // Large or split-up withdrawals only release a matured request
+    claim_withdrawal(portfolio, registry, amount, current_slot)?;
 
-    if portfolio_lamports < amount + min_rent_exempt {
-        msg!("Error: Withdrawal would make portfolio account not rent-exempt");
-        return Err(PercolatorError::InsufficientFunds.into());
-    }
+    // Never withdraw into the rent-exempt minimum: the runtime would reap the
+    // account with its state. A full exit goes through ClosePortfolio
+    let rent_floor = Rent::get()?.minimum_balance(portfolio_account.data_len());
+    check_rent_floor(portfolio_account.lamports(), amount, rent_floor)?;
 
     // Transfer SOL from portfolio to user using CPI to System Program
     // Build System Program transfer instruction
@@ -118,7 +259,179 @@ pub fn process_withdraw(
         .checked_sub(amount_i128)
         .ok_or(PercolatorError::Underflow)?;
 
+    // Open positions keep their initial margin (a failure reverts the
+    // transfer), with equity marked at the oracle as fills mark it
+    let unrealized_pnl = unrealized_pnl_at_oracle(
+        portfolio,
+        portfolio_account.key(),
+        registry,
+        oracle_accounts,
+        slab_accounts,
+        position_accounts,
+        program_id,
+    )?;
+    let haircut_bps = registry.collateral_haircut_bps(&crate::state::NATIVE_SOL_MINT);
+    check_withdraw_margin(portfolio, unrealized_pnl, haircut_bps)?;
+
+    registry.track_withdrawal(amount_i128);
+
     msg!("Withdrawal successful");
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USER: Pubkey = [1; 32];
+    const LARGE: u64 = 2_000_000_000_000; // 2,000 SOL, over the 1,000 SOL default threshold
+
+    fn setup() -> (Portfolio, SlabRegistry) {
+        let mut portfolio = Portfolio::new(Pubkey::default(), USER, 0);
+        portfolio.principal = 5_000_000_000_000;
+        portfolio.equity = 5_000_000_000_000;
+        (portfolio, SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0))
+    }
+
+    #[test]
+    fn test_small_withdrawal_is_instant() {
+        let (mut portfolio, registry) = setup();
+
+        assert_eq!(claim_withdrawal(&mut portfolio, &registry, 1_000_000_000, 10), Ok(()));
+        assert_eq!(portfolio.pending_withdrawal, 0);
+
+        // Nothing to queue for a small amount
+        assert_eq!(
+            process_request_withdraw(&mut portfolio, &USER, &registry, 1_000_000_000, 10),
+            Err(PercolatorError::InvalidQuantity)
+        );
+    }
+
+    #[test]
+    fn test_large_withdrawal_is_queued() {
+        let (mut portfolio, registry) = setup();
+
+        // Without a request a large withdrawal is refused
+        assert_eq!(claim_withdrawal(&mut portfolio, &registry, LARGE, 10), Err(PercolatorError::WithdrawalLocked));
+
+        process_request_withdraw(&mut portfolio, &USER, &registry, LARGE, 10).unwrap();
+        assert_eq!(portfolio.pending_withdrawal, LARGE);
+        assert_eq!(portfolio.withdrawal_unlock_slot, 160);
+
+        // Still locked before the delay, and the request blocks another
+        assert_eq!(claim_withdrawal(&mut portfolio, &registry, LARGE, 159), Err(PercolatorError::WithdrawalLocked));
+        assert_eq!(
+            process_request_withdraw(&mut portfolio, &USER, &registry, LARGE, 20),
+            Err(PercolatorError::WithdrawalPending)
+        );
+
+        // Only the owner can queue, and only what they could withdraw
+        let (mut other, _) = setup();
+        assert_eq!(
+            process_request_withdraw(&mut other, &[2; 32], &registry, LARGE, 10),
+            Err(PercolatorError::Unauthorized)
+        );
+        assert_eq!(
+            process_request_withdraw(&mut other, &USER, &registry, 6_000_000_000_000, 10),
+            Err(PercolatorError::InsufficientFunds)
+        );
+    }
+
+    #[test]
+    fn test_queued_withdrawal_claimed_after_delay() {
+        let (mut portfolio, registry) = setup();
+        process_request_withdraw(&mut portfolio, &USER, &registry, LARGE, 10).unwrap();
+
+        // A different amount doesn't claim the request
+        assert_eq!(
+            claim_withdrawal(&mut portfolio, &registry, LARGE + 1, 160),
+            Err(PercolatorError::WithdrawalLocked)
+        );
+
+        assert_eq!(claim_withdrawal(&mut portfolio, &registry, LARGE, 160), Ok(()));
+        assert_eq!(portfolio.pending_withdrawal, 0);
+
+        // The claim is one-shot; a fresh request can follow
+        assert_eq!(claim_withdrawal(&mut portfolio, &registry, LARGE, 200), Err(PercolatorError::WithdrawalLocked));
+        assert_eq!(process_request_withdraw(&mut portfolio, &USER, &registry, LARGE, 200), Ok(()));
+    }
+
+    #[test]
+    fn test_split_withdrawals_hit_the_queue() {
+        let (mut portfolio, registry) = setup();
+        let part = 400_000_000_000; // 400 SOL, under the 1,000 SOL threshold
+
+        // Two parts fit under the threshold, a third would reach it
+        assert_eq!(claim_withdrawal(&mut portfolio, &registry, part, 10), Ok(()));
+        assert_eq!(claim_withdrawal(&mut portfolio, &registry, part, 10), Ok(()));
+        assert_eq!(claim_withdrawal(&mut portfolio, &registry, part, 11), Err(PercolatorError::WithdrawalLocked));
+        assert_eq!(portfolio.withdrawn_in_window, 2 * part);
+
+        // The remainder is queued like any large withdrawal
+        process_request_withdraw(&mut portfolio, &USER, &registry, part, 11).unwrap();
+        assert_eq!(claim_withdrawal(&mut portfolio, &registry, part, 159), Err(PercolatorError::WithdrawalLocked));
+        assert_eq!(claim_withdrawal(&mut portfolio, &registry, part, 161), Ok(()));
+        assert_eq!(portfolio.pending_withdrawal, 0);
+
+        // Once the window has passed, instant withdrawals start from zero
+        assert_eq!(claim_withdrawal(&mut portfolio, &registry, part, 161), Ok(()));
+        assert_eq!(portfolio.withdrawn_in_window, part);
+        assert_eq!(portfolio.withdraw_window_start, 161);
+    }
+
+    #[test]
+    fn test_withdrawal_keeps_initial_margin() {
+        let (mut portfolio, _) = setup();
+        portfolio.update_margin(4_000_000_000_000, 2_000_000_000_000);
+
+        // Withdrawn down to exactly IM is fine, a lamport more is not
+        portfolio.equity -= 1_000_000_000_000;
+        assert_eq!(check_withdraw_margin(&portfolio, 0, 0), Ok(()));
+        portfolio.equity -= 1;
+        assert_eq!(check_withdraw_margin(&portfolio, 0, 0), Err(PercolatorError::PortfolioInsufficientMargin));
+
+        // Marked the same way fills are: unrealized PnL moves the boundary
+        assert_eq!(check_withdraw_margin(&portfolio, 1, 0), Ok(()));
+        assert_eq!(check_withdraw_margin(&portfolio, -1, 0), Err(PercolatorError::PortfolioInsufficientMargin));
+    }
+
+    #[test]
+    fn test_underwater_position_blocks_withdrawal() {
+        use crate::state::PositionDetails;
+
+        const SCALE: i64 = 1_000_000;
+        let (mut portfolio, _) = setup();
+        portfolio.update_margin(4_000_000_000_000, 2_000_000_000_000);
+
+        // Long 100 contracts of 1 SOL from $100, oracle now at $90
+        let long = PositionDetails::new(Pubkey::default(), 0, 0, 100 * SCALE, 100 * SCALE, 0, 0, 0, 1);
+        let unrealized = pnl_lamports(long.unrealized_pnl(90 * SCALE), SCALE, &ScaleConfig::default());
+        assert_eq!(unrealized, -11_111_111_000);
+
+        // Realized equity alone would allow a withdrawal to IM; the open loss doesn't
+        portfolio.equity -= 1_000_000_000_000;
+        assert_eq!(check_withdraw_margin(&portfolio, 0, 0), Ok(()));
+        assert_eq!(
+            check_withdraw_margin(&portfolio, unrealized, 0),
+            Err(PercolatorError::PortfolioInsufficientMargin)
+        );
+
+        // Leaving the loss in the account makes room again
+        portfolio.equity += unrealized.unsigned_abs() as i128;
+        assert_eq!(check_withdraw_margin(&portfolio, unrealized, 0), Ok(()));
+    }
+
+    #[test]
+    fn test_withdrawal_stops_at_rent_floor() {
+        let rent_floor = 100_000_000;
+        let balance = 5_100_000_000;
+
+        // Down to exactly the rent-exempt minimum is fine
+        assert_eq!(check_rent_floor(balance, 5_000_000_000, rent_floor), Ok(()));
+
+        // One lamport past it, or more than the balance, would deallocate
+        assert_eq!(check_rent_floor(balance, 5_000_000_001, rent_floor), Err(PercolatorError::WouldDeallocateAccount));
+        assert_eq!(check_rent_floor(balance, balance + 1, rent_floor), Err(PercolatorError::WouldDeallocateAccount));
+    }
+}
@@ -1,11 +1,33 @@
 #![cfg_attr(target_os = "solana", no_std)]
 
+/// Debug log that compiles to nothing unless the `verbose_logs` feature is on
+///
+/// `log_debug!("label")` logs a static message; `log_debug!("label", a, b, c, d, e)`
+/// follows it with one sol_log_64 of the five values. Use for hot-path tracing
+/// only - failure messages stay as plain `msg!`.
+macro_rules! log_debug {
+    ($msg:literal) => {{
+        #[cfg(feature = "verbose_logs")]
+        pinocchio::msg!($msg);
+    }};
+    ($msg:literal, $a:expr, $b:expr, $c:expr, $d:expr, $e:expr) => {{
+        #[cfg(feature = "verbose_logs")]
+        {
+            pinocchio::msg!($msg);
+            pinocchio::log::sol_log_64($a, $b, $c, $d, $e);
+        }
+        #[cfg(not(feature = "verbose_logs"))]
+        let _ = || ($a, $b, $c, $d, $e);
+    }};
+}
+
 pub mod state;
 pub mod instructions;
 pub mod pda;
 pub mod liquidation;
 pub mod chooser;
 pub mod oracle;
+pub mod events;
 
 // Always expose entrypoint for testing, but only register as entrypoint when feature enabled
 pub mod entrypoint;
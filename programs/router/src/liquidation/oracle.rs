@@ -1,5 +1,6 @@
 //! Oracle alignment validation for liquidations
 
+use percolator_common::{pnl_lamports, ScaleConfig};
 use pinocchio::msg;
 
 /// Validate if slab mark price is aligned with oracle price
@@ -71,6 +72,39 @@ pub fn calculate_price_band(oracle_price: i64, band_bps: u64) -> (i64, i64) {
     }
 }
 
+/// Price a liquidation fill executes at
+///
+/// `spread_bps` past the oracle, against the liquidated account: sells
+/// (side 1, closing a long) below it, buys (side 0, closing a short) above.
+pub fn liquidation_fill_price(oracle_price: i64, side: u8, spread_bps: u64) -> i64 {
+    let spread = ((oracle_price.unsigned_abs() as u128 * spread_bps as u128) / 10_000) as i64;
+    if side == 1 {
+        oracle_price.saturating_sub(spread)
+    } else {
+        oracle_price.saturating_add(spread)
+    }
+}
+
+/// Lamport value of filling `qty` at `fill_price` instead of the oracle
+///
+/// Priced like linear PnL settlement, as a position entered at the oracle
+/// and closed at the fill: qty * |oracle - fill| / fill, converted with the
+/// slab's contract size and scale.
+pub fn liquidation_spread_lamports(
+    qty: i64,
+    oracle_price: i64,
+    fill_price: i64,
+    contract_size: i64,
+    scale: impl Into<ScaleConfig>,
+) -> u128 {
+    if fill_price <= 0 {
+        return 0;
+    }
+    let diff = (oracle_price as i128 - fill_price as i128).abs();
+    let spread = (qty.unsigned_abs() as i128).saturating_mul(diff) / fill_price as i128;
+    pnl_lamports(spread, contract_size, &scale.into()).max(0) as u128
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -153,6 +187,16 @@ mod tests {
         assert_eq!(upper, 0);
     }
 
+    #[test]
+    fn test_liquidation_fill_price_against_account() {
+        let oracle_price = 100_000_000; // $100
+
+        // 0.5%: longs sell at $99.50, shorts buy at $100.50
+        assert_eq!(liquidation_fill_price(oracle_price, 1, 50), 99_500_000);
+        assert_eq!(liquidation_fill_price(oracle_price, 0, 50), 100_500_000);
+        assert_eq!(liquidation_fill_price(oracle_price, 1, 0), oracle_price);
+    }
+
     #[test]
     fn test_price_band_high_price() {
         let oracle_price = 50_000_000; // $50.00
@@ -1,7 +1,7 @@
 //! Reduce-only liquidation planner
 
 use crate::instructions::SlabSplit;
-use crate::liquidation::oracle::{calculate_price_band, validate_oracle_alignment};
+use crate::liquidation::oracle::{calculate_price_band, liquidation_fill_price, validate_oracle_alignment};
 use crate::state::{Portfolio, SlabRegistry};
 use percolator_common::*;
 use pinocchio::{msg, pubkey::Pubkey};
@@ -9,6 +9,9 @@ use pinocchio::{msg, pubkey::Pubkey};
 /// Maximum splits for a single liquidation (v0 limit for stack safety)
 pub const MAX_LIQUIDATION_SPLITS: usize = 8;
 
+/// Maximum open positions one liquidation can price and order
+pub const MAX_LIQUIDATION_POSITIONS: usize = 16;
+
 /// Liquidation plan containing splits and expected results
 #[derive(Debug, Clone)]
 pub struct LiquidationPlan {
@@ -22,6 +25,8 @@ pub struct LiquidationPlan {
     pub band_px_low: i64,
     /// Price band upper bound (for buys)
     pub band_px_high: i64,
+    /// Oracle price each split's fill price was spread from
+    pub oracle_prices: [i64; MAX_LIQUIDATION_SPLITS],
 }
 
 impl LiquidationPlan {
@@ -38,6 +43,7 @@ impl LiquidationPlan {
             expected_reduction: 0,
             band_px_low: 0,
             band_px_high: 0,
+            oracle_prices: [0; MAX_LIQUIDATION_SPLITS],
         }
     }
 
@@ -66,6 +72,8 @@ impl LiquidationPlan {
 /// Oracle price information
 #[derive(Debug, Clone, Copy)]
 pub struct OraclePrice {
+    /// Slab index in registry
+    pub slab_idx: u16,
     /// Instrument index
     pub instrument_idx: u16,
     /// Price (1e6 scale)
@@ -85,6 +93,25 @@ pub struct SlabInfo {
     pub mark_price: i64,
 }
 
+/// Unrealized PnL of one open position at mark
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PositionPnl {
+    /// Slab index in registry
+    pub slab_idx: u16,
+    /// Instrument index
+    pub instrument_idx: u16,
+    /// Unrealized PnL at mark (lamports)
+    pub unrealized_pnl: i128,
+}
+
+/// Order positions worst unrealized PnL first
+///
+/// Closing the most underwater positions first restores health fastest.
+/// Ties break on (slab, instrument) so the order is deterministic.
+pub fn sort_worst_first(positions: &mut [PositionPnl]) {
+    positions.sort_unstable_by_key(|p| (p.unrealized_pnl, p.slab_idx, p.instrument_idx));
+}
+
 /// Plan reduce-only liquidation execution
 ///
 /// This function analyzes the portfolio's exposures and plans how to
@@ -92,6 +119,7 @@ pub struct SlabInfo {
 ///
 /// # Arguments
 /// * `portfolio` - User's portfolio with exposures
+/// * `positions` - Open positions in the order to close them (see `sort_worst_first`)
 /// * `registry` - Slab registry with liquidation parameters
 /// * `oracle_prices` - Array of oracle prices per instrument
 /// * `oracle_count` - Number of valid oracle prices
@@ -104,14 +132,17 @@ pub struct SlabInfo {
 ///
 /// # Algorithm
 /// 1. Determine price band based on mode (pre-liq vs hard liq)
-/// 2. For each exposure in portfolio:
-///    - If qty > 0 (long), plan sell orders
-///    - If qty < 0 (short), plan buy orders
+/// 2. Take the first position, in `positions` order (worst PnL first), that
+///    has an oracle price and an aligned slab; each liquidation closes one
+///    position, and the next call moves on to the next worst:
+///    - If qty > 0 (long), plan a sell order
+///    - If qty < 0 (short), plan a buy order
 /// 3. Filter slabs by oracle alignment
 /// 4. Apply per-slab caps
-/// 5. Set limit prices within band
+/// 5. Set limit prices at the oracle less the liquidation spread, within band
 pub fn plan_reduce_only(
     portfolio: &Portfolio,
+    positions: &[PositionPnl],
     registry: &SlabRegistry,
     oracle_prices: &[OraclePrice],
     oracle_count: usize,
@@ -138,9 +169,10 @@ pub fn plan_reduce_only(
 
     msg!("Planner: Determined price band based on mode");
 
-    // Process each exposure in the portfolio
-    for i in 0..portfolio.exposure_count as usize {
-        let (exp_slab_idx, exp_instrument_idx, qty) = portfolio.exposures[i];
+    // Plan the worst position that can be closed
+    for position in positions {
+        let (exp_slab_idx, exp_instrument_idx) = (position.slab_idx, position.instrument_idx);
+        let qty = portfolio.get_exposure(exp_slab_idx, exp_instrument_idx);
 
         if qty == 0 {
             continue; // Skip zero exposures
@@ -148,8 +180,8 @@ pub fn plan_reduce_only(
 
         msg!("Planner: Processing portfolio exposure");
 
-        // Find oracle price for this instrument
-        let oracle_price = find_oracle_price(oracle_prices, oracle_count, exp_instrument_idx);
+        // Find oracle price for this slab's instrument
+        let oracle_price = find_oracle_price(oracle_prices, oracle_count, exp_slab_idx, exp_instrument_idx);
         if oracle_price == 0 {
             msg!("Planner: No oracle price available for instrument");
             continue; // Skip if no oracle price
@@ -163,15 +195,12 @@ pub fn plan_reduce_only(
         msg!("Planner: Calculated price band for liquidation");
 
         // Determine side and limit price
-        let (side, limit_px) = if qty > 0 {
-            // Long position: need to sell (reduce-only)
-            // Use lower band as limit (willing to sell at discount)
-            (1u8, band_low) // side=1 is sell
-        } else {
-            // Short position: need to buy (reduce-only)
-            // Use upper band as limit (willing to buy at premium)
-            (0u8, band_high) // side=0 is buy
-        };
+        // Long position: need to sell (reduce-only), short: need to buy.
+        // Either fills liquidation_spread_bps past the oracle against the
+        // account, but never beyond the band
+        let side = if qty > 0 { 1u8 } else { 0u8 }; // side=1 is sell
+        let spread_bps = registry.liquidation_spread_bps.min(band_bps);
+        let limit_px = liquidation_fill_price(oracle_price, side, spread_bps);
 
         let qty_to_reduce = qty.abs();
 
@@ -206,11 +235,18 @@ pub fn plan_reduce_only(
                 side,
                 limit_px,
             })?;
+            plan.oracle_prices[plan.split_count - 1] = oracle_price;
 
             // For v0, we only plan one split per exposure
             // In production, we could split across multiple slabs
             break;
         }
+
+        // One position per liquidation; a position with no price or no
+        // aligned slab falls through to the next worst
+        if plan.split_count > 0 {
+            break;
+        }
     }
 
     msg!("Planner: Liquidation plan completed");
@@ -218,14 +254,15 @@ pub fn plan_reduce_only(
     Ok(plan)
 }
 
-/// Find oracle price for a given instrument
+/// Find oracle price for a given slab's instrument
 fn find_oracle_price(
     oracle_prices: &[OraclePrice],
     count: usize,
+    slab_idx: u16,
     instrument_idx: u16,
 ) -> i64 {
     for i in 0..count.min(oracle_prices.len()) {
-        if oracle_prices[i].instrument_idx == instrument_idx {
+        if oracle_prices[i].slab_idx == slab_idx && oracle_prices[i].instrument_idx == instrument_idx {
             return oracle_prices[i].price;
         }
     }
@@ -264,32 +301,77 @@ mod tests {
     #[test]
     fn test_find_oracle_price_found() {
         let oracles = [
-            OraclePrice { instrument_idx: 0, price: 1_000_000 },
-            OraclePrice { instrument_idx: 1, price: 2_000_000 },
-            OraclePrice { instrument_idx: 2, price: 0 },
+            OraclePrice { slab_idx: 0, instrument_idx: 0, price: 1_000_000 },
+            OraclePrice { slab_idx: 1, instrument_idx: 0, price: 2_000_000 },
+            OraclePrice { slab_idx: 2, instrument_idx: 0, price: 0 },
         ];
 
-        let price = find_oracle_price(&oracles, 2, 1);
+        let price = find_oracle_price(&oracles, 2, 1, 0);
         assert_eq!(price, 2_000_000);
     }
 
     #[test]
     fn test_find_oracle_price_not_found() {
         let oracles = [
-            OraclePrice { instrument_idx: 0, price: 1_000_000 },
-            OraclePrice { instrument_idx: 1, price: 2_000_000 },
-            OraclePrice { instrument_idx: 2, price: 0 },
+            OraclePrice { slab_idx: 0, instrument_idx: 0, price: 1_000_000 },
+            OraclePrice { slab_idx: 1, instrument_idx: 0, price: 2_000_000 },
+            OraclePrice { slab_idx: 2, instrument_idx: 0, price: 0 },
         ];
 
-        let price = find_oracle_price(&oracles, 2, 5);
+        let price = find_oracle_price(&oracles, 2, 5, 0);
         assert_eq!(price, 0);
     }
 
     #[test]
     fn test_find_oracle_price_empty() {
-        let oracles = [OraclePrice { instrument_idx: 0, price: 0 }];
+        let oracles = [OraclePrice { slab_idx: 0, instrument_idx: 0, price: 0 }];
 
-        let price = find_oracle_price(&oracles, 0, 0);
+        let price = find_oracle_price(&oracles, 0, 0, 0);
         assert_eq!(price, 0);
     }
+
+    fn position(slab_idx: u16, unrealized_pnl: i128) -> PositionPnl {
+        PositionPnl { slab_idx, instrument_idx: 0, unrealized_pnl }
+    }
+
+    #[test]
+    fn test_sort_worst_first() {
+        let mut positions = [position(0, -5), position(1, 20), position(2, -50), position(3, -5)];
+        sort_worst_first(&mut positions);
+        let order: [u16; 4] = core::array::from_fn(|i| positions[i].slab_idx);
+        assert_eq!(order, [2, 0, 3, 1]);
+    }
+
+    #[test]
+    fn test_plan_closes_worst_position_first() {
+        const SCALE: i64 = 1_000_000;
+        let mut registry = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+        registry.router_cap_per_slab = u64::MAX;
+
+        // Mildly losing long on slab 0, badly losing short on slab 1
+        let mut portfolio = Portfolio::new(Pubkey::default(), Pubkey::default(), 0);
+        portfolio.update_exposure(0, 0, 10 * SCALE);
+        portfolio.update_exposure(1, 0, -10 * SCALE);
+        let mut positions = [position(0, -1_000_000), position(1, -50_000_000)];
+        sort_worst_first(&mut positions);
+
+        let oracles = [
+            OraclePrice { slab_idx: 0, instrument_idx: 0, price: 100 * SCALE },
+            OraclePrice { slab_idx: 1, instrument_idx: 0, price: 100 * SCALE },
+        ];
+        let slabs = [
+            SlabInfo { slab_id: [10; 32], slab_idx: 0, instrument_idx: 0, mark_price: 100 * SCALE },
+            SlabInfo { slab_id: [11; 32], slab_idx: 1, instrument_idx: 0, mark_price: 100 * SCALE },
+        ];
+
+        // The badly losing short is bought back; the long waits for the next call
+        let plan = plan_reduce_only(&portfolio, &positions, &registry, &oracles, 2, &slabs, 2, false).unwrap();
+        assert_eq!(plan.split_count, 1);
+        assert_eq!((plan.splits[0].slab_id, plan.splits[0].side), ([11; 32], 0));
+
+        // Without the short's slab, the long is the worst that can be closed
+        let plan = plan_reduce_only(&portfolio, &positions, &registry, &oracles, 2, &slabs[..1], 1, false).unwrap();
+        assert_eq!(plan.split_count, 1);
+        assert_eq!((plan.splits[0].slab_id, plan.splits[0].side), ([10; 32], 1));
+    }
 }
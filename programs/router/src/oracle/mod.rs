@@ -2,14 +2,16 @@
 //
 // Provides unified interface for reading oracle prices from:
 // - Pyth Network (production mainnet/devnet)
+// - Switchboard V2 aggregators (production mainnet/devnet)
 // - Custom oracle (localnet testing)
+// - TWAP buffer (time-weighted average of either of the above)
+//
+// The Pyth, Switchboard and Custom adapters live in percolator_common::oracle so the slab
+// can read the same feeds; only the TWAP buffer is router-specific.
 //
 // All prices are normalized to 1e6 scale (i.e., $50,000 = 50_000_000_000)
 
-pub mod adapter;
-pub mod pyth;
-pub mod custom;
+pub mod twap;
 
-pub use adapter::{OracleAdapter, OraclePrice, OracleError};
-pub use pyth::PythAdapter;
-pub use custom::CustomAdapter;
+pub use percolator_common::oracle::{OracleAdapter, OraclePrice, OracleError, PythAdapter, SwitchboardAdapter, CustomAdapter};
+pub use twap::{TwapAdapter, TwapBuffer, TwapSample, TWAP_CAPACITY};
@@ -0,0 +1,338 @@
+// TWAP oracle adapter for Barista DEX
+//
+// Reads a time-weighted average price from a ring buffer of (price, slot)
+// samples. The buffer is fed from a spot oracle by the UpdateTwap crank, so a
+// single-block oracle poke only moves the average by its share of the window.
+
+use percolator_common::oracle::{OracleAdapter, OracleError, OraclePrice};
+use pinocchio::{
+    account_info::AccountInfo,
+    pubkey::Pubkey,
+    sysvars::{clock::Clock, Sysvar},
+};
+
+/// Number of samples held in a TWAP buffer
+pub const TWAP_CAPACITY: usize = 32;
+
+/// Single spot price observation
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TwapSample {
+    /// Price in 1e6 scale
+    pub price: i64,
+    /// Slot the price was observed at
+    pub slot: u64,
+}
+
+/// TWAP sample buffer account
+/// Owned by the router, one per TWAP-priced slab
+#[repr(C)]
+pub struct TwapBuffer {
+    /// Magic bytes for validation (b"PRCLTWAP")
+    pub magic: [u8; 8],
+    /// Spot oracle this buffer samples from
+    pub source_oracle: Pubkey,
+    /// Next write index
+    pub head: u32,
+    /// Number of valid samples
+    pub count: u32,
+    /// Sample ring buffer
+    pub samples: [TwapSample; TWAP_CAPACITY],
+}
+
+impl TwapBuffer {
+    pub const LEN: usize = core::mem::size_of::<Self>();
+    pub const MAGIC: &'static [u8; 8] = b"PRCLTWAP";
+
+    /// Initialize buffer in-place (avoids stack allocation)
+    pub fn initialize_in_place(&mut self, source_oracle: Pubkey) {
+        self.magic = *Self::MAGIC;
+        self.source_oracle = source_oracle;
+        self.head = 0;
+        self.count = 0;
+        self.samples = [TwapSample::default(); TWAP_CAPACITY];
+    }
+
+    /// Check the buffer has been initialized
+    pub fn is_initialized(&self) -> bool {
+        &self.magic == Self::MAGIC
+    }
+
+    /// Ring index of the i-th valid sample (0 = oldest)
+    fn index_of(&self, i: usize) -> usize {
+        (self.head as usize + TWAP_CAPACITY - self.count as usize + i) % TWAP_CAPACITY
+    }
+
+    /// Most recent sample, if any
+    pub fn latest(&self) -> Option<TwapSample> {
+        if self.count == 0 {
+            return None;
+        }
+        Some(self.samples[self.index_of(self.count as usize - 1)])
+    }
+
+    /// Record a new sample, overwriting the oldest once full
+    ///
+    /// A sample in the same slot as the latest replaces it; samples older
+    /// than the latest are rejected so the buffer stays slot-ordered.
+    pub fn push(&mut self, price: i64, slot: u64) -> Result<(), OracleError> {
+        if price <= 0 {
+            return Err(OracleError::InvalidFormat);
+        }
+
+        if let Some(latest) = self.latest() {
+            if slot < latest.slot {
+                return Err(OracleError::InvalidFormat);
+            }
+            if slot == latest.slot {
+                let idx = self.index_of(self.count as usize - 1);
+                self.samples[idx].price = price;
+                return Ok(());
+            }
+        }
+
+        self.samples[self.head as usize] = TwapSample { price, slot };
+        self.head = ((self.head as usize + 1) % TWAP_CAPACITY) as u32;
+        if (self.count as usize) < TWAP_CAPACITY {
+            self.count += 1;
+        }
+        Ok(())
+    }
+
+    /// Drop samples older than the window from the front of the buffer
+    pub fn evict_stale(&mut self, current_slot: u64, window_slots: u64) {
+        let cutoff = current_slot.saturating_sub(window_slots);
+        while self.count > 0 && self.samples[self.index_of(0)].slot < cutoff {
+            self.count -= 1;
+        }
+    }
+
+    /// Time-weighted average over the last `window_slots` slots
+    ///
+    /// Each sample is weighted by the slots until the next sample (the newest
+    /// counts through `current_slot`). Returns None if no sample is in window.
+    pub fn twap(&self, current_slot: u64, window_slots: u64) -> Option<i64> {
+        let cutoff = current_slot.saturating_sub(window_slots);
+        let count = self.count as usize;
+
+        let mut weighted_sum: i128 = 0;
+        let mut total_weight: u128 = 0;
+
+        for i in 0..count {
+            let sample = self.samples[self.index_of(i)];
+            if sample.slot < cutoff || sample.slot > current_slot {
+                continue;
+            }
+
+            let end_slot = if i + 1 < count {
+                self.samples[self.index_of(i + 1)].slot.min(current_slot + 1)
+            } else {
+                current_slot + 1
+            };
+            let weight = end_slot.saturating_sub(sample.slot) as u128;
+
+            weighted_sum += sample.price as i128 * weight as i128;
+            total_weight += weight;
+        }
+
+        if total_weight == 0 {
+            return None;
+        }
+        Some((weighted_sum / total_weight as i128) as i64)
+    }
+}
+
+/// TWAP oracle adapter
+pub struct TwapAdapter {
+    /// Averaging window in slots
+    pub window_slots: u64,
+}
+
+impl TwapAdapter {
+    /// Create new TWAP adapter with default 150-slot (~60s) window
+    pub fn new() -> Self {
+        Self { window_slots: 150 }
+    }
+
+    /// Create TWAP adapter with specific window
+    pub fn with_window(window_slots: u64) -> Self {
+        Self { window_slots }
+    }
+}
+
+impl OracleAdapter for TwapAdapter {
+    fn read_price(&self, oracle_account: &AccountInfo) -> Result<OraclePrice, OracleError> {
+        self.validate_account(oracle_account)?;
+
+        let buffer = unsafe {
+            percolator_common::borrow_account_data::<TwapBuffer>(oracle_account)
+                .map_err(|_| OracleError::InvalidAccount)?
+        };
+
+        let clock = Clock::get().map_err(|_| OracleError::PriceUnavailable)?;
+        let price = buffer
+            .twap(clock.slot, self.window_slots)
+            .ok_or(OracleError::StalePrice)?;
+
+        Ok(OraclePrice {
+            price,
+            confidence: 0,
+            timestamp: clock.unix_timestamp,
+            expo: -6, // Samples are already 1e6 scale
+        })
+    }
+
+    fn validate_account(&self, oracle_account: &AccountInfo) -> Result<(), OracleError> {
+        // Only the router's UpdateTwap crank may have written the samples
+        if oracle_account.owner() != &crate::ID {
+            return Err(OracleError::InvalidAccount);
+        }
+
+        let data = oracle_account
+            .try_borrow_data()
+            .map_err(|_| OracleError::InvalidAccount)?;
+
+        if data.len() != TwapBuffer::LEN {
+            return Err(OracleError::InvalidAccount);
+        }
+
+        if &data[0..8] != TwapBuffer::MAGIC {
+            return Err(OracleError::InvalidAccount);
+        }
+
+        Ok(())
+    }
+
+    fn is_stale(&self, timestamp: i64, max_age_secs: i64) -> bool {
+        match Clock::get() {
+            Ok(clock) => clock.unix_timestamp - timestamp > max_age_secs,
+            Err(_) => false,
+        }
+    }
+
+    fn provider_name(&self) -> &'static str {
+        "TWAP"
+    }
+}
+
+impl Default for TwapAdapter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn empty_buffer() -> TwapBuffer {
+        TwapBuffer {
+            magic: [0; 8],
+            source_oracle: Pubkey::default(),
+            head: 0,
+            count: 0,
+            samples: [TwapSample::default(); TWAP_CAPACITY],
+        }
+    }
+
+    #[test]
+    fn test_single_sample_twap() {
+        let mut buf = empty_buffer();
+        buf.initialize_in_place(Pubkey::default());
+        assert!(buf.is_initialized());
+        assert_eq!(buf.twap(100, 50), None);
+
+        buf.push(100_000_000, 100).unwrap();
+        assert_eq!(buf.twap(100, 50), Some(100_000_000));
+    }
+
+    #[test]
+    fn test_windowed_average_is_time_weighted() {
+        let mut buf = empty_buffer();
+        buf.initialize_in_place(Pubkey::default());
+
+        // $100 held for 90 slots, then $200 for 10 slots
+        buf.push(100_000_000, 0).unwrap();
+        buf.push(200_000_000, 90).unwrap();
+
+        // Weights: 90 slots at $100, 10 slots (90..=99) at $200
+        assert_eq!(buf.twap(99, 100), Some(110_000_000));
+    }
+
+    #[test]
+    fn test_single_slot_poke_is_dampened() {
+        let mut buf = empty_buffer();
+        buf.initialize_in_place(Pubkey::default());
+
+        for slot in 0..10u64 {
+            buf.push(100_000_000, slot * 10).unwrap();
+        }
+        // Manipulated print in the current slot
+        buf.push(1_000_000_000, 100).unwrap();
+
+        let twap = buf.twap(100, 100).unwrap();
+        assert!(twap < 110_000_000, "1-slot poke moved TWAP to {}", twap);
+    }
+
+    #[test]
+    fn test_samples_outside_window_ignored() {
+        let mut buf = empty_buffer();
+        buf.initialize_in_place(Pubkey::default());
+
+        buf.push(500_000_000, 0).unwrap();
+        buf.push(100_000_000, 200).unwrap();
+
+        // Window starts at slot 200, so the old $500 sample doesn't count
+        assert_eq!(buf.twap(250, 50), Some(100_000_000));
+        // Everything is stale once the window moves past the last sample
+        assert_eq!(buf.twap(400, 50), None);
+    }
+
+    #[test]
+    fn test_evict_stale_samples() {
+        let mut buf = empty_buffer();
+        buf.initialize_in_place(Pubkey::default());
+
+        buf.push(100_000_000, 10).unwrap();
+        buf.push(101_000_000, 20).unwrap();
+        buf.push(102_000_000, 300).unwrap();
+
+        buf.evict_stale(310, 100);
+        assert_eq!(buf.count, 1);
+        assert_eq!(buf.latest(), Some(TwapSample { price: 102_000_000, slot: 300 }));
+
+        buf.evict_stale(1_000, 100);
+        assert_eq!(buf.count, 0);
+        assert_eq!(buf.latest(), None);
+        assert_eq!(buf.twap(1_000, 100), None);
+    }
+
+    #[test]
+    fn test_ring_buffer_wraps() {
+        let mut buf = empty_buffer();
+        buf.initialize_in_place(Pubkey::default());
+
+        for i in 0..(TWAP_CAPACITY as u64 + 5) {
+            buf.push(100_000_000 + i as i64, i).unwrap();
+        }
+
+        assert_eq!(buf.count as usize, TWAP_CAPACITY);
+        assert_eq!(buf.samples[buf.index_of(0)].slot, 5);
+        assert_eq!(buf.latest().unwrap().slot, TWAP_CAPACITY as u64 + 4);
+    }
+
+    #[test]
+    fn test_push_ordering() {
+        let mut buf = empty_buffer();
+        buf.initialize_in_place(Pubkey::default());
+
+        buf.push(100_000_000, 10).unwrap();
+        assert_eq!(buf.push(100_000_000, 9), Err(OracleError::InvalidFormat));
+        assert_eq!(buf.push(0, 11), Err(OracleError::InvalidFormat));
+
+        // Same slot replaces the latest sample
+        buf.push(105_000_000, 10).unwrap();
+        assert_eq!(buf.count, 1);
+        assert_eq!(buf.latest().unwrap().price, 105_000_000);
+    }
+}
@@ -2,8 +2,18 @@
 //!
 //! PDAs are deterministic addresses derived from seeds and the program ID.
 //! They allow the program to own and control accounts without needing a private key.
+//!
+//! # Seed domains
+//!
+//! The runtime hashes a PDA's seeds concatenated with no length markers, so
+//! `["ab", "c"]` and `["a", "bc"]` derive the same address. Every account type
+//! therefore leads with its own prefix, and no prefix of a type that takes
+//! further seeds may be a byte-prefix of another type's (checked at compile
+//! time below). Two derivations can then only share a preimage if they are the
+//! same account type. The bare singletons (registry, authority) take no further
+//! seeds, so their whole preimage is the prefix itself.
 
-use pinocchio::pubkey::{find_program_address, Pubkey};
+use pinocchio::pubkey::{create_program_address, find_program_address, Pubkey, MAX_SEEDS};
 
 /// Seed prefix for vault accounts (one per mint)
 pub const VAULT_SEED: &[u8] = b"vault";
@@ -20,9 +30,97 @@ pub const PORTFOLIO_SEED: &[u8] = b"portfolio";
 /// Seed prefix for slab registry
 pub const REGISTRY_SEED: &[u8] = b"registry";
 
+/// Seed prefix for registry pages (slabs beyond the registry's MAX_SLABS)
+pub const REGISTRY_PAGE_SEED: &[u8] = b"registry_page";
+
 /// Seed prefix for router authority (used for CPI signing)
 pub const AUTHORITY_SEED: &[u8] = b"authority";
 
+/// Seed prefix for position details accounts (per portfolio, slab, instrument)
+pub const POSITION_SEED: &[u8] = b"position";
+
+/// Seed prefix for router-derived fill receipt accounts (per portfolio, slab)
+pub const RECEIPT_SEED: &[u8] = b"receipt";
+
+/// Seed prefix for AMM LP collateral pools (per market)
+pub const LP_POOL_SEED: &[u8] = b"lp_pool";
+
+/// Prefixes of every account type that takes seeds after its prefix
+const DOMAIN_SEEDS: [&[u8]; 8] = [
+    VAULT_SEED,
+    ESCROW_SEED,
+    CAP_SEED,
+    PORTFOLIO_SEED,
+    REGISTRY_PAGE_SEED,
+    POSITION_SEED,
+    RECEIPT_SEED,
+    LP_POOL_SEED,
+];
+
+/// Whether either seed is a byte-prefix of the other
+const fn seeds_overlap(a: &[u8], b: &[u8]) -> bool {
+    let len = if a.len() < b.len() { a.len() } else { b.len() };
+    let mut i = 0;
+    while i < len {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Whether no domain prefix overlaps another
+const fn domains_disjoint(seeds: &[&[u8]]) -> bool {
+    let mut i = 0;
+    while i < seeds.len() {
+        let mut j = i + 1;
+        while j < seeds.len() {
+            if seeds_overlap(seeds[i], seeds[j]) {
+                return false;
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    true
+}
+
+const _: () = assert!(domains_disjoint(&DOMAIN_SEEDS), "PDA seed prefixes must not overlap");
+const _: () = assert!(!seeds_overlap(POSITION_SEED, RECEIPT_SEED));
+
+/// Cached bump value meaning "not cached" (fall back to find_program_address)
+pub const NO_CACHED_BUMP: u8 = 0;
+
+/// Derive a PDA from a cached bump, falling back to a bump search
+///
+/// `create_program_address` hashes once, while `find_program_address` may
+/// hash up to 256 times, so hot paths pass the canonical bump stored when the
+/// account was created. The cached bump must be the one find_program_address
+/// returned for the same seeds; NO_CACHED_BUMP (or seeds that don't yield a
+/// valid PDA with it) falls back to the full search.
+///
+/// # Arguments
+/// * `seeds` - Seeds without the bump
+/// * `cached_bump` - Canonical bump, or NO_CACHED_BUMP
+/// * `program_id` - The router program ID
+///
+/// # Returns
+/// * `(Pubkey, u8)` - The derived PDA and its bump seed
+pub fn derive_with_cached_bump(seeds: &[&[u8]], cached_bump: u8, program_id: &Pubkey) -> (Pubkey, u8) {
+    if cached_bump != NO_CACHED_BUMP && seeds.len() < MAX_SEEDS {
+        let bump_seed = [cached_bump];
+        let mut seeds_with_bump: [&[u8]; MAX_SEEDS] = [&[]; MAX_SEEDS];
+        seeds_with_bump[..seeds.len()].copy_from_slice(seeds);
+        seeds_with_bump[seeds.len()] = &bump_seed;
+
+        if let Ok(pda) = create_program_address(&seeds_with_bump[..seeds.len() + 1], program_id) {
+            return (pda, cached_bump);
+        }
+    }
+    find_program_address(seeds, program_id)
+}
+
 /// Derive router authority PDA
 ///
 /// This PDA is used as the router's signing authority for CPIs to slabs.
@@ -37,6 +135,18 @@ pub fn derive_authority_pda(program_id: &Pubkey) -> (Pubkey, u8) {
     find_program_address(&[AUTHORITY_SEED], program_id)
 }
 
+/// Derive router authority PDA using the bump cached in the registry
+///
+/// # Arguments
+/// * `program_id` - The router program ID
+/// * `cached_bump` - SlabRegistry.authority_bump (NO_CACHED_BUMP on old registries)
+///
+/// # Returns
+/// * `(Pubkey, u8)` - The derived PDA and its bump seed
+pub fn derive_authority_pda_cached(program_id: &Pubkey, cached_bump: u8) -> (Pubkey, u8) {
+    derive_with_cached_bump(&[AUTHORITY_SEED], cached_bump, program_id)
+}
+
 /// Derive vault PDA for a given mint
 ///
 /// Vault stores collateral for a specific mint (e.g., USDC, SOL)
@@ -134,9 +244,76 @@ pub fn derive_registry_pda(program_id: &Pubkey) -> (Pubkey, u8) {
     find_program_address(&[REGISTRY_SEED], program_id)
 }
 
+/// Derive registry page PDA
+///
+/// # Arguments
+/// * `page_index` - Page number (1-based)
+/// * `program_id` - The router program ID
+///
+/// # Returns
+/// * `(Pubkey, u8)` - The derived PDA and its bump seed
+pub fn derive_registry_page_pda(page_index: u16, program_id: &Pubkey) -> (Pubkey, u8) {
+    find_program_address(&[REGISTRY_PAGE_SEED, &page_index.to_le_bytes()], program_id)
+}
+
+/// Derive PositionDetails PDA for one portfolio position
+///
+/// # Arguments
+/// * `portfolio` - The user's portfolio pubkey
+/// * `slab_idx` - Slab index in the registry
+/// * `instrument_idx` - Instrument index on the slab
+/// * `cached_bump` - PositionDetails.bump of the existing account, or NO_CACHED_BUMP
+/// * `program_id` - The router program ID
+///
+/// # Returns
+/// * `(Pubkey, u8)` - The derived PDA and its bump seed
+pub fn derive_position_details_pda(
+    portfolio: &Pubkey,
+    slab_idx: u16,
+    instrument_idx: u16,
+    cached_bump: u8,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    derive_with_cached_bump(
+        &[
+            POSITION_SEED,
+            portfolio.as_ref(),
+            &slab_idx.to_le_bytes(),
+            &instrument_idx.to_le_bytes(),
+        ],
+        cached_bump,
+        program_id,
+    )
+}
+
+/// Derive fill receipt PDA for one portfolio's fills on a slab
+///
+/// # Arguments
+/// * `portfolio` - The user's portfolio pubkey
+/// * `slab_idx` - Slab index in the registry
+/// * `program_id` - The router program ID
+///
+/// # Returns
+/// * `(Pubkey, u8)` - The derived PDA and its bump seed
+pub fn derive_receipt_pda(portfolio: &Pubkey, slab_idx: u16, program_id: &Pubkey) -> (Pubkey, u8) {
+    find_program_address(&[RECEIPT_SEED, portfolio.as_ref(), &slab_idx.to_le_bytes()], program_id)
+}
+
+/// Derive the LP collateral pool PDA for an AMM market
+///
+/// # Arguments
+/// * `market` - The AMM market pubkey
+/// * `cached_bump` - LpPool.bump of the existing account, or NO_CACHED_BUMP
+/// * `program_id` - The router program ID
+///
+/// # Returns
+/// * `(Pubkey, u8)` - The derived PDA and its bump seed
+pub fn derive_lp_pool_pda(market: &Pubkey, cached_bump: u8, program_id: &Pubkey) -> (Pubkey, u8) {
+    derive_with_cached_bump(&[LP_POOL_SEED, market.as_ref()], cached_bump, program_id)
+}
+
 #[cfg(test)]
 mod tests {
-    #[cfg(target_os = "solana")]
     use super::*;
 
     // Note: PDA tests only run on Solana target due to syscall requirements
@@ -211,4 +388,57 @@ mod tests {
         assert_eq!(pda1, pda2);
         assert_eq!(bump1, bump2);
     }
+
+    #[test]
+    #[cfg(target_os = "solana")]
+    fn test_cached_bump_matches_find() {
+        let program_id = Pubkey::from([7; 32]);
+        let portfolio = Pubkey::from([9; 32]);
+
+        let (authority, bump) = derive_authority_pda(&program_id);
+        assert_eq!(derive_authority_pda_cached(&program_id, bump), (authority, bump));
+        assert_eq!(derive_authority_pda_cached(&program_id, NO_CACHED_BUMP), (authority, bump));
+
+        let (position, bump) = find_program_address(
+            &[POSITION_SEED, portfolio.as_ref(), &3u16.to_le_bytes(), &0u16.to_le_bytes()],
+            &program_id,
+        );
+        assert_eq!(derive_position_details_pda(&portfolio, 3, 0, bump, &program_id), (position, bump));
+        assert_eq!(derive_position_details_pda(&portfolio, 3, 0, NO_CACHED_BUMP, &program_id), (position, bump));
+    }
+
+    /// Concatenated seed bytes, which is all the runtime hashes besides the program ID
+    fn preimage(seeds: &[&[u8]]) -> Vec<u8> {
+        seeds.concat()
+    }
+
+    #[test]
+    fn test_position_and_receipt_seeds_never_alias() {
+        let portfolio = [9u8; 32];
+
+        // Without a type prefix, position (slab 1, instrument 2) and a receipt
+        // keyed on the same portfolio hash the same bytes
+        let position_tail = preimage(&[&portfolio, &1u16.to_le_bytes(), &2u16.to_le_bytes()]);
+        let receipt_tail = preimage(&[&portfolio, &0x0002_0001u32.to_le_bytes()]);
+        assert_eq!(position_tail, receipt_tail);
+
+        // Domain prefixes keep them apart for every index choice
+        for (slab_idx, instrument_idx) in [(1u16, 2u16), (0, 0), (u16::MAX, u16::MAX)] {
+            let position = preimage(&[
+                POSITION_SEED,
+                &portfolio,
+                &slab_idx.to_le_bytes(),
+                &instrument_idx.to_le_bytes(),
+            ]);
+            for receipt_slab in [slab_idx, instrument_idx, 0x0001, 0x0200] {
+                let receipt = preimage(&[RECEIPT_SEED, &portfolio, &receipt_slab.to_le_bytes()]);
+                assert_ne!(position, receipt);
+                assert!(!position.starts_with(RECEIPT_SEED));
+                assert!(!receipt.starts_with(POSITION_SEED));
+            }
+        }
+
+        assert!(domains_disjoint(&DOMAIN_SEEDS));
+        assert!(!domains_disjoint(&[POSITION_SEED, b"pos"]));
+    }
 }
@@ -0,0 +1,109 @@
+//! Funding rate from open interest imbalance
+//!
+//! Each slab accrues a cumulative funding index once per period. The rate
+//! for a period has two parts:
+//! - Imbalance: proportional to (long_oi - short_oi) / total_oi, so the
+//!   crowded side pays the other
+//! - Premium: proportional to how far mark trades from the oracle
+//!
+//! The sum is clamped to `max_rate_bps`. A positive rate means longs pay shorts.
+
+/// Funding parameters (configurable by governance)
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FundingParams {
+    /// Slots per funding period (~1 hour at 400ms slots)
+    pub period_slots: u64,
+    /// Rate when open interest is entirely one-sided (basis points per period)
+    pub imbalance_rate_bps: u64,
+    /// Share of the mark/oracle premium charged per period (basis points)
+    pub premium_weight_bps: u64,
+    /// Largest absolute rate per period (basis points)
+    pub max_rate_bps: u64,
+}
+
+impl Default for FundingParams {
+    fn default() -> Self {
+        Self {
+            period_slots: 9_000,       // ~1 hour
+            imbalance_rate_bps: 10,    // 0.10% per hour when fully one-sided
+            premium_weight_bps: 1_000, // 10% of the premium per hour
+            max_rate_bps: 50,          // 0.50% per hour
+        }
+    }
+}
+
+/// Signed funding rate for one period (basis points, positive = longs pay)
+///
+/// Balanced open interest at mark == oracle produces zero.
+pub fn compute_funding_rate(
+    long_oi: u64,
+    short_oi: u64,
+    mark_px: i64,
+    oracle_px: i64,
+    params: &FundingParams,
+) -> i64 {
+    let total_oi = long_oi as i128 + short_oi as i128;
+    let imbalance = if total_oi == 0 {
+        0
+    } else {
+        (long_oi as i128 - short_oi as i128) * params.imbalance_rate_bps as i128 / total_oi
+    };
+
+    let premium = if oracle_px <= 0 {
+        0
+    } else {
+        let premium_bps = (mark_px as i128 - oracle_px as i128) * 10_000 / oracle_px as i128;
+        premium_bps * params.premium_weight_bps as i128 / 10_000
+    };
+
+    let max = params.max_rate_bps.min(i64::MAX as u64) as i128;
+    (imbalance + premium).clamp(-max, max) as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCALE: i64 = 1_000_000;
+
+    /// Test: Balanced open interest at the oracle pays no funding
+    #[test]
+    fn test_balanced_oi_is_zero() {
+        let params = FundingParams::default();
+        assert_eq!(compute_funding_rate(5 * SCALE as u64, 5 * SCALE as u64, 100 * SCALE, 100 * SCALE, &params), 0);
+        assert_eq!(compute_funding_rate(0, 0, 100 * SCALE, 100 * SCALE, &params), 0);
+
+        // A sliver of imbalance rounds to nothing
+        assert_eq!(compute_funding_rate(5_000_001, 5_000_000, 100 * SCALE, 100 * SCALE, &params), 0);
+    }
+
+    /// Test: Crowded longs pay shorts, crowded shorts pay longs
+    #[test]
+    fn test_heavily_long_pays_positive() {
+        let params = FundingParams::default();
+
+        // 9:1 long: 80% imbalance of the 10 bps full-imbalance rate
+        assert_eq!(compute_funding_rate(9 * SCALE as u64, SCALE as u64, 100 * SCALE, 100 * SCALE, &params), 8);
+        assert_eq!(compute_funding_rate(SCALE as u64, 9 * SCALE as u64, 100 * SCALE, 100 * SCALE, &params), -8);
+
+        // Mark 1% over oracle adds 10% of 100 bps on top
+        assert_eq!(compute_funding_rate(9 * SCALE as u64, SCALE as u64, 101 * SCALE, 100 * SCALE, &params), 18);
+    }
+
+    /// Test: The rate is clamped to max_rate_bps in both directions
+    #[test]
+    fn test_rate_clamped_at_max() {
+        let params = FundingParams::default();
+
+        // 10% premium would be 100 bps alone
+        assert_eq!(compute_funding_rate(SCALE as u64, 0, 110 * SCALE, 100 * SCALE, &params), 50);
+        assert_eq!(compute_funding_rate(0, SCALE as u64, 90 * SCALE, 100 * SCALE, &params), -50);
+
+        // 100 bps premium + 10 bps imbalance lands exactly on a 110 bps cap
+        let at_cap = FundingParams { max_rate_bps: 110, ..params };
+        assert_eq!(compute_funding_rate(SCALE as u64, 0, 110 * SCALE, 100 * SCALE, &at_cap), 110);
+        let below_cap = FundingParams { max_rate_bps: 109, ..params };
+        assert_eq!(compute_funding_rate(SCALE as u64, 0, 110 * SCALE, 100 * SCALE, &below_cap), 109);
+    }
+}
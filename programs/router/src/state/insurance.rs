@@ -5,12 +5,21 @@
 //! - Pays out to cover bad debt after liquidations
 //! - Enforces per-event and daily payout caps
 //! - Tracks uncovered bad debt for telemetry
+//!
+//! The accrual is not a second fee on the taker. The router debits each
+//! fill's taker fee from the user once and splits it: `fee_bps_to_insurance`
+//! of fill notional goes to the insurance fund, capped at the fee actually
+//! collected, and the remainder goes to the LP. With a 20 bps taker fee and
+//! the default 10 bps, the fee splits evenly.
+
+/// Highest insurance accrual rate governance can set (1%)
+pub const MAX_INSURANCE_FEE_BPS: u16 = 100;
 
 /// Insurance fund parameters (configurable by governance)
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
 pub struct InsuranceParams {
-    /// Basis points of taker fees to insurance (e.g., 10 = 0.10%)
+    /// Basis points of fill notional credited to insurance out of the taker fee (e.g., 10 = 0.10%)
     pub fee_bps_to_insurance: u16,
     /// Max payout as bps of event notional (e.g., 50 = 0.50%)
     pub max_payout_bps_of_oi: u16,
@@ -18,6 +27,8 @@ pub struct InsuranceParams {
     pub max_daily_payout_bps_of_vault: u16,
     /// Cooldown between payouts for same instrument (optional, can be 0)
     pub cooloff_secs: u32,
+    /// Balance surplus withdrawals must leave in the vault
+    pub reserve_floor: u64,
 }
 
 impl Default for InsuranceParams {
@@ -27,6 +38,7 @@ impl Default for InsuranceParams {
             max_payout_bps_of_oi: 50,           // 0.50% of event notional cap
             max_daily_payout_bps_of_vault: 300, // 3% of vault per day
             cooloff_secs: 0,                     // No cooldown for v0
+            reserve_floor: 0,                    // Governance sets the floor
         }
     }
 }
@@ -106,6 +118,35 @@ impl InsuranceState {
         accrual
     }
 
+    /// Split a collected taker fee between the LP and the insurance fund
+    ///
+    /// The insurance share is `fee_bps_to_insurance` of `notional`, never
+    /// more than `fee`; it is booked like `accrue_from_fill`.
+    ///
+    /// # Returns
+    /// `(to_lp, to_insurance)`, summing to `fee`
+    pub fn split_taker_fee(&mut self, fee: u128, notional: u128, params: &InsuranceParams) -> (u128, u128) {
+        use model_safety::math::{mul_u128, div_u128, add_u128, min_u128};
+
+        let share = div_u128(mul_u128(notional, params.fee_bps_to_insurance as u128), 10_000);
+        let to_insurance = min_u128(share, fee);
+
+        self.vault_balance = add_u128(self.vault_balance, to_insurance);
+        self.total_fees_accrued = add_u128(self.total_fees_accrued, to_insurance);
+
+        (fee - to_insurance, to_insurance)
+    }
+
+    /// Book the spread a liquidation fill took past the oracle
+    ///
+    /// Uses formally verified arithmetic to prevent overflow.
+    pub fn accrue_liquidation_spread(&mut self, amount: u128) {
+        use model_safety::math::add_u128;
+
+        self.vault_balance = add_u128(self.vault_balance, amount);
+        self.total_fees_accrued = add_u128(self.total_fees_accrued, amount);
+    }
+
     /// Settle bad debt after liquidation
     ///
     /// Called at the end of liquidation if user equity < 0.
@@ -196,10 +237,12 @@ impl InsuranceState {
 
     /// Withdraw surplus (governance only, requires uncovered_bad_debt == 0)
     ///
+    /// Only the balance above `params.reserve_floor` is surplus.
+    ///
     /// # Safety
     ///
     /// Uses formally verified arithmetic to prevent underflow.
-    pub fn withdraw_surplus(&mut self, amount: u128) -> Result<(), ()> {
+    pub fn withdraw_surplus(&mut self, amount: u128, params: &InsuranceParams) -> Result<(), ()> {
         use model_safety::math::sub_u128;
 
         if self.uncovered_bad_debt > 0 {
@@ -208,6 +251,9 @@ impl InsuranceState {
         if self.vault_balance < amount {
             return Err(()); // Insufficient balance
         }
+        if self.vault_balance - amount < params.reserve_floor as u128 {
+            return Err(()); // Would dip into the reserve floor
+        }
         self.vault_balance = sub_u128(self.vault_balance, amount);
         Ok(())
     }
@@ -237,6 +283,55 @@ mod tests {
         assert_eq!(state.total_fees_accrued, 1000);
     }
 
+    #[test]
+    fn test_accrual_matches_rate_across_notionals() {
+        let notionals: [u128; 8] = [
+            0,
+            1,
+            9_999,
+            10_000,
+            1_000_000,
+            123_456_789,
+            1_000_000_000_000_000, // 1e9 SOL in lamports
+            u64::MAX as u128,
+        ];
+
+        for fee_bps in [0u16, 1, 10, 25, MAX_INSURANCE_FEE_BPS] {
+            let params = InsuranceParams { fee_bps_to_insurance: fee_bps, ..InsuranceParams::default() };
+            let mut state = InsuranceState::default();
+            let mut total = 0u128;
+
+            for notional in notionals {
+                let expected = notional * fee_bps as u128 / 10_000;
+                assert_eq!(state.accrue_from_fill(notional, &params), expected, "notional {} at {} bps", notional, fee_bps);
+                total += expected;
+            }
+            assert_eq!(state.vault_balance, total);
+            assert_eq!(state.total_fees_accrued, total);
+        }
+
+        // Sub-bp remainders round down: 9_999 at 1 bp accrues nothing
+        let params = InsuranceParams { fee_bps_to_insurance: 1, ..InsuranceParams::default() };
+        assert_eq!(InsuranceState::default().accrue_from_fill(9_999, &params), 0);
+    }
+
+    #[test]
+    fn test_accrual_saturates_at_extreme_notional() {
+        let params = InsuranceParams { fee_bps_to_insurance: MAX_INSURANCE_FEE_BPS, ..InsuranceParams::default() };
+        let mut state = InsuranceState::default();
+
+        // notional * bps would overflow u128; the product saturates first
+        let accrual = state.accrue_from_fill(u128::MAX, &params);
+        assert_eq!(accrual, u128::MAX / 10_000);
+
+        // Balances pin at u128::MAX instead of wrapping
+        state.vault_balance = u128::MAX - 1;
+        state.total_fees_accrued = u128::MAX - 1;
+        state.accrue_from_fill(u128::MAX, &params);
+        assert_eq!(state.vault_balance, u128::MAX);
+        assert_eq!(state.total_fees_accrued, u128::MAX);
+    }
+
     #[test]
     fn test_settle_bad_debt_full_coverage() {
         let mut state = InsuranceState::default();
@@ -344,7 +439,7 @@ mod tests {
     fn test_withdraw_surplus_success() {
         let mut state = InsuranceState::default();
         state.vault_balance = 100_000;
-        assert!(state.withdraw_surplus(30_000).is_ok());
+        assert!(state.withdraw_surplus(30_000, &InsuranceParams::default()).is_ok());
         assert_eq!(state.vault_balance, 70_000);
     }
 
@@ -353,13 +448,24 @@ mod tests {
         let mut state = InsuranceState::default();
         state.vault_balance = 100_000;
         state.uncovered_bad_debt = 1000;
-        assert!(state.withdraw_surplus(30_000).is_err());
+        assert!(state.withdraw_surplus(30_000, &InsuranceParams::default()).is_err());
     }
 
     #[test]
     fn test_withdraw_surplus_insufficient_balance() {
         let mut state = InsuranceState::default();
         state.vault_balance = 10_000;
-        assert!(state.withdraw_surplus(30_000).is_err());
+        assert!(state.withdraw_surplus(30_000, &InsuranceParams::default()).is_err());
+    }
+
+    #[test]
+    fn test_withdraw_surplus_keeps_reserve_floor() {
+        let mut state = InsuranceState::default();
+        state.vault_balance = 100_000;
+        let params = InsuranceParams { reserve_floor: 80_000, ..InsuranceParams::default() };
+
+        assert!(state.withdraw_surplus(20_001, &params).is_err());
+        assert!(state.withdraw_surplus(20_000, &params).is_ok());
+        assert_eq!(state.vault_balance, 80_000);
     }
 }
@@ -102,6 +102,10 @@ pub struct SlabLp {
     pub _padding: [u8; 6],
     /// Open order IDs (for cancellation)
     pub open_order_ids: [u64; MAX_OPEN_ORDERS],
+    /// Quote reserved by each open order (parallel to open_order_ids)
+    pub open_order_quote: [u64; MAX_OPEN_ORDERS],
+    /// Base reserved by each open order (parallel to open_order_ids)
+    pub open_order_base: [u64; MAX_OPEN_ORDERS],
 }
 
 impl SlabLp {
@@ -112,51 +116,70 @@ impl SlabLp {
             open_order_count: 0,
             _padding: [0; 6],
             open_order_ids: [0; MAX_OPEN_ORDERS],
+            open_order_quote: [0; MAX_OPEN_ORDERS],
+            open_order_base: [0; MAX_OPEN_ORDERS],
         }
     }
 
+    /// Find the slot of an open order
+    pub fn find_order(&self, order_id: u64) -> Option<usize> {
+        (0..self.open_order_count as usize).find(|&i| self.open_order_ids[i] == order_id)
+    }
+
     /// Add an order reservation
+    ///
+    /// The per-order amounts are recorded so cancellation frees exactly what
+    /// this order reserved. Order IDs must be unique within the bucket.
     pub fn add_reservation(&mut self, order_id: u64, quote: u128, base: u128) -> Result<(), ()> {
         if (self.open_order_count as usize) >= MAX_OPEN_ORDERS {
             return Err(());
         }
 
+        if self.find_order(order_id).is_some() {
+            return Err(());
+        }
+
+        let quote_u64 = u64::try_from(quote).map_err(|_| ())?;
+        let base_u64 = u64::try_from(base).map_err(|_| ())?;
+
         self.reserved_quote = self.reserved_quote.saturating_add(quote);
         self.reserved_base = self.reserved_base.saturating_add(base);
 
         let idx = self.open_order_count as usize;
         self.open_order_ids[idx] = order_id;
+        self.open_order_quote[idx] = quote_u64;
+        self.open_order_base[idx] = base_u64;
         self.open_order_count += 1;
 
         Ok(())
     }
 
     /// Remove an order reservation
-    pub fn remove_reservation(&mut self, order_id: u64, quote: u128, base: u128) -> Result<(), ()> {
-        // Find the order
-        let mut found_idx: Option<usize> = None;
-        for i in 0..self.open_order_count as usize {
-            if self.open_order_ids[i] == order_id {
-                found_idx = Some(i);
-                break;
-            }
-        }
+    ///
+    /// Returns the (quote, base) the order had reserved.
+    pub fn remove_reservation(&mut self, order_id: u64) -> Result<(u128, u128), ()> {
+        let idx = self.find_order(order_id).ok_or(())?;
 
-        let idx = found_idx.ok_or(())?;
+        let quote = self.open_order_quote[idx] as u128;
+        let base = self.open_order_base[idx] as u128;
 
         // Remove reservation
         self.reserved_quote = self.reserved_quote.saturating_sub(quote);
         self.reserved_base = self.reserved_base.saturating_sub(base);
 
-        // Remove order ID (swap with last)
+        // Remove order (swap with last)
         let last_idx = (self.open_order_count - 1) as usize;
         if idx != last_idx {
             self.open_order_ids[idx] = self.open_order_ids[last_idx];
+            self.open_order_quote[idx] = self.open_order_quote[last_idx];
+            self.open_order_base[idx] = self.open_order_base[last_idx];
         }
         self.open_order_ids[last_idx] = 0;
+        self.open_order_quote[last_idx] = 0;
+        self.open_order_base[last_idx] = 0;
         self.open_order_count -= 1;
 
-        Ok(())
+        Ok((quote, base))
     }
 }
 
@@ -270,14 +293,17 @@ mod tests {
         assert_eq!(slab.reserved_base, 1500);
         assert_eq!(slab.open_order_count, 2);
 
-        // Remove first reservation
-        assert!(slab.remove_reservation(1001, 1000, 500).is_ok());
+        // Remove first reservation: frees exactly what it reserved
+        assert_eq!(slab.remove_reservation(1001), Ok((1000, 500)));
         assert_eq!(slab.reserved_quote, 2000);
         assert_eq!(slab.reserved_base, 1000);
         assert_eq!(slab.open_order_count, 1);
 
         // Try to remove non-existent order
-        assert!(slab.remove_reservation(9999, 100, 100).is_err());
+        assert!(slab.remove_reservation(9999).is_err());
+
+        // Duplicate order IDs are rejected
+        assert!(slab.add_reservation(1002, 1, 1).is_err());
     }
 
     #[test]
@@ -0,0 +1,72 @@
+//! Collateral pool behind AMM LP shares
+//!
+//! LpDeposit moves collateral out of the depositor's portfolio into the
+//! market's pool and mints shares against the pool's assets; BurnLpShares
+//! pays redemptions back out of it. Share prices are always read from this
+//! account, never taken from instruction data.
+
+use pinocchio::pubkey::Pubkey;
+
+/// LP collateral pool for one AMM market
+/// PDA: ["lp_pool", market]
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct LpPool {
+    /// Router program ID
+    pub router_id: Pubkey,
+    /// AMM market the shares are issued for
+    pub market: Pubkey,
+    /// Collateral owed to shareholders (lamports; the account's rent and any
+    /// lamports sent to it directly are not counted)
+    pub total_assets: u128,
+    /// LP shares outstanding across all portfolios
+    pub total_shares: u64,
+    /// Bump seed
+    pub bump: u8,
+    /// Padding
+    pub _padding: [u8; 7],
+}
+
+impl LpPool {
+    pub const LEN: usize = core::mem::size_of::<Self>();
+
+    /// Empty pool for `market`
+    pub fn new(router_id: Pubkey, market: Pubkey, bump: u8) -> Self {
+        Self {
+            router_id,
+            market,
+            total_assets: 0,
+            total_shares: 0,
+            bump,
+            _padding: [0; 7],
+        }
+    }
+
+    /// Collateral `shares` redeem for (rounds down)
+    ///
+    /// Pro rata to the pool's assets, so burning every outstanding share
+    /// pays out exactly what the pool holds.
+    pub fn redemption_value(&self, shares: u64) -> u128 {
+        if self.total_shares == 0 {
+            return 0;
+        }
+        self.total_assets.saturating_mul(shares as u128) / self.total_shares as u128
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redemption_is_pro_rata_and_rounds_down() {
+        let mut pool = LpPool::new(Pubkey::default(), Pubkey::from([1; 32]), 255);
+        assert_eq!(pool.redemption_value(100), 0);
+
+        pool.total_assets = 1_000;
+        pool.total_shares = 3;
+        assert_eq!(pool.redemption_value(1), 333);
+        assert_eq!(pool.redemption_value(2), 666);
+        assert_eq!(pool.redemption_value(3), 1_000);
+    }
+}
@@ -1,11 +1,14 @@
 pub mod vault;
 pub mod portfolio;
 pub mod registry;
+pub mod registry_page;
 pub mod lp_bucket;
+pub mod lp_pool;
 pub mod insurance;
 pub mod pnl_vesting;
 pub mod model_bridge;
 pub mod position_details;
+pub mod funding;
 
 #[cfg(test)]
 pub mod withdrawal_limits_test;
@@ -13,8 +16,11 @@ pub mod withdrawal_limits_test;
 pub use vault::*;
 pub use portfolio::*;
 pub use registry::*;
+pub use registry_page::*;
 pub use lp_bucket::*;
+pub use lp_pool::*;
 pub use insurance::*;
 pub use pnl_vesting::*;
 pub use model_bridge::*;
 pub use position_details::*;
+pub use funding::*;
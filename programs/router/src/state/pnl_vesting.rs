@@ -53,6 +53,10 @@ pub struct GlobalHaircut {
     /// Total haircut applied since inception (1e9 = 100%)
     pub cumulative_haircut: i128,
 
+    /// Outstanding positive vested PnL across all portfolios, as of each
+    /// portfolio's last touch (the base system deficits are socialized over)
+    pub total_vested_pnl: i128,
+
     /// Haircut governance params
     pub max_haircut_per_event_bps: u16,  // e.g., 3000 = 30% max per event
     pub max_haircut_per_day_bps: u16,     // e.g., 5000 = 50% max per day
@@ -64,6 +68,7 @@ impl Default for GlobalHaircut {
             pnl_index: FP_ONE,
             last_event_id: 0,
             cumulative_haircut: 0,
+            total_vested_pnl: 0,
             max_haircut_per_event_bps: 3000,  // 30% max per event
             max_haircut_per_day_bps: 5000,     // 50% max per day
         }
@@ -165,6 +170,11 @@ pub fn one_minus_exp_neg(dt: u64, tau: u64) -> i128 {
 /// * `vesting_params` - Vesting parameters
 /// * `now_slot` - Current slot
 ///
+/// Idempotent within a slot: once a touch has brought `last_slot` to
+/// `now_slot` and the checkpoint to the global index, further touches in
+/// that slot return without changing anything. A haircut applied later in
+/// the same slot moves the index, so it is still caught up.
+///
 /// # Safety
 ///
 /// Uses formally verified arithmetic from model_safety::math to prevent
@@ -181,6 +191,11 @@ pub fn on_user_touch(
 ) {
     use model_safety::math::{max_i128, min_i128, sub_i128, add_i128, mul_i128, div_i128};
 
+    // Already touched this slot: nothing new to vest or catch up
+    if *last_slot == now_slot && *pnl_index_checkpoint == global_haircut.pnl_index {
+        return;
+    }
+
     // Step 1: Apply global haircut catchup
     // IMPORTANT: Haircuts only apply to POSITIVE PnL. Negative PnL (losses) are never haircutted.
     if *pnl_index_checkpoint != global_haircut.pnl_index {
@@ -239,6 +254,129 @@ pub fn on_user_touch(
     *vested_pnl = min_i128(*vested_pnl, *pnl);
 }
 
+/// Apply haircut catchup and vesting to a portfolio, keeping the global
+/// vested PnL total in step
+///
+/// Wraps `on_user_touch`; any change in the portfolio's positive vested PnL
+/// (new vesting, haircut catchup, losses) is reflected in
+/// `global_haircut.total_vested_pnl`.
+pub fn touch_portfolio(
+    portfolio: &mut crate::state::Portfolio,
+    global_haircut: &mut GlobalHaircut,
+    vesting_params: &PnlVestingParams,
+    now_slot: u64,
+) {
+    // Haircut catchup is already reflected in the total (apply_system_deficit
+    // scales it), so measure the change from the post-haircut value
+    let mut vested_before = portfolio.vested_pnl.max(0);
+    if portfolio.pnl > 0 && portfolio.pnl_index_checkpoint != global_haircut.pnl_index {
+        vested_before = vested_before.saturating_mul(global_haircut.pnl_index)
+            / portfolio.pnl_index_checkpoint.max(1);
+    }
+
+    on_user_touch(
+        portfolio.principal,
+        &mut portfolio.pnl,
+        &mut portfolio.vested_pnl,
+        &mut portfolio.last_slot,
+        &mut portfolio.pnl_index_checkpoint,
+        global_haircut,
+        vesting_params,
+        now_slot,
+    );
+
+    track_vested_change(global_haircut, vested_before, portfolio.vested_pnl);
+}
+
+/// Update the global vested PnL total after a portfolio's vested PnL moved
+/// from `vested_before` to `vested_after` (only the positive part counts)
+pub fn track_vested_change(global_haircut: &mut GlobalHaircut, vested_before: i128, vested_after: i128) {
+    let delta = vested_after.max(0) - vested_before.max(0);
+    global_haircut.total_vested_pnl = global_haircut
+        .total_vested_pnl
+        .saturating_add(delta)
+        .max(0);
+}
+
+/// Socialize a system deficit across profitable accounts
+///
+/// Called when insurance is exhausted and bad debt remains. Scales the global
+/// PnL index down by deficit / total vested PnL (capped per event), so each
+/// profitable portfolio's PnL shrinks pro-rata on its next touch.
+///
+/// # Returns
+/// Amount of the deficit socialized (less than `deficit_lamports` if the
+/// per-event cap bound or there wasn't enough vested PnL to absorb it)
+pub fn apply_system_deficit(registry: &mut crate::state::SlabRegistry, deficit_lamports: u128) -> u128 {
+    let global = &mut registry.global_haircut;
+    let total_vested = global.total_vested_pnl.max(0) as u128;
+
+    let keep = calculate_haircut_fraction(deficit_lamports, total_vested, global.max_haircut_per_event_bps);
+    if keep == FP_ONE {
+        return 0;
+    }
+
+    let haircut = FP_ONE - keep;
+    global.pnl_index = global.pnl_index.saturating_mul(keep) / FP_ONE;
+    global.cumulative_haircut = global.cumulative_haircut.saturating_add(haircut);
+    global.last_event_id = global.last_event_id.saturating_add(1);
+
+    let remaining_vested = global.total_vested_pnl.saturating_mul(keep) / FP_ONE;
+    let socialized = (global.total_vested_pnl - remaining_vested) as u128;
+    global.total_vested_pnl = remaining_vested;
+
+    socialized.min(deficit_lamports)
+}
+
+/// Vesting schedule snapshot for a portfolio, for front-end display
+///
+/// Projects the portfolio's vesting forward to `current_slot` without
+/// mutating it (global haircut catchup is not applied; that only happens on
+/// touch).
+///
+/// # Returns
+/// `(vested, locked, unlock_slot)` where:
+/// - `vested` is the vested PnL as of `current_slot`
+/// - `locked` is the positive PnL still vesting (0 if none)
+/// - `unlock_slot` is the slot at which the remainder is fully vested
+///   (`current_slot` when nothing is locked)
+pub fn vesting_status(
+    portfolio: &crate::state::Portfolio,
+    params: &PnlVestingParams,
+    current_slot: u64,
+) -> (i128, i128, u64) {
+    let mut pnl = portfolio.pnl;
+    let mut vested_pnl = portfolio.vested_pnl;
+    let mut last_slot = portfolio.last_slot;
+    let mut checkpoint = portfolio.pnl_index_checkpoint;
+    let no_haircut = GlobalHaircut {
+        pnl_index: checkpoint,
+        ..GlobalHaircut::default()
+    };
+
+    on_user_touch(
+        portfolio.principal,
+        &mut pnl,
+        &mut vested_pnl,
+        &mut last_slot,
+        &mut checkpoint,
+        &no_haircut,
+        params,
+        current_slot,
+    );
+
+    let locked = (pnl - vested_pnl).max(0);
+    if locked == 0 {
+        return (vested_pnl, 0, current_slot);
+    }
+
+    // one_minus_exp_neg saturates to 1.0 at 10 tau; the cliff gates any vesting
+    let full_vest_slots = params.tau_slots.saturating_mul(10).max(params.cliff_slots);
+    let unlock_slot = last_slot.saturating_add(full_vest_slots).max(current_slot);
+
+    (vested_pnl, locked, unlock_slot)
+}
+
 /// Calculate required global haircut to cover shortfall (using verified math)
 ///
 /// Called after insurance fund is exhausted and bad debt remains.
@@ -590,6 +728,35 @@ mod tests {
         // Principal unchanged
     }
 
+    #[test]
+    fn test_touch_twice_in_one_slot_is_noop() {
+        let params = PnlVestingParams::default();
+        let mut global = GlobalHaircut::default();
+        global.pnl_index = (FP_ONE * 90) / 100;
+
+        let mut pnl = 50_000_000;
+        let mut vested_pnl = 10_000_000;
+        let mut last_slot = 1000;
+        let mut checkpoint = FP_ONE;
+
+        // First touch catches up the haircut and vests
+        let now = 1000 + params.tau_slots;
+        on_user_touch(0, &mut pnl, &mut vested_pnl, &mut last_slot, &mut checkpoint, &global, &params, now);
+        assert_eq!(last_slot, now);
+        let after_first = (pnl, vested_pnl, last_slot, checkpoint);
+
+        // The second touch in the same slot changes nothing
+        on_user_touch(0, &mut pnl, &mut vested_pnl, &mut last_slot, &mut checkpoint, &global, &params, now);
+        assert_eq!((pnl, vested_pnl, last_slot, checkpoint), after_first);
+
+        // A haircut landing later in that slot is still applied once
+        global.pnl_index = (global.pnl_index * 50) / 100;
+        on_user_touch(0, &mut pnl, &mut vested_pnl, &mut last_slot, &mut checkpoint, &global, &params, now);
+        assert_eq!(pnl, after_first.0 / 2);
+        on_user_touch(0, &mut pnl, &mut vested_pnl, &mut last_slot, &mut checkpoint, &global, &params, now);
+        assert_eq!(pnl, after_first.0 / 2);
+    }
+
     // ===== Integration Tests (I01-I04) =====
 
     #[test]
@@ -824,4 +991,83 @@ mod tests {
         assert!((withdrawable - expected).abs() < tolerance,
                 "withdrawable={}, expected={}", withdrawable, expected);
     }
+
+    #[test]
+    fn test_vesting_status_partway_through_window() {
+        use crate::state::Portfolio;
+        use pinocchio::pubkey::Pubkey;
+
+        let params = PnlVestingParams {
+            tau_slots: 10_000,
+            cliff_slots: 0,
+        };
+
+        let mut portfolio = Portfolio::new(Pubkey::default(), Pubkey::default(), 0);
+        portfolio.pnl = 50_000_000;
+        portfolio.vested_pnl = 0;
+        portfolio.last_slot = 1000;
+        portfolio.pnl_index_checkpoint = FP_ONE;
+
+        // One tau in: ~63% vested, the rest locked until 10 tau past last_slot
+        let (vested, locked, unlock_slot) = vesting_status(&portfolio, &params, 1000 + 10_000);
+        let expected_vested = (50_000_000 * 632) / 1000;
+        assert!((vested - expected_vested).abs() < 50_000_000 / 100,
+            "vested={}, expected~{}", vested, expected_vested);
+        assert_eq!(vested + locked, 50_000_000);
+        assert_eq!(unlock_slot, 11_000 + 100_000);
+
+        // Query is read-only
+        assert_eq!(portfolio.vested_pnl, 0);
+        assert_eq!(portfolio.last_slot, 1000);
+
+        // Inside a cliff nothing has vested yet
+        let cliff = PnlVestingParams { tau_slots: 10_000, cliff_slots: 200_000 };
+        let (vested, locked, unlock_slot) = vesting_status(&portfolio, &cliff, 1000 + 10_000);
+        assert_eq!((vested, locked, unlock_slot), (0, 50_000_000, 1000 + 200_000));
+
+        // Losses have nothing locked
+        portfolio.pnl = -1_000;
+        let (_, locked, unlock_slot) = vesting_status(&portfolio, &params, 5_000);
+        assert_eq!((locked, unlock_slot), (0, 5_000));
+    }
+
+    #[test]
+    fn test_system_deficit_haircuts_profitable_accounts_pro_rata() {
+        use crate::state::{Portfolio, SlabRegistry};
+        use pinocchio::pubkey::Pubkey;
+
+        let mut registry = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+        registry.pnl_vesting_params = PnlVestingParams { tau_slots: 0, cliff_slots: 0 };
+
+        // Two profitable accounts, fully vested on touch: $30 and $10
+        let mut alice = Portfolio::new(Pubkey::default(), Pubkey::default(), 0);
+        let mut bob = Portfolio::new(Pubkey::default(), Pubkey::default(), 0);
+        alice.pnl = 30_000_000;
+        bob.pnl = 10_000_000;
+        let params = registry.pnl_vesting_params;
+        touch_portfolio(&mut alice, &mut registry.global_haircut, &params, 1);
+        touch_portfolio(&mut bob, &mut registry.global_haircut, &params, 1);
+        assert_eq!(registry.global_haircut.total_vested_pnl, 40_000_000);
+
+        // $4 deficit over $40 vested: everyone keeps 90%
+        let socialized = apply_system_deficit(&mut registry, 4_000_000);
+        assert_eq!(socialized, 4_000_000);
+        assert_eq!(registry.global_haircut.pnl_index, FP_ONE * 9 / 10);
+        assert_eq!(registry.global_haircut.total_vested_pnl, 36_000_000);
+
+        // Nothing changes until each account is touched
+        assert_eq!(alice.vested_pnl, 30_000_000);
+
+        touch_portfolio(&mut alice, &mut registry.global_haircut, &params, 2);
+        touch_portfolio(&mut bob, &mut registry.global_haircut, &params, 2);
+        assert_eq!(alice.vested_pnl, 27_000_000);
+        assert_eq!(bob.vested_pnl, 9_000_000);
+
+        // Catchup doesn't double-count against the already-scaled total
+        assert_eq!(registry.global_haircut.total_vested_pnl, 36_000_000);
+
+        // Per-event cap (30%) bounds a deficit larger than the cap
+        let socialized = apply_system_deficit(&mut registry, 100_000_000);
+        assert_eq!(socialized, 36_000_000 * 3 / 10);
+    }
 }
@@ -1,7 +1,7 @@
 //! User portfolio for cross-margin tracking
 
 use pinocchio::pubkey::Pubkey;
-use percolator_common::{MAX_INSTRUMENTS, MAX_SLABS};
+use percolator_common::{PercolatorError, MAX_INSTRUMENTS, MAX_SLABS};
 use crate::state::lp_bucket::{LpBucket, VenueId, MAX_LP_BUCKETS};
 
 /// Exposure key: (slab_index, instrument_index)
@@ -69,6 +69,32 @@ pub struct Portfolio {
     pub lp_bucket_count: u16,
     /// Padding for alignment
     pub _padding3: [u8; 6],
+
+    // Two-phase withdrawal
+    /// Lamports queued by RequestWithdraw (0 = no pending request)
+    pub pending_withdrawal: u64,
+    /// Slot from which the pending withdrawal can be claimed
+    pub withdrawal_unlock_slot: u64,
+
+    /// Slot of the last user-initiated trade (0 = never traded)
+    pub last_trade_slot: u64,
+
+    /// Taker fees paid over the account's life (1e6 scale); unlike a
+    /// position's total_fees, kept after the position closes
+    pub lifetime_fees_paid: i128,
+
+    /// Realized PnL of every closed position (lamports), rolled up as each
+    /// PositionDetails PDA closes so the record outlives the position
+    pub lifetime_realized_pnl: i128,
+
+    /// Key allowed to trade this portfolio on the user's behalf, e.g. a
+    /// strategy vault's PDA (default = none). It can't deposit or withdraw.
+    pub delegate: Pubkey,
+
+    /// Lamports withdrawn without a request since `withdraw_window_start`
+    pub withdrawn_in_window: u64,
+    /// Slot the current instant-withdrawal window opened
+    pub withdraw_window_start: u64,
 }
 
 impl Portfolio {
@@ -76,7 +102,7 @@ impl Portfolio {
 
     // Compile-time size check - will cause build to fail if size doesn't match
     const _SIZE_CHECK: () = {
-        const EXPECTED: usize = 12176;
+        const EXPECTED: usize = 14328;
         const ACTUAL: usize = core::mem::size_of::<Portfolio>();
         const _: [(); EXPECTED] = [(); ACTUAL];
     };
@@ -130,6 +156,16 @@ impl Portfolio {
                 MAX_LP_BUCKETS,
             );
         }
+
+        // No withdrawal queued
+        self.pending_withdrawal = 0;
+        self.withdrawal_unlock_slot = 0;
+        self.last_trade_slot = 0;
+        self.lifetime_fees_paid = 0;
+        self.lifetime_realized_pnl = 0;
+        self.delegate = Pubkey::default();
+        self.withdrawn_in_window = 0;
+        self.withdraw_window_start = 0;
     }
 
     /// Initialize new portfolio (for tests only - uses stack)
@@ -164,7 +200,41 @@ impl Portfolio {
             lp_buckets: [zero_bucket; MAX_LP_BUCKETS],
             lp_bucket_count: 0,
             _padding3: [0; 6],
+            pending_withdrawal: 0,
+            withdrawal_unlock_slot: 0,
+            last_trade_slot: 0,
+            lifetime_fees_paid: 0,
+            lifetime_realized_pnl: 0,
+            delegate: Pubkey::default(),
+            withdrawn_in_window: 0,
+            withdraw_window_start: 0,
+        }
+    }
+
+    /// Lamports withdrawn without a request in the window open at `current_slot`
+    ///
+    /// A window lasts `window_slots` from its first withdrawal; once it has
+    /// ended the count starts again from zero.
+    pub fn window_withdrawn(&self, current_slot: u64, window_slots: u64) -> u64 {
+        if current_slot >= self.withdraw_window_start.saturating_add(window_slots) {
+            0
+        } else {
+            self.withdrawn_in_window
+        }
+    }
+
+    /// Book an instant withdrawal, opening a new window if the last one ended
+    pub fn record_instant_withdrawal(&mut self, amount: u64, current_slot: u64, window_slots: u64) {
+        if self.window_withdrawn(current_slot, window_slots) == 0 {
+            self.withdraw_window_start = current_slot;
+            self.withdrawn_in_window = 0;
         }
+        self.withdrawn_in_window = self.withdrawn_in_window.saturating_add(amount);
+    }
+
+    /// Whether `authority` may trade this portfolio: the user, or the delegate if one is set
+    pub fn is_trading_authority(&self, authority: &Pubkey) -> bool {
+        authority == &self.user || (self.delegate != Pubkey::default() && authority == &self.delegate)
     }
 
     /// Update exposure for (slab, instrument)
@@ -239,14 +309,37 @@ impl Portfolio {
         self.free_collateral = sub_i128(equity, u128_to_i128(self.im));
     }
 
-    /// Check if sufficient margin
-    pub fn has_sufficient_margin(&self) -> bool {
-        self.equity >= self.im as i128
+    /// Equity counted toward margin once deposited collateral is haircut
+    ///
+    /// Only principal is haircut; PnL is already marked at the oracle.
+    pub fn margin_equity(&self, haircut_bps: u64) -> i128 {
+        let haircut = self.principal.max(0) * haircut_bps.min(10_000) as i128 / 10_000;
+        self.equity.saturating_sub(haircut)
+    }
+
+    /// Mark-to-market equity: stored (realized) equity plus `unrealized_pnl` (lamports)
+    pub fn effective_equity(&self, unrealized_pnl: i128) -> i128 {
+        self.equity.saturating_add(unrealized_pnl)
+    }
+
+    /// Check if sufficient margin, with collateral haircut at `haircut_bps`
+    ///
+    /// Realized equity only; see `has_sufficient_margin_mtm`.
+    pub fn has_sufficient_margin(&self, haircut_bps: u64) -> bool {
+        self.has_sufficient_margin_mtm(0, haircut_bps)
+    }
+
+    /// Check IM against equity marked with `unrealized_pnl`, haircut at `haircut_bps`
+    ///
+    /// The one initial margin check: fills and withdrawals both go through
+    /// it, so trade-time and withdraw-time margin can't drift apart.
+    pub fn has_sufficient_margin_mtm(&self, unrealized_pnl: i128, haircut_bps: u64) -> bool {
+        self.margin_equity(haircut_bps).saturating_add(unrealized_pnl) >= self.im as i128
     }
 
-    /// Check if above maintenance margin
-    pub fn is_above_maintenance(&self) -> bool {
-        self.equity >= self.mm as i128
+    /// Check if above maintenance margin, with collateral haircut at `haircut_bps`
+    pub fn is_above_maintenance(&self, haircut_bps: u64) -> bool {
+        self.margin_equity(haircut_bps) >= self.mm as i128
     }
 
     /// Find LP bucket by venue
@@ -393,6 +486,20 @@ impl Portfolio {
         // Total = principal + (vested_pnl * unlocked_frac)
         self.principal.saturating_add(withdrawable_pnl)
     }
+
+    /// Record a trade at `current_slot`, throttling rapid-fire trading
+    ///
+    /// Rejects the trade with TradeThrottled if the previous one was fewer
+    /// than `cooldown_slots` slots ago. A cooldown of 0 disables the throttle.
+    pub fn record_trade(&mut self, current_slot: u64, cooldown_slots: u64) -> Result<(), PercolatorError> {
+        if self.last_trade_slot != 0
+            && current_slot < self.last_trade_slot.saturating_add(cooldown_slots)
+        {
+            return Err(PercolatorError::TradeThrottled);
+        }
+        self.last_trade_slot = current_slot;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -423,16 +530,56 @@ mod tests {
         portfolio.update_equity(10000);
         portfolio.update_margin(5000, 2500);
 
-        assert!(portfolio.has_sufficient_margin());
-        assert!(portfolio.is_above_maintenance());
+        assert!(portfolio.has_sufficient_margin(0));
+        assert!(portfolio.is_above_maintenance(0));
         assert_eq!(portfolio.free_collateral, 5000);
 
         portfolio.update_equity(4000);
-        assert!(!portfolio.has_sufficient_margin());
-        assert!(portfolio.is_above_maintenance());
+        assert!(!portfolio.has_sufficient_margin(0));
+        assert!(portfolio.is_above_maintenance(0));
 
         portfolio.update_equity(2000);
-        assert!(!portfolio.is_above_maintenance());
+        assert!(!portfolio.is_above_maintenance(0));
+    }
+
+    #[test]
+    fn test_mark_to_market_margin() {
+        let mut portfolio = Portfolio::new(Pubkey::default(), Pubkey::default(), 0);
+        portfolio.update_equity(10_000);
+        portfolio.update_margin(8_000, 4_000);
+
+        // Unrealized PnL folds into equity either way
+        assert_eq!(portfolio.effective_equity(0), 10_000);
+        assert_eq!(portfolio.effective_equity(1_500), 11_500);
+        assert_eq!(portfolio.effective_equity(-1_500), 8_500);
+
+        // The check flips exactly where marked equity meets IM
+        assert!(portfolio.has_sufficient_margin_mtm(-2_000, 0));
+        assert!(!portfolio.has_sufficient_margin_mtm(-2_001, 0));
+
+        // An unrealized gain covers a realized shortfall
+        portfolio.update_equity(7_000);
+        assert!(!portfolio.has_sufficient_margin(0));
+        assert!(portfolio.has_sufficient_margin_mtm(1_000, 0));
+    }
+
+    #[test]
+    fn test_collateral_haircut_margin() {
+        let mut portfolio = Portfolio::new(Pubkey::default(), Pubkey::default(), 0);
+        portfolio.principal = 100_000_000;
+        portfolio.update_equity(100_000_000);
+        portfolio.update_margin(95_000_000, 50_000_000);
+
+        // $100 of collateral covers a $95 requirement at face value...
+        assert!(portfolio.has_sufficient_margin(0));
+        // ...but only counts as $90 under a 10% haircut
+        assert_eq!(portfolio.margin_equity(1_000), 90_000_000);
+        assert!(!portfolio.has_sufficient_margin(1_000));
+        assert!(portfolio.is_above_maintenance(1_000));
+
+        // Realized PnL isn't haircut
+        portfolio.update_equity(105_000_000);
+        assert!(portfolio.has_sufficient_margin(500));
     }
 
     #[test]
@@ -635,7 +782,7 @@ mod tests {
             assert_eq!(slab.open_order_count, 3);
 
             // Remove middle reservation
-            assert!(slab.remove_reservation(1002, 2000, 1000).is_ok());
+            assert_eq!(slab.remove_reservation(1002), Ok((2000, 1000)));
 
             // Verify exact accounting
             assert_eq!(slab.reserved_quote, 2500);
@@ -644,11 +791,11 @@ mod tests {
 
             // Remaining orders should be 1001 and 1003
             // (1003 swapped into 1002's position)
-            assert!(slab.remove_reservation(1001, 1000, 500).is_ok());
+            assert_eq!(slab.remove_reservation(1001), Ok((1000, 500)));
             assert_eq!(slab.reserved_quote, 1500);
             assert_eq!(slab.reserved_base, 750);
 
-            assert!(slab.remove_reservation(1003, 1500, 750).is_ok());
+            assert_eq!(slab.remove_reservation(1003), Ok((1500, 750)));
             assert_eq!(slab.reserved_quote, 0);
             assert_eq!(slab.reserved_base, 0);
             assert_eq!(slab.open_order_count, 0);
@@ -820,4 +967,27 @@ mod tests {
         // Principal is always fully withdrawable regardless of freeze
         assert_eq!(max, 200_000);
     }
+
+    #[test]
+    fn test_rapid_trades_throttled() {
+        let mut portfolio = Portfolio::new(Pubkey::default(), Pubkey::default(), 0);
+
+        assert_eq!(portfolio.record_trade(1_000, 5), Ok(()));
+        assert_eq!(portfolio.record_trade(1_002, 5), Err(PercolatorError::TradeThrottled));
+
+        // The rejected trade doesn't restart the cooldown
+        assert_eq!(portfolio.last_trade_slot, 1_000);
+    }
+
+    #[test]
+    fn test_spaced_trades_allowed() {
+        let mut portfolio = Portfolio::new(Pubkey::default(), Pubkey::default(), 0);
+
+        assert_eq!(portfolio.record_trade(1_000, 5), Ok(()));
+        assert_eq!(portfolio.record_trade(1_005, 5), Ok(()));
+        assert_eq!(portfolio.last_trade_slot, 1_005);
+
+        // No cooldown configured: same-slot trades pass
+        assert_eq!(portfolio.record_trade(1_005, 0), Ok(()));
+    }
 }
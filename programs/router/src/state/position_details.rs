@@ -9,10 +9,11 @@
 //! Each active position gets its own PositionDetails PDA, created on position open
 //! and closed when the position is fully exited (rent refunded).
 
+use percolator_common::{inverse_base_qty, margin_release, ContractKind, InstrumentScale, PercolatorError};
 use pinocchio::pubkey::Pubkey;
 
 /// Size of PositionDetails account
-pub const POSITION_DETAILS_SIZE: usize = 136;
+pub const POSITION_DETAILS_SIZE: usize = 144;
 
 /// Magic bytes for PositionDetails validation
 pub const POSITION_DETAILS_MAGIC: &[u8; 8] = b"BARTPOSN";
@@ -42,8 +43,13 @@ pub struct PositionDetails {
     /// Bump seed for PDA
     pub bump: u8,
 
+    /// Contract kind of the slab this position trades (ContractKind as u8)
+    ///
+    /// Inverse positions hold quote-notional qty and realize PnL in base.
+    pub contract_kind: u8,
+
     /// Padding for alignment
-    pub _padding1: [u8; 3],
+    pub _padding1: [u8; 2],
 
     /// Weighted average entry price (scaled by 1_000_000)
     ///
@@ -86,6 +92,12 @@ pub struct PositionDetails {
 
     /// Reserved for future use
     pub _reserved: [u8; 7],
+
+    /// Price at which the position's margin is exhausted (scaled by 1_000_000)
+    ///
+    /// avg_entry_price moved against the position by margin_held / qty,
+    /// refreshed whenever entry, size or leverage change. 0 when flat.
+    pub bankruptcy_price: i64,
 }
 
 impl PositionDetails {
@@ -108,13 +120,14 @@ impl PositionDetails {
         initial_margin: u128,
         leverage: u8,
     ) -> Self {
-        Self {
+        let mut details = Self {
             magic: u64::from_le_bytes(*POSITION_DETAILS_MAGIC),
             portfolio,
             slab_index,
             instrument_index,
             bump,
-            _padding1: [0; 3],
+            contract_kind: ContractKind::Linear as u8,
+            _padding1: [0; 2],
             avg_entry_price: entry_price,
             total_qty: initial_qty,
             realized_pnl: 0,
@@ -125,7 +138,10 @@ impl PositionDetails {
             margin_held: initial_margin,
             leverage,
             _reserved: [0; 7],
-        }
+            bankruptcy_price: 0,
+        };
+        details.refresh_bankruptcy_price();
+        details
     }
 
     /// Validate the magic bytes
@@ -137,6 +153,14 @@ impl PositionDetails {
     ///
     /// Calculates new weighted average entry price:
     /// new_avg = (old_avg * old_qty + fill_price * fill_qty) / (old_qty + fill_qty)
+    ///
+    /// Inverse positions average harmonically, since qty is quote notional:
+    /// new_avg = (old_qty + fill_qty) / (old_qty / old_avg + fill_qty / fill_price)
+    ///
+    /// An open position keeps the leverage it was opened at: adding at any
+    /// other leverage fails with LeverageMismatch, since margin_held would
+    /// otherwise mix contributions sized at different leverages. A flat
+    /// position takes the leverage of the fill.
     pub fn add_to_position(
         &mut self,
         fill_price: i64,
@@ -144,14 +168,32 @@ impl PositionDetails {
         fee: i128,
         timestamp: i64,
         additional_margin: u128,
-    ) {
-        // Calculate weighted average entry price
-        let old_cost = (self.avg_entry_price as i128) * (self.total_qty.abs() as i128);
-        let new_cost = (fill_price as i128) * (fill_qty.abs() as i128);
-        let total_cost = old_cost + new_cost;
+        leverage: u8,
+    ) -> Result<(), PercolatorError> {
+        if self.total_qty == 0 {
+            self.leverage = leverage;
+        } else if leverage != self.leverage {
+            return Err(PercolatorError::LeverageMismatch);
+        }
 
         let new_qty = self.total_qty + fill_qty;
-        self.avg_entry_price = (total_cost / (new_qty.abs() as i128)) as i64;
+
+        if self.contract_kind() == ContractKind::Inverse {
+            // Sum base held at each price, then divide notional back out
+            let old_base = inverse_base_qty(self.total_qty.abs() as i128, self.avg_entry_price, InstrumentScale::DEFAULT);
+            let new_base = inverse_base_qty(fill_qty.abs() as i128, fill_price, InstrumentScale::DEFAULT);
+            let total_base = old_base + new_base;
+            if total_base > 0 {
+                self.avg_entry_price = ((new_qty.abs() as i128) * 1_000_000 / total_base) as i64;
+            }
+        } else {
+            // Calculate weighted average entry price
+            let old_cost = (self.avg_entry_price as i128) * (self.total_qty.abs() as i128);
+            let new_cost = (fill_price as i128) * (fill_qty.abs() as i128);
+            let total_cost = old_cost + new_cost;
+
+            self.avg_entry_price = (total_cost / (new_qty.abs() as i128)) as i64;
+        }
 
         self.total_qty = new_qty;
         self.total_fees = self.total_fees.saturating_add(fee);
@@ -160,6 +202,8 @@ impl PositionDetails {
 
         // Track additional margin held in DLP
         self.margin_held = self.margin_held.saturating_add(additional_margin);
+        self.refresh_bankruptcy_price();
+        Ok(())
     }
 
     /// Update position when reducing existing position (opposite direction)
@@ -167,6 +211,9 @@ impl PositionDetails {
     /// Calculates realized PnL for the closed portion:
     /// pnl = qty_closed * (exit_price - avg_entry_price)
     ///
+    /// Inverse positions realize in base directly:
+    /// pnl = qty_closed * (1 / avg_entry_price - 1 / exit_price)
+    ///
     /// Returns: (realized_pnl, remaining_qty, margin_to_release)
     pub fn reduce_position(
         &mut self,
@@ -196,9 +243,23 @@ impl PositionDetails {
         // pnl_usd_raw = (micro-SOL * micro-USD/SOL) = micro^2-USD
         // exit_price = micro-USD/SOL
         // pnl_SOL = micro^2-USD / micro-USD/SOL = micro-SOL
-        // Then multiply by 1000 to convert from micro-SOL to lamports (1e6 -> 1e9)
         // Then multiply by leverage to get actual PnL on leveraged position
-        let pnl = (pnl_usd_raw / (exit_price as i128)) * 1_000 * (self.leverage as i128);
+        // Result stays in 1e6 scale; callers convert with pnl_to_lamports
+        //
+        // Inverse: qty is already quote notional, so the base PnL is
+        // qty * (exit - entry) / (entry * exit), rescaled back to 1e6
+        let pnl_base = match self.contract_kind() {
+            ContractKind::Linear => pnl_usd_raw / (exit_price as i128),
+            ContractKind::Inverse => {
+                let denom = (self.avg_entry_price as i128) * (exit_price as i128);
+                if denom > 0 {
+                    pnl_usd_raw.saturating_mul(1_000_000) / denom
+                } else {
+                    0
+                }
+            }
+        };
+        let pnl = pnl_base * (self.leverage as i128);
 
         self.realized_pnl = self.realized_pnl.saturating_add(pnl);
         self.total_fees = self.total_fees.saturating_add(fee);
@@ -206,34 +267,95 @@ impl PositionDetails {
         self.last_update_ts = timestamp;
 
         // Update remaining quantity
+        let prev_qty_abs = self.total_qty.unsigned_abs();
         if self.total_qty > 0 {
             self.total_qty -= qty_closed;
         } else {
             self.total_qty += qty_closed;
         }
 
-        // Calculate proportional margin to release
+        // Calculate proportional margin to release against the pre-close size
         // If closing entire position, release all margin
         // If partial close, release proportional amount
-        let total_qty_abs = (self.total_qty + if self.total_qty > 0 { qty_closed } else { -qty_closed }) as u128;
         let margin_to_release = if self.total_qty == 0 {
             // Full close - return all margin
             let full_margin = self.margin_held;
             self.margin_held = 0;
             full_margin
-        } else if total_qty_abs > 0 {
-            // Partial close - return proportional margin
-            let proportion = (qty_closed as u128 * 1_000_000) / total_qty_abs;
-            let release = (self.margin_held * proportion) / 1_000_000;
+        } else if prev_qty_abs > 0 {
+            // Partial close - return proportional margin, rounded down
+            let release = margin_release(self.margin_held, qty_closed as u64, prev_qty_abs);
             self.margin_held = self.margin_held.saturating_sub(release);
             release
         } else {
             0
         };
+        self.refresh_bankruptcy_price();
 
         (pnl, self.total_qty, margin_to_release)
     }
 
+    /// Recompute `bankruptcy_price` from the current entry, size and leverage
+    ///
+    /// Margin is posted at notional / leverage, so margin_held / qty in price
+    /// terms is avg_entry_price / leverage:
+    /// - Linear: entry * (1 - 1/leverage) for longs, entry * (1 + 1/leverage) for shorts
+    /// - Inverse: entry * leverage / (leverage + 1) for longs,
+    ///   entry * leverage / (leverage - 1) for shorts (unbounded at 1x)
+    pub fn refresh_bankruptcy_price(&mut self) {
+        if self.total_qty == 0 || self.avg_entry_price <= 0 {
+            self.bankruptcy_price = 0;
+            return;
+        }
+
+        let entry = self.avg_entry_price as i128;
+        let leverage = self.leverage.max(1) as i128;
+        let is_long = self.total_qty > 0;
+
+        let price = match self.contract_kind() {
+            ContractKind::Linear => {
+                let margin_per_unit = entry / leverage;
+                if is_long { entry - margin_per_unit } else { entry + margin_per_unit }
+            }
+            ContractKind::Inverse => {
+                if is_long {
+                    entry * leverage / (leverage + 1)
+                } else if leverage > 1 {
+                    entry * leverage / (leverage - 1)
+                } else {
+                    i64::MAX as i128
+                }
+            }
+        };
+
+        self.bankruptcy_price = price.clamp(0, i64::MAX as i128) as i64;
+    }
+
+    /// Oracle price at which this position should be liquidated
+    ///
+    /// Longs are liquidatable at or below it, shorts at or above it.
+    /// Returns 0 for a flat position.
+    pub fn liquidation_price(&self) -> i64 {
+        self.bankruptcy_price
+    }
+
+    /// PnL a full close at `mark_px` would realize (1e6 scale, 0 when flat)
+    ///
+    /// Runs reduce_position on a copy, so leverage and inverse quoting are
+    /// priced exactly as a real close would; the position is untouched.
+    pub fn unrealized_pnl(&self, mark_px: i64) -> i128 {
+        if self.total_qty == 0 {
+            return 0;
+        }
+        let mut closed = *self;
+        closed.reduce_position(mark_px, -self.total_qty, 0, 0).0
+    }
+
+    /// Contract kind this position settles under
+    pub fn contract_kind(&self) -> ContractKind {
+        ContractKind::from_u8(self.contract_kind)
+    }
+
     /// Derive the PDA for a position
     pub fn derive_pda(
         portfolio: &Pubkey,
@@ -245,7 +367,7 @@ impl PositionDetails {
 
         find_program_address(
             &[
-                b"position",
+                crate::pda::POSITION_SEED,
                 portfolio.as_ref(),
                 &slab_index.to_le_bytes(),
                 &instrument_index.to_le_bytes(),
@@ -1,8 +1,112 @@
 //! Slab registry for governance and validation
 
 use pinocchio::pubkey::Pubkey;
+use crate::pda::NO_CACHED_BUMP;
+use crate::state::funding::{compute_funding_rate, FundingParams};
 use percolator_common::MAX_SLABS;
 
+/// Leverage cap for slabs registered without an explicit one
+pub const DEFAULT_SLAB_MAX_LEVERAGE: u8 = 3;
+
+/// Largest oracle move accepted against the last settled price (basis points)
+pub const DEFAULT_MAX_MOVE_BPS: u64 = 2_000; // 20%
+
+/// Largest limit price deviation from the oracle for slabs without their own band (basis points)
+pub const DEFAULT_LIMIT_DEVIATION_BPS: u16 = 2_000; // 20%
+
+/// Slots a move beyond max_move_bps stays halted after the last settlement
+/// (~60s); a price still there once the window passes is accepted
+pub const CIRCUIT_BREAKER_WINDOW_SLOTS: u64 = 150;
+
+/// Liquidation fill spread past the oracle for new registries (basis points)
+pub const DEFAULT_LIQUIDATION_SPREAD_BPS: u64 = 50; // 0.5%
+
+/// Open positions a portfolio may hold in new registries; one liquidation
+/// can close at most this many, so the default keeps every account liquidatable
+pub const DEFAULT_MAX_POSITIONS_PER_PORTFOLIO: u64 = crate::liquidation::planner::MAX_LIQUIDATION_POSITIONS as u64;
+
+/// Length of the UTC day a slab's trading window repeats over
+pub const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Pause flag: order execution (ExecuteCrossSlab, CloseAll)
+pub const PAUSE_TRADING: u64 = 1 << 0;
+/// Pause flag: collateral deposits
+pub const PAUSE_DEPOSITS: u64 = 1 << 1;
+/// Pause flag: LP instructions (LpDeposit, BurnLpShares, CancelLpOrders)
+pub const PAUSE_LP: u64 = 1 << 2;
+/// Every pausable operation (withdrawals and liquidations are never paused)
+pub const PAUSE_ALL: u64 = PAUSE_TRADING | PAUSE_DEPOSITS | PAUSE_LP;
+
+/// Most keepers a permissioned registry can whitelist
+pub const MAX_LIQUIDATORS: usize = 8;
+
+/// Median feeds a slab can register beyond its primary oracle (5 in total)
+pub const MAX_EXTRA_ORACLES: usize = 4;
+
+/// Who may call LiquidateUser
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiquidatorAccess {
+    /// Any signer can liquidate
+    Open = 0,
+    /// Only whitelisted liquidators can liquidate
+    Permissioned = 1,
+}
+
+impl LiquidatorAccess {
+    /// Decode from the stored byte
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(LiquidatorAccess::Open),
+            1 => Some(LiquidatorAccess::Permissioned),
+            _ => None,
+        }
+    }
+}
+
+/// Number of maintenance margin tiers per slab
+pub const MM_TIER_COUNT: usize = 3;
+
+/// Maintenance margin band
+///
+/// Applies `mmr_bps` to the part of a position between the previous tier's
+/// `size_limit` and this one. Limits are cumulative abs(exposure) in 1e6 scale.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MmTier {
+    /// Upper bound of this band (1e6 scale, u64::MAX for the last tier)
+    pub size_limit: u64,
+    /// Maintenance margin ratio within this band (basis points)
+    pub mmr_bps: u64,
+}
+
+/// Default tiers: first 10 units at 5%, next 40 at 7%, beyond at 10%
+pub const DEFAULT_MM_TIERS: [MmTier; MM_TIER_COUNT] = [
+    MmTier { size_limit: 10_000_000, mmr_bps: 500 },
+    MmTier { size_limit: 50_000_000, mmr_bps: 700 },
+    MmTier { size_limit: u64::MAX, mmr_bps: 1000 },
+];
+
+/// Number of collateral mints the registry can price
+pub const MAX_COLLATERAL_MINTS: usize = 4;
+
+/// Wrapped SOL mint (So11111111111111111111111111111111111111112); portfolio
+/// principal is held in native SOL and is haircut at this mint's rate
+pub const NATIVE_SOL_MINT: Pubkey = [
+    0x06, 0x9b, 0x88, 0x57, 0xfe, 0xab, 0x81, 0x84, 0xfb, 0x68, 0x7f, 0x63, 0x46, 0x18, 0xc0, 0x35,
+    0xda, 0xc4, 0x39, 0xdc, 0x1a, 0xeb, 0x3b, 0x55, 0x98, 0xa0, 0xf0, 0x00, 0x00, 0x00, 0x00, 0x01,
+];
+
+/// Accepted collateral mint and how much of its value counts toward margin
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CollateralConfig {
+    /// Collateral mint
+    pub mint: Pubkey,
+    /// Share of the mint's value withheld from margin equity (basis points)
+    pub haircut_bps: u64,
+}
+
 /// Slab registration entry
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
@@ -29,8 +133,245 @@ pub struct SlabEntry {
     pub registered_ts: u64,
     /// Active flag
     pub active: bool,
+    /// Price source: false = spot oracle, true = TWAP buffer
+    pub use_twap: bool,
+    /// Maximum order leverage on this market (1-10x)
+    pub max_leverage: u8,
+    /// Number of extra_oracle_ids in use (0, 2 or 4)
+    pub extra_oracle_count: u8,
+    /// Largest limit price deviation from the oracle (basis points, 0 = DEFAULT_LIMIT_DEVIATION_BPS)
+    pub limit_deviation_bps: u16,
     /// Padding
-    pub _padding: [u8; 7],
+    pub _limit_padding: [u8; 2],
+    /// Maintenance margin tiers by position size (ascending size_limit)
+    pub mm_tiers: [MmTier; MM_TIER_COUNT],
+    /// Circuit breaker: largest move from last_settled_price (basis points)
+    pub max_move_bps: u64,
+    /// Oracle price of the last successful settlement (0 = none yet)
+    pub last_settled_price: i64,
+    /// Slot of the last successful settlement
+    pub last_settled_slot: u64,
+    /// Sum of all long user exposure on this slab (1e6 scale)
+    pub long_oi: u64,
+    /// Sum of all short user exposure on this slab (1e6 scale, absolute)
+    pub short_oi: u64,
+    /// Open interest cap per side (1e6 scale, 0 = uncapped)
+    pub max_oi: u64,
+    /// Daily trading window open, seconds after 00:00 UTC
+    pub trading_open_secs: u32,
+    /// Daily trading window close, seconds after 00:00 UTC (equal to open = always open)
+    pub trading_close_secs: u32,
+    /// Cumulative funding rate (basis points, positive = longs have paid)
+    pub global_funding_index: i128,
+    /// Rate applied in the most recent funding period (basis points)
+    pub funding_rate_bps: i64,
+    /// Slot funding was last accrued up to (0 = not started)
+    pub last_funding_slot: u64,
+    /// Further feeds priced alongside oracle_id for the median (see set_extra_oracles)
+    pub extra_oracle_ids: [Pubkey; MAX_EXTRA_ORACLES],
+}
+
+impl SlabEntry {
+    /// Build the entry for a newly registered, active slab
+    ///
+    /// Fails if `max_leverage` is 0.
+    pub fn new(
+        slab_id: Pubkey,
+        version_hash: [u8; 32],
+        oracle_id: Pubkey,
+        imr: u64,
+        mmr: u64,
+        maker_fee_cap: u64,
+        taker_fee_cap: u64,
+        latency_sla_ms: u64,
+        max_exposure: u128,
+        max_leverage: u8,
+        current_ts: u64,
+    ) -> Result<Self, ()> {
+        if max_leverage == 0 {
+            pinocchio::msg!("Error: max_leverage must be at least 1x");
+            return Err(());
+        }
+
+        Ok(Self {
+            slab_id,
+            version_hash,
+            oracle_id,
+            imr,
+            mmr,
+            maker_fee_cap,
+            taker_fee_cap,
+            latency_sla_ms,
+            max_exposure,
+            registered_ts: current_ts,
+            active: true,
+            use_twap: false,
+            max_leverage,
+            extra_oracle_count: 0,
+            limit_deviation_bps: DEFAULT_LIMIT_DEVIATION_BPS,
+            _limit_padding: [0; 2],
+            mm_tiers: DEFAULT_MM_TIERS,
+            max_move_bps: DEFAULT_MAX_MOVE_BPS,
+            last_settled_price: 0,
+            last_settled_slot: 0,
+            long_oi: 0,
+            short_oi: 0,
+            max_oi: 0,
+            trading_open_secs: 0,
+            trading_close_secs: 0,
+            global_funding_index: 0,
+            funding_rate_bps: 0,
+            last_funding_slot: 0,
+            extra_oracle_ids: [Pubkey::default(); MAX_EXTRA_ORACLES],
+        })
+    }
+
+    /// Feeds registered for the slab's median price (1, 3 or 5)
+    pub fn oracle_count(&self) -> usize {
+        1 + self.extra_oracle_count as usize
+    }
+
+    /// Whether `feed_id` is the primary oracle or one of the extra median feeds
+    pub fn is_registered_oracle(&self, feed_id: &Pubkey) -> bool {
+        &self.oracle_id == feed_id
+            || self.extra_oracle_ids[..self.extra_oracle_count as usize].contains(feed_id)
+    }
+
+    /// Band limit prices must fall within around the oracle (basis points)
+    ///
+    /// Entries written before the band existed read 0 and get the default.
+    pub fn limit_band_bps(&self) -> u64 {
+        if self.limit_deviation_bps == 0 {
+            DEFAULT_LIMIT_DEVIATION_BPS as u64
+        } else {
+            self.limit_deviation_bps as u64
+        }
+    }
+
+    /// Blended maintenance margin for a position across the size tiers
+    ///
+    /// Each band of `abs_qty` is charged its tier's ratio; the qty-weighted
+    /// ratio is then applied to the position's `notional`. Any size beyond the
+    /// last tier is charged at the last tier's ratio.
+    pub fn maintenance_margin(&self, abs_qty: u64, notional: u128) -> u128 {
+        if abs_qty == 0 {
+            return 0;
+        }
+
+        let mut weighted_bps: u128 = 0;
+        let mut prev_limit: u64 = 0;
+        for tier in self.mm_tiers.iter() {
+            if abs_qty <= prev_limit {
+                break;
+            }
+            let band_qty = abs_qty.min(tier.size_limit).saturating_sub(prev_limit);
+            weighted_bps += band_qty as u128 * tier.mmr_bps as u128;
+            prev_limit = tier.size_limit;
+        }
+        if abs_qty > prev_limit {
+            let last_bps = self.mm_tiers[MM_TIER_COUNT - 1].mmr_bps;
+            weighted_bps += (abs_qty - prev_limit) as u128 * last_bps as u128;
+        }
+
+        notional.saturating_mul(weighted_bps) / (abs_qty as u128 * 10_000)
+    }
+
+    /// Check an oracle reading against the circuit breaker
+    ///
+    /// Trips when `price` is more than `max_move_bps` away from the last
+    /// settled price and that settlement is within the breaker window. A
+    /// single-block print is rejected; a move that holds past the window is
+    /// accepted as the new reference.
+    pub fn circuit_breaker_tripped(&self, price: i64, current_slot: u64) -> bool {
+        if self.last_settled_price <= 0 || self.max_move_bps == 0 {
+            return false;
+        }
+        if current_slot.saturating_sub(self.last_settled_slot) > CIRCUIT_BREAKER_WINDOW_SLOTS {
+            return false;
+        }
+
+        let reference = self.last_settled_price as i128;
+        let move_bps = (price as i128 - reference).unsigned_abs() * 10_000 / reference as u128;
+        move_bps > self.max_move_bps as u128
+    }
+
+    /// Whether the daily trading window is open at `unix_ts`
+    ///
+    /// The window runs from trading_open_secs up to (not including)
+    /// trading_close_secs of each UTC day; a close earlier than the open
+    /// wraps past midnight. Equal bounds leave the market open around the
+    /// clock.
+    pub fn is_trading_open(&self, unix_ts: i64) -> bool {
+        let open = self.trading_open_secs as i64;
+        let close = self.trading_close_secs as i64;
+        if open == close {
+            return true;
+        }
+
+        let time_of_day = unix_ts.rem_euclid(SECONDS_PER_DAY);
+        if open < close {
+            time_of_day >= open && time_of_day < close
+        } else {
+            time_of_day >= open || time_of_day < close
+        }
+    }
+
+    /// Apply one account's exposure change to the slab's open interest
+    ///
+    /// Moving from `old_qty` to `new_qty` releases the old side and takes the
+    /// new one, so a flip moves interest across sides. Fails without changing
+    /// anything if a side that grows would end up above `max_oi`; reductions
+    /// always pass so positions can still be closed at the cap.
+    pub fn apply_open_interest(&mut self, old_qty: i64, new_qty: i64) -> Result<(), ()> {
+        let long = self
+            .long_oi
+            .saturating_sub(old_qty.max(0) as u64)
+            .saturating_add(new_qty.max(0) as u64);
+        let short = self
+            .short_oi
+            .saturating_sub(old_qty.min(0).unsigned_abs())
+            .saturating_add(new_qty.min(0).unsigned_abs());
+
+        if self.max_oi != 0
+            && ((long > self.long_oi && long > self.max_oi) || (short > self.short_oi && short > self.max_oi))
+        {
+            return Err(());
+        }
+
+        self.long_oi = long;
+        self.short_oi = short;
+        Ok(())
+    }
+
+    /// Accrue funding for every whole period since the last accrual
+    ///
+    /// Each elapsed period adds the rate from the current open interest and
+    /// premium to `global_funding_index`; leftover slots carry to the next
+    /// call. The first call only starts the clock.
+    pub fn accrue_funding(&mut self, mark_px: i64, oracle_px: i64, params: &FundingParams, current_slot: u64) {
+        if self.last_funding_slot == 0 || params.period_slots == 0 {
+            self.last_funding_slot = current_slot;
+            return;
+        }
+
+        let periods = current_slot.saturating_sub(self.last_funding_slot) / params.period_slots;
+        if periods == 0 {
+            return;
+        }
+
+        let rate = compute_funding_rate(self.long_oi, self.short_oi, mark_px, oracle_px, params);
+        self.global_funding_index = self
+            .global_funding_index
+            .saturating_add(rate as i128 * periods as i128);
+        self.funding_rate_bps = rate;
+        self.last_funding_slot += periods * params.period_slots;
+    }
+
+    /// Record the oracle price a settlement went through at
+    pub fn record_settlement(&mut self, price: i64, current_slot: u64) {
+        self.last_settled_price = price;
+        self.last_settled_slot = current_slot;
+    }
 }
 
 /// Slab registry account
@@ -41,12 +382,14 @@ pub struct SlabRegistry {
     pub router_id: Pubkey,
     /// Governance authority (can update registry)
     pub governance: Pubkey,
-    /// Number of registered slabs
+    /// Slots used in `slabs`, including tombstones; also the next index to assign
     pub slab_count: u16,
     /// Bump seed
     pub bump: u8,
+    /// Canonical bump of the router authority PDA (cached at init)
+    pub authority_bump: u8,
     /// Padding
-    pub _padding: [u8; 5],
+    pub _padding: [u8; 4],
 
     // Liquidation parameters (global)
     /// Initial margin ratio (basis points, e.g., 500 = 5%)
@@ -65,8 +408,8 @@ pub struct SlabRegistry {
     pub min_equity_to_quote: i128,
     /// Oracle price tolerance (basis points, e.g., 50 = 0.5%)
     pub oracle_tolerance_bps: u64,
-    /// Padding for alignment
-    pub _padding2: [u8; 8],
+    /// Ceiling on user-specified market order slippage (basis points, e.g., 500 = 5%)
+    pub max_slippage_bps: u64,
 
     // Insurance fund parameters and state
     /// Insurance parameters (configurable by governance)
@@ -88,8 +431,44 @@ pub struct SlabRegistry {
     /// Total deposits across all portfolios (used for warmup drain calculation)
     /// Updated on deposit/withdraw operations
     pub total_deposits: i128,
+    /// Maximum spread between oracle feeds for one instrument (basis points, e.g., 100 = 1%)
+    pub max_oracle_divergence_bps: u64,
+    /// Emergency halt bitflags (PAUSE_*), set by governance via SetPause
+    pub pause_flags: u64,
+    /// Funding rate parameters (configurable by governance)
+    pub funding_params: FundingParams,
+    /// LiquidatorAccess as u8 (0 = open, 1 = permissioned), set via SetLiquidators
+    pub liquidation_mode: u8,
+    /// Number of whitelisted liquidators
+    pub liquidator_count: u8,
+    /// Padding
+    pub _liquidator_padding: [u8; 6],
+    /// Keepers allowed to liquidate in permissioned mode
+    pub liquidators: [Pubkey; MAX_LIQUIDATORS],
+    /// Withdrawals of at least this many lamports must be requested first (0 = all instant)
+    pub withdraw_queue_threshold: u64,
+    /// Slots between RequestWithdraw and the matching Withdraw
+    pub withdraw_delay_slots: u64,
+    /// Largest principal one portfolio may hold (lamports, 0 = uncapped)
+    pub max_portfolio_deposit: u64,
+    /// Largest total_deposits across all portfolios (lamports, 0 = uncapped)
+    pub max_total_deposits: u64,
+    /// Minimum slots between a portfolio's trades (0 = unthrottled)
+    pub trade_cooldown_slots: u64,
+    /// Registry pages created by InitializeRegistryPage (slabs beyond MAX_SLABS)
+    pub page_count: u64,
+    /// Governance proposed by ProposeGovernance, live once it signs AcceptGovernance (default = none)
+    pub pending_governance: Pubkey,
+    /// Liquidation fills execute this far past the oracle, against the account (basis points)
+    pub liquidation_spread_bps: u64,
+    /// Number of active entries in `collateral`
+    pub collateral_count: u8,
     /// Padding for alignment
-    pub _padding3: [u8; 8],
+    pub _collateral_padding: [u8; 7],
+    /// Accepted collateral mints and their margin haircuts
+    pub collateral: [CollateralConfig; MAX_COLLATERAL_MINTS],
+    /// Most open exposures one portfolio may hold; opening past it fails with TooManyPositions
+    pub max_positions_per_portfolio: u64,
 
     /// Registered slabs
     pub slabs: [SlabEntry; MAX_SLABS],
@@ -107,7 +486,8 @@ impl SlabRegistry {
         self.governance = governance;
         self.slab_count = 0;
         self.bump = bump;
-        self._padding = [0; 5];
+        self.authority_bump = NO_CACHED_BUMP;
+        self._padding = [0; 4];
 
         // Initialize liquidation parameters with defaults
         self.imr = 500;  // 5% initial margin
@@ -118,7 +498,7 @@ impl SlabRegistry {
         self.router_cap_per_slab = 1_000_000_000;  // 1000 units max per slab
         self.min_equity_to_quote = 100_000_000;  // $100 minimum equity
         self.oracle_tolerance_bps = 50;  // 0.5% oracle tolerance
-        self._padding2 = [0; 8];
+        self.max_slippage_bps = 500;  // 5% slippage ceiling
 
         // Initialize insurance with defaults
         self.insurance_params = crate::state::insurance::InsuranceParams::default();
@@ -132,7 +512,25 @@ impl SlabRegistry {
         self.warmup_config = model_safety::adaptive_warmup::AdaptiveWarmupConfig::default();
         self.warmup_state = model_safety::adaptive_warmup::AdaptiveWarmupState::default();
         self.total_deposits = 0;
-        self._padding3 = [0; 8];
+        self.max_oracle_divergence_bps = 100;  // 1% max feed divergence
+        self.pause_flags = 0;
+        self.funding_params = FundingParams::default();
+        self.liquidation_mode = LiquidatorAccess::Open as u8;
+        self.liquidator_count = 0;
+        self._liquidator_padding = [0; 6];
+        self.liquidators = [Pubkey::default(); MAX_LIQUIDATORS];
+        self.withdraw_queue_threshold = 1_000_000_000_000;  // 1,000 SOL
+        self.withdraw_delay_slots = 150;  // ~1 minute
+        self.max_portfolio_deposit = 0;  // Uncapped until governance sets launch caps
+        self.max_total_deposits = 0;
+        self.trade_cooldown_slots = 0;  // Governance opts in via SetTradeCooldown
+        self.page_count = 0;
+        self.pending_governance = Pubkey::default();
+        self.liquidation_spread_bps = DEFAULT_LIQUIDATION_SPREAD_BPS;
+        self.collateral_count = 0;
+        self._collateral_padding = [0; 7];
+        self.collateral = [CollateralConfig::default(); MAX_COLLATERAL_MINTS];
+        self.max_positions_per_portfolio = DEFAULT_MAX_POSITIONS_PER_PORTFOLIO;
 
         // Zero out the slabs array using ptr::write_bytes (efficient and stack-safe)
         unsafe {
@@ -153,7 +551,8 @@ impl SlabRegistry {
             governance,
             slab_count: 0,
             bump,
-            _padding: [0; 5],
+            authority_bump: NO_CACHED_BUMP,
+            _padding: [0; 4],
             imr: 500,
             mmr: 250,
             liq_band_bps: 200,
@@ -162,7 +561,7 @@ impl SlabRegistry {
             router_cap_per_slab: 1_000_000_000,
             min_equity_to_quote: 100_000_000,
             oracle_tolerance_bps: 50,
-            _padding2: [0; 8],
+            max_slippage_bps: 500,
             insurance_params: crate::state::insurance::InsuranceParams::default(),
             insurance_state: crate::state::insurance::InsuranceState::default(),
             pnl_vesting_params: crate::state::pnl_vesting::PnlVestingParams::default(),
@@ -170,7 +569,25 @@ impl SlabRegistry {
             warmup_config: model_safety::adaptive_warmup::AdaptiveWarmupConfig::default(),
             warmup_state: model_safety::adaptive_warmup::AdaptiveWarmupState::default(),
             total_deposits: 0,
-            _padding3: [0; 8],
+            max_oracle_divergence_bps: 100,
+            pause_flags: 0,
+            funding_params: FundingParams::default(),
+            liquidation_mode: LiquidatorAccess::Open as u8,
+            liquidator_count: 0,
+            _liquidator_padding: [0; 6],
+            liquidators: [Pubkey::default(); MAX_LIQUIDATORS],
+            withdraw_queue_threshold: 1_000_000_000_000,
+            withdraw_delay_slots: 150,
+            max_portfolio_deposit: 0,
+            max_total_deposits: 0,
+            trade_cooldown_slots: 0,
+            page_count: 0,
+            pending_governance: Pubkey::default(),
+            liquidation_spread_bps: DEFAULT_LIQUIDATION_SPREAD_BPS,
+            collateral_count: 0,
+            _collateral_padding: [0; 7],
+            collateral: [CollateralConfig::default(); MAX_COLLATERAL_MINTS],
+            max_positions_per_portfolio: DEFAULT_MAX_POSITIONS_PER_PORTFOLIO,
             slabs: [SlabEntry {
                 slab_id: Pubkey::default(),
                 version_hash: [0; 32],
@@ -183,7 +600,24 @@ impl SlabRegistry {
                 max_exposure: 0,
                 registered_ts: 0,
                 active: false,
-                _padding: [0; 7],
+                use_twap: false,
+                max_leverage: 0,
+                extra_oracle_count: 0,
+                limit_deviation_bps: 0,
+                _limit_padding: [0; 2],
+                mm_tiers: [MmTier::default(); MM_TIER_COUNT],
+                max_move_bps: 0,
+                last_settled_price: 0,
+                last_settled_slot: 0,
+                long_oi: 0,
+                short_oi: 0,
+                max_oi: 0,
+                trading_open_secs: 0,
+            trading_close_secs: 0,
+                global_funding_index: 0,
+                funding_rate_bps: 0,
+                last_funding_slot: 0,
+                extra_oracle_ids: [Pubkey::default(); MAX_EXTRA_ORACLES],
             }; MAX_SLABS],
         }
     }
@@ -200,8 +634,32 @@ impl SlabRegistry {
         taker_fee_cap: u64,
         latency_sla_ms: u64,
         max_exposure: u128,
+        max_leverage: u8,
         current_ts: u64,
     ) -> Result<u16, ()> {
+        let entry = SlabEntry::new(
+            slab_id,
+            version_hash,
+            oracle_id,
+            imr,
+            mmr,
+            maker_fee_cap,
+            taker_fee_cap,
+            latency_sla_ms,
+            max_exposure,
+            max_leverage,
+            current_ts,
+        )?;
+        self.push_slab(entry)
+    }
+
+    /// Append an entry to the registry's own slab array
+    ///
+    /// Indices are assigned in order and never reused: PositionDetails PDAs
+    /// and portfolio exposures are keyed on them, so a tombstoned slot stays
+    /// taken. Fails once all MAX_SLABS are taken (further slabs go to
+    /// registry pages) or if the slab is already live.
+    pub fn push_slab(&mut self, entry: SlabEntry) -> Result<u16, ()> {
         use pinocchio::msg;
 
         if (self.slab_count as usize) >= MAX_SLABS {
@@ -209,23 +667,15 @@ impl SlabRegistry {
             return Err(());
         }
 
+        if self.find_slab(&entry.slab_id).is_some() {
+            msg!("Error: Slab is already registered");
+            return Err(());
+        }
+
         let idx = self.slab_count;
         msg!("Registry: Registering slab");
 
-        self.slabs[idx as usize] = SlabEntry {
-            slab_id,
-            version_hash,
-            oracle_id,
-            imr,
-            mmr,
-            maker_fee_cap,
-            taker_fee_cap,
-            latency_sla_ms,
-            max_exposure,
-            registered_ts: current_ts,
-            active: true,
-            _padding: [0; 7],
-        };
+        self.slabs[idx as usize] = entry;
         self.slab_count += 1;
 
         msg!("Registry: Slab registered successfully");
@@ -266,27 +716,182 @@ impl SlabRegistry {
         }
     }
 
-    /// Deactivate a slab
-    pub fn deactivate_slab(&mut self, slab_id: &Pubkey) -> Result<(), ()> {
+    /// Tombstone a slab, returning its retired index
+    ///
+    /// The slot keeps its entry (marked inactive) and is never compacted or
+    /// reassigned, so find_slab skips it while existing positions' slab_idx
+    /// still names the slab they were opened on.
+    pub fn tombstone_slab(&mut self, slab_id: &Pubkey) -> Result<u16, ()> {
+        let (idx, _) = self.find_slab(slab_id).ok_or(())?;
+        self.slabs[idx as usize].active = false;
+        Ok(idx)
+    }
+
+    /// Update slab risk params
+    pub fn update_risk_params(&mut self, slab_id: &Pubkey, imr: u64, mmr: u64) -> Result<(), ()> {
         if let Some((idx, _)) = self.find_slab(slab_id) {
-            self.slabs[idx as usize].active = false;
+            self.slabs[idx as usize].imr = imr;
+            self.slabs[idx as usize].mmr = mmr;
             Ok(())
         } else {
             Err(())
         }
     }
 
-    /// Update slab risk params
-    pub fn update_risk_params(&mut self, slab_id: &Pubkey, imr: u64, mmr: u64) -> Result<(), ()> {
+    /// Set a slab's maximum order leverage (governance)
+    pub fn set_max_leverage(&mut self, slab_id: &Pubkey, max_leverage: u8) -> Result<(), ()> {
+        if max_leverage == 0 {
+            return Err(());
+        }
         if let Some((idx, _)) = self.find_slab(slab_id) {
-            self.slabs[idx as usize].imr = imr;
-            self.slabs[idx as usize].mmr = mmr;
+            self.slabs[idx as usize].max_leverage = max_leverage;
+            Ok(())
+        } else {
+            Err(())
+        }
+    }
+
+    /// Set a slab's maintenance margin tiers (governance)
+    ///
+    /// Size limits must be strictly ascending and ratios non-decreasing, so a
+    /// larger position never gets a lower blended ratio.
+    pub fn set_mm_tiers(&mut self, slab_id: &Pubkey, tiers: [MmTier; MM_TIER_COUNT]) -> Result<(), ()> {
+        for pair in tiers.windows(2) {
+            if pair[1].size_limit <= pair[0].size_limit || pair[1].mmr_bps < pair[0].mmr_bps {
+                return Err(());
+            }
+        }
+        if let Some((idx, _)) = self.find_slab(slab_id) {
+            self.slabs[idx as usize].mm_tiers = tiers;
+            Ok(())
+        } else {
+            Err(())
+        }
+    }
+
+    /// Set a slab's circuit breaker threshold (governance, 0 disables)
+    pub fn set_max_move_bps(&mut self, slab_id: &Pubkey, max_move_bps: u64) -> Result<(), ()> {
+        if let Some((idx, _)) = self.find_slab(slab_id) {
+            self.slabs[idx as usize].max_move_bps = max_move_bps;
+            Ok(())
+        } else {
+            Err(())
+        }
+    }
+
+    /// Set a slab's limit price band around the oracle (governance, 0 = default)
+    ///
+    /// Fails for bands wider than 100%.
+    pub fn set_limit_deviation_bps(&mut self, slab_id: &Pubkey, limit_deviation_bps: u16) -> Result<(), ()> {
+        if limit_deviation_bps > 10_000 {
+            return Err(());
+        }
+        if let Some((idx, _)) = self.find_slab(slab_id) {
+            self.slabs[idx as usize].limit_deviation_bps = limit_deviation_bps;
             Ok(())
         } else {
             Err(())
         }
     }
 
+    /// Set a slab's open interest cap per side (governance, 0 = uncapped)
+    ///
+    /// Lowering it below the current interest only blocks further opens.
+    pub fn set_max_open_interest(&mut self, slab_id: &Pubkey, max_oi: u64) -> Result<(), ()> {
+        if let Some((idx, _)) = self.find_slab(slab_id) {
+            self.slabs[idx as usize].max_oi = max_oi;
+            Ok(())
+        } else {
+            Err(())
+        }
+    }
+
+    /// Set a slab's daily trading window (governance, equal bounds = always open)
+    ///
+    /// Both bounds are seconds after 00:00 UTC and must be within the day.
+    pub fn set_trading_hours(&mut self, slab_id: &Pubkey, open_secs: u32, close_secs: u32) -> Result<(), ()> {
+        if open_secs as i64 >= SECONDS_PER_DAY || close_secs as i64 >= SECONDS_PER_DAY {
+            return Err(());
+        }
+        if let Some((idx, _)) = self.find_slab(slab_id) {
+            let entry = &mut self.slabs[idx as usize];
+            entry.trading_open_secs = open_secs;
+            entry.trading_close_secs = close_secs;
+            Ok(())
+        } else {
+            Err(())
+        }
+    }
+
+    /// Clear a tripped circuit breaker (governance)
+    ///
+    /// Forgets the last settled price, so the next oracle reading becomes
+    /// the new reference.
+    pub fn reset_circuit_breaker(&mut self, slab_id: &Pubkey) -> Result<(), ()> {
+        if let Some((idx, _)) = self.find_slab(slab_id) {
+            self.slabs[idx as usize].record_settlement(0, 0);
+            Ok(())
+        } else {
+            Err(())
+        }
+    }
+
+    /// Whether `signer` may liquidate under the registry's liquidation mode
+    ///
+    /// Open mode admits anyone; permissioned mode (or an unknown stored
+    /// mode) only the whitelisted liquidators.
+    pub fn is_authorized_liquidator(&self, signer: &Pubkey) -> bool {
+        match LiquidatorAccess::from_u8(self.liquidation_mode) {
+            Some(LiquidatorAccess::Open) => true,
+            _ => {
+                let count = (self.liquidator_count as usize).min(MAX_LIQUIDATORS);
+                self.liquidators[..count].contains(signer)
+            }
+        }
+    }
+
+    /// Check whether any of the given PAUSE_* flags is set
+    pub fn is_paused(&self, flags: u64) -> bool {
+        self.pause_flags & flags != 0
+    }
+
+    /// Select spot (false) or TWAP (true) pricing for a slab
+    pub fn set_price_source(&mut self, slab_id: &Pubkey, use_twap: bool) -> Result<(), ()> {
+        if let Some((idx, _)) = self.find_slab(slab_id) {
+            self.slabs[idx as usize].use_twap = use_twap;
+            Ok(())
+        } else {
+            Err(())
+        }
+    }
+
+    /// Register the extra median feeds for a slab (governance)
+    ///
+    /// Together with oracle_id the slab is priced from 1, 3 or 5 feeds, so
+    /// `extra` holds 0, 2 or 4 ids. Fails on any other count, on a repeated
+    /// feed, or for an unregistered slab.
+    pub fn set_extra_oracles(&mut self, slab_id: &Pubkey, extra: &[Pubkey]) -> Result<(), ()> {
+        if !matches!(extra.len(), 0 | 2 | 4) {
+            return Err(());
+        }
+        let idx = match self.find_slab(slab_id) {
+            Some((idx, _)) => idx as usize,
+            None => return Err(()),
+        };
+
+        let entry = &mut self.slabs[idx];
+        for (i, feed_id) in extra.iter().enumerate() {
+            if feed_id == &entry.oracle_id || extra[..i].contains(feed_id) {
+                return Err(());
+            }
+        }
+
+        entry.extra_oracle_ids = [Pubkey::default(); MAX_EXTRA_ORACLES];
+        entry.extra_oracle_ids[..extra.len()].copy_from_slice(extra);
+        entry.extra_oracle_count = extra.len() as u8;
+        Ok(())
+    }
+
     /// Update global liquidation parameters (governance only)
     pub fn update_liquidation_params(
         &mut self,
@@ -307,6 +912,63 @@ impl SlabRegistry {
         self.oracle_tolerance_bps = oracle_tolerance_bps;
     }
 
+    /// Set the liquidation fill spread (governance)
+    ///
+    /// Fails above 100%. Liquidation fills never go past the liquidation
+    /// band, so a spread wider than the band is capped to it.
+    pub fn set_liquidation_spread_bps(&mut self, spread_bps: u64) -> Result<(), ()> {
+        if spread_bps > 10_000 {
+            return Err(());
+        }
+        self.liquidation_spread_bps = spread_bps;
+        Ok(())
+    }
+
+    /// Set the open position cap per portfolio (governance)
+    ///
+    /// Fails at 0 or above what one liquidation can close.
+    pub fn set_max_positions_per_portfolio(&mut self, max_positions: u64) -> Result<(), ()> {
+        if max_positions == 0 || max_positions > DEFAULT_MAX_POSITIONS_PER_PORTFOLIO {
+            return Err(());
+        }
+        self.max_positions_per_portfolio = max_positions;
+        Ok(())
+    }
+
+    /// Set the margin haircut for a collateral mint (governance)
+    ///
+    /// Adds the mint if it isn't listed yet. Fails above 100% or when the
+    /// table is full.
+    pub fn set_collateral_haircut(&mut self, mint: &Pubkey, haircut_bps: u64) -> Result<(), ()> {
+        if haircut_bps > 10_000 {
+            return Err(());
+        }
+        let count = self.collateral_count as usize;
+        if let Some(entry) = self.collateral[..count].iter_mut().find(|c| &c.mint == mint) {
+            entry.haircut_bps = haircut_bps;
+            return Ok(());
+        }
+        if count >= MAX_COLLATERAL_MINTS {
+            return Err(());
+        }
+        self.collateral[count] = CollateralConfig { mint: *mint, haircut_bps };
+        self.collateral_count += 1;
+        Ok(())
+    }
+
+    /// Margin haircut for a collateral mint (basis points, 0 if unlisted)
+    pub fn collateral_haircut_bps(&self, mint: &Pubkey) -> u64 {
+        self.collateral[..self.collateral_count as usize]
+            .iter()
+            .find(|c| &c.mint == mint)
+            .map_or(0, |c| c.haircut_bps)
+    }
+
+    /// Whether a withdrawal of `amount` lamports needs a RequestWithdraw first
+    pub fn requires_withdraw_request(&self, amount: u64) -> bool {
+        self.withdraw_queue_threshold != 0 && amount >= self.withdraw_queue_threshold
+    }
+
     /// Track deposit (increment total_deposits)
     pub fn track_deposit(&mut self, amount: i128) {
         self.total_deposits = self.total_deposits.saturating_add(amount);
@@ -400,6 +1062,7 @@ mod tests {
                 20,   // 0.2% taker fee cap
                 1000, // 1s latency SLA
                 1_000_000,
+                10,   // 10x max leverage
                 12345,
             )
             .unwrap();
@@ -414,7 +1077,218 @@ mod tests {
         assert!(registry.validate_version(&slab_id, &version_hash));
         assert!(!registry.validate_version(&slab_id, &[0; 32]));
 
-        registry.deactivate_slab(&slab_id).unwrap();
+        registry.tombstone_slab(&slab_id).unwrap();
         assert!(registry.find_slab(&slab_id).is_none());
     }
+
+    #[test]
+    fn test_tombstoned_index_never_reused() {
+        let mut registry = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+        let register = |registry: &mut SlabRegistry, id: u8| {
+            registry.register_slab([id; 32], [0; 32], Pubkey::default(), 500, 250, 10, 20, 1000, 1_000_000, 10, 0)
+        };
+
+        assert_eq!(register(&mut registry, 1), Ok(0));
+        assert_eq!(register(&mut registry, 2), Ok(1));
+        assert_eq!(register(&mut registry, 3), Ok(2));
+
+        assert_eq!(registry.tombstone_slab(&[2; 32]), Ok(1));
+        assert!(registry.find_slab(&[2; 32]).is_none());
+        assert!(registry.tombstone_slab(&[2; 32]).is_err());
+
+        // The fourth slab takes the next fresh index, not the tombstoned one
+        assert_eq!(register(&mut registry, 4), Ok(3));
+
+        // Neighbours keep their indices, and the old slot still names slab 2
+        assert_eq!(registry.find_slab(&[3; 32]).map(|(idx, _)| idx), Some(2));
+        assert_eq!(registry.slabs[1].slab_id, [2; 32]);
+        assert!(!registry.slabs[1].active);
+
+        // Re-listing a retired slab gets a new index; a live one can't be listed twice
+        assert_eq!(register(&mut registry, 2), Ok(4));
+        assert!(register(&mut registry, 4).is_err());
+    }
+
+    #[test]
+    fn test_price_source_flag() {
+        let mut registry = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+        let slab_id = Pubkey::from([1; 32]);
+
+        registry
+            .register_slab(slab_id, [0; 32], Pubkey::default(), 500, 250, 10, 20, 1000, 1_000_000, 10, 0)
+            .unwrap();
+
+        // Spot pricing by default
+        assert!(!registry.find_slab(&slab_id).unwrap().1.use_twap);
+
+        registry.set_price_source(&slab_id, true).unwrap();
+        assert!(registry.find_slab(&slab_id).unwrap().1.use_twap);
+
+        assert!(registry.set_price_source(&Pubkey::from([2; 32]), true).is_err());
+    }
+
+    #[test]
+    fn test_extra_oracles() {
+        let mut registry = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+        let slab_id = Pubkey::from([1; 32]);
+        let primary = Pubkey::from([11; 32]);
+
+        registry
+            .register_slab(slab_id, [0; 32], primary, 500, 250, 10, 20, 1000, 1_000_000, 10, 0)
+            .unwrap();
+
+        // Priced from the primary alone by default
+        let entry = registry.find_slab(&slab_id).unwrap().1;
+        assert_eq!(entry.oracle_count(), 1);
+        assert!(entry.is_registered_oracle(&primary));
+        assert!(!entry.is_registered_oracle(&Pubkey::default()));
+
+        let extra = [[12; 32], [13; 32], [14; 32], [15; 32]];
+        registry.set_extra_oracles(&slab_id, &extra).unwrap();
+        let entry = registry.find_slab(&slab_id).unwrap().1;
+        assert_eq!(entry.oracle_count(), 5);
+        assert!(extra.iter().all(|feed| entry.is_registered_oracle(feed)));
+
+        // Shrinking to 3 feeds unregisters the dropped ones
+        registry.set_extra_oracles(&slab_id, &extra[..2]).unwrap();
+        let entry = registry.find_slab(&slab_id).unwrap().1;
+        assert_eq!(entry.oracle_count(), 3);
+        assert!(!entry.is_registered_oracle(&extra[2]));
+
+        // Even feed counts, repeated feeds and unknown slabs are rejected
+        assert!(registry.set_extra_oracles(&slab_id, &extra[..1]).is_err());
+        assert!(registry.set_extra_oracles(&slab_id, &[[12; 32], [12; 32]]).is_err());
+        assert!(registry.set_extra_oracles(&slab_id, &[primary, [12; 32]]).is_err());
+        assert!(registry.set_extra_oracles(&Pubkey::from([2; 32]), &extra[..2]).is_err());
+        assert_eq!(registry.find_slab(&slab_id).unwrap().1.oracle_count(), 3);
+    }
+
+    #[test]
+    fn test_max_leverage() {
+        let mut registry = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+        let slab_id = Pubkey::from([1; 32]);
+
+        // A zero cap would make the market untradeable
+        assert!(registry
+            .register_slab(slab_id, [0; 32], Pubkey::default(), 500, 250, 10, 20, 1000, 1_000_000, 0, 0)
+            .is_err());
+
+        registry
+            .register_slab(slab_id, [0; 32], Pubkey::default(), 500, 250, 10, 20, 1000, 1_000_000, 3, 0)
+            .unwrap();
+        assert_eq!(registry.find_slab(&slab_id).unwrap().1.max_leverage, 3);
+
+        // Governance raises the cap
+        registry.set_max_leverage(&slab_id, 10).unwrap();
+        assert_eq!(registry.find_slab(&slab_id).unwrap().1.max_leverage, 10);
+
+        assert!(registry.set_max_leverage(&slab_id, 0).is_err());
+        assert!(registry.set_max_leverage(&Pubkey::from([2; 32]), 5).is_err());
+    }
+
+    #[test]
+    fn test_tiered_maintenance_margin() {
+        let mut registry = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+        let slab_id = Pubkey::from([1; 32]);
+        registry
+            .register_slab(slab_id, [0; 32], Pubkey::default(), 500, 250, 10, 20, 1000, 1_000_000, 10, 0)
+            .unwrap();
+        let entry = registry.find_slab(&slab_id).unwrap().1;
+
+        // 1 lamport of notional per 1e-6 unit keeps the ratios easy to read
+        let small_qty = 5_000_000; // 5 units, all in the 5% band
+        let large_qty = 100_000_000; // 100 units: 10 @ 5%, 40 @ 7%, 50 @ 10%
+        let small_mm = entry.maintenance_margin(small_qty, small_qty as u128);
+        let large_mm = entry.maintenance_margin(large_qty, large_qty as u128);
+
+        assert_eq!(small_mm, 250_000); // 5%
+        assert_eq!(large_mm, 500_000 + 2_800_000 + 5_000_000); // 8.3% blended
+
+        // Effective ratio (bps) grows with size
+        let small_ratio = small_mm * 10_000 / small_qty as u128;
+        let large_ratio = large_mm * 10_000 / large_qty as u128;
+        assert_eq!(small_ratio, 500);
+        assert_eq!(large_ratio, 830);
+        assert!(large_ratio > small_ratio);
+
+        assert_eq!(entry.maintenance_margin(0, 1_000), 0);
+    }
+
+    #[test]
+    fn test_mm_tiers_must_ascend() {
+        let mut registry = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+        let slab_id = Pubkey::from([1; 32]);
+        registry
+            .register_slab(slab_id, [0; 32], Pubkey::default(), 500, 250, 10, 20, 1000, 1_000_000, 10, 0)
+            .unwrap();
+
+        let mut tiers = DEFAULT_MM_TIERS;
+        tiers[1].mmr_bps = 400; // Lower ratio for a larger band
+        assert!(registry.set_mm_tiers(&slab_id, tiers).is_err());
+
+        let mut tiers = DEFAULT_MM_TIERS;
+        tiers[1].size_limit = tiers[0].size_limit;
+        assert!(registry.set_mm_tiers(&slab_id, tiers).is_err());
+
+        let mut tiers = DEFAULT_MM_TIERS;
+        tiers[2].mmr_bps = 1500;
+        registry.set_mm_tiers(&slab_id, tiers).unwrap();
+        assert_eq!(registry.find_slab(&slab_id).unwrap().1.mm_tiers, tiers);
+    }
+
+    #[test]
+    fn test_collateral_haircuts() {
+        let mut registry = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+        assert_eq!(registry.collateral_haircut_bps(&NATIVE_SOL_MINT), 0);
+
+        registry.set_collateral_haircut(&NATIVE_SOL_MINT, 1_000).unwrap();
+        registry.set_collateral_haircut(&NATIVE_SOL_MINT, 1_500).unwrap();
+        assert_eq!(registry.collateral_haircut_bps(&NATIVE_SOL_MINT), 1_500);
+        assert_eq!(registry.collateral_count, 1);
+
+        assert!(registry.set_collateral_haircut(&[9; 32], 10_001).is_err());
+        for i in 1..MAX_COLLATERAL_MINTS {
+            registry.set_collateral_haircut(&[i as u8; 32], 500).unwrap();
+        }
+        assert!(registry.set_collateral_haircut(&[9; 32], 500).is_err());
+    }
+
+    #[test]
+    fn test_max_positions_per_portfolio_bounds() {
+        let mut registry = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+        assert_eq!(registry.max_positions_per_portfolio, DEFAULT_MAX_POSITIONS_PER_PORTFOLIO);
+
+        registry.set_max_positions_per_portfolio(4).unwrap();
+        assert_eq!(registry.max_positions_per_portfolio, 4);
+        assert!(registry.set_max_positions_per_portfolio(0).is_err());
+        assert!(registry.set_max_positions_per_portfolio(DEFAULT_MAX_POSITIONS_PER_PORTFOLIO + 1).is_err());
+        assert_eq!(registry.max_positions_per_portfolio, 4);
+    }
+
+    #[test]
+    fn test_funding_accrues_per_period() {
+        let mut registry = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+        let slab_id = Pubkey::from([1; 32]);
+        registry
+            .register_slab(slab_id, [0; 32], Pubkey::default(), 500, 250, 10, 20, 1000, 1_000_000, 10, 0)
+            .unwrap();
+        let params = registry.funding_params;
+        let entry = &mut registry.slabs[0];
+        entry.long_oi = 9_000_000;
+        entry.short_oi = 1_000_000;
+
+        // First touch starts the clock
+        entry.accrue_funding(100, 100, &params, 1_000);
+        assert_eq!(entry.global_funding_index, 0);
+
+        // Partial period accrues nothing
+        entry.accrue_funding(100, 100, &params, 1_000 + params.period_slots - 1);
+        assert_eq!(entry.global_funding_index, 0);
+
+        // Two and a half periods: two at 8 bps, the half carries over
+        entry.accrue_funding(100, 100, &params, 1_000 + params.period_slots * 5 / 2);
+        assert_eq!(entry.global_funding_index, 16);
+        assert_eq!(entry.funding_rate_bps, 8);
+        assert_eq!(entry.last_funding_slot, 1_000 + params.period_slots * 2);
+    }
 }
@@ -0,0 +1,245 @@
+//! Registry pages - slab entries beyond the registry's own MAX_SLABS
+//!
+//! SlabRegistry holds the first MAX_SLABS slabs inline (page 0). Each
+//! further page is a PDA ["registry_page", page_index] holding another
+//! MAX_SLABS entries, created in order by governance via
+//! InitializeRegistryPage. A slab's global index is
+//! `page_index * MAX_SLABS + local index`, so indices on page 0 are
+//! unchanged and stay valid in portfolios and PositionDetails seeds.
+//!
+//! SlabDirectory searches the registry and then each page. The trade path
+//! still resolves slabs through SlabRegistry alone, so slabs on later pages
+//! can be registered and looked up but not yet traded.
+
+use crate::state::{SlabEntry, SlabRegistry};
+use percolator_common::MAX_SLABS;
+
+/// Most registry pages beyond the registry itself
+pub const MAX_REGISTRY_PAGES: usize = 15;
+
+/// Magic bytes identifying an initialized registry page
+pub const REGISTRY_PAGE_MAGIC: &[u8; 8] = b"BARTRPAG";
+
+/// One page of slab entries
+#[repr(C)]
+pub struct RegistryPage {
+    /// REGISTRY_PAGE_MAGIC once initialized
+    pub magic: u64,
+    /// Page number (1-based; page 0 is the registry's own array)
+    pub page_index: u16,
+    /// Slots used on this page, including tombstones
+    pub slab_count: u16,
+    /// PDA bump
+    pub bump: u8,
+    /// Padding
+    pub _padding: [u8; 3],
+    /// Registered slabs
+    pub slabs: [SlabEntry; MAX_SLABS],
+}
+
+impl RegistryPage {
+    pub const LEN: usize = core::mem::size_of::<Self>();
+
+    /// Initialize page in-place (avoids stack allocation)
+    pub fn initialize_in_place(&mut self, page_index: u16, bump: u8) {
+        self.magic = u64::from_le_bytes(*REGISTRY_PAGE_MAGIC);
+        self.page_index = page_index;
+        self.slab_count = 0;
+        self.bump = bump;
+        self._padding = [0; 3];
+        unsafe {
+            core::ptr::write_bytes(self.slabs.as_mut_ptr(), 0, MAX_SLABS);
+        }
+    }
+
+    /// Whether the page has been initialized
+    pub fn is_initialized(&self) -> bool {
+        self.magic == u64::from_le_bytes(*REGISTRY_PAGE_MAGIC)
+    }
+
+    /// Global index of the page's first slot
+    pub fn base_index(&self) -> u16 {
+        self.page_index * MAX_SLABS as u16
+    }
+
+    /// Append an entry, returning its global slab index
+    pub fn push_slab(&mut self, entry: SlabEntry) -> Result<u16, ()> {
+        if (self.slab_count as usize) >= MAX_SLABS {
+            return Err(());
+        }
+        let local = self.slab_count;
+        self.slabs[local as usize] = entry;
+        self.slab_count += 1;
+        Ok(self.base_index() + local)
+    }
+
+    /// Find an active slab on this page by ID, with its global index
+    pub fn find_slab(&self, slab_id: &pinocchio::pubkey::Pubkey) -> Option<(u16, &SlabEntry)> {
+        self.slabs[..self.slab_count as usize]
+            .iter()
+            .enumerate()
+            .find(|(_, entry)| entry.active && &entry.slab_id == slab_id)
+            .map(|(i, entry)| (self.base_index() + i as u16, entry))
+    }
+}
+
+/// The registry plus its pages, searched as one slab list
+pub struct SlabDirectory<'a> {
+    registry: &'a SlabRegistry,
+    pages: &'a [&'a RegistryPage],
+}
+
+impl<'a> SlabDirectory<'a> {
+    /// Pair the registry with its pages
+    ///
+    /// Fails unless `pages` are initialized and in order (page 1, 2, ...),
+    /// with no more than the registry has created.
+    pub fn new(registry: &'a SlabRegistry, pages: &'a [&'a RegistryPage]) -> Result<Self, ()> {
+        check_pages(registry, pages.iter().map(|page| (page.is_initialized(), page.page_index)))?;
+        Ok(Self { registry, pages })
+    }
+
+    /// Find an active slab by ID across the registry and every page
+    pub fn find_slab(&self, slab_id: &pinocchio::pubkey::Pubkey) -> Option<(u16, &'a SlabEntry)> {
+        let registry: &'a SlabRegistry = self.registry;
+        registry
+            .find_slab(slab_id)
+            .or_else(|| self.pages.iter().find_map(|page| page.find_slab(slab_id)))
+    }
+
+    /// Entry at a global slab index
+    pub fn entry(&self, slab_idx: u16) -> Option<&'a SlabEntry> {
+        let page = slab_idx as usize / MAX_SLABS;
+        let local = slab_idx as usize % MAX_SLABS;
+        if page == 0 {
+            let registry: &'a SlabRegistry = self.registry;
+            return (local < registry.slab_count as usize).then(|| &registry.slabs[local]);
+        }
+        let page: &'a RegistryPage = self.pages.get(page - 1)?;
+        (local < page.slab_count as usize).then(|| &page.slabs[local])
+    }
+}
+
+/// Register a slab in the first place with room: the registry, then each page
+///
+/// As on the registry, indices are never reused and a live slab can't be
+/// registered twice. `pages` must satisfy the same ordering as
+/// SlabDirectory::new.
+pub fn register_slab_paged(
+    registry: &mut SlabRegistry,
+    pages: &mut [&mut RegistryPage],
+    entry: SlabEntry,
+) -> Result<u16, ()> {
+    check_pages(registry, pages.iter().map(|page| (page.is_initialized(), page.page_index)))?;
+
+    if registry.find_slab(&entry.slab_id).is_some()
+        || pages.iter().any(|page| page.find_slab(&entry.slab_id).is_some())
+    {
+        return Err(());
+    }
+
+    if (registry.slab_count as usize) < MAX_SLABS {
+        return registry.push_slab(entry);
+    }
+    pages
+        .iter_mut()
+        .find(|page| (page.slab_count as usize) < MAX_SLABS)
+        .ok_or(())?
+        .push_slab(entry)
+}
+
+fn check_pages(registry: &SlabRegistry, pages: impl ExactSizeIterator<Item = (bool, u16)>) -> Result<(), ()> {
+    if pages.len() as u64 > registry.page_count {
+        return Err(());
+    }
+    for (i, (initialized, page_index)) in pages.enumerate() {
+        if !initialized || page_index as usize != i + 1 {
+            return Err(());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pinocchio::pubkey::Pubkey;
+
+    fn entry(id: u8) -> SlabEntry {
+        SlabEntry::new([id; 32], [0; 32], Pubkey::default(), 500, 250, 10, 20, 1000, u128::MAX, 10, 0).unwrap()
+    }
+
+    fn page(page_index: u16) -> Box<RegistryPage> {
+        let mut page: Box<RegistryPage> = Box::new(unsafe { core::mem::zeroed() });
+        page.initialize_in_place(page_index, 255);
+        page
+    }
+
+    #[test]
+    fn test_register_past_ceiling_resolves_on_second_page() {
+        let mut registry = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+        registry.page_count = 1;
+        let mut page1 = page(1);
+
+        // The registry's own array fills first
+        for id in 0..MAX_SLABS as u8 {
+            let idx = register_slab_paged(&mut registry, &mut [&mut *page1], entry(id + 1)).unwrap();
+            assert_eq!(idx, id as u16);
+        }
+        assert!(registry.register_slab([99; 32], [0; 32], Pubkey::default(), 500, 250, 10, 20, 1000, 0, 10, 0).is_err());
+
+        // The next slab spills onto page 1
+        let overflow = [200; 32];
+        let idx = register_slab_paged(&mut registry, &mut [&mut *page1], entry(200)).unwrap();
+        assert_eq!(idx, MAX_SLABS as u16);
+        assert_eq!(page1.slab_count, 1);
+        assert!(registry.find_slab(&overflow).is_none());
+        assert!(register_slab_paged(&mut registry, &mut [&mut *page1], entry(200)).is_err());
+
+        let pages = [&*page1];
+        let directory = SlabDirectory::new(&registry, &pages).unwrap();
+        let (found_idx, found) = directory.find_slab(&overflow).unwrap();
+        assert_eq!(found_idx, MAX_SLABS as u16);
+        assert_eq!(found.slab_id, overflow);
+        assert_eq!(directory.entry(found_idx).unwrap().slab_id, overflow);
+
+        // Page 0 slabs keep resolving from the registry itself
+        assert_eq!(directory.find_slab(&[3; 32]).map(|(idx, _)| idx), Some(2));
+        assert!(directory.entry(MAX_SLABS as u16 + 1).is_none());
+    }
+
+    #[test]
+    fn test_pages_must_be_created_and_in_order() {
+        let mut registry = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+        let page1 = page(1);
+        let page2 = page(2);
+
+        // Not yet created by the registry
+        assert!(SlabDirectory::new(&registry, &[&*page1]).is_err());
+
+        registry.page_count = 2;
+        assert!(SlabDirectory::new(&registry, &[&*page1, &*page2]).is_ok());
+        assert!(SlabDirectory::new(&registry, &[&*page2, &*page1]).is_err());
+
+        let blank: Box<RegistryPage> = Box::new(unsafe { core::mem::zeroed() });
+        assert!(SlabDirectory::new(&registry, &[&*blank]).is_err());
+    }
+
+    #[test]
+    fn test_all_pages_full() {
+        let mut registry = SlabRegistry::new(Pubkey::default(), Pubkey::default(), 0);
+        for id in 0..MAX_SLABS as u8 {
+            registry.push_slab(entry(id + 1)).unwrap();
+        }
+
+        // No pages: nowhere to put another slab
+        assert!(register_slab_paged(&mut registry, &mut [], entry(200)).is_err());
+
+        registry.page_count = 1;
+        let mut page1 = page(1);
+        for id in 0..MAX_SLABS as u8 {
+            register_slab_paged(&mut registry, &mut [&mut *page1], entry(100 + id)).unwrap();
+        }
+        assert!(register_slab_paged(&mut registry, &mut [&mut *page1], entry(250)).is_err());
+    }
+}
@@ -9,9 +9,10 @@ use pinocchio::{
     ProgramResult,
 };
 
-use crate::instructions::{SlabInstruction, process_initialize_slab, process_commit_fill, Side, OrderType};
+use crate::instructions::{SlabInstruction, process_initialize_slab, process_commit_fill, process_reset_seqno, Side, OrderType};
 use crate::state::SlabState;
-use percolator_common::{PercolatorError, validate_owner, validate_writable, borrow_account_data_mut, InstructionReader};
+use percolator_common::oracle::OracleGuard;
+use percolator_common::{PercolatorError, validate_owner, validate_writable, validate_signer, borrow_account_data_mut, InstructionReader, ContractKind, InstrumentScale, ScaleConfig};
 
 entrypoint!(process_instruction);
 
@@ -34,6 +35,7 @@ pub fn process_instruction(
     let instruction = match discriminator {
         0 => SlabInstruction::Initialize,
         1 => SlabInstruction::CommitFill,
+        2 => SlabInstruction::ResetSeqno,
         _ => {
             msg!("Error: Unknown instruction");
             return Err(PercolatorError::InvalidInstruction.into());
@@ -50,6 +52,10 @@ pub fn process_instruction(
             msg!("Instruction: CommitFill");
             process_commit_fill_inner(program_id, accounts, &instruction_data[1..])
         }
+        SlabInstruction::ResetSeqno => {
+            msg!("Instruction: ResetSeqno");
+            process_reset_seqno_inner(program_id, accounts, &instruction_data[1..])
+        }
     }
 }
 
@@ -62,7 +68,7 @@ pub fn process_instruction(
 /// 1. `[signer, writable]` Payer/authority
 /// 2. `[]` System program
 ///
-/// Expected data layout (121 bytes):
+/// Expected data layout (153 bytes, up to 222 with the optional trailing fields):
 /// - lp_owner: Pubkey (32 bytes)
 /// - router_id: Pubkey (32 bytes)
 /// - instrument: Pubkey (32 bytes)
@@ -70,6 +76,19 @@ pub fn process_instruction(
 /// - taker_fee_bps: i64 (8 bytes)
 /// - contract_size: i64 (8 bytes)
 /// - bump: u8 (1 byte)
+/// - version_hash: [u8; 32] (32 bytes)
+/// - contract_kind: u8 (1 byte, optional; 0 = linear, 1 = inverse, defaults to linear)
+/// - min_qty: i64 (8 bytes, optional; defaults to 0 = no minimum)
+/// - lot_size: i64 (8 bytes, optional; defaults to 1 = no rounding)
+/// - price_decimals: u8 (1 byte, optional; defaults to 6)
+/// - qty_decimals: u8 (1 byte, optional; defaults to 6)
+/// - oracle: Pubkey (32 bytes, optional) and max_deviation_bps: u64 (8 bytes) -
+///   registered oracle and band for the slab's own check on fill prices
+///   (defaults to the guard being off)
+/// - oracle_exponent: i8 (1 byte, optional; defaults to -6, the adapters' 1e6 prints)
+/// - collateral_decimals: u8 (1 byte, optional; defaults to 9, SOL lamports)
+/// - max_inventory: i64 (8 bytes, optional) - largest net position fills may
+///   leave the LP at, in the instrument's qty decimals (defaults to 0, no limit)
 ///
 fn process_initialize_inner(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
     if accounts.len() < 3 {
@@ -93,6 +112,52 @@ fn process_initialize_inner(program_id: &Pubkey, accounts: &[AccountInfo], data:
     let taker_fee_bps = reader.read_i64()?;
     let contract_size = reader.read_i64()?;
     let bump = reader.read_u8()?;
+    let version_hash = reader.read_bytes::<32>()?;
+    let contract_kind = if reader.remaining() > 0 {
+        match reader.read_u8()? {
+            0 => ContractKind::Linear,
+            1 => ContractKind::Inverse,
+            _ => {
+                msg!("Error: Unknown contract kind");
+                return Err(PercolatorError::InvalidInstruction.into());
+            }
+        }
+    } else {
+        ContractKind::Linear
+    };
+    let min_qty = if reader.remaining() >= 8 { reader.read_i64()? } else { 0 };
+    let lot_size = if reader.remaining() >= 8 { reader.read_i64()? } else { 1 };
+    if min_qty < 0 || lot_size <= 0 {
+        msg!("Error: Invalid min_qty or lot_size");
+        return Err(PercolatorError::InvalidQuantity.into());
+    }
+    let price_decimals = if reader.remaining() > 0 { reader.read_u8()? } else { InstrumentScale::DEFAULT.price_decimals };
+    let qty_decimals = if reader.remaining() > 0 { reader.read_u8()? } else { InstrumentScale::DEFAULT.qty_decimals };
+    let decimals_range = 1..=InstrumentScale::MAX_DECIMALS;
+    if !decimals_range.contains(&price_decimals) || !decimals_range.contains(&qty_decimals) {
+        msg!("Error: Invalid price or qty decimals");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+    let oracle_guard = if reader.remaining() >= 40 {
+        OracleGuard {
+            oracle: Pubkey::from(reader.read_bytes::<32>()?),
+            max_deviation_bps: reader.read_u64()?,
+        }
+    } else {
+        OracleGuard::default()
+    };
+    let oracle_exponent = if reader.remaining() > 0 { reader.read_u8()? as i8 } else { ScaleConfig::DEFAULT.oracle_exponent };
+    let collateral_decimals = if reader.remaining() > 0 { reader.read_u8()? } else { ScaleConfig::DEFAULT.collateral_decimals };
+    if !(1..=ScaleConfig::MAX_COLLATERAL_DECIMALS).contains(&collateral_decimals) {
+        msg!("Error: Invalid collateral decimals");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+    let scale = ScaleConfig { oracle_exponent, price_decimals, qty_decimals, collateral_decimals };
+    let max_inventory = if reader.remaining() >= 8 { reader.read_i64()? } else { 0 };
+    if max_inventory < 0 {
+        msg!("Error: Invalid max inventory");
+        return Err(PercolatorError::InvalidQuantity.into());
+    }
 
     let lp_owner = Pubkey::from(lp_owner_bytes);
     let router_id = Pubkey::from(router_id_bytes);
@@ -198,6 +263,13 @@ fn process_initialize_inner(program_id: &Pubkey, accounts: &[AccountInfo], data:
         taker_fee_bps,
         contract_size,
         bump,
+        version_hash,
+        contract_kind,
+        min_qty,
+        lot_size,
+        scale,
+        oracle_guard,
+        max_inventory,
     )?;
 
     msg!("Slab initialized successfully");
@@ -212,12 +284,14 @@ fn process_initialize_inner(program_id: &Pubkey, accounts: &[AccountInfo], data:
 /// 2. `[]` Oracle account (price feed)
 /// (Receipt temporarily removed for CPI testing)
 ///
-/// Expected data layout (22 bytes):
+/// Expected data layout (22 bytes, up to 31 with the optional trailing fields):
 /// - expected_seqno: u32 (4 bytes) - expected slab seqno (TOCTOU protection)
-/// - order_type: u8 (1 byte) - 0 = Market, 1 = Limit
+/// - order_type: u8 (1 byte) - 0 = Market, 1 = Limit, 2 = PostOnly
 /// - side: u8 (1 byte) - 0 = Buy, 1 = Sell
 /// - qty: i64 (8 bytes) - quantity to fill (1e6 scale)
 /// - limit_px: i64 (8 bytes) - limit price (1e6 scale)
+/// - oracle_px: i64 (8 bytes, optional) - oracle price for the mark (1e6 scale, defaults to 0 = none)
+/// - liquidation: u8 (1 byte, optional) - 1 = router liquidation fill, charged no taker fee (defaults to 0)
 fn process_commit_fill_inner(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
     msg!("SLAB: CommitFill inner called");
 
@@ -249,11 +323,14 @@ fn process_commit_fill_inner(program_id: &Pubkey, accounts: &[AccountInfo], data
     let side_byte = reader.read_u8()?;
     let qty = reader.read_i64()?;
     let limit_px = reader.read_i64()?;
+    let oracle_px = if reader.remaining() >= 8 { reader.read_i64()? } else { 0 };
+    let liquidation = if reader.remaining() > 0 { reader.read_u8()? != 0 } else { false };
 
     // Convert order type byte to OrderType enum
     let order_type = match order_type_byte {
         0 => OrderType::Market,
         1 => OrderType::Limit,
+        2 => OrderType::PostOnly,
         _ => {
             msg!("Error: Invalid order type");
             return Err(PercolatorError::InvalidOrderType.into());
@@ -282,8 +359,37 @@ fn process_commit_fill_inner(program_id: &Pubkey, accounts: &[AccountInfo], data
         side,
         qty,
         limit_px,
+        oracle_px,
+        liquidation,
     )?;
 
     msg!("CommitFill processed successfully");
     Ok(())
 }
+
+/// Process reset_seqno instruction
+///
+/// Expected accounts:
+/// 0. `[writable]` Slab state account
+/// 1. `[signer]` LP owner
+///
+/// Expected data layout: none
+fn process_reset_seqno_inner(program_id: &Pubkey, accounts: &[AccountInfo], _data: &[u8]) -> ProgramResult {
+    if accounts.len() < 2 {
+        msg!("Error: ResetSeqno instruction requires 2 accounts (slab, lp_owner)");
+        return Err(PercolatorError::InvalidInstruction.into());
+    }
+
+    let slab_account = &accounts[0];
+    let authority = &accounts[1];
+
+    validate_owner(slab_account, program_id)?;
+    validate_writable(slab_account)?;
+    validate_signer(authority)?;
+
+    let slab = unsafe { borrow_account_data_mut::<SlabState>(slab_account)? };
+    process_reset_seqno(slab, authority.key())?;
+
+    msg!("ResetSeqno processed successfully");
+    Ok(())
+}
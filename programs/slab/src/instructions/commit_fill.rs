@@ -20,6 +20,8 @@ pub enum OrderType {
     Market = 0,
     /// Limit order: Execute at specified limit price (v0: sanity check ±20% of oracle)
     Limit = 1,
+    /// Post-only limit order: rejected if it would cross the quote cache
+    PostOnly = 2,
 }
 
 /// Update quote cache after a fill (v0 stub)
@@ -30,18 +32,18 @@ fn update_quote_cache_after_fill(
     side: Side,
     px: i64,
     qty: i64,
-) {
+) -> Result<(), PercolatorError> {
     // For v0, simulate liquidity by adding fill as a quote level
     // This proves the cache update mechanism works
     let level = QuoteLevel { px, avail_qty: qty };
     match side {
         Side::Buy => {
             // Buy removes ask liquidity, add to bids
-            cache.update(seqno, &[level], &[]);
+            cache.update(seqno, &[level], &[])
         }
         Side::Sell => {
             // Sell removes bid liquidity, add to asks
-            cache.update(seqno, &[], &[level]);
+            cache.update(seqno, &[], &[level])
         }
     }
 }
@@ -56,32 +58,44 @@ fn update_quote_cache_after_fill(
 /// - Validating market vs limit order logic
 /// - Passing validated execution price to slab
 ///
-/// The oracle_account is passed through but NOT read by slab - it's for router's use only.
+/// The router passes the oracle price it validated with, and the fill blends it
+/// into the header's mark price. For defense in depth a slab with its oracle
+/// guard enabled also reads `oracle_account` itself (it must be the slab's
+/// registered oracle) and refuses, with PriceSlippage, an execution price
+/// outside its band - so even a compromised router can't fill at an absurd
+/// price. With the guard off the oracle account is not read.
+///
+/// Every fill is booked into the slab's LP inventory; with a max inventory set,
+/// a fill growing the LP's net position past it fails with InventoryLimit.
 ///
 /// # Arguments
 /// * `slab` - The slab state account
 /// * `receipt_account` - Account to write fill receipt
-/// * `oracle_account` - Oracle price feed account (for router, slab doesn't read it)
+/// * `oracle_account` - Oracle price feed account (read only when the oracle guard is on)
 /// * `router_signer` - Router authority (must match slab.header.router_id)
 /// * `expected_seqno` - Expected slab seqno (TOCTOU protection)
-/// * `order_type` - Market or Limit order (informational for v0)
+/// * `order_type` - Market, Limit, or PostOnly (PostOnly fails with WouldCrossBook if it would take liquidity)
 /// * `side` - Buy or Sell
-/// * `qty` - Desired quantity (1e6 scale, positive)
+/// * `qty` - Desired quantity (1e6 scale, positive; rounded down to the lot)
 /// * `limit_px` - Execution price (1e6 scale) - already validated by router
+/// * `oracle_px` - Oracle price the router read for this fill (1e6 scale, 0 = none)
+/// * `liquidation` - Fill closes a liquidated account, so no taker fee is charged
 ///
 /// # Returns
 /// * Writes FillReceipt to receipt_account
-/// * Updates slab state (book, seqno, quote_cache)
+/// * Updates slab state (book, seqno, quote_cache, mark_px, LP inventory)
 pub fn process_commit_fill(
     slab: &mut SlabState,
     receipt_account: &AccountInfo,
-    _oracle_account: &AccountInfo, // Passed through but not used by slab
+    oracle_account: &AccountInfo,
     router_signer: &Pubkey,
     expected_seqno: u32,
-    _order_type: OrderType,        // Informational only in v0
+    order_type: OrderType,
     side: Side,
     qty: i64,
     limit_px: i64,
+    oracle_px: i64,
+    liquidation: bool,
 ) -> Result<(), PercolatorError> {
     msg!("SLAB: Inside process_commit_fill");
 
@@ -107,34 +121,74 @@ pub fn process_commit_fill(
         return Err(PercolatorError::InvalidPrice);
     }
 
+    // Don't take the router's word for the price if the slab guards its own oracle
+    if slab.oracle_guard.is_enabled() {
+        use pinocchio::sysvars::{clock::Clock, Sysvar};
+        let now_ts = Clock::get().map(|clock| clock.unix_timestamp).unwrap_or(0);
+        slab.oracle_guard.check_fill(oracle_account, limit_px, now_ts)?;
+    }
+
+    let taker_side = match side {
+        Side::Buy => percolator_common::Side::Buy,
+        Side::Sell => percolator_common::Side::Sell,
+    };
+
+    // Post-only orders must rest, so refuse one that would match the opposing quotes
+    if order_type == OrderType::PostOnly && slab.quote_cache.would_cross(taker_side, limit_px) {
+        msg!("Error: Post-only order would cross the book");
+        return Err(PercolatorError::WouldCrossBook);
+    }
+
     // Capture seqno at start
     let seqno_start = slab.header.seqno;
 
+    // Enforce the minimum order size and round down to the lot; the receipt
+    // reports the rounded quantity so the router settles what actually filled
+    let filled_qty = slab.header.lot_rounded_qty(qty).map_err(|e| {
+        msg!("Error: Order below minimum size");
+        e
+    })?;
+
     // v0 Matching: Simulate instant fill at limit price
     // In v1, this will match against real book liquidity
-    let filled_qty = qty;
     let vwap_px = limit_px;
 
-    // Calculate notional: qty * contract_size * price / 1e6
-    // For v0, simplified: qty * price / 1e6 (assuming contract_size normalized)
-    let notional = (filled_qty as i128 * limit_px as i128 / 1_000_000) as i64;
-
-    // Calculate fee: notional * taker_fee_bps / 10000
-    let fee = (notional as i128 * slab.header.taker_fee_bps as i128 / 10_000) as i64;
+    // Notional (and so the fee) is on what filled at the price it filled at,
+    // at the instrument's price / qty decimals (1e6 quote scale)
+    let notional = contract_notional(
+        filled_qty,
+        vwap_px,
+        slab.header.contract_size,
+        ContractKind::from_u8(slab.header.contract_kind),
+        slab.header.scale(),
+    )
+    .min(i64::MAX as u128) as i64;
+
+    // Calculate fee: notional * taker_fee_bps / 10000, waived on liquidation fills
+    let fee = slab.header.taker_fee(notional, liquidation);
+
+    // Book the fill against the LP before anything else changes, so a fill
+    // past its inventory limit leaves the slab untouched
+    let signed_qty = taker_side.signed_qty(filled_qty);
+    slab.inventory.apply_fill(signed_qty, notional).map_err(|e| {
+        msg!("Error: Fill would push LP inventory past its limit");
+        e
+    })?;
+
+    // Increment seqno (book changed); refuses to wrap past u32::MAX
+    let next_seqno = slab.header.increment_seqno().map_err(|e| {
+        msg!("Error: Seqno exhausted - slab needs a seqno reset");
+        e
+    })?;
 
     // Update quote cache to reflect this fill
     // For v0, add this as liquidity at the fill price
-    update_quote_cache_after_fill(&mut slab.quote_cache, slab.header.seqno + 1, side, limit_px, filled_qty);
-
-    // Increment seqno (book changed)
-    slab.header.increment_seqno();
+    update_quote_cache_after_fill(&mut slab.quote_cache, next_seqno, side, limit_px, filled_qty)?;
 
-    // Write receipt for router to read
-    let signed_qty = match side {
-        Side::Buy => filled_qty,
-        Side::Sell => -filled_qty,
-    };
+    // Damp the mark toward this fill rather than snapping to it
+    slab.header.update_mark_price(vwap_px, oracle_px);
 
+    // Write receipt for router to read, signed by the shared convention (+buy, -sell)
     let mut receipt_data = receipt_account.try_borrow_mut_data()
         .map_err(|_| PercolatorError::InvalidAccount)?;
 
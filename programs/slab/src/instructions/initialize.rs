@@ -2,6 +2,7 @@
 
 use crate::state::{SlabHeader, SlabState};
 use percolator_common::*;
+use percolator_common::oracle::OracleGuard;
 use pinocchio::{account_info::AccountInfo, msg, pubkey::Pubkey};
 
 /// Process initialize instruction for slab (v0 minimal)
@@ -19,6 +20,13 @@ use pinocchio::{account_info::AccountInfo, msg, pubkey::Pubkey};
 /// * `taker_fee_bps` - Taker fee (basis points)
 /// * `contract_size` - Contract size (1e6 scale)
 /// * `bump` - PDA bump seed
+/// * `version_hash` - Slab program version hash (must match router registry entry)
+/// * `contract_kind` - Linear or inverse quoting (router branches PnL and margin on it)
+/// * `min_qty` - Minimum order size (1e6 scale, 0 = no minimum)
+/// * `lot_size` - Fills round down to a multiple of this (1e6 scale)
+/// * `scale` - Oracle, price, quantity and collateral scale of the instrument
+/// * `oracle_guard` - Slab-side oracle band on fill prices (default = off)
+/// * `max_inventory` - Largest net position fills may leave the LP at (0 = no limit)
 pub fn process_initialize_slab(
     program_id: &Pubkey,
     slab_account: &AccountInfo,
@@ -29,6 +37,13 @@ pub fn process_initialize_slab(
     taker_fee_bps: i64,
     contract_size: i64,
     bump: u8,
+    version_hash: [u8; 32],
+    contract_kind: ContractKind,
+    min_qty: i64,
+    lot_size: i64,
+    scale: ScaleConfig,
+    oracle_guard: OracleGuard,
+    max_inventory: i64,
 ) -> Result<(), PercolatorError> {
     // For v0, we skip PDA derivation and just verify ownership
     // In production, we would verify the account is a valid PDA
@@ -56,7 +71,7 @@ pub fn process_initialize_slab(
     let slab = unsafe { borrow_account_data_mut::<SlabState>(slab_account)? };
 
     // Initialize header with v0 parameters
-    let header = SlabHeader::new(
+    let mut header = SlabHeader::new(
         *program_id,
         lp_owner,
         router_id,
@@ -66,9 +81,19 @@ pub fn process_initialize_slab(
         contract_size,
         bump,
     );
+    header.version_hash = version_hash;
+    header.contract_kind = contract_kind as u8;
+    header.min_qty = min_qty;
+    header.lot = lot_size;
+    header.price_decimals = scale.price_decimals;
+    header.qty_decimals = scale.qty_decimals;
+    header.oracle_exponent = scale.oracle_exponent;
+    header.collateral_decimals = scale.collateral_decimals;
 
     // Create new slab state (initializes quote_cache and book automatically)
     *slab = SlabState::new(header);
+    slab.oracle_guard = oracle_guard;
+    slab.inventory = LpInventory::new(max_inventory);
 
     msg!("Slab initialized successfully");
     Ok(())
@@ -1,8 +1,10 @@
 pub mod initialize;
 pub mod commit_fill;
+pub mod reset_seqno;
 
 pub use initialize::*;
 pub use commit_fill::*;
+pub use reset_seqno::*;
 
 /// Instruction discriminator
 #[repr(u8)]
@@ -12,4 +14,6 @@ pub enum SlabInstruction {
     Initialize = 0,
     /// Commit fill (v0 - single instruction for fills)
     CommitFill = 1,
+    /// Restart an exhausted seqno (LP owner only)
+    ResetSeqno = 2,
 }
@@ -0,0 +1,29 @@
+//! Reset seqno instruction - restart an exhausted sequence number
+
+use crate::state::SlabState;
+use percolator_common::*;
+use pinocchio::{msg, pubkey::Pubkey};
+
+/// Process reset_seqno instruction
+///
+/// commit_fill refuses to wrap the header seqno, so a slab that reaches
+/// u32::MAX stops filling. The LP owner restarts it at zero; routers holding
+/// the old seqno fail their TOCTOU check and re-read.
+///
+/// # Arguments
+/// * `slab` - The slab state account
+/// * `authority` - Signer (must match slab.header.lp_owner)
+pub fn process_reset_seqno(slab: &mut SlabState, authority: &Pubkey) -> Result<(), PercolatorError> {
+    if &slab.header.lp_owner != authority {
+        msg!("Error: Only the LP owner can reset the seqno");
+        return Err(PercolatorError::Unauthorized);
+    }
+
+    slab.header.reset_seqno().map_err(|e| {
+        msg!("Error: Seqno is not exhausted");
+        e
+    })?;
+
+    msg!("Seqno reset");
+    Ok(())
+}
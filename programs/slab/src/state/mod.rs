@@ -3,4 +3,4 @@ pub mod slab;
 pub use slab::*;
 
 // Re-export from common
-pub use percolator_common::{SlabHeader, QuoteCache, QuoteLevel, FillReceipt};
+pub use percolator_common::{SlabHeader, QuoteCache, QuoteLevel, FillReceipt, LpInventory};
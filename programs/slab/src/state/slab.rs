@@ -1,6 +1,7 @@
 //! Slab state - v0 minimal single-account orderbook
 
-use super::{SlabHeader, QuoteCache};
+use super::{SlabHeader, QuoteCache, LpInventory};
+use percolator_common::oracle::OracleGuard;
 
 /// Book area - simplified price-time orderbook
 /// In v0, this is a stub placeholder for future book implementation
@@ -20,7 +21,7 @@ impl BookArea {
 }
 
 /// Main slab state - v0 minimal structure (~4KB)
-/// Layout: Header (256B) + QuoteCache (256B) + BookArea (3KB)
+/// Layout: Header (256B) + QuoteCache (256B) + BookArea (3KB) + OracleGuard (40B) + LpInventory (24B)
 #[repr(C)]
 pub struct SlabState {
     /// Header with metadata and offsets
@@ -29,6 +30,10 @@ pub struct SlabState {
     pub quote_cache: QuoteCache,
     /// Book area (price-time queues)
     pub book: BookArea,
+    /// Own oracle check on router execution prices (off unless set at initialize)
+    pub oracle_guard: OracleGuard,
+    /// LP's net position from fills and its risk limit (unlimited unless set at initialize)
+    pub inventory: LpInventory,
 }
 
 impl SlabState {
@@ -41,6 +46,8 @@ impl SlabState {
             header,
             quote_cache: QuoteCache::new(),
             book: BookArea::new(),
+            oracle_guard: OracleGuard::default(),
+            inventory: LpInventory::default(),
         }
     }
 }
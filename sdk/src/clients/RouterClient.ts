@@ -11,6 +11,7 @@ import BN from 'bn.js';
 import bs58 from 'bs58';
 import {
   RouterInstruction,
+  ROUTER_INSTRUCTION_VERSION,
   Portfolio,
   Registry,
   Vault,
@@ -41,7 +42,7 @@ import {
   serializeI64,
   serializeBool,
   serializePubkey,
-  createInstructionData,
+  createVersionedInstructionData,
   deserializeU64,
   deserializeU128,
   deserializeI64,
@@ -123,6 +124,19 @@ export class RouterClient {
     );
   }
 
+  /**
+   * Derive LP pool PDA for an AMM market
+   * PDA seeds: ["lp_pool", market]
+   * @param market AMM market public key
+   * @returns [PDA, bump]
+   */
+  deriveLpPoolPDA(market: PublicKey): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from('lp_pool'), market.toBuffer()],
+      this.programId
+    );
+  }
+
   /**
    * Derive PositionDetails PDA for a specific position
    * PDA seeds: ["position", portfolio_pda, slab_index (u16 LE), instrument_index (u16 LE)]
@@ -794,8 +808,9 @@ export class RouterClient {
   buildInitializeInstruction(payer: PublicKey, governance: PublicKey): TransactionInstruction {
     const [registryPDA] = this.deriveRegistryPDA();
 
-    const data = createInstructionData(
+    const data = createVersionedInstructionData(
       RouterInstruction.Initialize,
+      ROUTER_INSTRUCTION_VERSION,
       serializePubkey(governance)
     );
 
@@ -862,9 +877,11 @@ export class RouterClient {
     user: PublicKey
   ): Promise<TransactionInstruction> {
     const portfolioAddress = await this.derivePortfolioAddress(user);
+    const [registryPDA] = this.deriveRegistryPDA();
 
-    const data = createInstructionData(
+    const data = createVersionedInstructionData(
       RouterInstruction.Deposit,
+      ROUTER_INSTRUCTION_VERSION,
       serializeU64(amount)
     );
 
@@ -874,6 +891,7 @@ export class RouterClient {
         { pubkey: portfolioAddress, isSigner: false, isWritable: true },
         { pubkey: user, isSigner: true, isWritable: true },
         { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+        { pubkey: registryPDA, isSigner: false, isWritable: true }, // Pause flags, deposit caps, TVL
       ],
       data,
     });
@@ -882,6 +900,8 @@ export class RouterClient {
   /**
    * Build Withdraw instruction (SOL only)
    * Withdraws SOL from portfolio account to user's wallet
+   * Open positions are appended (slab, registered oracle, PositionDetails) so the
+   * program can mark them for the margin check
    * @param amount Amount of lamports to withdraw (u64)
    * @param user User's public key
    * @returns TransactionInstruction
@@ -893,19 +913,47 @@ export class RouterClient {
     const portfolioAddress = await this.derivePortfolioAddress(user);
     const [registryPDA] = this.deriveRegistryPDA();
 
-    const data = createInstructionData(
+    const exposures = (await this.getPortfolio(user))?.exposures ?? [];
+    if (exposures.length > 0xff) {
+      throw new Error('Too many open positions to pass to Withdraw');
+    }
+    const registry = exposures.length > 0 ? await this.getRegistry() : null;
+    if (exposures.length > 0 && !registry) {
+      throw new Error('Registry not found');
+    }
+    const slabs = exposures.map((exposure) => registry!.slabs[exposure.slabIndex]);
+
+    const data = createVersionedInstructionData(
       RouterInstruction.Withdraw,
-      serializeU64(amount)
+      ROUTER_INSTRUCTION_VERSION,
+      serializeU64(amount),
+      ...(exposures.length > 0 ? [Buffer.from([exposures.length])] : [])
     );
 
+    const keys = [
+      { pubkey: portfolioAddress, isSigner: false, isWritable: true },
+      { pubkey: user, isSigner: true, isWritable: true },
+      { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+      { pubkey: registryPDA, isSigner: false, isWritable: true }, // Warmup state, TVL
+    ];
+    for (const slab of slabs) {
+      keys.push({ pubkey: slab.slabId, isSigner: false, isWritable: false });
+    }
+    for (const slab of slabs) {
+      keys.push({ pubkey: slab.oracleId, isSigner: false, isWritable: false });
+    }
+    for (const exposure of exposures) {
+      const [positionDetailsPDA] = this.derivePositionDetailsPDA(
+        portfolioAddress,
+        exposure.slabIndex,
+        exposure.instrumentIndex
+      );
+      keys.push({ pubkey: positionDetailsPDA, isSigner: false, isWritable: false });
+    }
+
     return new TransactionInstruction({
       programId: this.programId,
-      keys: [
-        { pubkey: portfolioAddress, isSigner: false, isWritable: true },
-        { pubkey: user, isSigner: true, isWritable: true },
-        { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
-        { pubkey: registryPDA, isSigner: false, isWritable: false },
-      ],
+      keys,
       data,
     });
   }
@@ -954,10 +1002,12 @@ export class RouterClient {
     });
 
     // Instruction 2: Initialize portfolio
-    // Data format: [discriminator (1 byte), user_pubkey (32 bytes)]
-    const instructionData = Buffer.alloc(33);
-    instructionData.writeUInt8(RouterInstruction.InitializePortfolio, 0);
-    user.toBuffer().copy(instructionData, 1);
+    // Data format: [discriminator (1 byte), version (1 byte), user_pubkey (32 bytes)]
+    const instructionData = createVersionedInstructionData(
+      RouterInstruction.InitializePortfolio,
+      ROUTER_INSTRUCTION_VERSION,
+      user.toBuffer()
+    );
 
     const initializeIx = new TransactionInstruction({
       programId: this.programId,
@@ -1002,7 +1052,7 @@ export class RouterClient {
     const [portfolioPDA] = this.derivePortfolioPDA(user);
     const [registryPDA] = this.deriveRegistryPDA();
 
-    const data = createInstructionData(RouterInstruction.InitializePortfolio);
+    const data = createVersionedInstructionData(RouterInstruction.InitializePortfolio, ROUTER_INSTRUCTION_VERSION);
 
     return new TransactionInstruction({
       programId: this.programId,
@@ -1023,13 +1073,24 @@ export class RouterClient {
    * @param user User's public key
    * @param splits Array of slab splits (each includes oracle and dlpOwner)
    * @param orderType Market (0) or Limit (1) order
+   * @param leverage Leverage multiplier (1-10x)
+   * @param maxSlippageBps Market order slippage tolerance in bps (0 = program default, clamped to registry ceiling)
+   * @param simulate Dry run: the program logs the projected fill and margin (sol_log_data) and commits nothing
+   * @param reduceOnly Only shrink existing positions; the program rejects any split that would open, increase, or reverse one
+   * @param allowPartialBatch Let filled splits settle when others fill nothing (default: a dry split reverts the whole order)
+   * @param rentPayer Signer that funds any new PositionDetails rent instead of the user (e.g. a sponsor or vault)
    * @returns {instruction, receiptSetup, receiptKeypair} - execution instruction, receipt creation instruction, and receipt keypair
    */
   async buildExecuteCrossSlabInstruction(
     user: PublicKey,
     splits: SlabSplit[],
     orderType: ExecutionType = ExecutionType.Limit,
-    leverage: number = 1
+    leverage: number = 1,
+    maxSlippageBps: number = 0,
+    simulate: boolean = false,
+    reduceOnly: boolean = false,
+    allowPartialBatch: boolean = false,
+    rentPayer?: PublicKey
   ): Promise<{instruction: TransactionInstruction, receiptSetup: TransactionInstruction, receiptKeypair: Keypair}> {
     // v0.5: Single slab only (cross-slab routing disabled)
     if (splits.length !== 1) {
@@ -1047,11 +1108,47 @@ export class RouterClient {
     }
     const dlpPortfolioAddress = await this.derivePortfolioAddress(splits[0].dlpOwner);
 
+    // PositionDetails PDAs are keyed by registry slab index
+    // For v0.5 single-slab, fetch registry and lookup slab_index
+    const registry = await this.getRegistry();
+    if (!registry) {
+      throw new Error('Registry not found');
+    }
+    const splitSlabIndices = splits.map((split) => {
+      for (let i = 0; i < registry.slabCount; i++) {
+        if (registry.slabs[i].slabId.equals(split.slabMarket)) {
+          return i;
+        }
+      }
+      // Unknown slabs are auto-registered by the router at the next index
+      return registry.slabCount;
+    });
+
+    // The margin check needs every open position, so the ones this order
+    // doesn't trade are passed as extra PositionDetails accounts
+    const portfolio = await this.getPortfolio(user);
+    const extraPositionPDAs = (portfolio?.exposures ?? [])
+      .filter((exposure) => !splitSlabIndices.includes(exposure.slabIndex) || exposure.instrumentIndex !== 0)
+      .map((exposure) =>
+        this.derivePositionDetailsPDA(userPortfolioAddress, exposure.slabIndex, exposure.instrumentIndex)[0]
+      );
+    if (extraPositionPDAs.length > 0xff) {
+      throw new Error('Too many open positions to pass as extra PositionDetails');
+    }
+    const hasExtraPositions = extraPositionPDAs.length > 0;
+
     // Serialize instruction data:
     // - num_splits (u8)
     // - order_type (u8)
     // - leverage (u8) - 1-10x leverage
+    // - max_slippage_bps (u16) - market order slippage tolerance
+    // - oracles_per_slab (u8) - 1, 3, or 5 feeds per slab (median is used)
     // - For each split: side (u8) + qty (i64) + limit_px (i64)
+    // - simulate (u8, optional) - 1 = dry run
+    // - reduce_only (u8, optional, follows simulate) - 1 = only shrink positions
+    // - allow_partial_batch (u8, optional, follows reduce_only)
+    // - sponsored_rent (u8, optional, follows allow_partial_batch) - 1 = rent payer account appended
+    // - extra_positions (u8, optional, follows sponsored_rent) - untraded PositionDetails appended
 
     // Validate leverage
     if (leverage < 1 || leverage > 10) {
@@ -1061,6 +1158,21 @@ export class RouterClient {
     const numSplits = Buffer.from([splits.length]);
     const orderTypeBuffer = Buffer.from([orderType]);
     const leverageBuffer = Buffer.from([leverage]);
+    if (maxSlippageBps < 0 || maxSlippageBps > 0xffff) {
+      throw new Error('maxSlippageBps must fit in a u16');
+    }
+    const slippageBuffer = Buffer.alloc(2);
+    slippageBuffer.writeUInt16LE(maxSlippageBps);
+
+    // Every split must carry the same number of feeds
+    const oraclesPerSlab = 1 + (splits[0].extraOracles?.length ?? 0);
+    if (![1, 3, 5].includes(oraclesPerSlab)) {
+      throw new Error('Each split must have 1, 3, or 5 oracle feeds');
+    }
+    if (splits.some((split) => 1 + (split.extraOracles?.length ?? 0) !== oraclesPerSlab)) {
+      throw new Error('All splits must have the same number of oracle feeds');
+    }
+    const oraclesPerSlabBuffer = Buffer.from([oraclesPerSlab]);
     const splitBuffers = splits.map((split) => {
       const qtyBuffer = serializeI64(split.qty);
 
@@ -1071,12 +1183,20 @@ export class RouterClient {
       ]);
     });
 
-    const data = createInstructionData(
+    const data = createVersionedInstructionData(
       RouterInstruction.ExecuteCrossSlab,
+      ROUTER_INSTRUCTION_VERSION,
       numSplits,
       orderTypeBuffer,
       leverageBuffer,
-      ...splitBuffers
+      slippageBuffer,
+      oraclesPerSlabBuffer,
+      ...splitBuffers,
+      ...(simulate || reduceOnly || allowPartialBatch || rentPayer || hasExtraPositions ? [Buffer.from([simulate ? 1 : 0])] : []),
+      ...(reduceOnly || allowPartialBatch || rentPayer || hasExtraPositions ? [Buffer.from([reduceOnly ? 1 : 0])] : []),
+      ...(allowPartialBatch || rentPayer || hasExtraPositions ? [Buffer.from([allowPartialBatch ? 1 : 0])] : []),
+      ...(rentPayer || hasExtraPositions ? [Buffer.from([rentPayer ? 1 : 0])] : []),
+      ...(hasExtraPositions ? [Buffer.from([extraPositionPDAs.length])] : [])
     );
 
     // Build account list (v0.5 layout with PositionDetails):
@@ -1089,8 +1209,10 @@ export class RouterClient {
     // 6. slab_program (for CPI to slab)
     // 7..7+n. slab_accounts (writable)
     // 7+n..7+2n. receipt_accounts (writable)
-    // 7+2n..7+3n. oracle_accounts (readonly)
-    // 7+3n..7+4n. position_details_accounts (writable)
+    // 7+2n..7+2n+nk. oracle_accounts (readonly, k per slab grouped by slab)
+    // 7+2n+nk..7+3n+nk. position_details_accounts (writable)
+    // 7+3n+nk. rent_payer (signer, writable) - only with rentPayer
+    // then extra position_details_accounts (readonly) - open positions not in the splits
 
     // Get slab program ID (needed for CPI and receipt creation)
     const slabAccountInfo = await this.connection.getAccountInfo(splits[0].slabMarket);
@@ -1120,35 +1242,16 @@ export class RouterClient {
     // Add receipt account (writable, will be written by slab)
     keys.push({ pubkey: receiptKeypair.publicKey, isSigner: false, isWritable: true });
 
-    // Add oracle accounts
+    // Add oracle accounts (primary feed first, then any extra feeds)
     for (const split of splits) {
       keys.push({ pubkey: split.oracle, isSigner: false, isWritable: false });
+      for (const extraOracle of split.extraOracles ?? []) {
+        keys.push({ pubkey: extraOracle, isSigner: false, isWritable: false });
+      }
     }
 
     // Add PositionDetails accounts (one per split)
-    // For v0.5: We need to derive PositionDetails PDA for each position
-    // The challenge: slab_index depends on registry lookup, which we don't have client-side
-    // Solution: For v0.5 single-slab, fetch registry and lookup slab_index
-    const registry = await this.getRegistry();
-    if (!registry) {
-      throw new Error('Registry not found');
-    }
-    for (const split of splits) {
-      // Find slab index in registry
-      let slabIndex = -1;
-      for (let i = 0; i < registry.slabCount; i++) {
-        if (registry.slabs[i].slabId.equals(split.slabMarket)) {
-          slabIndex = i;
-          break;
-        }
-      }
-
-      // If slab not found, use index 0 (will be auto-registered by router)
-      // The router auto-registers unknown slabs during execution
-      if (slabIndex === -1) {
-        slabIndex = registry.slabCount; // Next available index
-      }
-
+    for (const slabIndex of splitSlabIndices) {
       const instrumentIndex = 0; // v0: single instrument per slab
       const [positionDetailsPDA] = this.derivePositionDetailsPDA(
         userPortfolioAddress,
@@ -1159,6 +1262,14 @@ export class RouterClient {
       keys.push({ pubkey: positionDetailsPDA, isSigner: false, isWritable: true });
     }
 
+    if (rentPayer) {
+      keys.push({ pubkey: rentPayer, isSigner: true, isWritable: true });
+    }
+
+    for (const positionDetailsPDA of extraPositionPDAs) {
+      keys.push({ pubkey: positionDetailsPDA, isSigner: false, isWritable: false });
+    }
+
     const instruction = new TransactionInstruction({
       programId: this.programId,
       keys,
@@ -1180,8 +1291,9 @@ export class RouterClient {
       params.portfolio // Note: portfolio is the target user's portfolio pubkey
     );
 
-    const data = createInstructionData(
+    const data = createVersionedInstructionData(
       RouterInstruction.LiquidateUser,
+      ROUTER_INSTRUCTION_VERSION,
       serializeBool(params.isPreliq),
       serializeU64(params.currentTs)
     );
@@ -1202,6 +1314,12 @@ export class RouterClient {
       keys.push({ pubkey: slab, isSigner: false, isWritable: true });
     }
 
+    // Trailing PositionDetails PDAs price the portfolio's open positions at oracle,
+    // and the closing fills update or close them
+    for (const position of params.positionDetails ?? []) {
+      keys.push({ pubkey: position, isSigner: false, isWritable: true });
+    }
+
     return new TransactionInstruction({
       programId: this.programId,
       keys,
@@ -1218,14 +1336,15 @@ export class RouterClient {
     params: BurnLpSharesParams
   ): TransactionInstruction {
     const [portfolioPDA] = this.derivePortfolioPDA(params.user);
+    const [registryPDA] = this.deriveRegistryPDA();
+    const [lpPoolPDA] = this.deriveLpPoolPDA(params.marketId);
 
-    const data = createInstructionData(
+    // The share price is read from the LP pool at the on-chain clock
+    const data = createVersionedInstructionData(
       RouterInstruction.BurnLpShares,
+      ROUTER_INSTRUCTION_VERSION,
       serializePubkey(params.marketId),
-      serializeU64(params.sharesToBurn),
-      serializeI64(params.currentSharePrice),
-      serializeU64(params.currentTs),
-      serializeU64(params.maxStalenessSeconds)
+      serializeU64(params.sharesToBurn)
     );
 
     return new TransactionInstruction({
@@ -1233,7 +1352,8 @@ export class RouterClient {
       keys: [
         { pubkey: portfolioPDA, isSigner: false, isWritable: true },
         { pubkey: params.user, isSigner: true, isWritable: false },
-        { pubkey: params.marketId, isSigner: false, isWritable: true },
+        { pubkey: registryPDA, isSigner: false, isWritable: false }, // Pause flags
+        { pubkey: lpPoolPDA, isSigner: false, isWritable: true }, // Pays the redemption
       ],
       data,
     });
@@ -1248,23 +1368,24 @@ export class RouterClient {
     params: CancelLpOrdersParams
   ): TransactionInstruction {
     const [portfolioPDA] = this.derivePortfolioPDA(params.user);
+    const [registryPDA] = this.deriveRegistryPDA();
 
     // Limit to 16 orders
     if (params.orderIds.length > 16) {
       throw new Error('Cannot cancel more than 16 orders at once');
     }
 
-    // Serialize: num_orders (u8) + order_ids + freed_quote + freed_base
+    // Serialize: num_orders (u8) + order_ids
+    // Freed collateral is looked up on-chain from each order's reservation
     const numOrders = Buffer.from([params.orderIds.length]);
     const orderIdBuffers = params.orderIds.map((id) => serializeU64(id));
 
-    const data = createInstructionData(
+    const data = createVersionedInstructionData(
       RouterInstruction.CancelLpOrders,
+      ROUTER_INSTRUCTION_VERSION,
       serializePubkey(params.marketId),
       numOrders,
-      ...orderIdBuffers,
-      serializeU128(params.freedQuote),
-      serializeU128(params.freedBase)
+      ...orderIdBuffers
     );
 
     return new TransactionInstruction({
@@ -1272,7 +1393,7 @@ export class RouterClient {
       keys: [
         { pubkey: portfolioPDA, isSigner: false, isWritable: true },
         { pubkey: params.user, isSigner: true, isWritable: false },
-        { pubkey: params.marketId, isSigner: false, isWritable: true },
+        { pubkey: registryPDA, isSigner: false, isWritable: false }, // Pause flags
       ],
       data,
     });
@@ -1502,6 +1623,9 @@ export class RouterClient {
           }
         }
 
+        // open_order_quote, open_order_base: [u64; MAX_OPEN_ORDERS] each
+        offset += 16 * MAX_OPEN_ORDERS;
+
         slab = {
           reservedQuote,
           reservedBase,
@@ -1509,8 +1633,8 @@ export class RouterClient {
           openOrderIds,
         };
       } else {
-        // Skip SlabLp size (16 + 16 + 2 + 6 + (8 * MAX_OPEN_ORDERS) bytes)
-        offset += 16 + 16 + 2 + 6 + (8 * MAX_OPEN_ORDERS);
+        // Skip SlabLp size (16 + 16 + 2 + 6 + (24 * MAX_OPEN_ORDERS) bytes)
+        offset += 16 + 16 + 2 + 6 + (24 * MAX_OPEN_ORDERS);
       }
 
       // im: u128 (16 bytes)
@@ -1594,8 +1718,12 @@ export class RouterClient {
     const bump = data.readUInt8(offset);
     offset += 1;
 
-    // Padding (5 bytes)
-    offset += 5;
+    // Authority bump (u8 = 1 byte)
+    const authorityBump = data.readUInt8(offset);
+    offset += 1;
+
+    // Padding (4 bytes)
+    offset += 4;
 
     // Liquidation parameters
     const imr = deserializeU64(data, offset);
@@ -1622,13 +1750,20 @@ export class RouterClient {
     const oracleToleranceBps = deserializeU64(data, offset);
     offset += 8;
 
-    // Padding2 (8 bytes)
+    const maxSlippageBps = deserializeU64(data, offset);
     offset += 8;
 
     // Skip complex nested structs (insurance, pnl vesting, warmup, etc.)
-    // The slabs array starts at offset 680 (verified by searching for slab pubkey in account data)
+    // The pending governance sits at offset 1056 (funding_params ends at 744,
+    // then liquidation_mode, liquidator_count, padding, 8 liquidator pubkeys,
+    // the withdrawal queue threshold and delay, the two deposit caps, the
+    // trade cooldown, and the registry page count)
     // This accounts for all intermediate structs with proper alignment
-    offset = 680;
+    const pendingGovernance = deserializePubkey(data, 1056);
+
+    // The slabs array follows at offset 1272, after the collateral haircut
+    // table and the per-portfolio position cap
+    offset = 1272;
 
     // Now we're at the slabs array
     // SlabEntry struct size:
@@ -1643,11 +1778,28 @@ export class RouterClient {
     // - max_exposure (u128): 16 bytes
     // - registered_ts (u64): 8 bytes
     // - active (bool): 1 byte
-    // - _padding ([u8; 7]): 7 bytes
-    // Total: 168 bytes per entry
+    // - use_twap (bool): 1 byte
+    // - max_leverage (u8): 1 byte
+    // - extra_oracle_count (u8): 1 byte
+    // - limit_deviation_bps (u16): 2 bytes
+    // - _limit_padding ([u8; 2]): 2 bytes
+    // - mm_tiers ([MmTier; 3]): 48 bytes (size_limit u64 + mmr_bps u64 each)
+    // - max_move_bps (u64): 8 bytes
+    // - last_settled_price (i64): 8 bytes
+    // - last_settled_slot (u64): 8 bytes
+    // - long_oi (u64): 8 bytes
+    // - short_oi (u64): 8 bytes
+    // - max_oi (u64): 8 bytes
+    // - trading_open_secs (u32): 4 bytes
+    // - trading_close_secs (u32): 4 bytes
+    // - global_funding_index (i128): 16 bytes
+    // - funding_rate_bps (i64): 8 bytes
+    // - last_funding_slot (u64): 8 bytes
+    // - extra_oracle_ids ([Pubkey; 4]): 128 bytes
+    // Total: 432 bytes per entry
 
     const slabs: any[] = [];
-    const SLAB_ENTRY_SIZE = 168;
+    const SLAB_ENTRY_SIZE = 432;
 
     for (let i = 0; i < slabCount && i < 256; i++) {
       const entryOffset = offset + (i * SLAB_ENTRY_SIZE);
@@ -1658,11 +1810,26 @@ export class RouterClient {
 
       // Skip other fields for now, just read active flag
       const active = data.readUInt8(entryOffset + 160) === 1;
+      const maxLeverage = data.readUInt8(entryOffset + 162);
+      const extraOracleCount = data.readUInt8(entryOffset + 163);
+      const limitDeviationBps = data.readUInt16LE(entryOffset + 164);
+      const longOi = deserializeU64(data, entryOffset + 240);
+      const shortOi = deserializeU64(data, entryOffset + 248);
+      const maxOi = deserializeU64(data, entryOffset + 256);
+      const tradingOpenSecs = data.readUInt32LE(entryOffset + 264);
+      const tradingCloseSecs = data.readUInt32LE(entryOffset + 268);
+      const globalFundingIndex = deserializeI128(data, entryOffset + 272);
+      const fundingRateBps = deserializeI64(data, entryOffset + 288);
+      const extraOracleIds: PublicKey[] = [];
+      for (let j = 0; j < extraOracleCount && j < 4; j++) {
+        extraOracleIds.push(deserializePubkey(data, entryOffset + 304 + j * 32));
+      }
 
       slabs.push({
         slabId,
         versionHash,
         oracleId,
+        extraOracleIds,
         imr: new BN(0), // TODO: Deserialize if needed
         mmr: new BN(0),
         makerFeeCap: new BN(0),
@@ -1671,14 +1838,25 @@ export class RouterClient {
         maxExposure: new BN(0),
         registeredTs: new BN(0),
         active,
+        maxLeverage,
+        limitDeviationBps,
+        longOi,
+        shortOi,
+        maxOi,
+        tradingOpenSecs,
+        tradingCloseSecs,
+        globalFundingIndex,
+        fundingRateBps,
       });
     }
 
     return {
       routerId,
       governance,
+      pendingGovernance,
       slabCount,
       bump,
+      authorityBump,
       imr,
       mmr,
       liqBandBps,
@@ -1687,6 +1865,7 @@ export class RouterClient {
       routerCapPerSlab,
       minEquityToQuote: new BN(minEquityToQuote.toString()),
       oracleToleranceBps,
+      maxSlippageBps,
       slabs,
     };
   }
@@ -1745,6 +1924,8 @@ export class RouterClient {
    * @param limitPrice Maximum price willing to pay (1e6 scale)
    * @param oracle (Optional) Oracle price feed public key - auto-fetched if omitted
    * @param orderType Market or Limit order (default: Limit for v0 compatibility)
+   * @param leverage Leverage multiplier (1-10x)
+   * @param maxSlippageBps Market order slippage tolerance in bps (0 = program default)
    * @returns {instruction, receiptSetup, receiptKeypair} - execution instruction, receipt creation instruction, and receipt keypair
    */
   async buildBuyInstruction(
@@ -1754,7 +1935,8 @@ export class RouterClient {
     limitPrice: BN,
     oracle?: PublicKey,
     orderType: ExecutionType = ExecutionType.Limit,
-    leverage: number = 1
+    leverage: number = 1,
+    maxSlippageBps: number = 0
   ): Promise<{instruction: TransactionInstruction, receiptSetup: TransactionInstruction, receiptKeypair: Keypair}> {
     // Auto-fetch oracle if not provided
     const oracleAccount = oracle || await this.getOracleForSlab(slabMarket);
@@ -1790,11 +1972,9 @@ export class RouterClient {
         throw new Error(`Invalid oracle account data: expected length 128 (Percolator) or >=216 (Pyth), got ${priceData.length}`);
       }
 
-      // For market buy: set limit to oracle + 0.5% slippage buffer
-      // This ensures the order passes the slippage check in the router
-      const slippageBps = new BN(50); // 0.5% = 50 bps
-      const slippageAmount = oraclePrice.mul(slippageBps).div(new BN(10000));
-      actualLimitPrice = oraclePrice.add(slippageAmount);
+      // For market buy: the observed oracle price is the worst acceptable price;
+      // the router allows maxSlippageBps of drift from it
+      actualLimitPrice = oraclePrice;
     }
 
     // v0.5: Fetch DLP owner from slab for PnL settlement
@@ -1813,7 +1993,7 @@ export class RouterClient {
       dlpOwner, // Required for v0.5 PnL settlement
     };
 
-    return await this.buildExecuteCrossSlabInstruction(user, [split], orderType, leverage);
+    return await this.buildExecuteCrossSlabInstruction(user, [split], orderType, leverage, maxSlippageBps);
   }
 
   /**
@@ -1825,6 +2005,8 @@ export class RouterClient {
    * @param limitPrice Minimum price willing to accept (1e6 scale)
    * @param oracle (Optional) Oracle price feed public key - auto-fetched if omitted
    * @param orderType Market or Limit order (default: Limit for v0 compatibility)
+   * @param leverage Leverage multiplier (1-10x)
+   * @param maxSlippageBps Market order slippage tolerance in bps (0 = program default)
    * @returns {instruction, receiptSetup, receiptKeypair} - execution instruction, receipt creation instruction, and receipt keypair
    */
   async buildSellInstruction(
@@ -1834,7 +2016,8 @@ export class RouterClient {
     limitPrice: BN,
     oracle?: PublicKey,
     orderType: ExecutionType = ExecutionType.Limit,
-    leverage: number = 1
+    leverage: number = 1,
+    maxSlippageBps: number = 0
   ): Promise<{instruction: TransactionInstruction, receiptSetup: TransactionInstruction, receiptKeypair: Keypair}> {
     // Auto-fetch oracle if not provided
     const oracleAccount = oracle || await this.getOracleForSlab(slabMarket);
@@ -1870,11 +2053,9 @@ export class RouterClient {
         throw new Error(`Invalid oracle account data: expected length 128 (Percolator) or >=216 (Pyth), got ${priceData.length}`);
       }
 
-      // For market sell: set limit to oracle - 0.5% slippage buffer
-      // This ensures the order passes the slippage check in the router
-      const slippageBps = new BN(50); // 0.5% = 50 bps
-      const slippageAmount = oraclePrice.mul(slippageBps).div(new BN(10000));
-      actualLimitPrice = oraclePrice.sub(slippageAmount);
+      // For market sell: the observed oracle price is the worst acceptable price;
+      // the router allows maxSlippageBps of drift from it
+      actualLimitPrice = oraclePrice;
     }
 
     // v0.5: Fetch DLP owner from slab for PnL settlement
@@ -1893,7 +2074,7 @@ export class RouterClient {
       dlpOwner, // Required for v0.5 PnL settlement
     };
 
-    return await this.buildExecuteCrossSlabInstruction(user, [split], orderType, leverage);
+    return await this.buildExecuteCrossSlabInstruction(user, [split], orderType, leverage, maxSlippageBps);
   }
 
   // ============================================================================
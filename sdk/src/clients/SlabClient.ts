@@ -9,6 +9,7 @@ import BN from 'bn.js';
 import {
   SlabInstruction,
   OrderSide,
+  ContractKind,
   OrderBook,
   PlaceOrderParams,
   CancelOrderParams,
@@ -163,6 +164,15 @@ export class SlabClient {
    * @param takerFeeBps Taker fee in basis points (1e6 scale)
    * @param contractSize Contract size (1e6 scale)
    * @param payer Payer and authority
+   * @param versionHash Slab program version hash (32 bytes, must match router registry entry)
+   * @param contractKind Linear or inverse quoting (defaults to linear)
+   * @param minQty Minimum order size (1e6 scale, 0 = no minimum)
+   * @param lotSize Fills round down to a multiple of this (1e6 scale, 1 = no rounding)
+   * @param priceDecimals Price decimals of the instrument (1-9, defaults to 6)
+   * @param qtyDecimals Quantity decimals of the instrument (1-9, defaults to 6)
+   * @param oracleGuard Registered oracle and band (bps) the slab checks router fill prices against (omit to leave the guard off)
+   * @param scale Oracle exponent (defaults to -6) and collateral decimals (1-18, defaults to 9 for lamports)
+   * @param maxInventory Largest net position fills may leave the LP at (qty decimals, omit or 0 for no limit)
    * @returns TransactionInstruction
    */
   buildInitializeSlabInstruction(
@@ -172,11 +182,23 @@ export class SlabClient {
     markPx: BN,
     takerFeeBps: BN,
     contractSize: BN,
-    payer: PublicKey
+    payer: PublicKey,
+    versionHash: Buffer = Buffer.alloc(32),
+    contractKind: ContractKind = ContractKind.Linear,
+    minQty: BN = new BN(0),
+    lotSize: BN = new BN(1),
+    priceDecimals: number = 6,
+    qtyDecimals: number = 6,
+    oracleGuard?: { oracle: PublicKey; maxDeviationBps: BN },
+    scale?: { oracleExponent: number; collateralDecimals: number },
+    maxInventory?: BN
   ): TransactionInstruction {
     const [slabPDA, bump] = this.deriveSlabPDA(lpOwner, instrument);
+    // Trailing fields are positional: a later one sends the earlier ones at their defaults
+    const scaleFields = scale ?? (maxInventory ? { oracleExponent: -6, collateralDecimals: 9 } : undefined);
+    const guard = oracleGuard ?? (scaleFields ? { oracle: PublicKey.default, maxDeviationBps: new BN(0) } : undefined);
 
-    // Data layout: lp_owner (32) + router_id (32) + instrument (32) + mark_px (8) + taker_fee_bps (8) + contract_size (8) + bump (1) = 121 bytes
+    // Data layout: lp_owner (32) + router_id (32) + instrument (32) + mark_px (8) + taker_fee_bps (8) + contract_size (8) + bump (1) + version_hash (32) + contract_kind (1) + min_qty (8) + lot_size (8) + price_decimals (1) + qty_decimals (1) = 172 bytes, + oracle (32) + max_deviation_bps (8) = 212 with the oracle guard, + oracle_exponent (1) + collateral_decimals (1) = 214 with the scale, + max_inventory (8) = 222 with the inventory limit
     const data = createInstructionData(
       SlabInstruction.Initialize,
       serializePubkey(lpOwner),
@@ -185,7 +207,15 @@ export class SlabClient {
       serializeI64(markPx),
       serializeI64(takerFeeBps),
       serializeI64(contractSize),
-      Buffer.from([bump])
+      Buffer.from([bump]),
+      versionHash,
+      Buffer.from([contractKind]),
+      serializeI64(minQty),
+      serializeI64(lotSize),
+      Buffer.from([priceDecimals, qtyDecimals]),
+      ...(guard ? [serializePubkey(guard.oracle), serializeU64(guard.maxDeviationBps)] : []),
+      ...(scaleFields ? [Buffer.from([scaleFields.oracleExponent & 0xff, scaleFields.collateralDecimals])] : []),
+      ...(maxInventory ? [serializeI64(maxInventory)] : [])
     );
 
     return new TransactionInstruction({
@@ -242,6 +272,25 @@ export class SlabClient {
     });
   }
 
+  /**
+   * Build ResetSeqno instruction (restart a seqno that reached u32::MAX)
+   * @param slab Slab public key
+   * @param lpOwner Slab LP owner (signer)
+   * @returns TransactionInstruction
+   */
+  buildResetSeqnoInstruction(slab: PublicKey, lpOwner: PublicKey): TransactionInstruction {
+    const data = createInstructionData(SlabInstruction.ResetSeqno);
+
+    return new TransactionInstruction({
+      programId: this.programId,
+      keys: [
+        { pubkey: slab, isSigner: false, isWritable: true },
+        { pubkey: lpOwner, isSigner: true, isWritable: false },
+      ],
+      data,
+    });
+  }
+
   // ============================================================================
   // Higher-Level Methods (Stub implementations for future expansion)
   // ============================================================================
@@ -372,6 +421,7 @@ export class SlabClient {
     // contract_size: i64
     // tick: i64
     // lot: i64
+    // min_qty: i64
     // mark_px: i64
     // taker_fee_bps: i64
     // bump: u8 (in offsets, not in header directly)
@@ -411,6 +461,9 @@ export class SlabClient {
     // lot: i64 - skip
     offset += 8;
 
+    // min_qty: i64 - skip
+    offset += 8;
+
     // mark_px: i64
     const markPx = deserializeI64(data, offset);
     offset += 8;
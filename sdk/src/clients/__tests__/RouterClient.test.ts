@@ -3,6 +3,7 @@ import BN from 'bn.js';
 import { RouterClient } from '../RouterClient';
 import {
   RouterInstruction,
+  ROUTER_INSTRUCTION_VERSION,
   SlabSplit,
   LiquidationParams,
   BurnLpSharesParams,
@@ -150,7 +151,8 @@ describe('RouterClient', () => {
         // + 1 slab + 1 receipt + 1 oracle = 9 accounts
         expect(ix.keys.length).toBe(9);
         expect(ix.data[0]).toBe(RouterInstruction.ExecuteCrossSlab);
-        expect(ix.data[1]).toBe(1); // num_splits
+        expect(ix.data[1]).toBe(ROUTER_INSTRUCTION_VERSION);
+        expect(ix.data[2]).toBe(1); // num_splits
       });
 
       it('should throw error for multiple splits (v0.5 limitation)', async () => {
@@ -228,7 +230,7 @@ describe('RouterClient', () => {
         };
 
         const ix = client.buildLiquidateUserInstruction(params);
-        expect(ix.data[1]).toBe(1); // isPreliq = true
+        expect(ix.data[2]).toBe(1); // isPreliq = true
       });
     });
 
@@ -238,17 +240,14 @@ describe('RouterClient', () => {
           user: wallet.publicKey,
           marketId: PublicKey.unique(),
           sharesToBurn: new BN(1000000),
-          currentSharePrice: new BN(1050000),
-          currentTs: new BN(Date.now() / 1000),
-          maxStalenessSeconds: new BN(60),
         };
 
         const ix = client.buildBurnLpSharesInstruction(params);
 
         expect(ix.programId.equals(programId)).toBe(true);
-        expect(ix.keys.length).toBe(3);
+        expect(ix.keys.length).toBe(4);
         expect(ix.data[0]).toBe(RouterInstruction.BurnLpShares);
-        expect(ix.data.length).toBe(65); // 1 + 32 + 8 + 8 + 8 + 8
+        expect(ix.data.length).toBe(42); // 1 + 1 + 32 + 8
       });
     });
 
@@ -258,8 +257,6 @@ describe('RouterClient', () => {
           user: wallet.publicKey,
           marketId: PublicKey.unique(),
           orderIds: [new BN(1)],
-          freedQuote: new BN(1000000),
-          freedBase: new BN(500000),
         };
 
         const ix = client.buildCancelLpOrdersInstruction(params);
@@ -274,8 +271,6 @@ describe('RouterClient', () => {
           user: wallet.publicKey,
           marketId: PublicKey.unique(),
           orderIds: [new BN(1), new BN(2), new BN(3)],
-          freedQuote: new BN(1000000),
-          freedBase: new BN(500000),
         };
 
         const ix = client.buildCancelLpOrdersInstruction(params);
@@ -288,8 +283,6 @@ describe('RouterClient', () => {
           user: wallet.publicKey,
           marketId: PublicKey.unique(),
           orderIds,
-          freedQuote: new BN(1000000),
-          freedBase: new BN(500000),
         };
 
         expect(() => client.buildCancelLpOrdersInstruction(params)).toThrow(
@@ -539,7 +532,7 @@ describe('RouterClient', () => {
     offset += 8; // router_cap_per_slab
     offset += 16; // min_equity_to_quote (i128)
     offset += 8; // oracle_tolerance_bps
-    offset += 8; // _padding2
+    offset += 8; // max_slippage_bps
 
     // Skip complex nested structs (~376 bytes as estimated in deserializeRegistry)
     offset += 376;
@@ -725,8 +718,9 @@ describe('RouterClient', () => {
             serializeU64(orderId).copy(buffer, offset);
             offset += 8;
           }
+          offset += 16 * MAX_OPEN_ORDERS; // open_order_quote, open_order_base
         } else {
-          offset += 16 + 16 + 2 + 6 + 8 * MAX_OPEN_ORDERS;
+          offset += 16 + 16 + 2 + 6 + 24 * MAX_OPEN_ORDERS;
         }
 
         // Final bucket fields
@@ -104,11 +104,11 @@ export const FP_ONE = 1_000_000_000;
 /**
  * Portfolio account size (exact)
  * This MUST match Portfolio::LEN from programs/router/src/state/portfolio.rs
- * Calculated as: size_of::<Portfolio>() = 12176 bytes (updated after MAX_SLABS reduced from 256 to 16)
+ * Calculated as: size_of::<Portfolio>() = 14328 bytes (updated after the instant-withdrawal window was added)
  *
  * DO NOT use the calculated approximation below - use this exact value!
  */
-export const PORTFOLIO_SIZE = 12176;
+export const PORTFOLIO_SIZE = 14328;
 
 /**
  * Portfolio size calculation (for reference only - DO NOT USE)
@@ -126,7 +126,7 @@ export const PORTFOLIO_SIZE_CALCULATED =
 /**
  * Slab account size (exact)
  * This MUST match SlabState::LEN from programs/slab/src/state/slab.rs
- * Layout: SlabHeader (256B) + QuoteCache (256B) + BookArea (3KB)
+ * Layout: SlabHeader (256B) + QuoteCache (256B) + BookArea (3KB) + OracleGuard (40B) + LpInventory (24B)
  * Total: ~4KB
  */
-export const SLAB_SIZE = 3584; // Exact size from Rust's size_of::<SlabState>()
+export const SLAB_SIZE = 3520; // Exact size from Rust's size_of::<SlabState>()
@@ -11,6 +11,7 @@ export { SlabClient, SlabState, FillReceipt } from './clients/SlabClient';
 // Types - Router
 export {
   RouterInstruction,
+  ROUTER_INSTRUCTION_VERSION,
   Portfolio,
   Exposure,
   VenueId,
@@ -64,6 +65,7 @@ export {
   deserializeBool,
   deserializePubkey,
   createInstructionData,
+  createVersionedInstructionData,
 } from './utils/serialization';
 
 // Utils - Formatting
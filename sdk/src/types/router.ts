@@ -16,6 +16,13 @@ export enum RouterInstruction {
   CancelLpOrders = 7,
 }
 
+/**
+ * Router instruction data layout version, sent in the byte after the discriminator
+ * IMPORTANT: Must be within MIN_INSTRUCTION_VERSION..=INSTRUCTION_VERSION in
+ * programs/router/src/instructions/mod.rs
+ */
+export const ROUTER_INSTRUCTION_VERSION = 1;
+
 /**
  * Exposure: (slab_index, instrument_index, position_qty)
  * Represents a trader position in a specific instrument on a specific slab
@@ -118,6 +125,7 @@ export interface SlabEntry {
   slabId: PublicKey;
   versionHash: Buffer;
   oracleId: PublicKey;  // Oracle for this slab
+  extraOracleIds: PublicKey[]; // Further registered feeds for median pricing (0, 2 or 4)
   imr: BN;              // Initial margin ratio (basis points)
   mmr: BN;              // Maintenance margin ratio (basis points)
   makerFeeCap: BN;
@@ -126,6 +134,15 @@ export interface SlabEntry {
   maxExposure: BN;
   registeredTs: BN;
   active: boolean;
+  maxLeverage: number;  // Maximum order leverage on this market
+  limitDeviationBps: number; // Limit price band around the oracle (bps, 0 = default 20%)
+  longOi: BN;           // Total long user exposure (1e6 scale)
+  shortOi: BN;          // Total short user exposure (1e6 scale)
+  maxOi: BN;            // Open interest cap per side (0 = uncapped)
+  tradingOpenSecs: number;  // Daily trading window open, seconds after 00:00 UTC
+  tradingCloseSecs: number; // Daily trading window close (equal to open = always open)
+  globalFundingIndex: BN; // Cumulative funding rate (bps, positive = longs have paid)
+  fundingRateBps: BN;   // Rate applied in the last funding period (bps)
 }
 
 /**
@@ -134,8 +151,10 @@ export interface SlabEntry {
 export interface Registry {
   routerId: PublicKey;
   governance: PublicKey;
+  pendingGovernance: PublicKey; // Proposed successor awaiting AcceptGovernance (default = none)
   slabCount: number;
   bump: number;
+  authorityBump: number;     // Cached router authority PDA bump (0 = not cached)
   // Liquidation parameters
   imr: BN;
   mmr: BN;
@@ -145,6 +164,7 @@ export interface Registry {
   routerCapPerSlab: BN;
   minEquityToQuote: BN;
   oracleToleranceBps: BN;
+  maxSlippageBps: BN;
   // Registered slabs
   slabs: SlabEntry[];
 }
@@ -180,6 +200,7 @@ export interface SlabSplit {
   qty: BN;           // i64 - quantity in 1e6 scale
   limitPx: BN;       // i64 - limit price in 1e6 scale
   oracle: PublicKey; // Oracle price feed for this slab
+  extraOracles?: PublicKey[]; // Additional feeds for median pricing (2 or 4, for 3 or 5 total)
   dlpOwner?: PublicKey; // LP/DLP owner (from slab.lp_owner) - required for v0.5 PnL settlement
 }
 
@@ -190,6 +211,7 @@ export interface LiquidationParams {
   portfolio: PublicKey;
   oracles: PublicKey[];
   slabs: PublicKey[];
+  positionDetails?: PublicKey[]; // One PositionDetails PDA per open exposure (health includes unrealized PnL)
   isPreliq: boolean;
   currentTs: BN;
 }
@@ -201,9 +223,6 @@ export interface BurnLpSharesParams {
   user: PublicKey;
   marketId: PublicKey;
   sharesToBurn: BN;
-  currentSharePrice: BN;
-  currentTs: BN;
-  maxStalenessSeconds: BN;
 }
 
 /**
@@ -213,8 +232,6 @@ export interface CancelLpOrdersParams {
   user: PublicKey;
   marketId: PublicKey;
   orderIds: BN[];
-  freedQuote: BN;
-  freedBase: BN;
 }
 
 /**
@@ -7,6 +7,7 @@ import BN from 'bn.js';
 export enum SlabInstruction {
   Initialize = 0,
   CommitFill = 1,
+  ResetSeqno = 2,
 }
 
 /**
@@ -17,6 +18,16 @@ export enum OrderSide {
   Ask = 1,
 }
 
+/**
+ * Contract quoting convention
+ */
+export enum ContractKind {
+  /** PnL = qty * (exit - entry) */
+  Linear = 0,
+  /** Qty is quote notional; PnL = qty * (1/entry - 1/exit), settled in base */
+  Inverse = 1,
+}
+
 /**
  * Order type
  */
@@ -149,3 +149,15 @@ export function createInstructionData(discriminator: number, ...parts: Buffer[])
   const discBuf = Buffer.from([discriminator]);
   return Buffer.concat([discBuf, ...parts]);
 }
+
+/**
+ * Create instruction data buffer with discriminator and layout version byte
+ */
+export function createVersionedInstructionData(
+  discriminator: number,
+  version: number,
+  ...parts: Buffer[]
+): Buffer {
+  const headerBuf = Buffer.from([discriminator, version]);
+  return Buffer.concat([headerBuf, ...parts]);
+}
@@ -26,7 +26,7 @@ pub async fn test_t01_layout_validity(ctx: &TestContext) -> Result<()> {
     println!("Created slab account: {}", slab_account.pubkey());
 
     // Build initialize instruction with correct format
-    // Expected data layout (121 bytes total after discriminator):
+    // Expected data layout (153 bytes total after discriminator):
     // - lp_owner: Pubkey (32 bytes)
     // - router_id: Pubkey (32 bytes)
     // - instrument: Pubkey (32 bytes)
@@ -34,6 +34,7 @@ pub async fn test_t01_layout_validity(ctx: &TestContext) -> Result<()> {
     // - taker_fee_bps: i64 (8 bytes)
     // - contract_size: i64 (8 bytes)
     // - bump: u8 (1 byte)
+    // - version_hash: [u8; 32] (32 bytes)
 
     let mut init_data = vec![0u8]; // Discriminator = 0 (Initialize)
 
@@ -59,6 +60,9 @@ pub async fn test_t01_layout_validity(ctx: &TestContext) -> Result<()> {
     // bump - PDA bump seed (use 255 for non-PDA account)
     init_data.push(255u8);
 
+    // version_hash - slab program version (zeroed for test)
+    init_data.extend_from_slice(&[0u8; 32]);
+
     let initialize_ix = Instruction::new_with_bytes(
         ctx.slab_program_id,
         &init_data,